@@ -1,51 +1,74 @@
-//! Resolve the version string compiled into the binary.
+//! Resolve the version string and build metadata compiled into the binary.
 //!
 //! Precedence is an explicit `SUNSETR_VERSION` from the environment, then
 //! `git describe` when `.git` sits next to `Cargo.toml`, then the Cargo
 //! manifest version for release tarballs that ship without a `.git`
 //! directory.
+//!
+//! The commit hash follows the same order through `SUNSETR_GIT_HASH`, and is
+//! empty when neither is available. `SUNSETR_FEATURES` lists the enabled cargo
+//! features, comma-separated.
 
 use std::path::Path;
 use std::process::Command;
 
 fn main() {
     println!("cargo:rerun-if-env-changed=SUNSETR_VERSION");
+    println!("cargo:rerun-if-env-changed=SUNSETR_GIT_HASH");
     println!("cargo:rerun-if-changed=.git/HEAD");
 
-    let version = env_override()
+    let version = env_override("SUNSETR_VERSION")
         .or_else(git_describe)
         .unwrap_or_else(|| std::env::var("CARGO_PKG_VERSION").unwrap());
+    let git_hash = env_override("SUNSETR_GIT_HASH")
+        .or_else(|| git(&["rev-parse", "--short=12", "HEAD"]))
+        .unwrap_or_default();
+
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(feature_name))
+        .collect();
+    features.sort();
 
     println!("cargo:rustc-env=SUNSETR_VERSION={version}");
+    println!("cargo:rustc-env=SUNSETR_GIT_HASH={git_hash}");
+    println!("cargo:rustc-env=SUNSETR_FEATURES={}", features.join(","));
 }
 
-fn env_override() -> Option<String> {
-    match std::env::var("SUNSETR_VERSION") {
+fn env_override(key: &str) -> Option<String> {
+    match std::env::var(key) {
         Ok(v) if !v.trim().is_empty() => Some(v.trim().to_string()),
         _ => None,
     }
 }
 
+/// Cargo passes `foo-bar` as `CARGO_FEATURE_FOO_BAR`.
+fn feature_name(key: &str) -> String {
+    key.to_lowercase().replace('_', "-")
+}
+
 fn git_describe() -> Option<String> {
+    let described = git(&["describe", "--tags", "--always", "--dirty"])?;
+    Some(
+        described
+            .strip_prefix('v')
+            .unwrap_or(&described)
+            .to_string(),
+    )
+}
+
+/// Trimmed, non-empty output of a git command run in a checkout next to `Cargo.toml`.
+fn git(args: &[&str]) -> Option<String> {
     let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").ok()?;
     if !Path::new(&manifest_dir).join(".git").exists() {
         return None;
     }
 
-    let output = Command::new("git")
-        .args(["describe", "--tags", "--always", "--dirty"])
-        .output()
-        .ok()?;
-
+    let output = Command::new("git").args(args).output().ok()?;
     if !output.status.success() {
         return None;
     }
 
-    let described = String::from_utf8(output.stdout).ok()?;
-    let described = described.trim();
-    if described.is_empty() {
-        return None;
-    }
-
-    Some(described.strip_prefix('v').unwrap_or(described).to_string())
+    let output = String::from_utf8(output.stdout).ok()?;
+    let output = output.trim();
+    (!output.is_empty()).then(|| output.to_string())
 }
@@ -39,6 +39,7 @@
           pname = "sunsetr";
           inherit version;
           env.SUNSETR_VERSION = version;
+          env.SUNSETR_GIT_HASH = self.shortRev or self.dirtyShortRev or "";
 
           src = pkgs.lib.fileset.toSource {
             root = ./.;
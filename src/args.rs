@@ -4,9 +4,51 @@ use crate::time::source::SimulationPace;
 
 #[derive(Debug, PartialEq)]
 pub enum PresetSubcommand {
-    Apply { name: String },
+    /// `expires_in` is the `--for` length, after which the previous presets come back.
+    Apply {
+        name: String,
+        expires_in: Option<std::time::Duration>,
+    },
+    Push {
+        name: String,
+        expires_in: Option<std::time::Duration>,
+    },
+    Pop,
     Active,
     List,
+    Rename {
+        from: String,
+        to: String,
+    },
+    Remove {
+        name: String,
+    },
+}
+
+/// Subcommands of `sunsetr presentation`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresentationSubcommand {
+    /// Hold `outputs` neutral, every output when empty.
+    On {
+        outputs: Vec<String>,
+    },
+    Off,
+}
+
+/// Subcommands of `sunsetr adjust`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdjustSubcommand {
+    /// Add `temp` Kelvin and `gamma` percentage points to the current offsets.
+    By { temp: i32, gamma: f64 },
+    /// Drop the offsets.
+    Reset,
+}
+
+/// Subcommands of `sunsetr config`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSubcommand {
+    Schema,
+    Check,
 }
 
 /// How a `set` value is applied. Assign is `field=value`, Increment is
@@ -22,7 +64,10 @@ pub enum SetOperator {
 /// The action determined by parsing the command line.
 #[derive(Debug, PartialEq)]
 pub enum CliAction {
-    ShowVersion,
+    /// Version banner, or build metadata as JSON with `--json`.
+    ShowVersion {
+        json: bool,
+    },
     ShowHelp,
     ShowHelpDueToError,
 
@@ -44,6 +89,10 @@ pub enum CliAction {
         debug_enabled: bool,
         config_dir: Option<String>,
         background: bool,
+        profile: bool,
+        safe_mode: bool,
+        /// Run everything but apply nothing, as with `backend = "none"`.
+        dry_run: bool,
     },
     Simulate {
         debug_enabled: bool,
@@ -51,6 +100,8 @@ pub enum CliAction {
         end_time: String,
         pace: SimulationPace,
         log_to_file: bool,
+        /// Print the update timeline as JSON lines instead of running the application.
+        json: bool,
         config_dir: Option<String>,
     },
     PresetCommand {
@@ -65,10 +116,27 @@ pub enum CliAction {
         background: bool,
     },
     StopCommand,
+    RefreshCommand,
+    ToggleCommand,
+    /// `expires_in` is the `--for` length and `until` the `--until` moment, after which
+    /// color shifting resumes by itself.
+    PauseCommand {
+        expires_in: Option<std::time::Duration>,
+        until: Option<crate::core::pause::PauseEvent>,
+    },
+    PresentationCommand {
+        subcommand: PresentationSubcommand,
+        config_dir: Option<String>,
+    },
+    AdjustCommand {
+        subcommand: AdjustSubcommand,
+        config_dir: Option<String>,
+    },
     GeoCommand {
         debug_enabled: bool,
         config_dir: Option<String>,
         target: Option<String>,
+        provider: Option<String>,
     },
     TestCommand {
         debug_enabled: bool,
@@ -79,16 +147,63 @@ pub enum CliAction {
         json: bool,
         follow: bool,
     },
+    HistoryCommand {
+        json: bool,
+    },
+    GrabCommand {
+        /// The screenshot command and its arguments.
+        command: Vec<String>,
+    },
+    DoctorCommand {
+        json: bool,
+        config_dir: Option<String>,
+    },
+    BugreportCommand {
+        output: Option<String>,
+        config_dir: Option<String>,
+    },
+    ComputeCommand {
+        at: Option<String>,
+        json: bool,
+        config_dir: Option<String>,
+    },
+    CurveCommand {
+        date: Option<String>,
+        svg: bool,
+        config_dir: Option<String>,
+    },
+    WeekCommand {
+        start: Option<String>,
+        ascii: bool,
+        config_dir: Option<String>,
+    },
+    RenderPreviewCommand {
+        temp: Option<u32>,
+        gamma: Option<f64>,
+        output: String,
+        config_dir: Option<String>,
+    },
+    ConfigCommand {
+        subcommand: ConfigSubcommand,
+        config_dir: Option<String>,
+    },
     SetCommand {
         fields: Vec<(String, SetOperator, String)>,
         config_dir: Option<String>,
         target: Option<String>,
+        create: bool,
+        /// Write the fields as runtime overrides instead of to the config files.
+        runtime: bool,
+        /// Drop all runtime overrides. Comes without fields.
+        clear: bool,
     },
     GetCommand {
         fields: Vec<String>,
         config_dir: Option<String>,
         target: Option<String>,
         json: bool,
+        /// List the runtime overrides instead of reading fields.
+        overrides: bool,
     },
 }
 
@@ -100,7 +215,16 @@ impl CliAction {
             | Self::Simulate { config_dir, .. }
             | Self::PresetCommand { config_dir, .. }
             | Self::RestartCommand { config_dir, .. }
+            | Self::PresentationCommand { config_dir, .. }
+            | Self::AdjustCommand { config_dir, .. }
             | Self::GeoCommand { config_dir, .. }
+            | Self::DoctorCommand { config_dir, .. }
+            | Self::BugreportCommand { config_dir, .. }
+            | Self::ComputeCommand { config_dir, .. }
+            | Self::CurveCommand { config_dir, .. }
+            | Self::RenderPreviewCommand { config_dir, .. }
+            | Self::WeekCommand { config_dir, .. }
+            | Self::ConfigCommand { config_dir, .. }
             | Self::SetCommand { config_dir, .. }
             | Self::GetCommand { config_dir, .. } => config_dir.as_deref(),
             _ => None,
@@ -116,10 +240,20 @@ impl CliAction {
             Self::PresetCommand { .. }
                 | Self::RestartCommand { .. }
                 | Self::StopCommand
+                | Self::RefreshCommand
+                | Self::ToggleCommand
+                | Self::PauseCommand { .. }
+                | Self::PresentationCommand { .. }
+                | Self::AdjustCommand { .. }
                 | Self::GeoCommand { .. }
                 | Self::TestCommand { .. }
                 | Self::SetCommand { .. }
                 | Self::GetCommand { .. }
+                | Self::ComputeCommand { .. }
+                | Self::CurveCommand { .. }
+                | Self::RenderPreviewCommand { .. }
+                | Self::WeekCommand { .. }
+                | Self::ConfigCommand { .. }
         )
     }
 
@@ -161,6 +295,11 @@ impl CliAction {
         if let Some(cmd_idx) = potential_command_idx {
             let command = &args_vec[cmd_idx];
 
+            // The wrapped command's own flags must not be read as sunsetr's
+            if command == "grab" {
+                return parse_grab(&args_vec[cmd_idx + 1..]);
+            }
+
             let debug_enabled = args_vec.iter().any(|arg| arg == "--debug" || arg == "-d");
             let background = args_vec
                 .iter()
@@ -170,7 +309,9 @@ impl CliAction {
                 .iter()
                 .any(|arg| arg == "--version" || arg == "-V" || arg == "-v")
             {
-                return CliAction::ShowVersion;
+                return CliAction::ShowVersion {
+                    json: args_vec.iter().any(|arg| arg == "--json" || arg == "-j"),
+                };
             }
             if args_vec.iter().any(|arg| arg == "--help" || arg == "-h") {
                 if command != "help" && !command.starts_with('-') {
@@ -200,17 +341,29 @@ impl CliAction {
                     }
                     if matches!(
                         arg.as_str(),
-                        "get"
+                        "adjust"
+                            | "bugreport"
+                            | "compute"
+                            | "config"
+                            | "curve"
+                            | "doctor"
+                            | "get"
                             | "g"
                             | "geo"
                             | "G"
+                            | "grab"
                             | "help"
                             | "h"
+                            | "history"
                             | "preset"
                             | "p"
+                            | "presentation"
+                            | "refresh"
                             | "reload"
+                            | "render-preview"
                             | "restart"
                             | "r"
+                            | "pause"
                             | "set"
                             | "s"
                             | "stop"
@@ -218,6 +371,8 @@ impl CliAction {
                             | "S"
                             | "test"
                             | "t"
+                            | "toggle"
+                            | "week"
                     ) {
                         return Some(arg.clone());
                     }
@@ -238,7 +393,9 @@ impl CliAction {
                     check_for_multiple_commands(next_idx)
                 }
                 "geo" | "G" => check_for_multiple_commands(cmd_idx + 1),
-                "stop" => check_for_multiple_commands(cmd_idx + 1),
+                "stop" | "refresh" | "toggle" | "pause" | "adjust" => {
+                    check_for_multiple_commands(cmd_idx + 1)
+                }
                 "test" | "t" => {
                     if cmd_idx + 2 < args_vec.len() {
                         check_for_multiple_commands(cmd_idx + 3)
@@ -246,14 +403,22 @@ impl CliAction {
                         None
                     }
                 }
-                "preset" | "p" => {
-                    if cmd_idx + 1 < args_vec.len() {
+                "preset" | "p" => match args_vec.get(cmd_idx + 1).map(String::as_str) {
+                    Some("push" | "remove") => check_for_multiple_commands(cmd_idx + 3),
+                    Some("rename") => check_for_multiple_commands(cmd_idx + 4),
+                    Some(_) => check_for_multiple_commands(cmd_idx + 2),
+                    None => None,
+                },
+                "config" => check_for_multiple_commands(cmd_idx + 2),
+                "presentation" => {
+                    if args_vec.get(cmd_idx + 1).map(String::as_str) == Some("off") {
                         check_for_multiple_commands(cmd_idx + 2)
                     } else {
                         None
                     }
                 }
-                "set" | "s" | "get" | "g" | "status" | "S" => None,
+                "set" | "s" | "get" | "g" | "status" | "S" | "history" | "doctor" | "bugreport"
+                | "compute" | "curve" | "render-preview" | "week" => None,
                 _ => None,
             };
 
@@ -308,6 +473,190 @@ impl CliAction {
                 "stop" => {
                     return CliAction::StopCommand;
                 }
+                "refresh" => {
+                    return CliAction::RefreshCommand;
+                }
+                "toggle" => {
+                    return CliAction::ToggleCommand;
+                }
+                "pause" => {
+                    let mut expires_in = None;
+                    let mut until = None;
+
+                    let mut i = cmd_idx + 1;
+                    while i < args_vec.len() {
+                        match args_vec[i].as_str() {
+                            "--for" => {
+                                let Some(text) = args_vec.get(i + 1) else {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "pause".to_string(),
+                                        error_message: "--for requires a duration, e.g. 45m"
+                                            .to_string(),
+                                    };
+                                };
+                                match crate::common::utils::parse_duration(text) {
+                                    Ok(duration) => expires_in = Some(duration),
+                                    Err(e) => {
+                                        return CliAction::ShowCommandUsageDueToError {
+                                            command: "pause".to_string(),
+                                            error_message: e.to_string(),
+                                        };
+                                    }
+                                }
+                                i += 1;
+                            }
+                            "--until" => {
+                                let event = args_vec
+                                    .get(i + 1)
+                                    .and_then(|text| crate::core::pause::PauseEvent::parse(text));
+                                let Some(event) = event else {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "pause".to_string(),
+                                        error_message: "--until requires sunrise or tomorrow"
+                                            .to_string(),
+                                    };
+                                };
+                                until = Some(event);
+                                i += 1;
+                            }
+                            "--config" | "-c" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    i += 1;
+                                }
+                            }
+                            "--help" | "-h" => {
+                                return CliAction::UsageHelp {
+                                    command: "pause".to_string(),
+                                };
+                            }
+                            arg if is_global_noop_flag(arg) => {}
+                            arg if arg.starts_with('-') => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "pause".to_string(),
+                                    error_message: format!("Unknown flag: {arg}"),
+                                };
+                            }
+                            _ => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "pause".to_string(),
+                                    error_message: format!("Unexpected argument: {}", args_vec[i]),
+                                };
+                            }
+                        }
+                        i += 1;
+                    }
+
+                    if expires_in.is_some() && until.is_some() {
+                        return CliAction::ShowCommandUsageDueToError {
+                            command: "pause".to_string(),
+                            error_message: "Use either --for or --until, not both".to_string(),
+                        };
+                    }
+                    return CliAction::PauseCommand { expires_in, until };
+                }
+                "presentation" => {
+                    let mut positional = Vec::new();
+                    let mut i = cmd_idx + 1;
+                    while i < args_vec.len() {
+                        let arg = &args_vec[i];
+                        if arg == "--config" || arg == "-c" {
+                            i += 1;
+                        } else if arg.starts_with('-') && !is_global_noop_flag(arg) {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "presentation".to_string(),
+                                error_message: format!("Unknown flag: {arg}"),
+                            };
+                        } else if !arg.starts_with('-') {
+                            positional.push(arg.clone());
+                        }
+                        i += 1;
+                    }
+                    let mut args = positional.into_iter();
+                    let subcommand = match args.next().as_deref() {
+                        Some("on") => PresentationSubcommand::On {
+                            outputs: args.collect(),
+                        },
+                        Some("off") => {
+                            if let Some(arg) = args.next() {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "presentation".to_string(),
+                                    error_message: format!("Unexpected argument: {arg}"),
+                                };
+                            }
+                            PresentationSubcommand::Off
+                        }
+                        Some(other) => {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "presentation".to_string(),
+                                error_message: format!("Unknown subcommand: {other}"),
+                            };
+                        }
+                        None => {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "presentation".to_string(),
+                                error_message: "Missing subcommand: on or off".to_string(),
+                            };
+                        }
+                    };
+                    return CliAction::PresentationCommand {
+                        subcommand,
+                        config_dir,
+                    };
+                }
+                "adjust" => {
+                    let mut temp = 0;
+                    let mut gamma = 0.0;
+                    let mut reset = false;
+                    let mut steps = 0;
+                    let mut i = cmd_idx + 1;
+                    while i < args_vec.len() {
+                        let arg = args_vec[i].as_str();
+                        if arg == "--config" || arg == "-c" {
+                            i += 1;
+                        } else if arg == "reset" {
+                            reset = true;
+                        } else if let Some(step) = parse_adjust_step(arg) {
+                            match step {
+                                AdjustStep::Temp(kelvin) => temp += kelvin,
+                                AdjustStep::Gamma(percent) => gamma += percent,
+                            }
+                            steps += 1;
+                        } else if arg.starts_with('-') && !is_global_noop_flag(arg) {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "adjust".to_string(),
+                                error_message: format!("Unknown flag: {arg}"),
+                            };
+                        } else if !is_global_noop_flag(arg) {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "adjust".to_string(),
+                                error_message: format!(
+                                    "Invalid adjustment: {arg} (expected e.g. +500, -300K or -10%)"
+                                ),
+                            };
+                        }
+                        i += 1;
+                    }
+                    let subcommand = match (reset, steps) {
+                        (true, 0) => AdjustSubcommand::Reset,
+                        (true, _) => {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "adjust".to_string(),
+                                error_message: "reset takes no adjustments".to_string(),
+                            };
+                        }
+                        (false, 0) => {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "adjust".to_string(),
+                                error_message: "Missing adjustment, e.g. +500 or -10%".to_string(),
+                            };
+                        }
+                        (false, _) => AdjustSubcommand::By { temp, gamma },
+                    };
+                    return CliAction::AdjustCommand {
+                        subcommand,
+                        config_dir,
+                    };
+                }
                 "test" | "t" => {
                     if cmd_idx + 2 < args_vec.len() {
                         if let (Ok(temp), Ok(gamma)) = (
@@ -348,21 +697,119 @@ impl CliAction {
                         }
                         None => None,
                     };
+                    let provider = match args_vec
+                        .iter()
+                        .position(|arg| arg == "--provider" || arg == "-p")
+                    {
+                        Some(i) => {
+                            if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                Some(args_vec[i + 1].clone())
+                            } else {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "geo".to_string(),
+                                    error_message: "Missing provider name for --provider flag"
+                                        .to_string(),
+                                };
+                            }
+                        }
+                        None => None,
+                    };
                     return CliAction::GeoCommand {
                         debug_enabled,
                         config_dir,
                         target,
+                        provider,
                     };
                 }
                 "preset" | "p" => {
                     if cmd_idx + 1 < args_vec.len() && !args_vec[cmd_idx + 1].starts_with('-') {
                         let subcommand_or_name = &args_vec[cmd_idx + 1];
 
+                        let expires_in = match args_vec[cmd_idx + 1..]
+                            .iter()
+                            .position(|arg| arg == "--for")
+                            .map(|offset| args_vec.get(cmd_idx + 2 + offset))
+                        {
+                            None => None,
+                            Some(Some(text)) => match crate::common::utils::parse_duration(text) {
+                                Ok(duration) => Some(duration),
+                                Err(e) => {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "preset".to_string(),
+                                        error_message: e.to_string(),
+                                    };
+                                }
+                            },
+                            Some(None) => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "preset".to_string(),
+                                    error_message: "--for requires a duration, e.g. 3h".to_string(),
+                                };
+                            }
+                        };
+                        if expires_in.is_some()
+                            && matches!(
+                                subcommand_or_name.as_str(),
+                                "active" | "list" | "pop" | "rename" | "remove"
+                            )
+                        {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "preset".to_string(),
+                                error_message: format!(
+                                    "--for only applies to switching presets, not '{subcommand_or_name}'"
+                                ),
+                            };
+                        }
+
                         let subcommand = match subcommand_or_name.as_str() {
                             "active" => PresetSubcommand::Active,
                             "list" => PresetSubcommand::List,
+                            "pop" => PresetSubcommand::Pop,
+                            "push" => match args_vec.get(cmd_idx + 2) {
+                                Some(name) if !name.starts_with('-') => PresetSubcommand::Push {
+                                    name: name.to_string(),
+                                    expires_in,
+                                },
+                                _ => {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "preset".to_string(),
+                                        error_message: "Missing preset name to push".to_string(),
+                                    };
+                                }
+                            },
+                            "rename" => {
+                                match (args_vec.get(cmd_idx + 2), args_vec.get(cmd_idx + 3)) {
+                                    (Some(from), Some(to))
+                                        if !from.starts_with('-') && !to.starts_with('-') =>
+                                    {
+                                        PresetSubcommand::Rename {
+                                            from: from.to_string(),
+                                            to: to.to_string(),
+                                        }
+                                    }
+                                    _ => {
+                                        return CliAction::ShowCommandUsageDueToError {
+                                            command: "preset".to_string(),
+                                            error_message: "Usage: preset rename <name> <new-name>"
+                                                .to_string(),
+                                        };
+                                    }
+                                }
+                            }
+                            "remove" => match args_vec.get(cmd_idx + 2) {
+                                Some(name) if !name.starts_with('-') => PresetSubcommand::Remove {
+                                    name: name.to_string(),
+                                },
+                                _ => {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "preset".to_string(),
+                                        error_message: "Missing preset name to remove".to_string(),
+                                    };
+                                }
+                            },
                             name => PresetSubcommand::Apply {
                                 name: name.to_string(),
+                                expires_in,
                             },
                         };
 
@@ -381,11 +828,23 @@ impl CliAction {
                     let mut fields = Vec::new();
                     let mut idx = cmd_idx + 1;
                     let mut target: Option<String> = None;
+                    let mut create = false;
+                    let mut runtime = false;
+                    let mut clear = false;
 
                     while idx < args_vec.len() {
                         let arg = &args_vec[idx];
 
-                        if arg == "--target" || arg == "-t" {
+                        if arg == "--create" {
+                            create = true;
+                            idx += 1;
+                        } else if arg == "--runtime" {
+                            runtime = true;
+                            idx += 1;
+                        } else if arg == "--clear" {
+                            clear = true;
+                            idx += 1;
+                        } else if arg == "--target" || arg == "-t" {
                             if idx + 1 < args_vec.len() && !args_vec[idx + 1].starts_with('-') {
                                 target = Some(args_vec[idx + 1].clone());
                                 idx += 2;
@@ -447,17 +906,41 @@ impl CliAction {
                         }
                     }
 
-                    if fields.is_empty() {
+                    if clear {
+                        if !fields.is_empty() || runtime || create || target.is_some() {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "set".to_string(),
+                                error_message: "--clear takes no fields or other flags".to_string(),
+                            };
+                        }
+                    } else if fields.is_empty() {
                         return CliAction::ShowCommandUsageDueToError {
                             command: "set".to_string(),
                             error_message: "Missing field=value pairs".to_string(),
                         };
                     }
 
+                    if create && target.is_none() {
+                        return CliAction::ShowCommandUsageDueToError {
+                            command: "set".to_string(),
+                            error_message: "--create requires --target <name>".to_string(),
+                        };
+                    }
+
+                    if runtime && target.is_some() {
+                        return CliAction::ShowCommandUsageDueToError {
+                            command: "set".to_string(),
+                            error_message: "--runtime cannot be combined with --target".to_string(),
+                        };
+                    }
+
                     return CliAction::SetCommand {
                         fields,
                         config_dir,
                         target,
+                        create,
+                        runtime,
+                        clear,
                     };
                 }
                 "get" | "g" => {
@@ -465,6 +948,7 @@ impl CliAction {
                     let mut idx = cmd_idx + 1;
                     let mut target: Option<String> = None;
                     let mut json_output = false;
+                    let mut overrides = false;
 
                     while idx < args_vec.len() {
                         let arg = &args_vec[idx];
@@ -482,6 +966,9 @@ impl CliAction {
                         } else if arg == "--json" || arg == "-j" {
                             json_output = true;
                             idx += 1;
+                        } else if arg == "--overrides" {
+                            overrides = true;
+                            idx += 1;
                         } else if is_global_noop_flag(arg) {
                             idx += 1;
                         } else if arg.starts_with('-') {
@@ -495,7 +982,15 @@ impl CliAction {
                         }
                     }
 
-                    if fields.is_empty() {
+                    if overrides {
+                        if !fields.is_empty() || target.is_some() {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "get".to_string(),
+                                error_message: "--overrides takes no field names or --target"
+                                    .to_string(),
+                            };
+                        }
+                    } else if fields.is_empty() {
                         return CliAction::ShowCommandUsageDueToError {
                             command: "get".to_string(),
                             error_message: "Missing field names".to_string(),
@@ -507,6 +1002,7 @@ impl CliAction {
                         config_dir,
                         target,
                         json: json_output,
+                        overrides,
                     };
                 }
                 "status" | "S" => {
@@ -550,186 +1046,575 @@ impl CliAction {
                         follow,
                     };
                 }
-                _ => {
-                    log_warning_standalone!("Unknown command: {command}");
-                    return CliAction::ShowHelpDueToError;
-                }
-            }
-        }
-
-        let mut debug_enabled = false;
-        let mut display_help = false;
-        let mut display_version = false;
-        let mut run_geo_selection = false;
-        let mut run_reload = false;
-        let mut run_test = false;
-        let mut test_temperature: Option<u32> = None;
-        let mut test_gamma: Option<f64> = None;
-        let mut run_simulate = false;
-        let mut simulate_start: Option<String> = None;
-        let mut simulate_end: Option<String> = None;
-        let mut simulate_pace: Option<SimulationPace> = None;
-        let mut log_to_file = false;
-        let mut unknown_arg_found = false;
-        let mut config_dir: Option<String> = None;
-        let mut background = false;
+                "history" => {
+                    let mut json_output = false;
 
-        let mut i = 0;
-        while i < args_vec.len() {
-            let arg_str = &args_vec[i];
-            match arg_str.as_str() {
-                "--help" | "-h" => display_help = true,
-                "--version" | "-V" | "-v" => display_version = true,
-                "--debug" | "-d" => debug_enabled = true,
-                "--background" | "-b" => background = true,
-                "--config" | "-c" => {
-                    if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
-                        config_dir = Some(args_vec[i + 1].clone());
+                    let mut i = cmd_idx + 1;
+                    while i < args_vec.len() {
+                        match args_vec[i].as_str() {
+                            "--json" | "-j" => json_output = true,
+                            "--config" | "-c" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    i += 1;
+                                }
+                            }
+                            "--help" | "-h" => {
+                                return CliAction::UsageHelp {
+                                    command: "history".to_string(),
+                                };
+                            }
+                            arg if is_global_noop_flag(arg) => {}
+                            arg if arg.starts_with('-') => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "history".to_string(),
+                                    error_message: format!("Unknown flag: {arg}"),
+                                };
+                            }
+                            _ => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "history".to_string(),
+                                    error_message: format!("Unexpected argument: {}", args_vec[i]),
+                                };
+                            }
+                        }
                         i += 1;
-                    } else {
-                        log_error_standalone!(
-                            "Missing directory for --config. Usage: --config <directory>"
-                        );
-                        unknown_arg_found = true;
                     }
+
+                    return CliAction::HistoryCommand { json: json_output };
                 }
-                "--geo" | "-g" => {
-                    show_deprecation_warning(arg_str, "sunsetr geo");
-                    run_geo_selection = true;
-                }
-                "--reload" | "-r" => {
-                    log_warning_standalone!(
-                        "'--reload' is deprecated and will be removed in v1.0.0\n\n\
-                        Sunsetr now has hot reloading for configuration changes.\n\
-                        Use 'sunsetr restart' when you need to re-initialize the application.\n\n\
-                        Note: 'restart' runs in foreground by default (breaking change).\n\
-                        Use 'sunsetr --background restart' for the old background behavior."
-                    );
-                    run_reload = true;
-                }
-                "--test" | "-t" => {
-                    show_deprecation_warning(arg_str, "sunsetr test");
-                    run_test = true;
-                    if i + 2 < args_vec.len() {
-                        match args_vec[i + 1].parse::<u32>() {
-                            Ok(temp) => test_temperature = Some(temp),
-                            Err(_) => {
-                                log_error_standalone!(
-                                    "Invalid temperature value: {}",
-                                    args_vec[i + 1]
-                                );
-                                unknown_arg_found = true;
+                "doctor" => {
+                    let mut json_output = false;
+
+                    let mut i = cmd_idx + 1;
+                    while i < args_vec.len() {
+                        match args_vec[i].as_str() {
+                            "--json" | "-j" => json_output = true,
+                            "--config" | "-c" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    i += 1;
+                                }
+                            }
+                            arg if is_global_noop_flag(arg) => {}
+                            arg if arg.starts_with('-') => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "doctor".to_string(),
+                                    error_message: format!("Unknown flag: {arg}"),
+                                };
+                            }
+                            _ => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "doctor".to_string(),
+                                    error_message: format!("Unexpected argument: {}", args_vec[i]),
+                                };
                             }
                         }
+                        i += 1;
+                    }
 
-                        match args_vec[i + 2].parse::<f64>() {
-                            Ok(gamma) => test_gamma = Some(gamma),
-                            Err(_) => {
-                                log_error_standalone!("Invalid gamma value: {}", args_vec[i + 2]);
-                                unknown_arg_found = true;
+                    return CliAction::DoctorCommand {
+                        json: json_output,
+                        config_dir,
+                    };
+                }
+                "bugreport" => {
+                    let mut output = None;
+
+                    let mut i = cmd_idx + 1;
+                    while i < args_vec.len() {
+                        match args_vec[i].as_str() {
+                            "--output" | "-o" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    output = Some(args_vec[i + 1].clone());
+                                    i += 1;
+                                } else {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "bugreport".to_string(),
+                                        error_message: "--output requires a file".to_string(),
+                                    };
+                                }
+                            }
+                            "--config" | "-c" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    i += 1;
+                                }
+                            }
+                            arg if is_global_noop_flag(arg) => {}
+                            arg if arg.starts_with('-') => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "bugreport".to_string(),
+                                    error_message: format!("Unknown flag: {arg}"),
+                                };
+                            }
+                            _ => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "bugreport".to_string(),
+                                    error_message: format!("Unexpected argument: {}", args_vec[i]),
+                                };
                             }
                         }
-
-                        i += 2;
-                    } else {
-                        log_error_standalone!(
-                            "Missing arguments for test. Usage: test <temperature> <gamma>"
-                        );
-                        unknown_arg_found = true;
+                        i += 1;
                     }
+
+                    return CliAction::BugreportCommand { output, config_dir };
                 }
-                "--simulate" | "-S" => {
-                    run_simulate = true;
-                    if i + 2 < args_vec.len() {
-                        let start_str = args_vec[i + 1].clone();
-                        let end_str = args_vec[i + 2].clone();
+                "compute" => {
+                    let mut at = None;
+                    let mut json_output = false;
 
-                        let validate_datetime = |s: &str| -> bool {
-                            s.len() == 19
-                                && s.chars().nth(4) == Some('-')
-                                && s.chars().nth(7) == Some('-')
-                                && s.chars().nth(10) == Some(' ')
-                                && s.chars().nth(13) == Some(':')
-                                && s.chars().nth(16) == Some(':')
-                        };
+                    let mut i = cmd_idx + 1;
+                    while i < args_vec.len() {
+                        match args_vec[i].as_str() {
+                            "--at" | "-a" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    at = Some(args_vec[i + 1].clone());
+                                    i += 1;
+                                } else {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "compute".to_string(),
+                                        error_message: "--at requires a time".to_string(),
+                                    };
+                                }
+                            }
+                            "--json" | "-j" => json_output = true,
+                            "--config" | "-c" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    i += 1;
+                                }
+                            }
+                            arg if is_global_noop_flag(arg) => {}
+                            arg if arg.starts_with('-') => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "compute".to_string(),
+                                    error_message: format!("Unknown flag: {arg}"),
+                                };
+                            }
+                            _ => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "compute".to_string(),
+                                    error_message: format!("Unexpected argument: {}", args_vec[i]),
+                                };
+                            }
+                        }
+                        i += 1;
+                    }
 
-                        if !validate_datetime(&start_str) {
-                            log_error_standalone!(
-                                "Invalid start time format: '{start_str}'. Use YYYY-MM-DD HH:MM:SS"
-                            );
-                            unknown_arg_found = true;
-                            i += 2;
-                        } else if !validate_datetime(&end_str) {
-                            log_error_standalone!(
-                                "Invalid end time format: '{end_str}'. Use YYYY-MM-DD HH:MM:SS"
-                            );
-                            unknown_arg_found = true;
-                            i += 2;
-                        } else {
-                            simulate_start = Some(start_str);
-                            simulate_end = Some(end_str);
-                            i += 2;
+                    return CliAction::ComputeCommand {
+                        at,
+                        json: json_output,
+                        config_dir,
+                    };
+                }
+                "curve" => {
+                    let mut date = None;
+                    let mut svg = false;
 
-                            if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
-                                if let Ok(mult) = args_vec[i + 1].parse::<f64>() {
-                                    if !(0.1..=3600.0).contains(&mult) {
-                                        log_error_standalone!(
-                                            "Invalid multiplier: {mult}. Must be between 0.1 and 3600."
-                                        );
-                                        unknown_arg_found = true;
-                                    } else {
-                                        simulate_pace = Some(SimulationPace::Multiplier(mult));
-                                    }
+                    let mut i = cmd_idx + 1;
+                    while i < args_vec.len() {
+                        match args_vec[i].as_str() {
+                            "--date" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    date = Some(args_vec[i + 1].clone());
                                     i += 1;
+                                } else {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "curve".to_string(),
+                                        error_message: "--date requires a date".to_string(),
+                                    };
                                 }
-                            } else if i + 1 < args_vec.len() && args_vec[i + 1] == "--fast-forward"
-                            {
-                                simulate_pace = Some(SimulationPace::FastForward);
-                                i += 1;
                             }
-
-                            if i + 1 < args_vec.len() && args_vec[i + 1] == "--log" {
-                                log_to_file = true;
-                                i += 1;
+                            "--svg" => svg = true,
+                            "--config" | "-c" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    i += 1;
+                                }
+                            }
+                            arg if is_global_noop_flag(arg) => {}
+                            arg if arg.starts_with('-') => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "curve".to_string(),
+                                    error_message: format!("Unknown flag: {arg}"),
+                                };
+                            }
+                            _ => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "curve".to_string(),
+                                    error_message: format!("Unexpected argument: {}", args_vec[i]),
+                                };
                             }
                         }
-                    } else {
-                        log_error_standalone!(
-                            "Missing arguments for --simulate. Usage: --simulate \"YYYY-MM-DD HH:MM:SS\" \"YYYY-MM-DD HH:MM:SS\" [multiplier | --fast-forward] [--log]"
-                        );
-                        unknown_arg_found = true;
+                        i += 1;
                     }
+
+                    return CliAction::CurveCommand {
+                        date,
+                        svg,
+                        config_dir,
+                    };
                 }
-                _ => {
-                    if arg_str.starts_with('-') {
-                        log_warning_standalone!("Unknown option: {arg_str}");
-                        unknown_arg_found = true;
+                "week" => {
+                    let mut start = None;
+                    let mut ascii = false;
+
+                    let mut i = cmd_idx + 1;
+                    while i < args_vec.len() {
+                        match args_vec[i].as_str() {
+                            "--start" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    start = Some(args_vec[i + 1].clone());
+                                    i += 1;
+                                } else {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "week".to_string(),
+                                        error_message: "--start requires a date".to_string(),
+                                    };
+                                }
+                            }
+                            "--ascii" => ascii = true,
+                            "--config" | "-c" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    i += 1;
+                                }
+                            }
+                            arg if is_global_noop_flag(arg) => {}
+                            arg if arg.starts_with('-') => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "week".to_string(),
+                                    error_message: format!("Unknown flag: {arg}"),
+                                };
+                            }
+                            _ => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "week".to_string(),
+                                    error_message: format!("Unexpected argument: {}", args_vec[i]),
+                                };
+                            }
+                        }
+                        i += 1;
                     }
+
+                    return CliAction::WeekCommand {
+                        start,
+                        ascii,
+                        config_dir,
+                    };
                 }
-            }
-            i += 1;
-        }
+                "render-preview" => {
+                    let mut temp = None;
+                    let mut gamma = None;
+                    let mut output = None;
 
-        if config_dir.is_some() {
-            let old_command = if run_geo_selection {
-                "geo"
-            } else if run_test {
-                "test"
-            } else if run_reload {
-                "reload"
-            } else if run_simulate {
-                "simulate"
-            } else {
-                "run"
-            };
-            if config_is_inert(old_command) {
+                    let mut i = cmd_idx + 1;
+                    while i < args_vec.len() {
+                        match args_vec[i].as_str() {
+                            "--temp" => match args_vec.get(i + 1).map(|v| v.parse::<u32>()) {
+                                Some(Ok(value)) => {
+                                    temp = Some(value);
+                                    i += 1;
+                                }
+                                _ => {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "render-preview".to_string(),
+                                        error_message: "--temp requires a temperature in Kelvin"
+                                            .to_string(),
+                                    };
+                                }
+                            },
+                            "--gamma" => match args_vec.get(i + 1).map(|v| v.parse::<f64>()) {
+                                Some(Ok(value)) => {
+                                    gamma = Some(value);
+                                    i += 1;
+                                }
+                                _ => {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "render-preview".to_string(),
+                                        error_message: "--gamma requires a percentage".to_string(),
+                                    };
+                                }
+                            },
+                            "--out" | "-o" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    output = Some(args_vec[i + 1].clone());
+                                    i += 1;
+                                } else {
+                                    return CliAction::ShowCommandUsageDueToError {
+                                        command: "render-preview".to_string(),
+                                        error_message: "--out requires a file".to_string(),
+                                    };
+                                }
+                            }
+                            "--config" | "-c" => {
+                                if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                    i += 1;
+                                }
+                            }
+                            arg if is_global_noop_flag(arg) => {}
+                            arg if arg.starts_with('-') => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "render-preview".to_string(),
+                                    error_message: format!("Unknown flag: {arg}"),
+                                };
+                            }
+                            _ => {
+                                return CliAction::ShowCommandUsageDueToError {
+                                    command: "render-preview".to_string(),
+                                    error_message: format!("Unexpected argument: {}", args_vec[i]),
+                                };
+                            }
+                        }
+                        i += 1;
+                    }
+
+                    let Some(output) = output else {
+                        return CliAction::ShowCommandUsageDueToError {
+                            command: "render-preview".to_string(),
+                            error_message: "Missing --out <file>".to_string(),
+                        };
+                    };
+                    return CliAction::RenderPreviewCommand {
+                        temp,
+                        gamma,
+                        output,
+                        config_dir,
+                    };
+                }
+                "config" => {
+                    let subcommand = match args_vec.get(cmd_idx + 1).map(String::as_str) {
+                        Some("schema") => ConfigSubcommand::Schema,
+                        Some("check") => ConfigSubcommand::Check,
+                        Some(other) if !other.starts_with('-') => {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "config".to_string(),
+                                error_message: format!("Unknown subcommand: {other}"),
+                            };
+                        }
+                        _ => {
+                            return CliAction::ShowCommandUsageDueToError {
+                                command: "config".to_string(),
+                                error_message: "Missing subcommand".to_string(),
+                            };
+                        }
+                    };
+                    return CliAction::ConfigCommand {
+                        subcommand,
+                        config_dir,
+                    };
+                }
+                _ => {
+                    log_warning_standalone!("Unknown command: {command}");
+                    return CliAction::ShowHelpDueToError;
+                }
+            }
+        }
+
+        let mut debug_enabled = false;
+        let mut display_help = false;
+        let mut display_version = false;
+        let mut version_json = false;
+        let mut run_geo_selection = false;
+        let mut run_reload = false;
+        let mut run_test = false;
+        let mut test_temperature: Option<u32> = None;
+        let mut test_gamma: Option<f64> = None;
+        let mut run_simulate = false;
+        let mut simulate_start: Option<String> = None;
+        let mut simulate_end: Option<String> = None;
+        let mut simulate_pace: Option<SimulationPace> = None;
+        let mut simulate_json = false;
+        let mut log_to_file = false;
+        let mut unknown_arg_found = false;
+        let mut config_dir: Option<String> = None;
+        let mut background = false;
+        let mut profile = false;
+        let mut safe_mode = false;
+        let mut dry_run = false;
+
+        let mut i = 0;
+        while i < args_vec.len() {
+            let arg_str = &args_vec[i];
+            match arg_str.as_str() {
+                "--help" | "-h" => display_help = true,
+                "--version" | "-V" | "-v" => display_version = true,
+                "--json" | "-j" => version_json = true,
+                "--debug" | "-d" => debug_enabled = true,
+                "--background" | "-b" => background = true,
+                "--profile" => profile = true,
+                "--safe-mode" => safe_mode = true,
+                "--dry-run" => dry_run = true,
+                "--config" | "-c" => {
+                    if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                        config_dir = Some(args_vec[i + 1].clone());
+                        i += 1;
+                    } else {
+                        log_error_standalone!(
+                            "Missing directory for --config. Usage: --config <directory>"
+                        );
+                        unknown_arg_found = true;
+                    }
+                }
+                "--geo" | "-g" => {
+                    show_deprecation_warning(arg_str, "sunsetr geo");
+                    run_geo_selection = true;
+                }
+                "--reload" | "-r" => {
+                    log_warning_standalone!(
+                        "'--reload' is deprecated and will be removed in v1.0.0\n\n\
+                        Sunsetr now has hot reloading for configuration changes.\n\
+                        Use 'sunsetr restart' when you need to re-initialize the application.\n\n\
+                        Note: 'restart' runs in foreground by default (breaking change).\n\
+                        Use 'sunsetr --background restart' for the old background behavior."
+                    );
+                    run_reload = true;
+                }
+                "--test" | "-t" => {
+                    show_deprecation_warning(arg_str, "sunsetr test");
+                    run_test = true;
+                    if i + 2 < args_vec.len() {
+                        match args_vec[i + 1].parse::<u32>() {
+                            Ok(temp) => test_temperature = Some(temp),
+                            Err(_) => {
+                                log_error_standalone!(
+                                    "Invalid temperature value: {}",
+                                    args_vec[i + 1]
+                                );
+                                unknown_arg_found = true;
+                            }
+                        }
+
+                        match args_vec[i + 2].parse::<f64>() {
+                            Ok(gamma) => test_gamma = Some(gamma),
+                            Err(_) => {
+                                log_error_standalone!("Invalid gamma value: {}", args_vec[i + 2]);
+                                unknown_arg_found = true;
+                            }
+                        }
+
+                        i += 2;
+                    } else {
+                        log_error_standalone!(
+                            "Missing arguments for test. Usage: test <temperature> <gamma>"
+                        );
+                        unknown_arg_found = true;
+                    }
+                }
+                "--simulate" | "-S" => {
+                    run_simulate = true;
+                    if i + 2 < args_vec.len() {
+                        let start_str = args_vec[i + 1].clone();
+                        let end_str = args_vec[i + 2].clone();
+
+                        let validate_datetime = |s: &str| -> bool {
+                            s.len() == 19
+                                && s.chars().nth(4) == Some('-')
+                                && s.chars().nth(7) == Some('-')
+                                && s.chars().nth(10) == Some(' ')
+                                && s.chars().nth(13) == Some(':')
+                                && s.chars().nth(16) == Some(':')
+                        };
+
+                        if !validate_datetime(&start_str) {
+                            log_error_standalone!(
+                                "Invalid start time format: '{start_str}'. Use YYYY-MM-DD HH:MM:SS"
+                            );
+                            unknown_arg_found = true;
+                            i += 2;
+                        } else if !validate_datetime(&end_str) {
+                            log_error_standalone!(
+                                "Invalid end time format: '{end_str}'. Use YYYY-MM-DD HH:MM:SS"
+                            );
+                            unknown_arg_found = true;
+                            i += 2;
+                        } else {
+                            simulate_start = Some(start_str);
+                            simulate_end = Some(end_str);
+                            i += 2;
+
+                            if i + 1 < args_vec.len() && !args_vec[i + 1].starts_with('-') {
+                                if let Ok(mult) = args_vec[i + 1].parse::<f64>() {
+                                    if !(0.1..=3600.0).contains(&mult) {
+                                        log_error_standalone!(
+                                            "Invalid multiplier: {mult}. Must be between 0.1 and 3600."
+                                        );
+                                        unknown_arg_found = true;
+                                    } else {
+                                        simulate_pace = Some(SimulationPace::Multiplier(mult));
+                                    }
+                                    i += 1;
+                                }
+                            } else if i + 1 < args_vec.len() && args_vec[i + 1] == "--fast-forward"
+                            {
+                                simulate_pace = Some(SimulationPace::FastForward);
+                                i += 1;
+                            }
+
+                            if i + 1 < args_vec.len() && args_vec[i + 1] == "--log" {
+                                log_to_file = true;
+                                i += 1;
+                            }
+
+                            if i + 1 < args_vec.len() && args_vec[i + 1] == "--format" {
+                                match args_vec.get(i + 2).map(String::as_str) {
+                                    Some("json") => simulate_json = true,
+                                    Some("text") => {}
+                                    other => {
+                                        log_error_standalone!(
+                                            "Invalid format: '{}'. Use 'json' or 'text'",
+                                            other.unwrap_or_default()
+                                        );
+                                        unknown_arg_found = true;
+                                    }
+                                }
+                                i += 2;
+                            }
+                        }
+                    } else {
+                        log_error_standalone!(
+                            "Missing arguments for --simulate. Usage: --simulate \"YYYY-MM-DD HH:MM:SS\" \"YYYY-MM-DD HH:MM:SS\" [multiplier | --fast-forward] [--log] [--format json]"
+                        );
+                        unknown_arg_found = true;
+                    }
+                }
+                _ => {
+                    if arg_str.starts_with('-') {
+                        log_warning_standalone!("Unknown option: {arg_str}");
+                        unknown_arg_found = true;
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        if config_dir.is_some() {
+            let old_command = if run_geo_selection {
+                "geo"
+            } else if run_test {
+                "test"
+            } else if run_reload {
+                "reload"
+            } else if run_simulate {
+                "simulate"
+            } else {
+                "run"
+            };
+            if config_is_inert(old_command) {
                 log_warning_standalone!("{}", config_ignored_message(old_command));
                 config_dir = None;
             }
         }
 
+        if dry_run && background {
+            log_error_standalone!(
+                "--dry-run runs in the foreground and can't be combined with --background"
+            );
+            unknown_arg_found = true;
+        }
+
+        if version_json && !display_version {
+            log_warning_standalone!("--json without a command only applies to --version");
+            unknown_arg_found = true;
+        }
+
         if display_version {
-            CliAction::ShowVersion
+            CliAction::ShowVersion { json: version_json }
         } else if display_help || unknown_arg_found {
             if unknown_arg_found {
                 CliAction::ShowHelpDueToError
@@ -741,6 +1626,7 @@ impl CliAction {
                 debug_enabled,
                 config_dir,
                 target: None,
+                provider: None,
             }
         } else if run_reload {
             CliAction::RestartCommand {
@@ -769,6 +1655,7 @@ impl CliAction {
                     end_time: end,
                     pace: simulate_pace.unwrap_or(SimulationPace::Multiplier(3600.0)),
                     log_to_file,
+                    json: simulate_json,
                     config_dir,
                 },
                 _ => {
@@ -781,6 +1668,9 @@ impl CliAction {
                 debug_enabled,
                 config_dir,
                 background,
+                profile,
+                safe_mode,
+                dry_run,
             }
         }
     }
@@ -790,16 +1680,78 @@ impl CliAction {
     }
 }
 
+/// Parse what follows `grab`: its own flags, then the command to run, optionally after `--`.
+fn parse_grab(args: &[String]) -> CliAction {
+    for (i, arg) in args.iter().enumerate() {
+        match arg.as_str() {
+            "--" => {
+                let command = args[i + 1..].to_vec();
+                if command.is_empty() {
+                    break;
+                }
+                return CliAction::GrabCommand { command };
+            }
+            "--help" | "-h" => {
+                return CliAction::UsageHelp {
+                    command: "grab".to_string(),
+                };
+            }
+            arg if is_global_noop_flag(arg) => {}
+            arg if arg.starts_with('-') => {
+                return CliAction::ShowCommandUsageDueToError {
+                    command: "grab".to_string(),
+                    error_message: format!("Unknown flag: {arg}"),
+                };
+            }
+            _ => {
+                return CliAction::GrabCommand {
+                    command: args[i..].to_vec(),
+                };
+            }
+        }
+    }
+    CliAction::ShowCommandUsageDueToError {
+        command: "grab".to_string(),
+        error_message: "Missing command to run".to_string(),
+    }
+}
+
 /// Whether `command` acts on the already-running instance, so a
 /// `--config` directory does not apply and is ignored. Everything else
 /// uses `--config` to choose which configuration directory to act on.
-fn config_is_inert(command: &str) -> bool {
-    matches!(command, "stop" | "test" | "t" | "status" | "S")
+/// One relative change given to `sunsetr adjust`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AdjustStep {
+    Temp(i32),
+    Gamma(f64),
 }
 
-/// Warning shown when --config is passed to a command that acts on the
-/// already-running instance and ignores it.
-fn config_ignored_message(command: &str) -> String {
+/// Parse a signed change: `+500` or `-300K` for the temperature, `-10%` for the gamma. The
+/// sign is required so a change can't be mistaken for an absolute value.
+fn parse_adjust_step(arg: &str) -> Option<AdjustStep> {
+    if !arg.starts_with(['+', '-']) {
+        return None;
+    }
+    if let Some(percent) = arg.strip_suffix('%') {
+        let percent: f64 = percent.parse().ok()?;
+        return (percent.is_finite() && percent.abs() <= 200.0)
+            .then_some(AdjustStep::Gamma(percent));
+    }
+    let kelvin = arg.strip_suffix(['K', 'k']).unwrap_or(arg);
+    let kelvin: i32 = kelvin.parse().ok()?;
+    (kelvin.abs() <= 20_000).then_some(AdjustStep::Temp(kelvin))
+}
+
+fn config_is_inert(command: &str) -> bool {
+    matches!(
+        command,
+        "stop" | "refresh" | "toggle" | "pause" | "test" | "t" | "status" | "S" | "history"
+    )
+}
+
+/// Warning shown when --config is passed to a command that acts on the
+/// already-running instance and ignores it.
+fn config_ignored_message(command: &str) -> String {
     format!("'{command}' ignores --config and acts on the running instance.")
 }
 
@@ -816,10 +1768,17 @@ fn show_deprecation_warning(old_form: &str, new_form: &str) {
     );
 }
 
-pub fn display_version_info() {
+/// Print the version banner, or the build metadata as JSON when `json` is set.
+pub fn display_version_info(json: bool) -> anyhow::Result<()> {
+    if json {
+        let info = crate::common::build_info::BuildInfo::current();
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(());
+    }
     log_version!();
     log_pipe!();
     println!("┗ {}", env!("CARGO_PKG_DESCRIPTION"));
+    Ok(())
 }
 
 pub fn display_help() {
@@ -830,20 +1789,41 @@ pub fn display_help() {
     log_indented!("-b, --background        Run process in background");
     log_indented!("-c, --config <dir>      Use custom configuration directory");
     log_indented!("-d, --debug             Enable detailed debug output");
+    log_indented!("    --dry-run           Run as usual but leave the display untouched");
     log_indented!("-h, --help              Print help information");
+    log_indented!("    --profile           Print loop and backend timings on exit");
+    log_indented!("    --safe-mode         Start with neutral values if the config is broken");
     log_indented!("-S, --simulate          Run with simulated time (for testing transitions)");
-    log_indented!("                        Usage: --simulate <start> <end> [mult] [--log]");
+    log_indented!(
+        "                        Usage: --simulate <start> <end> [mult] [--log] [--format json]"
+    );
     log_indented!("-V, --version           Print version information");
+    log_indented!("    --version --json    Print build metadata as JSON");
     log_block_start!("Commands:");
+    log_indented!("adjust <+|-N[K|%]>      Nudge the temperature or gamma for today");
+    log_indented!("bugreport [-o <file>]   Collect a redacted report for bug reports");
+    log_indented!("compute [--at <time>]   Print the values for a given time");
+    log_indented!("config schema           Print the JSON Schema of the config file");
+    log_indented!("config check            Validate the config and count transition updates");
+    log_indented!("curve [--svg]           Chart the temperature and gamma over a day");
+    log_indented!("doctor [--json]         Report compositor, quirks and backend choice");
     log_indented!("geo, G                  Interactive city selection for geo mode");
     log_indented!("get, g <field>          Read configuration field(s)");
     log_indented!("help, h [COMMAND]       Show help for a specific command");
+    log_indented!("grab -- <command>       Run a screenshot command with neutral colors");
+    log_indented!("history [--json]        List recently applied states");
+    log_indented!("pause [--for <time>]    Pause color shifting, optionally for a while");
     log_indented!("preset, p <name>        Apply a named preset configuration");
     log_indented!("restart, r [--instant]  Recreate backend and reload configuration");
     log_indented!("set, s <field>[op]=val  Update configuration field(s)");
+    log_indented!("presentation <on|off>   Hold outputs neutral until turned off");
+    log_indented!("refresh                 Rebuild gamma on every output and reapply");
+    log_indented!("render-preview          Write a test card PNG as a temperature shows it");
     log_indented!("status, S               Display current runtime state");
     log_indented!("stop                    Cleanly terminate running sunsetr instance");
     log_indented!("test, t <temp> <gamma>  Test specific temperature and gamma values");
+    log_indented!("toggle                  Pause color shifting, or resume it");
+    log_indented!("week [--start <date>]   Show a week of day, night and transitions");
     log_pipe!();
     log_info!("See 'sunsetr help <command>' for more information on a specific command.");
     log_end!();
@@ -862,558 +1842,1214 @@ mod tests {
             CliAction::Run {
                 debug_enabled: false,
                 config_dir: None,
-                background: false,
+                background: false,
+                profile: false,
+                safe_mode: false,
+                dry_run: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_debug_flag() {
+        let args = vec!["sunsetr", "--debug"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::Run {
+                debug_enabled: true,
+                config_dir: None,
+                background: false,
+                profile: false,
+                safe_mode: false,
+                dry_run: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_debug_short_flag() {
+        let args = vec!["sunsetr", "-d"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::Run {
+                debug_enabled: true,
+                config_dir: None,
+                background: false,
+                profile: false,
+                safe_mode: false,
+                dry_run: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_help_flag() {
+        let args = vec!["sunsetr", "--help"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(parsed, CliAction::ShowHelp);
+    }
+
+    #[test]
+    fn test_parse_help_short_flag() {
+        let args = vec!["sunsetr", "-h"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(parsed, CliAction::ShowHelp);
+    }
+
+    #[test]
+    fn test_parse_version_flag() {
+        let args = vec!["sunsetr", "--version"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(parsed, CliAction::ShowVersion { json: false });
+    }
+
+    #[test]
+    fn test_parse_version_short_flags() {
+        let args1 = vec!["sunsetr", "-V"];
+        let parsed1 = CliAction::parse(args1);
+        assert_eq!(parsed1, CliAction::ShowVersion { json: false });
+
+        let args2 = vec!["sunsetr", "-v"];
+        let parsed2 = CliAction::parse(args2);
+        assert_eq!(parsed2, CliAction::ShowVersion { json: false });
+    }
+
+    #[test]
+    fn test_parse_version_json() {
+        for args in [
+            vec!["sunsetr", "--version", "--json"],
+            vec!["sunsetr", "-j", "-V"],
+            vec!["sunsetr", "status", "--version", "--json"],
+        ] {
+            assert_eq!(
+                CliAction::parse(args),
+                CliAction::ShowVersion { json: true }
+            );
+        }
+
+        let args = vec!["sunsetr", "--json"];
+        assert_eq!(CliAction::parse(args), CliAction::ShowHelpDueToError);
+    }
+
+    #[test]
+    fn test_parse_multiple_flags() {
+        let args = vec!["sunsetr", "--debug", "--help"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(parsed, CliAction::ShowHelp);
+    }
+
+    #[test]
+    fn test_parse_unknown_flag() {
+        let args = vec!["sunsetr", "--unknown"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(parsed, CliAction::ShowHelpDueToError);
+    }
+
+    #[test]
+    fn test_parse_mixed_valid_and_invalid() {
+        let args = vec!["sunsetr", "--debug", "--invalid"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(parsed, CliAction::ShowHelpDueToError);
+    }
+
+    #[test]
+    fn test_version_takes_precedence() {
+        let args = vec!["sunsetr", "--version", "--help", "--debug"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(parsed, CliAction::ShowVersion { json: false });
+    }
+
+    #[test]
+    fn test_parse_geo_flag() {
+        let args = vec!["sunsetr", "--geo"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::GeoCommand {
+                debug_enabled: false,
+                config_dir: None,
+                target: None,
+                provider: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_geo_short_flag() {
+        let args = vec!["sunsetr", "-g"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::GeoCommand {
+                debug_enabled: false,
+                config_dir: None,
+                target: None,
+                provider: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_geo_with_debug() {
+        let args = vec!["sunsetr", "--geo", "--debug"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::GeoCommand {
+                debug_enabled: true,
+                config_dir: None,
+                target: None,
+                provider: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_debug_with_geo() {
+        let args = vec!["sunsetr", "--debug", "--geo"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::GeoCommand {
+                debug_enabled: true,
+                config_dir: None,
+                target: None,
+                provider: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_debug_with_test_subcommand() {
+        let args = vec!["sunsetr", "-d", "test", "2333", "70"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::TestCommand {
+                debug_enabled: true,
+                temperature: 2333,
+                gamma: 70.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_test_subcommand_with_debug_after() {
+        let args = vec!["sunsetr", "test", "2333", "70", "-d"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::TestCommand {
+                debug_enabled: true,
+                temperature: 2333,
+                gamma: 70.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_command_json_flag_before_field() {
+        let args = vec!["sunsetr", "get", "--json", "day_temp"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::GetCommand {
+                fields: vec!["day_temp".to_string()],
+                config_dir: None,
+                target: None,
+                json: true,
+                overrides: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_command_json_flag_after_field() {
+        let args = vec!["sunsetr", "get", "day_temp", "--json"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::GetCommand {
+                fields: vec!["day_temp".to_string()],
+                config_dir: None,
+                target: None,
+                json: true,
+                overrides: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_command_json_flag_between_fields() {
+        let args = vec!["sunsetr", "get", "day_temp", "--json", "night_temp"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::GetCommand {
+                fields: vec!["day_temp".to_string(), "night_temp".to_string()],
+                config_dir: None,
+                target: None,
+                json: true,
+                overrides: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_command_short_json_flag_after() {
+        let args = vec!["sunsetr", "get", "day_temp", "-j"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::GetCommand {
+                fields: vec!["day_temp".to_string()],
+                config_dir: None,
+                target: None,
+                json: true,
+                overrides: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_command_multiple_fields_json_at_end() {
+        let args = vec![
+            "sunsetr",
+            "get",
+            "day_temp",
+            "night_temp",
+            "gamma",
+            "--json",
+        ];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::GetCommand {
+                fields: vec![
+                    "day_temp".to_string(),
+                    "night_temp".to_string(),
+                    "gamma".to_string()
+                ],
+                config_dir: None,
+                target: None,
+                json: true,
+                overrides: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_command_with_target_and_json_at_end() {
+        let args = vec!["sunsetr", "get", "--target", "gaming", "day_temp", "--json"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::GetCommand {
+                fields: vec!["day_temp".to_string()],
+                config_dir: None,
+                target: Some("gaming".to_string()),
+                json: true,
+                overrides: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_command_target_flag_before_fields() {
+        let args = vec!["sunsetr", "set", "--target", "gaming", "day_temp=5000"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::SetCommand {
+                fields: vec![(
+                    "day_temp".to_string(),
+                    SetOperator::Assign,
+                    "5000".to_string()
+                )],
+                config_dir: None,
+                target: Some("gaming".to_string()),
+                create: false,
+                runtime: false,
+                clear: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_command_target_flag_after_fields() {
+        let args = vec!["sunsetr", "set", "day_temp=5000", "--target", "gaming"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::SetCommand {
+                fields: vec![(
+                    "day_temp".to_string(),
+                    SetOperator::Assign,
+                    "5000".to_string()
+                )],
+                config_dir: None,
+                target: Some("gaming".to_string()),
+                create: false,
+                runtime: false,
+                clear: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_command_target_flag_between_fields() {
+        let args = vec![
+            "sunsetr",
+            "set",
+            "day_temp=5000",
+            "--target",
+            "gaming",
+            "night_temp=2800",
+        ];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::SetCommand {
+                fields: vec![
+                    (
+                        "day_temp".to_string(),
+                        SetOperator::Assign,
+                        "5000".to_string()
+                    ),
+                    (
+                        "night_temp".to_string(),
+                        SetOperator::Assign,
+                        "2800".to_string()
+                    )
+                ],
+                config_dir: None,
+                target: Some("gaming".to_string()),
+                create: false,
+                runtime: false,
+                clear: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_command_short_target_flag_after_fields() {
+        let args = vec![
+            "sunsetr",
+            "set",
+            "day_temp=5000",
+            "gamma=0.9",
+            "-t",
+            "gaming",
+        ];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::SetCommand {
+                fields: vec![
+                    (
+                        "day_temp".to_string(),
+                        SetOperator::Assign,
+                        "5000".to_string()
+                    ),
+                    ("gamma".to_string(), SetOperator::Assign, "0.9".to_string())
+                ],
+                config_dir: None,
+                target: Some("gaming".to_string()),
+                create: false,
+                runtime: false,
+                clear: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_command_increment_operator() {
+        let args = vec!["sunsetr", "set", "night_temp+=500"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::SetCommand {
+                fields: vec![(
+                    "night_temp".to_string(),
+                    SetOperator::Increment,
+                    "500".to_string()
+                )],
+                config_dir: None,
+                target: None,
+                create: false,
+                runtime: false,
+                clear: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_command_decrement_operator() {
+        let args = vec!["sunsetr", "set", "static_gamma-=2"];
+        let parsed = CliAction::parse(args);
+        assert_eq!(
+            parsed,
+            CliAction::SetCommand {
+                fields: vec![(
+                    "static_gamma".to_string(),
+                    SetOperator::Decrement,
+                    "2".to_string()
+                )],
+                config_dir: None,
+                target: None,
+                create: false,
+                runtime: false,
+                clear: false,
             }
         );
     }
 
     #[test]
-    fn test_parse_debug_flag() {
-        let args = vec!["sunsetr", "--debug"];
+    fn test_set_command_mixed_operators() {
+        let args = vec![
+            "sunsetr",
+            "set",
+            "night_temp+=200",
+            "day_temp=6500",
+            "static_gamma-=5",
+        ];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::Run {
-                debug_enabled: true,
+            CliAction::SetCommand {
+                fields: vec![
+                    (
+                        "night_temp".to_string(),
+                        SetOperator::Increment,
+                        "200".to_string()
+                    ),
+                    (
+                        "day_temp".to_string(),
+                        SetOperator::Assign,
+                        "6500".to_string()
+                    ),
+                    (
+                        "static_gamma".to_string(),
+                        SetOperator::Decrement,
+                        "5".to_string()
+                    ),
+                ],
                 config_dir: None,
-                background: false,
+                target: None,
+                create: false,
+                runtime: false,
+                clear: false,
             }
         );
     }
 
     #[test]
-    fn test_parse_debug_short_flag() {
-        let args = vec!["sunsetr", "-d"];
+    fn test_set_command_increment_with_decimal() {
+        let args = vec!["sunsetr", "set", "night_gamma+=5.5"];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::Run {
-                debug_enabled: true,
+            CliAction::SetCommand {
+                fields: vec![(
+                    "night_gamma".to_string(),
+                    SetOperator::Increment,
+                    "5.5".to_string()
+                )],
                 config_dir: None,
-                background: false,
+                target: None,
+                create: false,
+                runtime: false,
+                clear: false,
             }
         );
     }
 
     #[test]
-    fn test_parse_help_flag() {
-        let args = vec!["sunsetr", "--help"];
-        let parsed = CliAction::parse(args);
-        assert_eq!(parsed, CliAction::ShowHelp);
-    }
-
-    #[test]
-    fn test_parse_help_short_flag() {
-        let args = vec!["sunsetr", "-h"];
-        let parsed = CliAction::parse(args);
-        assert_eq!(parsed, CliAction::ShowHelp);
-    }
-
-    #[test]
-    fn test_parse_version_flag() {
-        let args = vec!["sunsetr", "--version"];
-        let parsed = CliAction::parse(args);
-        assert_eq!(parsed, CliAction::ShowVersion);
-    }
-
-    #[test]
-    fn test_parse_version_short_flags() {
-        let args1 = vec!["sunsetr", "-V"];
-        let parsed1 = CliAction::parse(args1);
-        assert_eq!(parsed1, CliAction::ShowVersion);
-
-        let args2 = vec!["sunsetr", "-v"];
-        let parsed2 = CliAction::parse(args2);
-        assert_eq!(parsed2, CliAction::ShowVersion);
-    }
-
-    #[test]
-    fn test_parse_multiple_flags() {
-        let args = vec!["sunsetr", "--debug", "--help"];
-        let parsed = CliAction::parse(args);
-        assert_eq!(parsed, CliAction::ShowHelp);
-    }
-
-    #[test]
-    fn test_parse_unknown_flag() {
-        let args = vec!["sunsetr", "--unknown"];
-        let parsed = CliAction::parse(args);
-        assert_eq!(parsed, CliAction::ShowHelpDueToError);
-    }
-
-    #[test]
-    fn test_parse_mixed_valid_and_invalid() {
-        let args = vec!["sunsetr", "--debug", "--invalid"];
-        let parsed = CliAction::parse(args);
-        assert_eq!(parsed, CliAction::ShowHelpDueToError);
-    }
-
-    #[test]
-    fn test_version_takes_precedence() {
-        let args = vec!["sunsetr", "--version", "--help", "--debug"];
-        let parsed = CliAction::parse(args);
-        assert_eq!(parsed, CliAction::ShowVersion);
-    }
-
-    #[test]
-    fn test_parse_geo_flag() {
-        let args = vec!["sunsetr", "--geo"];
+    fn test_set_command_increment_with_target() {
+        let args = vec!["sunsetr", "set", "--target", "gaming", "night_temp+=500"];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::GeoCommand {
-                debug_enabled: false,
+            CliAction::SetCommand {
+                fields: vec![(
+                    "night_temp".to_string(),
+                    SetOperator::Increment,
+                    "500".to_string()
+                )],
                 config_dir: None,
-                target: None,
+                target: Some("gaming".to_string()),
+                create: false,
+                runtime: false,
+                clear: false,
             }
         );
     }
 
     #[test]
-    fn test_parse_geo_short_flag() {
-        let args = vec!["sunsetr", "-g"];
-        let parsed = CliAction::parse(args);
+    fn test_set_and_get_runtime_overrides() {
+        let parsed = CliAction::parse(vec!["sunsetr", "set", "--runtime", "night_temp-=300"]);
         assert_eq!(
             parsed,
-            CliAction::GeoCommand {
-                debug_enabled: false,
+            CliAction::SetCommand {
+                fields: vec![(
+                    "night_temp".to_string(),
+                    SetOperator::Decrement,
+                    "300".to_string()
+                )],
                 config_dir: None,
                 target: None,
+                create: false,
+                runtime: true,
+                clear: false,
             }
         );
+
+        assert!(matches!(
+            CliAction::parse(vec!["sunsetr", "set", "--clear"]),
+            CliAction::SetCommand { clear: true, .. }
+        ));
+        assert!(matches!(
+            CliAction::parse(vec!["sunsetr", "set", "--clear", "night_temp=3000"]),
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
+        assert!(matches!(
+            CliAction::parse(vec![
+                "sunsetr",
+                "set",
+                "--runtime",
+                "-t",
+                "gaming",
+                "day_temp=5000"
+            ]),
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
+        assert!(matches!(
+            CliAction::parse(vec!["sunsetr", "get", "--overrides", "--json"]),
+            CliAction::GetCommand {
+                overrides: true,
+                json: true,
+                ..
+            }
+        ));
     }
 
     #[test]
-    fn test_geo_with_debug() {
-        let args = vec!["sunsetr", "--geo", "--debug"];
+    fn test_parse_background_flag() {
+        let args = vec!["sunsetr", "--background"];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::GeoCommand {
-                debug_enabled: true,
+            CliAction::Run {
+                debug_enabled: false,
                 config_dir: None,
-                target: None,
+                background: true,
+                profile: false,
+                safe_mode: false,
+                dry_run: false,
             }
         );
     }
 
     #[test]
-    fn test_debug_with_geo() {
-        let args = vec!["sunsetr", "--debug", "--geo"];
+    fn test_parse_background_short_flag() {
+        let args = vec!["sunsetr", "-b"];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::GeoCommand {
-                debug_enabled: true,
+            CliAction::Run {
+                debug_enabled: false,
                 config_dir: None,
-                target: None,
+                background: true,
+                profile: false,
+                safe_mode: false,
+                dry_run: false,
             }
         );
     }
 
     #[test]
-    fn test_debug_with_test_subcommand() {
-        let args = vec!["sunsetr", "-d", "test", "2333", "70"];
+    fn test_background_with_debug() {
+        let args = vec!["sunsetr", "--background", "--debug"];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::TestCommand {
+            CliAction::Run {
                 debug_enabled: true,
-                temperature: 2333,
-                gamma: 70.0,
+                config_dir: None,
+                background: true,
+                profile: false,
+                safe_mode: false,
+                dry_run: false,
             }
         );
     }
 
     #[test]
-    fn test_test_subcommand_with_debug_after() {
-        let args = vec!["sunsetr", "test", "2333", "70", "-d"];
+    fn test_parse_profile_flag() {
+        let args = vec!["sunsetr", "--profile", "--debug"];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::TestCommand {
+            CliAction::Run {
                 debug_enabled: true,
-                temperature: 2333,
-                gamma: 70.0,
+                config_dir: None,
+                background: false,
+                profile: true,
+                safe_mode: false,
+                dry_run: false,
             }
         );
     }
 
     #[test]
-    fn test_get_command_json_flag_before_field() {
-        let args = vec!["sunsetr", "get", "--json", "day_temp"];
+    fn test_parse_safe_mode_flag() {
+        let args = vec!["sunsetr", "--safe-mode"];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::GetCommand {
-                fields: vec!["day_temp".to_string()],
+            CliAction::Run {
+                debug_enabled: false,
                 config_dir: None,
-                target: None,
-                json: true,
+                background: false,
+                profile: false,
+                safe_mode: true,
+                dry_run: false,
             }
         );
     }
 
     #[test]
-    fn test_get_command_json_flag_after_field() {
-        let args = vec!["sunsetr", "get", "day_temp", "--json"];
-        let parsed = CliAction::parse(args);
+    fn test_parse_dry_run_flag() {
+        let parsed = CliAction::parse(vec!["sunsetr", "--dry-run", "--debug"]);
         assert_eq!(
             parsed,
-            CliAction::GetCommand {
-                fields: vec!["day_temp".to_string()],
+            CliAction::Run {
+                debug_enabled: true,
                 config_dir: None,
-                target: None,
-                json: true,
+                background: false,
+                profile: false,
+                safe_mode: false,
+                dry_run: true,
             }
         );
+        assert_eq!(
+            CliAction::parse(vec!["sunsetr", "--dry-run", "--background"]),
+            CliAction::ShowHelpDueToError
+        );
     }
 
     #[test]
-    fn test_get_command_json_flag_between_fields() {
-        let args = vec!["sunsetr", "get", "day_temp", "--json", "night_temp"];
+    fn test_background_restart() {
+        let args = vec!["sunsetr", "--background", "restart"];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::GetCommand {
-                fields: vec!["day_temp".to_string(), "night_temp".to_string()],
+            CliAction::RestartCommand {
+                debug_enabled: false,
+                instant: false,
                 config_dir: None,
-                target: None,
-                json: true,
+                background: true,
             }
         );
     }
 
     #[test]
-    fn test_get_command_short_json_flag_after() {
-        let args = vec!["sunsetr", "get", "day_temp", "-j"];
+    fn test_background_restart_instant() {
+        let args = vec!["sunsetr", "-b", "restart", "--instant"];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::GetCommand {
-                fields: vec!["day_temp".to_string()],
+            CliAction::RestartCommand {
+                debug_enabled: false,
+                instant: true,
                 config_dir: None,
-                target: None,
-                json: true,
+                background: true,
             }
         );
     }
 
     #[test]
-    fn test_get_command_multiple_fields_json_at_end() {
+    fn test_simulate_format_json() {
         let args = vec![
             "sunsetr",
-            "get",
-            "day_temp",
-            "night_temp",
-            "gamma",
-            "--json",
+            "--simulate",
+            "2025-01-15 18:00:00",
+            "2025-01-16 08:00:00",
+            "--fast-forward",
+            "--format",
+            "json",
         ];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::GetCommand {
-                fields: vec![
-                    "day_temp".to_string(),
-                    "night_temp".to_string(),
-                    "gamma".to_string()
-                ],
-                config_dir: None,
-                target: None,
+            CliAction::Simulate {
+                debug_enabled: false,
+                start_time: "2025-01-15 18:00:00".to_string(),
+                end_time: "2025-01-16 08:00:00".to_string(),
+                pace: SimulationPace::FastForward,
+                log_to_file: false,
                 json: true,
+                config_dir: None,
             }
         );
+
+        let args = vec![
+            "sunsetr",
+            "--simulate",
+            "2025-01-15 18:00:00",
+            "2025-01-16 08:00:00",
+            "--format",
+            "yaml",
+        ];
+        assert_eq!(CliAction::parse(args), CliAction::ShowHelpDueToError);
     }
 
     #[test]
-    fn test_get_command_with_target_and_json_at_end() {
-        let args = vec!["sunsetr", "get", "--target", "gaming", "day_temp", "--json"];
+    fn test_doctor_json_with_config() {
+        let args = vec![
+            "sunsetr",
+            "doctor",
+            "--json",
+            "--config",
+            "/tmp/sunsetr_alt",
+        ];
         let parsed = CliAction::parse(args);
         assert_eq!(
             parsed,
-            CliAction::GetCommand {
-                fields: vec!["day_temp".to_string()],
-                config_dir: None,
-                target: Some("gaming".to_string()),
+            CliAction::DoctorCommand {
                 json: true,
+                config_dir: Some("/tmp/sunsetr_alt".to_string()),
             }
         );
     }
 
     #[test]
-    fn test_set_command_target_flag_before_fields() {
-        let args = vec!["sunsetr", "set", "--target", "gaming", "day_temp=5000"];
-        let parsed = CliAction::parse(args);
+    fn test_bugreport_output() {
         assert_eq!(
-            parsed,
-            CliAction::SetCommand {
-                fields: vec![(
-                    "day_temp".to_string(),
-                    SetOperator::Assign,
-                    "5000".to_string()
-                )],
+            CliAction::parse(vec!["sunsetr", "bugreport", "-o", "/tmp/report.md"]),
+            CliAction::BugreportCommand {
+                output: Some("/tmp/report.md".to_string()),
                 config_dir: None,
-                target: Some("gaming".to_string()),
             }
         );
+        assert!(matches!(
+            CliAction::parse(vec!["sunsetr", "bugreport", "--output"]),
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
     }
 
     #[test]
-    fn test_set_command_target_flag_after_fields() {
-        let args = vec!["sunsetr", "set", "day_temp=5000", "--target", "gaming"];
-        let parsed = CliAction::parse(args);
+    fn test_geo_provider() {
         assert_eq!(
-            parsed,
-            CliAction::SetCommand {
-                fields: vec![(
-                    "day_temp".to_string(),
-                    SetOperator::Assign,
-                    "5000".to_string()
-                )],
+            CliAction::parse(vec!["sunsetr", "geo", "--provider", "timezone"]),
+            CliAction::GeoCommand {
+                debug_enabled: false,
                 config_dir: None,
-                target: Some("gaming".to_string()),
+                target: None,
+                provider: Some("timezone".to_string()),
             }
         );
+        assert!(matches!(
+            CliAction::parse(vec!["sunsetr", "geo", "-p"]),
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
     }
 
     #[test]
-    fn test_set_command_target_flag_between_fields() {
-        let args = vec![
-            "sunsetr",
-            "set",
-            "day_temp=5000",
-            "--target",
-            "gaming",
-            "night_temp=2800",
-        ];
-        let parsed = CliAction::parse(args);
+    fn test_presentation_on_and_off() {
         assert_eq!(
-            parsed,
-            CliAction::SetCommand {
-                fields: vec![
-                    (
-                        "day_temp".to_string(),
-                        SetOperator::Assign,
-                        "5000".to_string()
-                    ),
-                    (
-                        "night_temp".to_string(),
-                        SetOperator::Assign,
-                        "2800".to_string()
-                    )
-                ],
+            CliAction::parse(vec!["sunsetr", "presentation", "on"]),
+            CliAction::PresentationCommand {
+                subcommand: PresentationSubcommand::On { outputs: vec![] },
+                config_dir: None,
+            }
+        );
+        assert_eq!(
+            CliAction::parse(vec![
+                "sunsetr",
+                "presentation",
+                "on",
+                "DP-1",
+                "--config",
+                "/tmp/sunsetr_alt",
+                "HDMI-A-1",
+            ]),
+            CliAction::PresentationCommand {
+                subcommand: PresentationSubcommand::On {
+                    outputs: vec!["DP-1".to_string(), "HDMI-A-1".to_string()],
+                },
+                config_dir: Some("/tmp/sunsetr_alt".to_string()),
+            }
+        );
+        assert_eq!(
+            CliAction::parse(vec!["sunsetr", "presentation", "off"]),
+            CliAction::PresentationCommand {
+                subcommand: PresentationSubcommand::Off,
                 config_dir: None,
-                target: Some("gaming".to_string()),
             }
         );
+
+        for args in [
+            vec!["sunsetr", "presentation"],
+            vec!["sunsetr", "presentation", "toggle"],
+            vec!["sunsetr", "presentation", "off", "DP-1"],
+            vec!["sunsetr", "presentation", "on", "--all"],
+        ] {
+            assert!(matches!(
+                CliAction::parse(args),
+                CliAction::ShowCommandUsageDueToError { .. }
+            ));
+        }
     }
 
     #[test]
-    fn test_set_command_short_target_flag_after_fields() {
-        let args = vec![
-            "sunsetr",
-            "set",
-            "day_temp=5000",
-            "gamma=0.9",
-            "-t",
-            "gaming",
-        ];
-        let parsed = CliAction::parse(args);
+    fn test_adjust() {
         assert_eq!(
-            parsed,
-            CliAction::SetCommand {
-                fields: vec![
-                    (
-                        "day_temp".to_string(),
-                        SetOperator::Assign,
-                        "5000".to_string()
-                    ),
-                    ("gamma".to_string(), SetOperator::Assign, "0.9".to_string())
-                ],
+            CliAction::parse(vec!["sunsetr", "adjust", "+500"]),
+            CliAction::AdjustCommand {
+                subcommand: AdjustSubcommand::By {
+                    temp: 500,
+                    gamma: 0.0
+                },
+                config_dir: None,
+            }
+        );
+        assert_eq!(
+            CliAction::parse(vec!["sunsetr", "adjust", "-300K", "-10%"]),
+            CliAction::AdjustCommand {
+                subcommand: AdjustSubcommand::By {
+                    temp: -300,
+                    gamma: -10.0
+                },
+                config_dir: None,
+            }
+        );
+        assert_eq!(
+            CliAction::parse(vec!["sunsetr", "adjust", "reset"]),
+            CliAction::AdjustCommand {
+                subcommand: AdjustSubcommand::Reset,
                 config_dir: None,
-                target: Some("gaming".to_string()),
             }
         );
+
+        for args in [
+            vec!["sunsetr", "adjust"],
+            vec!["sunsetr", "adjust", "500"],
+            vec!["sunsetr", "adjust", "+warm"],
+            vec!["sunsetr", "adjust", "-10%%"],
+            vec!["sunsetr", "adjust", "reset", "+500"],
+        ] {
+            assert!(matches!(
+                CliAction::parse(args),
+                CliAction::ShowCommandUsageDueToError { .. }
+            ));
+        }
     }
 
     #[test]
-    fn test_set_command_increment_operator() {
-        let args = vec!["sunsetr", "set", "night_temp+=500"];
-        let parsed = CliAction::parse(args);
+    fn test_compute_at_json() {
+        let parsed = CliAction::parse(vec![
+            "sunsetr",
+            "compute",
+            "--at",
+            "2025-06-21T23:00",
+            "--json",
+        ]);
         assert_eq!(
             parsed,
-            CliAction::SetCommand {
-                fields: vec![(
-                    "night_temp".to_string(),
-                    SetOperator::Increment,
-                    "500".to_string()
-                )],
+            CliAction::ComputeCommand {
+                at: Some("2025-06-21T23:00".to_string()),
+                json: true,
                 config_dir: None,
-                target: None,
             }
         );
+
+        let missing_time = CliAction::parse(vec!["sunsetr", "compute", "--at", "--json"]);
+        assert!(matches!(
+            missing_time,
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
     }
 
     #[test]
-    fn test_set_command_decrement_operator() {
-        let args = vec!["sunsetr", "set", "static_gamma-=2"];
-        let parsed = CliAction::parse(args);
+    fn test_curve_date_svg() {
         assert_eq!(
-            parsed,
-            CliAction::SetCommand {
-                fields: vec![(
-                    "static_gamma".to_string(),
-                    SetOperator::Decrement,
-                    "2".to_string()
-                )],
+            CliAction::parse(vec!["sunsetr", "curve", "--date", "2025-12-21", "--svg"]),
+            CliAction::CurveCommand {
+                date: Some("2025-12-21".to_string()),
+                svg: true,
                 config_dir: None,
-                target: None,
             }
         );
+        assert!(matches!(
+            CliAction::parse(vec!["sunsetr", "curve", "--date"]),
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
     }
 
     #[test]
-    fn test_set_command_mixed_operators() {
-        let args = vec![
-            "sunsetr",
-            "set",
-            "night_temp+=200",
-            "day_temp=6500",
-            "static_gamma-=5",
-        ];
-        let parsed = CliAction::parse(args);
+    fn test_week() {
         assert_eq!(
-            parsed,
-            CliAction::SetCommand {
-                fields: vec![
-                    (
-                        "night_temp".to_string(),
-                        SetOperator::Increment,
-                        "200".to_string()
-                    ),
-                    (
-                        "day_temp".to_string(),
-                        SetOperator::Assign,
-                        "6500".to_string()
-                    ),
-                    (
-                        "static_gamma".to_string(),
-                        SetOperator::Decrement,
-                        "5".to_string()
-                    ),
-                ],
+            CliAction::parse(vec!["sunsetr", "week", "--start", "2025-10-23", "--ascii"]),
+            CliAction::WeekCommand {
+                start: Some("2025-10-23".to_string()),
+                ascii: true,
                 config_dir: None,
-                target: None,
             }
         );
+        assert!(matches!(
+            CliAction::parse(vec!["sunsetr", "week", "--start"]),
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
     }
 
     #[test]
-    fn test_set_command_increment_with_decimal() {
-        let args = vec!["sunsetr", "set", "night_gamma+=5.5"];
-        let parsed = CliAction::parse(args);
+    fn test_render_preview() {
         assert_eq!(
-            parsed,
-            CliAction::SetCommand {
-                fields: vec![(
-                    "night_gamma".to_string(),
-                    SetOperator::Increment,
-                    "5.5".to_string()
-                )],
+            CliAction::parse(vec![
+                "sunsetr",
+                "render-preview",
+                "--temp",
+                "3300",
+                "--out",
+                "preview.png"
+            ]),
+            CliAction::RenderPreviewCommand {
+                temp: Some(3300),
+                gamma: None,
+                output: "preview.png".to_string(),
                 config_dir: None,
-                target: None,
             }
         );
+        assert!(matches!(
+            CliAction::parse(vec!["sunsetr", "render-preview", "--temp", "3300"]),
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
+        assert!(matches!(
+            CliAction::parse(vec![
+                "sunsetr",
+                "render-preview",
+                "--gamma",
+                "warm",
+                "-o",
+                "a.png"
+            ]),
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
     }
 
     #[test]
-    fn test_set_command_increment_with_target() {
-        let args = vec!["sunsetr", "set", "--target", "gaming", "night_temp+=500"];
-        let parsed = CliAction::parse(args);
+    fn test_config_subcommands() {
         assert_eq!(
-            parsed,
-            CliAction::SetCommand {
-                fields: vec![(
-                    "night_temp".to_string(),
-                    SetOperator::Increment,
-                    "500".to_string()
-                )],
+            CliAction::parse(vec!["sunsetr", "config", "schema"]),
+            CliAction::ConfigCommand {
+                subcommand: ConfigSubcommand::Schema,
                 config_dir: None,
-                target: Some("gaming".to_string()),
             }
         );
+        assert_eq!(
+            CliAction::parse(vec!["sunsetr", "--config", "/tmp/cfg", "config", "check"]),
+            CliAction::ConfigCommand {
+                subcommand: ConfigSubcommand::Check,
+                config_dir: Some("/tmp/cfg".to_string()),
+            }
+        );
+        for args in [vec!["sunsetr", "config"], vec!["sunsetr", "config", "dump"]] {
+            assert!(matches!(
+                CliAction::parse(args),
+                CliAction::ShowCommandUsageDueToError { .. }
+            ));
+        }
     }
 
     #[test]
-    fn test_parse_background_flag() {
-        let args = vec!["sunsetr", "--background"];
-        let parsed = CliAction::parse(args);
+    fn test_preset_push_and_pop() {
+        let parsed = CliAction::parse(vec!["sunsetr", "preset", "push", "movie"]);
         assert_eq!(
             parsed,
-            CliAction::Run {
+            CliAction::PresetCommand {
                 debug_enabled: false,
+                subcommand: PresetSubcommand::Push {
+                    name: "movie".to_string(),
+                    expires_in: None,
+                },
                 config_dir: None,
-                background: true,
             }
         );
+
+        let parsed = CliAction::parse(vec!["sunsetr", "p", "pop"]);
+        assert!(matches!(
+            parsed,
+            CliAction::PresetCommand {
+                subcommand: PresetSubcommand::Pop,
+                ..
+            }
+        ));
+
+        let parsed = CliAction::parse(vec!["sunsetr", "preset", "push"]);
+        assert!(matches!(
+            parsed,
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
     }
 
     #[test]
-    fn test_parse_background_short_flag() {
-        let args = vec!["sunsetr", "-b"];
-        let parsed = CliAction::parse(args);
+    fn test_pause() {
+        use crate::core::pause::PauseEvent;
+
         assert_eq!(
-            parsed,
-            CliAction::Run {
-                debug_enabled: false,
-                config_dir: None,
-                background: true,
+            CliAction::parse(vec!["sunsetr", "pause"]),
+            CliAction::PauseCommand {
+                expires_in: None,
+                until: None
+            }
+        );
+        assert_eq!(
+            CliAction::parse(vec!["sunsetr", "pause", "--for", "45m"]),
+            CliAction::PauseCommand {
+                expires_in: Some(std::time::Duration::from_secs(45 * 60)),
+                until: None
+            }
+        );
+        assert_eq!(
+            CliAction::parse(vec!["sunsetr", "pause", "--until", "sunrise"]),
+            CliAction::PauseCommand {
+                expires_in: None,
+                until: Some(PauseEvent::Sunrise)
             }
         );
+        for args in [
+            vec!["sunsetr", "pause", "--for"],
+            vec!["sunsetr", "pause", "--until", "noon"],
+            vec!["sunsetr", "pause", "--for", "1h", "--until", "tomorrow"],
+            vec!["sunsetr", "pause", "later"],
+        ] {
+            assert!(
+                matches!(
+                    CliAction::parse(args.clone()),
+                    CliAction::ShowCommandUsageDueToError { .. }
+                ),
+                "{args:?} was accepted"
+            );
+        }
     }
 
     #[test]
-    fn test_background_with_debug() {
-        let args = vec!["sunsetr", "--background", "--debug"];
-        let parsed = CliAction::parse(args);
+    fn test_toggle() {
+        assert_eq!(
+            CliAction::parse(vec!["sunsetr", "toggle"]),
+            CliAction::ToggleCommand
+        );
+        assert_eq!(
+            CliAction::parse(vec!["sunsetr", "toggle", "stop"]),
+            CliAction::ShowHelpDueToError
+        );
+    }
+
+    #[test]
+    fn test_preset_rename_and_remove() {
+        let parsed = CliAction::parse(vec!["sunsetr", "preset", "rename", "night", "late"]);
         assert_eq!(
             parsed,
-            CliAction::Run {
-                debug_enabled: true,
+            CliAction::PresetCommand {
+                debug_enabled: false,
+                subcommand: PresetSubcommand::Rename {
+                    from: "night".to_string(),
+                    to: "late".to_string(),
+                },
                 config_dir: None,
-                background: true,
             }
         );
+        assert!(matches!(
+            CliAction::parse(vec!["sunsetr", "p", "remove", "movie"]),
+            CliAction::PresetCommand {
+                subcommand: PresetSubcommand::Remove { .. },
+                ..
+            }
+        ));
+
+        for args in [
+            vec!["sunsetr", "preset", "rename", "night"],
+            vec!["sunsetr", "preset", "remove"],
+            vec!["sunsetr", "preset", "remove", "movie", "--for", "1h"],
+        ] {
+            assert!(matches!(
+                CliAction::parse(args),
+                CliAction::ShowCommandUsageDueToError { .. }
+            ));
+        }
     }
 
     #[test]
-    fn test_background_restart() {
-        let args = vec!["sunsetr", "--background", "restart"];
-        let parsed = CliAction::parse(args);
+    fn test_preset_for() {
+        let parsed = CliAction::parse(vec!["sunsetr", "preset", "night-shift", "--for", "3h"]);
         assert_eq!(
             parsed,
-            CliAction::RestartCommand {
+            CliAction::PresetCommand {
                 debug_enabled: false,
-                instant: false,
+                subcommand: PresetSubcommand::Apply {
+                    name: "night-shift".to_string(),
+                    expires_in: Some(std::time::Duration::from_secs(3 * 3600)),
+                },
                 config_dir: None,
-                background: true,
             }
         );
+        assert!(matches!(
+            CliAction::parse(vec!["sunsetr", "p", "push", "movie", "--for", "90m"]),
+            CliAction::PresetCommand {
+                subcommand: PresetSubcommand::Push {
+                    expires_in: Some(_),
+                    ..
+                },
+                ..
+            }
+        ));
+
+        for args in [
+            vec!["sunsetr", "preset", "movie", "--for"],
+            vec!["sunsetr", "preset", "movie", "--for", "soon"],
+            vec!["sunsetr", "preset", "pop", "--for", "1h"],
+        ] {
+            assert!(matches!(
+                CliAction::parse(args),
+                CliAction::ShowCommandUsageDueToError { .. }
+            ));
+        }
     }
 
     #[test]
-    fn test_background_restart_instant() {
-        let args = vec!["sunsetr", "-b", "restart", "--instant"];
-        let parsed = CliAction::parse(args);
+    fn test_set_create_requires_target() {
+        let args = vec![
+            "sunsetr",
+            "set",
+            "--create",
+            "--target",
+            "movie",
+            "night_temp=2800",
+        ];
         assert_eq!(
-            parsed,
-            CliAction::RestartCommand {
-                debug_enabled: false,
-                instant: true,
+            CliAction::parse(args),
+            CliAction::SetCommand {
+                fields: vec![(
+                    "night_temp".to_string(),
+                    SetOperator::Assign,
+                    "2800".to_string()
+                )],
                 config_dir: None,
-                background: true,
+                target: Some("movie".to_string()),
+                create: true,
+                runtime: false,
+                clear: false,
             }
         );
+
+        let args = vec!["sunsetr", "set", "--create", "night_temp=2800"];
+        assert!(matches!(
+            CliAction::parse(args),
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
     }
 
     #[test]
@@ -1429,6 +3065,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_history_json() {
+        let parsed = CliAction::parse(vec!["sunsetr", "history", "-j"]);
+        assert_eq!(parsed, CliAction::HistoryCommand { json: true });
+
+        let parsed = CliAction::parse(vec!["sunsetr", "history", "now"]);
+        assert!(matches!(
+            parsed,
+            CliAction::ShowCommandUsageDueToError { .. }
+        ));
+    }
+
+    #[test]
+    fn test_grab_keeps_the_command_flags() {
+        let parsed = CliAction::parse(vec!["sunsetr", "grab", "--", "grim", "-h", "-"]);
+        assert_eq!(
+            parsed,
+            CliAction::GrabCommand {
+                command: vec!["grim".to_string(), "-h".to_string(), "-".to_string()]
+            }
+        );
+
+        let parsed = CliAction::parse(vec!["sunsetr", "grab", "grim", "--debug"]);
+        assert_eq!(
+            parsed,
+            CliAction::GrabCommand {
+                command: vec!["grim".to_string(), "--debug".to_string()]
+            }
+        );
+
+        for args in [vec!["sunsetr", "grab"], vec!["sunsetr", "grab", "--"]] {
+            assert!(matches!(
+                CliAction::parse(args),
+                CliAction::ShowCommandUsageDueToError { .. }
+            ));
+        }
+    }
+
     #[test]
     fn test_status_json_and_follow() {
         let args = vec!["sunsetr", "status", "--json", "--follow"];
@@ -0,0 +1,485 @@
+//! DRM/KMS backend for sessions without a display server.
+//!
+//! Drives the gamma LUT of each CRTC on `/dev/dri/card*` directly, so sunsetr can tint a
+//! bare TTY, a kiosk or a greeter. Connected connectors are enumerated the way the Wayland
+//! backend enumerates outputs and named the same way (`HDMI-A-1`, `eDP-1`), so presentation
+//! mode takes the same names. Ramps are set with the legacy gamma ioctl, which the kernel
+//! stores as the CRTC's `GAMMA_LUT` property on atomic drivers.
+//!
+//! Setting gamma requires DRM master. sunsetr takes it only for the duration of each apply
+//! and drops it again, so a compositor started later can still take the card; while one
+//! holds it, applies fail and sunsetr waits for the next change. Hotplug comes from the
+//! kernel's uevents, the same messages udev reads, and any DRM event re-enumerates the
+//! connectors and reapplies, since the console resets a CRTC's LUT when it sets a mode.
+//!
+//! The kernel keeps the last LUT after sunsetr exits, so Core's exit write of the
+//! `shutdown_target` values is what stays on screen. Smooth transitions, HDR handling and
+//! `neutral_apps` are Wayland-only.
+
+use anyhow::Result;
+use drm::control::{Device as ControlDevice, connector, crtc};
+use std::collections::BTreeSet;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use nix::errno::Errno;
+use nix::sys::socket::{
+    AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol, SockType, bind, recv, socket,
+};
+
+use crate::backend::gamma::{self, ChannelGamma};
+use crate::backend::hdr::Adjustment;
+use crate::backend::{Capabilities, ColorTemperatureBackend, OutputEvent};
+use crate::common::constants::DEFAULT_BRIGHTNESS;
+use crate::common::error::Silent;
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::Config;
+use crate::state::presentation::Presentation;
+
+const DRI_DIR: &str = "/dev/dri";
+
+/// Multicast group of the uevents the kernel itself sends, before udev processes them.
+const KERNEL_UEVENT_GROUP: u32 = 1;
+
+/// An open `/dev/dri/card*` node.
+struct Card {
+    file: File,
+    path: PathBuf,
+}
+
+impl AsFd for Card {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl drm::Device for Card {}
+impl ControlDevice for Card {}
+
+impl Card {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Run `f` as DRM master, then drop master so another program can take the card.
+    fn as_master<T>(&self, f: impl FnOnce(&Self) -> io::Result<T>) -> io::Result<T> {
+        // Fails when already master, as right after opening a card nobody else held
+        let _ = drm::Device::acquire_master_lock(self);
+        let result = f(self);
+        let _ = drm::Device::release_master_lock(self);
+        result
+    }
+
+    /// Connected connectors that a CRTC is currently driving.
+    fn outputs(&self, card: usize) -> io::Result<Vec<Output>> {
+        let resources = self.resource_handles()?;
+        let mut outputs = Vec::new();
+        for &handle in resources.connectors() {
+            // Without a forced probe this reads the kernel's cached state
+            let info = self.get_connector(handle, false)?;
+            if info.state() != connector::State::Connected {
+                continue;
+            }
+            let Some(crtc) = info
+                .current_encoder()
+                .and_then(|encoder| self.get_encoder(encoder).ok())
+                .and_then(|encoder| encoder.crtc())
+            else {
+                continue;
+            };
+            let gamma_size = self.get_crtc(crtc)?.gamma_length() as usize;
+            if gamma_size < 2 {
+                continue;
+            }
+            outputs.push(Output {
+                card,
+                crtc,
+                name: connector_name(info.interface(), info.interface_id()),
+                gamma_size,
+            });
+        }
+        Ok(outputs)
+    }
+}
+
+/// A connected connector and the CRTC driving it.
+#[derive(Debug, Clone)]
+struct Output {
+    /// Index into [`DrmBackend::cards`].
+    card: usize,
+    crtc: crtc::Handle,
+    name: String,
+    gamma_size: usize,
+}
+
+/// DRM gamma backend for sessions without a compositor.
+pub struct DrmBackend {
+    cards: Vec<Card>,
+    outputs: Vec<Output>,
+    // Kernel uevents, None when the socket couldn't be opened (hotplug is then missed)
+    uevents: Option<OwnedFd>,
+    debug_enabled: bool,
+    // Stored so re-enumerated outputs can be re-applied without recomputing from state
+    current_temperature: u32,
+    current_gamma_percent: f64,
+    current_brightness: f64,
+    channel_gamma: ChannelGamma,
+    gamma_tables: gamma::TableCache,
+    presentation: Option<Presentation>,
+    output_events: Vec<OutputEvent>,
+}
+
+impl DrmBackend {
+    /// LUTs are set per CRTC and on CRTCs lit up later.
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        smoothing: false,
+        per_output: true,
+        hardware: true,
+        hotplug: true,
+        keeps_gamma_on_exit: true,
+    };
+
+    /// Open every card, check that gamma can be set, and subscribe to hotplug uevents.
+    pub fn new(config: &Config, debug_enabled: bool) -> Result<Self> {
+        log_decorated!("Initializing DRM gamma control backend...");
+
+        let cards = open_cards()?;
+        if cards.is_empty() {
+            log_pipe!();
+            log_error!("No DRM devices could be opened in {DRI_DIR}");
+            log_indented!("Your user needs access to /dev/dri/card*, usually through the");
+            log_indented!("video group or an active logind session on the seat.");
+            log_end!();
+            return Err(Silent.into());
+        }
+
+        let outputs = enumerate(&cards);
+        if outputs.is_empty() {
+            log_pipe!();
+            log_error!("No connected outputs found for gamma control");
+            log_end!();
+            return Err(Silent.into());
+        }
+
+        // Writing the current ramps back shows whether master can be had, without a flash
+        let probe = &outputs[0];
+        let card = &cards[probe.card];
+        let writable = card.as_master(|card| {
+            let size = probe.gamma_size;
+            let (mut red, mut green, mut blue) = (vec![0; size], vec![0; size], vec![0; size]);
+            card.get_gamma(probe.crtc, &mut red, &mut green, &mut blue)?;
+            card.set_gamma(probe.crtc, &red, &green, &blue)
+        });
+        if let Err(e) = writable {
+            log_pipe!();
+            log_error!(
+                "Could not set gamma on {} through {}: {e}",
+                probe.name,
+                card.path.display()
+            );
+            log_indented!("Another program holds DRM master, most likely a running compositor.");
+            log_indented!("Inside a Wayland or X11 session, use backend=\"auto\" instead.");
+            log_end!();
+            return Err(Silent.into());
+        }
+
+        let uevents = match uevent_socket() {
+            Ok(fd) => Some(fd),
+            Err(e) => {
+                log_pipe!();
+                log_warning!("Failed to listen for DRM hotplug events: {e}");
+                log_indented!("Outputs connected later will not be tinted until a restart");
+                None
+            }
+        };
+
+        if debug_enabled {
+            let names: Vec<_> = outputs.iter().map(|output| output.name.as_str()).collect();
+            log_pipe!();
+            log_debug!(
+                "Initialized gamma control for {} output(s): {}",
+                outputs.len(),
+                names.join(", ")
+            );
+        }
+
+        Ok(Self {
+            cards,
+            outputs,
+            uevents,
+            debug_enabled,
+            current_temperature: 6500,
+            current_gamma_percent: 100.0,
+            current_brightness: DEFAULT_BRIGHTNESS,
+            channel_gamma: config.channel_gamma(),
+            gamma_tables: gamma::TableCache::default(),
+            presentation: config.presentation.clone(),
+            output_events: Vec::new(),
+        })
+    }
+
+    /// Set ramps on every output for the stored values.
+    fn apply_ramps(&mut self) -> Result<()> {
+        let scheduled = (self.current_temperature, self.current_gamma_percent);
+        if self.debug_enabled {
+            log_pipe!();
+            log_debug!(
+                "Applying gamma to {} output(s): temp={}K, gamma={:.0}%, brightness={:.0}%",
+                self.outputs.len(),
+                scheduled.0,
+                scheduled.1,
+                self.current_brightness
+            );
+        }
+
+        let keys: Vec<(usize, Adjustment)> = self
+            .outputs
+            .iter()
+            .map(|output| {
+                let adjustment =
+                    Adjustment::for_presentation(self.presentation.as_ref(), &output.name);
+                (output.gamma_size, adjustment)
+            })
+            .collect();
+        let ramps = gamma::ramps_for(
+            keys.iter().copied(),
+            scheduled,
+            self.current_brightness,
+            self.channel_gamma,
+            &mut self.gamma_tables,
+            self.debug_enabled,
+        )?;
+
+        // One master section per card, so a compositor waiting for the card isn't kept out
+        // between outputs
+        let mut failed = Vec::new();
+        for (index, card) in self.cards.iter().enumerate() {
+            let outputs: Vec<_> = self
+                .outputs
+                .iter()
+                .zip(&keys)
+                .filter(|(output, _)| output.card == index)
+                .collect();
+            if outputs.is_empty() {
+                continue;
+            }
+            let result = card.as_master(|card| {
+                for (output, key) in &outputs {
+                    let (red, green, blue) = &ramps[key];
+                    card.set_gamma(output.crtc, red, green, blue)?;
+                }
+                Ok(())
+            });
+            if let Err(e) = result {
+                failed.push(format!("{} ({e})", card.path.display()));
+            }
+        }
+
+        if !failed.is_empty() {
+            anyhow::bail!("Failed to set gamma on {}", failed.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Drain pending uevents, reporting whether any came from the DRM subsystem.
+    fn drm_changed(&self) -> bool {
+        let Some(fd) = &self.uevents else {
+            return false;
+        };
+        let mut changed = false;
+        let mut buffer = [0u8; 8192];
+        loop {
+            match recv(fd.as_raw_fd(), &mut buffer, MsgFlags::empty()) {
+                Ok(len) => changed |= is_drm_uevent(&buffer[..len]),
+                Err(Errno::EINTR) => continue,
+                // EAGAIN once drained; other errors leave hotplug for the next poll
+                Err(_) => return changed,
+            }
+        }
+    }
+
+    /// Re-read the connected outputs after a DRM uevent, record outputs that came or
+    /// went, and reapply to all of them.
+    fn refresh_outputs(&mut self) -> Result<()> {
+        let outputs = enumerate(&self.cards);
+        let before: BTreeSet<String> = self.outputs.iter().map(|o| o.name.clone()).collect();
+        let after: BTreeSet<String> = outputs.iter().map(|o| o.name.clone()).collect();
+        self.output_events
+            .extend(OutputEvent::between(&before, &after));
+
+        if self.debug_enabled {
+            log_pipe!();
+            log_debug!(
+                "DRM reported a change, reapplying to: {}",
+                after.iter().cloned().collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        self.outputs = outputs;
+        self.apply_ramps()
+    }
+}
+
+/// The kernel's name for a connector, as compositors show it.
+fn connector_name(interface: connector::Interface, id: u32) -> String {
+    format!("{}-{id}", interface.as_str())
+}
+
+/// Open every `/dev/dri/card*` node that can be opened, in name order.
+fn open_cards() -> Result<Vec<Card>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(DRI_DIR)
+        .map_err(|e| anyhow::anyhow!("Failed to read {DRI_DIR}: {e}"))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("card"))
+        })
+        .collect();
+    paths.sort();
+    Ok(paths
+        .iter()
+        .filter_map(|path| Card::open(path).ok())
+        .collect())
+}
+
+/// Connected outputs across all cards. A card that fails to answer contributes none.
+fn enumerate(cards: &[Card]) -> Vec<Output> {
+    cards
+        .iter()
+        .enumerate()
+        .flat_map(|(index, card)| card.outputs(index).unwrap_or_default())
+        .collect()
+}
+
+/// A non-blocking socket receiving the kernel's uevents.
+fn uevent_socket() -> nix::Result<OwnedFd> {
+    let fd = socket(
+        AddressFamily::Netlink,
+        SockType::Datagram,
+        SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC,
+        SockProtocol::NetlinkKObjectUEvent,
+    )?;
+    bind(fd.as_raw_fd(), &NetlinkAddr::new(0, KERNEL_UEVENT_GROUP))?;
+    Ok(fd)
+}
+
+/// Whether a kernel uevent, `ACTION@DEVPATH` followed by NUL-separated `KEY=value`
+/// pairs, comes from the DRM subsystem.
+fn is_drm_uevent(message: &[u8]) -> bool {
+    message
+        .split(|byte| *byte == 0)
+        .any(|entry| entry == b"SUBSYSTEM=drm")
+}
+
+impl ColorTemperatureBackend for DrmBackend {
+    fn poll_hotplug(&mut self) -> Result<()> {
+        if self.drm_changed() {
+            self.refresh_outputs()?;
+        }
+        Ok(())
+    }
+
+    fn apply_transition_state(
+        &mut self,
+        runtime_state: &crate::core::runtime_state::RuntimeState,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        let (temp, gamma) = runtime_state.values();
+        self.current_temperature = temp.get();
+        self.current_gamma_percent = gamma.get();
+        self.current_brightness = runtime_state.brightness();
+        self.apply_ramps()
+    }
+
+    fn apply_startup_state(
+        &mut self,
+        runtime_state: &crate::core::runtime_state::RuntimeState,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        crate::core::period::log_state_announcement(runtime_state.period());
+        self.apply_transition_state(runtime_state, running)
+    }
+
+    fn apply_temperature_gamma(
+        &mut self,
+        temperature: Kelvin,
+        gamma: GammaPercent,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        self.current_temperature = temperature.get();
+        self.current_gamma_percent = gamma.get();
+        self.apply_ramps()
+    }
+
+    fn take_output_events(&mut self) -> Vec<OutputEvent> {
+        std::mem::take(&mut self.output_events)
+    }
+
+    fn reload_config(&mut self, config: &Config) -> Result<()> {
+        let channel_gamma = config.channel_gamma();
+        if channel_gamma == self.channel_gamma && config.presentation == self.presentation {
+            return Ok(());
+        }
+        self.channel_gamma = channel_gamma;
+        self.presentation = config.presentation.clone();
+        self.apply_ramps()
+    }
+
+    fn output_names(&self) -> Option<BTreeSet<String>> {
+        Some(
+            self.outputs
+                .iter()
+                .map(|output| output.name.clone())
+                .collect(),
+        )
+    }
+
+    /// Re-read the connectors and their gamma sizes as a DRM uevent would.
+    fn refresh(&mut self) -> Result<()> {
+        self.refresh_outputs()
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "DRM"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Self::CAPABILITIES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_drm_uevents_trigger_a_refresh() {
+        let hotplug = b"change@/devices/pci0000:00/0000:00:02.0/drm/card1\0ACTION=change\0\
+            DEVPATH=/devices/pci0000:00/0000:00:02.0/drm/card1\0SUBSYSTEM=drm\0HOTPLUG=1\0";
+        assert!(is_drm_uevent(hotplug));
+
+        let usb = b"add@/devices/usb1/1-1\0ACTION=add\0SUBSYSTEM=usb\0";
+        assert!(!is_drm_uevent(usb));
+
+        // The subsystem has to match the whole key, not a prefix of another subsystem
+        let drm_dp_aux = b"add@/devices/x\0ACTION=add\0SUBSYSTEM=drm_dp_aux_dev\0";
+        assert!(!is_drm_uevent(drm_dp_aux));
+    }
+
+    #[test]
+    fn connectors_are_named_like_compositor_outputs() {
+        assert_eq!(connector_name(connector::Interface::HDMIA, 1), "HDMI-A-1");
+        assert_eq!(
+            connector_name(connector::Interface::EmbeddedDisplayPort, 1),
+            "eDP-1"
+        );
+        assert_eq!(connector_name(connector::Interface::DisplayPort, 2), "DP-2");
+    }
+}
@@ -0,0 +1,81 @@
+//! Switching to another backend when the one in use keeps failing.
+//!
+//! A backend whose applies fail [`FAILOVER_AFTER`] times in a row is replaced at runtime
+//! instead of stopping sunsetr. The replacement is picked from [`CHAIN`]: the backends after
+//! the failed one, tried in order until one can be created. Backends outside the chain
+//! have nothing to fall back to, and neither does a backend the config names explicitly.
+
+use anyhow::{Result, anyhow};
+
+use super::BackendType;
+
+/// Consecutive failed applies after which the backend is replaced.
+pub const FAILOVER_AFTER: usize = 3;
+
+/// Backends that can stand in for each other, in the order they are tried.
+const CHAIN: &[BackendType] = &[
+    BackendType::Hyprland,
+    BackendType::Wayland,
+    BackendType::Hyprsunset,
+];
+
+/// The backends that may take over from `failed`, in the order they are tried.
+pub fn alternatives(failed: BackendType) -> &'static [BackendType] {
+    CHAIN
+        .iter()
+        .position(|&backend_type| backend_type == failed)
+        .map_or(&[], |index| &CHAIN[index + 1..])
+}
+
+/// Create the first alternative to `current` that works and make it the current one.
+pub fn fail_over<B>(
+    current: &mut BackendType,
+    mut create: impl FnMut(BackendType) -> Result<B>,
+) -> Result<B> {
+    for &candidate in alternatives(*current) {
+        match create(candidate) {
+            Ok(backend) => {
+                *current = candidate;
+                return Ok(backend);
+            }
+            Err(e) => {
+                log_indented!("The {} backend is unavailable: {e}", candidate.name());
+            }
+        }
+    }
+    Err(anyhow!(
+        "no other backend could take over from the {} backend",
+        current.name()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failover_tries_the_rest_of_the_chain_in_order() {
+        assert_eq!(
+            alternatives(BackendType::Hyprland),
+            &[BackendType::Wayland, BackendType::Hyprsunset]
+        );
+        assert!(alternatives(BackendType::Hyprsunset).is_empty());
+        assert!(alternatives(BackendType::X11).is_empty());
+
+        let mut current = BackendType::Hyprland;
+        let mut tried = Vec::new();
+        let created = fail_over(&mut current, |candidate| {
+            tried.push(candidate);
+            match candidate {
+                BackendType::Wayland => Err(anyhow!("no wlr-gamma-control")),
+                _ => Ok(candidate),
+            }
+        });
+        assert_eq!(created.unwrap(), BackendType::Hyprsunset);
+        assert_eq!(current, BackendType::Hyprsunset);
+        assert_eq!(tried, vec![BackendType::Wayland, BackendType::Hyprsunset]);
+
+        assert!(fail_over(&mut current, Ok).is_err());
+        assert_eq!(current, BackendType::Hyprsunset);
+    }
+}
@@ -0,0 +1,98 @@
+//! Focused-window tracking for per-application profiles.
+//!
+//! Hyprland, sway and niri report window focus over their IPC event streams. The
+//! compositor event threads publish the focused window's app id and output here, and the
+//! backends leave that output neutral while the app is listed in `neutral_apps`, so color
+//! work in e.g. GIMP or darktable isn't done through the tint.
+
+use std::sync::Mutex;
+
+/// The focused window as reported by the compositor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Focus {
+    /// Wayland app id, or the X11 window class for Xwayland windows.
+    pub app_id: Option<String>,
+    /// Connector name of the output the window is on, when the compositor says.
+    pub output: Option<String>,
+}
+
+/// Outputs held at neutral values because of the focused window.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum NeutralOutputs {
+    #[default]
+    None,
+    Output(String),
+    /// The focused app matched but its output is unknown.
+    All,
+}
+
+impl NeutralOutputs {
+    /// Which outputs `focus` neutralizes, matching app ids case-insensitively.
+    pub fn for_focus(focus: &Focus, apps: &[String]) -> Self {
+        let matched = focus
+            .app_id
+            .as_deref()
+            .is_some_and(|app_id| apps.iter().any(|app| app.eq_ignore_ascii_case(app_id)));
+        match (matched, &focus.output) {
+            (false, _) => NeutralOutputs::None,
+            (true, Some(output)) => NeutralOutputs::Output(output.clone()),
+            (true, None) => NeutralOutputs::All,
+        }
+    }
+
+    pub fn contains(&self, output: &str) -> bool {
+        match self {
+            NeutralOutputs::None => false,
+            NeutralOutputs::Output(name) => name == output,
+            NeutralOutputs::All => true,
+        }
+    }
+}
+
+/// The latest focus published by an event thread and not yet taken by the backend.
+#[derive(Debug, Default)]
+pub struct FocusSlot {
+    latest: Mutex<Option<Focus>>,
+}
+
+impl FocusSlot {
+    pub fn publish(&self, focus: Focus) {
+        *self.latest.lock().unwrap() = Some(focus);
+    }
+
+    pub fn take(&self) -> Option<Focus> {
+        self.latest.lock().unwrap().take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listed_apps_neutralize_their_output() {
+        let apps = vec!["gimp".to_string(), "darktable".to_string()];
+        let focus = |app_id: &str, output: Option<&str>| Focus {
+            app_id: Some(app_id.to_string()),
+            output: output.map(str::to_string),
+        };
+
+        let neutral = NeutralOutputs::for_focus(&focus("GIMP", Some("DP-1")), &apps);
+        assert_eq!(neutral, NeutralOutputs::Output("DP-1".to_string()));
+        assert!(neutral.contains("DP-1"));
+        assert!(!neutral.contains("HDMI-A-1"));
+
+        assert_eq!(
+            NeutralOutputs::for_focus(&focus("darktable", None), &apps),
+            NeutralOutputs::All
+        );
+        assert_eq!(
+            NeutralOutputs::for_focus(&focus("firefox", Some("DP-1")), &apps),
+            NeutralOutputs::None
+        );
+        assert_eq!(
+            NeutralOutputs::for_focus(&Focus::default(), &apps),
+            NeutralOutputs::None
+        );
+    }
+}
@@ -1,6 +1,18 @@
 //! Color temperature to RGB conversion (Tanner Helland approximation) for gamma tables.
 
 use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use crate::backend::hdr::Adjustment;
+
+/// Tables a [`TableCache`] keeps, enough for a transition's recent steps on outputs of a
+/// few different sizes and adjustments.
+const TABLE_CACHE_CAPACITY: usize = 32;
+
+/// Steps per unit that gamma exponents and brightness are rounded to in cache keys, far
+/// finer than a 16-bit table can show.
+const KEY_STEPS: f64 = 100_000.0;
 
 /// Calculate RGB using Tanner Helland's algorithm.
 ///
@@ -54,19 +66,65 @@ pub fn get_rgb_factors(temperature: u32) -> (f64, f64, f64) {
     )
 }
 
+/// Per-channel gamma exponents from the manual curve override, applied on top of the
+/// temperature and overall gamma in the same way as `xgamma -rgamma/-ggamma/-bgamma`,
+/// and the `red_trim`, `green_trim` and `blue_trim` multipliers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelGamma {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    /// Multipliers on the temperature's red, green and blue factors.
+    pub trim: (f64, f64, f64),
+}
+
+impl ChannelGamma {
+    pub const IDENTITY: Self = Self {
+        red: 1.0,
+        green: 1.0,
+        blue: 1.0,
+        trim: (1.0, 1.0, 1.0),
+    };
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// Whether the exponents are all 1.0, whatever the trims.
+    pub fn has_identity_curve(&self) -> bool {
+        Self {
+            trim: Self::IDENTITY.trim,
+            ..*self
+        }
+        .is_identity()
+    }
+}
+
+/// The temperature's RGB factors with `trim` applied to each channel.
+pub fn trimmed_rgb(temp: u32, trim: (f64, f64, f64)) -> (f64, f64, f64) {
+    let (r, g, b) = temperature_to_rgb(temp);
+    (r * trim.0, g * trim.1, b * trim.2)
+}
+
 /// Generate a gamma lookup table for one color channel.
 ///
-/// Applies `output = (input * color_factor)^(1/gamma)`, where `input` is normalized
-/// 0.0-1.0, `color_factor` (0.0-1.0) adjusts for color temperature, and `gamma`
-/// (typically 0.9-1.0) controls the brightness curve. Output is scaled to 0-65535 for
-/// the 16-bit protocol.
-pub fn generate_gamma_table(size: usize, color_factor: f64, gamma: f64) -> Vec<u16> {
+/// Applies `output = (input * color_factor)^(1/gamma) * brightness`, where `input` is
+/// normalized 0.0-1.0, `color_factor` (0.0-1.0) adjusts for color temperature, `gamma`
+/// (typically 0.9-1.0) controls the brightness curve, and `brightness` (0.0-1.0) scales
+/// the whole curve down. Output is scaled to 0-65535 for the 16-bit protocol.
+pub fn generate_gamma_table(
+    size: usize,
+    color_factor: f64,
+    gamma: f64,
+    brightness: f64,
+) -> Vec<u16> {
     let mut table = Vec::with_capacity(size);
 
     for i in 0..size {
         let val = i as f64 / (size - 1) as f64;
 
-        let output = ((val * color_factor).powf(1.0 / gamma) * 65535.0).clamp(0.0, 65535.0);
+        let output =
+            ((val * color_factor).powf(1.0 / gamma) * brightness * 65535.0).clamp(0.0, 65535.0);
 
         // Convert to u16 only at the final step (kept f64 to minimize rounding error)
         table.push(output as u16);
@@ -79,20 +137,34 @@ pub fn generate_gamma_table(size: usize, color_factor: f64, gamma: f64) -> Vec<u
 ///
 /// Uses f64 precision internally to minimize quantization artifacts in the final u16
 /// output. Returns the R, G, B tables concatenated as little-endian u16 bytes.
+///
+/// The channel exponents compose with `gamma_percent` by multiplication, since raising the
+/// curve to `1/gamma` and then to `1/channel` is one power of `1/(gamma * channel)`. The
+/// trims scale the color factors before either. `brightness` scales all three channels
+/// alike afterwards.
 pub fn create_gamma_tables(
     size: usize,
     temperature: u32,
     gamma_percent: f64,
+    brightness: f64,
+    channels: ChannelGamma,
     debug_enabled: bool,
 ) -> Result<Vec<u8>> {
-    let (red_factor, green_factor, blue_factor) = temperature_to_rgb(temperature);
+    let (red_factor, green_factor, blue_factor) = trimmed_rgb(temperature, channels.trim);
 
-    let red_table = generate_gamma_table(size, red_factor, gamma_percent);
-    let green_table = generate_gamma_table(size, green_factor, gamma_percent);
-    let blue_table = generate_gamma_table(size, blue_factor, gamma_percent);
+    let table = |factor: f64, channel: f64| {
+        generate_gamma_table(size, factor, gamma_percent * channel, brightness)
+    };
+    let red_table = table(red_factor, channels.red);
+    let green_table = table(green_factor, channels.green);
+    let blue_table = table(blue_factor, channels.blue);
 
     if debug_enabled {
-        let sample_indices = [0, 10, 128, 255];
+        // X servers can report ramps shorter than the usual 256 entries
+        let sample_indices: Vec<usize> = [0, 10, 128, 255]
+            .into_iter()
+            .filter(|&idx| idx < size)
+            .collect();
         let r_samples: Vec<u16> = sample_indices.iter().map(|&idx| red_table[idx]).collect();
         let g_samples: Vec<u16> = sample_indices.iter().map(|&idx| green_table[idx]).collect();
         let b_samples: Vec<u16> = sample_indices.iter().map(|&idx| blue_table[idx]).collect();
@@ -121,6 +193,142 @@ pub fn create_gamma_tables(
     Ok(gamma_data)
 }
 
+/// What a table from [`create_gamma_tables`] depends on, with the exponents, trims and
+/// brightness quantized so float noise from interpolation doesn't keep equal tables apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TableKey {
+    size: usize,
+    temperature: u32,
+    exponents: [i64; 3],
+    trims: [i64; 3],
+    brightness: i64,
+}
+
+impl TableKey {
+    fn new(
+        size: usize,
+        temperature: u32,
+        gamma_percent: f64,
+        brightness: f64,
+        channels: ChannelGamma,
+    ) -> Self {
+        let quantize = |value: f64| (value * KEY_STEPS).round() as i64;
+        Self {
+            size,
+            temperature,
+            exponents: [
+                quantize(gamma_percent * channels.red),
+                quantize(gamma_percent * channels.green),
+                quantize(gamma_percent * channels.blue),
+            ],
+            trims: [
+                quantize(channels.trim.0),
+                quantize(channels.trim.1),
+                quantize(channels.trim.2),
+            ],
+            brightness: quantize(brightness),
+        }
+    }
+}
+
+/// Recently created gamma tables, so values applied again, such as a transition step sent
+/// to several outputs or a reapply after hotplug, reuse the table instead of recomputing
+/// it. The least recently used table is dropped first.
+#[derive(Debug, Default)]
+pub struct TableCache {
+    // Most recently used last
+    tables: VecDeque<(TableKey, Arc<[u8]>)>,
+}
+
+impl TableCache {
+    /// The tables [`create_gamma_tables`] would create for these values.
+    pub fn get(
+        &mut self,
+        size: usize,
+        temperature: u32,
+        gamma_percent: f64,
+        brightness: f64,
+        channels: ChannelGamma,
+        debug_enabled: bool,
+    ) -> Result<Arc<[u8]>> {
+        let key = TableKey::new(size, temperature, gamma_percent, brightness, channels);
+        if let Some(index) = self.tables.iter().position(|(cached, _)| *cached == key) {
+            let entry = self.tables.remove(index).expect("index is in bounds");
+            let table = Arc::clone(&entry.1);
+            self.tables.push_back(entry);
+            return Ok(table);
+        }
+
+        let table: Arc<[u8]> = create_gamma_tables(
+            size,
+            temperature,
+            gamma_percent,
+            brightness,
+            channels,
+            debug_enabled,
+        )?
+        .into();
+        if self.tables.len() == TABLE_CACHE_CAPACITY {
+            self.tables.pop_front();
+        }
+        self.tables.push_back((key, Arc::clone(&table)));
+        Ok(table)
+    }
+}
+
+/// Red, green and blue ramps for one CRTC.
+pub type Ramps = (Vec<u16>, Vec<u16>, Vec<u16>);
+
+/// Split tables from [`create_gamma_tables`] into the separate red, green and blue ramps
+/// that XRandR and DRM take.
+pub fn split_tables(gamma_data: &[u8], size: usize) -> Ramps {
+    let values: Vec<u16> = gamma_data
+        .chunks_exact(2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect();
+    (
+        values[..size].to_vec(),
+        values[size..2 * size].to_vec(),
+        values[2 * size..3 * size].to_vec(),
+    )
+}
+
+/// Ramps for each distinct pair of ramp size and adjustment among `outputs`, so outputs
+/// sharing both share one set. An output with [`Adjustment::Full`] gets the `scheduled`
+/// (Kelvin, gamma percent) and `brightness` (percent) with `channels` on top, the others
+/// what their adjustment leaves of them. Tables come from `cache` when it has them.
+pub fn ramps_for(
+    outputs: impl IntoIterator<Item = (usize, Adjustment)>,
+    scheduled: (u32, f64),
+    brightness: f64,
+    channels: ChannelGamma,
+    cache: &mut TableCache,
+    debug_enabled: bool,
+) -> Result<HashMap<(usize, Adjustment), Ramps>> {
+    let mut ramps = HashMap::new();
+    for key in outputs {
+        if ramps.contains_key(&key) {
+            continue;
+        }
+        let (size, adjustment) = key;
+        let channels = match adjustment {
+            Adjustment::Full => channels,
+            _ => ChannelGamma::IDENTITY,
+        };
+        let (temperature, gamma_percent) = adjustment.values(scheduled);
+        let table = cache.get(
+            size,
+            temperature,
+            gamma_percent / 100.0,
+            adjustment.brightness(brightness) / 100.0,
+            channels,
+            debug_enabled && ramps.is_empty(), // Debug output only once
+        )?;
+        ramps.insert(key, split_tables(&table, size));
+    }
+    Ok(ramps)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -162,7 +370,7 @@ mod tests {
 
     #[test]
     fn test_gamma_table_generation() {
-        let table = generate_gamma_table(256, 1.0, 1.0);
+        let table = generate_gamma_table(256, 1.0, 1.0, 1.0);
         assert_eq!(table.len(), 256);
         assert_eq!(table[0], 0);
         assert_eq!(table[255], 65535);
@@ -174,19 +382,194 @@ mod tests {
 
     #[test]
     fn test_gamma_table_with_color_factor() {
-        let full_table = generate_gamma_table(256, 1.0, 1.0);
-        let half_table = generate_gamma_table(256, 0.5, 1.0);
+        let full_table = generate_gamma_table(256, 1.0, 1.0, 1.0);
+        let half_table = generate_gamma_table(256, 0.5, 1.0, 1.0);
 
         assert!(half_table[255] < full_table[255]);
         assert!(half_table[255] < 40000); // roughly half of 65535
     }
 
+    #[test]
+    fn tables_split_into_separate_ramps() {
+        let size = 256;
+        let gamma_data =
+            create_gamma_tables(size, 3000, 1.0, 1.0, ChannelGamma::IDENTITY, false).unwrap();
+
+        let (red, green, blue) = split_tables(&gamma_data, size);
+        let (r, g, b) = temperature_to_rgb(3000);
+        assert_eq!(red, generate_gamma_table(size, r, 1.0, 1.0));
+        assert_eq!(green, generate_gamma_table(size, g, 1.0, 1.0));
+        assert_eq!(blue, generate_gamma_table(size, b, 1.0, 1.0));
+    }
+
+    #[test]
+    fn ramps_are_built_once_per_size_and_adjustment() {
+        let channels = ChannelGamma {
+            blue: 1.25,
+            ..ChannelGamma::IDENTITY
+        };
+        let mut cache = TableCache::default();
+        let outputs = [
+            (256, Adjustment::Full),
+            (256, Adjustment::Neutral),
+            (256, Adjustment::Full),
+            (1024, Adjustment::Full),
+        ];
+        let ramps = ramps_for(outputs, (3300, 90.0), 100.0, channels, &mut cache, false).unwrap();
+
+        assert_eq!(ramps.len(), 3);
+        let full = create_gamma_tables(256, 3300, 0.9, 1.0, channels, false).unwrap();
+        assert_eq!(ramps[&(256, Adjustment::Full)], split_tables(&full, 256));
+        // Presentation mode leaves out the channel gammas along with the tint
+        let neutral = create_gamma_tables(256, 6500, 1.0, 1.0, ChannelGamma::IDENTITY, false);
+        assert_eq!(
+            ramps[&(256, Adjustment::Neutral)],
+            split_tables(&neutral.unwrap(), 256)
+        );
+        assert_eq!(ramps[&(1024, Adjustment::Full)].0.len(), 1024);
+        assert_eq!(cache.tables.len(), 3);
+    }
+
+    #[test]
+    fn cached_tables_are_reused_until_evicted() {
+        let mut cache = TableCache::default();
+        let first = cache
+            .get(256, 4000, 0.9, 1.0, ChannelGamma::IDENTITY, false)
+            .unwrap();
+        assert_eq!(
+            *first,
+            *create_gamma_tables(256, 4000, 0.9, 1.0, ChannelGamma::IDENTITY, false).unwrap()
+        );
+
+        // Interpolation noise still finds the same table, another size doesn't
+        let again = cache
+            .get(256, 4000, 0.9 + 1e-12, 1.0, ChannelGamma::IDENTITY, false)
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        let larger = cache
+            .get(1024, 4000, 0.9, 1.0, ChannelGamma::IDENTITY, false)
+            .unwrap();
+        assert_eq!(larger.len(), 1024 * 6);
+
+        // Used again, so the first table outlives the others
+        for temperature in 5000..5000 + TABLE_CACHE_CAPACITY as u32 - 1 {
+            cache
+                .get(256, temperature, 0.9, 1.0, ChannelGamma::IDENTITY, false)
+                .unwrap();
+            cache
+                .get(256, 4000, 0.9, 1.0, ChannelGamma::IDENTITY, false)
+                .unwrap();
+        }
+        let kept = cache
+            .get(256, 4000, 0.9, 1.0, ChannelGamma::IDENTITY, false)
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &kept));
+        assert_eq!(cache.tables.len(), TABLE_CACHE_CAPACITY);
+        assert!(
+            !cache
+                .tables
+                .iter()
+                .any(|(key, _)| key.size == 1024 && key.temperature == 4000)
+        );
+    }
+
+    #[test]
+    fn brightness_scales_every_channel() {
+        let full = generate_gamma_table(256, 0.8, 0.9, 1.0);
+        let dimmed = generate_gamma_table(256, 0.8, 0.9, 0.5);
+        assert_eq!(dimmed[0], 0);
+        for (full, dimmed) in full.iter().zip(&dimmed) {
+            assert!((*full as f64 * 0.5 - *dimmed as f64).abs() <= 1.0);
+        }
+
+        let mut cache = TableCache::default();
+        let bright = cache
+            .get(256, 4000, 0.9, 1.0, ChannelGamma::IDENTITY, false)
+            .unwrap();
+        let dim = cache
+            .get(256, 4000, 0.9, 0.6, ChannelGamma::IDENTITY, false)
+            .unwrap();
+        assert!(!Arc::ptr_eq(&bright, &dim));
+    }
+
     #[test]
     fn test_create_gamma_tables() {
-        let tables = create_gamma_tables(256, 6500, 1.0, false).unwrap();
+        let tables =
+            create_gamma_tables(256, 6500, 1.0, 1.0, ChannelGamma::IDENTITY, false).unwrap();
         assert_eq!(tables.len(), 256 * 3 * 2);
     }
 
+    #[test]
+    fn channel_gamma_composes_with_overall_gamma() {
+        let size = 256;
+        let channel = |tables: &[u8], index: usize| -> Vec<u16> {
+            tables[index * size * 2..(index + 1) * size * 2]
+                .chunks(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect()
+        };
+        let channels = ChannelGamma {
+            red: 0.8,
+            green: 1.0,
+            blue: 1.25,
+            ..ChannelGamma::IDENTITY
+        };
+        let tables = create_gamma_tables(size, 6500, 0.9, 1.0, channels, false).unwrap();
+        let (red_factor, green_factor, blue_factor) = temperature_to_rgb(6500);
+
+        assert_eq!(
+            channel(&tables, 0),
+            generate_gamma_table(size, red_factor, 0.72, 1.0)
+        );
+        assert_eq!(
+            channel(&tables, 1),
+            generate_gamma_table(size, green_factor, 0.9, 1.0)
+        );
+        assert_eq!(
+            channel(&tables, 2),
+            generate_gamma_table(size, blue_factor, 1.125, 1.0)
+        );
+
+        // A larger exponent brightens the midtones of its channel
+        let plain =
+            create_gamma_tables(size, 6500, 0.9, 1.0, ChannelGamma::IDENTITY, false).unwrap();
+        assert!(channel(&tables, 2)[128] > channel(&plain, 2)[128]);
+        assert!(channel(&tables, 0)[128] < channel(&plain, 0)[128]);
+    }
+
+    #[test]
+    fn trims_scale_the_temperature_factors() {
+        let size = 256;
+        let channels = ChannelGamma {
+            trim: (1.0, 0.9, 0.5),
+            ..ChannelGamma::IDENTITY
+        };
+        let tables = split_tables(
+            &create_gamma_tables(size, 4000, 1.0, 1.0, channels, false).unwrap(),
+            size,
+        );
+        let (red_factor, green_factor, blue_factor) = temperature_to_rgb(4000);
+
+        assert_eq!(tables.0, generate_gamma_table(size, red_factor, 1.0, 1.0));
+        assert_eq!(
+            tables.1,
+            generate_gamma_table(size, green_factor * 0.9, 1.0, 1.0)
+        );
+        assert_eq!(
+            tables.2,
+            generate_gamma_table(size, blue_factor * 0.5, 1.0, 1.0)
+        );
+        assert!(channels.has_identity_curve() && !channels.is_identity());
+
+        // Trimmed tables are kept apart from untrimmed ones
+        let mut cache = TableCache::default();
+        let plain = cache
+            .get(size, 4000, 1.0, 1.0, ChannelGamma::IDENTITY, false)
+            .unwrap();
+        let trimmed = cache.get(size, 4000, 1.0, 1.0, channels, false).unwrap();
+        assert_ne!(plain, trimmed);
+    }
+
     #[test]
     fn test_precision_warm_temperatures() {
         let (r1, g1, b1) = temperature_to_rgb(2000);
@@ -0,0 +1,371 @@
+//! GNOME backend setting CRTC gamma through Mutter's `DisplayConfig` D-Bus interface.
+//!
+//! Mutter implements no gamma protocol for Wayland clients, but it takes ramps over the
+//! session bus from `SetCrtcGamma`, the call GNOME's own Night Light makes. Each active
+//! CRTC gets ramps from the shared gamma module and is named after the connector it
+//! drives (`eDP-1`, `HDMI-1`), so presentation mode takes the same names as elsewhere.
+//! Mutter resets the ramps when the monitor configuration changes and announces it with
+//! `MonitorsChanged`, after which the CRTCs are re-read and the ramps reapplied.
+//!
+//! GNOME's Night Light writes the same ramps, so the two would overwrite each other. Its
+//! state is read from the settings daemon's `Color` interface at startup, with a warning
+//! when it is on. Smooth transitions, HDR handling and `neutral_apps` are Wayland-only.
+
+use anyhow::{Context, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use zbus::blocking::Connection;
+use zbus::zvariant::OwnedValue;
+
+use crate::backend::gamma::{self, ChannelGamma};
+use crate::backend::hdr::Adjustment;
+use crate::backend::{Capabilities, ColorTemperatureBackend, ConnectionLost, OutputEvent};
+use crate::common::constants::DEFAULT_BRIGHTNESS;
+use crate::common::error::Silent;
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::Config;
+use crate::state::presentation::Presentation;
+
+type Properties = HashMap<String, OwnedValue>;
+/// `(id, winsys_id, x, y, width, height, current_mode, current_transform, transforms,
+/// properties)`, with a `current_mode` of -1 for a disabled CRTC.
+type ResourceCrtc = (u32, i64, i32, i32, i32, i32, i32, u32, Vec<u32>, Properties);
+/// `(id, winsys_id, current_crtc, possible_crtcs, name, modes, clones, properties)`.
+type ResourceOutput = (
+    u32,
+    i64,
+    i32,
+    Vec<u32>,
+    String,
+    Vec<u32>,
+    Vec<u32>,
+    Properties,
+);
+/// `(id, winsys_id, width, height, frequency, flags)`.
+type ResourceMode = (u32, i64, u32, u32, f64, u32);
+type Resources = (
+    u32,
+    Vec<ResourceCrtc>,
+    Vec<ResourceOutput>,
+    Vec<ResourceMode>,
+    i32,
+    i32,
+);
+
+#[zbus::proxy(
+    interface = "org.gnome.Mutter.DisplayConfig",
+    default_service = "org.gnome.Mutter.DisplayConfig",
+    default_path = "/org/gnome/Mutter/DisplayConfig"
+)]
+trait DisplayConfig {
+    /// The CRTCs, outputs and modes, with the serial later calls must quote.
+    fn get_resources(&self) -> zbus::Result<Resources>;
+
+    fn get_crtc_gamma(
+        &self,
+        serial: u32,
+        crtc: u32,
+    ) -> zbus::Result<(Vec<u16>, Vec<u16>, Vec<u16>)>;
+
+    fn set_crtc_gamma(
+        &self,
+        serial: u32,
+        crtc: u32,
+        red: &[u16],
+        green: &[u16],
+        blue: &[u16],
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn monitors_changed(&self) -> zbus::Result<()>;
+}
+
+#[zbus::proxy(
+    interface = "org.gnome.SettingsDaemon.Color",
+    default_service = "org.gnome.SettingsDaemon.Color",
+    default_path = "/org/gnome/SettingsDaemon/Color"
+)]
+trait SettingsDaemonColor {
+    /// Whether GNOME's Night Light is tinting the screen right now.
+    #[zbus(property)]
+    fn night_light_active(&self) -> zbus::Result<bool>;
+}
+
+/// An active CRTC and the connector it is known by.
+#[derive(Debug, Clone)]
+struct Crtc {
+    id: u32,
+    name: String,
+    gamma_size: usize,
+}
+
+/// Mutter gamma backend for GNOME sessions.
+pub struct GnomeBackend {
+    proxy: DisplayConfigProxyBlocking<'static>,
+    serial: u32,
+    crtcs: Vec<Crtc>,
+    monitors_changed: Arc<AtomicBool>,
+    debug_enabled: bool,
+    // Stored so re-read CRTCs can be re-applied without recomputing from state
+    current_temperature: u32,
+    current_gamma_percent: f64,
+    current_brightness: f64,
+    channel_gamma: ChannelGamma,
+    gamma_tables: gamma::TableCache,
+    presentation: Option<Presentation>,
+    output_events: Vec<OutputEvent>,
+}
+
+impl GnomeBackend {
+    /// Mutter takes ramps per CRTC and reports monitor changes.
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        smoothing: false,
+        per_output: true,
+        hardware: true,
+        hotplug: true,
+        keeps_gamma_on_exit: true,
+    };
+
+    /// Connect to Mutter over the session bus and find the CRTCs to drive.
+    pub fn new(config: &Config, debug_enabled: bool) -> Result<Self> {
+        log_decorated!("Initializing GNOME gamma control backend...");
+
+        let connection = Connection::session().context("Failed to connect to session D-Bus")?;
+        let proxy = DisplayConfigProxyBlocking::new(&connection)
+            .context("Failed to create Mutter DisplayConfig proxy")?;
+
+        let (serial, crtcs) = match active_crtcs(&proxy) {
+            Ok(resources) => resources,
+            Err(e) => {
+                log_pipe!();
+                log_error!("Mutter's DisplayConfig interface is not reachable: {e:#}");
+                log_indented!("Are you running a GNOME session?");
+                log_end!();
+                return Err(Silent.into());
+            }
+        };
+        if crtcs.is_empty() {
+            log_pipe!();
+            log_error!("No active CRTCs found for gamma control");
+            log_end!();
+            return Err(Silent.into());
+        }
+
+        if debug_enabled {
+            let names: Vec<_> = crtcs.iter().map(|crtc| crtc.name.as_str()).collect();
+            log_debug!(
+                "Initialized gamma control for {} output(s): {}",
+                crtcs.len(),
+                names.join(", ")
+            );
+        }
+
+        warn_if_night_light_active(&connection);
+
+        Ok(Self {
+            monitors_changed: watch_monitors(&proxy)?,
+            proxy,
+            serial,
+            crtcs,
+            debug_enabled,
+            current_temperature: 6500,
+            current_gamma_percent: 100.0,
+            current_brightness: DEFAULT_BRIGHTNESS,
+            channel_gamma: config.channel_gamma(),
+            gamma_tables: gamma::TableCache::default(),
+            presentation: config.presentation.clone(),
+            output_events: Vec::new(),
+        })
+    }
+
+    /// Set ramps on every CRTC for the stored values.
+    fn apply_ramps(&mut self) -> Result<()> {
+        let scheduled = (self.current_temperature, self.current_gamma_percent);
+        if self.debug_enabled {
+            log_pipe!();
+            log_debug!(
+                "Applying gamma to {} output(s): temp={}K, gamma={:.0}%, brightness={:.0}%",
+                self.crtcs.len(),
+                scheduled.0,
+                scheduled.1,
+                self.current_brightness
+            );
+        }
+
+        let keys: Vec<(usize, Adjustment)> = self
+            .crtcs
+            .iter()
+            .map(|crtc| {
+                let adjustment =
+                    Adjustment::for_presentation(self.presentation.as_ref(), &crtc.name);
+                (crtc.gamma_size, adjustment)
+            })
+            .collect();
+        let ramps = gamma::ramps_for(
+            keys.iter().copied(),
+            scheduled,
+            self.current_brightness,
+            self.channel_gamma,
+            &mut self.gamma_tables,
+            self.debug_enabled,
+        )?;
+
+        for (crtc, key) in self.crtcs.iter().zip(&keys) {
+            let (red, green, blue) = &ramps[key];
+            self.proxy
+                .set_crtc_gamma(self.serial, crtc.id, red, green, blue)
+                .with_context(|| format!("Mutter rejected the gamma ramps for {}", crtc.name))?;
+        }
+        Ok(())
+    }
+
+    /// Re-read the CRTCs after Mutter reported a change, record outputs that came or went,
+    /// and reapply to all of them.
+    fn refresh_crtcs(&mut self) -> Result<()> {
+        let Ok((serial, crtcs)) = active_crtcs(&self.proxy) else {
+            return Err(ConnectionLost.into());
+        };
+
+        let before: BTreeSet<String> = self.crtcs.iter().map(|c| c.name.clone()).collect();
+        let after: BTreeSet<String> = crtcs.iter().map(|c| c.name.clone()).collect();
+        self.output_events
+            .extend(OutputEvent::between(&before, &after));
+
+        if self.debug_enabled {
+            log_pipe!();
+            log_debug!(
+                "Mutter reported a monitor change, reapplying to: {}",
+                after.iter().cloned().collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        self.serial = serial;
+        self.crtcs = crtcs;
+        self.apply_ramps()
+    }
+}
+
+/// The resources' serial and the CRTCs driving an output, in Mutter's order.
+fn active_crtcs(proxy: &DisplayConfigProxyBlocking<'_>) -> Result<(u32, Vec<Crtc>)> {
+    let (serial, resource_crtcs, outputs, _modes, _, _) = proxy.get_resources()?;
+
+    let mut crtcs = Vec::new();
+    for (id, _, _, _, _, _, current_mode, ..) in resource_crtcs {
+        // A disabled CRTC has no mode and drives nothing
+        if current_mode < 0 {
+            continue;
+        }
+        let Some(name) = outputs
+            .iter()
+            .find(|output| u32::try_from(output.2) == Ok(id))
+            .map(|output| output.4.clone())
+        else {
+            continue;
+        };
+
+        let gamma_size = proxy.get_crtc_gamma(serial, id)?.0.len();
+        if gamma_size < 2 {
+            continue;
+        }
+        crtcs.push(Crtc {
+            id,
+            name,
+            gamma_size,
+        });
+    }
+    Ok((serial, crtcs))
+}
+
+/// Follow `MonitorsChanged` on a background thread, returning the flag it raises. The
+/// thread ends at the first signal after the backend is gone.
+fn watch_monitors(proxy: &DisplayConfigProxyBlocking<'static>) -> Result<Arc<AtomicBool>> {
+    let changes = proxy
+        .receive_monitors_changed()
+        .context("Failed to subscribe to Mutter's monitor changes")?;
+    let changed = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&changed);
+    thread::spawn(move || {
+        for _ in changes {
+            if Arc::strong_count(&flag) == 1 {
+                break;
+            }
+            flag.store(true, Ordering::Relaxed);
+        }
+    });
+    Ok(changed)
+}
+
+fn warn_if_night_light_active(connection: &Connection) {
+    let active = SettingsDaemonColorProxyBlocking::new(connection)
+        .and_then(|proxy| proxy.night_light_active());
+    if let Ok(true) = active {
+        log_pipe!();
+        log_warning!("GNOME's Night Light is on and will overwrite sunsetr's gamma.");
+        log_indented!("Turn it off in Settings → Displays → Night Light.");
+    }
+}
+
+impl ColorTemperatureBackend for GnomeBackend {
+    fn poll_hotplug(&mut self) -> Result<()> {
+        if self.monitors_changed.swap(false, Ordering::Relaxed) {
+            self.refresh_crtcs()?;
+        }
+        Ok(())
+    }
+
+    fn apply_transition_state(
+        &mut self,
+        runtime_state: &crate::core::runtime_state::RuntimeState,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        let (temp, gamma) = runtime_state.values();
+        self.current_temperature = temp.get();
+        self.current_gamma_percent = gamma.get();
+        self.current_brightness = runtime_state.brightness();
+        self.apply_ramps()
+    }
+
+    fn apply_startup_state(
+        &mut self,
+        runtime_state: &crate::core::runtime_state::RuntimeState,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        crate::core::period::log_state_announcement(runtime_state.period());
+        self.apply_transition_state(runtime_state, running)
+    }
+
+    fn apply_temperature_gamma(
+        &mut self,
+        temperature: Kelvin,
+        gamma: GammaPercent,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        self.current_temperature = temperature.get();
+        self.current_gamma_percent = gamma.get();
+        self.apply_ramps()
+    }
+
+    fn take_output_events(&mut self) -> Vec<OutputEvent> {
+        std::mem::take(&mut self.output_events)
+    }
+
+    fn reload_config(&mut self, config: &Config) -> Result<()> {
+        let channel_gamma = config.channel_gamma();
+        if channel_gamma == self.channel_gamma && config.presentation == self.presentation {
+            return Ok(());
+        }
+        self.channel_gamma = channel_gamma;
+        self.presentation = config.presentation.clone();
+        self.apply_ramps()
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "GNOME"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Self::CAPABILITIES
+    }
+}
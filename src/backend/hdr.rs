@@ -0,0 +1,141 @@
+//! Per-output handling of HDR outputs.
+//!
+//! Gamma ramps assume an SDR signal. On an output the compositor drives in HDR, a ramp is
+//! applied before the compositor's own tone mapping and can crush or tint the image in ways
+//! the scheduled values don't describe. Hyprland and sway report which outputs are in HDR
+//! over their IPC, and each output's `hdr` setting then decides what it gets instead:
+//! nothing, a weaker tint, or the tint only where it goes through a color matrix.
+
+use std::collections::BTreeSet;
+
+use crate::common::constants::DEFAULT_BRIGHTNESS;
+use crate::common::utils::{interpolate_f64, interpolate_inverse_u32};
+use crate::config::HdrBehavior;
+use crate::state::presentation::Presentation;
+
+/// Values (Kelvin, gamma percent) that leave an output untouched.
+pub const NEUTRAL_VALUES: (u32, f64) = (6500, 100.0);
+
+/// How far [`Adjustment::Reduced`] moves from neutral toward the scheduled values.
+const REDUCED_STRENGTH: f32 = 0.5;
+
+/// How much of the scheduled tint an output receives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Adjustment {
+    Full,
+    Reduced,
+    Neutral,
+}
+
+impl Adjustment {
+    /// The adjustment for an HDR output with `behavior`, on a backend that applies a color
+    /// matrix when `ctm` is set and gamma ramps otherwise.
+    pub fn for_hdr(behavior: HdrBehavior, ctm: bool) -> Self {
+        match behavior {
+            HdrBehavior::Skip => Adjustment::Neutral,
+            HdrBehavior::Reduced => Adjustment::Reduced,
+            HdrBehavior::CtmOnly if ctm => Adjustment::Full,
+            HdrBehavior::CtmOnly => Adjustment::Neutral,
+        }
+    }
+
+    /// Neutral for an output `presentation` mode covers, full otherwise.
+    pub fn for_presentation(presentation: Option<&Presentation>, output: &str) -> Self {
+        if presentation.is_some_and(|presentation| presentation.covers(output)) {
+            Adjustment::Neutral
+        } else {
+            Adjustment::Full
+        }
+    }
+
+    /// The values to apply in place of the `scheduled` (Kelvin, gamma percent).
+    pub fn values(self, scheduled: (u32, f64)) -> (u32, f64) {
+        match self {
+            Adjustment::Full => scheduled,
+            Adjustment::Reduced => (
+                interpolate_inverse_u32(NEUTRAL_VALUES.0, scheduled.0, REDUCED_STRENGTH),
+                interpolate_f64(NEUTRAL_VALUES.1, scheduled.1, REDUCED_STRENGTH),
+            ),
+            Adjustment::Neutral => NEUTRAL_VALUES,
+        }
+    }
+
+    /// The brightness (percent) to apply in place of the `scheduled` one.
+    pub fn brightness(self, scheduled: f64) -> f64 {
+        match self {
+            Adjustment::Full => scheduled,
+            Adjustment::Reduced => interpolate_f64(DEFAULT_BRIGHTNESS, scheduled, REDUCED_STRENGTH),
+            Adjustment::Neutral => DEFAULT_BRIGHTNESS,
+        }
+    }
+}
+
+/// Report the outputs in HDR after the set changed.
+pub fn log_change(outputs: &BTreeSet<String>) {
+    log_pipe!();
+    if outputs.is_empty() {
+        log_info!("No outputs in HDR");
+    } else {
+        let names: Vec<&str> = outputs.iter().map(String::as_str).collect();
+        log_info!("HDR detected on {}", names.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn behaviors_map_to_adjustments_per_backend() {
+        for ctm in [false, true] {
+            assert_eq!(
+                Adjustment::for_hdr(HdrBehavior::Skip, ctm),
+                Adjustment::Neutral
+            );
+            assert_eq!(
+                Adjustment::for_hdr(HdrBehavior::Reduced, ctm),
+                Adjustment::Reduced
+            );
+        }
+        assert_eq!(
+            Adjustment::for_hdr(HdrBehavior::CtmOnly, true),
+            Adjustment::Full
+        );
+        assert_eq!(
+            Adjustment::for_hdr(HdrBehavior::CtmOnly, false),
+            Adjustment::Neutral
+        );
+    }
+
+    #[test]
+    fn reduced_values_sit_between_neutral_and_scheduled() {
+        assert_eq!(Adjustment::Full.values((3300, 90.0)), (3300, 90.0));
+        assert_eq!(Adjustment::Neutral.values((3300, 90.0)), NEUTRAL_VALUES);
+
+        let (temp, gamma) = Adjustment::Reduced.values((3300, 90.0));
+        assert!((3300..6500).contains(&temp), "{temp}");
+        assert_eq!(gamma, 95.0);
+
+        // Neutral stays neutral, and a cool tint is reduced toward 6500K as well
+        assert_eq!(Adjustment::Reduced.values(NEUTRAL_VALUES), NEUTRAL_VALUES);
+        let (temp, _) = Adjustment::Reduced.values((10000, 100.0));
+        assert!((6500..10000).contains(&temp), "{temp}");
+    }
+
+    #[test]
+    fn behavior_parses_from_snake_case() {
+        #[derive(serde::Deserialize)]
+        struct Table {
+            hdr: HdrBehavior,
+        }
+        let parse = |value: &str| {
+            toml::from_str::<Table>(&format!("hdr = \"{value}\""))
+                .ok()
+                .map(|table| table.hdr)
+        };
+        assert_eq!(parse("skip"), Some(HdrBehavior::Skip));
+        assert_eq!(parse("reduced"), Some(HdrBehavior::Reduced));
+        assert_eq!(parse("ctm_only"), Some(HdrBehavior::CtmOnly));
+        assert_eq!(parse("off"), None);
+    }
+}
@@ -0,0 +1,308 @@
+//! Hyprland IPC integration for per-application profiles and monitor hotplug.
+//!
+//! Hyprland publishes line-based events (`event>>data`) on `.socket2.sock` in the
+//! instance's runtime directory. `activewindow` carries the focused window's class and
+//! `focusedmon` the focused monitor, which together say which output the focused app is
+//! on. `monitoradded` and `monitorremoved` report hotplug. The current focus at connect
+//! time is read once through the request socket (`.socket.sock`), since the event stream
+//! only reports changes. The same socket answers which monitors use an HDR color
+//! management preset, and whether CTM changes fade in.
+
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use crate::backend::CompositorEvents;
+use crate::backend::focus::{Focus, FocusSlot};
+
+/// Subscription to Hyprland's event socket, read on a background thread.
+pub struct HyprlandEvents {
+    dir: PathBuf,
+    outputs_changed: Arc<AtomicBool>,
+    focus: Arc<FocusSlot>,
+    stream: UnixStream,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HyprlandEvents {
+    /// Subscribe through the running Hyprland instance. Returns `None` outside Hyprland or
+    /// if the event socket can't be reached.
+    pub fn connect(debug_enabled: bool) -> Option<Self> {
+        let dir = instance_dir()?;
+
+        match Self::connect_in(&dir) {
+            Ok(events) => {
+                if debug_enabled {
+                    log_debug!("Subscribed to Hyprland IPC for focus and monitor events");
+                }
+                Some(events)
+            }
+            Err(e) => {
+                if debug_enabled {
+                    log_debug!("Hyprland IPC unavailable, focus is not followed: {e}");
+                }
+                None
+            }
+        }
+    }
+
+    /// Read the current focus and subscribe to events in the instance directory `dir`.
+    fn connect_in(dir: &Path) -> std::io::Result<Self> {
+        let stream = UnixStream::connect(dir.join(".socket2.sock"))?;
+        let focus = Arc::new(FocusSlot::default());
+
+        let mut tracker = FocusTracker::default();
+        if let Ok(initial) = query_focus(&dir.join(".socket.sock")) {
+            tracker.focus = initial.clone();
+            focus.publish(initial);
+        }
+
+        let outputs_changed = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&outputs_changed);
+        let slot = Arc::clone(&focus);
+        let reader = BufReader::new(stream.try_clone()?);
+        let thread = thread::Builder::new()
+            .name("hyprland-events".to_string())
+            .spawn(move || {
+                for line in reader.lines() {
+                    let Ok(line) = line else { break };
+                    if is_monitor_change(&line) {
+                        flag.store(true, Ordering::SeqCst);
+                    }
+                    if let Some(focus) = tracker.handle_line(&line) {
+                        slot.publish(focus);
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            outputs_changed,
+            focus,
+            stream,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl CompositorEvents for HyprlandEvents {
+    fn take_outputs_changed(&self) -> bool {
+        self.outputs_changed.swap(false, Ordering::SeqCst)
+    }
+
+    fn take_focus(&self) -> Option<Focus> {
+        self.focus.take()
+    }
+
+    fn hdr_outputs(&self) -> BTreeSet<String> {
+        request(&self.dir.join(".socket.sock"), "monitors")
+            .map(|monitors| hdr_monitors(&monitors))
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for HyprlandEvents {
+    fn drop(&mut self) {
+        // Unblocks the reader so the thread can exit
+        let _ = self.stream.shutdown(Shutdown::Both);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Runtime directory of the running Hyprland instance, holding its sockets.
+fn instance_dir() -> Option<PathBuf> {
+    let instance = crate::backend::rediscover::hyprland_instance()?;
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+        .unwrap_or_else(|_| format!("/run/user/{}", nix::unistd::getuid()));
+    Some(PathBuf::from(runtime_dir).join("hypr").join(instance))
+}
+
+/// Whether Hyprland fades CTM changes in (`render:ctm_animation`), as it does by default
+/// except on NVIDIA. `false` outside Hyprland and on versions without the option.
+pub fn ctm_fades() -> bool {
+    instance_dir()
+        .and_then(|dir| request(&dir.join(".socket.sock"), "getoption render:ctm_animation").ok())
+        .and_then(|option| option.get("int").and_then(Value::as_i64))
+        .is_some_and(|value| value != 0)
+}
+
+/// Send one JSON request (`j/<command>`) over the request socket at `path`.
+fn request(path: &Path, command: &str) -> std::io::Result<Value> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.write_all(format!("j/{command}").as_bytes())?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    serde_json::from_str(&reply).map_err(std::io::Error::other)
+}
+
+/// The focused window's class and the focused monitor.
+fn query_focus(path: &Path) -> std::io::Result<Focus> {
+    let window = request(path, "activewindow")?;
+    let workspace = request(path, "activeworkspace")?;
+    Ok(Focus {
+        app_id: window
+            .get("class")
+            .and_then(Value::as_str)
+            .filter(|class| !class.is_empty())
+            .map(str::to_string),
+        output: workspace
+            .get("monitor")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    })
+}
+
+/// Monitors whose color management preset is one of the HDR ones (`hdr`, `hdredid`).
+fn hdr_monitors(monitors: &Value) -> BTreeSet<String> {
+    monitors
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|monitor| {
+            monitor
+                .get("colorManagementPreset")
+                .and_then(Value::as_str)
+                .is_some_and(|preset| preset.starts_with("hdr"))
+        })
+        .filter_map(|monitor| monitor.get("name")?.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Whether an event line reports a monitor connected or disconnected. Hyprland sends both
+/// the plain and the `v2` form of each.
+fn is_monitor_change(line: &str) -> bool {
+    let Some((event, _)) = line.split_once(">>") else {
+        return false;
+    };
+    matches!(
+        event,
+        "monitoradded" | "monitoraddedv2" | "monitorremoved" | "monitorremovedv2"
+    )
+}
+
+/// Follows focus from raw event lines.
+#[derive(Default)]
+struct FocusTracker {
+    focus: Focus,
+}
+
+impl FocusTracker {
+    /// Feed one event line, returning the new focus when it changed.
+    fn handle_line(&mut self, line: &str) -> Option<Focus> {
+        let (event, data) = line.split_once(">>")?;
+        // The class comes first and the title, which may contain commas, after it
+        let first = data.split(',').next().unwrap_or_default();
+        let previous = self.focus.clone();
+        match event {
+            "activewindow" => {
+                self.focus.app_id = (!first.is_empty()).then(|| first.to_string());
+            }
+            "focusedmon" => self.focus.output = Some(first.to_string()),
+            _ => return None,
+        }
+        (self.focus != previous).then(|| self.focus.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn focus_follows_window_and_monitor_events() {
+        let mut tracker = FocusTracker::default();
+        let focus = tracker
+            .handle_line("activewindow>>gimp,GNU Image Manipulation Program")
+            .unwrap();
+        assert_eq!(focus.app_id.as_deref(), Some("gimp"));
+        assert_eq!(focus.output, None);
+
+        let focus = tracker.handle_line("focusedmon>>DP-1,2").unwrap();
+        assert_eq!(focus.output.as_deref(), Some("DP-1"));
+
+        assert_eq!(tracker.handle_line("focusedmon>>DP-1,3"), None);
+        assert_eq!(tracker.handle_line("workspace>>3"), None);
+        assert_eq!(tracker.handle_line("not an event"), None);
+
+        let focus = tracker.handle_line("activewindow>>,").unwrap();
+        assert_eq!(focus.app_id, None);
+    }
+
+    #[test]
+    fn monitor_events_are_output_changes() {
+        assert!(is_monitor_change("monitoradded>>HDMI-A-1"));
+        assert!(is_monitor_change("monitorremovedv2>>1,HDMI-A-1,Dell"));
+        assert!(!is_monitor_change("focusedmon>>DP-1,2"));
+        assert!(!is_monitor_change("monitoradded"));
+    }
+
+    #[test]
+    fn hdr_monitors_are_found_by_color_management_preset() {
+        let monitors: Value = serde_json::from_str(
+            r#"[
+                {"name":"DP-1","colorManagementPreset":"hdr"},
+                {"name":"DP-2","colorManagementPreset":"srgb"},
+                {"name":"HDMI-A-1","colorManagementPreset":"hdredid"},
+                {"name":"eDP-1"}
+            ]"#,
+        )
+        .unwrap();
+        let names: Vec<_> = hdr_monitors(&monitors).into_iter().collect();
+        assert_eq!(names, ["DP-1", "HDMI-A-1"]);
+        assert!(hdr_monitors(&Value::Null).is_empty());
+    }
+
+    #[test]
+    fn subscription_reports_the_initial_and_changed_focus() {
+        let dir = tempfile::tempdir().unwrap();
+        let requests = UnixListener::bind(dir.path().join(".socket.sock")).unwrap();
+        let events = UnixListener::bind(dir.path().join(".socket2.sock")).unwrap();
+
+        let request_server = thread::spawn(move || {
+            for reply in [r#"{"class":"darktable"}"#, r#"{"monitor":"DP-2"}"#] {
+                let (mut stream, _) = requests.accept().unwrap();
+                let mut request = [0u8; 64];
+                let _ = stream.read(&mut request).unwrap();
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+        let (initial_taken, wait_for_initial) = std::sync::mpsc::channel();
+        let event_server = thread::spawn(move || {
+            let (mut stream, _) = events.accept().unwrap();
+            request_server.join().unwrap();
+            wait_for_initial.recv().unwrap();
+            writeln!(stream, "activewindow>>firefox,Mozilla Firefox").unwrap();
+            stream
+        });
+
+        let subscription = HyprlandEvents::connect_in(dir.path()).unwrap();
+        let initial = subscription.take_focus().unwrap();
+        assert_eq!(initial.app_id.as_deref(), Some("darktable"));
+        assert_eq!(initial.output.as_deref(), Some("DP-2"));
+        initial_taken.send(()).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let changed = loop {
+            if let Some(focus) = subscription.take_focus() {
+                break focus;
+            }
+            assert!(Instant::now() < deadline, "focus change was not reported");
+            thread::sleep(Duration::from_millis(5));
+        };
+        assert_eq!(changed.app_id.as_deref(), Some("firefox"));
+        assert_eq!(changed.output.as_deref(), Some("DP-2"));
+
+        drop(subscription);
+        drop(event_server.join().unwrap());
+    }
+}
@@ -5,8 +5,22 @@
 //! combined into a single CTM matrix before each commit, which keeps all adjustments to one
 //! smooth animation and avoids double-commit artifacts. Like the Wayland backend, it applies
 //! the CTM to every output and handles outputs being added or removed.
+//!
+//! Each output gets its own matrix when `[outputs.<name>]` tables are configured: offsets
+//! shift that output's temperature and gamma, its matrix, or else the top-level `matrix`, is
+//! composed under the tint, and excluded outputs are left at identity, as are outputs that
+//! `include_outputs` or `exclude_outputs` leave out, however they were plugged in. All
+//! matrices still go out in one commit. Outputs showing a focused `neutral_apps` window are
+//! left at identity too, for as long as Hyprland's IPC reports that window focused, and
+//! outputs in presentation mode for as long as it is on. Outputs in HDR get what their `hdr`
+//! setting asks for, re-read from Hyprland's IPC when outputs change and on reload.
+//!
+//! `night_saturation` is only possible here: the CTM mixes channels, so a saturation matrix
+//! is composed after the tint. It follows the schedule's saturation, so values applied
+//! outside the schedule, like previews and test mode, keep the last scheduled saturation.
 
 use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::atomic::AtomicBool;
 
 use wayland_client::{
@@ -14,12 +28,21 @@ use wayland_client::{
     protocol::{wl_output::WlOutput, wl_registry::WlRegistry},
 };
 
-use crate::backend::ColorTemperatureBackend;
+use crate::backend::focus::{Focus, NeutralOutputs};
+use crate::backend::hdr::{self, Adjustment};
+use crate::backend::{
+    Capabilities, ColorTemperatureBackend, CompositorEvents, ConnectionLost, OutputEvent,
+    connect_compositor_events,
+};
 use crate::common::error::Silent;
-use crate::config::Config;
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::{Config, OutputConfig, OutputFilter};
+use crate::state::presentation::Presentation;
 
 use super::gamma;
 
+pub mod ipc;
+
 pub mod protocol {
     use wayland_client;
     use wayland_client::protocol::*;
@@ -45,7 +68,20 @@ pub struct HyprlandBackend {
     debug_enabled: bool,
     current_temperature: u32,
     current_gamma_percent: f64,
+    current_saturation: f64,
+    current_brightness: f64,
     last_output_count: usize,
+    output_configs: BTreeMap<String, OutputConfig>,
+    // Top-level matrix and trims, for outputs that don't set their own
+    output_defaults: OutputConfig,
+    output_filter: OutputFilter,
+    // Focus events from Hyprland IPC
+    compositor_events: Option<Box<dyn CompositorEvents>>,
+    neutral_apps: Vec<String>,
+    focus: Focus,
+    neutral: NeutralOutputs,
+    hdr_outputs: BTreeSet<String>,
+    presentation: Option<Presentation>,
 }
 
 #[derive(Debug, Clone)]
@@ -53,6 +89,8 @@ struct OutputInfo {
     output: WlOutput,
     name: String,
     registry_name: u32,
+    // Set once the output's first `done` has arrived and it has been reported to IPC
+    announced: bool,
 }
 
 /// State for Wayland event handling
@@ -62,6 +100,9 @@ struct State {
     outputs: Vec<OutputInfo>,
     debug_enabled: bool,
     is_blocked: bool,
+    // Set when an output's real name arrives, since per-output matrices are chosen by name
+    names_changed: bool,
+    output_events: Vec<OutputEvent>,
 }
 
 impl State {
@@ -71,18 +112,43 @@ impl State {
             outputs: Vec::new(),
             debug_enabled,
             is_blocked: false,
+            names_changed: false,
+            output_events: Vec::new(),
         }
     }
 }
 
 impl HyprlandBackend {
+    /// Hyprland animates CTM changes itself, so states are sent whole, one matrix per output.
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        smoothing: false,
+        per_output: true,
+        hardware: false,
+        hotplug: true,
+        keeps_gamma_on_exit: false,
+    };
+
     /// Connect to the compositor, verify CTM protocol support, and enumerate outputs.
-    pub fn new(_config: &Config, debug_enabled: bool) -> Result<Self> {
+    pub fn new(config: &Config, debug_enabled: bool) -> Result<Self> {
         log_decorated!("Initializing native Hyprland CTM backend...");
 
-        let connection = Connection::connect_to_env()
+        let connection = crate::backend::rediscover::connect()
             .map_err(|e| anyhow::anyhow!("Failed to connect to Wayland compositor: {}", e))?;
 
+        let mut backend = Self::from_connection(connection, config, debug_enabled)?;
+        backend.compositor_events = connect_compositor_events(debug_enabled);
+        backend.refresh_hdr_outputs();
+        Ok(backend)
+    }
+
+    /// Set up CTM control over an already established connection.
+    ///
+    /// Split from `new` so tests can hand in a connection to an in-process compositor.
+    fn from_connection(
+        connection: Connection,
+        config: &Config,
+        debug_enabled: bool,
+    ) -> Result<Self> {
         let mut event_queue = connection.new_event_queue();
         let qh = event_queue.handle();
 
@@ -138,6 +204,12 @@ impl HyprlandBackend {
         }
 
         let output_count = state.outputs.len();
+        state.names_changed = false;
+        // Outputs present at startup are not hotplug events
+        for info in &mut state.outputs {
+            info.announced = true;
+        }
+        state.output_events.clear();
         Ok(Self {
             _connection: connection,
             event_queue,
@@ -145,7 +217,18 @@ impl HyprlandBackend {
             debug_enabled,
             current_temperature: 6500,
             current_gamma_percent: 100.0,
+            current_saturation: crate::common::constants::DEFAULT_SATURATION,
+            current_brightness: crate::common::constants::DEFAULT_BRIGHTNESS,
             last_output_count: output_count,
+            output_configs: config.outputs.clone(),
+            output_defaults: config.output_defaults(),
+            output_filter: config.output_filter(),
+            compositor_events: None,
+            neutral_apps: config.neutral_apps.clone(),
+            focus: Focus::default(),
+            neutral: NeutralOutputs::None,
+            hdr_outputs: BTreeSet::new(),
+            presentation: config.presentation.clone(),
         })
     }
 
@@ -157,41 +240,80 @@ impl HyprlandBackend {
                 log_debug!("Total outputs: {}", self.state.outputs.len());
                 log_pipe!();
                 log_debug!("Applying CTM to all outputs");
-            }
-
-            let (r, g, b) = gamma::temperature_to_rgb(self.current_temperature);
-
-            let gamma_ratio = self.current_gamma_percent / 100.0;
-            let r_adjusted = r * gamma_ratio;
-            let g_adjusted = g * gamma_ratio;
-            let b_adjusted = b * gamma_ratio;
-
-            if self.debug_enabled {
+                let (r, g, b) = gamma::temperature_to_rgb(self.current_temperature);
                 log_decorated!("Creating CTM matrix...");
                 log_indented!(
-                    "temp={}K, gamma={:.0}%, RGB factors=({:.3}, {:.3}, {:.3})",
+                    "temp={}K, gamma={:.0}%, saturation={:.0}%, brightness={:.0}%, RGB factors=({:.3}, {:.3}, {:.3})",
                     self.current_temperature,
                     self.current_gamma_percent,
+                    self.current_saturation,
+                    self.current_brightness,
                     r,
                     g,
                     b
                 );
-                log_decorated!("CTM matrix (3x3):");
-                log_indented!("[{:.3}  0.000  0.000]", r_adjusted);
-                log_indented!("[0.000  {:.3}  0.000]", g_adjusted);
-                log_indented!("[0.000  0.000  {:.3}]", b_adjusted);
             }
 
-            // Row-major 3x3 diagonal CTM: RGB scale factors on the diagonal
-            let ctm = [
-                r_adjusted, 0.0, 0.0, 0.0, g_adjusted, 0.0, 0.0, 0.0, b_adjusted,
-            ];
-
-            if self.debug_enabled {
-                log_decorated!("Setting CTM via Hyprland protocol");
-            }
+            let mut applied_names = Vec::new();
 
             for output_info in &self.state.outputs {
+                let output_config = match self.output_configs.get(&output_info.name) {
+                    Some(output_config) => output_config.with_defaults(&self.output_defaults),
+                    None => self.output_defaults.clone(),
+                };
+
+                // Left unset, the output is reset to identity by the commit
+                if self
+                    .presentation
+                    .as_ref()
+                    .is_some_and(|presentation| presentation.covers(&output_info.name))
+                {
+                    if self.debug_enabled {
+                        log_indented!("{}: neutral for presentation mode", output_info.name);
+                    }
+                    continue;
+                }
+                if !self.output_filter.allows(&output_info.name) {
+                    if self.debug_enabled {
+                        log_indented!("{}: excluded", output_info.name);
+                    }
+                    continue;
+                }
+                if self.neutral.contains(&output_info.name) {
+                    if self.debug_enabled {
+                        log_indented!("{}: neutral for the focused app", output_info.name);
+                    }
+                    continue;
+                }
+
+                let adjustment = if self.hdr_outputs.contains(&output_info.name) {
+                    Adjustment::for_hdr(output_config.hdr, true)
+                } else {
+                    Adjustment::Full
+                };
+                if adjustment == Adjustment::Neutral {
+                    if self.debug_enabled {
+                        log_indented!("{}: neutral in HDR", output_info.name);
+                    }
+                    continue;
+                }
+
+                let (temperature, gamma_percent) =
+                    adjustment.values((self.current_temperature, self.current_gamma_percent));
+                let brightness = adjustment.brightness(self.current_brightness) / 100.0;
+                let ctm = saturate(
+                    output_ctm(temperature, gamma_percent, &output_config),
+                    self.current_saturation,
+                )
+                .map(|value| value * brightness);
+
+                if self.debug_enabled {
+                    log_decorated!("CTM matrix for {} (3x3):", output_info.name);
+                    for row in ctm.chunks(3) {
+                        log_indented!("[{:.3}  {:.3}  {:.3}]", row[0], row[1], row[2]);
+                    }
+                }
+
                 manager.set_ctm_for_output(
                     &output_info.output,
                     ctm[0],
@@ -204,24 +326,100 @@ impl HyprlandBackend {
                     ctm[7],
                     ctm[8],
                 );
+                applied_names.push(output_info.name.as_str());
+            }
+
+            if self.debug_enabled {
+                log_decorated!("Setting CTM via Hyprland protocol");
             }
 
             // Commit all changes atomically
             manager.commit();
 
-            self.event_queue.roundtrip(&mut self.state)?;
-
             if self.debug_enabled {
-                let output_names: Vec<&str> =
-                    self.state.outputs.iter().map(|o| o.name.as_str()).collect();
-                log_debug!("Applied CTM to outputs: {}", output_names.join(", "));
+                log_debug!("Applied CTM to outputs: {}", applied_names.join(", "));
             }
+
+            self.event_queue.roundtrip(&mut self.state)?;
         }
 
         Ok(())
     }
 }
 
+impl HyprlandBackend {
+    /// Recompute which outputs the focused app leaves neutral, returning whether that changed.
+    fn update_neutral_outputs(&mut self) -> bool {
+        let neutral = NeutralOutputs::for_focus(&self.focus, &self.neutral_apps);
+        if neutral == self.neutral {
+            return false;
+        }
+        if self.debug_enabled {
+            log_pipe!();
+            log_debug!("Focused app changed neutral outputs: {:?}", neutral);
+        }
+        self.neutral = neutral;
+        true
+    }
+
+    /// Re-read which outputs are in HDR, returning whether that changed.
+    fn refresh_hdr_outputs(&mut self) -> bool {
+        let hdr_outputs = self
+            .compositor_events
+            .as_ref()
+            .map(|events| events.hdr_outputs())
+            .unwrap_or_default();
+        if hdr_outputs == self.hdr_outputs {
+            return false;
+        }
+        hdr::log_change(&hdr_outputs);
+        self.hdr_outputs = hdr_outputs;
+        true
+    }
+}
+
+/// Row-major CTM for one output: the output's own matrix (identity when unset) followed by
+/// the temperature tint, trimmed per channel and scaled by gamma, at the output's offset
+/// temperature and gamma.
+fn output_ctm(temperature: u32, gamma_percent: f64, output_config: &OutputConfig) -> [f64; 9] {
+    let (temperature, gamma_percent) = output_config.adjust(temperature, gamma_percent);
+    let (r, g, b) = gamma::trimmed_rgb(temperature, output_config.trim());
+    let gamma_ratio = gamma_percent / 100.0;
+    let tint = [r * gamma_ratio, g * gamma_ratio, b * gamma_ratio];
+
+    let base = output_config
+        .matrix
+        .map_or([1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0], |matrix| {
+            matrix.values()
+        });
+
+    // diag(tint) * base scales each row of the base matrix by its channel factor
+    let mut ctm = [0.0; 9];
+    for (i, value) in ctm.iter_mut().enumerate() {
+        *value = tint[i / 3] * base[i];
+    }
+    ctm
+}
+
+/// `ctm` applied after a saturation matrix that blends each channel toward the Rec. 709
+/// luminance, so 100% leaves `ctm` as is and 0% turns the picture gray.
+fn saturate(ctm: [f64; 9], saturation_percent: f64) -> [f64; 9] {
+    const LUMA: [f64; 3] = [0.2126, 0.7152, 0.0722];
+    let s = saturation_percent / 100.0;
+    if s == 1.0 {
+        return ctm;
+    }
+    let saturation_matrix: [f64; 9] =
+        std::array::from_fn(|i| (1.0 - s) * LUMA[i % 3] + if i / 3 == i % 3 { s } else { 0.0 });
+
+    std::array::from_fn(|i| {
+        let (row, col) = (i / 3, i % 3);
+        (0..3)
+            .map(|k| ctm[row * 3 + k] * saturation_matrix[k * 3 + col])
+            .sum()
+    })
+}
+
 impl ColorTemperatureBackend for HyprlandBackend {
     fn apply_transition_state(
         &mut self,
@@ -229,15 +427,19 @@ impl ColorTemperatureBackend for HyprlandBackend {
         _running: &AtomicBool,
     ) -> Result<()> {
         let (temp, gamma) = runtime_state.values();
-        self.current_temperature = temp;
-        self.current_gamma_percent = gamma;
+        self.current_temperature = temp.get();
+        self.current_gamma_percent = gamma.get();
+        self.current_saturation = runtime_state.saturation();
+        self.current_brightness = runtime_state.brightness();
 
         if self.debug_enabled {
             log_pipe!();
             log_debug!(
-                "Hyprland backend applying state: temp={}K, gamma={:.1}%",
+                "Hyprland backend applying state: temp={}K, gamma={:.1}%, saturation={:.0}%, brightness={:.0}%",
                 temp,
-                gamma
+                gamma,
+                self.current_saturation,
+                self.current_brightness
             );
         }
 
@@ -261,12 +463,12 @@ impl ColorTemperatureBackend for HyprlandBackend {
 
     fn apply_temperature_gamma(
         &mut self,
-        temperature: u32,
-        gamma: f64,
+        temperature: Kelvin,
+        gamma: GammaPercent,
         _running: &AtomicBool,
     ) -> Result<()> {
-        self.current_temperature = temperature;
-        self.current_gamma_percent = gamma;
+        self.current_temperature = temperature.get();
+        self.current_gamma_percent = gamma.get();
         self.apply_combined_ctm()
     }
 
@@ -274,19 +476,73 @@ impl ColorTemperatureBackend for HyprlandBackend {
         "Hyprland"
     }
 
-    fn poll_hotplug(&mut self) -> Result<()> {
+    fn capabilities(&self) -> Capabilities {
+        Self::CAPABILITIES
+    }
+
+    fn output_names(&self) -> Option<BTreeSet<String>> {
+        Some(
+            self.state
+                .outputs
+                .iter()
+                .filter(|info| info.announced)
+                .map(|info| info.name.clone())
+                .collect(),
+        )
+    }
+
+    /// Hyprland keeps no tables, so re-reading the outputs and their HDR state and
+    /// committing every output's matrix again is all there is to rebuild.
+    fn refresh(&mut self) -> Result<()> {
         self.event_queue.roundtrip(&mut self.state)?;
-        let current_output_count = self.state.outputs.len();
+        self.refresh_hdr_outputs();
+        self.apply_combined_ctm()
+    }
 
-        if current_output_count != self.last_output_count {
+    fn poll_hotplug(&mut self) -> Result<()> {
+        // Any roundtrip failure leaves the connection unusable
+        if let Err(e) = self.event_queue.roundtrip(&mut self.state) {
             if self.debug_enabled {
-                log_debug!(
-                    "Output count changed: {} -> {}",
-                    self.last_output_count,
-                    current_output_count
-                );
+                log_warning!("Hyprland roundtrip failed: {e}");
             }
+            return Err(ConnectionLost.into());
+        }
+        let current_output_count = self.state.outputs.len();
+        let count_changed = current_output_count != self.last_output_count;
 
+        if count_changed && self.debug_enabled {
+            log_debug!(
+                "Output count changed: {} -> {}",
+                self.last_output_count,
+                current_output_count
+            );
+        }
+
+        // A renamed output may now match a different `[outputs.<name>]` table
+        let names_changed = std::mem::take(&mut self.state.names_changed);
+
+        let focus_changed = match self
+            .compositor_events
+            .as_ref()
+            .and_then(|events| events.take_focus())
+        {
+            Some(focus) => {
+                self.focus = focus;
+                self.update_neutral_outputs()
+            }
+            None => false,
+        };
+
+        let hdr_changed = (count_changed || names_changed) && self.refresh_hdr_outputs();
+
+        if count_changed
+            || focus_changed
+            || hdr_changed
+            || (names_changed
+                && (!self.output_configs.is_empty()
+                    || !self.output_filter.allows_all()
+                    || !self.neutral_apps.is_empty()))
+        {
             self.last_output_count = current_output_count;
 
             if !self.state.outputs.is_empty() {
@@ -297,6 +553,39 @@ impl ColorTemperatureBackend for HyprlandBackend {
         Ok(())
     }
 
+    fn take_output_events(&mut self) -> Vec<OutputEvent> {
+        std::mem::take(&mut self.state.output_events)
+    }
+
+    fn reload_config(&mut self, config: &Config) -> Result<()> {
+        let output_filter = config.output_filter();
+        let outputs_changed = self.output_configs != config.outputs
+            || self.output_filter != output_filter
+            || self.output_defaults != config.output_defaults();
+        let neutral_changed = if self.neutral_apps != config.neutral_apps {
+            self.neutral_apps = config.neutral_apps.clone();
+            self.update_neutral_outputs()
+        } else {
+            false
+        };
+        let hdr_changed = self.refresh_hdr_outputs();
+        let presentation_changed = self.presentation != config.presentation;
+        if !outputs_changed && !neutral_changed && !hdr_changed && !presentation_changed {
+            return Ok(());
+        }
+
+        if self.debug_enabled && outputs_changed {
+            log_pipe!();
+            log_debug!("Per-output settings changed, reapplying CTM");
+        }
+
+        self.output_configs = config.outputs.clone();
+        self.output_defaults = config.output_defaults();
+        self.output_filter = output_filter;
+        self.presentation = config.presentation.clone();
+        self.apply_combined_ctm()
+    }
+
     fn cleanup(mut self: Box<Self>, debug_enabled: bool) {
         if debug_enabled {
             log_debug!("Native Hyprland backend shutting down");
@@ -347,11 +636,17 @@ impl Dispatch<WlRegistry, ()> for State {
                         output,
                         name: format!("output-{}", name),
                         registry_name: name,
+                        announced: false,
                     });
                 }
             }
             Event::GlobalRemove { name } => {
-                state.outputs.retain(|o| o.registry_name != name);
+                if let Some(index) = state.outputs.iter().position(|o| o.registry_name == name) {
+                    let info = state.outputs.remove(index);
+                    if info.announced {
+                        state.output_events.push(OutputEvent::Removed(info.name));
+                    }
+                }
             }
             _ => {}
         }
@@ -391,15 +686,30 @@ impl Dispatch<WlOutput, u32> for State {
     ) {
         use wayland_client::protocol::wl_output::Event;
 
-        if let Event::Name { name } = event {
-            // data is the registry name, used to match the right output
-            if let Some(info) = state.outputs.iter_mut().find(|o| o.registry_name == *data) {
+        // data is the registry name, used to match the right output
+        let Some(info) = state.outputs.iter_mut().find(|o| o.registry_name == *data) else {
+            return;
+        };
+
+        match event {
+            Event::Name { name } => {
                 let old_name = info.name.clone();
                 info.name = name.clone();
+                state.names_changed |= old_name != name;
                 if old_name.starts_with("output-") && state.debug_enabled {
                     log_debug!("Output identified: {}", name);
                 }
             }
+            Event::Done if !info.announced => {
+                info.announced = true;
+                state
+                    .output_events
+                    .push(OutputEvent::Added(info.name.clone()));
+            }
+            _ => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests;
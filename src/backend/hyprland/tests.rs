@@ -0,0 +1,500 @@
+//! Tests for per-output CTM against an in-process compositor.
+//!
+//! The server side of hyprland-ctm-control-v1 is generated from the same XML as the client,
+//! so these tests exercise the real wire encoding: matrices travel as `fixed` values and are
+//! grouped by `commit`.
+
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use wayland_server::{
+    Client, DataInit, Dispatch, Display, DisplayHandle, GlobalDispatch, New, Resource,
+    backend::ClientData,
+    protocol::wl_output::{self, WlOutput},
+};
+
+use super::*;
+use crate::common::constants::*;
+use crate::config::HdrBehavior;
+
+mod server {
+    #![allow(dead_code, non_upper_case_globals, non_camel_case_types, clippy::all)]
+
+    use wayland_server;
+    use wayland_server::protocol::*;
+
+    pub mod __interfaces {
+        use wayland_server::protocol::__interfaces::*;
+        wayland_scanner::generate_interfaces!("src/backend/hyprland/hyprland-ctm-control-v1.xml");
+    }
+
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_server_code!("src/backend/hyprland/hyprland-ctm-control-v1.xml");
+}
+
+use server::hyprland_ctm_control_manager_v1::{self, HyprlandCtmControlManagerV1};
+
+/// Matrices set for each output name in one `commit`, in request order.
+type Commit = Vec<(&'static str, [f64; 9])>;
+
+struct ServerState {
+    pending: Commit,
+    commits: Arc<Mutex<Vec<Commit>>>,
+}
+
+struct TestClient;
+
+impl ClientData for TestClient {}
+
+/// In-process compositor advertising the CTM manager and a fixed set of named outputs.
+struct FakeCompositor {
+    commits: Arc<Mutex<Vec<Commit>>>,
+    shutdown: mpsc::Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeCompositor {
+    fn start(outputs: &[&'static str]) -> (Self, Connection) {
+        let (client_socket, server_socket) = UnixStream::pair().unwrap();
+        let (shutdown, shutdown_receiver) = mpsc::channel();
+        let commits = Arc::new(Mutex::new(Vec::new()));
+        let server_commits = Arc::clone(&commits);
+        let outputs = outputs.to_vec();
+
+        let thread = thread::spawn(move || {
+            let mut display: Display<ServerState> = Display::new().unwrap();
+            let handle = display.handle();
+            handle.create_global::<ServerState, HyprlandCtmControlManagerV1, ()>(2, ());
+            for name in outputs {
+                handle.create_global::<ServerState, WlOutput, _>(4, name);
+            }
+            handle
+                .clone()
+                .insert_client(server_socket, Arc::new(TestClient))
+                .unwrap();
+
+            let mut state = ServerState {
+                pending: Vec::new(),
+                commits: server_commits,
+            };
+
+            while shutdown_receiver.try_recv() == Err(mpsc::TryRecvError::Empty) {
+                let _ = display.dispatch_clients(&mut state);
+                let _ = display.flush_clients();
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        let connection = Connection::from_socket(client_socket).unwrap();
+        (
+            Self {
+                commits,
+                shutdown,
+                thread: Some(thread),
+            },
+            connection,
+        )
+    }
+
+    fn last_commit(&self) -> Commit {
+        self.commits.lock().unwrap().last().cloned().unwrap()
+    }
+}
+
+impl Drop for FakeCompositor {
+    fn drop(&mut self) {
+        let _ = self.shutdown.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl GlobalDispatch<WlOutput, &'static str> for ServerState {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WlOutput>,
+        name: &&'static str,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let output = data_init.init(resource, *name);
+        if output.version() >= 4 {
+            output.name(name.to_string());
+        }
+        output.done();
+    }
+}
+
+impl Dispatch<WlOutput, &'static str> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlOutput,
+        _: wl_output::Request,
+        _: &&'static str,
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl GlobalDispatch<HyprlandCtmControlManagerV1, ()> for ServerState {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<HyprlandCtmControlManagerV1>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<HyprlandCtmControlManagerV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &HyprlandCtmControlManagerV1,
+        request: hyprland_ctm_control_manager_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            hyprland_ctm_control_manager_v1::Request::SetCtmForOutput {
+                output,
+                mat0,
+                mat1,
+                mat2,
+                mat3,
+                mat4,
+                mat5,
+                mat6,
+                mat7,
+                mat8,
+            } => {
+                let name = *output.data::<&'static str>().unwrap();
+                state
+                    .pending
+                    .push((name, [mat0, mat1, mat2, mat3, mat4, mat5, mat6, mat7, mat8]));
+            }
+            hyprland_ctm_control_manager_v1::Request::Commit => {
+                let commit = std::mem::take(&mut state.pending);
+                state.commits.lock().unwrap().push(commit);
+            }
+            hyprland_ctm_control_manager_v1::Request::Destroy => {}
+        }
+    }
+}
+
+fn config_with_outputs(outputs: &[(&str, OutputConfig)]) -> Config {
+    outputs
+        .iter()
+        .fold(
+            Config::builder()
+                .backend(crate::config::Backend::Hyprland)
+                .static_values(DEFAULT_NIGHT_TEMP, DEFAULT_NIGHT_GAMMA)
+                .smoothing(false)
+                .startup_duration(0.0)
+                .shutdown_duration(0.0),
+            |builder, (name, config)| builder.output(name, config.clone()),
+        )
+        .build()
+}
+
+/// `fixed` has 8 fractional bits, so values survive the wire only to within 1/256.
+fn assert_ctm_eq(actual: [f64; 9], expected: [f64; 9]) {
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() <= 1.0 / 256.0, "{actual:?} != {expected:?}");
+    }
+}
+
+#[test]
+fn output_ctm_applies_offsets_and_matrix() {
+    let plain = output_ctm(4000, 90.0, &OutputConfig::default());
+    let (r, g, b) = gamma::temperature_to_rgb(4000);
+    assert_eq!(
+        plain,
+        [r * 0.9, 0.0, 0.0, 0.0, g * 0.9, 0.0, 0.0, 0.0, b * 0.9]
+    );
+
+    let offset = OutputConfig {
+        temp_offset: 500,
+        gamma_offset: 10.0,
+        ..Default::default()
+    };
+    assert_eq!(
+        output_ctm(4000, 90.0, &offset),
+        output_ctm(4500, 100.0, &OutputConfig::default())
+    );
+
+    // Rows of the output matrix are scaled by the tint of their channel
+    let swap_red_green = OutputConfig {
+        matrix: Some(crate::config::ColorMatrix::Custom([
+            0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+        ])),
+        ..Default::default()
+    };
+    let (r, g, b) = gamma::temperature_to_rgb(6500);
+    assert_eq!(
+        output_ctm(6500, 100.0, &swap_red_green),
+        [0.0, r, 0.0, g, 0.0, 0.0, 0.0, 0.0, b]
+    );
+}
+
+#[test]
+fn output_settings_replace_the_top_level_ones() {
+    use crate::config::{ColorMatrix, MatrixPreset};
+
+    let (r, g, b) = gamma::temperature_to_rgb(4000);
+    let defaults = Config::builder()
+        .blue_trim(0.8)
+        .matrix(ColorMatrix::Preset(MatrixPreset::Grayscale))
+        .build()
+        .output_defaults();
+    let output = OutputConfig {
+        green_trim: Some(0.9),
+        ..Default::default()
+    }
+    .with_defaults(&defaults);
+    assert_eq!(output.trim(), (1.0, 0.9, 0.8));
+
+    // The top-level matrix goes under the tint of outputs without one
+    let luma = [0.2126, 0.7152, 0.0722];
+    let tint = [r, g * 0.9, b * 0.8];
+    assert_ctm_eq(
+        output_ctm(4000, 100.0, &output),
+        std::array::from_fn(|i| tint[i / 3] * luma[i % 3]),
+    );
+
+    let own_matrix = OutputConfig {
+        matrix: Some(ColorMatrix::Custom([
+            1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0,
+        ])),
+        ..Default::default()
+    }
+    .with_defaults(&defaults);
+    assert_eq!(
+        output_ctm(4000, 100.0, &own_matrix),
+        [r, 0.0, 0.0, 0.0, g, 0.0, 0.0, 0.0, b * 0.8]
+    );
+}
+
+#[test]
+fn saturation_blends_toward_luminance() {
+    let ctm = output_ctm(4000, 90.0, &OutputConfig::default());
+    assert_eq!(saturate(ctm, 100.0), ctm);
+
+    let identity = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+    let gray = saturate(identity, 0.0);
+    for row in gray.chunks(3) {
+        assert_ctm_row_eq(row, &[0.2126, 0.7152, 0.0722]);
+    }
+
+    // Composed after the tint, every entry stays in the range the protocol accepts
+    let half = saturate(ctm, 50.0);
+    assert!(half.iter().all(|v| *v >= 0.0));
+    for (row, tint) in half.chunks(3).zip([ctm[0], ctm[4], ctm[8]]) {
+        // A gray input keeps its tinted color
+        assert!((row.iter().sum::<f64>() - tint).abs() < 1e-9);
+    }
+}
+
+fn assert_ctm_row_eq(actual: &[f64], expected: &[f64]) {
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-9, "{actual:?} != {expected:?}");
+    }
+}
+
+#[test]
+fn offsets_are_clamped_to_valid_ranges() {
+    let config = OutputConfig {
+        temp_offset: -5000,
+        gamma_offset: 500.0,
+        ..Default::default()
+    };
+    assert_eq!(config.adjust(3000, 90.0), (MINIMUM_TEMP, MAXIMUM_GAMMA));
+}
+
+#[test]
+fn mixed_outputs_get_their_own_matrix_in_one_commit() {
+    let (compositor, connection) = FakeCompositor::start(&["DP-1", "DP-2", "HDMI-A-1"]);
+    let warmer = OutputConfig {
+        temp_offset: -500,
+        gamma_offset: -10.0,
+        ..Default::default()
+    };
+    let excluded = OutputConfig {
+        exclude: true,
+        ..Default::default()
+    };
+    let config = config_with_outputs(&[("DP-2", warmer.clone()), ("HDMI-A-1", excluded)]);
+    let mut backend = HyprlandBackend::from_connection(connection, &config, false).unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(4000).unwrap(),
+            GammaPercent::new(90.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    let commit = compositor.last_commit();
+    let names: Vec<_> = commit.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, vec!["DP-1", "DP-2"]);
+    assert_ctm_eq(
+        commit[0].1,
+        output_ctm(4000, 90.0, &OutputConfig::default()),
+    );
+    assert_ctm_eq(
+        commit[1].1,
+        output_ctm(3500, 80.0, &OutputConfig::default()),
+    );
+    assert_eq!(compositor.commits.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn reload_reapplies_only_when_outputs_change() {
+    let (compositor, connection) = FakeCompositor::start(&["DP-1"]);
+    let config = config_with_outputs(&[]);
+    let mut backend = HyprlandBackend::from_connection(connection, &config, false).unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(5000).unwrap(),
+            GammaPercent::new(100.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    backend.reload_config(&config).unwrap();
+    assert_eq!(compositor.commits.lock().unwrap().len(), 1);
+
+    let dimmer = OutputConfig {
+        gamma_offset: -20.0,
+        ..Default::default()
+    };
+    backend
+        .reload_config(&config_with_outputs(&[("DP-1", dimmer)]))
+        .unwrap();
+
+    let commits = compositor.commits.lock().unwrap().clone();
+    assert_eq!(commits.len(), 2);
+    assert_ctm_eq(
+        commits[1][0].1,
+        output_ctm(5000, 80.0, &OutputConfig::default()),
+    );
+}
+
+#[test]
+fn presentation_mode_leaves_its_outputs_out_until_turned_off() {
+    let (compositor, connection) = FakeCompositor::start(&["DP-1", "HDMI-A-1"]);
+    let config = config_with_outputs(&[]);
+    let mut backend = HyprlandBackend::from_connection(connection, &config, false).unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(4000).unwrap(),
+            GammaPercent::new(90.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    let presenting = Config {
+        presentation: Some(Presentation {
+            outputs: vec!["HDMI-A-1".to_string()],
+        }),
+        ..config.clone()
+    };
+    backend.reload_config(&presenting).unwrap();
+    let names: Vec<_> = compositor
+        .last_commit()
+        .iter()
+        .map(|(name, _)| *name)
+        .collect();
+    assert_eq!(names, vec!["DP-1"]);
+
+    // Scheduled updates keep it neutral
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(3500).unwrap(),
+            GammaPercent::new(90.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+    assert_eq!(compositor.last_commit().len(), 1);
+
+    backend.reload_config(&config).unwrap();
+    let commit = compositor.last_commit();
+    assert_eq!(commit.len(), 2);
+    assert_ctm_eq(
+        commit[1].1,
+        output_ctm(3500, 90.0, &OutputConfig::default()),
+    );
+}
+
+/// Reports a fixed set of outputs in HDR.
+struct HdrOutputs(&'static [&'static str]);
+
+impl CompositorEvents for HdrOutputs {
+    fn take_outputs_changed(&self) -> bool {
+        false
+    }
+
+    fn hdr_outputs(&self) -> BTreeSet<String> {
+        self.0.iter().map(|name| name.to_string()).collect()
+    }
+}
+
+#[test]
+fn hdr_outputs_follow_their_hdr_setting() {
+    let (compositor, connection) = FakeCompositor::start(&["DP-1", "DP-2", "HDMI-A-1"]);
+    let hdr = |hdr| OutputConfig {
+        hdr,
+        ..Default::default()
+    };
+    let config = config_with_outputs(&[
+        ("DP-2", hdr(HdrBehavior::Reduced)),
+        ("HDMI-A-1", hdr(HdrBehavior::CtmOnly)),
+    ]);
+    let mut backend = HyprlandBackend::from_connection(connection, &config, false).unwrap();
+    backend.compositor_events = Some(Box::new(HdrOutputs(&["DP-1", "DP-2", "HDMI-A-1"])));
+    assert!(backend.refresh_hdr_outputs());
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(3300).unwrap(),
+            GammaPercent::new(90.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    // DP-1 is skipped by default, and the matrix is all ctm_only needs
+    let commit = compositor.last_commit();
+    let names: Vec<_> = commit.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, vec!["DP-2", "HDMI-A-1"]);
+    let (temp, gamma) = Adjustment::Reduced.values((3300, 90.0));
+    assert_ctm_eq(
+        commit[0].1,
+        output_ctm(temp, gamma, &OutputConfig::default()),
+    );
+    assert_ctm_eq(
+        commit[1].1,
+        output_ctm(3300, 90.0, &OutputConfig::default()),
+    );
+
+    // Nothing changed, so a reload doesn't commit again
+    backend.reload_config(&config).unwrap();
+    assert_eq!(compositor.commits.lock().unwrap().len(), 1);
+}
@@ -2,7 +2,8 @@
 //!
 //! Commands are sent as formatted strings over Hyprland's IPC Unix socket, and the
 //! response is checked for a success or failure indication. The socket path is derived
-//! from Hyprland's environment: `HYPRLAND_INSTANCE_SIGNATURE` selects the instance and
+//! from Hyprland's environment: `HYPRLAND_INSTANCE_SIGNATURE`, or the instance found after
+//! Hyprland restarted (see [`crate::backend::rediscover`]), selects the instance and
 //! `XDG_RUNTIME_DIR` (or `/run/user/{uid}`) the base directory, giving
 //! `{runtime_dir}/hypr/{instance}/.hyprsunset.sock`.
 
@@ -29,7 +30,8 @@ impl HyprsunsetClient {
     /// Create a client with the resolved socket path. Does not require hyprsunset to be
     /// running yet. The connection is attempted per command.
     pub fn new(debug_enabled: bool) -> Result<Self> {
-        let his_env = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").ok();
+        let his_env = crate::backend::rediscover::hyprland_instance()
+            .map(|instance| instance.to_string_lossy().into_owned());
         let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
             .unwrap_or_else(|_| format!("/run/user/{}", nix::unistd::getuid()));
 
@@ -127,6 +129,8 @@ impl HyprsunsetClient {
         }
 
         let (temp, gamma) = runtime_state.values();
+        // hyprsunset has no brightness control, so dimming goes through gamma
+        let gamma = gamma.get() * runtime_state.brightness() / 100.0;
 
         if self.debug_enabled {
             log_pipe!();
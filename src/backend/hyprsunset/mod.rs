@@ -5,17 +5,37 @@
 //! the native CTM backend (`backend = "hyprland"`) is recommended because it needs no external
 //! process.
 //!
-//! The backend always operates in managed mode: it starts hyprsunset as a child during
+//! By default the backend runs in managed mode: it starts hyprsunset as a child during
 //! initialization, refuses to run alongside an externally started instance, and ensures the
-//! child is cleaned up on shutdown via PR_SET_PDEATHSIG. Commands are sent as formatted
-//! strings over the IPC socket, whose path is detected from Hyprland's environment.
+//! child is cleaned up on shutdown via PR_SET_PDEATHSIG. With `hyprsunset_mode = "attach"` it
+//! uses a hyprsunset started elsewhere, typically a systemd user service, and neither starts
+//! nor stops it. Stopping the child is what clears the tint in managed mode, so an attached
+//! instance is sent neutral values on exit instead. Commands are sent as formatted strings
+//! over the IPC socket, whose path is detected from Hyprland's environment.
+//!
+//! hyprsunset tints every output alike, so presentation mode holds the whole screen neutral
+//! whichever outputs it names. It leaves a monitor connected later at 6500K, so the
+//! backend follows Hyprland's monitor events and sends the last values again on hotplug.
+//!
+//! The main loop only sends new values once per `update_interval`, which shows as visible
+//! steps on hyprsunset. During a transition the backend fills in between: each worker poll
+//! recomputes the values for the current instant and forwards them when they changed, at
+//! most once per [`STEP_INTERVAL`] so the socket isn't flooded.
+//!
+//! hyprsunset has no brightness of its own, so the scheduled brightness is folded into the
+//! gamma it is sent.
 
 use anyhow::Result;
 use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
 
-use crate::backend::ColorTemperatureBackend;
+use crate::backend::hdr::NEUTRAL_VALUES;
+use crate::backend::hyprland::ipc::HyprlandEvents;
+use crate::backend::{Capabilities, ColorTemperatureBackend, CompositorEvents};
 use crate::common::error::Silent;
-use crate::config::Config;
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::{Config, HyprsunsetMode};
+use crate::core::runtime_state::RuntimeState;
 
 pub mod client;
 pub mod process;
@@ -23,80 +43,184 @@ pub mod process;
 pub use client::HyprsunsetClient;
 pub use process::{HyprsunsetProcess, is_hyprsunset_running};
 
+/// Shortest time between two values sent in between the main loop's updates.
+const STEP_INTERVAL: Duration = Duration::from_millis(250);
+
 /// Hyprsunset backend that manages the hyprsunset process for gamma control on Hyprland.
 pub struct HyprsunsetBackend {
     client: HyprsunsetClient,
     process: Option<HyprsunsetProcess>,
-    /// The last temperature and gamma values that were successfully applied to hyprsunset.
-    /// Used to avoid redundant state applications.
+    /// The last temperature and gamma values that were successfully applied to hyprsunset,
+    /// with brightness folded in. Used to avoid redundant state applications.
     last_applied_values: Option<(u32, f64)>,
+    /// The scheduled brightness in percent, kept for values applied without a state.
+    brightness: f64,
+    /// Whether presentation mode holds the screen neutral.
+    presentation: bool,
+    /// The values last asked for, put back when presentation mode ends.
+    requested_values: Option<(Kelvin, GammaPercent)>,
+    /// The transition being stepped through between updates, with when it was last stepped.
+    transition: Option<(RuntimeState, Instant)>,
+    /// Hyprland's event stream, for monitors connected or disconnected.
+    compositor_events: Option<Box<dyn CompositorEvents>>,
 }
 
 impl HyprsunsetBackend {
+    /// hyprsunset tints every output alike and animates through its own CTM. Hotplugged
+    /// monitors get the last values again.
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        smoothing: false,
+        per_output: false,
+        hardware: false,
+        hotplug: true,
+        keeps_gamma_on_exit: false,
+    };
+
     /// Create a backend by computing the current temperature and gamma from the schedule,
-    /// then starting the managed hyprsunset process with those initial values.
+    /// then starting or attaching to hyprsunset with those initial values.
     pub fn new(
         config: &Config,
         debug_enabled: bool,
         geo_times: Option<&crate::geo::times::GeoTimes>,
     ) -> Result<Self> {
         let schedule = crate::core::schedule::Schedule::from_config(config, geo_times.cloned());
-        let now = crate::time::source::now();
-        let current_state = schedule
-            .as_ref()
-            .map_or(crate::core::period::Period::Static, |schedule| {
-                schedule.current_period(now)
-            });
-        let runtime_state =
-            crate::core::runtime_state::RuntimeState::new(current_state, config, schedule, now);
+        let runtime_state = crate::core::runtime_state::RuntimeState::at(
+            config,
+            schedule,
+            crate::time::source::now(),
+        );
         let (temp, gamma) = runtime_state.values();
-
-        Self::new_with_initial_values(debug_enabled, temp, gamma)
+        let brightness = runtime_state.brightness();
+
+        let mut backend = Self::new_with_initial_values(
+            debug_enabled,
+            config.hyprsunset_mode,
+            temp.get(),
+            gamma.get() * brightness / 100.0,
+        )?;
+        backend.brightness = brightness;
+        backend.requested_values = Some((temp, gamma));
+        Ok(backend)
     }
 
-    /// Create a backend that starts hyprsunset directly with the given temperature and gamma.
+    /// Create a backend whose hyprsunset starts out at the given temperature and gamma.
     ///
-    /// Used by the test command to start with test values, avoiding a second apply after
-    /// initialization.
+    /// A managed hyprsunset is started with them directly. The test command relies on this
+    /// to start with test values, avoiding a second apply after initialization. An attached
+    /// one is sent them once connected.
     pub fn new_with_initial_values(
         debug_enabled: bool,
+        mode: HyprsunsetMode,
         initial_temp: u32,
         initial_gamma: f64,
     ) -> Result<Self> {
         verify_hyprsunset_installed_and_version()?;
 
-        if is_hyprsunset_running() {
-            log_pipe!();
-            log_warning!("hyprsunset is already running.");
-            log_pipe!();
-            log_error!("Please kill the existing hyprsunset process: pkill hyprsunset");
-            log_indented!(
-                "The Hyprsunset backend manages hyprsunset internally and cannot work with"
-            );
-            log_indented!("an externally started hyprsunset instance.");
-            log_end!();
-            return Err(Silent.into());
-        }
-
-        let (process, last_applied_values) = (
-            Some(HyprsunsetProcess::new(
-                initial_temp,
-                initial_gamma,
-                debug_enabled,
-            )?),
-            Some((initial_temp, initial_gamma)),
-        );
+        let process = match mode {
+            HyprsunsetMode::Managed => {
+                if is_hyprsunset_running() {
+                    log_pipe!();
+                    log_warning!("hyprsunset is already running.");
+                    log_pipe!();
+                    log_error!("Please kill the existing hyprsunset process: pkill hyprsunset");
+                    log_indented!(
+                        "The Hyprsunset backend manages hyprsunset internally. To use an"
+                    );
+                    log_indented!(
+                        "instance started elsewhere, set hyprsunset_mode = \"attach\" instead."
+                    );
+                    log_end!();
+                    return Err(Silent.into());
+                }
+                Some(HyprsunsetProcess::new(
+                    initial_temp,
+                    initial_gamma,
+                    debug_enabled,
+                )?)
+            }
+            HyprsunsetMode::Attach => {
+                if !is_hyprsunset_running() {
+                    log_pipe!();
+                    log_error!("hyprsunset_mode is \"attach\" but hyprsunset isn't running.");
+                    log_indented!("Start it first, e.g. systemctl --user start hyprsunset,");
+                    log_indented!("or remove hyprsunset_mode to let sunsetr start its own.");
+                    log_end!();
+                    return Err(Silent.into());
+                }
+                if debug_enabled {
+                    log_debug!("Attaching to the running hyprsunset");
+                }
+                None
+            }
+        };
 
         let mut client = HyprsunsetClient::new(debug_enabled)?;
 
         verify_hyprsunset_connection(&mut client)?;
 
+        if process.is_none() {
+            // Still on whatever values it had before, possibly from an earlier sunsetr
+            client.apply_temperature_gamma(initial_temp, initial_gamma, &AtomicBool::new(true))?;
+        }
+        let last_applied_values = Some((initial_temp, initial_gamma));
+
         Ok(Self {
             client,
             process,
             last_applied_values,
+            brightness: crate::common::constants::DEFAULT_BRIGHTNESS,
+            presentation: false,
+            requested_values: Kelvin::new(initial_temp)
+                .ok()
+                .zip(GammaPercent::new(initial_gamma).ok()),
+            transition: None,
+            compositor_events: HyprlandEvents::connect(debug_enabled)
+                .map(|events| Box::new(events) as Box<dyn CompositorEvents>),
         })
     }
+
+    /// Hold the screen at neutral values for presentation mode.
+    fn apply_neutral(&mut self, running: &AtomicBool) -> Result<()> {
+        if self.last_applied_values == Some(NEUTRAL_VALUES) {
+            return Ok(());
+        }
+        let (temp, gamma) = NEUTRAL_VALUES;
+        self.client.apply_temperature_gamma(temp, gamma, running)?;
+        self.last_applied_values = Some(NEUTRAL_VALUES);
+        Ok(())
+    }
+
+    /// Send the last values again, so a monitor connected since gets them too.
+    fn reapply_after_output_change(&mut self) -> Result<()> {
+        let Some((temp, gamma)) = self.last_applied_values else {
+            return Ok(());
+        };
+        if self.client.debug_enabled {
+            log_debug!("Hyprland monitors changed, reapplying {temp}K @ {gamma}%");
+        }
+        self.client
+            .apply_temperature_gamma(temp, gamma, &AtomicBool::new(true))
+    }
+
+    /// Follow `runtime_state` between updates while it is a transition, or stop following.
+    /// Power saving keeps to the main loop's coarser updates.
+    fn track_transition(&mut self, runtime_state: &RuntimeState) {
+        self.transition = (runtime_state.period().is_transitioning()
+            && !runtime_state.power_saving())
+        .then(|| (runtime_state.clone(), Instant::now()));
+    }
+
+    /// `values` with the scheduled brightness folded into the gamma.
+    fn dimmed(&self, (temp, gamma): (Kelvin, GammaPercent)) -> (u32, f64) {
+        (temp.get(), gamma.get() * self.brightness / 100.0)
+    }
+}
+
+/// The state for the current instant while it is still in `runtime_state`'s transition.
+/// Once the period has moved on, the main loop applies the next one.
+fn transition_step(runtime_state: &RuntimeState) -> Option<RuntimeState> {
+    let (current, _) = runtime_state.with_current_period();
+    (current.period() == runtime_state.period()).then_some(current)
 }
 
 impl ColorTemperatureBackend for HyprsunsetBackend {
@@ -105,10 +229,15 @@ impl ColorTemperatureBackend for HyprsunsetBackend {
         runtime_state: &crate::core::runtime_state::RuntimeState,
         running: &AtomicBool,
     ) -> Result<()> {
+        self.requested_values = Some(runtime_state.values());
+        self.brightness = runtime_state.brightness();
+        self.track_transition(runtime_state);
+        if self.presentation {
+            return self.apply_neutral(running);
+        }
         self.client.apply_transition_state(runtime_state, running)?;
 
-        let (temp, gamma) = runtime_state.values();
-        self.last_applied_values = Some((temp, gamma));
+        self.last_applied_values = Some(self.dimmed(runtime_state.values()));
 
         Ok(())
     }
@@ -118,7 +247,14 @@ impl ColorTemperatureBackend for HyprsunsetBackend {
         runtime_state: &crate::core::runtime_state::RuntimeState,
         running: &AtomicBool,
     ) -> Result<()> {
-        let (target_temp, target_gamma) = runtime_state.values();
+        self.requested_values = Some(runtime_state.values());
+        self.brightness = runtime_state.brightness();
+        let (target_temp, target_gamma) = self.dimmed(runtime_state.values());
+        self.track_transition(runtime_state);
+        if self.presentation {
+            crate::core::period::log_state_announcement(runtime_state.period());
+            return self.apply_neutral(running);
+        }
 
         // Skip the redundant apply if hyprsunset already has the target values
         if let Some((last_temp, last_gamma)) = self.last_applied_values
@@ -138,10 +274,17 @@ impl ColorTemperatureBackend for HyprsunsetBackend {
 
     fn apply_temperature_gamma(
         &mut self,
-        temperature: u32,
-        gamma: f64,
+        temperature: Kelvin,
+        gamma: GammaPercent,
         running: &AtomicBool,
     ) -> Result<()> {
+        self.requested_values = Some((temperature, gamma));
+        // Exact values replace the schedule until the next state arrives
+        self.transition = None;
+        if self.presentation {
+            return self.apply_neutral(running);
+        }
+        let (temperature, gamma) = self.dimmed((temperature, gamma));
         self.client
             .apply_temperature_gamma(temperature, gamma, running)?;
 
@@ -154,21 +297,86 @@ impl ColorTemperatureBackend for HyprsunsetBackend {
         "Hyprsunset"
     }
 
-    fn cleanup(self: Box<Self>, debug_enabled: bool) {
-        if let Some(process) = self.process {
-            if debug_enabled {
-                log_decorated!("Stopping managed hyprsunset process...");
+    fn capabilities(&self) -> Capabilities {
+        Self::CAPABILITIES
+    }
+
+    fn poll_hotplug(&mut self) -> Result<()> {
+        if self
+            .compositor_events
+            .as_ref()
+            .is_some_and(|events| events.take_outputs_changed())
+        {
+            self.reapply_after_output_change()?;
+        }
+
+        let Some((runtime_state, last_step)) = &mut self.transition else {
+            return Ok(());
+        };
+        if self.presentation || last_step.elapsed() < STEP_INTERVAL {
+            return Ok(());
+        }
+        *last_step = Instant::now();
+        let Some(current) = transition_step(runtime_state) else {
+            self.transition = None;
+            return Ok(());
+        };
+        self.brightness = current.brightness();
+        let (temp, gamma) = self.dimmed(current.values());
+        if self.last_applied_values == Some((temp, gamma)) {
+            return Ok(());
+        }
+
+        let running = AtomicBool::new(true);
+        self.client.apply_temperature_gamma(temp, gamma, &running)?;
+        self.last_applied_values = Some((temp, gamma));
+        self.requested_values = Some(current.values());
+        Ok(())
+    }
+
+    fn reload_config(&mut self, config: &Config) -> Result<()> {
+        let presentation = config.presentation.is_some();
+        if presentation == self.presentation {
+            return Ok(());
+        }
+        self.presentation = presentation;
+
+        let running = AtomicBool::new(true);
+        if presentation {
+            return self.apply_neutral(&running);
+        }
+        match self.requested_values {
+            Some((temp, gamma)) => self.apply_temperature_gamma(temp, gamma, &running),
+            None => Ok(()),
+        }
+    }
+
+    fn cleanup(mut self: Box<Self>, debug_enabled: bool) {
+        let Some(process) = self.process.take() else {
+            // Left running for whoever manages it, without sunsetr's tint
+            let (temp, gamma) = NEUTRAL_VALUES;
+            if let Err(e) = self
+                .client
+                .apply_temperature_gamma(temp, gamma, &AtomicBool::new(true))
+            {
+                log_decorated!("Warning: Failed to reset the attached hyprsunset: {e}");
+            } else if debug_enabled {
+                log_decorated!("Attached hyprsunset reset to neutral and left running");
             }
-            match process.stop(debug_enabled) {
-                Ok(_) => {
-                    if debug_enabled {
-                        log_decorated!("Hyprsunset process stopped successfully");
-                    }
-                }
-                Err(e) => {
-                    log_decorated!("Warning: Failed to stop hyprsunset process: {e}")
+            return;
+        };
+        if debug_enabled {
+            log_decorated!("Stopping managed hyprsunset process...");
+        }
+        match process.stop(debug_enabled) {
+            Ok(_) => {
+                if debug_enabled {
+                    log_decorated!("Hyprsunset process stopped successfully");
                 }
             }
+            Err(e) => {
+                log_decorated!("Warning: Failed to stop hyprsunset process: {e}")
+            }
         }
     }
 }
@@ -278,3 +486,42 @@ pub fn verify_hyprsunset_connection(client: &mut HyprsunsetClient) -> Result<()>
     log_end!();
     Err(Silent.into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TransitionMode;
+    use crate::time::source::{SimulatedTimeSource, SimulationPace, with_scoped_source};
+    use chrono::TimeZone;
+    use std::sync::Arc;
+
+    #[test]
+    fn transition_is_stepped_until_its_period_ends() {
+        let config = Config::builder()
+            .transition_mode(TransitionMode::FinishBy)
+            .transition_duration(30)
+            .build();
+        let at = |hour, minute| {
+            chrono::Local
+                .with_ymd_and_hms(2025, 6, 10, hour, minute, 0)
+                .unwrap()
+        };
+        let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+        let sunset = RuntimeState::at(&config, schedule, at(18, 40));
+        assert!(sunset.period().is_transitioning());
+
+        let step_at = |time| {
+            let source = Arc::new(SimulatedTimeSource::new(
+                time,
+                time + chrono::Duration::hours(1),
+                SimulationPace::FastForward,
+            ));
+            with_scoped_source(source, || transition_step(&sunset))
+        };
+
+        let (start_temp, _) = sunset.values();
+        let (later_temp, _) = step_at(at(18, 45)).expect("still in the sunset").values();
+        assert!(later_temp < start_temp);
+        assert!(step_at(at(19, 5)).is_none());
+    }
+}
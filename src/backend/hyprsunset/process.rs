@@ -12,7 +12,10 @@ use std::{
     time::Duration,
 };
 
-use crate::{backend::hyprsunset::client::HyprsunsetClient, common::constants::*};
+use crate::{
+    backend::hyprsunset::client::HyprsunsetClient,
+    common::units::{GammaPercent, Kelvin},
+};
 
 /// Manages a hyprsunset process started by sunsetr, terminating and reaping it on shutdown.
 pub struct HyprsunsetProcess {
@@ -34,22 +37,8 @@ impl HyprsunsetProcess {
             );
         }
 
-        if !(MINIMUM_TEMP..=MAXIMUM_TEMP).contains(&initial_temp) {
-            return Err(anyhow::anyhow!(
-                "Invalid temperature: {}K (must be {}-{})",
-                initial_temp,
-                MINIMUM_TEMP,
-                MAXIMUM_TEMP
-            ));
-        }
-        if !(MINIMUM_GAMMA..=MAXIMUM_GAMMA).contains(&initial_gamma) {
-            return Err(anyhow::anyhow!(
-                "Invalid gamma: {:.1}% (must be {:.1}-{:.1})",
-                initial_gamma,
-                MINIMUM_GAMMA,
-                MAXIMUM_GAMMA
-            ));
-        }
+        Kelvin::new(initial_temp)?;
+        GammaPercent::new(initial_gamma)?;
 
         let mut cmd = Command::new("hyprsunset");
         cmd.arg("-t")
@@ -58,6 +47,13 @@ impl HyprsunsetProcess {
             .arg(initial_gamma.to_string())
             .stdout(Stdio::null())
             .stderr(Stdio::null());
+        // Our own environment still names the compositor from before any restart
+        if let Some(display) = crate::backend::rediscover::wayland_display() {
+            cmd.env("WAYLAND_DISPLAY", display);
+        }
+        if let Some(instance) = crate::backend::rediscover::hyprland_instance() {
+            cmd.env("HYPRLAND_INSTANCE_SIGNATURE", instance);
+        }
 
         // Create new process group to isolate hyprsunset from terminal signals
         // This prevents Ctrl+C from killing hyprsunset before sunsetr can reset gamma
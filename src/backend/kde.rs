@@ -0,0 +1,220 @@
+//! KDE Plasma backend driving KWin's Night Light over the session D-Bus.
+//!
+//! KWin implements neither wlr-gamma-control nor hyprland-ctm-control, but it tints the
+//! screen itself and lets other programs take over: an inhibit suspends Night Light's own
+//! schedule, and a preview shows any temperature until it is stopped. sunsetr holds one
+//! inhibit for as long as it runs and keeps its temperature on screen as a preview. KWin
+//! drops a preview after 15 seconds, so the preview is renewed well before that, and
+//! releases the inhibit by itself when sunsetr's bus connection goes away.
+//!
+//! Night Light only sets a temperature between 1000K and 6500K, so gamma and the manual
+//! per-channel curves are ignored. Smooth transitions, presentation mode and
+//! `neutral_apps` are not available either, since KWin animates each change itself and
+//! tints every output the same.
+
+use anyhow::{Context, Result};
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+use zbus::blocking::Connection;
+
+use crate::backend::{Capabilities, ColorTemperatureBackend, ConnectionLost};
+use crate::common::error::Silent;
+use crate::common::units::{GammaPercent, Kelvin};
+
+/// KWin ends a preview after 15 seconds. Renewing it sooner keeps the temperature on
+/// screen without a flicker back to neutral.
+const PREVIEW_RENEW_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Highest temperature Night Light accepts, also its neutral one.
+const NEUTRAL_TEMPERATURE: u32 = 6500;
+
+/// Object path and interface of Night Light in older Plasma releases, which named it
+/// Night Color and, before that, ColorCorrect.
+const LEGACY_INTERFACES: [(&str, &str); 2] = [
+    ("/org/kde/KWin/NightColor", "org.kde.KWin.NightColor"),
+    ("/ColorCorrect", "org.kde.kwin.ColorCorrect"),
+];
+
+#[zbus::proxy(
+    interface = "org.kde.KWin.NightLight",
+    default_service = "org.kde.KWin",
+    default_path = "/org/kde/KWin/NightLight"
+)]
+trait NightLight {
+    /// Whether the platform lets KWin set gamma at all.
+    #[zbus(property, name = "available")]
+    fn available(&self) -> zbus::Result<bool>;
+
+    /// Suspend Night Light's own schedule, returning a cookie to lift the inhibit with.
+    #[zbus(name = "inhibit")]
+    fn inhibit(&self) -> zbus::Result<u32>;
+
+    #[zbus(name = "uninhibit")]
+    fn uninhibit(&self, cookie: u32) -> zbus::Result<()>;
+
+    /// Show `temperature` until the preview is stopped or times out.
+    #[zbus(name = "preview")]
+    fn preview(&self, temperature: u32) -> zbus::Result<()>;
+
+    #[zbus(name = "stopPreview")]
+    fn stop_preview(&self) -> zbus::Result<()>;
+}
+
+/// Night Light backend for KDE Plasma.
+pub struct KdeBackend {
+    proxy: NightLightProxyBlocking<'static>,
+    inhibit_cookie: u32,
+    debug_enabled: bool,
+    current_temperature: u32,
+    last_preview: Instant,
+}
+
+impl KdeBackend {
+    /// KWin's Night Light applies one temperature everywhere and fades changes itself.
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        smoothing: false,
+        per_output: false,
+        hardware: false,
+        hotplug: false,
+        keeps_gamma_on_exit: false,
+    };
+
+    /// Find KWin's Night Light and take over from its schedule.
+    pub fn new(debug_enabled: bool) -> Result<Self> {
+        log_decorated!("Initializing KDE Night Light backend...");
+
+        let connection = Connection::session().context("Failed to connect to session D-Bus")?;
+        let Some(proxy) = connect_proxy(&connection) else {
+            log_pipe!();
+            log_error!("KWin's Night Light is not reachable over D-Bus.");
+            log_indented!("Are you running KDE Plasma 5.18 or later?");
+            log_end!();
+            return Err(Silent.into());
+        };
+
+        if !proxy.available().unwrap_or(false) {
+            log_pipe!();
+            log_error!("KWin reports that Night Light is unavailable on this system.");
+            log_indented!("It needs gamma control from the graphics driver.");
+            log_end!();
+            return Err(Silent.into());
+        }
+
+        let inhibit_cookie = proxy
+            .inhibit()
+            .context("Failed to inhibit Night Light's own schedule")?;
+        if debug_enabled {
+            log_pipe!();
+            log_debug!("Inhibited Night Light's schedule (cookie {inhibit_cookie})");
+        }
+
+        Ok(Self {
+            proxy,
+            inhibit_cookie,
+            debug_enabled,
+            current_temperature: NEUTRAL_TEMPERATURE,
+            last_preview: Instant::now(),
+        })
+    }
+
+    /// Show the stored temperature as a Night Light preview.
+    fn apply_preview(&mut self) -> Result<()> {
+        self.proxy
+            .preview(self.current_temperature)
+            .context("KWin rejected the Night Light preview")?;
+        self.last_preview = Instant::now();
+        Ok(())
+    }
+}
+
+/// Night Light under its current name, or one of the names older Plasma releases use.
+fn connect_proxy(connection: &Connection) -> Option<NightLightProxyBlocking<'static>> {
+    let current = NightLightProxyBlocking::new(connection).ok();
+    let legacy = LEGACY_INTERFACES.iter().filter_map(|&(path, interface)| {
+        NightLightProxyBlocking::builder(connection)
+            .path(path)
+            .ok()?
+            .interface(interface)
+            .ok()?
+            .build()
+            .ok()
+    });
+    current
+        .into_iter()
+        .chain(legacy)
+        .find(|proxy| proxy.available().is_ok())
+}
+
+impl ColorTemperatureBackend for KdeBackend {
+    /// Renews the preview before KWin times it out. A failed renewal means KWin is gone,
+    /// and the inhibit with it, so the worker recreates the backend once KWin is back.
+    fn poll_hotplug(&mut self) -> Result<()> {
+        if self.last_preview.elapsed() < PREVIEW_RENEW_INTERVAL {
+            return Ok(());
+        }
+        if let Err(e) = self.apply_preview() {
+            if self.debug_enabled {
+                log_warning!("Failed to renew the Night Light preview: {e:#}");
+            }
+            return Err(ConnectionLost.into());
+        }
+        Ok(())
+    }
+
+    fn apply_transition_state(
+        &mut self,
+        runtime_state: &crate::core::runtime_state::RuntimeState,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        let (temp, gamma) = runtime_state.values();
+        self.apply_temperature_gamma(temp, gamma, running)
+    }
+
+    fn apply_startup_state(
+        &mut self,
+        runtime_state: &crate::core::runtime_state::RuntimeState,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        crate::core::period::log_state_announcement(runtime_state.period());
+        self.apply_transition_state(runtime_state, running)
+    }
+
+    fn apply_temperature_gamma(
+        &mut self,
+        temperature: Kelvin,
+        _gamma: GammaPercent,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        self.current_temperature = temperature.get().min(NEUTRAL_TEMPERATURE);
+        if self.debug_enabled {
+            log_pipe!();
+            log_debug!(
+                "Previewing {}K through Night Light",
+                self.current_temperature
+            );
+        }
+        self.apply_preview()
+    }
+
+    /// Hands the screen back to Night Light, which returns to its own schedule or to
+    /// neutral when it's disabled.
+    fn cleanup(self: Box<Self>, debug_enabled: bool) {
+        if debug_enabled {
+            log_decorated!("Returning control to Night Light before exit...");
+        }
+        if let Err(e) = self.proxy.stop_preview() {
+            log_warning!("Failed to stop the Night Light preview: {e}");
+        }
+        if let Err(e) = self.proxy.uninhibit(self.inhibit_cookie) {
+            log_warning!("Failed to lift the Night Light inhibit: {e}");
+        }
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "KDE"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Self::CAPABILITIES
+    }
+}
@@ -0,0 +1,78 @@
+//! Backend that applies nothing, for `backend = "none"` and `sunsetr --dry-run`.
+//!
+//! Everything up to the backend runs as usual: the schedule and geo calculation, smooth
+//! transitions, presets, IPC and logging. The values end here instead of on a display,
+//! so NixOS module tests, CI and reproductions of scheduling reports can run sunsetr
+//! without a compositor. With `--debug` each state it is handed is logged.
+
+use anyhow::Result;
+use std::sync::atomic::AtomicBool;
+
+use crate::backend::{Capabilities, ColorTemperatureBackend};
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::core::runtime_state::RuntimeState;
+
+pub struct MockBackend {
+    debug_enabled: bool,
+}
+
+impl MockBackend {
+    /// Smooth transitions are stepped through like on a real display, so they can be
+    /// followed over IPC. There are no outputs to tell apart.
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        smoothing: true,
+        per_output: false,
+        hardware: false,
+        hotplug: false,
+        keeps_gamma_on_exit: false,
+    };
+
+    pub fn new(debug_enabled: bool) -> Self {
+        log_decorated!("Using the mock backend, the display is left untouched");
+        Self { debug_enabled }
+    }
+
+    fn log_state(&self, runtime_state: &RuntimeState) {
+        if self.debug_enabled {
+            let (temp, gamma) = runtime_state.values();
+            log_debug!("Mock backend, not applied: {temp}K @ {gamma}% gamma");
+        }
+    }
+}
+
+impl ColorTemperatureBackend for MockBackend {
+    fn apply_transition_state(
+        &mut self,
+        runtime_state: &RuntimeState,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        self.log_state(runtime_state);
+        Ok(())
+    }
+
+    fn apply_startup_state(
+        &mut self,
+        runtime_state: &RuntimeState,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        self.log_state(runtime_state);
+        Ok(())
+    }
+
+    fn apply_temperature_gamma(
+        &mut self,
+        _temperature: Kelvin,
+        _gamma: GammaPercent,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "Mock"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Self::CAPABILITIES
+    }
+}
@@ -1,22 +1,47 @@
 //! Backend abstraction for color temperature and gamma control.
 //!
-//! The `ColorTemperatureBackend` trait provides a common interface over three backends:
+//! The `ColorTemperatureBackend` trait provides a common interface over seven backends:
 //! the Hyprland native CTM backend (hyprland-ctm-control-v1), the hyprsunset-process
-//! backend, and the generic Wayland backend (wlr-gamma-control-unstable-v1, used by many
-//! compositors). The backend is taken from config or auto-detected with priority
-//! Hyprland -> Wayland -> error.
+//! backend, the generic Wayland backend (wlr-gamma-control-unstable-v1, used by many
+//! compositors), the X11 backend (XRandR CRTC gamma ramps), the DRM backend (CRTC gamma
+//! LUTs on `/dev/dri`, for sessions without a display server), the KDE backend (KWin's
+//! Night Light over D-Bus) and the GNOME backend (Mutter's `SetCrtcGamma` over D-Bus).
+//! A mock backend that applies nothing stands in for them with `backend = "none"` and
+//! `--dry-run`.
+//! The backend is taken from config or auto-detected with priority Hyprland -> KDE
+//! Plasma -> GNOME -> Wayland -> X11 -> error. DRM is only used when configured, since
+//! opening the cards from the wrong session fails.
 
 use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeSet;
 use std::sync::atomic::AtomicBool;
+use std::time::Duration;
 
 use crate::common::error::Silent;
+use crate::common::units::{GammaPercent, Kelvin};
 use crate::config::{Backend, Config};
 use crate::core::runtime_state::RuntimeState;
 
+pub mod drm;
+pub mod failover;
+pub mod focus;
 pub mod gamma;
+pub mod gnome;
+pub mod hdr;
 pub mod hyprland;
 pub mod hyprsunset;
+pub mod kde;
+pub mod mock;
+pub mod niri;
+pub mod probe;
+pub mod profiled;
+pub mod quirks;
+pub mod rediscover;
+pub mod sway;
 pub mod wayland;
+pub mod worker;
+pub mod x11;
 
 /// Wayland compositors sunsetr recognizes for detection and process parenting.
 #[derive(Debug, Clone, PartialEq)]
@@ -24,21 +49,125 @@ pub enum Compositor {
     Hyprland,
     Niri,
     Sway,
+    River,
+    Labwc,
     Other(String),
 }
 
+impl Compositor {
+    /// Map an `XDG_CURRENT_DESKTOP` value, which may list several names separated by colons.
+    fn from_desktop(desktop: &str) -> Self {
+        desktop
+            .split(':')
+            .find_map(|name| match name.to_lowercase().as_str() {
+                "niri" => Some(Compositor::Niri),
+                "sway" => Some(Compositor::Sway),
+                "hyprland" => Some(Compositor::Hyprland),
+                "river" => Some(Compositor::River),
+                "labwc" => Some(Compositor::Labwc),
+                _ => None,
+            })
+            .unwrap_or_else(|| Compositor::Other(desktop.to_string()))
+    }
+}
+
 impl std::fmt::Display for Compositor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Compositor::Hyprland => write!(f, "hyprland"),
             Compositor::Niri => write!(f, "niri"),
             Compositor::Sway => write!(f, "sway"),
+            Compositor::River => write!(f, "river"),
+            Compositor::Labwc => write!(f, "labwc"),
             Compositor::Other(name) => write!(f, "{name}"),
         }
     }
 }
 
+/// An output connected or disconnected at runtime, identified by its connector name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputEvent {
+    Added(String),
+    Removed(String),
+}
+
+impl OutputEvent {
+    /// The removals and additions that turn the `before` output names into `after`.
+    pub fn between(before: &BTreeSet<String>, after: &BTreeSet<String>) -> Vec<OutputEvent> {
+        let removed = before.difference(after).cloned().map(OutputEvent::Removed);
+        let added = after.difference(before).cloned().map(OutputEvent::Added);
+        removed.chain(added).collect()
+    }
+}
+
+/// Returned from [`ColorTemperatureBackend::poll_hotplug`] when the compositor has closed
+/// the connection, usually because it exited or restarted. The worker reacts by waiting
+/// for the compositor to come back and recreating the backend.
+#[derive(Debug)]
+pub struct ConnectionLost;
+
+impl std::fmt::Display for ConnectionLost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("compositor closed the connection")
+    }
+}
+
+impl std::error::Error for ConnectionLost {}
+
+/// A compositor's own IPC event stream, followed alongside Wayland events so output
+/// changes are acted on as soon as the compositor reports them.
+pub trait CompositorEvents {
+    /// Whether the compositor reported an output change since the last call.
+    fn take_outputs_changed(&self) -> bool;
+
+    /// The focused window, if focus moved since the last call.
+    fn take_focus(&self) -> Option<focus::Focus> {
+        None
+    }
+
+    /// Names of the outputs the compositor currently drives in HDR, queried on demand.
+    /// Empty when the compositor doesn't report HDR.
+    fn hdr_outputs(&self) -> BTreeSet<String> {
+        BTreeSet::new()
+    }
+}
+
+/// Subscribe to the running compositor's IPC events, if it offers any that sunsetr follows.
+pub fn connect_compositor_events(debug_enabled: bool) -> Option<Box<dyn CompositorEvents>> {
+    if let Some(events) = niri::NiriEvents::connect(debug_enabled) {
+        return Some(Box::new(events));
+    }
+    if let Some(events) = sway::SwayEvents::connect(debug_enabled) {
+        return Some(Box::new(events));
+    }
+    if let Some(events) = hyprland::ipc::HyprlandEvents::connect(debug_enabled) {
+        return Some(Box::new(events));
+    }
+    None
+}
+
+/// What a backend can do, so callers decide from this rather than from which backend it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Shows sunsetr's smooth transitions step by step. Backends without it apply each
+    /// state at once, usually because the compositor animates changes itself.
+    pub smoothing: bool,
+    /// Can give outputs different values, for presentation mode, neutral apps and HDR.
+    pub per_output: bool,
+    /// Ends up in the display's gamma ramps, rather than in a color matrix or another
+    /// program's own night light.
+    pub hardware: bool,
+    /// Brings outputs connected while running up to the current values.
+    pub hotplug: bool,
+    /// The last values written stay on screen after sunsetr exits, so the shutdown values
+    /// are written before exiting even when the smooth shutdown is skipped.
+    pub keeps_gamma_on_exit: bool,
+}
+
 /// Common interface implemented by each color temperature and gamma backend.
+///
+/// Calls are blocking. At runtime each backend sits behind a [`worker::BackendWorker`],
+/// which calls these methods on a dedicated thread so the main loop never waits on them.
 pub trait ColorTemperatureBackend {
     /// Apply the color temperature and gamma for a state, interpolating during transitions.
     fn apply_transition_state(
@@ -59,19 +188,65 @@ pub trait ColorTemperatureBackend {
     /// bypassing state-based application for fine-grained control during animations.
     fn apply_temperature_gamma(
         &mut self,
-        temperature: u32,
-        gamma: f64,
+        temperature: Kelvin,
+        gamma: GammaPercent,
         running: &AtomicBool,
     ) -> Result<()>;
 
     fn backend_name(&self) -> &'static str;
 
+    /// What this backend can do. Each backend declares its own, so a new one gets the
+    /// behavior that fits it without callers checking its name.
+    fn capabilities(&self) -> Capabilities;
+
     /// Perform a quick, non-blocking hotplug poll and apply if needed.
-    /// Default no-op. Backends that support dynamic outputs can override.
+    /// Default no-op. Backends that support dynamic outputs can override. The worker
+    /// calls this between commands. Returns [`ConnectionLost`] once the compositor is gone.
     fn poll_hotplug(&mut self) -> Result<()> {
         Ok(())
     }
 
+    /// Drain output hotplug events seen since the last call. Outputs present at startup are
+    /// not reported. The default reports nothing, for backends that don't track outputs.
+    fn take_output_events(&mut self) -> Vec<OutputEvent> {
+        Vec::new()
+    }
+
+    /// Names of the connected outputs, for presets with `activate_when_outputs`. `None` for
+    /// backends that don't track outputs, which is the default.
+    fn output_names(&self) -> Option<BTreeSet<String>> {
+        None
+    }
+
+    /// Time between frames on the fastest output, when the backend knows the outputs' modes.
+    /// Smooth transitions step in whole frames of it. The default reports nothing.
+    fn refresh_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Block until the compositor reports drawing a frame with the values last applied, for
+    /// at most `timeout`. Returns false right away when the backend gets no such feedback,
+    /// and once `timeout` passes without it, leaving the caller to time the step itself.
+    /// The default gets no feedback.
+    fn wait_for_frame(&mut self, timeout: Duration) -> bool {
+        let _ = timeout;
+        false
+    }
+
+    /// Pick up backend-specific settings from a reloaded config. The default is a no-op.
+    /// Schedule-driven values are applied separately through the apply methods.
+    fn reload_config(&mut self, config: &Config) -> Result<()> {
+        let _ = config;
+        Ok(())
+    }
+
+    /// Rebuild what the backend holds for each output, such as gamma controls and tables,
+    /// after re-reading the outputs, for `sunsetr refresh`. The values are applied again
+    /// separately. The default is a no-op, for backends that keep nothing per output.
+    fn refresh(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     /// Release backend resources at shutdown. The default is a no-op. Backends override it
     /// to perform specific cleanup such as stopping a managed process.
     fn cleanup(self: Box<Self>, debug_enabled: bool) {
@@ -79,94 +254,183 @@ pub trait ColorTemperatureBackend {
     }
 }
 
-/// Resolve the backend from the config's explicit choice or, for `auto`, from the
-/// environment. Errors when the session is not Wayland or the choice is unavailable.
-pub fn detect_backend(config: &Config) -> Result<BackendType> {
-    match config.backend {
-        Backend::Auto => {
-            if std::env::var("WAYLAND_DISPLAY").is_err() {
-                log_pipe!();
-                log_error!("sunsetr requires a Wayland session. WAYLAND_DISPLAY is not set.");
-                log_indented!("Please ensure you're running on a Wayland compositor.");
-                log_end!();
-                return Err(Silent.into());
-            }
+/// The environment variables sunsetr reads to understand the session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Session {
+    pub wayland_display: bool,
+    pub x11_display: bool,
+    pub hyprland_instance: bool,
+    pub niri_socket: bool,
+    pub swaysock: bool,
+    pub xdg_current_desktop: Option<String>,
+    /// The outer display when the compositor is nested inside another one.
+    pub nested_in: Option<String>,
+}
 
-            if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() {
-                Ok(BackendType::Hyprland)
-            } else {
-                Ok(BackendType::Wayland)
-            }
+impl Session {
+    pub fn from_env() -> Self {
+        Self {
+            wayland_display: std::env::var_os("WAYLAND_DISPLAY").is_some(),
+            x11_display: std::env::var_os("DISPLAY").is_some(),
+            hyprland_instance: std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some(),
+            niri_socket: std::env::var_os("NIRI_SOCKET").is_some(),
+            swaysock: std::env::var_os("SWAYSOCK").is_some(),
+            xdg_current_desktop: std::env::var("XDG_CURRENT_DESKTOP").ok(),
+            nested_in: crate::io::nested::parent_display().map(str::to_string),
         }
-        Backend::Wayland => {
-            if std::env::var("WAYLAND_DISPLAY").is_err() {
-                log_pipe!();
-                log_error!(
-                    "Configuration specifies backend=\"wayland\" but WAYLAND_DISPLAY is not set."
-                );
-                log_indented!("Are you running on Wayland?");
-                log_end!();
-                return Err(Silent.into());
-            }
-            Ok(BackendType::Wayland)
-        }
-        Backend::Hyprland => {
-            if std::env::var("WAYLAND_DISPLAY").is_err() {
-                log_pipe!();
-                log_error!(
-                    "Configuration specifies backend=\"hyprland\" but WAYLAND_DISPLAY is not set."
-                );
-                log_indented!("Are you running on Wayland?");
-                log_end!();
-                return Err(Silent.into());
-            }
+    }
 
-            if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_err() {
-                log_pipe!();
-                log_error!(
-                    "Configuration specifies backend=\"hyprland\" but you're not running on Hyprland."
-                );
-                log_block_start!("To fix this, either:");
-                log_indented!(
-                    "• Switch to automatic detection: set backend=\"auto\" in sunsetr.toml"
-                );
-                log_indented!("• Use the Wayland backend: set backend=\"wayland\" in sunsetr.toml");
-                log_indented!("• Run sunsetr on Hyprland instead of your current compositor");
-                log_end!();
-                return Err(Silent.into());
-            }
+    /// Whether the desktop is KDE Plasma, whose KWin offers Night Light over D-Bus
+    /// instead of a gamma protocol.
+    pub fn kde_plasma(&self) -> bool {
+        self.lists_desktop("kde")
+    }
+
+    /// Whether the desktop is GNOME, whose Mutter takes gamma ramps over D-Bus instead of
+    /// a gamma protocol.
+    pub fn gnome(&self) -> bool {
+        self.lists_desktop("gnome")
+    }
 
-            Ok(BackendType::Hyprland)
+    /// Whether `XDG_CURRENT_DESKTOP`, a colon-separated list, names `desktop`.
+    fn lists_desktop(&self, desktop: &str) -> bool {
+        self.xdg_current_desktop.as_deref().is_some_and(|desktops| {
+            desktops
+                .split(':')
+                .any(|name| name.eq_ignore_ascii_case(desktop))
+        })
+    }
+
+    /// The compositor IPC that [`connect_compositor_events`] will try first, if any.
+    pub fn compositor_ipc(&self) -> Option<&'static str> {
+        if self.niri_socket {
+            Some("niri")
+        } else if self.swaysock {
+            Some("sway")
+        } else {
+            None
         }
-        Backend::Hyprsunset => {
-            if std::env::var("WAYLAND_DISPLAY").is_err() {
-                log_pipe!();
-                log_error!(
-                    "Configuration specifies backend=\"hyprsunset\" but WAYLAND_DISPLAY is not set."
-                );
-                log_indented!("Are you running on Wayland?");
-                log_end!();
-                return Err(Silent.into());
-            }
+    }
+}
 
-            if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_err() {
-                log_pipe!();
-                log_error!(
-                    "Configuration specifies backend=\"hyprsunset\" but you're not running on Hyprland."
-                );
-                log_block_start!("To fix this, either:");
-                log_indented!(
-                    "• Switch to automatic detection: set backend=\"auto\" in sunsetr.toml"
-                );
-                log_indented!("• Use the Wayland backend: set backend=\"wayland\" in sunsetr.toml");
-                log_indented!("• Run sunsetr on Hyprland instead of your current compositor");
-                log_end!();
-                return Err(Silent.into());
+/// The backend a configured choice resolves to in `session`, or `None` when it can't run.
+///
+/// A Wayland session also sets `DISPLAY` for XWayland, but ramps set through XWayland
+/// never reach the real outputs, so X11 is only used without a Wayland display. DRM is
+/// only used without either, as a display server holds DRM master. KDE and GNOME work
+/// with KWin and Mutter under both, and `auto` picks them on Wayland, where those
+/// compositors offer no gamma protocol.
+pub fn resolve_backend(choice: Backend, session: &Session) -> Option<BackendType> {
+    if choice == Backend::None {
+        return Some(BackendType::Mock);
+    }
+    if !session.wayland_display {
+        return match choice {
+            Backend::Auto | Backend::X11 => session.x11_display.then_some(BackendType::X11),
+            Backend::Drm => (!session.x11_display).then_some(BackendType::Drm),
+            Backend::Kde => {
+                (session.x11_display && session.kde_plasma()).then_some(BackendType::Kde)
             }
+            Backend::Gnome => {
+                (session.x11_display && session.gnome()).then_some(BackendType::Gnome)
+            }
+            _ => None,
+        };
+    }
+    match choice {
+        Backend::Auto if session.hyprland_instance => Some(BackendType::Hyprland),
+        Backend::Auto if session.kde_plasma() => Some(BackendType::Kde),
+        Backend::Kde => session.kde_plasma().then_some(BackendType::Kde),
+        Backend::Auto if session.gnome() => Some(BackendType::Gnome),
+        Backend::Gnome => session.gnome().then_some(BackendType::Gnome),
+        Backend::Auto | Backend::Wayland => Some(BackendType::Wayland),
+        Backend::Hyprland => session.hyprland_instance.then_some(BackendType::Hyprland),
+        Backend::Hyprsunset => session.hyprland_instance.then_some(BackendType::Hyprsunset),
+        Backend::X11 | Backend::Drm | Backend::None => None,
+    }
+}
 
-            Ok(BackendType::Hyprsunset)
+/// Resolve the backend from the config's explicit choice or, for `auto`, from the
+/// environment and the protocols the compositor advertises. Errors when there is no
+/// display to drive or the choice is unavailable in this session.
+pub fn detect_backend(config: &Config) -> Result<BackendType> {
+    let session = Session::from_env();
+    if let Some(backend_type) = resolve_backend(config.backend, &session) {
+        // Hyprland releases without CTM support still offer wlr-gamma-control
+        if config.backend == Backend::Auto
+            && backend_type == BackendType::Hyprland
+            && let Ok(protocols) = probe::probe_protocols()
+            && let Some(BackendType::Wayland) =
+                probe::select_backend(Backend::Auto, &session, &protocols)
+        {
+            log_pipe!();
+            log_warning!(
+                "Hyprland does not advertise hyprland-ctm-control-v1, using the Wayland backend"
+            );
+            return Ok(BackendType::Wayland);
         }
+        return Ok(backend_type);
     }
+
+    log_pipe!();
+    match config.backend {
+        Backend::Auto => {
+            log_error!(
+                "sunsetr requires a Wayland or X11 session. Neither WAYLAND_DISPLAY nor DISPLAY is set."
+            );
+            log_indented!("Please ensure you're running on a Wayland compositor or an X server.");
+            log_indented!("On a bare TTY, set backend=\"drm\" in sunsetr.toml.");
+        }
+        Backend::Drm => {
+            log_error!("Configuration specifies backend=\"drm\" but a display server is running.");
+            log_indented!("It holds DRM master, so sunsetr cannot set gamma on the cards.");
+            log_block_start!("To fix this, set backend=\"auto\" in sunsetr.toml, which picks");
+            log_indented!("the right backend for the display server.");
+        }
+        Backend::X11 if session.wayland_display => {
+            log_error!("Configuration specifies backend=\"x11\" but this is a Wayland session.");
+            log_indented!("Gamma ramps set through XWayland do not reach your outputs.");
+            log_block_start!("To fix this, set backend=\"auto\" in sunsetr.toml, which picks");
+            log_indented!("the Wayland backend here and the X11 backend under X11.");
+        }
+        Backend::X11 => {
+            log_error!("Configuration specifies backend=\"x11\" but DISPLAY is not set.");
+            log_indented!("Are you running on X11?");
+        }
+        Backend::Kde => {
+            log_error!(
+                "Configuration specifies backend=\"kde\" but this is not a KDE Plasma session."
+            );
+            log_indented!("XDG_CURRENT_DESKTOP does not list KDE.");
+            log_block_start!("To fix this, set backend=\"auto\" in sunsetr.toml, which picks");
+            log_indented!("the right backend for the display server.");
+        }
+        Backend::Gnome => {
+            log_error!(
+                "Configuration specifies backend=\"gnome\" but this is not a GNOME session."
+            );
+            log_indented!("XDG_CURRENT_DESKTOP does not list GNOME.");
+            log_block_start!("To fix this, set backend=\"auto\" in sunsetr.toml, which picks");
+            log_indented!("the right backend for the display server.");
+        }
+        choice if !session.wayland_display => {
+            log_error!(
+                "Configuration specifies backend=\"{choice}\" but WAYLAND_DISPLAY is not set."
+            );
+            log_indented!("Are you running on Wayland?");
+        }
+        choice => {
+            log_error!(
+                "Configuration specifies backend=\"{choice}\" but you're not running on Hyprland."
+            );
+            log_block_start!("To fix this, either:");
+            log_indented!("• Switch to automatic detection: set backend=\"auto\" in sunsetr.toml");
+            log_indented!("• Use the Wayland backend: set backend=\"wayland\" in sunsetr.toml");
+            log_indented!("• Run sunsetr on Hyprland instead of your current compositor");
+        }
+    }
+    log_end!();
+    Err(Silent.into())
 }
 
 /// Detect the current Wayland compositor.
@@ -182,12 +446,14 @@ pub fn detect_compositor() -> Compositor {
         return Compositor::Sway;
     }
 
+    if std::env::var("LABWC_PID").is_ok() {
+        return Compositor::Labwc;
+    }
+
     if let Ok(desktop) = std::env::var("XDG_CURRENT_DESKTOP") {
-        match desktop.to_lowercase().as_str() {
-            "niri" => return Compositor::Niri,
-            "sway" => return Compositor::Sway,
-            "hyprland" => return Compositor::Hyprland,
-            _ => {}
+        let compositor = Compositor::from_desktop(&desktop);
+        if !matches!(compositor, Compositor::Other(_)) {
+            return compositor;
         }
     }
 
@@ -214,7 +480,7 @@ pub fn create_backend(
     config: &Config,
     debug_enabled: bool,
     geo_times: Option<&crate::geo::times::GeoTimes>,
-    initial_values: Option<(u32, f64)>, // Optional pre-calculated (temp, gamma) for optimization
+    initial_values: Option<(Kelvin, GammaPercent)>, // Optional pre-calculated (temp, gamma) for optimization
 ) -> Result<Box<dyn ColorTemperatureBackend>> {
     match backend_type {
         BackendType::Hyprland => Ok(
@@ -226,8 +492,9 @@ pub fn create_backend(
                 Ok(
                     Box::new(hyprsunset::HyprsunsetBackend::new_with_initial_values(
                         debug_enabled,
-                        temp,
-                        gamma,
+                        config.hyprsunset_mode,
+                        temp.get(),
+                        gamma.get(),
                     )?) as Box<dyn ColorTemperatureBackend>,
                 )
             } else {
@@ -237,11 +504,26 @@ pub fn create_backend(
                     geo_times,
                 )?) as Box<dyn ColorTemperatureBackend>)
             }
+            .and_then(|mut backend| {
+                // Starts from the scheduled values, presentation mode is picked up like a reload
+                backend.reload_config(config)?;
+                Ok(backend)
+            })
         }
         BackendType::Wayland => Ok(
             Box::new(wayland::WaylandBackend::new(config, debug_enabled)?)
                 as Box<dyn ColorTemperatureBackend>,
         ),
+        BackendType::X11 => Ok(Box::new(x11::X11Backend::new(config, debug_enabled)?)
+            as Box<dyn ColorTemperatureBackend>),
+        BackendType::Drm => Ok(Box::new(drm::DrmBackend::new(config, debug_enabled)?)
+            as Box<dyn ColorTemperatureBackend>),
+        BackendType::Kde => {
+            Ok(Box::new(kde::KdeBackend::new(debug_enabled)?) as Box<dyn ColorTemperatureBackend>)
+        }
+        BackendType::Gnome => Ok(Box::new(gnome::GnomeBackend::new(config, debug_enabled)?)
+            as Box<dyn ColorTemperatureBackend>),
+        BackendType::Mock => Ok(Box::new(mock::MockBackend::new(debug_enabled))),
     }
 }
 
@@ -254,6 +536,16 @@ pub enum BackendType {
     Hyprsunset,
     /// Generic Wayland compositor using wlr-gamma-control-unstable-v1 protocol
     Wayland,
+    /// X server using XRandR per-CRTC gamma ramps
+    X11,
+    /// DRM/KMS device using CRTC gamma LUTs, for sessions without a display server
+    Drm,
+    /// KDE Plasma's KWin Night Light over D-Bus
+    Kde,
+    /// GNOME's Mutter CRTC gamma over D-Bus
+    Gnome,
+    /// Applies nothing, for `backend = "none"` and `--dry-run`
+    Mock,
 }
 
 impl BackendType {
@@ -262,6 +554,181 @@ impl BackendType {
             BackendType::Hyprland => "Hyprland",
             BackendType::Hyprsunset => "Hyprsunset",
             BackendType::Wayland => "Wayland",
+            BackendType::X11 => "X11",
+            BackendType::Drm => "DRM",
+            BackendType::Kde => "KDE",
+            BackendType::Gnome => "GNOME",
+            BackendType::Mock => "Mock",
+        }
+    }
+
+    /// The capabilities of this backend, for callers that haven't created it, such as
+    /// `sunsetr stop` sizing its wait for the smooth shutdown.
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            BackendType::Hyprland => hyprland::HyprlandBackend::CAPABILITIES,
+            BackendType::Hyprsunset => hyprsunset::HyprsunsetBackend::CAPABILITIES,
+            BackendType::Wayland => wayland::WaylandBackend::CAPABILITIES,
+            BackendType::X11 => x11::X11Backend::CAPABILITIES,
+            BackendType::Drm => drm::DrmBackend::CAPABILITIES,
+            BackendType::Kde => kde::KdeBackend::CAPABILITIES,
+            BackendType::Gnome => gnome::GnomeBackend::CAPABILITIES,
+            BackendType::Mock => mock::MockBackend::CAPABILITIES,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backend_choice_resolves_against_the_session() {
+        let wayland = Session {
+            wayland_display: true,
+            ..Session::default()
+        };
+        let hyprland = Session {
+            hyprland_instance: true,
+            ..wayland.clone()
+        };
+
+        assert_eq!(
+            resolve_backend(Backend::Auto, &hyprland),
+            Some(BackendType::Hyprland)
+        );
+        assert_eq!(
+            resolve_backend(Backend::Auto, &wayland),
+            Some(BackendType::Wayland)
+        );
+        assert_eq!(
+            resolve_backend(Backend::Hyprsunset, &hyprland),
+            Some(BackendType::Hyprsunset)
+        );
+        assert_eq!(resolve_backend(Backend::Hyprland, &wayland), None);
+        assert_eq!(resolve_backend(Backend::Wayland, &Session::default()), None);
+    }
+
+    #[test]
+    fn x11_is_used_only_without_a_wayland_display() {
+        let x11 = Session {
+            x11_display: true,
+            ..Session::default()
+        };
+        let xwayland = Session {
+            wayland_display: true,
+            ..x11.clone()
+        };
+
+        assert_eq!(resolve_backend(Backend::Auto, &x11), Some(BackendType::X11));
+        assert_eq!(resolve_backend(Backend::X11, &x11), Some(BackendType::X11));
+        assert_eq!(resolve_backend(Backend::Wayland, &x11), None);
+        assert_eq!(resolve_backend(Backend::Hyprland, &x11), None);
+        assert_eq!(
+            resolve_backend(Backend::Auto, &xwayland),
+            Some(BackendType::Wayland)
+        );
+        assert_eq!(resolve_backend(Backend::X11, &xwayland), None);
+        assert_eq!(resolve_backend(Backend::Auto, &Session::default()), None);
+    }
+
+    #[test]
+    fn drm_is_used_only_without_a_display_server() {
+        let tty = Session::default();
+        let x11 = Session {
+            x11_display: true,
+            ..Session::default()
+        };
+        let wayland = Session {
+            wayland_display: true,
+            ..Session::default()
+        };
+
+        assert_eq!(resolve_backend(Backend::Drm, &tty), Some(BackendType::Drm));
+        assert_eq!(resolve_backend(Backend::Drm, &x11), None);
+        assert_eq!(resolve_backend(Backend::Drm, &wayland), None);
+        // Never picked automatically
+        assert_eq!(resolve_backend(Backend::Auto, &tty), None);
+    }
+
+    #[test]
+    fn kde_is_used_only_on_plasma() {
+        let plasma = Session {
+            wayland_display: true,
+            xdg_current_desktop: Some("KDE".to_string()),
+            ..Session::default()
+        };
+        let plasma_x11 = Session {
+            wayland_display: false,
+            x11_display: true,
+            ..plasma.clone()
+        };
+        let sway = Session {
+            wayland_display: true,
+            xdg_current_desktop: Some("sway".to_string()),
+            ..Session::default()
+        };
+
+        assert_eq!(
+            resolve_backend(Backend::Auto, &plasma),
+            Some(BackendType::Kde)
+        );
+        assert_eq!(
+            resolve_backend(Backend::Kde, &plasma),
+            Some(BackendType::Kde)
+        );
+        assert_eq!(
+            resolve_backend(Backend::Kde, &plasma_x11),
+            Some(BackendType::Kde)
+        );
+        // Plasma's X11 session keeps the plain ramps unless KDE is asked for
+        assert_eq!(
+            resolve_backend(Backend::Auto, &plasma_x11),
+            Some(BackendType::X11)
+        );
+        assert_eq!(resolve_backend(Backend::Kde, &sway), None);
+        assert_eq!(resolve_backend(Backend::Kde, &Session::default()), None);
+    }
+
+    #[test]
+    fn gnome_is_used_only_on_gnome() {
+        let gnome = Session {
+            wayland_display: true,
+            xdg_current_desktop: Some("ubuntu:GNOME".to_string()),
+            ..Session::default()
+        };
+        let plasma = Session {
+            wayland_display: true,
+            xdg_current_desktop: Some("KDE".to_string()),
+            ..Session::default()
+        };
+
+        assert_eq!(
+            resolve_backend(Backend::Auto, &gnome),
+            Some(BackendType::Gnome)
+        );
+        assert_eq!(
+            resolve_backend(Backend::Gnome, &gnome),
+            Some(BackendType::Gnome)
+        );
+        assert_eq!(resolve_backend(Backend::Gnome, &plasma), None);
+        assert_eq!(resolve_backend(Backend::Gnome, &Session::default()), None);
+    }
+
+    #[test]
+    fn none_runs_without_a_display() {
+        assert_eq!(
+            resolve_backend(Backend::None, &Session::default()),
+            Some(BackendType::Mock)
+        );
+        let hyprland = Session {
+            wayland_display: true,
+            hyprland_instance: true,
+            ..Session::default()
+        };
+        assert_eq!(
+            resolve_backend(Backend::None, &hyprland),
+            Some(BackendType::Mock)
+        );
+    }
+}
@@ -0,0 +1,386 @@
+//! niri IPC integration for the Wayland backend.
+//!
+//! niri takes gamma through wlr-gamma-control like any other compositor, but it also
+//! publishes an event stream on `$NIRI_SOCKET`. When that socket is available the Wayland
+//! backend subscribes to it and reapplies the current values to every output as soon as
+//! niri reports an output change, rather than waiting for the change to show up as Wayland
+//! globals.
+//!
+//! The stream has no dedicated output events. Output changes are inferred from the set of
+//! outputs that workspaces live on, which niri resends whenever an output is connected,
+//! disconnected or reconfigured, and from config reloads that may have changed output
+//! settings. niri does not report fullscreen state over IPC, so that is not tracked.
+//!
+//! Window focus is followed for `neutral_apps`: the stream reports each window's app id and
+//! workspace, and the workspace's output locates the focused window.
+
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use super::CompositorEvents;
+use super::focus::{Focus, FocusSlot};
+
+/// Subscription to niri's event stream, read on a background thread.
+pub struct NiriEvents {
+    outputs_changed: Arc<AtomicBool>,
+    focus: Arc<FocusSlot>,
+    stream: UnixStream,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl NiriEvents {
+    /// Subscribe through `$NIRI_SOCKET`. Returns `None` outside niri or if the socket
+    /// refuses the subscription, in which case hotplug falls back to Wayland events alone.
+    pub fn connect(debug_enabled: bool) -> Option<Self> {
+        let socket = std::env::var_os("NIRI_SOCKET")?;
+        match Self::connect_to(Path::new(&socket)) {
+            Ok(events) => {
+                if debug_enabled {
+                    log_debug!("Subscribed to niri IPC for output change events");
+                }
+                Some(events)
+            }
+            Err(e) => {
+                if debug_enabled {
+                    log_debug!("niri IPC unavailable, relying on Wayland events: {e}");
+                }
+                None
+            }
+        }
+    }
+
+    /// Subscribe to the event stream on the socket at `path`.
+    fn connect_to(path: &Path) -> std::io::Result<Self> {
+        let mut stream = UnixStream::connect(path)?;
+        stream.write_all(b"\"EventStream\"\n")?;
+
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut reply = String::new();
+        reader.read_line(&mut reply)?;
+        if serde_json::from_str::<Value>(&reply)
+            .ok()
+            .and_then(|v| v.get("Ok").cloned())
+            != Some(Value::String("Handled".to_string()))
+        {
+            return Err(std::io::Error::other(format!(
+                "unexpected reply to EventStream: {}",
+                reply.trim()
+            )));
+        }
+
+        let outputs_changed = Arc::new(AtomicBool::new(false));
+        let focus = Arc::new(FocusSlot::default());
+        let flag = Arc::clone(&outputs_changed);
+        let slot = Arc::clone(&focus);
+        let thread = thread::Builder::new()
+            .name("niri-events".to_string())
+            .spawn(move || {
+                let mut tracker = OutputTracker::default();
+                let mut focus_tracker = FocusTracker::default();
+                for line in reader.lines() {
+                    let Ok(line) = line else { break };
+                    if tracker.handle_line(&line) {
+                        flag.store(true, Ordering::SeqCst);
+                    }
+                    if let Some(focus) = focus_tracker.handle_line(&line) {
+                        slot.publish(focus);
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            outputs_changed,
+            focus,
+            stream,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl CompositorEvents for NiriEvents {
+    fn take_outputs_changed(&self) -> bool {
+        self.outputs_changed.swap(false, Ordering::SeqCst)
+    }
+
+    fn take_focus(&self) -> Option<Focus> {
+        self.focus.take()
+    }
+}
+
+impl Drop for NiriEvents {
+    fn drop(&mut self) {
+        // Unblocks the reader so the thread can exit
+        let _ = self.stream.shutdown(Shutdown::Both);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Decides from raw event lines whether niri's outputs changed.
+///
+/// niri opens the stream with the current state, so the first workspace list and config
+/// load only establish a baseline.
+#[derive(Default)]
+struct OutputTracker {
+    outputs: Option<BTreeSet<String>>,
+    config_seen: bool,
+}
+
+impl OutputTracker {
+    /// Feed one line of the event stream, returning true when outputs need a reapply.
+    fn handle_line(&mut self, line: &str) -> bool {
+        let Ok(event) = serde_json::from_str::<Value>(line) else {
+            return false;
+        };
+
+        if let Some(workspaces) = event
+            .get("WorkspacesChanged")
+            .and_then(|changed| changed.get("workspaces"))
+            .and_then(Value::as_array)
+        {
+            let outputs: BTreeSet<String> = workspaces
+                .iter()
+                .filter_map(|workspace| workspace.get("output")?.as_str())
+                .map(str::to_string)
+                .collect();
+            let changed = self
+                .outputs
+                .as_ref()
+                .is_some_and(|previous| *previous != outputs);
+            self.outputs = Some(outputs);
+            return changed;
+        }
+
+        if let Some(loaded) = event.get("ConfigLoaded") {
+            let first = !std::mem::replace(&mut self.config_seen, true);
+            let failed = loaded.get("failed").and_then(Value::as_bool) == Some(true);
+            return !first && !failed;
+        }
+
+        false
+    }
+}
+
+/// Follows the focused window's app id and output from raw event lines.
+#[derive(Default)]
+struct FocusTracker {
+    /// Window id to its app id and workspace id.
+    windows: HashMap<u64, (Option<String>, Option<u64>)>,
+    /// Workspace id to the output it lives on.
+    workspaces: HashMap<u64, String>,
+    focused: Option<u64>,
+    last: Option<Focus>,
+}
+
+impl FocusTracker {
+    /// Feed one line of the event stream, returning the new focus when it changed.
+    fn handle_line(&mut self, line: &str) -> Option<Focus> {
+        let event = serde_json::from_str::<Value>(line).ok()?;
+
+        if let Some(workspaces) = event
+            .pointer("/WorkspacesChanged/workspaces")
+            .and_then(Value::as_array)
+        {
+            self.workspaces = workspaces
+                .iter()
+                .filter_map(|workspace| {
+                    let id = workspace.get("id")?.as_u64()?;
+                    Some((id, workspace.get("output")?.as_str()?.to_string()))
+                })
+                .collect();
+        } else if let Some(windows) = event
+            .pointer("/WindowsChanged/windows")
+            .and_then(Value::as_array)
+        {
+            self.windows.clear();
+            self.focused = None;
+            for window in windows {
+                self.track_window(window);
+            }
+        } else if let Some(window) = event.pointer("/WindowOpenedOrChanged/window") {
+            self.track_window(window);
+        } else if let Some(id) = event.pointer("/WindowClosed/id").and_then(Value::as_u64) {
+            self.windows.remove(&id);
+            if self.focused == Some(id) {
+                self.focused = None;
+            }
+        } else if let Some(changed) = event.get("WindowFocusChanged") {
+            self.focused = changed.get("id").and_then(Value::as_u64);
+        } else {
+            return None;
+        }
+
+        let focus = self.current();
+        if self.last.as_ref() == Some(&focus) {
+            return None;
+        }
+        self.last = Some(focus.clone());
+        Some(focus)
+    }
+
+    fn track_window(&mut self, window: &Value) {
+        let Some(id) = window.get("id").and_then(Value::as_u64) else {
+            return;
+        };
+        let app_id = window
+            .get("app_id")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let workspace = window.get("workspace_id").and_then(Value::as_u64);
+        self.windows.insert(id, (app_id, workspace));
+        if window.get("is_focused").and_then(Value::as_bool) == Some(true) {
+            self.focused = Some(id);
+        }
+    }
+
+    fn current(&self) -> Focus {
+        let Some((app_id, workspace)) = self.focused.and_then(|id| self.windows.get(&id)) else {
+            return Focus::default();
+        };
+        Focus {
+            app_id: app_id.clone(),
+            output: workspace.and_then(|id| self.workspaces.get(&id).cloned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::time::{Duration, Instant};
+
+    fn workspaces(outputs: &[&str]) -> String {
+        let workspaces: Vec<_> = outputs
+            .iter()
+            .enumerate()
+            .map(|(id, output)| {
+                serde_json::json!({
+                    "id": id + 1,
+                    "idx": 1,
+                    "name": null,
+                    "output": output,
+                    "is_active": true,
+                    "is_focused": id == 0,
+                    "active_window_id": null
+                })
+            })
+            .collect();
+        serde_json::json!({ "WorkspacesChanged": { "workspaces": workspaces } }).to_string()
+    }
+
+    #[test]
+    fn workspace_outputs_are_compared_against_the_baseline() {
+        let mut tracker = OutputTracker::default();
+        assert!(!tracker.handle_line(&workspaces(&["DP-1"])));
+        // A new workspace on the same output is not an output change
+        assert!(!tracker.handle_line(&workspaces(&["DP-1", "DP-1"])));
+        assert!(tracker.handle_line(&workspaces(&["DP-1", "HDMI-A-1"])));
+        assert!(tracker.handle_line(&workspaces(&["HDMI-A-1"])));
+    }
+
+    #[test]
+    fn config_reloads_after_the_first_trigger_a_reapply() {
+        let mut tracker = OutputTracker::default();
+        assert!(!tracker.handle_line(r#"{"ConfigLoaded":{"failed":false}}"#));
+        assert!(tracker.handle_line(r#"{"ConfigLoaded":{"failed":false}}"#));
+        assert!(!tracker.handle_line(r#"{"ConfigLoaded":{"failed":true}}"#));
+    }
+
+    #[test]
+    fn focus_is_located_through_the_window_workspace() {
+        let mut tracker = FocusTracker::default();
+        assert_eq!(
+            tracker.handle_line(r#"{"WorkspacesChanged":{"workspaces":[{"id":1,"output":"DP-1"},{"id":2,"output":"HDMI-A-1"}]}}"#),
+            Some(Focus::default())
+        );
+
+        let focus = tracker
+            .handle_line(r#"{"WindowsChanged":{"windows":[{"id":7,"app_id":"gimp","workspace_id":2,"is_focused":true},{"id":8,"app_id":"foot","workspace_id":1,"is_focused":false}]}}"#)
+            .unwrap();
+        assert_eq!(focus.app_id.as_deref(), Some("gimp"));
+        assert_eq!(focus.output.as_deref(), Some("HDMI-A-1"));
+
+        let focus = tracker
+            .handle_line(r#"{"WindowFocusChanged":{"id":8}}"#)
+            .unwrap();
+        assert_eq!(focus.app_id.as_deref(), Some("foot"));
+        assert_eq!(focus.output.as_deref(), Some("DP-1"));
+
+        // A title change on the focused window is not a focus change
+        assert_eq!(
+            tracker.handle_line(r#"{"WindowOpenedOrChanged":{"window":{"id":8,"app_id":"foot","workspace_id":1,"is_focused":true}}}"#),
+            None
+        );
+        assert_eq!(
+            tracker.handle_line(r#"{"WindowClosed":{"id":8}}"#),
+            Some(Focus::default())
+        );
+    }
+
+    #[test]
+    fn unrelated_and_malformed_lines_are_ignored() {
+        let mut tracker = OutputTracker::default();
+        assert!(!tracker.handle_line(r#"{"WindowFocusChanged":{"id":12}}"#));
+        assert!(!tracker.handle_line("not json"));
+    }
+
+    #[test]
+    fn subscription_reports_output_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            assert_eq!(request, "\"EventStream\"\n");
+
+            let mut writer = stream;
+            writeln!(writer, r#"{{"Ok":"Handled"}}"#).unwrap();
+            writeln!(writer, "{}", workspaces(&["DP-1"])).unwrap();
+            writeln!(writer, "{}", workspaces(&["DP-1", "DP-2"])).unwrap();
+            writer
+        });
+
+        let events = NiriEvents::connect_to(&path).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !events.take_outputs_changed() {
+            assert!(Instant::now() < deadline, "output change was not reported");
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(!events.take_outputs_changed());
+
+        drop(events);
+        drop(server.join().unwrap());
+    }
+
+    #[test]
+    fn rejected_subscription_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            BufReader::new(stream.try_clone().unwrap())
+                .read_line(&mut request)
+                .unwrap();
+            writeln!(stream, r#"{{"Err":"unknown request"}}"#).unwrap();
+        });
+
+        assert!(NiriEvents::connect_to(&path).is_err());
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+//! Detects which gamma protocols the running compositor advertises.
+//!
+//! The environment says which compositor sunsetr runs under, but not which of its
+//! protocols are available: Hyprland only gained hyprland-ctm-control-v1 in later
+//! releases. Probing the registry settles it at startup for `backend = "auto"`, and again
+//! after the compositor restarts, when an update may have added or dropped a protocol.
+
+use anyhow::Result;
+use wayland_client::{
+    Connection, Dispatch, Proxy, QueueHandle,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::wl_registry::WlRegistry,
+};
+use wayland_protocols_wlr::gamma_control::v1::client::zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1;
+
+use super::hyprland::protocol::hyprland_ctm_control_manager_v1::HyprlandCtmControlManagerV1;
+use super::{BackendType, Session};
+use crate::config::Backend;
+
+/// Gamma protocols advertised by the compositor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Protocols {
+    pub hyprland_ctm: bool,
+    pub wlr_gamma: bool,
+}
+
+struct Probe;
+
+impl Dispatch<WlRegistry, GlobalListContents> for Probe {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+/// Connect to the compositor's display and list the gamma protocols it advertises.
+pub fn probe_protocols() -> Result<Protocols> {
+    let connection = super::rediscover::connect()?;
+    probe_connection(&connection)
+}
+
+/// List the gamma protocols advertised over an established connection.
+pub(super) fn probe_connection(connection: &Connection) -> Result<Protocols> {
+    let (globals, _queue) = registry_queue_init::<Probe>(connection)?;
+    Ok(globals.contents().with_list(|globals| {
+        let advertises = |interface: &str| globals.iter().any(|g| g.interface == interface);
+        Protocols {
+            hyprland_ctm: advertises(HyprlandCtmControlManagerV1::interface().name),
+            wlr_gamma: advertises(ZwlrGammaControlManagerV1::interface().name),
+        }
+    }))
+}
+
+/// The backend to use for `choice` given what the compositor advertises, or `None` when
+/// it offers nothing the choice can run on. Explicit choices are kept as they are; only
+/// `auto` moves between the Hyprland and Wayland backends.
+pub fn select_backend(
+    choice: Backend,
+    session: &Session,
+    protocols: &Protocols,
+) -> Option<BackendType> {
+    match choice {
+        Backend::None => Some(BackendType::Mock),
+        Backend::Auto if session.hyprland_instance && protocols.hyprland_ctm => {
+            Some(BackendType::Hyprland)
+        }
+        // KWin and Mutter are driven over D-Bus, whatever they advertise on Wayland
+        Backend::Auto | Backend::Kde if session.kde_plasma() => Some(BackendType::Kde),
+        Backend::Auto | Backend::Gnome if session.gnome() => Some(BackendType::Gnome),
+        Backend::Auto | Backend::Wayland => protocols.wlr_gamma.then_some(BackendType::Wayland),
+        Backend::Hyprland => protocols.hyprland_ctm.then_some(BackendType::Hyprland),
+        Backend::Hyprsunset => {
+            (session.hyprland_instance && protocols.hyprland_ctm).then_some(BackendType::Hyprsunset)
+        }
+        Backend::X11 | Backend::Drm | Backend::Kde | Backend::Gnome => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_follows_the_advertised_protocols() {
+        let hyprland = Session {
+            wayland_display: true,
+            hyprland_instance: true,
+            ..Session::default()
+        };
+        let old_hyprland = Protocols {
+            hyprland_ctm: false,
+            wlr_gamma: true,
+        };
+        let new_hyprland = Protocols {
+            hyprland_ctm: true,
+            ..old_hyprland
+        };
+
+        assert_eq!(
+            select_backend(Backend::Auto, &hyprland, &old_hyprland),
+            Some(BackendType::Wayland)
+        );
+        assert_eq!(
+            select_backend(Backend::Auto, &hyprland, &new_hyprland),
+            Some(BackendType::Hyprland)
+        );
+        assert_eq!(
+            select_backend(Backend::Wayland, &hyprland, &new_hyprland),
+            Some(BackendType::Wayland)
+        );
+        assert_eq!(
+            select_backend(Backend::Hyprland, &hyprland, &old_hyprland),
+            None
+        );
+        assert_eq!(
+            select_backend(Backend::Auto, &hyprland, &Protocols::default()),
+            None
+        );
+    }
+}
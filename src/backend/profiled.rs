@@ -0,0 +1,97 @@
+//! Backend wrapper that times every call for the `--profile` report.
+
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use super::{Capabilities, ColorTemperatureBackend, OutputEvent};
+use crate::common::profile::Span;
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::Config;
+use crate::core::runtime_state::RuntimeState;
+
+/// Forwards to the wrapped backend, recording each call as a profile span.
+pub struct ProfiledBackend {
+    inner: Box<dyn ColorTemperatureBackend>,
+}
+
+impl ProfiledBackend {
+    pub fn new(inner: Box<dyn ColorTemperatureBackend>) -> Self {
+        Self { inner }
+    }
+}
+
+impl ColorTemperatureBackend for ProfiledBackend {
+    fn apply_transition_state(
+        &mut self,
+        runtime_state: &RuntimeState,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        let _span = Span::start("backend apply_transition_state");
+        self.inner.apply_transition_state(runtime_state, running)
+    }
+
+    fn apply_startup_state(
+        &mut self,
+        runtime_state: &RuntimeState,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        let _span = Span::start("backend apply_startup_state");
+        self.inner.apply_startup_state(runtime_state, running)
+    }
+
+    fn apply_temperature_gamma(
+        &mut self,
+        temperature: Kelvin,
+        gamma: GammaPercent,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        let _span = Span::start("backend apply_temperature_gamma");
+        self.inner
+            .apply_temperature_gamma(temperature, gamma, running)
+    }
+
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn poll_hotplug(&mut self) -> Result<()> {
+        let _span = Span::start("backend poll_hotplug");
+        self.inner.poll_hotplug()
+    }
+
+    fn take_output_events(&mut self) -> Vec<OutputEvent> {
+        self.inner.take_output_events()
+    }
+
+    fn output_names(&self) -> Option<BTreeSet<String>> {
+        self.inner.output_names()
+    }
+
+    fn refresh_interval(&self) -> Option<Duration> {
+        self.inner.refresh_interval()
+    }
+
+    fn wait_for_frame(&mut self, timeout: Duration) -> bool {
+        self.inner.wait_for_frame(timeout)
+    }
+
+    fn reload_config(&mut self, config: &Config) -> Result<()> {
+        let _span = Span::start("backend reload_config");
+        self.inner.reload_config(config)
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        let _span = Span::start("backend refresh");
+        self.inner.refresh()
+    }
+
+    fn cleanup(self: Box<Self>, debug_enabled: bool) {
+        self.inner.cleanup(debug_enabled);
+    }
+}
@@ -0,0 +1,97 @@
+//! Per-compositor behavior that the Wayland backend has to work around.
+//!
+//! Every compositor implements wlr-gamma-control slightly differently in the corners the
+//! protocol leaves open. Rather than testing for compositor names where each difference
+//! matters, the differences are collected here, keyed by [`Compositor`]. Supporting a new
+//! compositor means adding a variant to `Compositor`, a row to [`Compositor::quirks`] and
+//! an entry to [`Compositor::TESTED`].
+
+use serde::Serialize;
+
+use super::Compositor;
+
+/// How a compositor treats gamma tables outside of plain `set_gamma` calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CompositorQuirks {
+    /// Gamma set on an output survives the output being powered off and on or
+    /// reconfigured. When false, every output is reapplied whenever the compositor reports
+    /// an output change, not just the outputs that are new.
+    pub gamma_persists_across_power_cycle: bool,
+    /// The compositor restores the original gamma tables when sunsetr's gamma control is
+    /// destroyed, as the protocol requires. When false, Core writes the `shutdown_target`
+    /// values before exiting, since they are what stays on screen.
+    pub restores_gamma_on_exit: bool,
+}
+
+impl CompositorQuirks {
+    /// Assumptions for compositors without a tested profile: reapply on every output
+    /// change, and write the shutdown values before exiting rather than rely on the
+    /// compositor restoring gamma. Both cost little when the compositor does better.
+    pub const UNTESTED: Self = Self {
+        gamma_persists_across_power_cycle: false,
+        restores_gamma_on_exit: false,
+    };
+}
+
+impl Compositor {
+    /// Compositors with a tested profile in [`Compositor::quirks`].
+    pub const TESTED: [Compositor; 5] = [
+        Compositor::Hyprland,
+        Compositor::Niri,
+        Compositor::Sway,
+        Compositor::River,
+        Compositor::Labwc,
+    ];
+
+    /// The tested behavior of this compositor.
+    pub fn quirks(&self) -> CompositorQuirks {
+        match self {
+            Compositor::Hyprland => CompositorQuirks {
+                gamma_persists_across_power_cycle: true,
+                restores_gamma_on_exit: true,
+            },
+            // niri drives the CRTC gamma itself and resets it when an output is re-enabled
+            Compositor::Niri => CompositorQuirks {
+                gamma_persists_across_power_cycle: false,
+                restores_gamma_on_exit: true,
+            },
+            Compositor::Sway => CompositorQuirks {
+                gamma_persists_across_power_cycle: true,
+                restores_gamma_on_exit: true,
+            },
+            Compositor::River => CompositorQuirks {
+                gamma_persists_across_power_cycle: false,
+                restores_gamma_on_exit: true,
+            },
+            Compositor::Labwc => CompositorQuirks {
+                gamma_persists_across_power_cycle: true,
+                restores_gamma_on_exit: true,
+            },
+            Compositor::Other(_) => CompositorQuirks::UNTESTED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untested_compositors_reapply_on_output_changes() {
+        let quirks = Compositor::Other("wayfire".to_string()).quirks();
+        assert_eq!(quirks, CompositorQuirks::UNTESTED);
+        assert!(!quirks.gamma_persists_across_power_cycle);
+        assert!(!quirks.restores_gamma_on_exit);
+    }
+
+    #[test]
+    fn desktop_names_select_tested_profiles() {
+        assert_eq!(Compositor::from_desktop("river"), Compositor::River);
+        assert_eq!(Compositor::from_desktop("labwc:wlroots"), Compositor::Labwc);
+        assert_eq!(Compositor::from_desktop("Hyprland"), Compositor::Hyprland);
+        assert_eq!(
+            Compositor::from_desktop("wayfire"),
+            Compositor::Other("wayfire".to_string())
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Finding the compositor again after it restarts.
+//!
+//! sunsetr learns where the compositor is from `WAYLAND_DISPLAY` and
+//! `HYPRLAND_INSTANCE_SIGNATURE`, but neither has to hold once the compositor restarts: a
+//! new Hyprland instance gets a new signature, and a Wayland socket left behind by a crash
+//! pushes the new one to the next free name. Before reconnecting, [`rediscover`] checks
+//! that the sockets still answer and, if not, looks for the live ones in
+//! `$XDG_RUNTIME_DIR`. What it finds is kept here rather than written back to the
+//! environment, which other threads may be reading at the same time.
+
+use anyhow::{Context, Result, bail};
+use std::ffi::{OsStr, OsString};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use wayland_client::Connection;
+
+/// Display found by [`rediscover`], used instead of `$WAYLAND_DISPLAY`.
+static WAYLAND_DISPLAY: Mutex<Option<OsString>> = Mutex::new(None);
+
+/// Hyprland instance found by [`rediscover`], used instead of `$HYPRLAND_INSTANCE_SIGNATURE`.
+static HYPRLAND_INSTANCE: Mutex<Option<OsString>> = Mutex::new(None);
+
+/// The Wayland display the compositor currently listens on.
+pub fn wayland_display() -> Option<OsString> {
+    let found = WAYLAND_DISPLAY.lock().unwrap().clone();
+    found.or_else(|| std::env::var_os("WAYLAND_DISPLAY"))
+}
+
+/// The signature of the running Hyprland instance.
+pub fn hyprland_instance() -> Option<OsString> {
+    let found = HYPRLAND_INSTANCE.lock().unwrap().clone();
+    found.or_else(|| std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE"))
+}
+
+/// Connect to the compositor's Wayland display.
+pub fn connect() -> Result<Connection> {
+    let found = WAYLAND_DISPLAY.lock().unwrap().clone();
+    let Some(display) = found else {
+        // Also covers a socket handed over through WAYLAND_SOCKET
+        return Ok(Connection::connect_to_env()?);
+    };
+    let stream = UnixStream::connect(socket_path(&runtime_dir(), &display))?;
+    Ok(Connection::from_socket(stream)?)
+}
+
+/// Make sure the display and Hyprland instance point at a compositor that answers,
+/// switching to the ones it listens on now when it has moved. Fails while it is not
+/// back yet.
+pub fn rediscover() -> Result<()> {
+    let runtime_dir = runtime_dir();
+
+    if let Some(display) = wayland_display()
+        && !answers(&socket_path(&runtime_dir, &display))
+    {
+        // A nested compositor's display is its own; any other belongs to another session
+        if crate::io::nested::parent_display().is_some() {
+            bail!("{} is not accepting connections", display.to_string_lossy());
+        }
+        let found = live_displays(&runtime_dir)
+            .into_iter()
+            .next()
+            .context("No Wayland display is accepting connections yet")?;
+        log_indented!("The compositor now listens on {}", found.to_string_lossy());
+        *WAYLAND_DISPLAY.lock().unwrap() = Some(found);
+    }
+
+    if let Some(instance) = hyprland_instance()
+        && !answers(&hyprland_socket(&runtime_dir, &instance))
+    {
+        let found = newest_hyprland_instance(&runtime_dir)
+            .context("Hyprland is not accepting connections yet")?;
+        log_indented!("Hyprland is now instance {}", found.to_string_lossy());
+        *HYPRLAND_INSTANCE.lock().unwrap() = Some(found);
+    }
+    Ok(())
+}
+
+fn runtime_dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("/run/user/{}", nix::unistd::getuid())))
+}
+
+fn socket_path(runtime_dir: &Path, display: &OsStr) -> PathBuf {
+    // An absolute display names the socket itself, which join keeps as it is
+    runtime_dir.join(display)
+}
+
+fn hyprland_socket(runtime_dir: &Path, instance: &OsStr) -> PathBuf {
+    runtime_dir.join("hypr").join(instance).join(".socket.sock")
+}
+
+/// Whether something accepts connections on the socket at `path`.
+fn answers(path: &Path) -> bool {
+    UnixStream::connect(path).is_ok()
+}
+
+/// The `wayland-N` displays in `runtime_dir` that accept connections, lowest number first.
+fn live_displays(runtime_dir: &Path) -> Vec<OsString> {
+    let Ok(entries) = std::fs::read_dir(runtime_dir) else {
+        return Vec::new();
+    };
+    let mut displays: Vec<(u32, OsString)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let number = name.to_str()?.strip_prefix("wayland-")?.parse().ok()?;
+            answers(&entry.path()).then_some((number, name))
+        })
+        .collect();
+    displays.sort();
+    displays.into_iter().map(|(_, name)| name).collect()
+}
+
+/// The most recently started Hyprland instance in `runtime_dir` that accepts connections.
+fn newest_hyprland_instance(runtime_dir: &Path) -> Option<OsString> {
+    std::fs::read_dir(runtime_dir.join("hypr"))
+        .ok()?
+        .flatten()
+        .filter(|entry| answers(&entry.path().join(".socket.sock")))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.file_name())))
+        .max()
+        .map(|(_, instance)| instance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn only_displays_that_answer_are_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let _second = UnixListener::bind(dir.path().join("wayland-2")).unwrap();
+        let _first = UnixListener::bind(dir.path().join("wayland-1")).unwrap();
+        // Left behind by a compositor that crashed
+        drop(UnixListener::bind(dir.path().join("wayland-0")).unwrap());
+        std::fs::write(dir.path().join("wayland-0.lock"), "").unwrap();
+
+        assert_eq!(
+            live_displays(dir.path()),
+            vec![OsString::from("wayland-1"), OsString::from("wayland-2")]
+        );
+    }
+
+    #[test]
+    fn a_restarted_hyprland_instance_is_found() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(newest_hyprland_instance(dir.path()), None);
+
+        let old = dir.path().join("hypr").join("old_1700000000_1");
+        std::fs::create_dir_all(&old).unwrap();
+        drop(UnixListener::bind(old.join(".socket.sock")).unwrap());
+        let new = dir.path().join("hypr").join("new_1700000500_2");
+        std::fs::create_dir_all(&new).unwrap();
+        let _listener = UnixListener::bind(new.join(".socket.sock")).unwrap();
+
+        assert_eq!(
+            newest_hyprland_instance(dir.path()),
+            Some(OsString::from("new_1700000500_2"))
+        );
+        assert!(!answers(&hyprland_socket(
+            dir.path(),
+            OsStr::new("old_1700000000_1")
+        )));
+    }
+}
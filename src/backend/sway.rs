@@ -0,0 +1,434 @@
+//! Sway IPC integration for the Wayland backend.
+//!
+//! Sway publishes `output` events on `$SWAYSOCK` whenever an output is connected,
+//! disconnected or reconfigured. Subscribing to them lets the Wayland backend reapply the
+//! current values as soon as sway reports the change. Messages use the i3 IPC framing: the
+//! `i3-ipc` magic, a payload length and a message type, both in native byte order, then a
+//! JSON payload.
+//!
+//! Sway's IPC carries no idle events, since idle is reported over the ext-idle-notify
+//! Wayland protocol instead. Besides output changes, window and workspace events are
+//! followed to track the focused window for `neutral_apps`, starting from the focus found
+//! in the layout tree at connect time. Which outputs are in HDR is asked separately, on a
+//! fresh connection, since the subscribed one only carries events.
+
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use serde_json::Value;
+
+use super::CompositorEvents;
+use super::focus::{Focus, FocusSlot};
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const SUBSCRIBE: u32 = 2;
+const GET_OUTPUTS: u32 = 3;
+const GET_TREE: u32 = 4;
+const EVENT_WORKSPACE: u32 = 0x8000_0000;
+const EVENT_OUTPUT: u32 = 0x8000_0001;
+const EVENT_WINDOW: u32 = 0x8000_0003;
+const EVENT_SHUTDOWN: u32 = 0x8000_0006;
+
+/// Subscription to sway's output and focus events, read on a background thread.
+pub struct SwayEvents {
+    path: PathBuf,
+    outputs_changed: Arc<AtomicBool>,
+    focus: Arc<FocusSlot>,
+    stream: UnixStream,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SwayEvents {
+    /// Subscribe through `$SWAYSOCK`. Returns `None` outside sway or if the subscription
+    /// fails, in which case hotplug falls back to Wayland events alone.
+    pub fn connect(debug_enabled: bool) -> Option<Self> {
+        let socket = std::env::var_os("SWAYSOCK")?;
+        match Self::connect_to(Path::new(&socket)) {
+            Ok(events) => {
+                if debug_enabled {
+                    log_debug!("Subscribed to sway IPC for output change events");
+                }
+                Some(events)
+            }
+            Err(e) => {
+                if debug_enabled {
+                    log_debug!("sway IPC unavailable, relying on Wayland events: {e}");
+                }
+                None
+            }
+        }
+    }
+
+    /// Read the current focus and subscribe to events on the socket at `path`.
+    fn connect_to(path: &Path) -> std::io::Result<Self> {
+        let mut stream = UnixStream::connect(path)?;
+
+        let focus = Arc::new(FocusSlot::default());
+        let mut tracker = FocusTracker::default();
+        stream.write_all(&encode(GET_TREE, b""))?;
+        let (_, tree) = read_message(&mut stream)?;
+        if let Some((initial, id)) = serde_json::from_slice::<Value>(&tree)
+            .ok()
+            .and_then(|tree| focus_in_tree(&tree, None))
+        {
+            tracker.focus = initial.clone();
+            tracker.focused_id = id;
+            focus.publish(initial);
+        }
+
+        stream.write_all(&encode(SUBSCRIBE, br#"["output","window","workspace"]"#))?;
+
+        let (_, reply) = read_message(&mut stream)?;
+        let success = serde_json::from_slice::<serde_json::Value>(&reply)
+            .ok()
+            .and_then(|v| v.get("success")?.as_bool());
+        if success != Some(true) {
+            return Err(std::io::Error::other(format!(
+                "subscription rejected: {}",
+                String::from_utf8_lossy(&reply)
+            )));
+        }
+
+        let outputs_changed = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&outputs_changed);
+        let slot = Arc::clone(&focus);
+        let mut reader = stream.try_clone()?;
+        let thread = thread::Builder::new()
+            .name("sway-events".to_string())
+            .spawn(move || {
+                while let Ok((message_type, payload)) = read_message(&mut reader) {
+                    match message_type {
+                        EVENT_OUTPUT => flag.store(true, Ordering::SeqCst),
+                        EVENT_WORKSPACE | EVENT_WINDOW => {
+                            if let Some(focus) = tracker.handle_event(message_type, &payload) {
+                                slot.publish(focus);
+                            }
+                        }
+                        EVENT_SHUTDOWN => break,
+                        _ => {}
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            outputs_changed,
+            focus,
+            stream,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl CompositorEvents for SwayEvents {
+    fn take_outputs_changed(&self) -> bool {
+        self.outputs_changed.swap(false, Ordering::SeqCst)
+    }
+
+    fn take_focus(&self) -> Option<Focus> {
+        self.focus.take()
+    }
+
+    fn hdr_outputs(&self) -> BTreeSet<String> {
+        query_outputs(&self.path)
+            .map(|outputs| hdr_outputs(&outputs))
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for SwayEvents {
+    fn drop(&mut self) {
+        // Unblocks the reader so the thread can exit
+        let _ = self.stream.shutdown(Shutdown::Both);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Frame a message for sway's IPC socket.
+fn encode(message_type: u32, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&message_type.to_ne_bytes());
+    message.extend_from_slice(payload);
+    message
+}
+
+/// Read one framed message, returning its type and payload.
+fn read_message(stream: &mut impl Read) -> std::io::Result<(u32, Vec<u8>)> {
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header)?;
+    if &header[..6] != MAGIC {
+        return Err(std::io::Error::other("invalid sway IPC magic"));
+    }
+
+    let length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+    let message_type = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+    let mut payload = vec![0u8; length as usize];
+    stream.read_exact(&mut payload)?;
+    Ok((message_type, payload))
+}
+
+/// Ask for the output list on a new connection to the socket at `path`.
+fn query_outputs(path: &Path) -> std::io::Result<Value> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.write_all(&encode(GET_OUTPUTS, b""))?;
+    let (_, payload) = read_message(&mut stream)?;
+    serde_json::from_slice(&payload).map_err(std::io::Error::other)
+}
+
+/// Outputs that `get_outputs` reports with HDR enabled.
+fn hdr_outputs(outputs: &Value) -> BTreeSet<String> {
+    outputs
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|output| output.get("hdr").and_then(Value::as_bool) == Some(true))
+        .filter_map(|output| output.get("name")?.as_str().map(str::to_string))
+        .collect()
+}
+
+/// A window's app id, or its X11 class for Xwayland windows.
+fn window_app_id(container: &Value) -> Option<String> {
+    container
+        .get("app_id")
+        .and_then(Value::as_str)
+        .or_else(|| container.pointer("/window_properties/class")?.as_str())
+        .map(str::to_string)
+}
+
+/// The focused node in a layout tree and the output above it, along with the node's id.
+fn focus_in_tree(node: &Value, output: Option<&str>) -> Option<(Focus, Option<u64>)> {
+    let output = match node.get("type").and_then(Value::as_str) {
+        Some("output") => node.get("name").and_then(Value::as_str),
+        _ => output,
+    };
+
+    if node.get("focused").and_then(Value::as_bool) == Some(true) {
+        let focus = Focus {
+            app_id: window_app_id(node),
+            output: output.map(str::to_string),
+        };
+        return Some((focus, node.get("id").and_then(Value::as_u64)));
+    }
+
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node.get(key)?.as_array())
+        .flatten()
+        .find_map(|child| focus_in_tree(child, output))
+}
+
+/// Follows focus from window and workspace events.
+#[derive(Default)]
+struct FocusTracker {
+    focus: Focus,
+    focused_id: Option<u64>,
+}
+
+impl FocusTracker {
+    /// Feed one event, returning the new focus when it changed.
+    fn handle_event(&mut self, message_type: u32, payload: &[u8]) -> Option<Focus> {
+        let event: Value = serde_json::from_slice(payload).ok()?;
+        let previous = self.focus.clone();
+
+        match (message_type, event.get("change").and_then(Value::as_str)) {
+            (EVENT_WORKSPACE, Some("focus")) => {
+                let current = event.get("current")?;
+                self.focus.output = current
+                    .get("output")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                // Focusing an empty workspace sends no window event
+                if current
+                    .get("focus")
+                    .and_then(Value::as_array)
+                    .is_some_and(Vec::is_empty)
+                {
+                    self.focus.app_id = None;
+                    self.focused_id = None;
+                }
+            }
+            (EVENT_WINDOW, Some("focus")) => {
+                let container = event.get("container")?;
+                self.focus.app_id = window_app_id(container);
+                self.focused_id = container.get("id").and_then(Value::as_u64);
+            }
+            (EVENT_WINDOW, Some("close")) => {
+                let id = event.pointer("/container/id").and_then(Value::as_u64);
+                if id.is_some() && id == self.focused_id {
+                    self.focus.app_id = None;
+                    self.focused_id = None;
+                }
+            }
+            _ => return None,
+        }
+
+        (self.focus != previous).then(|| self.focus.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn messages_round_trip_through_the_framing() {
+        let message = encode(EVENT_OUTPUT, br#"{"change":"unspecified"}"#);
+        assert_eq!(&message[..6], b"i3-ipc");
+
+        let (message_type, payload) = read_message(&mut message.as_slice()).unwrap();
+        assert_eq!(message_type, EVENT_OUTPUT);
+        assert_eq!(payload, br#"{"change":"unspecified"}"#);
+
+        let mut corrupt = message.clone();
+        corrupt[0] = b'x';
+        assert!(read_message(&mut corrupt.as_slice()).is_err());
+    }
+
+    /// Serve one client: answer its tree request, check its subscription, answer with
+    /// `reply`, then send `events`.
+    fn serve(listener: UnixListener, reply: &'static [u8], events: Vec<u32>) -> UnixStream {
+        let (mut stream, _) = listener.accept().unwrap();
+        let (message_type, _) = read_message(&mut stream).unwrap();
+        assert_eq!(message_type, GET_TREE);
+        stream
+            .write_all(&encode(GET_TREE, TREE.as_bytes()))
+            .unwrap();
+
+        let (message_type, payload) = read_message(&mut stream).unwrap();
+        assert_eq!(message_type, SUBSCRIBE);
+        assert_eq!(payload, br#"["output","window","workspace"]"#);
+
+        stream.write_all(&encode(SUBSCRIBE, reply)).unwrap();
+        for event in events {
+            stream
+                .write_all(&encode(event, br#"{"change":"unspecified"}"#))
+                .unwrap();
+        }
+        stream
+    }
+
+    const TREE: &str = r#"{"id":1,"type":"root","focused":false,"nodes":[
+        {"id":3,"type":"output","name":"DP-1","focused":false,"nodes":[
+            {"id":4,"type":"workspace","focused":false,"nodes":[],"floating_nodes":[
+                {"id":9,"type":"floating_con","focused":true,"app_id":null,
+                 "window_properties":{"class":"Gimp"}}
+            ]}
+        ]}
+    ]}"#;
+
+    #[test]
+    fn initial_focus_is_found_in_the_layout_tree() {
+        let tree: Value = serde_json::from_str(TREE).unwrap();
+        let (focus, id) = focus_in_tree(&tree, None).unwrap();
+        assert_eq!(focus.app_id.as_deref(), Some("Gimp"));
+        assert_eq!(focus.output.as_deref(), Some("DP-1"));
+        assert_eq!(id, Some(9));
+    }
+
+    #[test]
+    fn focus_follows_window_and_workspace_events() {
+        let mut tracker = FocusTracker::default();
+        let focus = tracker
+            .handle_event(
+                EVENT_WORKSPACE,
+                br#"{"change":"focus","current":{"output":"HDMI-A-1","focus":[12]}}"#,
+            )
+            .unwrap();
+        assert_eq!(focus.output.as_deref(), Some("HDMI-A-1"));
+
+        let focus = tracker
+            .handle_event(
+                EVENT_WINDOW,
+                br#"{"change":"focus","container":{"id":12,"app_id":"darktable"}}"#,
+            )
+            .unwrap();
+        assert_eq!(focus.app_id.as_deref(), Some("darktable"));
+
+        assert_eq!(
+            tracker.handle_event(
+                EVENT_WINDOW,
+                br#"{"change":"title","container":{"id":12,"app_id":"darktable"}}"#
+            ),
+            None
+        );
+
+        let focus = tracker
+            .handle_event(
+                EVENT_WINDOW,
+                br#"{"change":"close","container":{"id":12,"app_id":"darktable"}}"#,
+            )
+            .unwrap();
+        assert_eq!(focus.app_id, None);
+        assert_eq!(focus.output.as_deref(), Some("HDMI-A-1"));
+    }
+
+    #[test]
+    fn subscription_reports_output_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sway.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server =
+            thread::spawn(move || serve(listener, br#"{"success":true}"#, vec![EVENT_OUTPUT]));
+
+        let events = SwayEvents::connect_to(&path).unwrap();
+        assert_eq!(
+            events
+                .take_focus()
+                .and_then(|focus| focus.app_id)
+                .as_deref(),
+            Some("Gimp")
+        );
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !events.take_outputs_changed() {
+            assert!(Instant::now() < deadline, "output change was not reported");
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(!events.take_outputs_changed());
+
+        drop(events);
+        drop(server.join().unwrap());
+    }
+
+    #[test]
+    fn hdr_outputs_are_queried_on_their_own_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sway.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (message_type, _) = read_message(&mut stream).unwrap();
+            assert_eq!(message_type, GET_OUTPUTS);
+            let outputs =
+                br#"[{"name":"DP-1","hdr":true},{"name":"DP-2","hdr":false},{"name":"eDP-1"}]"#;
+            stream.write_all(&encode(GET_OUTPUTS, outputs)).unwrap();
+        });
+
+        let outputs = query_outputs(&path).unwrap();
+        server.join().unwrap();
+        let names: Vec<_> = hdr_outputs(&outputs).into_iter().collect();
+        assert_eq!(names, ["DP-1"]);
+    }
+
+    #[test]
+    fn rejected_subscription_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sway.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = thread::spawn(move || serve(listener, br#"{"success":false}"#, vec![]));
+
+        assert!(SwayEvents::connect_to(&path).is_err());
+        drop(server.join().unwrap());
+    }
+}
@@ -0,0 +1,265 @@
+//! Frame feedback for smooth transitions.
+//!
+//! wlr-gamma-control says nothing about when a table reaches the screen, only that it
+//! takes effect from the output's next frame. Where the compositor offers wlr-layer-shell,
+//! the backend maps a transparent 1x1 surface on the fastest output, on the overlay layer
+//! so windows don't cover it, taking no input and reserving no space. After each step of a
+//! smooth transition it asks for a `wl_surface.frame` callback, whose `done` arrives as the
+//! compositor draws that output's next frame, the one showing the step. Transitions then
+//! step on the compositor's frames instead of a timer that drifts against them.
+//!
+//! A compositor may hold back callbacks for a surface it isn't drawing, such as on an
+//! output that is off. While one goes unanswered, transitions time their steps themselves.
+
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::AsFd;
+
+use wayland_client::{
+    Connection, Dispatch, QueueHandle, delegate_noop,
+    protocol::{
+        wl_buffer::WlBuffer,
+        wl_callback::{self, WlCallback},
+        wl_compositor::WlCompositor,
+        wl_output::WlOutput,
+        wl_region::WlRegion,
+        wl_registry::WlRegistry,
+        wl_shm::{self, WlShm},
+        wl_shm_pool::WlShmPool,
+        wl_surface::WlSurface,
+    },
+};
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{self, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, ZwlrLayerSurfaceV1},
+};
+
+use super::State;
+
+/// The globals a frame clock needs, and the clock once there is an output to put it on.
+#[derive(Debug, Default)]
+pub(super) struct Frames {
+    compositor: Option<WlCompositor>,
+    shm: Option<WlShm>,
+    layer_shell: Option<ZwlrLayerShellV1>,
+    clock: Option<FrameClock>,
+}
+
+/// The surface whose frame callbacks pace smooth transitions.
+#[derive(Debug)]
+struct FrameClock {
+    // Registry name of the output the surface is on
+    output: u32,
+    surface: WlSurface,
+    layer_surface: ZwlrLayerSurfaceV1,
+    // The transparent pixel, attached at the first configure. The file backs its pool.
+    buffer: Option<(WlBuffer, File)>,
+    // Set when the compositor closes the surface, as when its output goes away
+    closed: bool,
+    // The callback for the last step, until its `done` arrives
+    pending: Option<WlCallback>,
+}
+
+impl Frames {
+    /// Bind `interface` when it is one of the globals a frame clock needs.
+    pub(super) fn bind(
+        &mut self,
+        registry: &WlRegistry,
+        name: u32,
+        interface: &str,
+        version: u32,
+        qh: &QueueHandle<State>,
+    ) {
+        match interface {
+            "wl_compositor" => {
+                self.compositor = Some(registry.bind(name, version.min(4), qh, ()));
+            }
+            "wl_shm" => self.shm = Some(registry.bind(name, 1, qh, ())),
+            "zwlr_layer_shell_v1" => self.layer_shell = Some(registry.bind(name, 1, qh, ())),
+            _ => {}
+        }
+    }
+
+    /// Keep the clock on `output`, given by registry name, moving it there from an output
+    /// that is no longer the fastest or has gone away.
+    pub(super) fn follow(&mut self, output: Option<(u32, &WlOutput)>, qh: &QueueHandle<State>) {
+        if self
+            .clock
+            .as_ref()
+            .is_some_and(|clock| clock.closed || Some(clock.output) != output.map(|(name, _)| name))
+            && let Some(clock) = self.clock.take()
+        {
+            clock.destroy();
+        }
+        let (Some(compositor), Some(_), Some(layer_shell), None, Some((name, output))) = (
+            &self.compositor,
+            &self.shm,
+            &self.layer_shell,
+            &self.clock,
+            output,
+        ) else {
+            return;
+        };
+        self.clock = Some(FrameClock::new(compositor, layer_shell, name, output, qh));
+    }
+
+    /// Ask for a callback on the next frame. False when there is no mapped clock or the
+    /// callback for an earlier step is still outstanding, as there is nothing to wait on.
+    pub(super) fn request(&mut self, qh: &QueueHandle<State>) -> bool {
+        let Some(clock) = &mut self.clock else {
+            return false;
+        };
+        if clock.buffer.is_none() || clock.pending.is_some() {
+            return false;
+        }
+        clock.pending = Some(clock.surface.frame(qh, ()));
+        clock.surface.commit();
+        true
+    }
+
+    /// Whether the callback last asked for has yet to arrive.
+    pub(super) fn waiting(&self) -> bool {
+        self.clock
+            .as_ref()
+            .is_some_and(|clock| clock.pending.is_some() && !clock.closed)
+    }
+
+    /// Whether the callback last asked for has arrived, rather than the surface closing.
+    pub(super) fn answered(&self) -> bool {
+        self.clock
+            .as_ref()
+            .is_some_and(|clock| clock.pending.is_none() && !clock.closed)
+    }
+
+    /// Attach the transparent pixel once the compositor has sized the surface, which maps it.
+    fn configure(&mut self, serial: u32, qh: &QueueHandle<State>) {
+        let (Some(clock), Some(shm)) = (&mut self.clock, &self.shm) else {
+            return;
+        };
+        clock.layer_surface.ack_configure(serial);
+        if clock.buffer.is_none() {
+            match transparent_pixel(shm, qh) {
+                Ok(buffer) => clock.buffer = Some(buffer),
+                // Without a buffer the surface stays unmapped and transitions use the timer
+                Err(_) => return,
+            }
+        }
+        if let Some((buffer, _)) = &clock.buffer {
+            clock.surface.attach(Some(buffer), 0, 0);
+            clock.surface.damage(0, 0, 1, 1);
+        }
+        clock.surface.commit();
+    }
+}
+
+impl FrameClock {
+    fn new(
+        compositor: &WlCompositor,
+        layer_shell: &ZwlrLayerShellV1,
+        name: u32,
+        output: &WlOutput,
+        qh: &QueueHandle<State>,
+    ) -> Self {
+        let surface = compositor.create_surface(qh, ());
+        // An empty input region passes clicks and touches through to whatever is below
+        let region = compositor.create_region(qh, ());
+        surface.set_input_region(Some(&region));
+        region.destroy();
+
+        let layer_surface = layer_shell.get_layer_surface(
+            &surface,
+            Some(output),
+            zwlr_layer_shell_v1::Layer::Overlay,
+            "sunsetr-frames".to_string(),
+            qh,
+            (),
+        );
+        layer_surface.set_size(1, 1);
+        layer_surface
+            .set_anchor(zwlr_layer_surface_v1::Anchor::Top | zwlr_layer_surface_v1::Anchor::Left);
+        // The first commit has no buffer, and asks the compositor for a configure
+        surface.commit();
+
+        Self {
+            output: name,
+            surface,
+            layer_surface,
+            buffer: None,
+            closed: false,
+            pending: None,
+        }
+    }
+
+    fn destroy(self) {
+        self.layer_surface.destroy();
+        self.surface.destroy();
+        if let Some((buffer, _)) = self.buffer {
+            buffer.destroy();
+        }
+    }
+}
+
+/// A 1x1 buffer holding one fully transparent pixel.
+fn transparent_pixel(shm: &WlShm, qh: &QueueHandle<State>) -> std::io::Result<(WlBuffer, File)> {
+    let mut file = tempfile::tempfile()?;
+    file.write_all(&[0; 4])?;
+    let pool = shm.create_pool(file.as_fd(), 4, qh, ());
+    let buffer = pool.create_buffer(0, 1, 1, 4, wl_shm::Format::Argb8888, qh, ());
+    pool.destroy();
+    Ok((buffer, file))
+}
+
+impl Dispatch<WlCallback, ()> for State {
+    fn event(
+        state: &mut Self,
+        callback: &WlCallback,
+        event: wl_callback::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event
+            && let Some(clock) = &mut state.frames.clock
+            && clock.pending.as_ref() == Some(callback)
+        {
+            clock.pending = None;
+        }
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        layer_surface: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let is_clock = state
+            .frames
+            .clock
+            .as_ref()
+            .is_some_and(|clock| &clock.layer_surface == layer_surface);
+        match event {
+            zwlr_layer_surface_v1::Event::Configure { serial, .. } if is_clock => {
+                state.frames.configure(serial, qh);
+            }
+            zwlr_layer_surface_v1::Event::Closed if is_clock => {
+                if let Some(clock) = &mut state.frames.clock {
+                    clock.closed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Globals and objects that send nothing a frame clock needs
+delegate_noop!(State: WlCompositor);
+delegate_noop!(State: WlRegion);
+delegate_noop!(State: ignore WlSurface);
+delegate_noop!(State: ignore WlShm);
+delegate_noop!(State: WlShmPool);
+delegate_noop!(State: ignore WlBuffer);
+delegate_noop!(State: ZwlrLayerShellV1);
@@ -5,26 +5,67 @@
 //! temperature to RGB comes from the shared gamma module (Tanner Helland approximation)
 //! applied as per-channel gamma tables. All connected outputs are discovered at startup and
 //! updated together, and outputs added or removed at runtime are handled dynamically.
+//!
+//! With `verify_gamma` enabled, the backend tracks the values it last applied to each output
+//! and checks them against its targets after every apply and hotplug poll. The protocol has
+//! no way to read gamma back, so a revoked gamma control (compositor restart, another tool
+//! taking over) is treated as an overwrite: it is logged as a fight and reapplied.
+//!
+//! Outputs the compositor reports in HDR are left neutral or given a reduced tint, per
+//! their `[outputs.<name>]` `hdr` setting, since ramps meant for SDR distort HDR output.
+//! Besides compositor IPC, HDR is read from wp-color-management-v1 where the compositor
+//! offers it: an output whose image description uses the PQ or HLG transfer function is
+//! in HDR. The protocol only describes outputs, it can't change their white point, so the
+//! tint itself always goes through gamma control.
+//! Outputs in presentation mode get neutral tables until it is turned off.
+//!
+//! Outputs left out by `include_outputs` or `exclude_outputs` get no gamma control at all,
+//! so the compositor keeps its own gamma on them. A hotplugged output's name arrives after
+//! its control may already exist, so controls on outputs that turn out to be excluded are
+//! destroyed before any table is sent.
 
 use anyhow::Result;
-use std::collections::{HashMap, HashSet};
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::os::fd::AsFd;
+use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
 
 use wayland_client::{
-    Connection, Dispatch, EventQueue, Proxy, QueueHandle,
-    protocol::{wl_output::WlOutput, wl_registry::WlRegistry},
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle, WEnum,
+    backend::WaylandError,
+    protocol::{
+        wl_output::{self, WlOutput},
+        wl_registry::WlRegistry,
+    },
+};
+use wayland_protocols::wp::color_management::v1::client::{
+    wp_color_management_output_v1::{self, WpColorManagementOutputV1},
+    wp_color_manager_v1::{self, WpColorManagerV1},
+    wp_image_description_info_v1::{self, WpImageDescriptionInfoV1},
+    wp_image_description_v1::{self, WpImageDescriptionV1},
 };
 use wayland_protocols_wlr::gamma_control::v1::client::{
     zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1,
     zwlr_gamma_control_v1::{Event as GammaControlEvent, ZwlrGammaControlV1},
 };
 
-use crate::backend::ColorTemperatureBackend;
+use crate::backend::gamma::ChannelGamma;
+use crate::backend::{Capabilities, ColorTemperatureBackend, ConnectionLost, OutputEvent};
+use crate::common::constants::DEFAULT_BRIGHTNESS;
 use crate::common::error::Silent;
-use crate::config::Config;
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::{Config, HdrBehavior, OutputFilter};
+use crate::state::presentation::Presentation;
 
+use super::focus::{Focus, NeutralOutputs};
 use super::gamma;
+use super::hdr::{self, Adjustment};
+use super::quirks::CompositorQuirks;
+use super::{CompositorEvents, connect_compositor_events, detect_compositor};
+
+mod frames;
 
 /// Wayland gamma-control backend for compositors that implement wlr-gamma-control-unstable-v1.
 pub struct WaylandBackend {
@@ -32,9 +73,29 @@ pub struct WaylandBackend {
     event_queue: EventQueue<State>,
     state: State,
     debug_enabled: bool,
+    verify_gamma: bool,
     // Stored so hotplugged outputs can be re-applied without recomputing from state
     current_temperature: u32,
     current_gamma_percent: f64,
+    current_brightness: f64,
+    // Output change and focus events from Hyprland, niri or sway IPC, when running on one
+    compositor_events: Option<Box<dyn CompositorEvents>>,
+    quirks: CompositorQuirks,
+    neutral_apps: Vec<String>,
+    focus: Focus,
+    // Outputs held at neutral values while a `neutral_apps` window has focus
+    neutral: NeutralOutputs,
+    // Manual per-channel curve, left off the neutral outputs
+    channel_gamma: ChannelGamma,
+    // The same with the trims of outputs that set their own
+    output_channel_gamma: BTreeMap<String, ChannelGamma>,
+    // Outputs the compositor drives in HDR, and what each configured output gets then
+    hdr_outputs: BTreeSet<String>,
+    hdr_behaviors: BTreeMap<String, HdrBehavior>,
+    presentation: Option<Presentation>,
+    output_filter: OutputFilter,
+    // Tables from earlier applies, reused while a transition sends the same values again
+    gamma_tables: gamma::TableCache,
 }
 
 #[derive(Debug, Clone)]
@@ -46,30 +107,115 @@ struct OutputInfo {
     // Set when an output is new or newly ready (gamma_size known). Cleared after a successful apply.
     needs_apply: bool,
     registry_name: u32,
+    // Values (Kelvin, gamma percent) confirmed on this output by the last successful apply
+    applied: Option<(u32, f64)>,
+    // Set when the gamma control fails after values were applied through it
+    revoked: bool,
+    // Overwrites detected by verification, for logging
+    fights: u32,
+    // Set once the output's first `done` has arrived and it has been reported to IPC
+    announced: bool,
+    // Refresh rate of the current mode in mHz, unknown until its `mode` event
+    refresh_mhz: Option<u32>,
+    // Image description updates, when the compositor offers wp-color-management-v1
+    color_output: Option<WpColorManagementOutputV1>,
+    // Transfer function of the image description being read, until its `done`
+    pending_tf: Option<WEnum<wp_color_manager_v1::TransferFunction>>,
+    // Whether the last complete image description uses an HDR transfer function
+    hdr_signal: bool,
 }
 
 /// Application data for Wayland event handling
 #[derive(Debug)]
 struct State {
     gamma_manager: Option<ZwlrGammaControlManagerV1>,
+    color_manager: Option<WpColorManagerV1>,
     outputs: Vec<OutputInfo>,
     debug_enabled: bool,
+    output_events: Vec<OutputEvent>,
+    // Set when an output's image description switched between SDR and HDR
+    hdr_signal_changed: bool,
+    // Frame callbacks that pace smooth transitions, where the compositor offers layer-shell
+    frames: frames::Frames,
 }
 
 impl State {
     fn new(debug_enabled: bool) -> Self {
         Self {
             gamma_manager: None,
+            color_manager: None,
             outputs: Vec::new(),
             debug_enabled,
+            output_events: Vec::new(),
+            hdr_signal_changed: false,
+            frames: frames::Frames::default(),
+        }
+    }
+
+    /// Follow the image description of every output that isn't followed yet.
+    fn setup_color_outputs(&mut self, qh: &QueueHandle<State>) {
+        let Some(manager) = &self.color_manager else {
+            return;
+        };
+        for output_info in &mut self.outputs {
+            if output_info.color_output.is_none() {
+                let color_output =
+                    manager.get_output(&output_info.output, qh, output_info.registry_name);
+                color_output.get_image_description(qh, output_info.registry_name);
+                output_info.color_output = Some(color_output);
+            }
         }
     }
+
+    fn output_mut(&mut self, registry_name: u32) -> Option<&mut OutputInfo> {
+        self.outputs
+            .iter_mut()
+            .find(|output| output.registry_name == registry_name)
+    }
+
+    /// The output with gamma control and the highest refresh rate, which shows a step first.
+    fn fastest_output(&self) -> Option<&OutputInfo> {
+        self.outputs
+            .iter()
+            .filter(|o| o.gamma_control.is_some() && o.refresh_mhz.is_some())
+            .max_by_key(|o| o.refresh_mhz)
+    }
+
+    /// Put the frame clock on the fastest output, or move it there.
+    fn follow_fastest_output(&mut self, qh: &QueueHandle<State>) {
+        let output = self
+            .fastest_output()
+            .map(|o| (o.registry_name, o.output.clone()));
+        self.frames
+            .follow(output.as_ref().map(|(name, output)| (*name, output)), qh);
+    }
+}
+
+/// Whether `tf` is a transfer function only HDR signals use.
+fn is_hdr_transfer(tf: WEnum<wp_color_manager_v1::TransferFunction>) -> bool {
+    matches!(
+        tf,
+        WEnum::Value(
+            wp_color_manager_v1::TransferFunction::St2084Pq
+                | wp_color_manager_v1::TransferFunction::Hlg
+        )
+    )
 }
 
 impl WaylandBackend {
+    /// Gamma tables go to each output on its own, cheaply enough to step a smooth
+    /// transition every frame.
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        smoothing: true,
+        per_output: true,
+        hardware: true,
+        hotplug: true,
+        keeps_gamma_on_exit: false,
+    };
+
     /// Connect to the Wayland display, negotiate wlr-gamma-control-unstable-v1, and set up
     /// gamma control for the current outputs.
-    pub fn new(_config: &Config, debug_enabled: bool) -> Result<Self> {
+    pub fn new(config: &Config, debug_enabled: bool) -> Result<Self> {
         if std::env::var("WAYLAND_DISPLAY").is_err() {
             log_error_end!("WAYLAND_DISPLAY is not set. Are you running on Wayland?");
             return Err(Silent.into());
@@ -77,9 +223,31 @@ impl WaylandBackend {
 
         log_decorated!("Initializing Wayland gamma control backend...");
 
-        let connection = Connection::connect_to_env()
+        let connection = crate::backend::rediscover::connect()
             .map_err(|e| anyhow::anyhow!("Failed to connect to Wayland display: {}", e))?;
 
+        let mut backend = Self::from_connection(connection, debug_enabled, config.verify_gamma)?;
+        backend.compositor_events = connect_compositor_events(debug_enabled);
+        backend.quirks = detect_compositor().quirks();
+        backend.neutral_apps = config.neutral_apps.clone();
+        backend.channel_gamma = config.channel_gamma();
+        backend.output_channel_gamma = config.output_channel_gamma();
+        backend.hdr_behaviors = hdr_behaviors(config);
+        backend.presentation = config.presentation.clone();
+        backend.output_filter = config.output_filter();
+        backend.release_excluded_outputs();
+        backend.refresh_hdr_outputs();
+        Ok(backend)
+    }
+
+    /// Set up gamma control over an already established connection.
+    ///
+    /// Split from `new` so tests can hand in a connection to an in-process compositor.
+    fn from_connection(
+        connection: Connection,
+        debug_enabled: bool,
+        verify_gamma: bool,
+    ) -> Result<Self> {
         let display = connection.display();
 
         let mut event_queue = connection.new_event_queue();
@@ -115,7 +283,9 @@ impl WaylandBackend {
             log_debug!("Found wlr-gamma-control-unstable-v1 support");
         }
 
-        Self::setup_gamma_controls(&mut state, &qh)?;
+        Self::setup_gamma_controls(&mut state, &qh, &OutputFilter::default())?;
+        state.setup_color_outputs(&qh);
+        state.follow_fastest_output(&qh);
 
         // Roundtrip so gamma_size events arrive before we use them
         event_queue.roundtrip(&mut state).map_err(|e| {
@@ -140,21 +310,45 @@ impl WaylandBackend {
             );
         }
 
+        // Outputs present at startup are not hotplug events
+        for output_info in &mut state.outputs {
+            output_info.announced = true;
+        }
+        state.output_events.clear();
+
         Ok(Self {
             connection,
             event_queue,
             state,
             debug_enabled,
+            verify_gamma,
             current_temperature: 6500,
             current_gamma_percent: 100.0,
+            current_brightness: DEFAULT_BRIGHTNESS,
+            compositor_events: None,
+            quirks: CompositorQuirks::UNTESTED,
+            neutral_apps: Vec::new(),
+            focus: Focus::default(),
+            neutral: NeutralOutputs::None,
+            channel_gamma: ChannelGamma::IDENTITY,
+            output_channel_gamma: BTreeMap::new(),
+            hdr_outputs: BTreeSet::new(),
+            hdr_behaviors: BTreeMap::new(),
+            presentation: None,
+            output_filter: OutputFilter::default(),
+            gamma_tables: gamma::TableCache::default(),
         })
     }
 
-    /// Set up gamma controls for all available outputs
-    fn setup_gamma_controls(state: &mut State, qh: &QueueHandle<State>) -> Result<()> {
+    /// Set up gamma controls for all available outputs the filter allows
+    fn setup_gamma_controls(
+        state: &mut State,
+        qh: &QueueHandle<State>,
+        filter: &OutputFilter,
+    ) -> Result<()> {
         if let Some(ref manager) = state.gamma_manager {
             for output_info in &mut state.outputs {
-                if output_info.gamma_control.is_none() {
+                if output_info.gamma_control.is_none() && filter.allows(&output_info.name) {
                     let gamma_control = manager.get_gamma_control(&output_info.output, qh, ());
                     output_info.gamma_control = Some(gamma_control);
                     // gamma_size arrives later via GammaSize, so needs_apply defers the apply until then
@@ -168,6 +362,7 @@ impl WaylandBackend {
     /// Apply gamma tables to outputs that have needs_apply flag set
     /// For scheduled transitions: Set all outputs' needs_apply=true before calling
     /// For hotplug events: Only new outputs have needs_apply=true
+    /// All tables are sent together and confirmed with a single roundtrip.
     fn apply_gamma_to_outputs(&mut self, temperature: u32, gamma: f64) -> Result<()> {
         if self.debug_enabled {
             log_pipe!();
@@ -178,7 +373,7 @@ impl WaylandBackend {
             .state
             .outputs
             .iter()
-            .filter(|o| o.needs_apply)
+            .filter(|o| o.needs_apply && self.output_filter.allows(&o.name))
             .map(|o| o.name.clone())
             .collect();
 
@@ -191,73 +386,86 @@ impl WaylandBackend {
             log_debug!("Applying gamma to {} output(s)", outputs_to_update.len());
             log_decorated!("Creating gamma tables...");
             log_indented!(
-                "temp={}K, gamma={:.0}%, RGB factors={:?}",
+                "temp={}K, gamma={:.0}%, brightness={:.0}%, RGB factors={:?}",
                 temperature,
                 gamma * 100.0,
+                self.current_brightness,
                 gamma::get_rgb_factors(temperature)
             );
         }
 
-        // Different monitors can have different gamma_size values (e.g. 256 vs 1024)
-        let unique_gamma_sizes: HashSet<usize> = self
+        // Different monitors can have different gamma_size values (e.g. 256 vs 1024), and
+        // outputs left neutral by the focused app or reduced in HDR get their own tables
+        let adjustments = self.adjustments();
+        // Outputs with trims of their own get their own tables too
+        let table_key = |size: usize, name: &str| {
+            let adjustment = adjustments[name];
+            let own_trim =
+                adjustment == Adjustment::Full && self.output_channel_gamma.contains_key(name);
+            (size, adjustment, own_trim.then(|| name.to_string()))
+        };
+        let unique_tables: HashSet<(usize, Adjustment, Option<String>)> = self
             .state
             .outputs
             .iter()
             .filter(|o| o.needs_apply && o.gamma_control.is_some() && o.gamma_size.is_some())
-            .map(|o| o.gamma_size.unwrap())
+            .map(|o| table_key(o.gamma_size.unwrap(), &o.name))
             .collect();
 
-        // Generate one gamma table per unique size, not per output (outputs often share a size)
-        let mut gamma_data_cache: HashMap<usize, Vec<u8>> = HashMap::new();
-
-        for &gamma_size in &unique_gamma_sizes {
-            let gamma_data = gamma::create_gamma_tables(
+        // Look up one gamma table per unique size, not per output (outputs often share a size)
+        let mut gamma_data_cache: HashMap<(usize, Adjustment, Option<String>), Arc<[u8]>> =
+            HashMap::new();
+
+        for (gamma_size, adjustment, own_trim) in unique_tables {
+            let channels = match &own_trim {
+                Some(name) => self.output_channel_gamma[name],
+                None => self.channel_gamma,
+            };
+            let (temperature, gamma, channels) = match adjustment {
+                Adjustment::Full => (temperature, gamma, channels),
+                adjustment => {
+                    let (temperature, gamma_percent) =
+                        adjustment.values((temperature, gamma * 100.0));
+                    (temperature, gamma_percent / 100.0, ChannelGamma::IDENTITY)
+                }
+            };
+            let brightness = adjustment.brightness(self.current_brightness);
+            let gamma_data = self.gamma_tables.get(
                 gamma_size,
                 temperature,
                 gamma,
+                brightness / 100.0,
+                channels,
                 self.debug_enabled && gamma_data_cache.is_empty(), // Debug output only once
             )?;
-            gamma_data_cache.insert(gamma_size, gamma_data);
+            gamma_data_cache.insert((gamma_size, adjustment, own_trim), gamma_data);
         }
 
         if self.debug_enabled {
             log_decorated!("Setting gamma via Wayland protocol");
         }
 
-        // Keep temp files alive until after event dispatch
-        let mut temp_files = Vec::new();
-        let mut successful_outputs = Vec::new();
+        // Write every output's file before sending any request, so the set_gamma requests
+        // leave back to back and the compositor takes them in the same frame instead of
+        // shearing between monitors. Outputs sharing a table still need their own file, as
+        // the compositor reads from the fd's offset, which a clone would share.
+        // The files stay alive until the roundtrip confirms the batch.
+        let mut prepared = Vec::new();
         let mut failed_outputs = Vec::new();
 
-        for output_info in self.state.outputs.iter_mut() {
-            if !output_info.needs_apply {
+        for (index, output_info) in self.state.outputs.iter().enumerate() {
+            if !output_info.needs_apply || !self.output_filter.allows(&output_info.name) {
                 continue;
             }
 
-            if let (Some(gamma_control), Some(output_gamma_size)) =
+            if let (Some(_), Some(output_gamma_size)) =
                 (&output_info.gamma_control, output_info.gamma_size)
             {
-                let gamma_data = gamma_data_cache.get(&output_gamma_size).ok_or_else(|| {
+                let key = table_key(output_gamma_size, &output_info.name);
+                let gamma_data = gamma_data_cache.get(&key).ok_or_else(|| {
                     anyhow::anyhow!("Gamma data not found for size {}", output_gamma_size)
                 })?;
-
-                let mut temp_file = tempfile::tempfile()
-                    .map_err(|e| anyhow::anyhow!("Failed to create temporary file: {}", e))?;
-
-                std::io::Write::write_all(&mut temp_file, gamma_data)
-                    .map_err(|e| anyhow::anyhow!("Failed to write gamma data: {}", e))?;
-
-                std::io::Write::flush(&mut temp_file)
-                    .map_err(|e| anyhow::anyhow!("Failed to flush gamma data: {}", e))?;
-
-                // CRITICAL: rewind to start. The compositor reads from the current position (else EOF)
-                std::io::Seek::seek(&mut temp_file, std::io::SeekFrom::Start(0))
-                    .map_err(|e| anyhow::anyhow!("Failed to reset file position: {}", e))?;
-
-                gamma_control.set_gamma(temp_file.as_fd());
-
-                temp_files.push(temp_file);
-                successful_outputs.push(output_info.name.clone());
+                prepared.push((index, gamma_file(gamma_data)?));
             } else {
                 failed_outputs.push(output_info.name.clone());
                 if self.debug_enabled {
@@ -271,23 +479,34 @@ impl WaylandBackend {
             }
         }
 
-        // dispatch_pending (not blocking_dispatch) so this never hangs
-        match self.event_queue.dispatch_pending(&mut self.state) {
-            Ok(_) => {}
-            Err(e) => {
-                if self.debug_enabled {
-                    log_warning!("Wayland event dispatch failed: {e}");
-                }
-                // Don't fail the whole operation just because of event dispatch issues
+        if prepared.is_empty() {
+            if self.debug_enabled && !failed_outputs.is_empty() {
+                log_warning!("No outputs were available for gamma control");
+            }
+            return Ok(());
+        }
+
+        let mut successful_outputs = Vec::new();
+        let mut sent_to = Vec::new();
+        for (index, temp_file) in &prepared {
+            let output_info = &mut self.state.outputs[*index];
+            if let Some(gamma_control) = &output_info.gamma_control {
+                gamma_control.set_gamma(temp_file.as_fd());
+                successful_outputs.push(output_info.name.clone());
+                sent_to.push(output_info.registry_name);
+                // A `failed` event during the roundtrip below sets this again
+                output_info.needs_apply = false;
             }
         }
 
-        // Roundtrip so the compositor actually processes the gamma tables
-        match self.connection.roundtrip() {
+        // One roundtrip for the whole batch: the compositor has processed every table once it
+        // returns, and any `failed` events it sent have been dispatched
+        match self.event_queue.roundtrip(&mut self.state) {
             Ok(_) => {
+                let values = (self.current_temperature, self.current_gamma_percent);
                 for output in &mut self.state.outputs {
-                    if output.needs_apply {
-                        output.needs_apply = false;
+                    if output.gamma_control.is_some() && sent_to.contains(&output.registry_name) {
+                        output.applied = adjustments.get(&output.name).map(|a| a.values(values));
                     }
                 }
             }
@@ -295,42 +514,343 @@ impl WaylandBackend {
                 if self.debug_enabled {
                     log_warning!("Roundtrip failed: {e}");
                 }
+                for output in &mut self.state.outputs {
+                    if sent_to.contains(&output.registry_name) {
+                        output.needs_apply = true;
+                    }
+                }
             }
         }
 
-        if !successful_outputs.is_empty() {
-            if self.debug_enabled {
-                log_debug!(
-                    "Applied gamma to outputs: {}",
-                    successful_outputs.join(", ")
-                );
+        if self.debug_enabled {
+            log_debug!(
+                "Applied gamma to outputs: {}",
+                successful_outputs.join(", ")
+            );
+        }
+
+        drop(prepared);
+
+        if self.verify_gamma {
+            self.verify_outputs();
+        }
+        Ok(())
+    }
+
+    /// Destroy the gamma controls of outputs the filter leaves out, which hands their gamma
+    /// back to the compositor.
+    fn release_excluded_outputs(&mut self) {
+        for output in &mut self.state.outputs {
+            if self.output_filter.allows(&output.name) {
+                continue;
             }
-        } else if self.debug_enabled && !failed_outputs.is_empty() {
-            log_warning!("No outputs were available for gamma control");
+            if let Some(control) = output.gamma_control.take() {
+                if self.debug_enabled {
+                    log_debug!(
+                        "Releasing gamma control of excluded output '{}'",
+                        output.name
+                    );
+                }
+                control.destroy();
+            }
+            output.gamma_size = None;
+            output.applied = None;
+            output.revoked = false;
+            output.needs_apply = false;
+        }
+    }
+
+    /// Read and dispatch any events the compositor has already sent, without blocking.
+    ///
+    /// A failed read means the socket is closed, which is reported as [`ConnectionLost`].
+    fn dispatch_available_events(&mut self) -> Result<()> {
+        let _ = self.connection.flush();
+
+        // None means events are already queued, and dispatch_pending handles those
+        if let Some(guard) = self.event_queue.prepare_read() {
+            match guard.read() {
+                Ok(_) => {}
+                Err(WaylandError::Io(e)) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(e) => {
+                    if self.debug_enabled {
+                        log_warning!("Failed to read Wayland events: {e}");
+                    }
+                    return Err(ConnectionLost.into());
+                }
+            }
+        }
+
+        if let Err(e) = self.event_queue.dispatch_pending(&mut self.state)
+            && self.debug_enabled
+        {
+            log_warning!("Wayland event dispatch failed: {e}");
+        }
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for events and dispatch them. False when none arrived in time
+    /// or they couldn't be read, which the next poll reports.
+    fn read_events_within(&mut self, timeout: Duration) -> bool {
+        if let Some(guard) = self.event_queue.prepare_read() {
+            let mut fds = [PollFd::new(guard.connection_fd(), PollFlags::POLLIN)];
+            // Round up, so a wait shorter than a millisecond doesn't turn into none at all
+            let millis = timeout.as_micros().div_ceil(1000);
+            match poll(
+                &mut fds,
+                PollTimeout::try_from(millis).unwrap_or(PollTimeout::MAX),
+            ) {
+                Ok(0) | Err(_) => return false,
+                Ok(_) => {
+                    if guard.read().is_err() {
+                        return false;
+                    }
+                }
+            }
+        }
+        self.event_queue.dispatch_pending(&mut self.state).is_ok()
+    }
+
+    /// The compositor reported an output change over its IPC, which can arrive before the
+    /// matching Wayland events. A roundtrip brings the output list up to date. Unless the
+    /// compositor is known to keep gamma across power cycles, every output is then
+    /// reapplied in case its gamma was reset while it was reconfigured.
+    fn reapply_after_output_change(&mut self) -> Result<()> {
+        self.event_queue.roundtrip(&mut self.state)?;
+        // Sway reports toggling HDR as an output change
+        self.refresh_hdr_outputs();
+        if self.quirks.gamma_persists_across_power_cycle {
+            return Ok(());
         }
 
-        drop(temp_files);
+        if self.debug_enabled {
+            log_debug!("Compositor reported an output change, reapplying to all outputs");
+        }
+        for output in &mut self.state.outputs {
+            output.needs_apply = true;
+        }
         Ok(())
     }
+
+    /// Recompute which outputs the focused app leaves neutral, and schedule a reapply (on the
+    /// next hotplug poll) for each output that enters or leaves that set.
+    fn update_neutral_outputs(&mut self) {
+        let neutral = NeutralOutputs::for_focus(&self.focus, &self.neutral_apps);
+        if neutral == self.neutral {
+            return;
+        }
+
+        if self.debug_enabled {
+            log_pipe!();
+            log_debug!("Focused app changed neutral outputs: {:?}", neutral);
+        }
+        for output in &mut self.state.outputs {
+            if neutral.contains(&output.name) != self.neutral.contains(&output.name) {
+                output.needs_apply = true;
+            }
+        }
+        self.neutral = neutral;
+    }
+
+    /// What each output gets of the scheduled tint, by name.
+    fn adjustments(&self) -> HashMap<String, Adjustment> {
+        self.state
+            .outputs
+            .iter()
+            .map(|output| {
+                let presenting = self
+                    .presentation
+                    .as_ref()
+                    .is_some_and(|presentation| presentation.covers(&output.name));
+                let adjustment = if presenting || self.neutral.contains(&output.name) {
+                    Adjustment::Neutral
+                } else if self.hdr_outputs.contains(&output.name) {
+                    let behavior = self.hdr_behaviors.get(&output.name).copied();
+                    Adjustment::for_hdr(behavior.unwrap_or_default(), false)
+                } else {
+                    Adjustment::Full
+                };
+                (output.name.clone(), adjustment)
+            })
+            .collect()
+    }
+
+    /// Re-read which outputs are in HDR, and schedule a reapply (on the next hotplug poll)
+    /// for each output whose adjustment changed with it.
+    fn refresh_hdr_outputs(&mut self) {
+        let mut hdr_outputs = self
+            .compositor_events
+            .as_ref()
+            .map(|events| events.hdr_outputs())
+            .unwrap_or_default();
+        hdr_outputs.extend(
+            self.state
+                .outputs
+                .iter()
+                .filter(|output| output.hdr_signal)
+                .map(|output| output.name.clone()),
+        );
+        if hdr_outputs == self.hdr_outputs {
+            return;
+        }
+
+        hdr::log_change(&hdr_outputs);
+        self.set_hdr(hdr_outputs, self.hdr_behaviors.clone());
+    }
+
+    /// Replace the HDR outputs and behaviors, marking outputs whose adjustment changed.
+    fn set_hdr(
+        &mut self,
+        hdr_outputs: BTreeSet<String>,
+        hdr_behaviors: BTreeMap<String, HdrBehavior>,
+    ) {
+        let before = self.adjustments();
+        self.hdr_outputs = hdr_outputs;
+        self.hdr_behaviors = hdr_behaviors;
+        let after = self.adjustments();
+        for output in &mut self.state.outputs {
+            if before.get(&output.name) != after.get(&output.name) {
+                output.needs_apply = true;
+            }
+        }
+    }
+
+    /// Replace the presentation mode outputs, marking outputs that enter or leave it.
+    fn set_presentation(&mut self, presentation: Option<Presentation>) {
+        let before = self.adjustments();
+        self.presentation = presentation;
+        let after = self.adjustments();
+        for output in &mut self.state.outputs {
+            if before.get(&output.name) != after.get(&output.name) {
+                output.needs_apply = true;
+            }
+        }
+    }
+
+    /// Compare the values tracked for each output against the current targets and schedule
+    /// a reapply (on the next hotplug poll) for any output whose gamma was lost.
+    fn verify_outputs(&mut self) {
+        let scheduled = (self.current_temperature, self.current_gamma_percent);
+        let adjustments = self.adjustments();
+
+        for output in &mut self.state.outputs {
+            let expected = adjustments[&output.name].values(scheduled);
+            let overwritten = if output.revoked {
+                true
+            } else {
+                // Outputs that are still waiting on setup or an apply are not mismatches yet
+                output.gamma_control.is_some()
+                    && output.gamma_size.is_some()
+                    && !output.needs_apply
+                    && output.applied != Some(expected)
+            };
+
+            if !overwritten {
+                continue;
+            }
+
+            output.fights += 1;
+            log_pipe!();
+            if let Some((temp, gamma)) = output.applied {
+                log_warning!(
+                    "Gamma on '{}' was overwritten by another client (expected {}K @ {}%, fight #{})",
+                    output.name,
+                    temp,
+                    gamma,
+                    output.fights
+                );
+            } else {
+                log_warning!(
+                    "Gamma on '{}' does not match the applied state (fight #{})",
+                    output.name,
+                    output.fights
+                );
+            }
+            log_indented!("Reapplying {}K @ {}%", expected.0, expected.1);
+
+            output.revoked = false;
+            output.applied = None;
+            output.needs_apply = true;
+        }
+    }
+}
+
+/// An anonymous file holding `gamma_data`, positioned at its start for the compositor.
+fn gamma_file(gamma_data: &[u8]) -> Result<std::fs::File> {
+    let mut temp_file = tempfile::tempfile()
+        .map_err(|e| anyhow::anyhow!("Failed to create temporary file: {}", e))?;
+
+    std::io::Write::write_all(&mut temp_file, gamma_data)
+        .map_err(|e| anyhow::anyhow!("Failed to write gamma data: {}", e))?;
+
+    std::io::Write::flush(&mut temp_file)
+        .map_err(|e| anyhow::anyhow!("Failed to flush gamma data: {}", e))?;
+
+    // CRITICAL: rewind to start. The compositor reads from the current position (else EOF)
+    std::io::Seek::seek(&mut temp_file, std::io::SeekFrom::Start(0))
+        .map_err(|e| anyhow::anyhow!("Failed to reset file position: {}", e))?;
+
+    Ok(temp_file)
+}
+
+/// The `hdr` setting of each output with an `[outputs.<name>]` table.
+fn hdr_behaviors(config: &Config) -> BTreeMap<String, HdrBehavior> {
+    config
+        .outputs
+        .iter()
+        .map(|(name, output)| (name.clone(), output.hdr))
+        .collect()
 }
 
 impl ColorTemperatureBackend for WaylandBackend {
     fn poll_hotplug(&mut self) -> Result<()> {
         let initial_count = self.state.outputs.len();
 
-        // Roundtrip to actively read the socket. This is how hotplug add/remove events arrive
-        let _ = self.event_queue.roundtrip(&mut self.state);
+        // Hotplug add/remove, gamma_size, and failed events are read as they arrive. A quiet
+        // poll costs one non-blocking read and no roundtrip
+        self.dispatch_available_events()?;
+        self.release_excluded_outputs();
+
+        if self
+            .compositor_events
+            .as_ref()
+            .is_some_and(|events| events.take_outputs_changed())
+        {
+            self.reapply_after_output_change()?;
+        }
+
+        if let Some(focus) = self
+            .compositor_events
+            .as_ref()
+            .and_then(|events| events.take_focus())
+        {
+            self.focus = focus;
+            self.update_neutral_outputs();
+        }
+
+        if self.verify_gamma {
+            self.verify_outputs();
+        }
 
         let current_count = self.state.outputs.len();
-        if current_count != initial_count && self.debug_enabled {
-            log_indented!(
-                "Output count changed: {} -> {}",
-                initial_count,
-                current_count
-            );
+        if current_count != initial_count {
+            if self.debug_enabled {
+                log_indented!(
+                    "Output count changed: {} -> {}",
+                    initial_count,
+                    current_count
+                );
+            }
+            self.refresh_hdr_outputs();
+        }
+        if std::mem::take(&mut self.state.hdr_signal_changed) {
+            self.refresh_hdr_outputs();
         }
 
-        let needs_setup = self.state.outputs.iter().any(|o| o.gamma_control.is_none());
+        let needs_setup = self
+            .state
+            .outputs
+            .iter()
+            .any(|o| o.gamma_control.is_none() && self.output_filter.allows(&o.name));
 
         if needs_setup {
             if self.debug_enabled {
@@ -338,7 +858,7 @@ impl ColorTemperatureBackend for WaylandBackend {
                     .state
                     .outputs
                     .iter()
-                    .filter(|o| o.gamma_control.is_none())
+                    .filter(|o| o.gamma_control.is_none() && self.output_filter.allows(&o.name))
                     .map(|o| o.name.as_str())
                     .collect();
                 log_debug!(
@@ -348,12 +868,19 @@ impl ColorTemperatureBackend for WaylandBackend {
             }
 
             let qh = self.event_queue.handle();
-            Self::setup_gamma_controls(&mut self.state, &qh)?;
+            Self::setup_gamma_controls(&mut self.state, &qh, &self.output_filter)?;
+            self.state.setup_color_outputs(&qh);
 
-            // Roundtrip to receive the new outputs' gamma_size events
-            let _ = self.event_queue.roundtrip(&mut self.state);
+            // No roundtrip here: each new control's gamma_size event flags its output for
+            // apply, which a later poll picks up
+            let _ = self.connection.flush();
         }
 
+        let qh = self.event_queue.handle();
+        self.state.follow_fastest_output(&qh);
+        // A clock put on a new output asks for its configure with a commit
+        let _ = self.connection.flush();
+
         let needs_any_apply = self
             .state
             .outputs
@@ -382,14 +909,15 @@ impl ColorTemperatureBackend for WaylandBackend {
             log_pipe!();
             log_debug!("Wayland backend applying state: temp={temp}K, gamma={gamma:.1}%");
         }
-        self.current_temperature = temp;
-        self.current_gamma_percent = gamma;
+        self.current_temperature = temp.get();
+        self.current_gamma_percent = gamma.get();
+        self.current_brightness = runtime_state.brightness();
 
         for output in &mut self.state.outputs {
             output.needs_apply = true;
         }
 
-        self.apply_gamma_to_outputs(temp, gamma / 100.0)
+        self.apply_gamma_to_outputs(temp.get(), gamma.get() / 100.0)
     }
 
     fn apply_startup_state(
@@ -409,23 +937,145 @@ impl ColorTemperatureBackend for WaylandBackend {
 
     fn apply_temperature_gamma(
         &mut self,
-        temperature: u32,
-        gamma: f64,
+        temperature: Kelvin,
+        gamma: GammaPercent,
         _running: &AtomicBool,
     ) -> Result<()> {
-        self.current_temperature = temperature;
-        self.current_gamma_percent = gamma;
+        self.current_temperature = temperature.get();
+        self.current_gamma_percent = gamma.get();
 
         for output in &mut self.state.outputs {
             output.needs_apply = true;
         }
 
-        self.apply_gamma_to_outputs(temperature, gamma / 100.0)
+        self.apply_gamma_to_outputs(temperature.get(), gamma.get() / 100.0)
+    }
+
+    fn take_output_events(&mut self) -> Vec<OutputEvent> {
+        std::mem::take(&mut self.state.output_events)
+    }
+
+    /// Replace every gamma control with a new one, so outputs whose control failed or
+    /// whose gamma size changed get tables built afresh. Dropping the old controls resets
+    /// each output until the new tables land, within the same call.
+    fn refresh(&mut self) -> Result<()> {
+        // Bring the output list up to date before rebuilding from it
+        self.event_queue.roundtrip(&mut self.state)?;
+        for output in &mut self.state.outputs {
+            if let Some(control) = output.gamma_control.take() {
+                control.destroy();
+            }
+            output.gamma_size = None;
+            output.applied = None;
+            output.revoked = false;
+            output.needs_apply = true;
+        }
+
+        let qh = self.event_queue.handle();
+        Self::setup_gamma_controls(&mut self.state, &qh, &self.output_filter)?;
+        self.state.setup_color_outputs(&qh);
+        // The new controls' gamma_size events arrive with this roundtrip
+        self.event_queue.roundtrip(&mut self.state)?;
+        self.refresh_hdr_outputs();
+
+        if self.debug_enabled {
+            log_debug!(
+                "Recreated gamma controls for {} output(s)",
+                self.state
+                    .outputs
+                    .iter()
+                    .filter(|o| o.gamma_control.is_some())
+                    .count()
+            );
+        }
+        let (temp, gamma) = (self.current_temperature, self.current_gamma_percent);
+        self.apply_gamma_to_outputs(temp, gamma / 100.0)
+    }
+
+    /// Outputs are listed once their name has arrived.
+    fn output_names(&self) -> Option<BTreeSet<String>> {
+        Some(
+            self.state
+                .outputs
+                .iter()
+                .filter(|o| o.announced)
+                .map(|o| o.name.clone())
+                .collect(),
+        )
+    }
+
+    /// Gamma tables take effect on the next frame of each output, so steps faster than the
+    /// fastest output's refresh rate are never seen.
+    fn refresh_interval(&self) -> Option<Duration> {
+        self.state
+            .fastest_output()
+            .and_then(|o| o.refresh_mhz)
+            .map(|mhz| Duration::from_secs_f64(1000.0 / f64::from(mhz)))
+    }
+
+    /// Waits on a frame callback of the fastest output, see [`frames`].
+    fn wait_for_frame(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        // A configure or the answer to an earlier step may be waiting already
+        if self.dispatch_available_events().is_err() {
+            return false;
+        }
+        let qh = self.event_queue.handle();
+        if !self.state.frames.request(&qh) {
+            return false;
+        }
+        let _ = self.connection.flush();
+        while self.state.frames.waiting() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || !self.read_events_within(remaining) {
+                return false;
+            }
+        }
+        self.state.frames.answered()
+    }
+
+    fn reload_config(&mut self, config: &Config) -> Result<()> {
+        if self.neutral_apps != config.neutral_apps {
+            self.neutral_apps = config.neutral_apps.clone();
+            self.update_neutral_outputs();
+        }
+        let hdr_behaviors = hdr_behaviors(config);
+        if hdr_behaviors != self.hdr_behaviors {
+            self.set_hdr(self.hdr_outputs.clone(), hdr_behaviors);
+        }
+        self.refresh_hdr_outputs();
+        if config.presentation != self.presentation {
+            self.set_presentation(config.presentation.clone());
+        }
+        let channel_gamma = config.channel_gamma();
+        let output_channel_gamma = config.output_channel_gamma();
+        if channel_gamma != self.channel_gamma || output_channel_gamma != self.output_channel_gamma
+        {
+            self.channel_gamma = channel_gamma;
+            self.output_channel_gamma = output_channel_gamma;
+            for output in &mut self.state.outputs {
+                output.needs_apply = true;
+            }
+        }
+        let output_filter = config.output_filter();
+        if output_filter != self.output_filter {
+            // Newly allowed outputs get a control on the next poll
+            self.output_filter = output_filter;
+            self.release_excluded_outputs();
+        }
+        Ok(())
     }
 
     fn backend_name(&self) -> &'static str {
         "Wayland"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            keeps_gamma_on_exit: !self.quirks.restores_gamma_on_exit,
+            ..Self::CAPABILITIES
+        }
+    }
 }
 
 impl Dispatch<WlRegistry, ()> for State {
@@ -451,6 +1101,11 @@ impl Dispatch<WlRegistry, ()> for State {
                             registry.bind::<ZwlrGammaControlManagerV1, _, _>(name, version, qh, ());
                         state.gamma_manager = Some(manager);
                     }
+                    // Version 1 is enough to read output image descriptions
+                    "wp_color_manager_v1" => {
+                        let manager = registry.bind::<WpColorManagerV1, _, _>(name, 1, qh, ());
+                        state.color_manager = Some(manager);
+                    }
                     "wl_output" => {
                         let output = registry.bind::<WlOutput, _, _>(name, version, qh, ());
                         // Placeholder until the real name arrives via the Name event
@@ -462,9 +1117,17 @@ impl Dispatch<WlRegistry, ()> for State {
                             name: output_name,
                             needs_apply: true,
                             registry_name: name,
+                            applied: None,
+                            revoked: false,
+                            fights: 0,
+                            announced: false,
+                            refresh_mhz: None,
+                            color_output: None,
+                            pending_tf: None,
+                            hdr_signal: false,
                         });
                     }
-                    _ => {}
+                    interface => state.frames.bind(registry, name, interface, version, qh),
                 }
             }
             Event::GlobalRemove { name } => {
@@ -474,6 +1137,14 @@ impl Dispatch<WlRegistry, ()> for State {
                         if state.debug_enabled {
                             log_debug!("Output removed: {}", output_info.name);
                         }
+                        if output_info.announced {
+                            state
+                                .output_events
+                                .push(OutputEvent::Removed(output_info.name.clone()));
+                        }
+                        if let Some(color_output) = &output_info.color_output {
+                            color_output.destroy();
+                        }
                         false
                     } else {
                         true
@@ -542,6 +1213,7 @@ impl Dispatch<ZwlrGammaControlV1, ()> for State {
                             );
                         }
                         // Drop it so setup_gamma_controls can recreate it
+                        output_info.revoked = output_info.applied.is_some();
                         output_info.gamma_control = None;
                         output_info.gamma_size = None;
                         output_info.needs_apply = true;
@@ -567,17 +1239,130 @@ impl Dispatch<WlOutput, ()> for State {
     ) {
         use wayland_client::protocol::wl_output::Event;
 
-        if let Event::Name { name } = event {
-            for output_info in &mut state.outputs {
-                if &output_info.output == output {
-                    let old_name = output_info.name.clone();
-                    output_info.name = name.clone();
-                    if old_name.starts_with("output-") && state.debug_enabled {
-                        log_debug!("Output identified: {}", name);
+        let Some(output_info) = state.outputs.iter_mut().find(|o| &o.output == output) else {
+            return;
+        };
+
+        match event {
+            Event::Name { name } => {
+                let old_name = output_info.name.clone();
+                output_info.name = name.clone();
+                if old_name.starts_with("output-") && state.debug_enabled {
+                    log_debug!("Output identified: {}", name);
+                }
+            }
+            // Only the current mode says how often the output shows a new frame. Virtual
+            // outputs may report 0 for a rate they don't have.
+            Event::Mode { flags, refresh, .. } => {
+                if let WEnum::Value(flags) = flags
+                    && flags.contains(wl_output::Mode::Current)
+                {
+                    output_info.refresh_mhz = u32::try_from(refresh).ok().filter(|&r| r > 0);
+                }
+            }
+            // `done` follows the initial burst of properties, so the name is final by now
+            Event::Done if !output_info.announced => {
+                output_info.announced = true;
+                state
+                    .output_events
+                    .push(OutputEvent::Added(output_info.name.clone()));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WpColorManagerV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &WpColorManagerV1,
+        _: wp_color_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // Supported features only matter for creating image descriptions, which sunsetr doesn't
+    }
+}
+
+impl Dispatch<WpColorManagementOutputV1, u32> for State {
+    fn event(
+        _: &mut Self,
+        color_output: &WpColorManagementOutputV1,
+        event: wp_color_management_output_v1::Event,
+        registry_name: &u32,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wp_color_management_output_v1::Event::ImageDescriptionChanged = event {
+            color_output.get_image_description(qh, *registry_name);
+        }
+    }
+}
+
+impl Dispatch<WpImageDescriptionV1, u32> for State {
+    fn event(
+        state: &mut Self,
+        description: &WpImageDescriptionV1,
+        event: wp_image_description_v1::Event,
+        registry_name: &u32,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        use wp_image_description_v1::Event;
+        match event {
+            Event::Ready { .. } | Event::Ready2 { .. } => {
+                if let Some(output_info) = state.output_mut(*registry_name) {
+                    output_info.pending_tf = None;
+                }
+                description.get_information(qh, *registry_name);
+                description.destroy();
+            }
+            Event::Failed { msg, .. } => {
+                if state.debug_enabled {
+                    log_debug!("Output image description unavailable: {msg}");
+                }
+                description.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WpImageDescriptionInfoV1, u32> for State {
+    fn event(
+        state: &mut Self,
+        _: &WpImageDescriptionInfoV1,
+        event: wp_image_description_info_v1::Event,
+        registry_name: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        use wp_image_description_info_v1::Event;
+        let debug_enabled = state.debug_enabled;
+        let Some(output_info) = state.output_mut(*registry_name) else {
+            return;
+        };
+        match event {
+            Event::TfNamed { tf } => output_info.pending_tf = Some(tf),
+            Event::Done => {
+                let hdr = output_info.pending_tf.take().is_some_and(is_hdr_transfer);
+                if hdr != output_info.hdr_signal {
+                    if debug_enabled {
+                        log_debug!(
+                            "Color management reports {} in {}",
+                            output_info.name,
+                            if hdr { "HDR" } else { "SDR" }
+                        );
                     }
-                    break;
+                    output_info.hdr_signal = hdr;
+                    state.hdr_signal_changed = true;
                 }
             }
+            _ => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,1519 @@
+//! End-to-end tests for the Wayland backend against an in-process fake compositor.
+//!
+//! The fake compositor implements just enough of `wl_output` and
+//! wlr-gamma-control-unstable-v1 to drive `WaylandBackend` over a real socket: gamma size
+//! negotiation, `set_gamma` table capture, output hotplug, and `failed` events, including
+//! revoking a control that was already in use. It also describes each output through
+//! wp-color-management-v1, so outputs can be reported in HDR. Started with
+//! [`FakeCompositor::start_drawing`], it also offers wlr-layer-shell and answers frame
+//! callbacks as if it drew a frame on every pass over its clients.
+
+use std::io::Read;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use wayland_protocols::wp::color_management::v1::server::{
+    wp_color_management_output_v1::{self, WpColorManagementOutputV1},
+    wp_color_manager_v1::{self, WpColorManagerV1},
+    wp_image_description_info_v1::{self, WpImageDescriptionInfoV1},
+    wp_image_description_v1::{self, WpImageDescriptionV1},
+};
+use wayland_protocols_wlr::gamma_control::v1::server::{
+    zwlr_gamma_control_manager_v1::{self, ZwlrGammaControlManagerV1},
+    zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+};
+use wayland_protocols_wlr::layer_shell::v1::server::{
+    zwlr_layer_shell_v1::{self, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, ZwlrLayerSurfaceV1},
+};
+use wayland_server::{
+    Client, DataInit, Dispatch, Display, DisplayHandle, GlobalDispatch, New, Resource,
+    backend::{ClientData, GlobalId},
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_callback::{self, WlCallback},
+        wl_compositor::{self, WlCompositor},
+        wl_output::{self, WlOutput},
+        wl_region::{self, WlRegion},
+        wl_shm::{self, WlShm},
+        wl_shm_pool::{self, WlShmPool},
+        wl_surface::{self, WlSurface},
+    },
+};
+
+use super::*;
+
+/// A monitor advertised by the fake compositor.
+#[derive(Debug, Clone)]
+struct FakeOutput {
+    name: &'static str,
+    gamma_size: u32,
+    refresh_mhz: i32,
+    // Answer gamma control requests with `failed`, as compositors do when another
+    // client already holds the output's gamma
+    reject_gamma: bool,
+    // Describe the output with the PQ transfer function instead of gamma 2.2
+    hdr: bool,
+}
+
+impl FakeOutput {
+    fn new(name: &'static str, gamma_size: u32) -> Self {
+        Self {
+            name,
+            gamma_size,
+            refresh_mhz: 60_000,
+            reject_gamma: false,
+            hdr: false,
+        }
+    }
+
+    fn with_refresh(mut self, refresh_mhz: i32) -> Self {
+        self.refresh_mhz = refresh_mhz;
+        self
+    }
+
+    fn in_hdr(mut self) -> Self {
+        self.hdr = true;
+        self
+    }
+
+    fn rejecting(name: &'static str) -> Self {
+        Self {
+            name,
+            gamma_size: 256,
+            refresh_mhz: 60_000,
+            reject_gamma: true,
+            hdr: false,
+        }
+    }
+}
+
+/// A gamma table received through `set_gamma`, tagged with the output it was set on.
+#[derive(Debug, Clone)]
+struct AppliedTable {
+    output: &'static str,
+    data: Vec<u8>,
+    // Which pass over the client's requests delivered it
+    dispatch: usize,
+}
+
+enum Command {
+    AddOutput(FakeOutput, mpsc::Sender<GlobalId>),
+    RemoveOutput(GlobalId, mpsc::Sender<()>),
+    RevokeGamma(&'static str, mpsc::Sender<()>),
+    Shutdown,
+}
+
+struct ServerState {
+    applied: Arc<Mutex<Vec<AppliedTable>>>,
+    controls: Vec<(&'static str, ZwlrGammaControlV1)>,
+    // Image description details to send once the request creating them is done, since
+    // `done` destroys the object
+    descriptions: Vec<(WpImageDescriptionInfoV1, bool)>,
+    dispatch: usize,
+    // Layer surfaces with the output each was put on, and whether it was configured
+    layer_surfaces: Vec<(WlSurface, ZwlrLayerSurfaceV1, &'static str, bool)>,
+    layer_outputs: Arc<Mutex<Vec<&'static str>>>,
+    // Frame callbacks to answer on the next pass while `drawing` is set
+    frame_callbacks: Vec<WlCallback>,
+    drawing: Arc<AtomicBool>,
+}
+
+struct TestClient;
+
+impl ClientData for TestClient {}
+
+/// In-process compositor serving a single client on its own thread.
+///
+/// Commands are acknowledged only after the resulting events have been flushed, so a
+/// client roundtrip issued afterwards is guaranteed to observe them.
+struct FakeCompositor {
+    commands: mpsc::Sender<Command>,
+    applied: Arc<Mutex<Vec<AppliedTable>>>,
+    output_ids: Vec<GlobalId>,
+    layer_outputs: Arc<Mutex<Vec<&'static str>>>,
+    drawing: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeCompositor {
+    /// Start the compositor with `outputs` and return it with a client connection.
+    fn start(outputs: Vec<FakeOutput>) -> (Self, Connection) {
+        Self::start_with(outputs, false)
+    }
+
+    /// Start the compositor with wlr-layer-shell and the globals a surface needs, drawing
+    /// frames until told to hold them.
+    fn start_drawing(outputs: Vec<FakeOutput>) -> (Self, Connection) {
+        Self::start_with(outputs, true)
+    }
+
+    fn start_with(outputs: Vec<FakeOutput>, layer_shell: bool) -> (Self, Connection) {
+        let (client_socket, server_socket) = UnixStream::pair().unwrap();
+        let (commands, command_receiver) = mpsc::channel();
+        let (ready_sender, ready_receiver) = mpsc::channel();
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let server_applied = Arc::clone(&applied);
+        let layer_outputs = Arc::new(Mutex::new(Vec::new()));
+        let server_layer_outputs = Arc::clone(&layer_outputs);
+        let drawing = Arc::new(AtomicBool::new(true));
+        let server_drawing = Arc::clone(&drawing);
+
+        let thread = thread::spawn(move || {
+            let mut display: Display<ServerState> = Display::new().unwrap();
+            let handle = display.handle();
+            handle.create_global::<ServerState, ZwlrGammaControlManagerV1, ()>(1, ());
+            handle.create_global::<ServerState, WpColorManagerV1, ()>(1, ());
+            if layer_shell {
+                handle.create_global::<ServerState, WlCompositor, ()>(4, ());
+                handle.create_global::<ServerState, WlShm, ()>(1, ());
+                handle.create_global::<ServerState, ZwlrLayerShellV1, ()>(1, ());
+            }
+            let output_ids: Vec<GlobalId> = outputs
+                .into_iter()
+                .map(|output| handle.create_global::<ServerState, WlOutput, _>(4, output))
+                .collect();
+            display
+                .handle()
+                .insert_client(server_socket, Arc::new(TestClient))
+                .unwrap();
+            ready_sender.send(output_ids).unwrap();
+
+            let mut state = ServerState {
+                applied: server_applied,
+                controls: Vec::new(),
+                descriptions: Vec::new(),
+                dispatch: 0,
+                layer_surfaces: Vec::new(),
+                layer_outputs: server_layer_outputs,
+                frame_callbacks: Vec::new(),
+                drawing: server_drawing,
+            };
+
+            loop {
+                state.dispatch += 1;
+                let _ = display.dispatch_clients(&mut state);
+                for (information, hdr) in state.descriptions.drain(..) {
+                    information.tf_named(if hdr {
+                        wp_color_manager_v1::TransferFunction::St2084Pq
+                    } else {
+                        wp_color_manager_v1::TransferFunction::Gamma22
+                    });
+                    information.done();
+                }
+                if state.drawing.load(Ordering::SeqCst) {
+                    for callback in state.frame_callbacks.drain(..) {
+                        callback.done(0);
+                    }
+                }
+                let _ = display.flush_clients();
+
+                match command_receiver.try_recv() {
+                    Ok(Command::AddOutput(output, reply)) => {
+                        let id = handle.create_global::<ServerState, WlOutput, _>(4, output);
+                        let _ = display.flush_clients();
+                        let _ = reply.send(id);
+                    }
+                    Ok(Command::RemoveOutput(id, reply)) => {
+                        handle.remove_global::<ServerState>(id);
+                        let _ = display.flush_clients();
+                        let _ = reply.send(());
+                    }
+                    Ok(Command::RevokeGamma(name, reply)) => {
+                        state.controls.retain(|(output, control)| {
+                            if *output == name {
+                                control.failed();
+                            }
+                            *output != name
+                        });
+                        let _ = display.flush_clients();
+                        let _ = reply.send(());
+                    }
+                    Ok(Command::Shutdown) | Err(mpsc::TryRecvError::Disconnected) => break,
+                    Err(mpsc::TryRecvError::Empty) => thread::sleep(Duration::from_millis(1)),
+                }
+            }
+        });
+
+        let output_ids = ready_receiver.recv().unwrap();
+        let connection = Connection::from_socket(client_socket).unwrap();
+
+        (
+            Self {
+                commands,
+                applied,
+                output_ids,
+                layer_outputs,
+                drawing,
+                thread: Some(thread),
+            },
+            connection,
+        )
+    }
+
+    fn add_output(&mut self, output: FakeOutput) {
+        let (reply, ack) = mpsc::channel();
+        self.commands
+            .send(Command::AddOutput(output, reply))
+            .unwrap();
+        self.output_ids.push(ack.recv().unwrap());
+    }
+
+    /// Remove the output at `index` in the order outputs were advertised.
+    fn remove_output(&mut self, index: usize) {
+        let id = self.output_ids.remove(index);
+        let (reply, ack) = mpsc::channel();
+        self.commands
+            .send(Command::RemoveOutput(id, reply))
+            .unwrap();
+        ack.recv().unwrap();
+    }
+
+    /// Send `failed` on the gamma control held for `output`, as when another client takes over.
+    fn revoke_gamma(&self, output: &'static str) {
+        let (reply, ack) = mpsc::channel();
+        self.commands
+            .send(Command::RevokeGamma(output, reply))
+            .unwrap();
+        ack.recv().unwrap();
+    }
+
+    fn tables_for(&self, output: &str) -> Vec<Vec<u8>> {
+        self.applied
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|table| table.output == output)
+            .map(|table| table.data.clone())
+            .collect()
+    }
+
+    /// The distinct dispatch passes that delivered the tables received so far.
+    fn table_dispatches(&self) -> std::collections::BTreeSet<usize> {
+        self.applied
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|table| table.dispatch)
+            .collect()
+    }
+
+    fn clear_tables(&self) {
+        self.applied.lock().unwrap().clear();
+    }
+
+    /// The outputs layer surfaces were put on, in order.
+    fn layer_outputs(&self) -> Vec<&'static str> {
+        self.layer_outputs.lock().unwrap().clone()
+    }
+
+    /// Stop answering frame callbacks, as for a surface the compositor isn't drawing.
+    fn hold_frames(&self) {
+        self.drawing.store(false, Ordering::SeqCst);
+    }
+
+    fn draw_frames(&self) {
+        self.drawing.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for FakeCompositor {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl GlobalDispatch<WlOutput, FakeOutput> for ServerState {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WlOutput>,
+        output: &FakeOutput,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let wl_output = data_init.init(resource, output.clone());
+        wl_output.mode(wl_output::Mode::Current, 1920, 1080, output.refresh_mhz);
+        if wl_output.version() >= 4 {
+            wl_output.name(output.name.to_string());
+        }
+        if wl_output.version() >= 2 {
+            wl_output.done();
+        }
+    }
+}
+
+impl Dispatch<WlOutput, FakeOutput> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlOutput,
+        _: wl_output::Request,
+        _: &FakeOutput,
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl GlobalDispatch<ZwlrGammaControlManagerV1, ()> for ServerState {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<ZwlrGammaControlManagerV1>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZwlrGammaControlManagerV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwlrGammaControlManagerV1,
+        request: zwlr_gamma_control_manager_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwlr_gamma_control_manager_v1::Request::GetGammaControl { id, output } = request {
+            let output = output.data::<FakeOutput>().unwrap().clone();
+            let control = data_init.init(id, output.clone());
+            if output.reject_gamma {
+                control.failed();
+            } else {
+                control.gamma_size(output.gamma_size);
+                state.controls.push((output.name, control));
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, FakeOutput> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwlrGammaControlV1,
+        request: zwlr_gamma_control_v1::Request,
+        output: &FakeOutput,
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        if let zwlr_gamma_control_v1::Request::SetGamma { fd } = request {
+            let mut data = Vec::new();
+            std::fs::File::from(fd).read_to_end(&mut data).unwrap();
+            state.applied.lock().unwrap().push(AppliedTable {
+                output: output.name,
+                data,
+                dispatch: state.dispatch,
+            });
+        }
+    }
+}
+
+impl GlobalDispatch<WpColorManagerV1, ()> for ServerState {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WpColorManagerV1>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let manager = data_init.init(resource, ());
+        manager.supported_tf_named(wp_color_manager_v1::TransferFunction::St2084Pq);
+        manager.done();
+    }
+}
+
+impl Dispatch<WpColorManagerV1, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WpColorManagerV1,
+        request: wp_color_manager_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wp_color_manager_v1::Request::GetOutput { id, output } = request {
+            data_init.init(id, output.data::<FakeOutput>().unwrap().clone());
+        }
+    }
+}
+
+impl Dispatch<WpColorManagementOutputV1, FakeOutput> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WpColorManagementOutputV1,
+        request: wp_color_management_output_v1::Request,
+        output: &FakeOutput,
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wp_color_management_output_v1::Request::GetImageDescription { image_description } =
+            request
+        {
+            data_init.init(image_description, output.clone()).ready(1);
+        }
+    }
+}
+
+impl Dispatch<WpImageDescriptionV1, FakeOutput> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &WpImageDescriptionV1,
+        request: wp_image_description_v1::Request,
+        output: &FakeOutput,
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wp_image_description_v1::Request::GetInformation { information } = request {
+            let information = data_init.init(information, ());
+            state.descriptions.push((information, output.hdr));
+        }
+    }
+}
+
+impl Dispatch<WpImageDescriptionInfoV1, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WpImageDescriptionInfoV1,
+        _: wp_image_description_info_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+fn expected_table(size: usize, temperature: u32, gamma_percent: f64) -> Vec<u8> {
+    gamma::create_gamma_tables(
+        size,
+        temperature,
+        gamma_percent / 100.0,
+        1.0,
+        gamma::ChannelGamma::IDENTITY,
+        false,
+    )
+    .unwrap()
+}
+
+fn applied_values(backend: &WaylandBackend, output: &str) -> Option<(u32, f64)> {
+    backend
+        .state
+        .outputs
+        .iter()
+        .find(|o| o.name == output)
+        .and_then(|o| o.applied)
+}
+
+/// Poll until `done` holds. A new control's gamma size arrives on its own schedule, so
+/// settling a hotplug can take more than one poll.
+impl GlobalDispatch<WlCompositor, ()> for ServerState {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WlCompositor>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<WlCompositor, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlCompositor,
+        request: wl_compositor::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_compositor::Request::CreateSurface { id } => {
+                data_init.init(id, ());
+            }
+            wl_compositor::Request::CreateRegion { id } => {
+                data_init.init(id, ());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlSurface, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        surface: &WlSurface,
+        request: wl_surface::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_surface::Request::Frame { callback } => {
+                state.frame_callbacks.push(data_init.init(callback, ()));
+            }
+            // The first commit of a layer surface asks for its configure
+            wl_surface::Request::Commit => {
+                for (layer_surface_of, layer_surface, _, configured) in &mut state.layer_surfaces {
+                    if layer_surface_of == surface && !*configured {
+                        layer_surface.configure(1, 1, 1);
+                        *configured = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl GlobalDispatch<WlShm, ()> for ServerState {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WlShm>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<WlShm, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlShm,
+        request: wl_shm::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_shm::Request::CreatePool { id, .. } = request {
+            data_init.init(id, ());
+        }
+    }
+}
+
+impl Dispatch<WlShmPool, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlShmPool,
+        request: wl_shm_pool::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_shm_pool::Request::CreateBuffer { id, .. } = request {
+            data_init.init(id, ());
+        }
+    }
+}
+
+impl GlobalDispatch<ZwlrLayerShellV1, ()> for ServerState {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<ZwlrLayerShellV1>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZwlrLayerShellV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwlrLayerShellV1,
+        request: zwlr_layer_shell_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwlr_layer_shell_v1::Request::GetLayerSurface {
+            id,
+            surface,
+            output,
+            ..
+        } = request
+        {
+            let output = output
+                .as_ref()
+                .and_then(|output| output.data::<FakeOutput>())
+                .map_or("", |output| output.name);
+            state.layer_outputs.lock().unwrap().push(output);
+            let layer_surface = data_init.init(id, ());
+            state
+                .layer_surfaces
+                .push((surface, layer_surface, output, false));
+        }
+    }
+}
+
+impl Dispatch<WlRegion, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlRegion,
+        _: wl_region::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlBuffer, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlBuffer,
+        _: wl_buffer::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlCallback, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlCallback,
+        _: wl_callback::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &ZwlrLayerSurfaceV1,
+        _: zwlr_layer_surface_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+fn poll_until(backend: &mut WaylandBackend, done: impl Fn(&WaylandBackend) -> bool) {
+    for _ in 0..1000 {
+        backend.poll_hotplug().unwrap();
+        if done(backend) {
+            return;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("backend did not settle");
+}
+
+#[test]
+fn negotiates_gamma_size_per_output() {
+    let (compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256),
+        FakeOutput::new("HDMI-A-1", 1024),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    let mut sizes: Vec<_> = backend
+        .state
+        .outputs
+        .iter()
+        .map(|o| (o.name.clone(), o.gamma_size))
+        .collect();
+    sizes.sort();
+    assert_eq!(
+        sizes,
+        vec![
+            ("DP-1".to_string(), Some(256)),
+            ("HDMI-A-1".to_string(), Some(1024))
+        ]
+    );
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(3300).unwrap(),
+            GammaPercent::new(90.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![expected_table(256, 3300, 90.0)]
+    );
+    assert_eq!(
+        compositor.tables_for("HDMI-A-1"),
+        vec![expected_table(1024, 3300, 90.0)]
+    );
+}
+
+#[test]
+fn every_output_receives_its_table_in_one_batch() {
+    let (compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256),
+        FakeOutput::new("DP-2", 256),
+        FakeOutput::new("HDMI-A-1", 1024),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+    let running = AtomicBool::new(true);
+
+    for (temperature, gamma) in [(5000, 98.0), (4500, 96.0), (4000, 94.0)] {
+        compositor.clear_tables();
+        backend
+            .apply_temperature_gamma(
+                Kelvin::new(temperature).unwrap(),
+                GammaPercent::new(gamma).unwrap(),
+                &running,
+            )
+            .unwrap();
+
+        for (output, size) in [("DP-1", 256), ("DP-2", 256), ("HDMI-A-1", 1024)] {
+            assert_eq!(
+                compositor.tables_for(output),
+                vec![expected_table(size, temperature, gamma)]
+            );
+        }
+        assert_eq!(compositor.table_dispatches().len(), 1);
+    }
+}
+
+#[test]
+fn refresh_interval_follows_the_fastest_output() {
+    let (mut compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256).with_refresh(144_000),
+        FakeOutput::new("HDMI-A-1", 256),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    assert_eq!(
+        backend.refresh_interval(),
+        Some(Duration::from_secs_f64(1.0 / 144.0))
+    );
+
+    compositor.remove_output(0);
+    poll_until(&mut backend, |b| b.state.outputs.len() == 1);
+    assert_eq!(
+        backend.refresh_interval(),
+        Some(Duration::from_secs_f64(1.0 / 60.0))
+    );
+}
+
+#[test]
+fn hotplugged_output_receives_current_values() {
+    let (mut compositor, connection) = FakeCompositor::start(vec![FakeOutput::new("DP-1", 256)]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(4000).unwrap(),
+            GammaPercent::new(80.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+    compositor.clear_tables();
+
+    compositor.add_output(FakeOutput::new("DP-2", 512));
+    poll_until(&mut backend, |b| applied_values(b, "DP-2").is_some());
+
+    assert_eq!(backend.state.outputs.len(), 2);
+    assert_eq!(
+        compositor.tables_for("DP-2"),
+        vec![expected_table(512, 4000, 80.0)]
+    );
+    assert!(
+        compositor.tables_for("DP-1").is_empty(),
+        "existing outputs should not be re-applied on hotplug"
+    );
+}
+
+#[test]
+fn excluded_output_is_never_given_a_table() {
+    let (mut compositor, connection) = FakeCompositor::start(vec![FakeOutput::new("DP-1", 256)]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+    backend.output_filter = Config::builder()
+        .exclude_outputs(vec!["DP-2".to_string()])
+        .build()
+        .output_filter();
+
+    compositor.add_output(FakeOutput::new("DP-2", 256));
+    poll_until(&mut backend, |b| {
+        b.state.outputs.iter().any(|o| o.name == "DP-2")
+    });
+    backend.poll_hotplug().unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(4000).unwrap(),
+            GammaPercent::new(80.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    assert_eq!(
+        compositor.tables_for("DP-1").last(),
+        Some(&expected_table(256, 4000, 80.0))
+    );
+    assert!(compositor.tables_for("DP-2").is_empty());
+    assert!(
+        backend
+            .state
+            .outputs
+            .iter()
+            .all(|o| o.name != "DP-2" || o.gamma_control.is_none())
+    );
+}
+
+#[test]
+fn removed_output_is_dropped() {
+    let (mut compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256),
+        FakeOutput::new("DP-2", 256),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    compositor.remove_output(0);
+    backend.poll_hotplug().unwrap();
+
+    let names: Vec<_> = backend.state.outputs.iter().map(|o| &o.name).collect();
+    assert_eq!(names, vec!["DP-2"]);
+    compositor.clear_tables();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(5000).unwrap(),
+            GammaPercent::new(100.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    assert!(compositor.tables_for("DP-1").is_empty());
+    assert_eq!(compositor.tables_for("DP-2").len(), 1);
+}
+
+#[test]
+fn failed_gamma_control_is_skipped() {
+    let (compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256),
+        FakeOutput::rejecting("DP-2"),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    let rejected = backend
+        .state
+        .outputs
+        .iter()
+        .find(|o| o.name == "DP-2")
+        .unwrap();
+    assert!(rejected.gamma_control.is_none());
+    assert!(rejected.gamma_size.is_none());
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(3500).unwrap(),
+            GammaPercent::new(95.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![expected_table(256, 3500, 95.0)]
+    );
+    assert!(compositor.tables_for("DP-2").is_empty());
+}
+
+#[test]
+fn applied_values_are_tracked_per_output() {
+    let (_compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256),
+        FakeOutput::rejecting("DP-2"),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, true).unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(3300).unwrap(),
+            GammaPercent::new(90.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    let applied: Vec<_> = backend
+        .state
+        .outputs
+        .iter()
+        .map(|o| (o.name.as_str(), o.applied, o.fights))
+        .collect();
+    assert!(applied.contains(&("DP-1", Some((3300, 90.0)), 0)));
+    // Never holding gamma is not a fight
+    assert!(applied.contains(&("DP-2", None, 0)));
+}
+
+#[test]
+fn revoked_gamma_is_reapplied_when_verifying() {
+    let (compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256),
+        FakeOutput::new("DP-2", 256),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, true).unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(4000).unwrap(),
+            GammaPercent::new(85.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+    compositor.clear_tables();
+
+    compositor.revoke_gamma("DP-1");
+    poll_until(&mut backend, |b| applied_values(b, "DP-1").is_some());
+
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![expected_table(256, 4000, 85.0)]
+    );
+    assert!(compositor.tables_for("DP-2").is_empty());
+
+    let dp1 = backend
+        .state
+        .outputs
+        .iter()
+        .find(|o| o.name == "DP-1")
+        .unwrap();
+    assert_eq!(dp1.fights, 1);
+    assert_eq!(dp1.applied, Some((4000, 85.0)));
+
+    // Nothing changed since, so another poll finds no fight
+    backend.poll_hotplug().unwrap();
+    assert_eq!(compositor.tables_for("DP-1").len(), 1);
+}
+
+#[test]
+fn refresh_rebuilds_every_gamma_control() {
+    let (compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256),
+        FakeOutput::new("DP-2", 1024),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(4000).unwrap(),
+            GammaPercent::new(85.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+    compositor.clear_tables();
+    compositor.revoke_gamma("DP-1");
+
+    // No poll in between: the refresh itself picks up the failed control
+    backend.refresh().unwrap();
+
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![expected_table(256, 4000, 85.0)]
+    );
+    assert_eq!(
+        compositor.tables_for("DP-2"),
+        vec![expected_table(1024, 4000, 85.0)]
+    );
+    assert_eq!(applied_values(&backend, "DP-1"), Some((4000, 85.0)));
+}
+
+#[test]
+fn simultaneous_hotplugs_are_applied_once_each() {
+    let (mut compositor, connection) = FakeCompositor::start(vec![FakeOutput::new("DP-1", 256)]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(3800).unwrap(),
+            GammaPercent::new(90.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+    compositor.clear_tables();
+
+    compositor.add_output(FakeOutput::new("DP-2", 256));
+    compositor.add_output(FakeOutput::new("HDMI-A-1", 1024));
+    poll_until(&mut backend, |b| {
+        applied_values(b, "DP-2").is_some() && applied_values(b, "HDMI-A-1").is_some()
+    });
+
+    // Quiet polls after settling must not resend anything
+    for _ in 0..5 {
+        backend.poll_hotplug().unwrap();
+    }
+
+    assert_eq!(
+        compositor.tables_for("DP-2"),
+        vec![expected_table(256, 3800, 90.0)]
+    );
+    assert_eq!(
+        compositor.tables_for("HDMI-A-1"),
+        vec![expected_table(1024, 3800, 90.0)]
+    );
+    assert!(compositor.tables_for("DP-1").is_empty());
+}
+
+#[test]
+fn hotplug_events_name_outputs_added_and_removed() {
+    let (mut compositor, connection) = FakeCompositor::start(vec![FakeOutput::new("DP-1", 256)]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+    assert!(
+        backend.take_output_events().is_empty(),
+        "outputs present at startup are not hotplug events"
+    );
+
+    compositor.add_output(FakeOutput::new("DP-2", 256));
+    poll_until(&mut backend, |b| {
+        b.state
+            .outputs
+            .iter()
+            .any(|o| o.name == "DP-2" && o.announced)
+    });
+    compositor.remove_output(0);
+    poll_until(&mut backend, |b| b.state.outputs.len() == 1);
+
+    assert_eq!(
+        backend.take_output_events(),
+        vec![
+            OutputEvent::Added("DP-2".to_string()),
+            OutputEvent::Removed("DP-1".to_string())
+        ]
+    );
+    assert!(backend.take_output_events().is_empty());
+}
+
+/// Reports a single output change, as niri or sway IPC would.
+struct OneOutputChange(AtomicBool);
+
+impl CompositorEvents for OneOutputChange {
+    fn take_outputs_changed(&self) -> bool {
+        self.0.swap(false, std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+#[test]
+fn compositor_output_change_reapplies_unless_gamma_persists() {
+    for persists in [false, true] {
+        let (compositor, connection) = FakeCompositor::start(vec![FakeOutput::new("DP-1", 256)]);
+        let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+        backend.quirks.gamma_persists_across_power_cycle = persists;
+
+        let running = AtomicBool::new(true);
+        backend
+            .apply_temperature_gamma(
+                Kelvin::new(4000).unwrap(),
+                GammaPercent::new(80.0).unwrap(),
+                &running,
+            )
+            .unwrap();
+        compositor.clear_tables();
+
+        backend.compositor_events = Some(Box::new(OneOutputChange(AtomicBool::new(true))));
+        backend.poll_hotplug().unwrap();
+
+        let expected = if persists {
+            vec![]
+        } else {
+            vec![expected_table(256, 4000, 80.0)]
+        };
+        assert_eq!(
+            compositor.tables_for("DP-1"),
+            expected,
+            "persists: {persists}"
+        );
+    }
+}
+
+#[test]
+fn output_described_in_hdr_is_left_untinted() {
+    let (compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256).in_hdr(),
+        FakeOutput::new("HDMI-A-1", 256),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+    poll_until(&mut backend, |b| b.hdr_outputs.contains("DP-1"));
+    assert_eq!(backend.hdr_outputs, BTreeSet::from(["DP-1".to_string()]));
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(4000).unwrap(),
+            GammaPercent::new(80.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    assert_eq!(
+        compositor.tables_for("DP-1").last(),
+        Some(&expected_table(256, 6500, 100.0))
+    );
+    assert_eq!(
+        compositor.tables_for("HDMI-A-1").last(),
+        Some(&expected_table(256, 4000, 80.0))
+    );
+}
+
+/// Reports one focus change, as compositor IPC does when a window is focused.
+struct OneFocusChange(Mutex<Option<Focus>>);
+
+impl CompositorEvents for OneFocusChange {
+    fn take_outputs_changed(&self) -> bool {
+        false
+    }
+
+    fn take_focus(&self) -> Option<Focus> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+#[test]
+fn focused_neutral_app_leaves_only_its_output_untinted() {
+    let (compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256),
+        FakeOutput::new("HDMI-A-1", 256),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+    backend.neutral_apps = vec!["gimp".to_string()];
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(4000).unwrap(),
+            GammaPercent::new(80.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+    compositor.clear_tables();
+
+    let focus = Focus {
+        app_id: Some("gimp".to_string()),
+        output: Some("DP-1".to_string()),
+    };
+    backend.compositor_events = Some(Box::new(OneFocusChange(Mutex::new(Some(focus)))));
+    backend.poll_hotplug().unwrap();
+
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![expected_table(256, 6500, 100.0)]
+    );
+    assert!(compositor.tables_for("HDMI-A-1").is_empty());
+    assert_eq!(applied_values(&backend, "DP-1"), Some((6500, 100.0)));
+
+    // Focus moving to another app restores the tint
+    compositor.clear_tables();
+    let focus = Focus {
+        app_id: Some("firefox".to_string()),
+        output: Some("DP-1".to_string()),
+    };
+    backend.compositor_events = Some(Box::new(OneFocusChange(Mutex::new(Some(focus)))));
+    backend.poll_hotplug().unwrap();
+
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![expected_table(256, 4000, 80.0)]
+    );
+    assert_eq!(applied_values(&backend, "DP-1"), Some((4000, 80.0)));
+}
+
+#[test]
+fn compositors_that_keep_gamma_ask_for_the_exit_write() {
+    let (_compositor, connection) = FakeCompositor::start(vec![FakeOutput::new("DP-1", 256)]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    backend.quirks = crate::backend::Compositor::Sway.quirks();
+    assert!(!backend.capabilities().keeps_gamma_on_exit);
+    backend.quirks.restores_gamma_on_exit = false;
+    assert!(backend.capabilities().keeps_gamma_on_exit);
+}
+
+#[test]
+fn reloaded_channel_gamma_is_applied_on_the_next_poll() {
+    let (compositor, connection) = FakeCompositor::start(vec![FakeOutput::new("DP-1", 256)]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(4000).unwrap(),
+            GammaPercent::new(80.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+    compositor.clear_tables();
+
+    let config = crate::config::RawConfig {
+        transition_mode: crate::config::TransitionMode::Static,
+        static_temp: Some(4000),
+        static_gamma: Some(80.0),
+        gamma_b: Some(1.25),
+        ..Default::default()
+    }
+    .resolve()
+    .unwrap();
+    backend.reload_config(&config).unwrap();
+    backend.poll_hotplug().unwrap();
+
+    let channels = gamma::ChannelGamma {
+        blue: 1.25,
+        ..gamma::ChannelGamma::IDENTITY
+    };
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![gamma::create_gamma_tables(256, 4000, 0.8, 1.0, channels, false).unwrap()]
+    );
+}
+
+/// Reports a fixed set of outputs in HDR.
+struct HdrOutputs(&'static [&'static str]);
+
+impl CompositorEvents for HdrOutputs {
+    fn take_outputs_changed(&self) -> bool {
+        false
+    }
+
+    fn hdr_outputs(&self) -> std::collections::BTreeSet<String> {
+        self.0.iter().map(|name| name.to_string()).collect()
+    }
+}
+
+#[test]
+fn outputs_with_their_own_trims_get_their_own_tables() {
+    let (compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256),
+        FakeOutput::new("DP-2", 256),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    let config: crate::config::RawConfig = toml::from_str(
+        "transition_mode = \"static\"\nstatic_temp = 4000\nstatic_gamma = 80\n\
+         blue_trim = 0.9\n[outputs.DP-2]\ngreen_trim = 0.8\n",
+    )
+    .unwrap();
+    backend.reload_config(&config.resolve().unwrap()).unwrap();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(4000).unwrap(),
+            GammaPercent::new(80.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    let trimmed = |trim| {
+        let channels = gamma::ChannelGamma {
+            trim,
+            ..gamma::ChannelGamma::IDENTITY
+        };
+        gamma::create_gamma_tables(256, 4000, 0.8, 1.0, channels, false).unwrap()
+    };
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![trimmed((1.0, 1.0, 0.9))]
+    );
+    assert_eq!(
+        compositor.tables_for("DP-2"),
+        vec![trimmed((1.0, 0.8, 0.9))]
+    );
+}
+
+#[test]
+fn hdr_outputs_follow_their_hdr_setting() {
+    let (compositor, connection) = FakeCompositor::start(vec![
+        FakeOutput::new("DP-1", 256),
+        FakeOutput::new("HDMI-A-1", 256),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+    backend.compositor_events = Some(Box::new(HdrOutputs(&["DP-1"])));
+    backend.refresh_hdr_outputs();
+
+    let running = AtomicBool::new(true);
+    backend
+        .apply_temperature_gamma(
+            Kelvin::new(3300).unwrap(),
+            GammaPercent::new(90.0).unwrap(),
+            &running,
+        )
+        .unwrap();
+
+    // Skipped by default, while the SDR output is tinted as usual
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![expected_table(256, 6500, 100.0)]
+    );
+    assert_eq!(
+        compositor.tables_for("HDMI-A-1"),
+        vec![expected_table(256, 3300, 90.0)]
+    );
+    assert_eq!(applied_values(&backend, "DP-1"), Some((6500, 100.0)));
+
+    // Asking for a reduced tint reapplies just that output
+    compositor.clear_tables();
+    let config = crate::config::RawConfig {
+        transition_mode: crate::config::TransitionMode::Static,
+        static_temp: Some(3300),
+        static_gamma: Some(90.0),
+        outputs: Some(
+            [(
+                "DP-1".to_string(),
+                crate::config::OutputConfig {
+                    hdr: HdrBehavior::Reduced,
+                    ..Default::default()
+                },
+            )]
+            .into(),
+        ),
+        ..Default::default()
+    }
+    .resolve()
+    .unwrap();
+    backend.reload_config(&config).unwrap();
+    backend.poll_hotplug().unwrap();
+
+    let reduced = Adjustment::Reduced.values((3300, 90.0));
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![expected_table(256, reduced.0, reduced.1)]
+    );
+    assert!(compositor.tables_for("HDMI-A-1").is_empty());
+    assert_eq!(applied_values(&backend, "DP-1"), Some(reduced));
+
+    // ctm_only has no matrix to go through on this backend
+    backend.set_hdr(
+        backend.hdr_outputs.clone(),
+        [("DP-1".to_string(), HdrBehavior::CtmOnly)].into(),
+    );
+    compositor.clear_tables();
+    backend.poll_hotplug().unwrap();
+    assert_eq!(
+        compositor.tables_for("DP-1"),
+        vec![expected_table(256, 6500, 100.0)]
+    );
+}
+
+#[test]
+fn probe_lists_the_advertised_gamma_protocols() {
+    let (_compositor, connection) = FakeCompositor::start(vec![FakeOutput::new("DP-1", 256)]);
+    let protocols = crate::backend::probe::probe_connection(&connection).unwrap();
+    assert_eq!(
+        protocols,
+        crate::backend::probe::Protocols {
+            hyprland_ctm: false,
+            wlr_gamma: true,
+        }
+    );
+}
+
+#[test]
+fn compositor_exit_is_reported_as_a_lost_connection() {
+    let (compositor, connection) = FakeCompositor::start(vec![FakeOutput::new("DP-1", 256)]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+    backend.poll_hotplug().unwrap();
+
+    drop(compositor);
+    let error = backend.poll_hotplug().unwrap_err();
+    assert!(error.is::<ConnectionLost>(), "unexpected error: {error}");
+}
+
+/// Wait for frames until one is answered, as a clock that was just put on an output has
+/// to be configured before it can be drawn.
+fn wait_for_first_frame(backend: &mut WaylandBackend) {
+    for _ in 0..1000 {
+        backend.poll_hotplug().unwrap();
+        if backend.wait_for_frame(Duration::from_millis(50)) {
+            return;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("no frame was drawn");
+}
+
+#[test]
+fn frames_are_awaited_on_the_fastest_output() {
+    let (mut compositor, connection) = FakeCompositor::start_drawing(vec![
+        FakeOutput::new("DP-1", 256).with_refresh(144_000),
+        FakeOutput::new("HDMI-A-1", 256),
+    ]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+
+    wait_for_first_frame(&mut backend);
+    assert!(backend.wait_for_frame(Duration::from_secs(1)));
+    assert_eq!(compositor.layer_outputs(), ["DP-1"]);
+
+    compositor.remove_output(0);
+    poll_until(&mut backend, |b| b.state.outputs.len() == 1);
+    wait_for_first_frame(&mut backend);
+    assert_eq!(compositor.layer_outputs(), ["DP-1", "HDMI-A-1"]);
+}
+
+#[test]
+fn frames_held_back_time_out() {
+    let (compositor, connection) =
+        FakeCompositor::start_drawing(vec![FakeOutput::new("DP-1", 256)]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+    wait_for_first_frame(&mut backend);
+
+    compositor.hold_frames();
+    let start = Instant::now();
+    assert!(!backend.wait_for_frame(Duration::from_millis(50)));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    // The unanswered callback leaves nothing to wait on until it arrives
+    assert!(!backend.wait_for_frame(Duration::from_secs(1)));
+
+    compositor.draw_frames();
+    wait_for_first_frame(&mut backend);
+}
+
+#[test]
+fn frames_are_not_awaited_without_layer_shell() {
+    let (_compositor, connection) = FakeCompositor::start(vec![FakeOutput::new("DP-1", 256)]);
+    let mut backend = WaylandBackend::from_connection(connection, false, false).unwrap();
+    backend.poll_hotplug().unwrap();
+
+    let start = Instant::now();
+    assert!(!backend.wait_for_frame(Duration::from_secs(1)));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
@@ -0,0 +1,867 @@
+//! Runs a backend on its own thread behind a command channel.
+//!
+//! [`BackendWorker`] implements [`ColorTemperatureBackend`] by forwarding each call as a
+//! [`Command`] to a dedicated thread that owns the real backend. Value applies are
+//! fire-and-forget, so a slow backend (a stalled compositor, a hyprsunset socket that is
+//! being restarted) never holds up the main loop's timing. When the backend falls behind,
+//! consecutive applies are coalesced and only the newest value is sent. The worker also
+//! polls for hotplug events between commands, which keeps Wayland dispatch off the main
+//! loop entirely, and forwards the outputs it sees come and go to IPC clients.
+//!
+//! Startup applies, config reloads and cleanup wait for the worker's reply: they are rare,
+//! their log output has to stay in order, and callers act on their result. Errors from
+//! fire-and-forget applies are reported by the next fire-and-forget apply, after it is
+//! queued, and never in place of another command's own result. Frame waits
+//! during smooth transitions also wait for a reply, and run after the applies queued
+//! before them, so they wait on the frame showing the step just sent.
+//!
+//! When the backend reports [`ConnectionLost`], the worker drops it and calls the factory
+//! again every [`RECONNECT_INTERVAL`] until the compositor is back. Values and configs
+//! that arrive in the meantime are kept, and the newest of each is applied to the new
+//! backend, so a compositor restart does not need a sunsetr restart. The factory may pick
+//! a different backend the second time around, which is why the reported name and
+//! capabilities can change.
+//!
+//! Errors from fire-and-forget applies are held back until [`FAILOVER_AFTER`] fail in a
+//! row. The worker then asks the factory for another backend (see [`super::failover`]) and
+//! replays the latest config and values onto it the same way. Only when the factory has
+//! nothing to offer is the error reported, and the main loop stops as before.
+//!
+//! The backend is constructed on the worker thread, so implementations do not need to be
+//! `Send`. A hyprsunset child spawned there is tied to the worker's lifetime through
+//! PR_SET_PDEATHSIG, which matches the backend's own lifetime.
+
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::failover::FAILOVER_AFTER;
+use super::{Capabilities, ColorTemperatureBackend, ConnectionLost};
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::Config;
+use crate::core::runtime_state::RuntimeState;
+use crate::state::ipc::IpcNotifier;
+
+/// How often the worker polls the backend for hotplug events.
+const HOTPLUG_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often the worker tries to recreate the backend after losing the compositor.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Why the worker's factory is being called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attempt {
+    /// The first backend, when the worker starts.
+    Start,
+    /// A backend after the compositor went away and may have come back.
+    Reconnect,
+    /// Another backend, since the current one keeps failing.
+    Failover,
+}
+
+/// Requests sent from [`BackendWorker`] to its thread.
+///
+/// `running` is sampled when the call is made, so a backend sees the same shutdown state
+/// it would have seen if called directly.
+enum Command {
+    Transition {
+        runtime_state: Box<RuntimeState>,
+        running: bool,
+    },
+    Values {
+        temperature: Kelvin,
+        gamma: GammaPercent,
+        running: bool,
+    },
+    Startup {
+        runtime_state: Box<RuntimeState>,
+        running: bool,
+        reply: Sender<Result<()>>,
+    },
+    Reload {
+        config: Box<Config>,
+        reply: Sender<Result<()>>,
+    },
+    Refresh {
+        reply: Sender<Result<()>>,
+    },
+    Frame {
+        deadline: Instant,
+        reply: Sender<bool>,
+    },
+    Cleanup {
+        debug_enabled: bool,
+    },
+}
+
+impl Command {
+    /// Whether this command only sets display values and can be superseded by a later one.
+    fn is_coalescible(&self) -> bool {
+        matches!(self, Command::Transition { .. } | Command::Values { .. })
+    }
+}
+
+/// What the backend last reported about its outputs, read without a round trip to the
+/// worker thread.
+#[derive(Debug, Clone, Default)]
+struct Outputs {
+    refresh_interval: Option<Duration>,
+    names: Option<BTreeSet<String>>,
+}
+
+impl Outputs {
+    fn of(backend: &dyn ColorTemperatureBackend) -> Self {
+        Self {
+            refresh_interval: backend.refresh_interval(),
+            names: backend.output_names(),
+        }
+    }
+}
+
+/// Handle to a backend running on its own thread.
+pub struct BackendWorker {
+    // Shared with the thread, which updates it when a reconnect picks another backend
+    name: Arc<Mutex<&'static str>>,
+    capabilities: Arc<Mutex<Capabilities>>,
+    // Shared with the thread, which refreshes it as outputs come, go and change mode
+    outputs: Arc<Mutex<Outputs>>,
+    commands: Sender<Command>,
+    errors: Receiver<anyhow::Error>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BackendWorker {
+    /// Start a worker thread and construct the backend on it with `create`, which is
+    /// called again to reconnect after the compositor goes away and to fail over when the
+    /// backend keeps failing. Output hotplug events are sent to `ipc_notifier` when one is
+    /// given.
+    ///
+    /// Blocks until the backend is ready, returning its construction error if it fails.
+    pub fn spawn<F>(mut create: F, ipc_notifier: Option<IpcNotifier>) -> Result<Self>
+    where
+        F: FnMut(Attempt) -> Result<Box<dyn ColorTemperatureBackend>> + Send + 'static,
+    {
+        let (commands, command_receiver) = mpsc::channel();
+        let (error_sender, errors) = mpsc::channel();
+        let (ready_sender, ready) = mpsc::channel();
+
+        let thread = thread::Builder::new()
+            .name("sunsetr-backend".to_string())
+            .spawn(move || match create(Attempt::Start) {
+                Ok(backend) => {
+                    let name = Arc::new(Mutex::new(backend.backend_name()));
+                    let capabilities = Arc::new(Mutex::new(backend.capabilities()));
+                    let outputs = Arc::new(Mutex::new(Outputs::of(backend.as_ref())));
+                    let _ = ready_sender.send(Ok((
+                        Arc::clone(&name),
+                        Arc::clone(&capabilities),
+                        Arc::clone(&outputs),
+                    )));
+                    let worker = Worker {
+                        backend: Some(backend),
+                        create: Box::new(create),
+                        name,
+                        capabilities,
+                        outputs,
+                        errors: error_sender,
+                        ipc_notifier,
+                        last_values: None,
+                        last_config: None,
+                        last_attempt: Instant::now(),
+                        failures: 0,
+                    };
+                    worker.run(command_receiver);
+                }
+                Err(e) => {
+                    let _ = ready_sender.send(Err(e));
+                }
+            })?;
+
+        let (name, capabilities, outputs) = ready
+            .recv()
+            .map_err(|_| anyhow!("Backend worker exited during initialization"))??;
+
+        Ok(Self {
+            name,
+            capabilities,
+            outputs,
+            commands,
+            errors,
+            thread: Some(thread),
+        })
+    }
+
+    /// Queue a command.
+    fn send(&self, command: Command) -> Result<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| anyhow!("{} backend worker has stopped", self.backend_name()))
+    }
+
+    /// Queue a fire-and-forget apply, reporting the oldest error left by an earlier one.
+    /// The apply is queued either way, and each error is reported once, by one apply.
+    fn send_apply(&self, command: Command) -> Result<()> {
+        let deferred = self.errors.try_recv();
+        self.send(command)?;
+        deferred.map_or(Ok(()), Err)
+    }
+
+    /// Queue a command and wait for the worker's reply.
+    fn request(&self, command: impl FnOnce(Sender<Result<()>>) -> Command) -> Result<()> {
+        let (reply, response) = mpsc::channel();
+        self.send(command(reply))?;
+        response
+            .recv()
+            .map_err(|_| anyhow!("{} backend worker has stopped", self.backend_name()))?
+    }
+}
+
+impl ColorTemperatureBackend for BackendWorker {
+    fn apply_transition_state(
+        &mut self,
+        runtime_state: &RuntimeState,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        self.send_apply(Command::Transition {
+            runtime_state: Box::new(runtime_state.clone()),
+            running: running.load(Ordering::SeqCst),
+        })
+    }
+
+    fn apply_startup_state(
+        &mut self,
+        runtime_state: &RuntimeState,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        self.request(|reply| Command::Startup {
+            runtime_state: Box::new(runtime_state.clone()),
+            running: running.load(Ordering::SeqCst),
+            reply,
+        })
+    }
+
+    fn apply_temperature_gamma(
+        &mut self,
+        temperature: Kelvin,
+        gamma: GammaPercent,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        self.send_apply(Command::Values {
+            temperature,
+            gamma,
+            running: running.load(Ordering::SeqCst),
+        })
+    }
+
+    fn backend_name(&self) -> &'static str {
+        *self.name.lock().unwrap()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        *self.capabilities.lock().unwrap()
+    }
+
+    fn output_names(&self) -> Option<BTreeSet<String>> {
+        self.outputs.lock().unwrap().names.clone()
+    }
+
+    fn refresh_interval(&self) -> Option<Duration> {
+        self.outputs.lock().unwrap().refresh_interval
+    }
+
+    /// Waits on the worker thread, after the applies queued before it.
+    fn wait_for_frame(&mut self, timeout: Duration) -> bool {
+        let (reply, shown) = mpsc::channel();
+        let deadline = Instant::now() + timeout;
+        if self
+            .commands
+            .send(Command::Frame { deadline, reply })
+            .is_err()
+        {
+            return false;
+        }
+        shown.recv_timeout(timeout).unwrap_or(false)
+    }
+
+    /// The worker polls on its own thread, so there is nothing to do here.
+    fn poll_hotplug(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn reload_config(&mut self, config: &Config) -> Result<()> {
+        self.request(|reply| Command::Reload {
+            config: Box::new(config.clone()),
+            reply,
+        })
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        self.request(|reply| Command::Refresh { reply })
+    }
+
+    /// Let queued applies finish, clean up the backend and wait for the thread to exit.
+    fn cleanup(mut self: Box<Self>, debug_enabled: bool) {
+        let _ = self.commands.send(Command::Cleanup { debug_enabled });
+        if let Some(thread) = self.thread.take()
+            && thread.join().is_err()
+        {
+            log_warning!("Backend worker panicked during cleanup");
+        }
+    }
+}
+
+/// State owned by the worker thread.
+struct Worker {
+    /// `None` while waiting for the compositor to come back.
+    backend: Option<Box<dyn ColorTemperatureBackend>>,
+    create: Box<dyn FnMut(Attempt) -> Result<Box<dyn ColorTemperatureBackend>> + Send>,
+    name: Arc<Mutex<&'static str>>,
+    capabilities: Arc<Mutex<Capabilities>>,
+    outputs: Arc<Mutex<Outputs>>,
+    errors: Sender<anyhow::Error>,
+    ipc_notifier: Option<IpcNotifier>,
+    // Replayed onto a reconnected backend
+    last_values: Option<Command>,
+    last_config: Option<Box<Config>>,
+    last_attempt: Instant,
+    // Applies that failed in a row on the current backend
+    failures: usize,
+}
+
+impl Worker {
+    /// Execute commands in order until cleanup or until the handle is dropped.
+    fn run(mut self, commands: Receiver<Command>) {
+        let mut pending = VecDeque::new();
+        let mut last_poll = Instant::now();
+
+        loop {
+            if pending.is_empty() {
+                match commands
+                    .recv_timeout(HOTPLUG_POLL_INTERVAL.saturating_sub(last_poll.elapsed()))
+                {
+                    Ok(command) => pending.push_back(command),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            pending.extend(commands.try_iter());
+
+            if last_poll.elapsed() >= HOTPLUG_POLL_INTERVAL {
+                self.poll();
+                last_poll = Instant::now();
+            }
+
+            let Some(command) = pending.pop_front() else {
+                continue;
+            };
+
+            // A newer value is already queued, so sending this one would only delay it
+            if command.is_coalescible() && pending.front().is_some_and(Command::is_coalescible) {
+                continue;
+            }
+
+            if !self.execute(command) {
+                return;
+            }
+        }
+    }
+
+    /// Poll the backend for hotplug events, or try to reconnect when there is none.
+    fn poll(&mut self) {
+        let Some(backend) = &mut self.backend else {
+            if self.last_attempt.elapsed() >= RECONNECT_INTERVAL {
+                self.reconnect();
+            }
+            return;
+        };
+
+        if let Err(e) = backend.poll_hotplug()
+            && e.is::<ConnectionLost>()
+        {
+            log_pipe!();
+            log_warning!("Lost connection to the compositor, waiting for it to return...");
+            // The connection is gone, so there is nothing left to clean up
+            self.backend = None;
+            *self.outputs.lock().unwrap() = Outputs::default();
+            self.last_attempt = Instant::now();
+            return;
+        }
+        *self.outputs.lock().unwrap() = Outputs::of(backend.as_ref());
+
+        for event in backend.take_output_events() {
+            if let Some(notifier) = &self.ipc_notifier {
+                notifier.send_output_event(event);
+            }
+        }
+    }
+
+    /// Recreate the backend and bring it up to date with the latest config and values.
+    fn reconnect(&mut self) {
+        self.last_attempt = Instant::now();
+        // Failing here just means the compositor is not back yet
+        let Ok(backend) = (self.create)(Attempt::Reconnect) else {
+            return;
+        };
+
+        log_block_start!("Reconnected to the compositor");
+        log_indented!("Using the {} backend", backend.backend_name());
+        self.install(backend);
+    }
+
+    /// Replace the failing backend with the factory's alternative, or report `error` when
+    /// it has none.
+    fn fail_over(&mut self, error: anyhow::Error) {
+        self.failures = 0;
+        let failed = *self.name.lock().unwrap();
+        log_pipe!();
+        log_warning!("The {failed} backend failed {FAILOVER_AFTER} times in a row: {error}");
+
+        match (self.create)(Attempt::Failover) {
+            Ok(backend) => {
+                if let Some(old) = self.backend.take() {
+                    old.cleanup(false);
+                }
+                log_indented!("Switched to the {} backend", backend.backend_name());
+                self.install(backend);
+            }
+            Err(e) => {
+                log_indented!("Cannot switch backends: {e}");
+                let _ = self.errors.send(error);
+            }
+        }
+    }
+
+    /// Make `backend` the current one and bring it up to date with the latest config and
+    /// values.
+    fn install(&mut self, mut backend: Box<dyn ColorTemperatureBackend>) {
+        *self.name.lock().unwrap() = backend.backend_name();
+        *self.capabilities.lock().unwrap() = backend.capabilities();
+        *self.outputs.lock().unwrap() = Outputs::of(backend.as_ref());
+
+        let mut result = Ok(());
+        if let Some(config) = &self.last_config {
+            result = backend.reload_config(config);
+        }
+        self.backend = Some(backend);
+        if let Some(values) = self.last_values.take()
+            && result.is_ok()
+        {
+            self.execute(values);
+        }
+        if let Err(e) = result {
+            let _ = self.errors.send(e);
+        }
+    }
+
+    /// Run one command, returning false once the worker should exit.
+    fn execute(&mut self, command: Command) -> bool {
+        let Some(backend) = &mut self.backend else {
+            // Keep what a reconnected backend will need and report success meanwhile
+            match command {
+                Command::Transition { .. } | Command::Values { .. } => {
+                    self.last_values = Some(command);
+                }
+                Command::Startup {
+                    runtime_state,
+                    running,
+                    reply,
+                } => {
+                    self.last_values = Some(Command::Transition {
+                        runtime_state,
+                        running,
+                    });
+                    let _ = reply.send(Ok(()));
+                }
+                Command::Reload { config, reply } => {
+                    self.last_config = Some(config);
+                    let _ = reply.send(Ok(()));
+                }
+                Command::Refresh { reply } => {
+                    // Don't wait for the next attempt, the compositor may be back already
+                    self.reconnect();
+                    let _ = reply.send(Ok(()));
+                }
+                Command::Frame { reply, .. } => {
+                    let _ = reply.send(false);
+                }
+                Command::Cleanup { .. } => return false,
+            }
+            return true;
+        };
+
+        // Startup, reload and refresh answer their caller, only applies count toward failover
+        let apply = command.is_coalescible();
+        let result = match command {
+            Command::Transition {
+                runtime_state,
+                running,
+            } => {
+                let result =
+                    backend.apply_transition_state(&runtime_state, &AtomicBool::new(running));
+                self.last_values = Some(Command::Transition {
+                    runtime_state,
+                    running,
+                });
+                result
+            }
+            Command::Values {
+                temperature,
+                gamma,
+                running,
+            } => {
+                self.last_values = Some(Command::Values {
+                    temperature,
+                    gamma,
+                    running,
+                });
+                backend.apply_temperature_gamma(temperature, gamma, &AtomicBool::new(running))
+            }
+            Command::Startup {
+                runtime_state,
+                running,
+                reply,
+            } => {
+                let _ = reply
+                    .send(backend.apply_startup_state(&runtime_state, &AtomicBool::new(running)));
+                self.last_values = Some(Command::Transition {
+                    runtime_state,
+                    running,
+                });
+                Ok(())
+            }
+            Command::Reload { config, reply } => {
+                let _ = reply.send(backend.reload_config(&config));
+                self.last_config = Some(config);
+                Ok(())
+            }
+            Command::Refresh { reply } => {
+                let result = backend.refresh();
+                *self.outputs.lock().unwrap() = Outputs::of(backend.as_ref());
+                let _ = reply.send(result);
+                Ok(())
+            }
+            Command::Frame { deadline, reply } => {
+                let _ = reply.send(
+                    backend.wait_for_frame(deadline.saturating_duration_since(Instant::now())),
+                );
+                Ok(())
+            }
+            Command::Cleanup { debug_enabled } => {
+                if let Some(backend) = self.backend.take() {
+                    backend.cleanup(debug_enabled);
+                }
+                return false;
+            }
+        };
+
+        match result {
+            Ok(()) if apply => self.failures = 0,
+            Ok(()) => {}
+            Err(e) => {
+                self.failures += 1;
+                if self.failures >= FAILOVER_AFTER {
+                    self.fail_over(e);
+                } else {
+                    log_pipe!();
+                    let name = *self.name.lock().unwrap();
+                    log_warning!("Failed to apply on the {name} backend: {e}");
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::OutputEvent;
+    use crate::common::constants::MINIMUM_TEMP;
+    use crate::state::ipc::events::IpcEvent;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Record {
+        applied: Vec<(u32, f64)>,
+        reloads: usize,
+        polls: usize,
+        cleaned_up: bool,
+        output_events: Vec<OutputEvent>,
+        created: usize,
+        // The next poll reports the compositor gone
+        lose_connection: bool,
+        // The factory has no backend to fail over to
+        refuse_failover: bool,
+    }
+
+    /// Backend that takes `delay` per apply and rejects the lowest temperature.
+    struct SlowBackend {
+        delay: Duration,
+        record: Arc<Mutex<Record>>,
+        capabilities: Capabilities,
+    }
+
+    impl ColorTemperatureBackend for SlowBackend {
+        fn apply_transition_state(&mut self, _: &RuntimeState, _: &AtomicBool) -> Result<()> {
+            Ok(())
+        }
+
+        fn apply_startup_state(&mut self, _: &RuntimeState, _: &AtomicBool) -> Result<()> {
+            Ok(())
+        }
+
+        fn apply_temperature_gamma(
+            &mut self,
+            temperature: Kelvin,
+            gamma: GammaPercent,
+            running: &AtomicBool,
+        ) -> Result<()> {
+            thread::sleep(self.delay);
+            if temperature.get() == MINIMUM_TEMP {
+                return Err(anyhow!("invalid temperature"));
+            }
+            if running.load(Ordering::SeqCst) {
+                self.record
+                    .lock()
+                    .unwrap()
+                    .applied
+                    .push((temperature.get(), gamma.get()));
+            }
+            Ok(())
+        }
+
+        fn backend_name(&self) -> &'static str {
+            "Slow"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            self.capabilities
+        }
+
+        fn poll_hotplug(&mut self) -> Result<()> {
+            let mut record = self.record.lock().unwrap();
+            record.polls += 1;
+            if std::mem::take(&mut record.lose_connection) {
+                return Err(ConnectionLost.into());
+            }
+            Ok(())
+        }
+
+        fn take_output_events(&mut self) -> Vec<OutputEvent> {
+            std::mem::take(&mut self.record.lock().unwrap().output_events)
+        }
+
+        fn reload_config(&mut self, _: &Config) -> Result<()> {
+            self.record.lock().unwrap().reloads += 1;
+            Ok(())
+        }
+
+        fn cleanup(self: Box<Self>, _: bool) {
+            self.record.lock().unwrap().cleaned_up = true;
+        }
+    }
+
+    fn static_config() -> Config {
+        toml::from_str::<crate::config::RawConfig>(
+            "transition_mode = \"static\"\nstatic_temp = 4000\nstatic_gamma = 90.0",
+        )
+        .unwrap()
+        .resolve()
+        .unwrap()
+    }
+
+    fn apply(
+        worker: &mut BackendWorker,
+        temp: u32,
+        gamma: f64,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        let (temp, gamma) = (
+            Kelvin::new(temp).unwrap(),
+            GammaPercent::new(gamma).unwrap(),
+        );
+        worker.apply_temperature_gamma(temp, gamma, running)
+    }
+
+    fn spawn_slow(delay: Duration) -> (Box<BackendWorker>, Arc<Mutex<Record>>) {
+        spawn_slow_with(delay, None)
+    }
+
+    fn spawn_slow_with(
+        delay: Duration,
+        ipc_notifier: Option<IpcNotifier>,
+    ) -> (Box<BackendWorker>, Arc<Mutex<Record>>) {
+        let record = Arc::new(Mutex::new(Record::default()));
+        let backend_record = Arc::clone(&record);
+        let worker = BackendWorker::spawn(
+            move |attempt| {
+                let mut record = backend_record.lock().unwrap();
+                if attempt == Attempt::Failover && record.refuse_failover {
+                    return Err(anyhow!("no other backend"));
+                }
+                record.created += 1;
+                // Backends taking over can't smooth, unlike the first
+                let capabilities = if record.created == 1 {
+                    crate::backend::wayland::WaylandBackend::CAPABILITIES
+                } else {
+                    crate::backend::kde::KdeBackend::CAPABILITIES
+                };
+                drop(record);
+                Ok(Box::new(SlowBackend {
+                    delay,
+                    record: Arc::clone(&backend_record),
+                    capabilities,
+                }) as Box<dyn ColorTemperatureBackend>)
+            },
+            ipc_notifier,
+        )
+        .unwrap();
+        (Box::new(worker), record)
+    }
+
+    #[test]
+    fn slow_backend_does_not_block_applies() {
+        let (mut worker, record) = spawn_slow(Duration::from_millis(20));
+        let running = AtomicBool::new(true);
+
+        let start = Instant::now();
+        for step in 0..50 {
+            apply(&mut worker, 6500 - step * 10, 100.0, &running).unwrap();
+        }
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        worker.cleanup(false);
+        let record = record.lock().unwrap();
+        assert!(record.cleaned_up);
+        assert!(
+            record.applied.len() < 50,
+            "queued applies were not coalesced"
+        );
+        assert_eq!(record.applied.last(), Some(&(6010, 100.0)));
+    }
+
+    #[test]
+    fn apply_errors_surface_once_no_backend_can_take_over() {
+        let (mut worker, record) = spawn_slow(Duration::ZERO);
+        record.lock().unwrap().refuse_failover = true;
+        let running = AtomicBool::new(true);
+
+        // Reload waits for the worker, which keeps the failed applies from being coalesced.
+        // The failures before the last are held back
+        for _ in 1..FAILOVER_AFTER {
+            apply(&mut worker, MINIMUM_TEMP, 100.0, &running).unwrap();
+            worker.reload_config(&static_config()).unwrap();
+        }
+        apply(&mut worker, MINIMUM_TEMP, 100.0, &running).unwrap();
+        // The reload is carried out regardless, leaving the failure to the next apply
+        worker.reload_config(&static_config()).unwrap();
+        let error = apply(&mut worker, 4000, 90.0, &running).unwrap_err();
+        assert_eq!(error.to_string(), "invalid temperature");
+
+        // The apply reporting it was queued all the same, and the failure is reported once
+        worker.refresh().unwrap();
+        apply(&mut worker, 3500, 85.0, &running).unwrap();
+        worker.cleanup(false);
+
+        let record = record.lock().unwrap();
+        assert_eq!(record.reloads, FAILOVER_AFTER);
+        assert_eq!(record.applied, vec![(4000, 90.0), (3500, 85.0)]);
+    }
+
+    #[test]
+    fn failing_backend_is_replaced_with_the_latest_values() {
+        let (mut worker, record) = spawn_slow(Duration::ZERO);
+        let running = AtomicBool::new(true);
+        assert!(worker.capabilities().smoothing);
+
+        for _ in 0..FAILOVER_AFTER {
+            apply(&mut worker, MINIMUM_TEMP, 100.0, &running).unwrap();
+            worker.reload_config(&static_config()).unwrap();
+        }
+        // The replacement's capabilities are reported from then on
+        assert!(!worker.capabilities().smoothing);
+        apply(&mut worker, 3500, 85.0, &running).unwrap();
+        worker.cleanup(false);
+
+        let record = record.lock().unwrap();
+        assert_eq!(record.created, 2);
+        assert_eq!(record.applied, vec![(3500, 85.0)]);
+        assert!(record.cleaned_up);
+    }
+
+    #[test]
+    fn running_flag_is_sampled_when_called() {
+        let (mut worker, record) = spawn_slow(Duration::ZERO);
+        let running = AtomicBool::new(false);
+
+        apply(&mut worker, 3000, 80.0, &running).unwrap();
+        running.store(true, Ordering::SeqCst);
+        worker.cleanup(false);
+
+        assert!(record.lock().unwrap().applied.is_empty());
+    }
+
+    #[test]
+    fn worker_polls_hotplug_on_its_own() {
+        let (worker, record) = spawn_slow(Duration::ZERO);
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while record.lock().unwrap().polls < 3 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        worker.cleanup(false);
+        assert!(record.lock().unwrap().polls >= 3);
+    }
+
+    #[test]
+    fn output_events_are_forwarded_to_ipc() {
+        let (notifier, events) = IpcNotifier::new();
+        let (worker, record) = spawn_slow_with(Duration::ZERO, Some(notifier));
+        record
+            .lock()
+            .unwrap()
+            .output_events
+            .push(OutputEvent::Added("HDMI-A-1".to_string()));
+
+        let event = events.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert!(matches!(event, IpcEvent::OutputAdded { name } if name == "HDMI-A-1"));
+        worker.cleanup(false);
+    }
+
+    #[test]
+    fn construction_errors_are_returned_from_spawn() {
+        let result = BackendWorker::spawn(|_| Err(anyhow!("no compositor")), None);
+        assert_eq!(result.err().unwrap().to_string(), "no compositor");
+    }
+
+    #[test]
+    fn lost_connection_reconnects_with_the_latest_values() {
+        let (mut worker, record) = spawn_slow(Duration::ZERO);
+        let running = AtomicBool::new(true);
+        record.lock().unwrap().lose_connection = true;
+
+        // Wait for the worker to notice, then apply while it is disconnected
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while record.lock().unwrap().lose_connection {
+            assert!(Instant::now() < deadline, "worker stopped polling");
+            thread::sleep(Duration::from_millis(5));
+        }
+        apply(&mut worker, 3500, 85.0, &running).unwrap();
+        apply(&mut worker, 3400, 84.0, &running).unwrap();
+
+        let deadline = Instant::now() + RECONNECT_INTERVAL + Duration::from_secs(2);
+        while record.lock().unwrap().applied.is_empty() {
+            assert!(Instant::now() < deadline, "backend was not recreated");
+            thread::sleep(Duration::from_millis(5));
+        }
+        worker.cleanup(false);
+
+        let record = record.lock().unwrap();
+        assert_eq!(record.created, 2);
+        assert_eq!(record.applied, vec![(3400, 84.0)]);
+        assert!(record.cleaned_up);
+    }
+}
@@ -0,0 +1,343 @@
+//! X11 backend using XRandR per-CRTC gamma ramps.
+//!
+//! Lets one config drive both display servers: with `backend = "auto"`, sessions without a
+//! Wayland display land here. Each active CRTC gets ramps from the shared gamma module and
+//! is named after the first output it drives (`HDMI-1`, as `xrandr` shows it), so
+//! presentation mode takes the same output names as elsewhere. RandR reports screen, CRTC
+//! and output changes, and each one re-enumerates the CRTCs and reapplies, since the X
+//! server may reset a CRTC's ramps when its mode changes.
+//!
+//! The X server keeps the last ramps after sunsetr disconnects, so Core's exit write of the
+//! `shutdown_target` values is what stays on screen. Smooth transitions, HDR handling and
+//! `neutral_apps` are Wayland-only.
+
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::sync::atomic::AtomicBool;
+
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::Event;
+use x11rb::protocol::randr::{self, ConnectionExt as _, NotifyMask};
+use x11rb::protocol::xproto::Window;
+use x11rb::rust_connection::RustConnection;
+
+use crate::backend::gamma::{self, ChannelGamma};
+use crate::backend::hdr::Adjustment;
+use crate::backend::{Capabilities, ColorTemperatureBackend, ConnectionLost, OutputEvent};
+use crate::common::constants::DEFAULT_BRIGHTNESS;
+use crate::common::error::Silent;
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::Config;
+use crate::state::presentation::Presentation;
+
+/// RandR 1.3 added `GetScreenResourcesCurrent`, which doesn't make the server re-probe
+/// outputs on every call.
+const RANDR_MIN_VERSION: (u32, u32) = (1, 3);
+
+/// An active CRTC and the output it is known by.
+#[derive(Debug, Clone)]
+struct Crtc {
+    crtc: randr::Crtc,
+    name: String,
+    gamma_size: usize,
+}
+
+/// XRandR gamma backend for X11 sessions.
+pub struct X11Backend {
+    connection: RustConnection,
+    root: Window,
+    crtcs: Vec<Crtc>,
+    debug_enabled: bool,
+    // Stored so re-enumerated CRTCs can be re-applied without recomputing from state
+    current_temperature: u32,
+    current_gamma_percent: f64,
+    current_brightness: f64,
+    channel_gamma: ChannelGamma,
+    gamma_tables: gamma::TableCache,
+    presentation: Option<Presentation>,
+    output_events: Vec<OutputEvent>,
+}
+
+impl X11Backend {
+    /// Ramps are set per CRTC and re-set on CRTCs that appear.
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        smoothing: false,
+        per_output: true,
+        hardware: true,
+        hotplug: true,
+        keeps_gamma_on_exit: true,
+    };
+
+    /// Connect to `$DISPLAY`, check for RandR 1.3, and find the CRTCs to drive.
+    pub fn new(config: &Config, debug_enabled: bool) -> Result<Self> {
+        if std::env::var("DISPLAY").is_err() {
+            log_error_end!("DISPLAY is not set. Are you running on X11?");
+            return Err(Silent.into());
+        }
+
+        log_decorated!("Initializing X11 gamma control backend...");
+
+        let (connection, screen) = x11rb::connect(None)
+            .map_err(|e| anyhow::anyhow!("Failed to connect to X display: {}", e))?;
+        let root = connection.setup().roots[screen].root;
+
+        let version = connection
+            .randr_query_version(RANDR_MIN_VERSION.0, RANDR_MIN_VERSION.1)
+            .map_err(ReplyError::from)
+            .and_then(|cookie| cookie.reply());
+        match version {
+            Ok(reply) if (reply.major_version, reply.minor_version) >= RANDR_MIN_VERSION => {
+                if debug_enabled {
+                    log_pipe!();
+                    log_debug!(
+                        "Found RandR {}.{}",
+                        reply.major_version,
+                        reply.minor_version
+                    );
+                }
+            }
+            _ => {
+                log_pipe!();
+                log_error!("X server does not support RandR 1.3 or later.");
+                log_indented!("This is required for color temperature control on X11.");
+                log_end!();
+                return Err(Silent.into());
+            }
+        }
+
+        connection.randr_select_input(
+            root,
+            NotifyMask::SCREEN_CHANGE | NotifyMask::CRTC_CHANGE | NotifyMask::OUTPUT_CHANGE,
+        )?;
+
+        let crtcs = active_crtcs(&connection, root)?;
+        if crtcs.is_empty() {
+            log_pipe!();
+            log_error!("No active CRTCs found for gamma control");
+            log_end!();
+            return Err(Silent.into());
+        }
+
+        if debug_enabled {
+            let names: Vec<_> = crtcs.iter().map(|crtc| crtc.name.as_str()).collect();
+            log_debug!(
+                "Initialized gamma control for {} output(s): {}",
+                crtcs.len(),
+                names.join(", ")
+            );
+        }
+
+        Ok(Self {
+            connection,
+            root,
+            crtcs,
+            debug_enabled,
+            current_temperature: 6500,
+            current_gamma_percent: 100.0,
+            current_brightness: DEFAULT_BRIGHTNESS,
+            channel_gamma: config.channel_gamma(),
+            gamma_tables: gamma::TableCache::default(),
+            presentation: config.presentation.clone(),
+            output_events: Vec::new(),
+        })
+    }
+
+    /// Set ramps on every CRTC for the stored values.
+    fn apply_ramps(&mut self) -> Result<()> {
+        let scheduled = (self.current_temperature, self.current_gamma_percent);
+        if self.debug_enabled {
+            log_pipe!();
+            log_debug!(
+                "Applying gamma to {} output(s): temp={}K, gamma={:.0}%, brightness={:.0}%",
+                self.crtcs.len(),
+                scheduled.0,
+                scheduled.1,
+                self.current_brightness
+            );
+        }
+
+        let keys: Vec<(usize, Adjustment)> = self
+            .crtcs
+            .iter()
+            .map(|crtc| {
+                let adjustment =
+                    Adjustment::for_presentation(self.presentation.as_ref(), &crtc.name);
+                (crtc.gamma_size, adjustment)
+            })
+            .collect();
+        let ramps = gamma::ramps_for(
+            keys.iter().copied(),
+            scheduled,
+            self.current_brightness,
+            self.channel_gamma,
+            &mut self.gamma_tables,
+            self.debug_enabled,
+        )?;
+
+        for (crtc, key) in self.crtcs.iter().zip(&keys) {
+            let (red, green, blue) = &ramps[key];
+            self.connection
+                .randr_set_crtc_gamma(crtc.crtc, red, green, blue)?;
+        }
+
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    /// Re-read the active CRTCs after RandR reported a change, record outputs that came
+    /// or went, and reapply to all of them.
+    fn refresh_crtcs(&mut self) -> Result<()> {
+        let crtcs = match active_crtcs(&self.connection, self.root) {
+            Ok(crtcs) => crtcs,
+            Err(ReplyError::ConnectionError(_)) => return Err(ConnectionLost.into()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let before: BTreeSet<String> = self.crtcs.iter().map(|c| c.name.clone()).collect();
+        let after: BTreeSet<String> = crtcs.iter().map(|c| c.name.clone()).collect();
+        self.output_events
+            .extend(OutputEvent::between(&before, &after));
+
+        if self.debug_enabled {
+            log_pipe!();
+            log_debug!(
+                "RandR reported a change, reapplying to: {}",
+                after.iter().cloned().collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        self.crtcs = crtcs;
+        self.apply_ramps()
+    }
+}
+
+/// CRTCs that are driving an output, in the server's order.
+fn active_crtcs(connection: &RustConnection, root: Window) -> Result<Vec<Crtc>, ReplyError> {
+    let resources = connection
+        .randr_get_screen_resources_current(root)?
+        .reply()?;
+
+    let mut crtcs = Vec::new();
+    for &crtc in &resources.crtcs {
+        let info = connection
+            .randr_get_crtc_info(crtc, resources.config_timestamp)?
+            .reply()?;
+        // A disabled CRTC has no mode and drives nothing
+        let Some(&output) = info.outputs.first() else {
+            continue;
+        };
+        if info.mode == 0 {
+            continue;
+        }
+
+        let gamma_size = connection.randr_get_crtc_gamma_size(crtc)?.reply()?.size as usize;
+        if gamma_size < 2 {
+            continue;
+        }
+
+        let output = connection
+            .randr_get_output_info(output, resources.config_timestamp)?
+            .reply()?;
+        crtcs.push(Crtc {
+            crtc,
+            name: String::from_utf8_lossy(&output.name).into_owned(),
+            gamma_size,
+        });
+    }
+    Ok(crtcs)
+}
+
+impl ColorTemperatureBackend for X11Backend {
+    fn poll_hotplug(&mut self) -> Result<()> {
+        let mut changed = false;
+        loop {
+            match self.connection.poll_for_event() {
+                Ok(Some(Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_))) => {
+                    changed = true;
+                }
+                Ok(Some(Event::Error(e))) => {
+                    if self.debug_enabled {
+                        log_warning!("X server reported an error: {:?}", e.error_kind);
+                    }
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(e) => {
+                    if self.debug_enabled {
+                        log_warning!("Failed to read X events: {e}");
+                    }
+                    return Err(ConnectionLost.into());
+                }
+            }
+        }
+
+        if changed {
+            self.refresh_crtcs()?;
+        }
+        Ok(())
+    }
+
+    fn apply_transition_state(
+        &mut self,
+        runtime_state: &crate::core::runtime_state::RuntimeState,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        let (temp, gamma) = runtime_state.values();
+        self.current_temperature = temp.get();
+        self.current_gamma_percent = gamma.get();
+        self.current_brightness = runtime_state.brightness();
+        self.apply_ramps()
+    }
+
+    fn apply_startup_state(
+        &mut self,
+        runtime_state: &crate::core::runtime_state::RuntimeState,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        crate::core::period::log_state_announcement(runtime_state.period());
+        self.apply_transition_state(runtime_state, running)
+    }
+
+    fn apply_temperature_gamma(
+        &mut self,
+        temperature: Kelvin,
+        gamma: GammaPercent,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        self.current_temperature = temperature.get();
+        self.current_gamma_percent = gamma.get();
+        self.apply_ramps()
+    }
+
+    fn take_output_events(&mut self) -> Vec<OutputEvent> {
+        std::mem::take(&mut self.output_events)
+    }
+
+    fn reload_config(&mut self, config: &Config) -> Result<()> {
+        let channel_gamma = config.channel_gamma();
+        if channel_gamma == self.channel_gamma && config.presentation == self.presentation {
+            return Ok(());
+        }
+        self.channel_gamma = channel_gamma;
+        self.presentation = config.presentation.clone();
+        self.apply_ramps()
+    }
+
+    fn output_names(&self) -> Option<BTreeSet<String>> {
+        Some(self.crtcs.iter().map(|crtc| crtc.name.clone()).collect())
+    }
+
+    /// Re-read the CRTCs and their gamma sizes as a RandR change would.
+    fn refresh(&mut self) -> Result<()> {
+        self.refresh_crtcs()
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "X11"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Self::CAPABILITIES
+    }
+}
@@ -0,0 +1,104 @@
+//! Nudge the scheduled temperature and gamma for the rest of the day.
+
+use anyhow::{Context, Result};
+use chrono::{Local, SubsecRound};
+
+use crate::args::AdjustSubcommand;
+use crate::config::Config;
+use crate::core::pause::next_midnight;
+use crate::geo::times::GeoTimes;
+use crate::state::value_offset::{self, ValueOffset};
+
+/// Add to the current offsets, or drop them. A running instance picks the change up
+/// through its state directory watcher.
+pub fn handle_adjust_command(subcommand: &AdjustSubcommand) -> Result<()> {
+    log_version!();
+
+    match *subcommand {
+        AdjustSubcommand::By { temp, gamma } => {
+            let mut config = Config::load()?;
+            let now = Local::now();
+            let current = config.value_offset.take().filter(|a| a.is_active(now));
+
+            let geo_times = GeoTimes::from_config(&config)
+                .context("Failed to initialize geo transition times")?;
+            let state = super::compute::runtime_state(&config, geo_times, now);
+            let offset = ValueOffset {
+                temp: current.map_or(0, |a| a.temp).saturating_add(temp),
+                gamma: current.map_or(0.0, |a| a.gamma) + gamma,
+                expires_at: state
+                    .next_sunrise_start()
+                    .unwrap_or_else(|| next_midnight(now))
+                    .trunc_subsecs(0),
+            };
+
+            if offset.is_zero() {
+                value_offset::clear()?;
+                log_block_start!("Offsets cancel out, back to the scheduled values");
+            } else {
+                value_offset::save(&offset)?;
+                let (scheduled_temp, scheduled_gamma) = state.values();
+                let (temp, gamma) = offset.apply((scheduled_temp, scheduled_gamma));
+                log_block_start!(
+                    "Adjusted by {} until {}",
+                    offset.describe(),
+                    offset.expires_at.format("%a %H:%M")
+                );
+                log_indented!(
+                    "Now {temp}K @ {gamma:.1}% instead of {scheduled_temp}K @ {scheduled_gamma:.1}%"
+                );
+                log_indented!("Undo with: sunsetr adjust reset");
+            }
+        }
+        AdjustSubcommand::Reset => {
+            if value_offset::clear()? {
+                log_block_start!("Adjustment dropped, back to the scheduled values");
+            } else {
+                log_block_start!("No adjustment is set");
+                log_end!();
+                return Ok(());
+            }
+        }
+    }
+
+    if let Ok(pid) = crate::io::instance::get_running_instance_pid() {
+        log_indented!("Configuration reloaded successfully (PID: {})", pid);
+    } else {
+        log_indented!("Takes effect when sunsetr starts");
+    }
+    log_end!();
+    Ok(())
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr adjust <+|-><value>[K|%] [...] | reset");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help adjust");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Nudge the scheduled temperature or gamma for the rest of the day");
+    log_block_start!("Usage: sunsetr adjust <+|-><value>[K|%] [...] | reset");
+    log_block_start!("Arguments:");
+    log_indented!("<+|-><value>[K]   Kelvin to add to or take from the temperature");
+    log_indented!("<+|-><value>%     Percentage points to add to or take from the gamma");
+    log_indented!("reset             Drop the offsets");
+    log_block_start!("Notes:");
+    log_indented!("Offsets add up over several calls and apply on top of whatever the");
+    log_indented!("schedule gives, transitions included. They are kept in the state");
+    log_indented!("directory rather than the config files, survive restarts, and are");
+    log_indented!("dropped when the next sunrise starts, or at midnight in static mode.");
+    log_block_start!("Examples:");
+    log_indented!("# Tonight feels too orange");
+    log_indented!("sunsetr adjust +500");
+    log_pipe!();
+    log_indented!("# Warmer and a little dimmer");
+    log_indented!("sunsetr adjust -300K -10%");
+    log_pipe!();
+    log_indented!("# Back to the schedule");
+    log_indented!("sunsetr adjust reset");
+    log_end!();
+}
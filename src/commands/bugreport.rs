@@ -0,0 +1,294 @@
+//! Gathers what a bug report needs into one markdown file.
+//!
+//! The file holds the `sunsetr doctor` report, the active config, the running instance's
+//! recent IPC events, its recent journal lines and the newest crash report, each in its
+//! own section so it reads well when attached to a GitHub issue. Coordinates are removed
+//! the same way as in crash reports. Parts that can't be read say why instead of failing
+//! the command.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::common::build_info::BuildInfo;
+use crate::common::logger::{RECENT_LINE_COUNT, strip_ansi_codes};
+use crate::io::crash::{REDACTED_KEYS, redact_config, redact_log_line};
+use crate::state::ipc::client::IpcClient;
+use crate::state::ipc::events::IpcEvent;
+
+/// One section of the report: its contents, or why they are missing.
+type Section = Result<String, String>;
+
+struct BugReport {
+    generated: String,
+    doctor: Section,
+    config: Section,
+    events: Section,
+    logs: Section,
+    crash: Option<(String, String)>,
+}
+
+impl BugReport {
+    fn collect() -> Self {
+        Self {
+            generated: chrono::Local::now().to_rfc3339(),
+            doctor: crate::commands::doctor::report_json().map_err(|e| format!("{e:#}")),
+            config: read_config(),
+            events: recent_events(),
+            logs: journal_lines(),
+            crash: latest_crash_report(),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let build = BuildInfo::current();
+        let mut markdown = String::new();
+        let _ = writeln!(markdown, "# sunsetr bug report\n");
+        let _ = writeln!(
+            markdown,
+            "Generated {} by sunsetr {}. Coordinates are removed; check the file before attaching it.\n",
+            self.generated, build.version
+        );
+        let _ = writeln!(
+            markdown,
+            "- Commit: {}",
+            build.git_hash.unwrap_or("unknown")
+        );
+        let _ = writeln!(markdown, "- Profile: {}", build.profile);
+        let _ = writeln!(markdown, "- Target: {}", build.target);
+        let features = if build.features.is_empty() {
+            "none".to_string()
+        } else {
+            build.features.join(", ")
+        };
+        let _ = writeln!(markdown, "- Features: {features}");
+
+        section(&mut markdown, "Doctor", "json", &self.doctor);
+        section(&mut markdown, "Config", "toml", &self.config);
+        section(&mut markdown, "Recent IPC events", "json", &self.events);
+        section(&mut markdown, "Recent logs", "", &self.logs);
+
+        let _ = writeln!(markdown, "\n## Latest crash report\n");
+        match &self.crash {
+            Some((name, content)) => {
+                let _ = writeln!(
+                    markdown,
+                    "<details><summary>{name}</summary>\n\n```\n{}\n```\n\n</details>",
+                    content.trim_end()
+                );
+            }
+            None => {
+                let _ = writeln!(markdown, "None.");
+            }
+        }
+        markdown
+    }
+}
+
+fn section(markdown: &mut String, title: &str, language: &str, section: &Section) {
+    let _ = writeln!(markdown, "\n## {title}\n");
+    match section {
+        Ok(content) => {
+            let _ = writeln!(markdown, "```{language}\n{}\n```", content.trim_end());
+        }
+        Err(reason) => {
+            let _ = writeln!(markdown, "Unavailable: {reason}");
+        }
+    }
+}
+
+/// Write the report to `output`, or to a timestamped file in the current directory.
+pub fn handle_bugreport_command(output: Option<String>) -> Result<()> {
+    log_version!();
+    log_block_start!("Collecting bug report...");
+
+    let report = BugReport::collect();
+    let path = output.map(PathBuf::from).unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "sunsetr-bugreport-{}.md",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ))
+    });
+    std::fs::write(&path, report.to_markdown())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    for (name, section) in [
+        ("Config", &report.config),
+        ("IPC events", &report.events),
+        ("Logs", &report.logs),
+    ] {
+        if let Err(reason) = section {
+            log_indented!("{name}: {reason}");
+        }
+    }
+    log_block_start!("Bug report written to {}", path.display());
+    log_indented!("Coordinates are removed, but read it through before attaching it");
+    log_end!();
+    Ok(())
+}
+
+fn read_config() -> Section {
+    let path = crate::commands::resolve_target_config_path(None).map_err(|e| format!("{e:#}"))?;
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    Ok(redact_config(&content))
+}
+
+fn recent_events() -> Section {
+    let mut client = IpcClient::connect().map_err(|_| "sunsetr is not running".to_string())?;
+    let events = client
+        .recent_events()
+        .map_err(|e| format!("the running instance did not answer: {e:#}"))?;
+    if events.is_empty() {
+        return Err("no events since sunsetr started".to_string());
+    }
+    Ok(events
+        .iter()
+        .map(|event| redact_event(event).to_string())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// The event as JSON with the values of [`REDACTED_KEYS`] removed.
+fn redact_event(event: &IpcEvent) -> serde_json::Value {
+    let mut value = serde_json::to_value(event).unwrap_or_default();
+    if let Some(fields) = value.as_object_mut() {
+        for key in REDACTED_KEYS {
+            if let Some(field) = fields.get_mut(*key) {
+                *field = "<removed>".into();
+            }
+        }
+    }
+    value
+}
+
+/// The last lines of the systemd user service's journal.
+fn journal_lines() -> Section {
+    let output = Command::new("journalctl")
+        .args([
+            "--user",
+            "--unit",
+            "sunsetr.service",
+            "--no-pager",
+            "--output",
+            "cat",
+        ])
+        .args(["--lines", &RECENT_LINE_COUNT.to_string()])
+        .output()
+        .map_err(|e| format!("journalctl could not be run: {e}"))?;
+    if !output.status.success() {
+        return Err("journalctl failed, sunsetr may not run as a systemd service".to_string());
+    }
+
+    let text = strip_ansi_codes(&String::from_utf8_lossy(&output.stdout));
+    let lines: Vec<_> = text
+        .lines()
+        .filter(|line| !line.starts_with("-- "))
+        .map(redact_log_line)
+        .collect();
+    if lines.is_empty() {
+        return Err("the sunsetr.service journal is empty".to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+/// The name and contents of the newest crash report, already redacted when written.
+fn latest_crash_report() -> Option<(String, String)> {
+    let config_dir = crate::config::get_custom_config_dir();
+    let state_dir = crate::state::preset::get_state_dir(config_dir.as_deref()).ok()?;
+    let path = newest_crash_report(&state_dir)?;
+    let content = std::fs::read_to_string(&path).ok()?;
+    Some((path.file_name()?.to_string_lossy().into_owned(), content))
+}
+
+fn newest_crash_report(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("crash-") && name.ends_with(".txt"))
+        })
+        // Names start with the time, so the last in order is the newest
+        .max()
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr bugreport [--output <file>]");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help bugreport");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Collect what a bug report needs into one markdown file");
+    log_block_start!("Usage: sunsetr bugreport [--output <file>]");
+    log_block_start!("Options:");
+    log_indented!("-o, --output <file>  Write the report here instead of the current directory");
+    log_block_start!("Contents:");
+    log_indented!("Version, doctor report, config, recent IPC events, recent journal");
+    log_indented!("lines and the newest crash report. Coordinates are removed.");
+    log_block_start!("Examples:");
+    log_indented!("# Write sunsetr-bugreport-<time>.md to the current directory");
+    log_indented!("sunsetr bugreport");
+    log_pipe!();
+    log_indented!("# Write it somewhere else");
+    log_indented!("sunsetr bugreport --output /tmp/sunsetr.md");
+    log_end!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PlaceholderCoordinates, TransitionMode};
+
+    #[test]
+    fn report_sections_and_redaction() {
+        let rejected = IpcEvent::coordinates_rejected(
+            &PlaceholderCoordinates {
+                latitude: 12.5,
+                longitude: 12.5,
+                reason: "latitude equals longitude",
+            },
+            TransitionMode::FinishBy,
+        );
+        let redacted = redact_event(&rejected);
+        assert_eq!(redacted["latitude"], "<removed>");
+        assert_eq!(redacted["longitude"], "<removed>");
+        assert_eq!(redacted["reason"], "latitude equals longitude");
+
+        let report = BugReport {
+            generated: "2025-01-15T23:21:40+00:00".to_string(),
+            doctor: Ok("{}".to_string()),
+            config: Ok("night_temp = 3300\n".to_string()),
+            events: Err("sunsetr is not running".to_string()),
+            logs: Ok("┣ Night temp: 3300K".to_string()),
+            crash: None,
+        };
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("## Config\n\n```toml\nnight_temp = 3300\n```"));
+        assert!(markdown.contains("## Recent IPC events\n\nUnavailable: sunsetr is not running"));
+        assert!(markdown.contains("## Latest crash report\n\nNone."));
+    }
+
+    #[test]
+    fn newest_crash_report_is_picked_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "crash-20250115-232140-42.txt",
+            "crash-20250116-080000-7.txt",
+            "overrides.toml",
+        ] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        assert_eq!(
+            newest_crash_report(dir.path()),
+            Some(dir.path().join("crash-20250116-080000-7.txt"))
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Computes the values sunsetr would apply at a given instant.
+//!
+//! Runs the active config through the same schedule and [`RuntimeState`] the daemon uses,
+//! with the clock pinned to the requested instant, and prints the result. No backend is
+//! started and the running instance is not contacted, so other systems can reuse
+//! sunsetr's timing and interpolation, e.g. to drive their own outputs.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::{Config, TransitionMode};
+use crate::core::period::Period;
+use crate::core::runtime_state::RuntimeState;
+use crate::core::schedule::Schedule;
+use crate::geo::times::GeoTimes;
+use crate::time::source::{SimulatedTimeSource, SimulationPace};
+
+/// Formats accepted by `--at` when no UTC offset is given.
+const NAIVE_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+];
+
+/// The state sunsetr would apply at one instant.
+#[derive(Debug, Serialize)]
+struct ComputedState {
+    time: DateTime<Local>,
+    period: Period,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<f32>,
+    temp: Kelvin,
+    gamma: GammaPercent,
+    /// Start of the following period, absent in static mode.
+    next_period: Option<DateTime<Local>>,
+}
+
+impl ComputedState {
+    fn from_state(state: &RuntimeState, time: DateTime<Local>) -> Self {
+        let (temp, gamma) = state.values();
+        Self {
+            time,
+            period: state.period(),
+            progress: state.phase().progress(),
+            temp,
+            gamma,
+            next_period: state.next_period_start(),
+        }
+    }
+}
+
+/// Print the values for `at`, or for now when not given, as JSON when `json` is set.
+pub fn handle_compute_command(at: Option<String>, json: bool) -> Result<()> {
+    let config = Config::load()?;
+    let time = match at {
+        Some(text) => parse_instant(&text, geo_timezone(&config))?,
+        None => Local::now(),
+    };
+
+    // Geo times and every schedule query read the clock, so pin it to the instant
+    crate::time::source::init_time_source(Arc::new(SimulatedTimeSource::new(
+        time,
+        time,
+        SimulationPace::FastForward,
+    )));
+
+    let geo_times =
+        GeoTimes::from_config(&config).context("Failed to initialize geo transition times")?;
+    let state = runtime_state(&config, geo_times, time);
+    let computed = ComputedState::from_state(&state, time);
+
+    if json {
+        println!("{}", serde_json::to_string(&computed)?);
+    } else {
+        display_state(&computed);
+    }
+    Ok(())
+}
+
+/// The runtime state at `time`, on `geo_times` computed for that instant in geo mode.
+pub(crate) fn runtime_state(
+    config: &Config,
+    geo_times: Option<GeoTimes>,
+    time: DateTime<Local>,
+) -> RuntimeState {
+    RuntimeState::at(config, Schedule::from_config(config, geo_times), time)
+}
+
+/// Geo times as the daemon would have them at `time`, or `None` outside geo mode.
+pub(crate) fn geo_times_at(config: &Config, time: DateTime<Local>) -> Result<Option<GeoTimes>> {
+    match (config.transition_mode, config.latitude, config.longitude) {
+        (TransitionMode::Geo, Some(latitude), Some(longitude)) => {
+            GeoTimes::at(latitude, longitude, time)
+                .map(Some)
+                .context("Failed to calculate solar times")
+        }
+        _ => Ok(None),
+    }
+}
+
+/// The configured location's timezone in geo mode, which naive times are read in.
+fn geo_timezone(config: &Config) -> Option<chrono_tz::Tz> {
+    if config.transition_mode != TransitionMode::Geo {
+        return None;
+    }
+    Some(crate::geo::solar::determine_timezone(
+        config.latitude?,
+        config.longitude?,
+    ))
+}
+
+/// Parse `--at` as RFC 3339, or as a date and time without offset in `tz` (local time
+/// when `None`), matching how `--simulate` reads its range.
+fn parse_instant(text: &str, tz: Option<chrono_tz::Tz>) -> Result<DateTime<Local>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Local));
+    }
+
+    let naive = NAIVE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .with_context(|| {
+            format!("Invalid time '{text}'. Use YYYY-MM-DDTHH:MM[:SS] or an RFC 3339 timestamp")
+        })?;
+
+    let time = match tz {
+        Some(tz) => tz
+            .from_local_datetime(&naive)
+            .single()
+            .map(|time| time.with_timezone(&Local)),
+        None => Local.from_local_datetime(&naive).single(),
+    };
+    time.with_context(|| format!("'{text}' is ambiguous or does not exist in this timezone"))
+}
+
+fn display_state(state: &ComputedState) {
+    log_version!();
+    log_block_start!("Values at {}:", state.time.format("%Y-%m-%d %H:%M:%S %:z"));
+    match state.progress {
+        Some(progress) => log_indented!(
+            "Period:      {} ({:.1}%)",
+            state.period.display_name(),
+            progress * 100.0
+        ),
+        None => log_indented!("Period:      {}", state.period.display_name()),
+    }
+    log_indented!("Temperature: {}K", state.temp);
+    log_indented!("Gamma:       {:.1}%", state.gamma);
+    if let Some(next) = state.next_period {
+        log_indented!("Next period: {}", next.format("%Y-%m-%d %H:%M:%S"));
+    }
+    log_end!();
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr compute [--at <time>] [--json]");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help compute");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Compute the values sunsetr would apply at a given time");
+    log_block_start!("Usage: sunsetr compute [--at <time>] [--json]");
+    log_block_start!("Options:");
+    log_indented!("--at <time>  Instant to compute, YYYY-MM-DDTHH:MM[:SS] or RFC 3339");
+    log_indented!("             (defaults to now)");
+    log_indented!("--json       Output the values as a JSON object");
+    log_block_start!("Examples:");
+    log_indented!("# Values for the current time");
+    log_indented!("sunsetr compute");
+    log_pipe!();
+    log_indented!("# Values for a midsummer night, for another program to apply");
+    log_indented!("sunsetr compute --at 2025-06-21T23:00 --json");
+    log_end!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::units::values;
+    use crate::config::{Backend, UpdateInterval};
+
+    fn config() -> Config {
+        Config::builder()
+            .backend(Backend::Wayland)
+            .smoothing(false)
+            .startup_duration(0.0)
+            .shutdown_duration(0.0)
+            .update_interval(UpdateInterval::Fixed(60))
+            .transition_duration(60)
+            .build()
+    }
+
+    fn local(text: &str) -> DateTime<Local> {
+        parse_instant(text, None).unwrap()
+    }
+
+    #[test]
+    fn instants_parse_with_and_without_offset() {
+        let naive = local("2025-06-21T23:00");
+        assert_eq!(naive, local("2025-06-21 23:00:00"));
+        assert_eq!(naive.format("%H:%M:%S").to_string(), "23:00:00");
+
+        let utc = parse_instant("2025-06-21T23:00:00Z", None).unwrap();
+        assert_eq!(utc.timestamp(), 1_750_546_800);
+
+        // A naive time in the geo timezone keeps that wall clock time there
+        let tokyo = parse_instant("2025-06-21T23:00", Some(chrono_tz::Asia::Tokyo)).unwrap();
+        assert_eq!(tokyo.timestamp(), 1_750_514_400);
+
+        assert!(parse_instant("tonight", None).is_err());
+        assert!(parse_instant("2025-06-21", None).is_err());
+    }
+
+    #[test]
+    fn stable_periods_report_configured_values() {
+        let config = config();
+        let time = local("2025-06-21T23:00");
+        let computed = ComputedState::from_state(&runtime_state(&config, None, time), time);
+
+        assert_eq!(computed.period, Period::Night);
+        assert_eq!((computed.temp, computed.gamma), values(3300, 90.0));
+        assert_eq!(computed.progress, None);
+        assert_eq!(computed.next_period, Some(local("2025-06-22T05:00")));
+    }
+
+    #[test]
+    fn transitions_report_progress_and_interpolated_values() {
+        let config = config();
+        let time = local("2025-06-21T18:30");
+        let computed = ComputedState::from_state(&runtime_state(&config, None, time), time);
+
+        assert_eq!(computed.period, Period::Sunset);
+        assert_eq!(computed.progress, Some(0.5));
+        assert_eq!((computed.temp, computed.gamma), values(4378, 95.0));
+        assert_eq!(computed.next_period, Some(local("2025-06-21T19:00")));
+
+        let value = serde_json::to_value(&computed).unwrap();
+        assert_eq!(value["period"], "sunset");
+        assert_eq!(value["temp"], 4378);
+        assert_eq!(value["gamma"], 95.0);
+    }
+
+    #[test]
+    fn static_mode_has_no_next_period() {
+        let mut config = config();
+        config.transition_mode = TransitionMode::Static;
+        config.static_temp = Some(4000);
+        config.static_gamma = Some(85.0);
+        let time = local("2025-06-21T12:00");
+        let computed = ComputedState::from_state(&runtime_state(&config, None, time), time);
+
+        assert_eq!(computed.period, Period::Static);
+        assert_eq!((computed.temp, computed.gamma), values(4000, 85.0));
+        assert_eq!(computed.next_period, None);
+        let value = serde_json::to_value(&computed).unwrap();
+        assert!(value["next_period"].is_null());
+        assert!(value.get("progress").is_none());
+    }
+}
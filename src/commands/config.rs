@@ -0,0 +1,99 @@
+//! Inspect the configuration format and check the active config.
+//!
+//! `sunsetr config schema` prints the JSON Schema of `sunsetr.toml`, for editors, home-manager
+//! modules and GUI frontends that want to validate a config the way sunsetr does. It reads no
+//! config. `sunsetr config check` loads the active config as the daemon would, without
+//! starting a backend, and reports how many updates each transition takes.
+
+use anyhow::{Context, Result};
+
+use crate::args::ConfigSubcommand;
+use crate::common::constants::MINIMUM_TRANSITION_STEPS;
+use crate::config::{Config, UpdateInterval};
+use crate::core::period::Period;
+use crate::core::period::calculations::fixed_interval_for_transition;
+use crate::core::schedule::{Schedule, Scheduler};
+use crate::geo::times::GeoTimes;
+
+pub fn handle_config_command(subcommand: &ConfigSubcommand) -> Result<()> {
+    match subcommand {
+        ConfigSubcommand::Schema => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&crate::config::schema::schema())?
+            );
+        }
+        ConfigSubcommand::Check => check_config()?,
+    }
+    Ok(())
+}
+
+/// Load the active config, which logs any validation warnings and fails on an error, then
+/// list its transitions with their updates.
+fn check_config() -> Result<()> {
+    let path = crate::commands::resolve_target_config_path(None)?;
+    if !path.exists() {
+        anyhow::bail!("No config at {}", path.display());
+    }
+
+    log_version!();
+    let config = Config::load_from_path(&path)?;
+    log_block_start!("{} is valid", path.display());
+
+    let geo_times =
+        GeoTimes::from_config(&config).context("Failed to initialize geo transition times")?;
+    if let Some(schedule) = Schedule::from_config(&config, geo_times) {
+        for (name, period) in [("Sunset", Period::Sunset), ("Sunrise", Period::Sunrise)] {
+            if let Some(length) = schedule.transition_length(period) {
+                log_indented!("{name}: {}", describe_transition(&config, length.as_secs()));
+            }
+        }
+    }
+    log_end!();
+    Ok(())
+}
+
+/// A transition's length and the updates it takes at the configured interval.
+fn describe_transition(config: &Config, length_secs: u64) -> String {
+    let minutes = length_secs / 60;
+    let UpdateInterval::Fixed(configured) = config.update_interval else {
+        return format!("{minutes} min, adaptive updates");
+    };
+
+    let interval = fixed_interval_for_transition(config, configured, length_secs);
+    let steps = length_secs / interval;
+    let updates = if steps == 1 { "update" } else { "updates" };
+    let mut line = format!("{minutes} min, {steps} {updates} every {interval} seconds");
+    if interval < configured {
+        line.push_str(&format!(" (adjusted from {configured})"));
+    } else if steps < MINIMUM_TRANSITION_STEPS {
+        line.push_str(&format!(" (fewer than {MINIMUM_TRANSITION_STEPS})"));
+    }
+    line
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr config <subcommand>");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help config");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Inspect the configuration format or check the active config");
+    log_block_start!("Usage: sunsetr config <subcommand>");
+    log_block_start!("Subcommands:");
+    log_indented!("schema  Print a JSON Schema of sunsetr.toml with every field,");
+    log_indented!("        its type, range, default and deprecation");
+    log_indented!("check   Validate the active config and list how many updates");
+    log_indented!("        each transition takes");
+    log_block_start!("Examples:");
+    log_indented!("# Save the schema for an editor's TOML language server");
+    log_indented!("sunsetr config schema > ~/.config/sunsetr/sunsetr.schema.json");
+    log_pipe!();
+    log_indented!("# Check a config after editing it");
+    log_indented!("sunsetr config check");
+    log_end!();
+}
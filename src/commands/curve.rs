@@ -0,0 +1,329 @@
+//! Charts the temperature and gamma sunsetr would apply over one day.
+//!
+//! Samples the active config through the same schedule and [`RuntimeState`] as `compute`,
+//! so modes, transition durations, easing and the wind-down all show up as they will on
+//! screen. The charts are drawn in the terminal, or written as an SVG document with
+//! `--svg`.
+//!
+//! [`RuntimeState`]: crate::core::runtime_state::RuntimeState
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use std::fmt::Write;
+
+use crate::commands::compute::{geo_times_at, runtime_state};
+use crate::config::Config;
+
+/// Columns of a terminal chart, one per 20 minutes.
+const CHART_COLUMNS: usize = 72;
+/// Lines of a terminal chart. Each cell is split in half with `▄`.
+const CHART_ROWS: usize = 10;
+/// Intervals along an SVG curve, one per 5 minutes.
+const SVG_INTERVALS: i32 = 288;
+
+const SVG_WIDTH: f64 = 960.0;
+const SVG_LEFT: f64 = 70.0;
+const SVG_RIGHT: f64 = 20.0;
+const SVG_PANEL_HEIGHT: f64 = 200.0;
+
+/// Values at one instant of the day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sample {
+    temp: u32,
+    gamma: f64,
+}
+
+/// Chart `date`, today when not given, in the terminal or as SVG on stdout.
+pub fn handle_curve_command(date: Option<String>, svg: bool) -> Result<()> {
+    let config = Config::load()?;
+    let date = match date {
+        Some(text) => NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+            .with_context(|| format!("Invalid date '{text}'. Use YYYY-MM-DD"))?,
+        None => Local::now().date_naive(),
+    };
+    let (start, end) = day_bounds(date)?;
+
+    if svg {
+        let times = (0..=SVG_INTERVALS).map(|i| start + (end - start) * i / SVG_INTERVALS);
+        print!("{}", render_svg(&sample(&config, times)?, date));
+    } else {
+        let slot = (end - start) / CHART_COLUMNS as i32;
+        let times = (0..CHART_COLUMNS as i32).map(|i| start + slot * i + slot / 2);
+        display_charts(&config, &sample(&config, times)?, date);
+    }
+    Ok(())
+}
+
+/// Local midnight at the start and end of `date`.
+fn day_bounds(date: NaiveDate) -> Result<(DateTime<Local>, DateTime<Local>)> {
+    let midnight = |date: NaiveDate| {
+        Local
+            .from_local_datetime(&date.and_time(chrono::NaiveTime::MIN))
+            .earliest()
+            .with_context(|| format!("Midnight of {date} does not exist in this timezone"))
+    };
+    let next = date.succ_opt().context("Date out of range")?;
+    Ok((midnight(date)?, midnight(next)?))
+}
+
+fn sample(config: &Config, times: impl Iterator<Item = DateTime<Local>>) -> Result<Vec<Sample>> {
+    times
+        .map(|time| {
+            let geo_times = geo_times_at(config, time)?;
+            let (temp, gamma) = runtime_state(config, geo_times, time).values();
+            Ok(Sample {
+                temp: temp.get(),
+                gamma: gamma.get(),
+            })
+        })
+        .collect()
+}
+
+fn display_charts(config: &Config, samples: &[Sample], date: NaiveDate) {
+    let temps: Vec<f64> = samples.iter().map(|s| f64::from(s.temp)).collect();
+    let gammas: Vec<f64> = samples.iter().map(|s| s.gamma).collect();
+
+    log_version!();
+    log_block_start!("{date} ({} mode)", config.transition_mode);
+    log_block_start!("Temperature:");
+    for line in chart(&temps, |value| format!("{value:.0}K")) {
+        log_indented!("{line}");
+    }
+    log_block_start!("Gamma:");
+    for line in chart(&gammas, |value| format!("{value:.1}%")) {
+        log_indented!("{line}");
+    }
+    log_end!();
+}
+
+/// An area chart of `values`, one column each, with the range labeled on the left and
+/// the hours below.
+fn chart(values: &[f64], label: impl Fn(f64) -> String) -> Vec<String> {
+    let (low, high) = range(values);
+    let half_rows = CHART_ROWS * 2;
+    // In half rows, at least one so the lowest values still show
+    let heights: Vec<usize> = values
+        .iter()
+        .map(|&value| {
+            if high > low {
+                1 + ((value - low) / (high - low) * (half_rows - 1) as f64).round() as usize
+            } else {
+                half_rows
+            }
+        })
+        .collect();
+
+    let mut lines: Vec<String> = (0..CHART_ROWS)
+        .map(|row| {
+            let base = (CHART_ROWS - 1 - row) * 2;
+            let cells: String = heights
+                .iter()
+                .map(|&height| match height.saturating_sub(base) {
+                    0 => ' ',
+                    1 => '▄',
+                    _ => '█',
+                })
+                .collect();
+            let axis = match row {
+                0 => label(high),
+                _ if row == CHART_ROWS - 1 => label(low),
+                _ => String::new(),
+            };
+            format!("{axis:>7} │{cells}").trim_end().to_string()
+        })
+        .collect();
+
+    lines.push(format!("{:>7} └{}", "", "─".repeat(values.len())));
+    lines.push(format!("{:>7}  {}", "", hour_labels(values.len())));
+    lines
+}
+
+/// `00:00` to `24:00` every six hours, spread over `width` columns. Labels that don't
+/// fit are left out.
+fn hour_labels(width: usize) -> String {
+    let mut labels = vec![' '; width];
+    for hour in [0, 6, 12, 18, 24] {
+        let text = format!("{hour:02}:00");
+        let column = (width * hour / 24).min(width.saturating_sub(text.len()));
+        if let Some(slot) = labels.get_mut(column..column + text.len()) {
+            for (cell, c) in slot.iter_mut().zip(text.chars()) {
+                *cell = c;
+            }
+        }
+    }
+    labels.into_iter().collect()
+}
+
+fn range(values: &[f64]) -> (f64, f64) {
+    values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &value| {
+            (low.min(value), high.max(value))
+        })
+}
+
+/// Both curves as an SVG document, temperature above gamma.
+fn render_svg(samples: &[Sample], date: NaiveDate) -> String {
+    let temps: Vec<f64> = samples.iter().map(|s| f64::from(s.temp)).collect();
+    let gammas: Vec<f64> = samples.iter().map(|s| s.gamma).collect();
+    let height = 40.0 + 2.0 * SVG_PANEL_HEIGHT + 90.0;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{SVG_WIDTH}\" height=\"{height}\" \
+         viewBox=\"0 0 {SVG_WIDTH} {height}\" font-family=\"sans-serif\" font-size=\"12\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n\
+         <text x=\"{SVG_LEFT}\" y=\"24\" font-size=\"16\">sunsetr curves for {date}</text>\n"
+    );
+    svg_panel(&mut svg, &temps, 40.0, "#e8833a", |value| {
+        format!("{value:.0}K")
+    });
+    svg_panel(
+        &mut svg,
+        &gammas,
+        40.0 + SVG_PANEL_HEIGHT + 50.0,
+        "#4a78c2",
+        |value| format!("{value:.1}%"),
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// One framed panel at `top` with hour gridlines, the range labeled and `values` as a line.
+fn svg_panel(
+    svg: &mut String,
+    values: &[f64],
+    top: f64,
+    color: &str,
+    label: impl Fn(f64) -> String,
+) {
+    let (low, high) = range(values);
+    // A flat curve is drawn through the middle
+    let (low, high) = if high > low {
+        (low, high)
+    } else {
+        (low - 1.0, high + 1.0)
+    };
+    let width = SVG_WIDTH - SVG_LEFT - SVG_RIGHT;
+    let bottom = top + SVG_PANEL_HEIGHT;
+
+    let _ = writeln!(
+        svg,
+        "<rect x=\"{SVG_LEFT}\" y=\"{top}\" width=\"{width}\" height=\"{SVG_PANEL_HEIGHT}\" \
+         fill=\"none\" stroke=\"#cccccc\"/>"
+    );
+    for hour in (0..=24).step_by(3) {
+        let x = SVG_LEFT + width * f64::from(hour) / 24.0;
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{x:.1}\" y1=\"{top}\" x2=\"{x:.1}\" y2=\"{bottom}\" stroke=\"#eeeeee\"/>\n\
+             <text x=\"{x:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{hour:02}:00</text>",
+            bottom + 16.0
+        );
+    }
+    let _ = writeln!(
+        svg,
+        "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n\
+         <text x=\"{:.1}\" y=\"{bottom:.1}\" text-anchor=\"end\">{}</text>",
+        SVG_LEFT - 6.0,
+        top + 12.0,
+        label(high),
+        SVG_LEFT - 6.0,
+        label(low)
+    );
+
+    let last = values.len().saturating_sub(1).max(1) as f64;
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let x = SVG_LEFT + width * i as f64 / last;
+            let y = bottom - (value - low) / (high - low) * SVG_PANEL_HEIGHT;
+            format!("{x:.1},{y:.1}")
+        })
+        .collect();
+    let _ = writeln!(
+        svg,
+        "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"2\"/>",
+        points.join(" ")
+    );
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr curve [--date <YYYY-MM-DD>] [--svg]");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help curve");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Chart the temperature and gamma over one day");
+    log_block_start!("Usage: sunsetr curve [--date <YYYY-MM-DD>] [--svg]");
+    log_block_start!("Options:");
+    log_indented!("--date <date>  Day to chart, in local time (defaults to today)");
+    log_indented!("--svg          Write the charts as an SVG document to stdout");
+    log_block_start!("Examples:");
+    log_indented!("# Check tonight's transitions before nightfall");
+    log_indented!("sunsetr curve");
+    log_pipe!();
+    log_indented!("# Midwinter in geo mode, as an image");
+    log_indented!("sunsetr curve --date 2025-12-21 --svg > curve.svg");
+    log_end!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charts_fill_up_to_each_value() {
+        let values = [3300.0, 3300.0, 4900.0, 6500.0];
+        let lines = chart(&values, |value| format!("{value:.0}K"));
+
+        assert_eq!(lines.len(), CHART_ROWS + 2);
+        assert_eq!(lines[0], "  6500K │   █");
+        assert_eq!(lines[CHART_ROWS - 1], "  3300K │▄▄██");
+        assert!(lines[CHART_ROWS].ends_with("└────"));
+    }
+
+    #[test]
+    fn flat_charts_fill_every_row() {
+        let lines = chart(&[4000.0; 3], |value| format!("{value:.0}K"));
+        assert_eq!(lines[0], "  4000K │███");
+        assert!(
+            lines[1..CHART_ROWS]
+                .iter()
+                .all(|line| line.ends_with("███"))
+        );
+    }
+
+    #[test]
+    fn hour_labels_span_the_day() {
+        let labels = hour_labels(CHART_COLUMNS);
+        assert_eq!(labels.chars().count(), CHART_COLUMNS);
+        assert!(labels.starts_with("00:00"));
+        assert_eq!(&labels[36..41], "12:00");
+        assert!(labels.ends_with("24:00"));
+    }
+
+    #[test]
+    fn svg_draws_one_line_per_curve() {
+        let samples = [
+            Sample {
+                temp: 3300,
+                gamma: 90.0,
+            },
+            Sample {
+                temp: 6500,
+                gamma: 100.0,
+            },
+        ];
+        let svg = render_svg(&samples, NaiveDate::from_ymd_opt(2025, 6, 21).unwrap());
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.contains("6500K") && svg.contains("90.0%"));
+    }
+}
@@ -0,0 +1,233 @@
+//! Reports how sunsetr sees the current session.
+//!
+//! Collects the facts behind sunsetr's runtime decisions (session variables, detected
+//! compositor, its quirks profile, compositor IPC and backend selection) without starting
+//! a backend or touching the running instance. `--json` emits the same report for scripts
+//! and packaging tests that assert the expected behavior per compositor.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::backend::quirks::CompositorQuirks;
+use crate::backend::{Compositor, Session, detect_compositor, resolve_backend};
+use crate::common::build_info::BuildInfo;
+use crate::common::constants::DEFAULT_BACKEND;
+use crate::config::{Backend, Config};
+
+/// Everything `sunsetr doctor` reports.
+#[derive(Debug, Serialize)]
+struct DoctorReport {
+    version: &'static str,
+    /// The same metadata as `sunsetr --version --json`.
+    build: BuildInfo,
+    session: Session,
+    compositor: String,
+    quirks: CompositorQuirks,
+    /// Every profile sunsetr knows, whichever compositor is running, with `untested` for
+    /// the rest.
+    profiles: BTreeMap<String, CompositorQuirks>,
+    /// Compositor IPC the Wayland backend follows for output changes.
+    compositor_ipc: Option<&'static str>,
+    /// Backend from the active config, or the default when there is no config yet.
+    configured_backend: Backend,
+    /// Backend sunsetr would start with, or `None` if the configured one can't run here.
+    resolved_backend: Option<String>,
+    config_error: Option<String>,
+}
+
+impl DoctorReport {
+    fn collect() -> Self {
+        let session = Session::from_env();
+        let compositor = detect_compositor();
+
+        // Never create a config here; a missing one reports the default backend
+        let (configured_backend, config_error) =
+            match crate::commands::resolve_target_config_path(None) {
+                Ok(path) if !path.exists() => (DEFAULT_BACKEND, None),
+                Ok(path) => match Config::load_from_path(&path) {
+                    Ok(config) => (config.backend, None),
+                    Err(e) => (DEFAULT_BACKEND, Some(format!("{e:#}"))),
+                },
+                Err(e) => (DEFAULT_BACKEND, Some(format!("{e:#}"))),
+            };
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            build: BuildInfo::current(),
+            compositor_ipc: session.compositor_ipc(),
+            resolved_backend: resolve_backend(configured_backend, &session)
+                .map(|backend| backend.name().to_lowercase()),
+            quirks: compositor.quirks(),
+            profiles: profiles(),
+            compositor: compositor.to_string(),
+            session,
+            configured_backend,
+            config_error,
+        }
+    }
+}
+
+/// The quirks of each tested compositor by name, and those assumed for any other.
+fn profiles() -> BTreeMap<String, CompositorQuirks> {
+    Compositor::TESTED
+        .iter()
+        .map(|compositor| (compositor.to_string(), compositor.quirks()))
+        .chain([("untested".to_string(), CompositorQuirks::UNTESTED)])
+        .collect()
+}
+
+/// Print the report, as JSON when `json` is set.
+pub fn handle_doctor_command(json: bool) -> Result<()> {
+    let report = DoctorReport::collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        display_report(&report);
+    }
+    Ok(())
+}
+
+/// The report as pretty JSON, for `sunsetr bugreport`.
+pub fn report_json() -> Result<String> {
+    Ok(serde_json::to_string_pretty(&DoctorReport::collect())?)
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+fn display_report(report: &DoctorReport) {
+    let session = &report.session;
+    log_version!();
+    log_block_start!("Build:");
+    log_indented!("Commit:  {}", report.build.git_hash.unwrap_or("unknown"));
+    log_indented!("Profile: {}", report.build.profile);
+    log_indented!("Target:  {}", report.build.target);
+    log_block_start!("Session:");
+    log_indented!("WAYLAND_DISPLAY set:   {}", yes_no(session.wayland_display));
+    log_indented!("DISPLAY set:           {}", yes_no(session.x11_display));
+    log_indented!(
+        "Hyprland instance:     {}",
+        yes_no(session.hyprland_instance)
+    );
+    log_indented!("NIRI_SOCKET set:       {}", yes_no(session.niri_socket));
+    log_indented!("SWAYSOCK set:          {}", yes_no(session.swaysock));
+    log_indented!(
+        "XDG_CURRENT_DESKTOP:   {}",
+        session.xdg_current_desktop.as_deref().unwrap_or("(unset)")
+    );
+    if let Some(ref outer) = session.nested_in {
+        log_indented!("Nested inside:         {outer}");
+    }
+
+    log_block_start!("Compositor: {}", report.compositor);
+    log_indented!(
+        "Gamma persists across power cycles: {}",
+        yes_no(report.quirks.gamma_persists_across_power_cycle)
+    );
+    log_indented!(
+        "Restores gamma on exit:             {}",
+        yes_no(report.quirks.restores_gamma_on_exit)
+    );
+    log_indented!(
+        "Output change events via IPC:       {}",
+        report.compositor_ipc.unwrap_or("none")
+    );
+
+    log_block_start!("Backend:");
+    log_indented!("Configured: {}", report.configured_backend);
+    log_indented!(
+        "Resolved:   {}",
+        report.resolved_backend.as_deref().unwrap_or("unavailable")
+    );
+
+    if let Some(error) = &report.config_error {
+        log_pipe!();
+        log_warning!("Could not read the active config: {error}");
+    }
+    log_end!();
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr doctor [--json]");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help doctor");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Report how sunsetr sees the current session");
+    log_block_start!("Usage: sunsetr doctor [--json]");
+    log_block_start!("Options:");
+    log_indented!("--json     Output the report in JSON format");
+    log_block_start!("Examples:");
+    log_indented!("# Show the detected compositor, its quirks and backend choice");
+    log_indented!("sunsetr doctor");
+    log_pipe!();
+    log_indented!("# Machine-readable report for scripts and packaging tests");
+    log_indented!("sunsetr doctor --json");
+    log_end!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_serializes_with_stable_field_names() {
+        let report = DoctorReport {
+            version: "0.0.0",
+            build: BuildInfo::current(),
+            session: Session {
+                wayland_display: true,
+                niri_socket: true,
+                xdg_current_desktop: Some("niri".to_string()),
+                ..Session::default()
+            },
+            compositor: "niri".to_string(),
+            quirks: Compositor::Niri.quirks(),
+            profiles: profiles(),
+            compositor_ipc: Some("niri"),
+            configured_backend: Backend::Auto,
+            resolved_backend: Some("wayland".to_string()),
+            config_error: None,
+        };
+
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(value["compositor"], "niri");
+        assert_eq!(value["session"]["niri_socket"], true);
+        assert_eq!(value["quirks"]["gamma_persists_across_power_cycle"], false);
+        assert_eq!(value["quirks"]["restores_gamma_on_exit"], true);
+        assert_eq!(value["compositor_ipc"], "niri");
+        assert_eq!(value["configured_backend"], "auto");
+        assert_eq!(value["resolved_backend"], "wayland");
+        assert!(value["config_error"].is_null());
+        assert_eq!(value["build"]["format_version"], 1);
+    }
+
+    #[test]
+    fn profiles_list_every_tested_compositor() {
+        let profiles = serde_json::to_value(profiles()).unwrap();
+        let expected = [
+            ("hyprland", true, true),
+            ("labwc", true, true),
+            ("niri", false, true),
+            ("river", false, true),
+            ("sway", true, true),
+            ("untested", false, false),
+        ];
+
+        assert_eq!(profiles.as_object().unwrap().len(), expected.len());
+        for (name, persists, restores) in expected {
+            assert_eq!(
+                profiles[name]["gamma_persists_across_power_cycle"], persists,
+                "{name}"
+            );
+            assert_eq!(profiles[name]["restores_gamma_on_exit"], restores, "{name}");
+        }
+    }
+}
@@ -2,7 +2,11 @@
 
 use anyhow::Result;
 
-pub fn handle_geo_command(debug_enabled: bool, target: Option<String>) -> Result<()> {
+pub fn handle_geo_command(
+    debug_enabled: bool,
+    target: Option<String>,
+    provider: Option<String>,
+) -> Result<()> {
     if crate::io::instance::is_test_mode_active() {
         log_error_end!(
             "Cannot change location while test mode is active\n   Exit test mode first (press Escape in the test terminal)"
@@ -10,14 +14,16 @@ pub fn handle_geo_command(debug_enabled: bool, target: Option<String>) -> Result
         return Ok(());
     }
 
-    match crate::geo::run_geo_workflow(debug_enabled, target)? {
+    crate::config::ensure_config_writable()?;
+
+    match crate::geo::run_geo_workflow(debug_enabled, target, provider.as_deref())? {
         crate::geo::GeoSelectionResult::Updated => {
             log_block_start!("Configuration updated.");
             log_end!();
             Ok(())
         }
         crate::geo::GeoSelectionResult::Cancelled => {
-            log_block_start!("City selection cancelled.");
+            log_block_start!("Location selection cancelled.");
             log_end!();
             Ok(())
         }
@@ -31,6 +37,7 @@ pub fn show_usage() {
     log_indented!("-t, --target <name>  Target configuration to update");
     log_indented!("                     'default' = base configuration");
     log_indented!("                     <name> = named preset");
+    log_indented!("-p, --provider <name>  Where to get the location from");
     log_pipe!();
     log_info!("For detailed help with examples, try: sunsetr help geo");
     log_end!();
@@ -44,6 +51,11 @@ pub fn display_help() {
     log_indented!("-t, --target <name>  Target configuration to update");
     log_indented!("                     'default' = base configuration");
     log_indented!("                     <name> = named preset");
+    log_indented!("-p, --provider <name>  Where to get the location from");
+    log_block_start!("Location Providers:");
+    for provider in crate::geo::provider::PROVIDERS {
+        log_indented!("{:<10} {}", provider.name(), provider.description());
+    }
     log_block_start!("Features:");
     log_indented!("- Search by city name (partial matching)");
     log_indented!("- Filter results by city/country");
@@ -69,6 +81,9 @@ pub fn display_help() {
     log_indented!("# Update a specific preset's coordinates");
     log_indented!("sunsetr geo --target gaming");
     log_pipe!();
+    log_indented!("# Use the system timezone without the interactive search");
+    log_indented!("sunsetr geo --provider timezone");
+    log_pipe!();
     log_indented!("# Update the default config in a custom base directory");
     log_indented!("sunsetr --config ~/.dotfiles/sunsetr/ geo --target default");
     log_end!();
@@ -99,13 +99,16 @@ pub fn handle_get_command(fields: &[String], target: Option<&str>, json: bool) -
                     log_error!("Unknown configuration field: '{}'", field);
                     log_block_start!("Available fields:");
                     log_indented!("all (special: returns all fields)");
-                    log_indented!("backend, transition_mode");
+                    log_indented!("backend, transition_mode, verify_gamma, hyprsunset_mode");
+                    log_indented!("smoothing, reduce_motion, startup_duration, shutdown_duration");
+                    log_indented!("shutdown_target, adaptive_interval");
                     log_indented!(
-                        "smoothing, startup_duration, shutdown_duration, adaptive_interval"
+                        "night_temp, day_temp, night_gamma, day_gamma, night_saturation, update_interval"
                     );
-                    log_indented!("night_temp, day_temp, night_gamma, day_gamma, update_interval");
+                    log_indented!("night_brightness, day_brightness");
                     log_indented!("static_temp, static_gamma");
-                    log_indented!("sunset, sunrise, transition_duration");
+                    log_indented!("sunset, sunrise, transition_duration, night_latest_start");
+                    log_indented!("min_day_duration");
                     log_indented!("latitude, longitude");
                     log_end!();
                     std::process::exit(1);
@@ -131,6 +134,22 @@ pub fn handle_get_command(fields: &[String], target: Option<&str>, json: bool) -
     Ok(())
 }
 
+/// Print the runtime overrides as `field=value` lines or a JSON object, nothing when none
+/// are set.
+pub fn handle_get_overrides(json: bool) -> Result<()> {
+    let overrides = crate::state::overrides::load()?;
+
+    if json {
+        println!("{}", serde_json::to_string(&overrides)?);
+    } else {
+        for (field, value) in &overrides {
+            println!("{}={}", field, format_toml_value(value)?);
+        }
+    }
+
+    Ok(())
+}
+
 /// Read one field, taking latitude/longitude from geo.toml and everything else from the config.
 fn get_field_value(field: &str, config: &toml::Table, geo: Option<&toml::Table>) -> Result<String> {
     if (field == "latitude" || field == "longitude")
@@ -170,20 +189,30 @@ fn get_all_field_names() -> Vec<String> {
     vec![
         "backend".to_string(),
         "transition_mode".to_string(),
+        "verify_gamma".to_string(),
+        "hyprsunset_mode".to_string(),
         "smoothing".to_string(),
+        "reduce_motion".to_string(),
         "startup_duration".to_string(),
         "shutdown_duration".to_string(),
+        "shutdown_target".to_string(),
         "adaptive_interval".to_string(),
         "night_temp".to_string(),
         "day_temp".to_string(),
         "night_gamma".to_string(),
         "day_gamma".to_string(),
+        "night_saturation".to_string(),
+        "night_brightness".to_string(),
+        "day_brightness".to_string(),
         "update_interval".to_string(),
         "static_temp".to_string(),
         "static_gamma".to_string(),
         "sunset".to_string(),
         "sunrise".to_string(),
         "transition_duration".to_string(),
+        "night_latest_start".to_string(),
+        "min_day_duration".to_string(),
+        "matrix".to_string(),
         "latitude".to_string(),
         "longitude".to_string(),
     ]
@@ -198,6 +227,7 @@ pub fn show_usage() {
     log_indented!("                     <name> = named preset");
     log_indented!("                     (omit to use active configuration)");
     log_indented!("-j, --json           Output in JSON format");
+    log_indented!("    --overrides      List the runtime overrides set with 'set --runtime'");
     log_block_start!("Arguments:");
     log_indented!("<field>              Configuration field(s) to retrieve");
     log_indented!("                     Use 'all' to get all fields");
@@ -216,25 +246,34 @@ pub fn display_help() {
     log_indented!("                     <name> = named preset");
     log_indented!("                     (omit to use active configuration)");
     log_indented!("-j, --json           Output in JSON format");
+    log_indented!("    --overrides      List the runtime overrides set with 'set --runtime'");
     log_block_start!("Special Fields:");
     log_indented!("all                  Get all configuration fields");
     log_block_start!("Available Fields:");
     log_indented!("backend              Backend: auto, hyprland, or wayland");
     log_indented!("transition_mode      Mode: geo, static, center, finish_by, start_at");
+    log_indented!("verify_gamma         Reapply gamma overwritten by other clients (true/false)");
+    log_indented!("hyprsunset_mode      hyprsunset handling: managed or attach");
     log_indented!("smoothing            Enable smooth transitions (true/false)");
+    log_indented!("reduce_motion        Apply every change at once, even in presets (true/false)");
     log_indented!("startup_duration     Smooth startup time in seconds");
     log_indented!("shutdown_duration    Smooth shutdown time in seconds");
+    log_indented!("shutdown_target      Shutdown fade target: day, reset, or current");
     log_indented!("adaptive_interval    Smooth transition interval in milliseconds");
     log_indented!("night_temp           Night color temperature (1000-20000)");
     log_indented!("night_gamma          Night gamma percentage (10-200)");
     log_indented!("day_temp             Day color temperature (1000-20000)");
     log_indented!("day_gamma            Day gamma percentage (10-200)");
+    log_indented!("night_brightness     Night brightness percentage (10-100)");
+    log_indented!("day_brightness       Day brightness percentage (10-100)");
     log_indented!("update_interval      Update interval: integer (10-300) sec or \"auto\"");
     log_indented!("static_temp          Static mode temperature (1000-20000)");
     log_indented!("static_gamma         Static mode gamma percentage (10-200)");
     log_indented!("sunset               Sunset time (HH:MM:SS format)");
     log_indented!("sunrise              Sunrise time (HH:MM:SS format)");
     log_indented!("transition_duration  Transition time in minutes");
+    log_indented!("night_latest_start   Latest time night may begin (HH:MM:SS format)");
+    log_indented!("min_day_duration     Minutes of day kept before sunset (60-720)");
     log_indented!("latitude             Geographic latitude (-90 to 90)");
     log_indented!("longitude            Geographic longitude (-180 to 180)");
     log_block_start!("Examples:");
@@ -260,6 +299,9 @@ pub fn display_help() {
     log_pipe!();
     log_indented!("# Get all values from preset as JSON");
     log_indented!("sunsetr get -t night --json all");
+    log_pipe!();
+    log_indented!("# List temporary tweaks made with set --runtime");
+    log_indented!("sunsetr get --overrides");
     log_end!();
 }
 
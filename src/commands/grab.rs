@@ -0,0 +1,114 @@
+//! Runs a screenshot command while every output is held neutral, so the capture shows the
+//! colors applications drew rather than sunsetr's tint.
+//!
+//! The hold belongs to the IPC connection: the running instance lets go as soon as `grab`
+//! disconnects, so a capture that fails, hangs up or is killed can't leave the screen
+//! neutral. Messages go to stderr, since the wrapped command may write the image to stdout.
+
+use anyhow::Result;
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::common::error::Silent;
+use crate::state::ipc::client::IpcClient;
+
+/// Time for the neutral values to reach the screen after the backend has them: the next
+/// frame on every output, with room for a worker poll.
+const SETTLE: Duration = Duration::from_millis(100);
+
+/// Time to let Hyprland's fade to the neutral CTM finish, when it fades.
+const HYPRLAND_CTM_SETTLE: Duration = Duration::from_millis(600);
+
+/// Hold every output neutral, run `command`, then release the hold and exit as `command`
+/// did. Without a running instance nothing is tinted, so `command` just runs.
+pub fn handle_grab_command(command: &[String]) -> Result<()> {
+    let hold = match IpcClient::connect() {
+        Ok(mut client) => match client.hold_neutral() {
+            Ok(confirmed) => {
+                if confirmed {
+                    std::thread::sleep(settle_time());
+                } else {
+                    eprintln!("sunsetr grab: outputs not confirmed neutral, capturing anyway");
+                }
+                Some(client)
+            }
+            Err(e) => {
+                eprintln!("sunsetr grab: outputs not held neutral ({e}), capturing anyway");
+                None
+            }
+        },
+        Err(_) => None,
+    };
+
+    let status = Command::new(&command[0]).args(&command[1..]).status();
+    // Closing the connection releases the hold
+    drop(hold);
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => std::process::exit(exit_code(status)),
+        Err(e) => {
+            eprintln!("sunsetr grab: failed to run {}: {e}", command[0]);
+            Err(Silent.into())
+        }
+    }
+}
+
+fn settle_time() -> Duration {
+    if crate::backend::hyprland::ipc::ctm_fades() {
+        HYPRLAND_CTM_SETTLE
+    } else {
+        SETTLE
+    }
+}
+
+/// The exit code a shell would report for `status`, 128 plus the signal for a killed process.
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr grab -- <command> [args...]");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help grab");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Run a screenshot command with every output held neutral");
+    log_block_start!("Usage: sunsetr grab -- <command> [args...]");
+    log_block_start!("Notes:");
+    log_indented!("The running instance holds every output neutral, the command runs");
+    log_indented!("once that is on screen, and the schedule's values come back as soon");
+    log_indented!("as it exits, even if grab is interrupted. grab exits with the");
+    log_indented!("command's status and prints nothing to stdout of its own, so the");
+    log_indented!("image can be piped. Without a running instance the command just runs.");
+    log_indented!("On Hyprland, grab waits for the CTM fade to finish before capturing.");
+    log_block_start!("Examples:");
+    log_indented!("# Screenshot of every output to a file");
+    log_indented!("sunsetr grab -- grim ~/screenshot.png");
+    log_pipe!();
+    log_indented!("# Region to the clipboard");
+    log_indented!("sunsetr grab -- sh -c 'grim -g \"$(slurp)\" - | wl-copy'");
+    log_end!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_follow_the_shell() {
+        let status = |raw| std::process::ExitStatus::from_raw(raw);
+        assert_eq!(exit_code(status(0)), 0);
+        assert_eq!(exit_code(status(3 << 8)), 3);
+        // Killed by SIGINT
+        assert_eq!(exit_code(status(2)), 130);
+    }
+}
@@ -5,16 +5,36 @@ use anyhow::Result;
 /// Brief usage line for a command, shown alongside error messages.
 pub fn show_command_usage(command: &str) {
     match command {
+        "adjust" => log_block_start!("Usage: sunsetr adjust <+|-><value>[K|%] [...] | reset"),
         "geo" | "G" => log_block_start!("Usage: sunsetr geo"),
         "get" | "g" => log_block_start!("Usage: sunsetr get [OPTIONS] <field> [<field>...]"),
         "preset" | "p" => log_block_start!("Usage: sunsetr preset <subcommand|name>"),
+        "presentation" => {
+            log_block_start!("Usage: sunsetr presentation <on [OUTPUT...]|off>")
+        }
         "restart" | "r" => log_block_start!("Usage: sunsetr restart [--instant]"),
+        "bugreport" => log_block_start!("Usage: sunsetr bugreport [--output <file>]"),
+        "compute" => log_block_start!("Usage: sunsetr compute [--at <time>] [--json]"),
+        "config" => log_block_start!("Usage: sunsetr config <subcommand>"),
+        "curve" => log_block_start!("Usage: sunsetr curve [--date <YYYY-MM-DD>] [--svg]"),
+        "doctor" => log_block_start!("Usage: sunsetr doctor [--json]"),
         "set" | "s" => {
             log_block_start!("Usage: sunsetr set [OPTIONS] <field>[+|-]=<value> [...]")
         }
+        "grab" => log_block_start!("Usage: sunsetr grab -- <command> [args...]"),
+        "history" => log_block_start!("Usage: sunsetr history [--json]"),
+        "pause" => {
+            log_block_start!("Usage: sunsetr pause [--for <duration> | --until <sunrise|tomorrow>]")
+        }
+        "refresh" => log_block_start!("Usage: sunsetr refresh"),
+        "render-preview" => log_block_start!(
+            "Usage: sunsetr render-preview [--temp <K>] [--gamma <%>] --out <file>"
+        ),
         "status" | "S" => log_block_start!("Usage: sunsetr status [--json] [--follow]"),
         "stop" => log_block_start!("Usage: sunsetr stop"),
         "test" | "t" => log_block_start!("Usage: sunsetr test <temperature> <gamma>"),
+        "toggle" => log_block_start!("Usage: sunsetr toggle"),
+        "week" => log_block_start!("Usage: sunsetr week [--start <YYYY-MM-DD>] [--ascii]"),
         _ => log_block_start!("Usage: sunsetr [OPTIONS] [COMMAND]"),
     }
 }
@@ -24,14 +44,28 @@ pub fn show_command_usage(command: &str) {
 /// Unknown commands fall back to the top-level help output.
 pub fn show_usage(command: &str) -> Result<()> {
     match command {
+        "adjust" => super::adjust::show_usage(),
+        "bugreport" => super::bugreport::show_usage(),
+        "compute" => super::compute::show_usage(),
+        "config" => super::config::show_usage(),
+        "curve" => super::curve::show_usage(),
+        "doctor" => super::doctor::show_usage(),
         "geo" | "G" => super::geo::show_usage(),
         "get" | "g" => super::get::show_usage(),
+        "grab" => super::grab::show_usage(),
+        "pause" => super::pause::show_usage(),
         "preset" | "p" => super::preset::show_usage(),
+        "presentation" => super::presentation::show_usage(),
+        "history" => super::history::show_usage(),
+        "refresh" => super::refresh::show_usage(),
+        "render-preview" => super::render_preview::show_usage(),
         "restart" | "r" => super::restart::show_usage(),
         "set" | "s" => super::set::show_usage(),
         "status" | "S" => super::status::show_usage(),
         "stop" => super::stop::show_usage(),
         "test" | "t" => super::test::show_usage(),
+        "toggle" => super::toggle::show_usage(),
+        "week" => super::week::show_usage(),
         _ => {
             log_warning_standalone!("Unknown command: {}", command);
             crate::args::display_help();
@@ -60,14 +94,28 @@ pub fn run_help_command(command: Option<&str>) -> Result<()> {
     match command {
         None => display_general_help(),
         Some("get") | Some("g") => super::get::display_help(),
+        Some("adjust") => super::adjust::display_help(),
+        Some("bugreport") => super::bugreport::display_help(),
+        Some("compute") => super::compute::display_help(),
+        Some("config") => super::config::display_help(),
+        Some("curve") => super::curve::display_help(),
+        Some("doctor") => super::doctor::display_help(),
         Some("geo") | Some("G") => super::geo::display_help(),
+        Some("grab") => super::grab::display_help(),
         Some("help") | Some("h") => display_help_help(),
         Some("preset") | Some("p") => super::preset::display_help(),
+        Some("presentation") => super::presentation::display_help(),
+        Some("history") => super::history::display_help(),
+        Some("pause") => super::pause::display_help(),
+        Some("refresh") => super::refresh::display_help(),
+        Some("render-preview") => super::render_preview::display_help(),
         Some("restart") | Some("r") => super::restart::display_help(),
         Some("set") | Some("s") => super::set::display_help(),
         Some("status") | Some("S") => super::status::display_help(),
         Some("stop") => super::stop::display_help(),
         Some("test") | Some("t") => super::test::display_help(),
+        Some("toggle") => super::toggle::display_help(),
+        Some("week") => super::week::display_help(),
         Some(unknown) => {
             log_warning_standalone!("Unknown command: {}", unknown);
             display_general_help();
@@ -79,15 +127,30 @@ pub fn run_help_command(command: Option<&str>) -> Result<()> {
 fn display_general_help() {
     log_version!();
     log_block_start!("Available Commands:");
+    log_indented!("adjust <+|-N[K|%]>      Nudge the temperature or gamma for today");
+    log_indented!("bugreport [-o <file>]   Collect a redacted report for bug reports");
+    log_indented!("compute [--at <time>]   Print the values for a given time");
+    log_indented!("config schema           Print the JSON Schema of the config file");
+    log_indented!("config check            Validate the config and count transition updates");
+    log_indented!("curve [--svg]           Chart the temperature and gamma over a day");
+    log_indented!("doctor [--json]         Report compositor, quirks and backend choice");
     log_indented!("geo, G                  Interactive city selection for geographic mode");
     log_indented!("get, g <field>          Read configuration field(s)");
     log_indented!("help, h [COMMAND]       Show detailed help for a command");
+    log_indented!("grab -- <command>       Run a screenshot command with neutral colors");
+    log_indented!("history [--json]        List recently applied states");
+    log_indented!("pause [--for <time>]    Pause color shifting, optionally for a while");
     log_indented!("preset, p <sub|name>    Manage and apply preset configurations");
     log_indented!("restart, r [--instant]  Recreate backend and reload configuration");
     log_indented!("set, s <field>[op]=val  Update configuration field(s)");
+    log_indented!("presentation <on|off>   Hold outputs neutral until turned off");
+    log_indented!("refresh                 Rebuild gamma on every output and reapply");
+    log_indented!("render-preview          Write a test card PNG as a temperature shows it");
     log_indented!("status, S               Display current runtime state");
     log_indented!("stop                    Cleanly terminate running sunsetr instance");
     log_indented!("test, t <temp> <gamma>  Test specific temperature and gamma values");
+    log_indented!("toggle                  Pause color shifting, or resume it");
+    log_indented!("week [--start <date>]   Show a week of day, night and transitions");
     log_pipe!();
     log_info!("Use 'sunsetr help <command>' to see detailed help for a specific command.");
     log_indented!("Use 'sunsetr --help' to see all options and general usage.");
@@ -0,0 +1,101 @@
+//! Lists the states the running instance applied most recently, to line up a flash or
+//! flicker seen at some time with what sunsetr did then.
+
+use anyhow::{Context, Result};
+
+use crate::core::history::{AppliedEntry, HISTORY_LEN};
+use crate::state::ipc::client::IpcClient;
+
+/// Ask the running instance for its applied history and print it, oldest first.
+pub fn handle_history_command(json: bool) -> Result<()> {
+    let Ok(mut client) = IpcClient::connect() else {
+        log_error_standalone!("No sunsetr process is running");
+        println!("  Start sunsetr first or use 'sunsetr --debug' to run");
+        return Ok(());
+    };
+    let entries = client
+        .history()
+        .context("Failed to receive the applied history from sunsetr process")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+    } else if entries.is_empty() {
+        println!("Nothing applied since sunsetr started");
+    } else {
+        for entry in &entries {
+            println!("{}", format_entry(entry));
+        }
+    }
+    Ok(())
+}
+
+fn format_entry(entry: &AppliedEntry) -> String {
+    let outputs = entry
+        .outputs
+        .as_ref()
+        .map_or_else(|| "-".to_string(), |outputs| outputs.join(", "));
+    format!(
+        "{}  {:>5}K  {:>5.1}%  {:<19}  {outputs}",
+        entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+        entry.temp,
+        entry.gamma,
+        entry.reason.to_string(),
+    )
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr history [--json]");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help history");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("List the states the running instance applied most recently");
+    log_block_start!("Usage: sunsetr history [--json]");
+    log_block_start!("Options:");
+    log_indented!("-j, --json  Print the entries as a JSON array");
+    log_block_start!("Output:");
+    log_indented!("One line per applied state, oldest first: when it was applied,");
+    log_indented!("its temperature and gamma, why, and the outputs connected then.");
+    log_indented!("The last {HISTORY_LEN} are kept. Steps of a smooth startup, reload or");
+    log_indented!("shutdown are not listed on their own.");
+    log_block_start!("Examples:");
+    log_indented!("# What was applied around the time the screen flashed");
+    log_indented!("sunsetr history | grep ' 03:1'");
+    log_end!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::units::{GammaPercent, Kelvin};
+    use crate::core::period::ChangeReason;
+    use chrono::TimeZone;
+
+    #[test]
+    fn entries_print_one_per_line() {
+        let entry = AppliedEntry {
+            timestamp: chrono::Local
+                .with_ymd_and_hms(2025, 11, 11, 3, 12, 5)
+                .unwrap(),
+            temp: Kelvin::new(3300).unwrap(),
+            gamma: GammaPercent::new(90.0).unwrap(),
+            reason: ChangeReason::Wake,
+            outputs: Some(vec!["DP-2".to_string(), "eDP-1".to_string()]),
+        };
+        assert_eq!(
+            format_entry(&entry),
+            "2025-11-11 03:12:05   3300K   90.0%  wake                 DP-2, eDP-1"
+        );
+        assert!(
+            format_entry(&AppliedEntry {
+                outputs: None,
+                ..entry
+            })
+            .ends_with("wake                 -")
+        );
+    }
+}
@@ -2,15 +2,29 @@
 //!
 //! One-shot CLI command implementations, one submodule per command.
 
+pub mod adjust;
+pub mod bugreport;
+pub mod compute;
+pub mod config;
+pub mod curve;
+pub mod doctor;
 pub mod geo;
 pub mod get;
+pub mod grab;
 pub mod help;
+pub mod history;
+pub mod pause;
+pub mod presentation;
 pub mod preset;
+pub mod refresh;
+pub mod render_preview;
 pub mod restart;
 pub mod set;
 pub mod status;
 pub mod stop;
 pub mod test;
+pub mod toggle;
+pub mod week;
 
 use anyhow::{Context, Result};
 use std::fs;
@@ -0,0 +1,97 @@
+//! Pause color shifting on the running instance, until resumed or for a while.
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+
+use crate::common::error::Silent;
+use crate::core::pause::{PauseEvent, PauseUntil};
+use crate::state::ipc::client::IpcClient;
+
+/// Pause the running instance over IPC, to resume by itself after `expires_in` or at
+/// `until` when given, and report when it resumes.
+pub fn handle_pause_command(expires_in: Option<Duration>, until: Option<PauseEvent>) -> Result<()> {
+    log_version!();
+
+    let until = match (expires_in, until) {
+        (Some(expires_in), _) => {
+            let end = chrono::Duration::from_std(expires_in)
+                .ok()
+                .and_then(|duration| chrono::Local::now().checked_add_signed(duration))
+                .context("Pause length is out of range")?;
+            Some(PauseUntil::Time(end))
+        }
+        (None, Some(event)) => Some(PauseUntil::Event(event)),
+        (None, None) => None,
+    };
+
+    let Some(mut client) = IpcClient::connect_for_request()? else {
+        log_block_start!("sunsetr isn't running");
+        log_end!();
+        return Ok(());
+    };
+
+    match client.pause(Some(true), until) {
+        Ok(Some(state)) if state.paused => {
+            log_block_start!("Color shifting paused, outputs at 6500K and 100%");
+            match state.paused_until {
+                Some(resumes_at) => {
+                    log_indented!("Resuming at {}", resumes_at.format("%Y-%m-%d %H:%M"));
+                }
+                None if until.is_some() => {
+                    log_pipe!();
+                    log_warning!("The current schedule has no sunrise to resume at");
+                    log_indented!("Run 'sunsetr toggle' to resume");
+                }
+                None => log_indented!("Run 'sunsetr toggle' to resume"),
+            }
+            log_end!();
+            Ok(())
+        }
+        Ok(Some(_)) => {
+            log_pipe!();
+            log_warning!("sunsetr did not stay paused");
+            log_end!();
+            Ok(())
+        }
+        Ok(None) => {
+            log_pipe!();
+            log_warning!("sunsetr did not confirm the pause");
+            log_indented!("Older releases can't be paused, try 'sunsetr restart'");
+            log_end!();
+            Ok(())
+        }
+        Err(e) => {
+            log_error_end!("Pause rejected: {e}");
+            Err(Silent.into())
+        }
+    }
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr pause [--for <duration> | --until <sunrise|tomorrow>]");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help pause");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Pause color shifting until resumed, or for a while");
+    log_block_start!("Usage: sunsetr pause [OPTIONS]");
+    log_block_start!("Options:");
+    log_indented!("--for <duration>    Resume after that long (e.g. 45m, 2h, 1h30m)");
+    log_indented!("--until sunrise     Resume as the next sunrise starts");
+    log_indented!("--until tomorrow    Resume at midnight");
+    log_block_start!("Notes:");
+    log_indented!("While paused, every output shows 6500K at 100% gamma. The schedule");
+    log_indented!("keeps running underneath. 'sunsetr toggle' resumes early, and pausing");
+    log_indented!("again replaces the end. A pause does not survive a restart.");
+    log_block_start!("Examples:");
+    log_indented!("# Neutral colors for the next 45 minutes");
+    log_indented!("sunsetr pause --for 45m");
+    log_pipe!();
+    log_indented!("# No tint tonight");
+    log_indented!("sunsetr pause --until sunrise");
+    log_end!();
+}
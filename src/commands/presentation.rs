@@ -0,0 +1,73 @@
+//! Turn presentation mode on or off for the running instance and future starts.
+
+use anyhow::Result;
+
+use crate::args::PresentationSubcommand;
+use crate::state::presentation;
+
+/// Turn presentation mode on or off. A running instance picks the change up through its
+/// state directory watcher.
+pub fn handle_presentation_command(subcommand: &PresentationSubcommand) -> Result<()> {
+    log_version!();
+
+    match subcommand {
+        PresentationSubcommand::On { outputs } => {
+            let presentation = presentation::enable(outputs.clone())?;
+            log_block_start!(
+                "Presentation mode on, {} held neutral",
+                presentation.describe()
+            );
+            log_indented!("Turn it off with: sunsetr presentation off");
+            if let Ok(pid) = crate::io::instance::get_running_instance_pid() {
+                log_block_start!("Configuration reloaded successfully (PID: {})", pid);
+            } else {
+                log_block_start!("Takes effect when sunsetr starts");
+            }
+        }
+        PresentationSubcommand::Off => {
+            if presentation::disable()? {
+                log_block_start!("Presentation mode off");
+                if let Ok(pid) = crate::io::instance::get_running_instance_pid() {
+                    log_indented!("Configuration reloaded successfully (PID: {})", pid);
+                }
+            } else {
+                log_block_start!("Presentation mode is not on");
+            }
+        }
+    }
+
+    log_end!();
+    Ok(())
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr presentation <on [OUTPUT...]|off>");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help presentation");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Hold outputs at neutral color for screen sharing or color work");
+    log_block_start!("Usage: sunsetr presentation <on [OUTPUT...]|off>");
+    log_block_start!("Subcommands:");
+    log_indented!("on [OUTPUT...]  Hold the named outputs neutral, or all outputs");
+    log_indented!("off             Return to the scheduled values");
+    log_block_start!("Notes:");
+    log_indented!("Unlike a preview or test mode, presentation mode survives period");
+    log_indented!("changes, config reloads, preset switches and restarts until turned off.");
+    log_indented!("The schedule keeps running, so 'sunsetr status' still shows the");
+    log_indented!("scheduled values. The hyprsunset backend holds every output neutral.");
+    log_block_start!("Examples:");
+    log_indented!("# Neutral on every output");
+    log_indented!("sunsetr presentation on");
+    log_pipe!();
+    log_indented!("# Neutral on the projector only");
+    log_indented!("sunsetr presentation on HDMI-A-1");
+    log_pipe!();
+    log_indented!("# Back to the schedule");
+    log_indented!("sunsetr presentation off");
+    log_end!();
+}
@@ -1,7 +1,11 @@
 //! Switch between named presets stored under `presets/<name>/sunsetr.toml`.
 
 use crate::args::PresetSubcommand;
+use crate::state::preset::{PresetReferences, PresetState, PresetSwitch};
 use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, PartialEq)]
 pub enum PresetResult {
@@ -12,16 +16,21 @@ pub enum PresetResult {
 
 pub fn handle_preset_command(subcommand: &PresetSubcommand) -> Result<PresetResult> {
     match subcommand {
-        PresetSubcommand::Apply { name } => handle_preset_apply(name),
+        PresetSubcommand::Apply { name, expires_in } => handle_preset_apply(name, *expires_in),
+        PresetSubcommand::Push { name, expires_in } => handle_preset_push(name, *expires_in),
+        PresetSubcommand::Pop => handle_preset_pop(),
         PresetSubcommand::Active => handle_preset_active(),
         PresetSubcommand::List => handle_preset_list(),
+        PresetSubcommand::Rename { from, to } => handle_preset_rename(from, to),
+        PresetSubcommand::Remove { name } => handle_preset_remove(name),
     }
 }
 
 /// Apply a preset by name. When a process is running and the preset is already active, toggle it
 /// off and restore the default configuration. With no process running the preset is always applied
-/// so scheduled invocations stay idempotent.
-fn handle_preset_apply(preset_name: &str) -> Result<PresetResult> {
+/// so scheduled invocations stay idempotent. With `expires_in` the switch is undone after
+/// that long.
+fn handle_preset_apply(preset_name: &str, expires_in: Option<Duration>) -> Result<PresetResult> {
     log_version!();
 
     if crate::io::instance::is_test_mode_active() {
@@ -34,7 +43,7 @@ fn handle_preset_apply(preset_name: &str) -> Result<PresetResult> {
     let running_pid = crate::io::instance::get_running_instance_pid().ok();
 
     if preset_name.to_lowercase() == "default" {
-        return handle_default_preset();
+        return handle_default_preset(expires_in);
     }
 
     validate_preset_name(preset_name)?;
@@ -48,29 +57,78 @@ fn handle_preset_apply(preset_name: &str) -> Result<PresetResult> {
 
     if let Some(pid) = running_pid {
         if current_preset.as_deref() == Some(preset_name) {
-            if let Err(e) = crate::state::preset::clear_active_preset() {
-                log_error_end!("Failed to clear active preset: {e}");
-                std::process::exit(1);
-            }
+            let switch = match PresetSwitch::begin(None, expires_in) {
+                Ok(switch) => switch,
+                Err(e) => {
+                    log_error_end!("Failed to clear active preset: {e}");
+                    std::process::exit(1);
+                }
+            };
             log_block_start!(
                 "Deactivated preset '{}', restored default configuration",
                 preset_name
             );
+            log_expiry(&switch, expires_in);
 
-            reload_running_process(pid)?;
+            reload_running_process(pid, switch)?;
         } else {
-            apply_preset(preset_name, config_dir)?;
-            reload_running_process(pid)?;
+            let switch = apply_preset(preset_name, config_dir, expires_in)?;
+            reload_running_process(pid, switch)?;
         }
         log_end!();
         Ok(PresetResult::Exit)
     } else {
-        apply_preset(preset_name, config_dir)?;
+        apply_preset(preset_name, config_dir, expires_in)?.commit();
         Ok(PresetResult::ContinueExecution)
     }
 }
 
-fn apply_preset(preset_name: &str, config_dir: &std::path::Path) -> Result<()> {
+/// Say when a `--for` switch ends and which presets come back then.
+fn log_expiry(switch: &PresetSwitch, expires_in: Option<Duration>) {
+    let Some(expires_in) = expires_in else {
+        return;
+    };
+    let until = chrono::Duration::from_std(expires_in)
+        .ok()
+        .and_then(|duration| crate::time::source::now().checked_add_signed(duration));
+    if let Some(until) = until {
+        log_indented!(
+            "Until {}, then back to {}",
+            until.format("%Y-%m-%d %H:%M"),
+            switch.previous().label()
+        );
+    }
+}
+
+/// Check that the preset can be applied, then make it active. The returned
+/// switch is rolled back unless the caller commits it once the apply succeeds.
+fn apply_preset(
+    preset_name: &str,
+    config_dir: &Path,
+    expires_in: Option<Duration>,
+) -> Result<PresetSwitch> {
+    let preset_config = require_preset(preset_name, config_dir)?;
+    let config = match crate::config::Config::load_from_path(&preset_config) {
+        Ok(config) => config,
+        Err(e) => {
+            log_pipe!();
+            log_error!("Preset '{}' has invalid configuration:", preset_name);
+            log_indented!("{}", e);
+            log_end!();
+            std::process::exit(1);
+        }
+    };
+    require_backend_available(preset_name, &config);
+
+    let switch = PresetSwitch::begin(Some(preset_name), expires_in)?;
+
+    log_block_start!("Active preset: {}", preset_name);
+    log_expiry(&switch, expires_in);
+    Ok(switch)
+}
+
+/// Path to the preset's config, exiting with the available presets listed when it doesn't exist.
+fn require_preset(preset_name: &str, config_dir: &Path) -> Result<PathBuf> {
     let preset_config = config_dir
         .join("presets")
         .join(preset_name)
@@ -88,41 +146,287 @@ fn apply_preset(preset_name: &str, config_dir: &std::path::Path) -> Result<()> {
         super::handle_preset_not_found_error(&error);
     }
 
-    if let Err(e) = crate::config::Config::load_from_path(&preset_config) {
+    Ok(preset_config)
+}
+
+/// Exit without touching the preset state when `config` names a backend that can't run here.
+fn require_backend_available(preset_name: &str, config: &crate::config::Config) {
+    let session = crate::backend::Session::from_env();
+    if crate::backend::resolve_backend(config.backend, &session).is_none() {
         log_pipe!();
-        log_error!("Preset '{}' has invalid configuration:", preset_name);
-        log_indented!("{}", e);
+        log_error!(
+            "Preset '{}' uses the {} backend, which is not available in this session",
+            preset_name,
+            config.backend
+        );
+        log_indented!("The active preset was left unchanged");
         log_end!();
         std::process::exit(1);
     }
+}
+
+/// Stack a preset on top of the active one, so it overrides only the settings it sets.
+fn handle_preset_push(preset_name: &str, expires_in: Option<Duration>) -> Result<PresetResult> {
+    log_version!();
+
+    if crate::io::instance::is_test_mode_active() {
+        log_error_end!(
+            "Cannot switch presets while test mode is active\n   Exit test mode first (press Escape in the test terminal)"
+        );
+        return Ok(PresetResult::TestModeActive);
+    }
 
-    crate::state::preset::set_active_preset(preset_name)?;
+    if preset_name.eq_ignore_ascii_case("default") {
+        log_error_end!("The default configuration is always the bottom layer and can't be pushed");
+        std::process::exit(1);
+    }
+    validate_preset_name(preset_name)?;
 
-    log_block_start!("Active preset: {}", preset_name);
+    let running_pid = crate::io::instance::get_running_instance_pid().ok();
+    let config_path = crate::config::Config::get_config_path()?;
+    let config_dir = config_path
+        .parent()
+        .context("Failed to get config directory")?;
+
+    let preset_config = require_preset(preset_name, config_dir)?;
+    let current = PresetState::load()?;
+    if current.base.as_deref() == Some(preset_name)
+        || current.stack.iter().any(|name| name == preset_name)
+    {
+        log_error_end!(
+            "Preset '{}' is already active ({})",
+            preset_name,
+            current.label()
+        );
+        std::process::exit(1);
+    }
+
+    let base_config = match &current.base {
+        Some(base) => require_preset(base, config_dir)?,
+        None => config_path.clone(),
+    };
+    let mut layers: Vec<PathBuf> = current
+        .stack
+        .iter()
+        .map(|name| require_preset(name, config_dir))
+        .collect::<Result<_>>()?;
+    layers.push(preset_config);
+
+    let config = match crate::config::Config::load_layered(&base_config, &layers) {
+        Ok(config) => config,
+        Err(e) => {
+            log_pipe!();
+            log_error!(
+                "Preset '{}' can't be stacked on {}:",
+                preset_name,
+                current.label()
+            );
+            log_indented!("{:#}", e);
+            log_end!();
+            std::process::exit(1);
+        }
+    };
+    require_backend_available(preset_name, &config);
+
+    let switch = PresetSwitch::push(preset_name, expires_in)?;
+    log_block_start!("Active presets: {} + {}", current.label(), preset_name);
+    log_expiry(&switch, expires_in);
+
+    finish_switch(running_pid, switch)
+}
+
+/// Remove the most recently pushed preset.
+fn handle_preset_pop() -> Result<PresetResult> {
+    log_version!();
+
+    if crate::io::instance::is_test_mode_active() {
+        log_error_end!(
+            "Cannot switch presets while test mode is active\n   Exit test mode first (press Escape in the test terminal)"
+        );
+        return Ok(PresetResult::TestModeActive);
+    }
+
+    let running_pid = crate::io::instance::get_running_instance_pid().ok();
+
+    match PresetSwitch::pop()? {
+        Some((switch, popped)) => {
+            let mut remaining = switch.previous().clone();
+            remaining.stack.pop();
+            log_block_start!(
+                "Popped preset '{}', active presets: {}",
+                popped,
+                remaining.label()
+            );
+            finish_switch(running_pid, switch)
+        }
+        None => {
+            log_block_start!("No stacked preset to pop");
+            if running_pid.is_some() {
+                log_end!();
+                Ok(PresetResult::Exit)
+            } else {
+                Ok(PresetResult::ContinueExecution)
+            }
+        }
+    }
+}
+
+/// Hand `switch` to the running process, or keep it for the instance about to start.
+fn finish_switch(running_pid: Option<u32>, switch: PresetSwitch) -> Result<PresetResult> {
+    match running_pid {
+        Some(pid) => {
+            reload_running_process(pid, switch)?;
+            log_end!();
+            Ok(PresetResult::Exit)
+        }
+        None => {
+            switch.commit();
+            Ok(PresetResult::ContinueExecution)
+        }
+    }
+}
+
+/// Rename preset `from` to `to`, taking the preset state along. A preset that is active or
+/// waiting to come back is copied, and its old directory only removed once the state points
+/// at the copy, so the running instance never finds it missing.
+fn handle_preset_rename(from: &str, to: &str) -> Result<PresetResult> {
+    log_version!();
+
+    if crate::io::instance::is_test_mode_active() {
+        log_error_end!(
+            "Cannot rename presets while test mode is active\n   Exit test mode first (press Escape in the test terminal)"
+        );
+        return Ok(PresetResult::TestModeActive);
+    }
+    if from.eq_ignore_ascii_case("default") || to.eq_ignore_ascii_case("default") {
+        log_error_end!("The default configuration is not a preset and can't be renamed");
+        std::process::exit(1);
+    }
+    validate_preset_name(from)?;
+    validate_preset_name(to)?;
+
+    let config_path = crate::config::Config::get_config_path()?;
+    let config_dir = config_path
+        .parent()
+        .context("Failed to get config directory")?;
+    let from_dir = config_dir.join("presets").join(from);
+    let to_dir = config_dir.join("presets").join(to);
+    require_preset(from, config_dir)?;
+    if to_dir.exists() {
+        log_error_end!("Preset '{}' already exists at {}", to, to_dir.display());
+        std::process::exit(1);
+    }
+
+    let references = PresetReferences::load()?;
+    let active = if references.mention(from) {
+        if let Err(e) = copy_dir(&from_dir, &to_dir) {
+            let _ = fs::remove_dir_all(&to_dir);
+            return Err(e).with_context(|| format!("Failed to copy {}", from_dir.display()));
+        }
+        let active = references.update(from, Some(to))?;
+        fs::remove_dir_all(&from_dir)
+            .with_context(|| format!("Failed to remove {}", from_dir.display()))?;
+        active
+    } else {
+        fs::rename(&from_dir, &to_dir)
+            .with_context(|| format!("Failed to move {}", from_dir.display()))?;
+        None
+    };
+
+    log_block_start!("Renamed preset '{}' to '{}'", from, to);
+    finish_management(active)
+}
+
+/// Delete preset `name`, dropping it from the active presets first.
+fn handle_preset_remove(name: &str) -> Result<PresetResult> {
+    log_version!();
+
+    if crate::io::instance::is_test_mode_active() {
+        log_error_end!(
+            "Cannot remove presets while test mode is active\n   Exit test mode first (press Escape in the test terminal)"
+        );
+        return Ok(PresetResult::TestModeActive);
+    }
+    if name.eq_ignore_ascii_case("default") {
+        log_error_end!("The default configuration is not a preset and can't be removed");
+        std::process::exit(1);
+    }
+    validate_preset_name(name)?;
+
+    let config_path = crate::config::Config::get_config_path()?;
+    let config_dir = config_path
+        .parent()
+        .context("Failed to get config directory")?;
+    let preset_dir = config_dir.join("presets").join(name);
+    require_preset(name, config_dir)?;
+
+    let active = PresetReferences::load()?.update(name, None)?;
+    fs::remove_dir_all(&preset_dir)
+        .with_context(|| format!("Failed to remove {}", preset_dir.display()))?;
+
+    log_block_start!("Removed preset '{}'", name);
+    log_indented!("Deleted {}", preset_dir.display());
+    finish_management(active)
+}
+
+/// Report the active presets when a rename or removal changed them, and have the running
+/// process pick them up.
+fn finish_management(active: Option<PresetState>) -> Result<PresetResult> {
+    if let Some(active) = active {
+        log_indented!("Active presets: {}", active.label());
+        if let Ok(pid) = crate::io::instance::get_running_instance_pid() {
+            log_block_start!("Signaling configuration reload...");
+            crate::io::instance::send_reload_signal(pid)
+                .context("Failed to send reload signal to sunsetr process")?;
+            log_decorated!("Configuration reloaded");
+        }
+    }
+    log_end!();
+    Ok(PresetResult::Exit)
+}
+
+/// Copy directory `from` to `to`, which must not exist yet.
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
     Ok(())
 }
 
-/// Deactivate any active preset, restoring the base configuration.
-fn handle_default_preset() -> Result<PresetResult> {
+/// Deactivate any active preset, restoring the base configuration, for `expires_in` when
+/// given.
+fn handle_default_preset(expires_in: Option<Duration>) -> Result<PresetResult> {
     let running_pid = crate::io::instance::get_running_instance_pid().ok();
 
     let current_preset = crate::state::preset::get_active_preset().ok().flatten();
 
     if let Some(preset_name) = current_preset {
-        if let Err(e) = crate::state::preset::clear_active_preset() {
-            log_error_end!("Failed to remove active preset marker: {e}");
-            std::process::exit(1);
-        }
+        let switch = match PresetSwitch::begin(None, expires_in) {
+            Ok(switch) => switch,
+            Err(e) => {
+                log_error_end!("Failed to remove active preset marker: {e}");
+                std::process::exit(1);
+            }
+        };
         log_block_start!(
             "Deactivated preset '{}', using default configuration",
             preset_name
         );
+        log_expiry(&switch, expires_in);
 
         if let Some(pid) = running_pid {
-            reload_running_process(pid)?;
+            reload_running_process(pid, switch)?;
             log_end!();
             Ok(PresetResult::Exit)
         } else {
+            switch.commit();
             Ok(PresetResult::ContinueExecution)
         }
     } else {
@@ -173,24 +477,29 @@ pub(crate) fn validate_preset_name(name: &str) -> Result<()> {
     Ok(())
 }
 
-fn reload_running_process(pid: u32) -> Result<()> {
+/// Signal the running process to pick up `switch`, keeping the switch only if
+/// the signal is delivered.
+fn reload_running_process(pid: u32, switch: PresetSwitch) -> Result<()> {
     log_block_start!("Signaling configuration reload...");
 
-    crate::io::instance::send_reload_signal(pid)
-        .context("Failed to send reload signal to sunsetr process")?;
+    if let Err(e) = crate::io::instance::send_reload_signal(pid) {
+        let previous = switch.previous().label();
+        switch
+            .rollback()
+            .context("Failed to restore the previous preset")?;
+        return Err(e).with_context(|| {
+            format!("Failed to send reload signal to sunsetr process, kept preset '{previous}'")
+        });
+    }
+    switch.commit();
     log_decorated!("Configuration reloaded");
 
     Ok(())
 }
 
 fn handle_preset_active() -> Result<PresetResult> {
-    let active_preset = crate::state::preset::get_active_preset().ok().flatten();
-
-    if let Some(preset_name) = active_preset {
-        println!("{}", preset_name);
-    } else {
-        println!("default");
-    }
+    let active = PresetState::load().unwrap_or_default();
+    println!("{}", active.label());
 
     Ok(PresetResult::Exit)
 }
@@ -214,10 +523,16 @@ pub fn show_usage() {
     log_version!();
     log_block_start!("Usage: sunsetr preset <subcommand|name>");
     log_block_start!("Subcommands:");
-    log_indented!("active       Show the currently active preset");
-    log_indented!("list         List all available presets");
-    log_indented!("<name>       Apply the named preset");
-    log_indented!("default      Return to base configuration");
+    log_indented!("active             Show the currently active preset");
+    log_indented!("list               List all available presets");
+    log_indented!("<name>             Apply the named preset");
+    log_indented!("push <name>        Stack a preset on top of the active one");
+    log_indented!("pop                Remove the most recently pushed preset");
+    log_indented!("rename <old> <new> Rename a preset");
+    log_indented!("remove <name>      Delete a preset");
+    log_indented!("default            Return to base configuration");
+    log_block_start!("Options:");
+    log_indented!("--for <duration>  Undo the switch after e.g. 90m or 3h");
     log_pipe!();
     log_info!("For detailed help with examples, try: sunsetr help preset");
     log_end!();
@@ -254,14 +569,21 @@ pub fn display_help() {
     log_block_start!("Manage and apply preset configurations");
     log_block_start!("Usage: sunsetr preset <subcommand|name>");
     log_block_start!("Subcommands:");
-    log_indented!("active       Show the currently active preset");
-    log_indented!("list         List all available presets");
-    log_indented!("<name>       Apply the named preset");
-    log_indented!("default      Return to base configuration");
+    log_indented!("active             Show the currently active preset");
+    log_indented!("list               List all available presets");
+    log_indented!("<name>             Apply the named preset");
+    log_indented!("push <name>        Stack a preset on top of the active one");
+    log_indented!("pop                Remove the most recently pushed preset");
+    log_indented!("rename <old> <new> Rename a preset");
+    log_indented!("remove <name>      Delete a preset");
+    log_indented!("default            Return to base configuration");
+    log_block_start!("Options:");
+    log_indented!("--for <duration>  Undo a switch or push after that long (e.g. 90m, 3h, 1h30m)");
     log_block_start!("Preset Files:");
     log_indented!("Presets are stored in: ~/.config/sunsetr/presets/<name>/sunsetr.toml");
     log_indented!("Each preset can override any configuration field");
     log_indented!("Fields not specified in a preset use the default values");
+    log_indented!("A pushed preset only overrides the fields it sets");
     log_block_start!("Examples:");
     log_indented!("# Show the currently active preset");
     log_indented!("sunsetr preset active");
@@ -275,6 +597,16 @@ pub fn display_help() {
     log_indented!("# Apply a night-time preset");
     log_indented!("sunsetr preset night");
     log_pipe!();
+    log_indented!("# Use the night-shift preset for three hours, then go back");
+    log_indented!("sunsetr preset night-shift --for 3h");
+    log_pipe!();
+    log_indented!("# Lower gamma further for a movie, then go back");
+    log_indented!("sunsetr preset push movie");
+    log_indented!("sunsetr preset pop");
+    log_pipe!();
+    log_indented!("# Rename a preset, keeping it active if it is");
+    log_indented!("sunsetr preset rename night late-night");
+    log_pipe!();
     log_indented!("# Return to default configuration");
     log_indented!("sunsetr preset default");
     log_pipe!();
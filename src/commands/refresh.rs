@@ -0,0 +1,69 @@
+//! Ask the running instance to rebuild its backend's outputs and apply the current values.
+
+use anyhow::Result;
+
+use crate::common::error::Silent;
+use crate::state::ipc::client::IpcClient;
+
+/// Send a refresh request over IPC and report the values applied afterwards.
+pub fn handle_refresh_command() -> Result<()> {
+    log_version!();
+
+    let Some(mut client) = IpcClient::connect_for_request()? else {
+        log_block_start!("sunsetr isn't running");
+        log_end!();
+        return Ok(());
+    };
+
+    log_block_start!("Refreshing all outputs...");
+    match client.refresh() {
+        Ok(Some(state)) => {
+            log_indented!(
+                "Applied {}K @ {}% ({})",
+                state.current_temp,
+                state.current_gamma,
+                state.period.display_name()
+            );
+            log_end!();
+            Ok(())
+        }
+        Ok(None) => {
+            log_pipe!();
+            log_warning!("sunsetr did not confirm the refresh");
+            log_indented!("A preview holds back the scheduled values until it ends");
+            log_end!();
+            Ok(())
+        }
+        Err(e) => {
+            log_error_end!("Refresh rejected: {e}");
+            Err(Silent.into())
+        }
+    }
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr refresh");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help refresh");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Rebuild gamma on every output and apply the current values again");
+    log_block_start!("Usage: sunsetr refresh");
+    log_block_start!("Process:");
+    log_indented!("1. Re-reads the connected outputs");
+    log_indented!("2. Recreates gamma controls, including ones the compositor revoked");
+    log_indented!("3. Builds new gamma tables at each output's current size");
+    log_indented!("4. Applies the current temperature and gamma to every output");
+    log_block_start!("Notes:");
+    log_indented!("Useful when an output is left untinted after a driver reset or");
+    log_indented!("another tool took over its gamma. Unlike 'sunsetr restart', the");
+    log_indented!("process keeps running with its config. A running test mode ends.");
+    log_block_start!("Examples:");
+    log_indented!("# Reapply after the screen came back untinted");
+    log_indented!("sunsetr refresh");
+    log_end!();
+}
@@ -0,0 +1,283 @@
+//! Renders a small test card as a PNG, passed through the gamma tables sunsetr would
+//! apply at a given temperature and gamma.
+//!
+//! The top half shows the card unchanged and the bottom half as an output would show it,
+//! so values can be judged on another device or shared. Values not given are the config's
+//! night values, and its channel gammas and trims apply as they do on screen. The PNG is
+//! written with uncompressed deflate blocks, which keeps an image crate out of the build
+//! for a file of a few hundred kilobytes.
+
+use anyhow::{Context, Result};
+
+use crate::backend::gamma::{self, ChannelGamma};
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::Config;
+
+const WIDTH: usize = 384;
+/// Height of each strip of the card: a gray ramp, color swatches and a hue sweep.
+const STRIP_HEIGHT: usize = 32;
+const STRIPS: usize = 3;
+const DIVIDER_HEIGHT: usize = 4;
+const HEIGHT: usize = 2 * STRIPS * STRIP_HEIGHT + DIVIDER_HEIGHT;
+
+/// White, the primaries and secondaries, and a skin tone.
+const SWATCHES: [[u8; 3]; 8] = [
+    [255, 255, 255],
+    [255, 0, 0],
+    [0, 255, 0],
+    [0, 0, 255],
+    [0, 255, 255],
+    [255, 0, 255],
+    [255, 255, 0],
+    [224, 172, 140],
+];
+
+const DIVIDER: [u8; 3] = [32, 32, 32];
+
+/// Render the card at `temp` and `gamma`, or the config's night values, to `output`.
+pub fn handle_render_preview_command(
+    temp: Option<u32>,
+    gamma: Option<f64>,
+    output: &str,
+) -> Result<()> {
+    let config = Config::load()?;
+    let temp = Kelvin::new(temp.unwrap_or(config.night_temp))?;
+    let gamma = GammaPercent::new(gamma.unwrap_or(config.night_gamma))?;
+
+    let pixels = render(temp, gamma, config.channel_gamma())?;
+    std::fs::write(output, encode_png(WIDTH, HEIGHT, &pixels))
+        .with_context(|| format!("Failed to write {output}"))?;
+
+    log_version!();
+    log_block_start!("Rendered {temp}K @ {gamma}% to {output}");
+    log_indented!("Top half unchanged, bottom half as sunsetr would show it");
+    log_end!();
+    Ok(())
+}
+
+/// The card's pixels, row by row: the card itself, a divider, then the card through the
+/// gamma tables.
+fn render(temp: Kelvin, gamma: GammaPercent, channels: ChannelGamma) -> Result<Vec<[u8; 3]>> {
+    let size = 256;
+    let tables =
+        gamma::create_gamma_tables(size, temp.get(), gamma.get() / 100.0, 1.0, channels, false)?;
+    let (red, green, blue) = gamma::split_tables(&tables, size);
+    let shift = |pixel: [u8; 3]| {
+        [
+            (red[usize::from(pixel[0])] >> 8) as u8,
+            (green[usize::from(pixel[1])] >> 8) as u8,
+            (blue[usize::from(pixel[2])] >> 8) as u8,
+        ]
+    };
+
+    let card_height = STRIPS * STRIP_HEIGHT;
+    let mut pixels = Vec::with_capacity(WIDTH * HEIGHT);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let pixel = if y < card_height {
+                card_pixel(x, y / STRIP_HEIGHT)
+            } else if y < card_height + DIVIDER_HEIGHT {
+                DIVIDER
+            } else {
+                shift(card_pixel(
+                    x,
+                    (y - card_height - DIVIDER_HEIGHT) / STRIP_HEIGHT,
+                ))
+            };
+            pixels.push(pixel);
+        }
+    }
+    Ok(pixels)
+}
+
+/// The unchanged color at column `x` of strip `strip`.
+fn card_pixel(x: usize, strip: usize) -> [u8; 3] {
+    let position = x as f64 / (WIDTH - 1) as f64;
+    match strip {
+        0 => {
+            let level = (position * 255.0).round() as u8;
+            [level; 3]
+        }
+        1 => SWATCHES[x * SWATCHES.len() / WIDTH],
+        _ => hue(position * 360.0),
+    }
+}
+
+/// The fully saturated color of `degrees` on the color wheel.
+fn hue(degrees: f64) -> [u8; 3] {
+    let sector = degrees.rem_euclid(360.0) / 60.0;
+    let rising = ((sector % 1.0) * 255.0).round() as u8;
+    let falling = 255 - rising;
+    match sector as u32 {
+        0 => [255, rising, 0],
+        1 => [falling, 255, 0],
+        2 => [0, 255, rising],
+        3 => [0, falling, 255],
+        4 => [rising, 0, 255],
+        _ => [255, 0, falling],
+    }
+}
+
+/// An 8-bit RGB PNG of `pixels`, given row by row.
+fn encode_png(width: usize, height: usize, pixels: &[[u8; 3]]) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // Bit depth 8, truecolor, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    // Each row starts with its filter type, none here
+    let mut scanlines = Vec::with_capacity(height * (1 + width * 3));
+    for row in pixels.chunks_exact(width) {
+        scanlines.push(0);
+        scanlines.extend(row.iter().flatten());
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// `data` as a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Deflate with a 256-byte window and no preset dictionary, a valid header check
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(usize::from(u16::MAX)).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        stream.push(u8::from(last));
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr render-preview [--temp <K>] [--gamma <%>] --out <file>");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help render-preview");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Render a test card as sunsetr would show it, to a PNG");
+    log_block_start!("Usage: sunsetr render-preview [--temp <K>] [--gamma <%>] --out <file>");
+    log_block_start!("Options:");
+    log_indented!("--temp <K>     Temperature to render (defaults to night_temp)");
+    log_indented!("--gamma <%>    Gamma to render (defaults to night_gamma)");
+    log_indented!("--out <file>   PNG file to write");
+    log_block_start!("Notes:");
+    log_indented!("The top half of the card is unchanged and the bottom half is passed");
+    log_indented!("through the same gamma tables as the outputs, including the config's");
+    log_indented!("channel gammas and trims.");
+    log_block_start!("Examples:");
+    log_indented!("# See what a warm night looks like on another screen");
+    log_indented!("sunsetr render-preview --temp 3300 --out preview.png");
+    log_end!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_known_values() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn png_has_the_signature_size_and_every_row() {
+        let pixels = vec![[10, 20, 30]; 3 * 2];
+        let png = encode_png(3, 2, &pixels);
+
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..20], &3u32.to_be_bytes());
+        assert_eq!(&png[20..24], &2u32.to_be_bytes());
+        assert!(png.ends_with(&[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+
+        // Two rows of a filter byte and three pixels, stored in one final block
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        assert_eq!(idat_len, 2 + 5 + 2 * (1 + 9) + 4);
+        assert_eq!(png[43], 1);
+    }
+
+    #[test]
+    fn stored_blocks_split_at_the_deflate_limit() {
+        let data = vec![7; usize::from(u16::MAX) + 10];
+        let stream = zlib_stored(&data);
+        assert_eq!(stream.len(), 2 + 2 * 5 + data.len() + 4);
+        assert_eq!(stream[2], 0);
+        assert_eq!(stream[2 + 5 + usize::from(u16::MAX)], 1);
+    }
+
+    #[test]
+    fn warm_values_take_blue_out_of_the_bottom_half() {
+        let pixels = render(
+            Kelvin::new(3300).unwrap(),
+            GammaPercent::new(100.0).unwrap(),
+            ChannelGamma::IDENTITY,
+        )
+        .unwrap();
+        let card_height = STRIPS * STRIP_HEIGHT;
+        let white = |y: usize| pixels[y * WIDTH + WIDTH - 1];
+
+        assert_eq!(white(0), [255, 255, 255]);
+        let [red, green, blue] = white(card_height + DIVIDER_HEIGHT);
+        assert!(red > green && green > blue, "{:?}", [red, green, blue]);
+        assert_eq!(pixels.len(), WIDTH * HEIGHT);
+    }
+
+    #[test]
+    fn hue_sweep_starts_red_and_passes_green_and_blue() {
+        assert_eq!(hue(0.0), [255, 0, 0]);
+        assert_eq!(hue(120.0), [0, 255, 0]);
+        assert_eq!(hue(240.0), [0, 0, 255]);
+        assert_eq!(hue(360.0), [255, 0, 0]);
+    }
+}
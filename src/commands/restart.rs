@@ -19,34 +19,23 @@ pub fn handle_restart_command(instant: bool, debug_enabled: bool, background: bo
             log_pipe!();
             log_info!("Restarting sunsetr instance (PID: {})...", pid);
 
-            let termination_result = if instant {
-                match crate::io::instance::send_instant_shutdown_signal(pid) {
-                    Ok(()) => {
-                        if debug_enabled {
-                            log_pipe!();
-                            log_debug!("Instant shutdown signal sent successfully");
-                        }
-                        Ok(())
-                    }
-                    Err(e) => {
-                        log_warning!("Failed to send instant shutdown signal: {}", e);
-                        if debug_enabled {
-                            log_pipe!();
-                            log_debug!("Falling back to normal termination...");
-                        }
-                        crate::io::instance::terminate_instance(pid)
+            // The restart flag tells the instance's IPC clients to reconnect to the
+            // replacement rather than treat the closed socket as sunsetr stopping
+            let termination_result = match crate::io::instance::send_restart_signal(pid, instant) {
+                Ok(()) => {
+                    if debug_enabled {
+                        log_pipe!();
+                        log_debug!("Restart signal sent successfully");
                     }
+                    Ok(())
                 }
-            } else {
-                match crate::io::instance::terminate_instance(pid) {
-                    Ok(()) => {
-                        if debug_enabled {
-                            log_pipe!();
-                            log_debug!("Termination signal sent successfully");
-                        }
-                        Ok(())
+                Err(e) => {
+                    log_warning!("Failed to send restart signal: {}", e);
+                    if debug_enabled {
+                        log_pipe!();
+                        log_debug!("Falling back to normal termination...");
                     }
-                    Err(e) => Err(e),
+                    crate::io::instance::terminate_instance(pid)
                 }
             };
 
@@ -59,11 +48,9 @@ pub fn handle_restart_command(instant: bool, debug_enabled: bool, background: bo
                             Ok(config) => {
                                 let resolved_backend = crate::backend::detect_backend(&config)
                                     .unwrap_or(crate::backend::BackendType::Wayland);
-                                let backend_supports_smoothing = matches!(
-                                    resolved_backend,
-                                    crate::backend::BackendType::Wayland
-                                );
-                                let smoothing_enabled = config.smoothing;
+                                let backend_supports_smoothing =
+                                    resolved_backend.capabilities().smoothing;
+                                let smoothing_enabled = config.smoothing && !config.reduce_motion;
                                 let shutdown_duration = config.shutdown_duration;
 
                                 let base_timeout_ms = 3000u64;
@@ -129,12 +116,12 @@ pub fn handle_restart_command(instant: bool, debug_enabled: bool, background: bo
         }
     }
 
-    // --instant only affects the Wayland backend. Warn when it is a no-op
+    // --instant only affects backends that run smooth transitions. Warn when it is a no-op
     // so the user is not surprised that nothing changed.
     if instant
         && let Ok(config) = crate::config::Config::load()
         && let Ok(backend_type) = crate::backend::detect_backend(&config)
-        && !matches!(backend_type, crate::backend::BackendType::Wayland)
+        && !backend_type.capabilities().smoothing
     {
         log_pipe!();
         log_warning!("The --instant flag has no effect with Hyprland-based backends");
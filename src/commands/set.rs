@@ -2,9 +2,9 @@
 //! instance can hot-reload the change.
 
 use crate::args::SetOperator;
+use crate::common::units::{GammaPercent, Kelvin};
 use crate::common::utils::private_path;
-use crate::config::{Backend, TransitionMode};
-use crate::core::period::Period;
+use crate::config::{Backend, ColorMatrix, HyprsunsetMode, ShutdownTarget, TransitionMode};
 use crate::state::ipc::client::IpcClient;
 use anyhow::{Context, Result};
 use std::fs;
@@ -23,10 +23,12 @@ const INCREMENTABLE_FIELDS: &[&str] = &[
 ];
 
 /// Apply `field=value` (and `+=` / `-=`) updates to the active config, the base config
-/// (`target` = "default"), or a named preset.
+/// (`target` = "default"), or a named preset. With `create`, a missing target preset is
+/// first made as a copy of the active config.
 pub fn handle_set_command(
     fields: Vec<(String, SetOperator, String)>,
     target: Option<&str>,
+    create: bool,
 ) -> Result<()> {
     log_version!();
 
@@ -37,6 +39,8 @@ pub fn handle_set_command(
         return Ok(());
     }
 
+    crate::config::ensure_config_writable()?;
+
     let has_current_alias = fields
         .iter()
         .any(|(f, _, _)| f == "current_temp" || f == "current_gamma");
@@ -97,10 +101,32 @@ pub fn handle_set_command(
         target
     };
 
+    if create
+        && let Some(preset_name) = final_target
+        && preset_name != "default"
+    {
+        super::preset::validate_preset_name(preset_name)?;
+        let base_config_path = crate::config::Config::get_config_path()?;
+        let preset_dir = base_config_path
+            .parent()
+            .context("Failed to get config directory")?
+            .join("presets")
+            .join(preset_name);
+        let active_config_path = super::resolve_target_config_path(None)?;
+        if scaffold_preset(&active_config_path, &preset_dir)? {
+            log_block_start!(
+                "Created preset '{}' from the active configuration",
+                preset_name
+            );
+        }
+    }
+
     let config_path = match super::resolve_target_config_path(final_target) {
         Ok(path) => path,
         Err(e) => {
             if let Some(preset_error) = e.downcast_ref::<super::PresetNotFoundError>() {
+                log_pipe!();
+                log_info!("Add --create to make it from the active configuration");
                 super::handle_preset_not_found_error(preset_error);
             } else {
                 return Err(e);
@@ -112,42 +138,8 @@ pub fn handle_set_command(
     let is_active_config = config_path == active_config_path;
     let mut fields = fields;
     resolve_current_aliases(&mut fields)?;
-    let fields = resolve_relative_operations(fields, &config_path)?;
-    let mut validated_fields = Vec::new();
-
-    for (field, value) in &fields {
-        match validate_field_value(field, value) {
-            Err(e) => {
-                if e.to_string().starts_with("Unknown field") {
-                    log_pipe!();
-                    log_error!("Unknown configuration field: '{}'", field);
-                    log_block_start!("Available fields:");
-                    log_indented!("backend, transition_mode");
-                    log_indented!(
-                        "smoothing, startup_duration, shutdown_duration, adaptive_interval"
-                    );
-                    log_indented!("night_temp, day_temp, night_gamma, day_gamma, update_interval");
-                    log_indented!("static_temp, static_gamma");
-                    log_indented!("sunset, sunrise, transition_duration");
-                    log_indented!("latitude, longitude");
-                } else {
-                    let error_msg = e.to_string();
-                    if let Some((first_line, rest)) = error_msg.split_once('\n') {
-                        log_error_end!("{}: {}", field, first_line);
-                        for line in rest.lines() {
-                            println!("  {}", line);
-                        }
-                    } else {
-                        log_error_end!("{}: {}", field, error_msg);
-                    }
-                }
-                std::process::exit(1);
-            }
-            Ok(formatted_value) => {
-                validated_fields.push((field.as_ref(), formatted_value));
-            }
-        }
-    }
+    let fields = resolve_relative_operations(fields, &read_values(&config_path)?)?;
+    let validated_fields = validate_fields_or_exit(&fields);
 
     let geo_path = config_path
         .parent()
@@ -246,6 +238,16 @@ pub fn handle_set_command(
         }
 
         if is_active_config {
+            let overrides = crate::state::overrides::load().unwrap_or_default();
+            for (field, _) in updated_fields
+                .iter()
+                .filter(|(field, _)| overrides.contains_key(**field))
+            {
+                log_warning!(
+                    "{} is overridden at runtime until: sunsetr set --clear",
+                    field
+                );
+            }
             if let Ok(pid) = crate::io::instance::get_running_instance_pid() {
                 log_block_start!("Configuration reloaded successfully (PID: {})", pid);
             } else {
@@ -273,6 +275,130 @@ pub fn handle_set_command(
     Ok(())
 }
 
+/// Validate each `(field, value)`, returning the values formatted as TOML. Reports the
+/// first invalid field and exits.
+fn validate_fields_or_exit(fields: &[(String, String)]) -> Vec<(&str, String)> {
+    let mut validated_fields = Vec::new();
+
+    for (field, value) in fields {
+        match validate_field_value(field, value) {
+            Err(e) => {
+                if e.to_string().starts_with("Unknown field") {
+                    log_pipe!();
+                    log_error!("Unknown configuration field: '{}'", field);
+                    log_block_start!("Available fields:");
+                    log_indented!("backend, transition_mode, verify_gamma, hyprsunset_mode");
+                    log_indented!("smoothing, reduce_motion, startup_duration, shutdown_duration");
+                    log_indented!("shutdown_target, adaptive_interval");
+                    log_indented!("night_temp, day_temp, night_gamma, day_gamma, update_interval");
+                    log_indented!("night_brightness, day_brightness");
+                    log_indented!("static_temp, static_gamma");
+                    log_indented!("sunset, sunrise, transition_duration, night_latest_start");
+                    log_indented!("min_day_duration");
+                    log_indented!("latitude, longitude");
+                } else {
+                    let error_msg = e.to_string();
+                    if let Some((first_line, rest)) = error_msg.split_once('\n') {
+                        log_error_end!("{}: {}", field, first_line);
+                        for line in rest.lines() {
+                            println!("  {}", line);
+                        }
+                    } else {
+                        log_error_end!("{}: {}", field, error_msg);
+                    }
+                }
+                std::process::exit(1);
+            }
+            Ok(formatted_value) => {
+                validated_fields.push((field.as_ref(), formatted_value));
+            }
+        }
+    }
+
+    validated_fields
+}
+
+/// Apply `field=value` updates as runtime overrides, leaving the config files untouched.
+/// Relative operators start from the active config with the current overrides applied.
+pub fn handle_runtime_set(fields: Vec<(String, SetOperator, String)>) -> Result<()> {
+    log_version!();
+
+    if crate::io::instance::is_test_mode_active() {
+        log_error_end!(
+            "Cannot modify configuration while test mode is active\n   Exit test mode first (press Escape in the test terminal)"
+        );
+        return Ok(());
+    }
+
+    let mut fields = fields;
+    resolve_current_aliases(&mut fields)?;
+    if let Some((field, _, _)) = fields
+        .iter()
+        .find(|(f, _, _)| f == "latitude" || f == "longitude")
+    {
+        log_pipe!();
+        log_error!("{} cannot be overridden at runtime", field);
+        log_indented!("Change the location with: sunsetr geo");
+        log_end!();
+        std::process::exit(1);
+    }
+
+    let config_path = super::resolve_target_config_path(None)?;
+    let mut current = read_values(&config_path)?;
+    current.extend(crate::state::overrides::load()?);
+    let fields = resolve_relative_operations(fields, &current)?;
+    let validated_fields = validate_fields_or_exit(&fields);
+
+    let mut values = toml::Table::new();
+    for (field, formatted_value) in &validated_fields {
+        let parsed: toml::Table = format!("{field} = {formatted_value}").parse()?;
+        values.extend(parsed);
+    }
+    crate::state::overrides::set(values)?;
+
+    log_block_start!("Set runtime overrides");
+    for (field, value) in &validated_fields {
+        log_indented!("{} = {}", field, value);
+    }
+    log_indented!("Clear them with: sunsetr set --clear");
+    if let Ok(pid) = crate::io::instance::get_running_instance_pid() {
+        log_block_start!("Configuration reloaded successfully (PID: {})", pid);
+    } else {
+        log_block_start!("Start sunsetr to apply the overrides");
+    }
+
+    log_end!();
+    Ok(())
+}
+
+/// Drop all runtime overrides, returning to the config files alone.
+pub fn handle_clear_overrides() -> Result<()> {
+    log_version!();
+
+    if crate::state::overrides::clear()? {
+        log_block_start!("Cleared runtime overrides");
+        if let Ok(pid) = crate::io::instance::get_running_instance_pid() {
+            log_indented!("Configuration reloaded successfully (PID: {})", pid);
+        }
+    } else {
+        log_block_start!("No runtime overrides to clear");
+    }
+
+    log_end!();
+    Ok(())
+}
+
+/// The top-level values of the config at `config_path`, empty when it doesn't exist.
+fn read_values(config_path: &Path) -> Result<toml::Table> {
+    if !config_path.exists() {
+        return Ok(toml::Table::new());
+    }
+    fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read config from {}", config_path.display()))?
+        .parse()
+        .with_context(|| format!("Failed to parse {}", config_path.display()))
+}
+
 /// Resolve `current_temp` and `current_gamma` aliases to concrete field names.
 ///
 /// These virtual aliases resolve to the field matching the running instance's active period:
@@ -327,13 +453,7 @@ fn resolve_current_aliases(fields: &mut [(String, SetOperator, String)]) -> Resu
         }
     };
 
-    let (temp_field, gamma_field) = match display_state.period {
-        Period::Static => ("static_temp", "static_gamma"),
-        Period::Day => ("day_temp", "day_gamma"),
-        Period::Night => ("night_temp", "night_gamma"),
-        Period::Sunset => ("night_temp", "night_gamma"),
-        Period::Sunrise => ("day_temp", "day_gamma"),
-    };
+    let (temp_field, gamma_field) = crate::core::preview::fields_for(display_state.period);
 
     for (field, _, _) in fields.iter_mut() {
         if field == "current_temp" {
@@ -359,11 +479,11 @@ fn resolve_current_aliases(fields: &mut [(String, SetOperator, String)]) -> Resu
 /// Resolve increment/decrement operations to absolute values.
 ///
 /// For `Assign` operations, passes through unchanged. For `Increment` and `Decrement`,
-/// reads the current value from the config file, computes the new absolute value,
+/// takes the current value from `current`, computes the new absolute value,
 /// and returns it as a plain field=value pair for the existing validation pipeline.
 fn resolve_relative_operations(
     fields: Vec<(String, SetOperator, String)>,
-    config_path: &Path,
+    current: &toml::Table,
 ) -> Result<Vec<(String, String)>> {
     let has_relative = fields.iter().any(|(_, op, _)| *op != SetOperator::Assign);
     if !has_relative {
@@ -373,16 +493,6 @@ fn resolve_relative_operations(
             .collect());
     }
 
-    let content = if config_path.exists() {
-        fs::read_to_string(config_path)
-            .with_context(|| format!("Failed to read config from {}", config_path.display()))?
-    } else {
-        String::new()
-    };
-    let doc: toml_edit::DocumentMut = content
-        .parse()
-        .with_context(|| format!("Failed to parse {}", config_path.display()))?;
-
     let mut resolved = Vec::with_capacity(fields.len());
 
     for (field, op, value) in fields {
@@ -401,7 +511,7 @@ fn resolve_relative_operations(
                     std::process::exit(1);
                 }
 
-                let current_value = match doc.get(&field).and_then(|item| item.as_value()) {
+                let current_value = match current.get(&field) {
                     Some(value) => value,
                     None => {
                         let op_word = match op {
@@ -492,7 +602,7 @@ fn resolve_relative_operations(
     Ok(resolved)
 }
 
-fn atomic_write_file(path: &std::path::Path, content: &str) -> Result<()> {
+pub(crate) fn atomic_write_file(path: &std::path::Path, content: &str) -> Result<()> {
     let parent = path
         .parent()
         .context("Failed to get parent directory for atomic write")?;
@@ -517,7 +627,17 @@ fn atomic_write_file(path: &std::path::Path, content: &str) -> Result<()> {
 
 fn validate_field_value(field: &str, value: &str) -> Result<String> {
     let toml_value = match field {
-        "sunset" | "sunrise" | "backend" | "transition_mode" => {
+        "sunset" | "sunrise" | "night_latest_start" | "backend" | "transition_mode"
+        | "shutdown_target" | "hyprsunset_mode" => {
+            if (value.starts_with('"') && value.ends_with('"'))
+                || (value.starts_with('\'') && value.ends_with('\''))
+            {
+                value.to_string()
+            } else {
+                format!("\"{}\"", value)
+            }
+        }
+        "matrix" if !value.starts_with('[') => {
             if (value.starts_with('"') && value.ends_with('"'))
                 || (value.starts_with('\'') && value.ends_with('\''))
             {
@@ -553,40 +673,69 @@ fn validate_field_value(field: &str, value: &str) -> Result<String> {
             let temp = field_value
                 .as_integer()
                 .context("Temperature must be an integer")?;
-            if temp < crate::common::constants::MINIMUM_TEMP as i64
-                || temp > crate::common::constants::MAXIMUM_TEMP as i64
+            let temp = u32::try_from(temp)
+                .map_err(|_| anyhow::anyhow!("Temperature {temp}K is out of range"))?;
+            Ok(Kelvin::new(temp)?.get().to_string())
+        }
+
+        "night_gamma" | "day_gamma" | "static_gamma" => {
+            let gamma = field_value
+                .as_float()
+                .or_else(|| field_value.as_integer().map(|i| i as f64))
+                .context("Gamma must be a number")?;
+            GammaPercent::new(gamma)?;
+            if field_value.is_integer() || gamma.fract() == 0.0 {
+                Ok((gamma as i64).to_string())
+            } else {
+                Ok(format!("{:.1}", gamma))
+            }
+        }
+
+        "night_saturation" => {
+            let saturation = field_value
+                .as_float()
+                .or_else(|| field_value.as_integer().map(|i| i as f64))
+                .context("Saturation must be a number")?;
+            if !(crate::common::constants::MINIMUM_SATURATION
+                ..=crate::common::constants::MAXIMUM_SATURATION)
+                .contains(&saturation)
             {
                 anyhow::bail!(
-                    "Temperature must be between {} and {} K",
-                    crate::common::constants::MINIMUM_TEMP,
-                    crate::common::constants::MAXIMUM_TEMP
+                    "Saturation must be between {}% and {}%",
+                    crate::common::constants::MINIMUM_SATURATION,
+                    crate::common::constants::MAXIMUM_SATURATION
                 );
             }
-            Ok(temp.to_string())
+            if field_value.is_integer() || saturation.fract() == 0.0 {
+                Ok((saturation as i64).to_string())
+            } else {
+                Ok(format!("{:.1}", saturation))
+            }
         }
 
-        "night_gamma" | "day_gamma" | "static_gamma" => {
-            let gamma = field_value
+        "night_brightness" | "day_brightness" => {
+            let brightness = field_value
                 .as_float()
                 .or_else(|| field_value.as_integer().map(|i| i as f64))
-                .context("Gamma must be a number")?;
-            if !(crate::common::constants::MINIMUM_GAMMA..=crate::common::constants::MAXIMUM_GAMMA)
-                .contains(&gamma)
+                .context("Brightness must be a number")?;
+            if !(crate::common::constants::MINIMUM_BRIGHTNESS
+                ..=crate::common::constants::MAXIMUM_BRIGHTNESS)
+                .contains(&brightness)
             {
                 anyhow::bail!(
-                    "Gamma must be between {}% and {}%",
-                    crate::common::constants::MINIMUM_GAMMA,
-                    crate::common::constants::MAXIMUM_GAMMA
+                    "Brightness must be between {}% and {}%",
+                    crate::common::constants::MINIMUM_BRIGHTNESS,
+                    crate::common::constants::MAXIMUM_BRIGHTNESS
                 );
             }
-            if field_value.is_integer() || gamma.fract() == 0.0 {
-                Ok((gamma as i64).to_string())
+            if field_value.is_integer() || brightness.fract() == 0.0 {
+                Ok((brightness as i64).to_string())
             } else {
-                Ok(format!("{:.1}", gamma))
+                Ok(format!("{:.1}", brightness))
             }
         }
 
-        "sunset" | "sunrise" => {
+        "sunset" | "sunrise" | "night_latest_start" => {
             let time_str = field_value.as_str().context("Time must be a string")?;
 
             use chrono::NaiveTime;
@@ -620,6 +769,22 @@ fn validate_field_value(field: &str, value: &str) -> Result<String> {
             Ok(duration.to_string())
         }
 
+        "min_day_duration" => {
+            let duration = field_value
+                .as_integer()
+                .context("Duration must be an integer (minutes)")?;
+            if duration < crate::common::constants::MINIMUM_DAY_DURATION_MIN as i64
+                || duration > crate::common::constants::MAXIMUM_DAY_DURATION_MIN as i64
+            {
+                anyhow::bail!(
+                    "Minimum day duration must be between {} and {} minutes",
+                    crate::common::constants::MINIMUM_DAY_DURATION_MIN,
+                    crate::common::constants::MAXIMUM_DAY_DURATION_MIN
+                );
+            }
+            Ok(duration.to_string())
+        }
+
         "startup_duration" | "shutdown_duration" => {
             let duration = field_value
                 .as_float()
@@ -686,7 +851,7 @@ fn validate_field_value(field: &str, value: &str) -> Result<String> {
             Ok(interval.to_string())
         }
 
-        "smoothing" => {
+        "smoothing" | "reduce_motion" | "verify_gamma" => {
             let bool_value = field_value.as_bool().context("Must be true or false")?;
             Ok(bool_value.to_string())
         }
@@ -699,6 +864,22 @@ fn validate_field_value(field: &str, value: &str) -> Result<String> {
             Ok(format!("\"{backend}\""))
         }
 
+        "hyprsunset_mode" => {
+            let mode: HyprsunsetMode = field_value
+                .as_str()
+                .context("Hyprsunset mode must be a string")?
+                .parse()?;
+            Ok(format!("\"{mode}\""))
+        }
+
+        "shutdown_target" => {
+            let target: ShutdownTarget = field_value
+                .as_str()
+                .context("Shutdown target must be a string")?
+                .parse()?;
+            Ok(format!("\"{target}\""))
+        }
+
         "transition_mode" => {
             let mode: TransitionMode = field_value
                 .as_str()
@@ -707,6 +888,26 @@ fn validate_field_value(field: &str, value: &str) -> Result<String> {
             Ok(format!("\"{mode}\""))
         }
 
+        "matrix" => {
+            let matrix: ColorMatrix = field_value
+                .clone()
+                .try_into()
+                .context("Matrix must be a preset name or nine numbers")?;
+            match matrix {
+                ColorMatrix::Preset(preset) => Ok(format!("\"{preset}\"")),
+                ColorMatrix::Custom(values) => {
+                    if values
+                        .iter()
+                        .any(|value| !value.is_finite() || *value < 0.0)
+                    {
+                        anyhow::bail!("Matrix entries must be finite and non-negative");
+                    }
+                    let entries: Vec<String> = values.iter().map(f64::to_string).collect();
+                    Ok(format!("[{}]", entries.join(", ")))
+                }
+            }
+        }
+
         "latitude" => {
             let lat = field_value
                 .as_float()
@@ -754,6 +955,10 @@ pub fn show_usage() {
     log_indented!("                     'default' = base configuration");
     log_indented!("                     <name> = named preset");
     log_indented!("                     (omit to use active configuration)");
+    log_indented!("    --create         Create a missing target preset from the active config");
+    log_indented!("    --runtime        Keep the change as a runtime override in the state");
+    log_indented!("                     directory instead of editing config files");
+    log_indented!("    --clear          Drop all runtime overrides");
     log_block_start!("Operators:");
     log_indented!("<field>=<value>      Set field to value");
     log_indented!("<field>+=<value>     Increment field by value (temp/gamma only)");
@@ -775,27 +980,44 @@ pub fn display_help() {
     log_indented!("                     'default' = base configuration");
     log_indented!("                     <name> = named preset");
     log_indented!("                     (omit to use active configuration)");
+    log_indented!("    --create         Create a missing target preset from the active config");
+    log_indented!("    --runtime        Keep the change as a runtime override in the state");
+    log_indented!("                     directory instead of editing config files");
+    log_indented!("    --clear          Drop all runtime overrides");
     log_block_start!("Operators:");
     log_indented!("<field>=<value>      Set field to value");
     log_indented!("<field>+=<value>     Increment field by value (temp/gamma only)");
     log_indented!("<field>-=<value>     Decrement field by value (temp/gamma only)");
     log_block_start!("Available Fields:");
-    log_indented!("backend              Backend: auto, hyprland, hyprsunset, or wayland");
-    log_indented!("transition_mode      Mode: geo, static, center, finish_by, start_at");
+    log_indented!(
+        "backend              Backend: auto, hyprland, hyprsunset, wayland, x11, drm, kde, gnome, or none"
+    );
+    log_indented!(
+        "transition_mode      Mode: geo, static, center, finish_by, start_at, external, darkman"
+    );
+    log_indented!("verify_gamma         Reapply gamma overwritten by other clients (true/false)");
+    log_indented!("hyprsunset_mode      hyprsunset handling: managed or attach");
     log_indented!("smoothing            Enable smooth transitions (true/false)");
+    log_indented!("reduce_motion        Apply every change at once, even in presets (true/false)");
     log_indented!("startup_duration     Smooth startup time in seconds");
     log_indented!("shutdown_duration    Smooth shutdown time in seconds");
+    log_indented!("shutdown_target      Shutdown fade target: day, reset, or current");
     log_indented!("adaptive_interval    Smooth transition interval in seconds");
     log_indented!("night_temp           Night color temperature (1000-20000)");
     log_indented!("night_gamma          Night gamma percentage (10-200)");
     log_indented!("day_temp             Day color temperature (1000-20000)");
     log_indented!("day_gamma            Day gamma percentage (10-200)");
+    log_indented!("night_brightness     Night brightness percentage (10-100)");
+    log_indented!("day_brightness       Day brightness percentage (10-100)");
     log_indented!("update_interval      Update interval: integer (10-300) sec or \"auto\"");
     log_indented!("static_temp          Static mode temperature (1000-20000)");
     log_indented!("static_gamma         Static mode gamma percentage (10-200)");
     log_indented!("sunset               Sunset time (HH:MM:SS format)");
     log_indented!("sunrise              Sunrise time (HH:MM:SS format)");
     log_indented!("transition_duration  Transition time in minutes");
+    log_indented!("night_latest_start   Latest time night may begin (HH:MM:SS format)");
+    log_indented!("min_day_duration     Minutes of day kept before sunset (60-720)");
+    log_indented!("matrix               Color matrix preset or nine entries (Hyprland only)");
     log_indented!("latitude             Geographic latitude (-90 to 90)");
     log_indented!("longitude            Geographic longitude (-180 to 180)");
     log_block_start!("Aliases (require running instance):");
@@ -823,6 +1045,9 @@ pub fn display_help() {
     log_indented!("sunsetr set --target gaming static_temp=3000");
     log_indented!("sunsetr set -t night night_temp=2800");
     log_pipe!();
+    log_indented!("# Make a variant of the active config with one value changed");
+    log_indented!("sunsetr set --create --target movie night_gamma=80");
+    log_pipe!();
     log_indented!("# Update default configuration while preset is active");
     log_indented!("sunsetr set --target default day_temp=6500");
     log_pipe!();
@@ -831,13 +1056,73 @@ pub fn display_help() {
     log_pipe!();
     log_indented!("# Set multiple fields at once");
     log_indented!("sunsetr set night_temp=3000 day_temp=6500 transition_duration=60");
+    log_pipe!();
+    log_indented!("# Tweak the running config without touching your dotfiles");
+    log_indented!("sunsetr set --runtime night_temp-=300");
+    log_indented!("sunsetr set --clear");
     log_end!();
 }
 
+/// Copy `source` (and a geo.toml beside it) into `preset_dir`, unless the preset already
+/// has a config. Returns whether the preset was created.
+fn scaffold_preset(source: &Path, preset_dir: &Path) -> Result<bool> {
+    let preset_path = preset_dir.join("sunsetr.toml");
+    if preset_path.exists() {
+        return Ok(false);
+    }
+    if !source.exists() {
+        anyhow::bail!(
+            "No configuration to copy at {}\nRun sunsetr once to create one",
+            private_path(source)
+        );
+    }
+
+    fs::create_dir_all(preset_dir)
+        .with_context(|| format!("Failed to create {}", private_path(preset_dir)))?;
+    fs::copy(source, &preset_path)
+        .with_context(|| format!("Failed to create {}", private_path(&preset_path)))?;
+
+    // Keep the preset's coordinates when they live in a private geo.toml
+    if let Some(geo_path) = source.parent().map(|dir| dir.join("geo.toml"))
+        && geo_path.exists()
+    {
+        fs::copy(&geo_path, preset_dir.join("geo.toml"))
+            .with_context(|| format!("Failed to copy {}", private_path(&geo_path)))?;
+    }
+    Ok(true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn scaffold_copies_config_and_geo_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("sunsetr.toml"),
+            "# mine\nnight_temp = 3300\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("geo.toml"), "latitude = 1.0\n").unwrap();
+        let preset_dir = dir.path().join("presets").join("movie");
+
+        assert!(scaffold_preset(&dir.path().join("sunsetr.toml"), &preset_dir).unwrap());
+        assert_eq!(
+            fs::read_to_string(preset_dir.join("sunsetr.toml")).unwrap(),
+            "# mine\nnight_temp = 3300\n"
+        );
+        assert!(preset_dir.join("geo.toml").exists());
+
+        // An existing preset is left alone
+        fs::write(preset_dir.join("sunsetr.toml"), "night_temp = 2000\n").unwrap();
+        assert!(!scaffold_preset(&dir.path().join("sunsetr.toml"), &preset_dir).unwrap());
+        assert_eq!(
+            fs::read_to_string(preset_dir.join("sunsetr.toml")).unwrap(),
+            "night_temp = 2000\n"
+        );
+    }
+
     #[test]
     fn validate_accepts_valid_values() {
         assert_eq!(validate_field_value("day_temp", "3500").unwrap(), "3500");
@@ -850,11 +1135,46 @@ mod tests {
             validate_field_value("transition_mode", "static").unwrap(),
             "\"static\""
         );
+        assert_eq!(
+            validate_field_value("shutdown_target", "current").unwrap(),
+            "\"current\""
+        );
+        assert_eq!(
+            validate_field_value("hyprsunset_mode", "attach").unwrap(),
+            "\"attach\""
+        );
+        assert_eq!(
+            validate_field_value("verify_gamma", "true").unwrap(),
+            "true"
+        );
+        assert_eq!(
+            validate_field_value("night_saturation", "72.5").unwrap(),
+            "72.5"
+        );
+        assert_eq!(
+            validate_field_value("matrix", "deuteranopia").unwrap(),
+            "\"deuteranopia\""
+        );
+        assert_eq!(
+            validate_field_value("matrix", "[1, 0, 0, 0, 1, 0, 0, 0.5, 0.5]").unwrap(),
+            "[1, 0, 0, 0, 1, 0, 0, 0.5, 0.5]"
+        );
     }
 
     #[test]
     fn validate_rejects_out_of_range() {
         assert!(validate_field_value("static_temp", "99999").is_err());
         assert!(validate_field_value("day_gamma", "500").is_err());
+        assert!(validate_field_value("night_saturation", "150").is_err());
+        assert_eq!(
+            validate_field_value("night_brightness", "60").unwrap(),
+            "60"
+        );
+        assert!(validate_field_value("day_brightness", "120").is_err());
+        assert!(validate_field_value("shutdown_target", "night").is_err());
+        assert!(validate_field_value("hyprsunset_mode", "spawn").is_err());
+        assert!(validate_field_value("matrix", "rainbow").is_err());
+        assert!(validate_field_value("matrix", "[1, 0, 0, 0, 1, 0, 0, 0, -1]").is_err());
+        assert!(validate_field_value("matrix", "[1, 0, 0]").is_err());
     }
 }
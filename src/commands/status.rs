@@ -5,17 +5,19 @@
 //! text output.
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
 use std::io::Write;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::Duration;
 
+use crate::common::units::{GammaPercent, Kelvin};
 use crate::common::utils::format_progress_percentage;
-use crate::core::period::Period;
+use crate::core::period::{ChangeReason, Period};
 use crate::state::display::DisplayState;
-use crate::state::ipc::client::{ConnectionClosed, IpcClient};
-use crate::state::ipc::events::IpcEvent;
+use crate::state::ipc::client::{ConnectionClosed, IpcClient, RECONNECT_TIMEOUT};
+use crate::state::ipc::events::{IpcEvent, OverrideKind, PROTOCOL_VERSION, PreviewAction};
 
 /// Time remaining until the next period, rounded up to whole seconds.
 fn calculate_time_remaining(state: &DisplayState) -> Option<u64> {
@@ -34,6 +36,14 @@ fn calculate_time_remaining(state: &DisplayState) -> Option<u64> {
     }
 }
 
+/// Time left until `end`, such as the end of a `preset --for` switch or a pause, rounded
+/// up to whole seconds.
+fn time_remaining_until(end: Option<DateTime<Local>>) -> Option<u64> {
+    let duration = end? - chrono::Local::now();
+    (duration.num_seconds() > 0)
+        .then(|| crate::common::utils::format_chrono_duration_seconds_ceil(duration))
+}
+
 /// Connect over IPC and either print the current state once or, in follow mode, stream
 /// events until interrupted.
 pub fn handle_status_command(json: bool, follow: bool) -> Result<()> {
@@ -67,35 +77,45 @@ fn output_status(state: &DisplayState, json: bool) -> Result<()> {
 }
 
 fn display_human_readable(state: &DisplayState) -> Result<()> {
-    println!(" Active preset: {}", state.active_preset);
+    match (
+        state.preset_expires_at,
+        time_remaining_until(state.preset_expires_at),
+    ) {
+        (Some(expires_at), Some(remaining)) => println!(
+            " Active preset: {} (until {}, in {})",
+            state.active_preset,
+            expires_at.format("%H:%M"),
+            format_duration(remaining)
+        ),
+        _ => println!(" Active preset: {}", state.active_preset),
+    }
 
-    if state.period.is_transitioning() {
+    if let Some(transition) = &state.transition {
         println!(
             "Current period: {} {} ({})",
             state.period.display_name(),
             state.period.symbol(),
-            format_progress_percentage(
-                state
-                    .progress
-                    .expect("transitioning period should always have progress"),
-                None
-            )
+            format_progress_percentage(transition.progress, None)
         );
         println!("         State: {}", state.period_type);
         println!(
             "   Temperature: {}K → {}K",
-            state.current_temp,
-            state
-                .target_temp
-                .expect("transitioning period should always have target_temp")
+            state.current_temp, transition.target_temp
         );
         println!(
             "         Gamma: {:.1}% → {:.1}%",
-            state.current_gamma,
-            state
-                .target_gamma
-                .expect("transitioning period should always have target_gamma")
+            state.current_gamma, transition.target_gamma
         );
+        if let Some(next_update) = &state.next_update {
+            let remaining = crate::common::utils::format_chrono_duration_seconds_ceil(
+                *next_update - chrono::Local::now(),
+            );
+            println!(
+                "   Next update: {} (in {})",
+                next_update.format("%H:%M:%S"),
+                format_duration(remaining)
+            );
+        }
     } else {
         println!(
             "Current period: {} {}",
@@ -107,6 +127,30 @@ fn display_human_readable(state: &DisplayState) -> Result<()> {
         println!("         Gamma: {:.1}%", state.current_gamma);
     }
 
+    if let Some(presentation) = &state.presentation {
+        println!("  Presentation: neutral on {}", presentation.describe());
+    }
+    if let Some(adjustment) = &state.adjustment {
+        match time_remaining_until(Some(adjustment.expires_at)) {
+            Some(remaining) => println!(
+                "    Adjustment: {} (until {}, in {})",
+                adjustment.describe(),
+                adjustment.expires_at.format("%H:%M"),
+                format_duration(remaining)
+            ),
+            None => println!("    Adjustment: {}", adjustment.describe()),
+        }
+    }
+    match (state.paused_until, time_remaining_until(state.paused_until)) {
+        (Some(until), Some(remaining)) if state.paused => println!(
+            "        Paused: 6500K @ 100% on all outputs (until {}, in {})",
+            until.format("%H:%M"),
+            format_duration(remaining)
+        ),
+        _ if state.paused => println!("        Paused: 6500K @ 100% on all outputs"),
+        _ => {}
+    }
+
     if !state.period.is_static()
         && let Some(remaining) = calculate_time_remaining(state)
         && let Some(next) = &state.next_period
@@ -129,7 +173,16 @@ fn handle_follow_mode_via_ipc(mut ipc_client: IpcClient, json: bool) -> Result<(
     signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&stop))?;
     signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(&stop))?;
 
+    let protocol_version = ipc_client
+        .handshake()
+        .context("Failed to negotiate IPC protocol with sunsetr process")?;
+
     if !json {
+        if protocol_version < PROTOCOL_VERSION {
+            println!(
+                "Running sunsetr speaks IPC protocol v{protocol_version} (this client: v{PROTOCOL_VERSION}). Restart it to get every event."
+            );
+        }
         println!("Following sunsetr state changes (press Ctrl+C to stop)...\n");
     }
 
@@ -138,6 +191,9 @@ fn handle_follow_mode_via_ipc(mut ipc_client: IpcClient, json: bool) -> Result<(
         .context("Failed to set IPC socket to non-blocking mode")?;
 
     let mut previous_progress: Option<f32> = None;
+    // Set by a restarting event, after which a closed connection means the socket is
+    // about to come back rather than that sunsetr stopped
+    let mut restarting = false;
 
     loop {
         if stop.load(Ordering::SeqCst) {
@@ -146,9 +202,40 @@ fn handle_follow_mode_via_ipc(mut ipc_client: IpcClient, json: bool) -> Result<(
 
         match ipc_client.try_receive_event() {
             Ok(Some(event)) => {
+                restarting |= matches!(event, IpcEvent::Restarting);
                 display_ipc_event(&event, json, &mut previous_progress)?;
             }
             Ok(None) => {}
+            Err(e) if restarting && e.downcast_ref::<ConnectionClosed>().is_some() => {
+                restarting = false;
+                match IpcClient::reconnect(RECONNECT_TIMEOUT, &stop) {
+                    Ok(Some(client)) => {
+                        ipc_client = client;
+                        ipc_client
+                            .handshake()
+                            .context("Failed to negotiate IPC protocol with sunsetr process")?;
+                        ipc_client
+                            .set_nonblocking(true)
+                            .context("Failed to set IPC socket to non-blocking mode")?;
+                        previous_progress = None;
+                        if !json {
+                            println!(
+                                "[{}] RESTART: reconnected",
+                                chrono::Local::now().format("%H:%M:%S")
+                            );
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(_) => {
+                        if !json {
+                            eprintln!(
+                                "Sunsetr did not come back after restarting. Exiting follow mode."
+                            );
+                        }
+                        break;
+                    }
+                }
+            }
             Err(e) => {
                 if !json {
                     if e.downcast_ref::<ConnectionClosed>().is_some() {
@@ -184,14 +271,15 @@ fn display_ipc_event(
         std::io::stdout().flush()?;
     } else {
         match event {
-            IpcEvent::StateApplied { state } => {
+            IpcEvent::StateApplied { state, .. } => {
                 display_state_event(state, previous_progress)?;
             }
             IpcEvent::PeriodChanged {
                 from_period,
                 to_period,
+                reason,
             } => {
-                display_period_changed_event(from_period, to_period)?;
+                display_period_changed_event(from_period, to_period, *reason)?;
                 *previous_progress = None;
             }
             IpcEvent::PresetChanged {
@@ -216,6 +304,39 @@ fn display_ipc_event(
             } => {
                 display_config_changed_event(target_period, *target_temp, *target_gamma)?;
             }
+            IpcEvent::OutputAdded { name } => display_output_event("connected", name)?,
+            IpcEvent::OutputRemoved { name } => display_output_event("disconnected", name)?,
+            IpcEvent::PreviewChanged {
+                action,
+                temp,
+                gamma,
+            } => display_preview_event(*action, *temp, *gamma)?,
+            IpcEvent::CoordinatesRejected {
+                latitude,
+                longitude,
+                reason,
+                fallback_mode,
+            } => display_coordinates_rejected_event(*latitude, *longitude, reason, fallback_mode)?,
+            IpcEvent::OverrideExpired { kind, fields } => {
+                display_override_expired_event(*kind, fields)?
+            }
+            IpcEvent::NeutralHeld { held } => display_neutral_held_event(*held)?,
+            IpcEvent::PauseChanged { paused, until } => display_pause_event(*paused, *until)?,
+            IpcEvent::Restarting => {
+                println!(
+                    "[{}] RESTART: sunsetr is restarting, reconnecting...",
+                    chrono::Local::now().format("%H:%M:%S")
+                );
+                std::io::stdout().flush()?;
+            }
+            // Liveness only, nothing to show
+            // Text output already shows every applied state
+            IpcEvent::ValuesChanged { .. }
+            | IpcEvent::Heartbeat { .. }
+            | IpcEvent::Hello { .. }
+            | IpcEvent::RequestRejected { .. }
+            | IpcEvent::RecentEvents { .. }
+            | IpcEvent::History { .. } => {}
         }
     }
     Ok(())
@@ -229,13 +350,10 @@ fn display_state_event(
     print!("[{}] ", now.format("%H:%M:%S"));
 
     let label = display_state.period.display_name().to_lowercase();
-    let state_description = if display_state.period.is_transitioning() {
-        let progress = display_state
-            .progress
-            .expect("transitioning period should always have progress");
+    let state_description = if let Some(transition) = &display_state.transition {
         let mut desc = format!(
             "{label} {}",
-            format_progress_percentage(progress, *previous_progress)
+            format_progress_percentage(transition.progress, *previous_progress)
         );
         if let Some(remaining) = calculate_time_remaining(display_state) {
             let duration_str = format_duration(remaining);
@@ -254,42 +372,66 @@ fn display_state_event(
         display_state.current_gamma
     );
 
-    if display_state.period.is_transitioning() {
+    if let Some(transition) = &display_state.transition {
         print!(
             " → {}K @ {:.1}%",
-            display_state
-                .target_temp
-                .expect("Transitioning period should always have target_temp"),
-            display_state
-                .target_gamma
-                .expect("Transitioning period should always have target_gamma")
+            transition.target_temp, transition.target_gamma
         );
     } else if let Some(remaining) = calculate_time_remaining(display_state) {
         let duration_str = format_duration(remaining);
         print!(" | {} until next", duration_str);
     }
 
+    if let Some(remaining) = time_remaining_until(display_state.preset_expires_at) {
+        print!(" | preset ends in {}", format_duration(remaining));
+    }
+
+    if let Some(presentation) = &display_state.presentation {
+        print!(" | presentation: {}", presentation.describe());
+    }
+
+    if let Some(adjustment) = &display_state.adjustment {
+        print!(" | adjusted {}", adjustment.describe());
+    }
+
+    if let Some(remaining) = time_remaining_until(display_state.paused_until) {
+        print!(" | paused for {}", format_duration(remaining));
+    } else if display_state.paused {
+        print!(" | paused");
+    }
+
     println!();
     std::io::stdout().flush()?;
 
     if display_state.period.is_transitioning() {
-        *previous_progress = display_state.progress;
+        *previous_progress = display_state
+            .transition
+            .map(|transition| transition.progress);
     }
 
     Ok(())
 }
 
-fn display_period_changed_event(from_period: &Period, to_period: &Period) -> Result<()> {
+fn display_period_changed_event(
+    from_period: &Period,
+    to_period: &Period,
+    reason: ChangeReason,
+) -> Result<()> {
     let now = chrono::Local::now();
     print!("[{}] ", now.format("%H:%M:%S"));
 
-    println!(
+    print!(
         "PERIOD: {} {} → {} {}",
         from_period.display_name().to_lowercase(),
         from_period.symbol(),
         to_period.display_name().to_lowercase(),
         to_period.symbol()
     );
+    // Only name causes other than the schedule simply moving on
+    match reason {
+        ChangeReason::Schedule | ChangeReason::Boundary | ChangeReason::Unknown => println!(),
+        reason => println!(" ({reason})"),
+    }
     std::io::stdout().flush()?;
     Ok(())
 }
@@ -298,8 +440,8 @@ fn display_preset_changed_event(
     from_preset: &Option<String>,
     to_preset: &Option<String>,
     target_period: &Period,
-    target_temp: u32,
-    target_gamma: f64,
+    target_temp: Kelvin,
+    target_gamma: GammaPercent,
 ) -> Result<()> {
     let now = chrono::Local::now();
     print!("[{}] ", now.format("%H:%M:%S"));
@@ -321,8 +463,8 @@ fn display_preset_changed_event(
 
 fn display_config_changed_event(
     target_period: &Period,
-    target_temp: u32,
-    target_gamma: f64,
+    target_temp: Kelvin,
+    target_gamma: GammaPercent,
 ) -> Result<()> {
     let now = chrono::Local::now();
     print!("[{}] ", now.format("%H:%M:%S"));
@@ -334,6 +476,85 @@ fn display_config_changed_event(
     Ok(())
 }
 
+fn display_output_event(change: &str, name: &str) -> Result<()> {
+    let now = chrono::Local::now();
+    println!("[{}] OUTPUT: {name} {change}", now.format("%H:%M:%S"));
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
+fn display_preview_event(action: PreviewAction, temp: Kelvin, gamma: GammaPercent) -> Result<()> {
+    let now = chrono::Local::now();
+    let action = match action {
+        PreviewAction::Set => "showing",
+        PreviewAction::Commit => "committed",
+        PreviewAction::Cancel => "cancelled",
+    };
+    println!(
+        "[{}] PREVIEW: {action} {temp}K @ {gamma:.1}%",
+        now.format("%H:%M:%S")
+    );
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
+fn display_neutral_held_event(held: bool) -> Result<()> {
+    let now = chrono::Local::now();
+    let change = if held {
+        "outputs held for a screenshot"
+    } else {
+        "released"
+    };
+    println!("[{}] NEUTRAL: {change}", now.format("%H:%M:%S"));
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
+fn display_pause_event(paused: bool, until: Option<DateTime<Local>>) -> Result<()> {
+    let now = chrono::Local::now();
+    let change = match until {
+        Some(until) if paused => format!("paused until {}", until.format("%H:%M")),
+        _ if paused => "paused".to_string(),
+        _ => "resumed".to_string(),
+    };
+    println!(
+        "[{}] PAUSE: color shifting {change}",
+        now.format("%H:%M:%S")
+    );
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
+fn display_override_expired_event(kind: OverrideKind, fields: &[String]) -> Result<()> {
+    let now = chrono::Local::now();
+    let dropped = match kind {
+        OverrideKind::Runtime => fields.join(", "),
+        OverrideKind::Test => "test mode".to_string(),
+        OverrideKind::Adjustment => "adjustment".to_string(),
+    };
+    println!(
+        "[{}] OVERRIDE: {dropped} expired, back on schedule",
+        now.format("%H:%M:%S")
+    );
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
+fn display_coordinates_rejected_event(
+    latitude: f64,
+    longitude: f64,
+    reason: &str,
+    fallback_mode: &str,
+) -> Result<()> {
+    let now = chrono::Local::now();
+    println!(
+        "[{}] LOCATION: ignoring {latitude}, {longitude} ({reason}), using {fallback_mode} times",
+        now.format("%H:%M:%S")
+    );
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
 /// A duration of at least a minute rounds to the nearest minute. Shorter
 /// durations show whole seconds.
 fn format_duration(total_seconds: u64) -> String {
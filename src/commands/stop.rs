@@ -30,9 +30,8 @@ pub fn handle_stop_command() -> Result<()> {
     }
 
     let resolved_backend = crate::backend::detect_backend(&config)?;
-    let backend_supports_smoothing =
-        matches!(resolved_backend, crate::backend::BackendType::Wayland);
-    let smoothing_enabled = config.smoothing;
+    let backend_supports_smoothing = resolved_backend.capabilities().smoothing;
+    let smoothing_enabled = config.smoothing && !config.reduce_motion;
     let shutdown_duration = config.shutdown_duration;
 
     if backend_supports_smoothing && smoothing_enabled && shutdown_duration >= 0.1 {
@@ -3,7 +3,8 @@
 //! backend. Escape or Ctrl+C restores the previous state.
 
 use crate::backend::ColorTemperatureBackend;
-use crate::config::Config;
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::{Config, OverrideExpiry};
 use crate::core::period::Period;
 use crate::core::runtime_state::RuntimeState;
 use crate::io::signals::{SignalMessage, TestModeParams};
@@ -13,23 +14,47 @@ use std::sync::mpsc::Sender;
 
 /// Dispatch a signal received inside the test-mode loop, returning whether the loop should break.
 ///
-/// `Reload` and `ResumeFromSleep` are the main loop's responsibility, so they are re-emitted via
-/// `sender` before breaking, letting the main loop process them once test mode returns.
+/// `Reload`, `NeutralHold`, `Pause`, `ResumeFromSleep` and `Refresh` are the main loop's responsibility,
+/// so they are re-emitted via `sender` before breaking, letting the main loop process them
+/// once test mode returns.
 fn handle_test_mode_signal(msg: SignalMessage, sender: &Sender<SignalMessage>) -> ControlFlow<()> {
     match msg {
-        SignalMessage::TestMode(new_params) => {
-            if new_params.temperature == 0 {
-                log_indented!("Exiting test mode, restoring normal operation...");
-                ControlFlow::Break(())
-            } else {
-                ControlFlow::Continue(())
-            }
+        SignalMessage::TestMode(None) => {
+            log_indented!("Exiting test mode, restoring normal operation...");
+            ControlFlow::Break(())
         }
+        SignalMessage::TestMode(Some(_)) => ControlFlow::Continue(()),
         SignalMessage::Reload(config) => {
             log_decorated!("Reload signal received, exiting test mode...");
             let _ = sender.send(SignalMessage::Reload(config));
             ControlFlow::Break(())
         }
+        SignalMessage::NeutralHold(held) => {
+            log_decorated!("Screenshot in progress, exiting test mode...");
+            let _ = sender.send(SignalMessage::NeutralHold(held));
+            ControlFlow::Break(())
+        }
+        SignalMessage::Pause { paused, until } => {
+            log_decorated!("Pause requested, exiting test mode...");
+            let _ = sender.send(SignalMessage::Pause { paused, until });
+            ControlFlow::Break(())
+        }
+        SignalMessage::Preview(_) => {
+            log_decorated!("Preview request ignored while in test mode");
+            ControlFlow::Continue(())
+        }
+        SignalMessage::ExternalTargets { .. } => {
+            log_decorated!("External targets ignored while in test mode");
+            ControlFlow::Continue(())
+        }
+        // Picked up by the main loop's next update once test mode ends
+        SignalMessage::PowerProfileChanged => ControlFlow::Continue(()),
+        SignalMessage::DarkModeChanged => ControlFlow::Continue(()),
+        SignalMessage::MediaPlaybackChanged => ControlFlow::Continue(()),
+        SignalMessage::ReduceMotion(enabled) => {
+            crate::core::smoothing::set_reduce_motion(enabled);
+            ControlFlow::Continue(())
+        }
         SignalMessage::TimeChange => {
             log_decorated!("Time change detected, exiting test mode...");
             ControlFlow::Break(())
@@ -43,50 +68,19 @@ fn handle_test_mode_signal(msg: SignalMessage, sender: &Sender<SignalMessage>) -
             let _ = sender.send(SignalMessage::ResumeFromSleep);
             ControlFlow::Break(())
         }
+        SignalMessage::Refresh => {
+            log_decorated!("Refresh requested, exiting test mode...");
+            let _ = sender.send(SignalMessage::Refresh);
+            ControlFlow::Break(())
+        }
     }
 }
 
-fn validate_temperature(temp: u32) -> Result<()> {
-    use crate::common::constants::{MAXIMUM_TEMP, MINIMUM_TEMP};
-
-    if temp < MINIMUM_TEMP {
-        anyhow::bail!(
-            "Temperature {} is too low (minimum: {}K)",
-            temp,
-            MINIMUM_TEMP
-        );
-    }
-
-    if temp > MAXIMUM_TEMP {
-        anyhow::bail!(
-            "Temperature {} is too high (maximum: {}K)",
-            temp,
-            MAXIMUM_TEMP
-        );
-    }
-
-    Ok(())
-}
-
-fn validate_gamma(gamma: f64) -> Result<()> {
-    use crate::common::constants::{MAXIMUM_GAMMA, MINIMUM_GAMMA};
-
-    if gamma < MINIMUM_GAMMA {
-        anyhow::bail!("Gamma {} is too low (minimum: {})", gamma, MINIMUM_GAMMA);
-    }
-
-    if gamma > MAXIMUM_GAMMA {
-        anyhow::bail!("Gamma {} is too high (maximum: {})", gamma, MAXIMUM_GAMMA);
-    }
-
-    Ok(())
-}
-
 pub fn handle_test_command(temperature: u32, gamma: f64, debug_enabled: bool) -> Result<()> {
     log_version!();
 
-    validate_temperature(temperature)?;
-    validate_gamma(gamma)?;
+    let temperature = Kelvin::new(temperature)?;
+    let gamma = GammaPercent::new(gamma)?;
     let config = Config::load()?;
     log_block_start!("Testing display settings: {}K @ {}%", temperature, gamma);
 
@@ -104,7 +98,7 @@ pub fn handle_test_command(temperature: u32, gamma: f64, debug_enabled: bool) ->
                     );
                 }
 
-                match crate::io::instance::send_test_signal(pid, temperature, gamma) {
+                match crate::io::instance::send_test_signal(pid, temperature.get(), gamma.get()) {
                     Ok(_) => {
                         log_indented!("Test signal sent successfully");
                         std::thread::sleep(std::time::Duration::from_millis(200));
@@ -154,8 +148,8 @@ pub fn handle_test_command(temperature: u32, gamma: f64, debug_enabled: bool) ->
 }
 
 fn run_direct_test(
-    temperature: u32,
-    gamma: f64,
+    temperature: Kelvin,
+    gamma: GammaPercent,
     debug_enabled: bool,
     config: &Config,
 ) -> Result<()> {
@@ -164,8 +158,9 @@ fn run_direct_test(
         crate::backend::BackendType::Hyprsunset => {
             crate::backend::hyprsunset::HyprsunsetBackend::new_with_initial_values(
                 debug_enabled,
-                temperature,
-                gamma,
+                config.hyprsunset_mode,
+                temperature.get(),
+                gamma.get(),
             )
             .map(|backend| Box::new(backend) as Box<dyn crate::backend::ColorTemperatureBackend>)
         }
@@ -181,9 +176,9 @@ fn run_direct_test(
             use std::sync::Arc;
             use std::sync::atomic::AtomicBool;
             let running = Arc::new(AtomicBool::new(true));
-            let is_wayland = backend.backend_name() == "Wayland";
+            let supports_smoothing = backend.capabilities().smoothing;
 
-            let smoothing_enabled = is_wayland && config.smoothing;
+            let smoothing_enabled = supports_smoothing && config.smoothing && !config.reduce_motion;
             let startup_duration = config.startup_duration;
 
             let day_runtime_state = RuntimeState::new(
@@ -238,7 +233,7 @@ fn run_direct_test(
             let _terminal_guard = crate::common::utils::TerminalGuard::new();
             wait_for_user_exit(None)?;
 
-            if is_wayland {
+            if supports_smoothing {
                 log_block_start!("Restoring display...");
 
                 let shutdown_duration = config.shutdown_duration;
@@ -301,14 +296,14 @@ fn run_direct_test(
 
 /// Take over when the main loop receives a SIGUSR1 test signal: apply the test values, wait for an
 /// exit signal, then restore the calculated values. Preserves the main loop's state and timing so
-/// it resumes unchanged.
+/// it resumes unchanged. Returns whether `override_expiry` ended test mode.
 pub fn run_test_mode_loop(
     test_params: TestModeParams,
     backend: &mut Box<dyn ColorTemperatureBackend>,
     signal_state: &crate::io::signals::SignalState,
     current_runtime_state: &crate::core::runtime_state::RuntimeState,
     debug_enabled: bool,
-) -> Result<()> {
+) -> Result<bool> {
     if debug_enabled {
         log_pipe!();
         log_debug!(
@@ -324,8 +319,8 @@ pub fn run_test_mode_loop(
         test_params.gamma
     );
 
-    let is_wayland = backend.backend_name() == "Wayland";
-    let smoothing_enabled = is_wayland && current_runtime_state.config().smoothing;
+    let supports_smoothing = backend.capabilities().smoothing;
+    let smoothing_enabled = supports_smoothing && current_runtime_state.smoothing();
 
     let startup_duration = current_runtime_state.config().startup_duration;
 
@@ -360,7 +355,7 @@ pub fn run_test_mode_loop(
                     Err(e) => {
                         log_pipe!();
                         log_error!("Failed to apply test values: {e}");
-                        return Ok(());
+                        return Ok(false);
                     }
                 }
             }
@@ -386,11 +381,17 @@ pub fn run_test_mode_loop(
             Err(e) => {
                 log_pipe!();
                 log_error!("Failed to apply test values: {e}");
-                return Ok(());
+                return Ok(false);
             }
         }
     }
 
+    let expires_at = (current_runtime_state.config().override_expiry
+        == OverrideExpiry::NextTransition)
+        .then(|| current_runtime_state.next_transition_start())
+        .flatten();
+    let mut expired = false;
+
     loop {
         if !signal_state
             .running
@@ -398,6 +399,11 @@ pub fn run_test_mode_loop(
         {
             break;
         }
+        if expires_at.is_some_and(|at| crate::time::source::now() >= at) {
+            log_decorated!("Next transition began, test mode expired, restoring the schedule...");
+            expired = true;
+            break;
+        }
 
         match signal_state
             .signal_receiver
@@ -476,7 +482,7 @@ pub fn run_test_mode_loop(
         log_debug!("Exiting test mode loop");
     }
 
-    Ok(())
+    Ok(expired)
 }
 
 /// Block until the user presses Escape or Ctrl+C.
@@ -560,27 +566,12 @@ mod tests {
     use crate::config::{Config, TransitionMode};
 
     fn empty_config() -> Config {
-        use crate::common::constants::*;
-        Config {
-            backend: DEFAULT_BACKEND,
-            transition_mode: TransitionMode::Geo,
-            smoothing: DEFAULT_SMOOTHING,
-            startup_duration: DEFAULT_STARTUP_DURATION_SEC,
-            shutdown_duration: DEFAULT_SHUTDOWN_DURATION_SEC,
-            adaptive_interval: DEFAULT_ADAPTIVE_INTERVAL_MS,
-            night_temp: DEFAULT_NIGHT_TEMP,
-            day_temp: DEFAULT_DAY_TEMP,
-            night_gamma: DEFAULT_NIGHT_GAMMA,
-            day_gamma: DEFAULT_DAY_GAMMA,
-            update_interval: crate::config::UpdateInterval::Adaptive,
-            static_temp: None,
-            static_gamma: None,
-            sunset: None,
-            sunrise: None,
-            transition_duration: DEFAULT_TRANSITION_DURATION_MIN,
-            latitude: None,
-            longitude: None,
-        }
+        let mut config = Config::builder()
+            .transition_mode(TransitionMode::Geo)
+            .build();
+        config.sunset = None;
+        config.sunrise = None;
+        config
     }
 
     #[test]
@@ -608,6 +599,15 @@ mod tests {
         assert!(rx.try_recv().is_err());
     }
 
+    #[test]
+    fn refresh_re_emits_and_breaks() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let result = handle_test_mode_signal(SignalMessage::Refresh, &tx);
+        assert!(result.is_break());
+        assert!(matches!(rx.try_recv(), Ok(SignalMessage::Refresh)));
+        assert!(rx.try_recv().is_err());
+    }
+
     #[test]
     fn shutdown_breaks_without_reemit() {
         let (tx, rx) = std::sync::mpsc::channel();
@@ -627,13 +627,7 @@ mod tests {
     #[test]
     fn test_mode_zero_temperature_breaks() {
         let (tx, rx) = std::sync::mpsc::channel();
-        let result = handle_test_mode_signal(
-            SignalMessage::TestMode(TestModeParams {
-                temperature: 0,
-                gamma: 0.0,
-            }),
-            &tx,
-        );
+        let result = handle_test_mode_signal(SignalMessage::TestMode(None), &tx);
         assert!(result.is_break());
         assert!(rx.try_recv().is_err());
     }
@@ -642,10 +636,10 @@ mod tests {
     fn test_mode_nonzero_temperature_continues() {
         let (tx, rx) = std::sync::mpsc::channel();
         let result = handle_test_mode_signal(
-            SignalMessage::TestMode(TestModeParams {
-                temperature: 4500,
-                gamma: 90.0,
-            }),
+            SignalMessage::TestMode(Some(TestModeParams {
+                temperature: Kelvin::new(4500).unwrap(),
+                gamma: GammaPercent::new(90.0).unwrap(),
+            })),
             &tx,
         );
         assert!(result.is_continue());
@@ -0,0 +1,71 @@
+//! Pause color shifting on the running instance, or resume it.
+
+use anyhow::Result;
+
+use crate::common::error::Silent;
+use crate::state::ipc::client::IpcClient;
+
+/// Flip the running instance between paused and its schedule over IPC, and report which
+/// it is now.
+pub fn handle_toggle_command() -> Result<()> {
+    log_version!();
+
+    let Some(mut client) = IpcClient::connect_for_request()? else {
+        log_block_start!("sunsetr isn't running");
+        log_end!();
+        return Ok(());
+    };
+
+    match client.pause(None, None) {
+        Ok(Some(state)) if state.paused => {
+            log_block_start!("Color shifting paused, outputs at 6500K and 100%");
+            log_indented!("Run 'sunsetr toggle' again to resume");
+            log_end!();
+            Ok(())
+        }
+        Ok(Some(state)) => {
+            log_block_start!(
+                "Color shifting resumed at {}K @ {}% ({})",
+                state.current_temp,
+                state.current_gamma,
+                state.period.display_name()
+            );
+            log_end!();
+            Ok(())
+        }
+        Ok(None) => {
+            log_pipe!();
+            log_warning!("sunsetr did not confirm the toggle");
+            log_indented!("Older releases can't be paused, try 'sunsetr restart'");
+            log_end!();
+            Ok(())
+        }
+        Err(e) => {
+            log_error_end!("Toggle rejected: {e}");
+            Err(Silent.into())
+        }
+    }
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr toggle");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help toggle");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Pause color shifting, or resume it");
+    log_block_start!("Usage: sunsetr toggle");
+    log_block_start!("Notes:");
+    log_indented!("While paused, every output shows 6500K at 100% gamma. The schedule");
+    log_indented!("keeps running underneath, so resuming goes straight to the values it");
+    log_indented!("has reached. A pause lasts until toggled again or sunsetr exits;");
+    log_indented!("unlike presentation mode it does not survive a restart.");
+    log_block_start!("Examples:");
+    log_indented!("# Bind to a hotkey for color-sensitive work");
+    log_indented!("sunsetr toggle");
+    log_end!();
+}
@@ -0,0 +1,338 @@
+//! Summarizes a week of the schedule as a grid of days by hours of the day.
+//!
+//! Each cell covers half an hour of local clock time and shows whether it is day, night or
+//! a sunset or sunrise, sampled through the same schedule and [`RuntimeState`] as
+//! `compute`. Rows line up by clock time, so a daylight saving change shows as the
+//! transitions jumping an hour, and weeks near the poles as days without a night or a
+//! sunrise.
+//!
+//! [`RuntimeState`]: crate::core::runtime_state::RuntimeState
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeDelta, TimeZone};
+use std::io::IsTerminal;
+
+use crate::commands::compute::{geo_times_at, runtime_state};
+use crate::config::{Config, TransitionMode};
+use crate::core::period::Period;
+
+const DAYS: usize = 7;
+/// Minutes of clock time per cell.
+const CELL_MINUTES: u32 = 30;
+const CELLS: usize = (24 * 60 / CELL_MINUTES) as usize;
+/// Minutes between samples within a cell, starting halfway into the first step.
+const SAMPLE_MINUTES: u32 = 5;
+
+/// What a half hour of the day looks like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Day,
+    /// Any part of it in a sunset or sunrise.
+    Transition,
+    Night,
+    /// Skipped by a daylight saving change.
+    Skipped,
+}
+
+impl Cell {
+    fn ascii(self) -> char {
+        match self {
+            Self::Day => '#',
+            Self::Transition => '+',
+            Self::Night => '.',
+            Self::Skipped => ' ',
+        }
+    }
+
+    /// ANSI color of the block drawn for the cell.
+    fn color(self) -> Option<&'static str> {
+        match self {
+            Self::Day => Some("\x1b[33m"),
+            Self::Transition => Some("\x1b[31m"),
+            Self::Night => Some("\x1b[34m"),
+            Self::Skipped => None,
+        }
+    }
+}
+
+/// One row of the grid.
+#[derive(Debug, Clone, PartialEq)]
+struct DaySummary {
+    date: NaiveDate,
+    cells: Vec<Cell>,
+    sunrise: Option<DateTime<Local>>,
+    sunset: Option<DateTime<Local>>,
+    /// Whether the UTC offset differs between the start and end of the day.
+    clocks_change: bool,
+}
+
+/// Show the week starting on `start`, today when not given, in colored blocks on a
+/// terminal or in ASCII when `ascii` is set or output is redirected.
+pub fn handle_week_command(start: Option<String>, ascii: bool) -> Result<()> {
+    let config = Config::load()?;
+    let start = match start {
+        Some(text) => NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+            .with_context(|| format!("Invalid date '{text}'. Use YYYY-MM-DD"))?,
+        None => Local::now().date_naive(),
+    };
+
+    log_version!();
+    if config.transition_mode == TransitionMode::Static {
+        log_block_start!("Static mode holds the same values all week, nothing to chart");
+        log_end!();
+        return Ok(());
+    }
+
+    let days = start
+        .iter_days()
+        .take(DAYS)
+        .map(|date| summarize_day(&config, date))
+        .collect::<Result<Vec<_>>>()?;
+    let color = !ascii && std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+
+    log_block_start!("Week of {start} ({} mode)", config.transition_mode);
+    log_pipe!();
+    for line in render(&days, color) {
+        log_indented!("{line}");
+    }
+    log_block_start!("{}", legend(color));
+    log_end!();
+    Ok(())
+}
+
+fn summarize_day(config: &Config, date: NaiveDate) -> Result<DaySummary> {
+    let at = |minutes: u32| -> Option<DateTime<Local>> {
+        let time = NaiveTime::MIN + TimeDelta::minutes(i64::from(minutes));
+        Local.from_local_datetime(&date.and_time(time)).earliest()
+    };
+    let period_at = |time: DateTime<Local>| -> Result<Period> {
+        Ok(runtime_state(config, geo_times_at(config, time)?, time).period())
+    };
+
+    let mut cells = Vec::with_capacity(CELLS);
+    let mut sunrise = None;
+    let mut sunset = None;
+    let mut previous: Option<(DateTime<Local>, Period)> = None;
+    for cell in 0..CELLS as u32 {
+        let mut periods = Vec::new();
+        for step in 0..CELL_MINUTES / SAMPLE_MINUTES {
+            let minutes = cell * CELL_MINUTES + step * SAMPLE_MINUTES + SAMPLE_MINUTES / 2;
+            let Some(time) = at(minutes) else {
+                continue;
+            };
+            let period = period_at(time)?;
+            // The exact start of a transition, from the sample before it
+            if let Some((before, before_period)) = previous
+                && before_period != period
+            {
+                let start = runtime_state(config, geo_times_at(config, before)?, before)
+                    .next_period_start();
+                match period {
+                    Period::Sunrise => sunrise = sunrise.or(start),
+                    Period::Sunset => sunset = sunset.or(start),
+                    _ => {}
+                }
+            }
+            previous = Some((time, period));
+            periods.push(period);
+        }
+        cells.push(classify(&periods));
+    }
+
+    let offset = |time: Option<DateTime<Local>>| time.map(|time| *time.offset());
+    let end_of_day = date
+        .succ_opt()
+        .and_then(|next| {
+            Local
+                .from_local_datetime(&next.and_time(NaiveTime::MIN))
+                .earliest()
+        })
+        .map(|midnight| midnight - TimeDelta::seconds(1));
+    Ok(DaySummary {
+        date,
+        cells,
+        sunrise,
+        sunset,
+        clocks_change: offset(at(0)) != offset(end_of_day),
+    })
+}
+
+/// The cell for `periods` sampled across it: a transition when any sample is in one,
+/// otherwise whichever of day and night most samples are in.
+fn classify(periods: &[Period]) -> Cell {
+    if periods.is_empty() {
+        return Cell::Skipped;
+    }
+    if periods.iter().any(|period| period.is_transitioning()) {
+        return Cell::Transition;
+    }
+    let night = periods
+        .iter()
+        .filter(|&&period| period == Period::Night)
+        .count();
+    if night * 2 > periods.len() {
+        Cell::Night
+    } else {
+        Cell::Day
+    }
+}
+
+/// The hour header followed by one row per day.
+fn render(days: &[DaySummary], color: bool) -> Vec<String> {
+    let mut lines = vec![format!("{:10} {}", "", hour_labels().trim_end())];
+    for day in days {
+        let time = |time: Option<DateTime<Local>>| {
+            time.map_or_else(
+                || "--:--".to_string(),
+                |time| time.format("%H:%M").to_string(),
+            )
+        };
+        let mut line = format!(
+            "{} {}  sunrise {}  sunset {}",
+            day.date.format("%a %b %d"),
+            cells(&day.cells, color),
+            time(day.sunrise),
+            time(day.sunset)
+        );
+        if day.clocks_change {
+            line.push_str("  clocks change");
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Every third hour labeled above its first cell.
+fn hour_labels() -> String {
+    let cells_per_hour = (60 / CELL_MINUTES) as usize;
+    (0..24)
+        .step_by(3)
+        .map(|hour| {
+            format!(
+                "{:<width$}",
+                format!("{hour:02}"),
+                width = 3 * cells_per_hour
+            )
+        })
+        .collect()
+}
+
+/// `cells` as colored blocks, one color escape per run, or as ASCII.
+fn cells(cells: &[Cell], color: bool) -> String {
+    if !color {
+        return cells.iter().map(|cell| cell.ascii()).collect();
+    }
+    let mut text = String::new();
+    let mut current = None;
+    for cell in cells {
+        if current != Some(*cell) {
+            text.push_str(cell.color().unwrap_or("\x1b[0m"));
+            current = Some(*cell);
+        }
+        text.push(if *cell == Cell::Skipped { ' ' } else { '█' });
+    }
+    text.push_str("\x1b[0m");
+    text
+}
+
+fn legend(color: bool) -> String {
+    [Cell::Day, Cell::Transition, Cell::Night]
+        .into_iter()
+        .zip(["day", "sunset or sunrise", "night"])
+        .map(|(cell, name)| format!("{} {name}", self::cells(&[cell], color)))
+        .collect::<Vec<_>>()
+        .join("   ")
+}
+
+pub fn show_usage() {
+    log_version!();
+    log_block_start!("Usage: sunsetr week [--start <YYYY-MM-DD>] [--ascii]");
+    log_pipe!();
+    log_info!("For detailed help with examples, try: sunsetr help week");
+    log_end!();
+}
+
+pub fn display_help() {
+    log_version!();
+    log_block_start!("Show a week of day, night and transitions as a grid");
+    log_block_start!("Usage: sunsetr week [--start <YYYY-MM-DD>] [--ascii]");
+    log_block_start!("Options:");
+    log_indented!("--start <date>  First day of the week, in local time (defaults to today)");
+    log_indented!("--ascii         Draw with plain characters instead of colored blocks");
+    log_block_start!("Notes:");
+    log_indented!("Each cell is half an hour of local clock time. Colors are left out");
+    log_indented!("when output is not a terminal or NO_COLOR is set.");
+    log_block_start!("Examples:");
+    log_indented!("# The coming week");
+    log_indented!("sunsetr week");
+    log_pipe!();
+    log_indented!("# The week daylight saving time ends in Europe");
+    log_indented!("sunsetr week --start 2025-10-23");
+    log_end!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Backend, UpdateInterval};
+
+    fn config() -> Config {
+        Config::builder()
+            .backend(Backend::Wayland)
+            .transition_mode(TransitionMode::Center)
+            .sunset("19:00:00")
+            .sunrise("06:00:00")
+            .transition_duration(60)
+            .update_interval(UpdateInterval::Fixed(60))
+            .build()
+    }
+
+    #[test]
+    fn transitions_win_over_day_and_night() {
+        use Period::*;
+        assert_eq!(classify(&[Night, Night, Sunrise, Day]), Cell::Transition);
+        assert_eq!(classify(&[Night, Night, Night, Day]), Cell::Night);
+        assert_eq!(classify(&[Night, Day, Day, Day]), Cell::Day);
+        assert_eq!(classify(&[]), Cell::Skipped);
+    }
+
+    #[test]
+    fn days_show_the_manual_schedule() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let day = summarize_day(&config(), date).unwrap();
+        let row: String = day.cells.iter().map(|cell| cell.ascii()).collect();
+
+        // Centered hour-long transitions around 06:00 and 19:00
+        assert_eq!(row.len(), CELLS);
+        assert_eq!(&row[..11], "...........");
+        assert_eq!(&row[11..13], "++");
+        assert_eq!(&row[13..37], "#".repeat(24));
+        assert_eq!(&row[37..39], "++");
+        assert_eq!(&row[39..], ".........");
+
+        let time = |time: Option<DateTime<Local>>| time.unwrap().format("%H:%M").to_string();
+        assert_eq!(time(day.sunrise), "05:30");
+        assert_eq!(time(day.sunset), "18:30");
+        assert!(!day.clocks_change);
+    }
+
+    #[test]
+    fn ascii_rows_line_up_under_the_hours() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let day = summarize_day(&config(), date).unwrap();
+        let lines = render(&[day], false);
+
+        assert!(lines[0].trim_start().starts_with("00    03    06"));
+        // Date, space, then two cells an hour
+        assert_eq!(lines[0].find("12"), Some(11 + 24));
+        assert_eq!(lines[1].chars().nth(11 + 24), Some('#'));
+        assert!(lines[1].starts_with("Fri Jun 21 ..........."));
+        assert!(lines[1].ends_with("sunrise 05:30  sunset 18:30"));
+    }
+
+    #[test]
+    fn colored_cells_reset_at_the_end() {
+        let text = cells(&[Cell::Day, Cell::Day, Cell::Night], true);
+        assert_eq!(text, "\x1b[33m██\x1b[34m█\x1b[0m");
+    }
+}
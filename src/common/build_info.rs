@@ -0,0 +1,89 @@
+//! Build and protocol metadata, printed by `sunsetr --version --json` and included in
+//! `sunsetr doctor --json`.
+//!
+//! Issue templates and packaging scripts read these fields, so they are a stable interface:
+//! new fields may appear, but renaming or removing one bumps [`BUILD_INFO_VERSION`].
+
+use serde::Serialize;
+
+/// Bump when a field of [`BuildInfo`] is renamed, removed or changes meaning.
+pub const BUILD_INFO_VERSION: u32 = 1;
+
+/// What this binary is and which interfaces it speaks.
+#[derive(Debug, Serialize)]
+pub struct BuildInfo {
+    pub format_version: u32,
+    /// The full version, including the git description for development builds.
+    pub version: &'static str,
+    /// The version in `Cargo.toml`.
+    pub package_version: &'static str,
+    /// Abbreviated commit hash, `None` for builds outside a git checkout.
+    pub git_hash: Option<&'static str>,
+    /// `debug` or `release`.
+    pub profile: &'static str,
+    /// Architecture and operating system, e.g. `x86_64-linux`.
+    pub target: String,
+    /// Enabled cargo features.
+    pub features: Vec<&'static str>,
+    /// Backends that `backend` in the config can select.
+    pub backends: Vec<&'static str>,
+    /// Wayland protocols the backends bind.
+    pub wayland_protocols: Vec<&'static str>,
+    /// Compositor IPC followed for output and focus changes.
+    pub compositor_ipc: Vec<&'static str>,
+    /// Wire format version of IPC events, see `PROTOCOL_VERSION`.
+    pub ipc_protocol_version: u32,
+    /// Version of the schema printed by `sunsetr config schema`.
+    pub config_schema_version: u32,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        Self {
+            format_version: BUILD_INFO_VERSION,
+            version: env!("SUNSETR_VERSION"),
+            package_version: env!("CARGO_PKG_VERSION"),
+            git_hash: Some(env!("SUNSETR_GIT_HASH")).filter(|hash| !hash.is_empty()),
+            profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            },
+            target: format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+            features: env!("SUNSETR_FEATURES")
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .collect(),
+            backends: vec!["hyprland", "hyprsunset", "wayland"],
+            wayland_protocols: vec!["wlr-gamma-control-unstable-v1", "hyprland-ctm-control-v1"],
+            compositor_ipc: vec!["hyprland", "niri", "sway"],
+            ipc_protocol_version: crate::state::ipc::events::PROTOCOL_VERSION,
+            config_schema_version: crate::config::schema::SCHEMA_VERSION,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Backend;
+
+    #[test]
+    fn serializes_with_stable_field_names() {
+        let value = serde_json::to_value(BuildInfo::current()).unwrap();
+        assert_eq!(value["format_version"], BUILD_INFO_VERSION);
+        assert_eq!(value["package_version"], env!("CARGO_PKG_VERSION"));
+        assert!(value["git_hash"].is_null() || value["git_hash"].is_string());
+        assert!(value["features"].is_array());
+        assert_eq!(value["ipc_protocol_version"], 1);
+        assert_eq!(value["config_schema_version"], 1);
+    }
+
+    #[test]
+    fn backends_are_the_configurable_ones() {
+        for backend in BuildInfo::current().backends {
+            let parsed: Backend = serde_json::from_value(backend.into()).unwrap();
+            assert_ne!(parsed, Backend::Auto);
+        }
+    }
+}
@@ -1,14 +1,26 @@
 //! Configuration defaults and validation limits.
 
-use crate::config::{Backend, TransitionMode};
+use crate::config::{
+    Backend, HyprsunsetMode, OverrideExpiry, PowerSaving, ShutdownTarget, TransitionMode,
+};
 
 // Application Configuration Defaults
 
 pub const DEFAULT_BACKEND: Backend = Backend::Auto;
+pub const DEFAULT_VERIFY_GAMMA: bool = false;
+pub const DEFAULT_HYPRSUNSET_MODE: HyprsunsetMode = HyprsunsetMode::Managed;
+pub const DEFAULT_READONLY_CONFIG: bool = false;
+pub const DEFAULT_HEARTBEAT_INTERVAL_SEC: u64 = 60;
+pub const DEFAULT_VALUES_CHANGED_TEMP_STEP: u32 = 100;
+pub const DEFAULT_VALUES_CHANGED_GAMMA_STEP: f64 = 1.0;
+pub const DEFAULT_CHANNEL_GAMMA: f64 = 1.0;
+pub const DEFAULT_CHANNEL_TRIM: f64 = 1.0;
 
 pub const DEFAULT_SMOOTHING: bool = true;
+pub const DEFAULT_REDUCE_MOTION: bool = false;
 pub const DEFAULT_STARTUP_DURATION_SEC: f64 = 0.5;
 pub const DEFAULT_SHUTDOWN_DURATION_SEC: f64 = 0.5;
+pub const DEFAULT_SHUTDOWN_TARGET: ShutdownTarget = ShutdownTarget::Day;
 pub const DEFAULT_ADAPTIVE_INTERVAL_MS: u64 = 1;
 pub const DEFAULT_SUNSET: &str = "19:00:00";
 pub const DEFAULT_SUNRISE: &str = "06:00:00";
@@ -16,8 +28,14 @@ pub const DEFAULT_NIGHT_TEMP: u32 = 3300;
 pub const DEFAULT_DAY_TEMP: u32 = 6500;
 pub const DEFAULT_NIGHT_GAMMA: f64 = 90.0;
 pub const DEFAULT_DAY_GAMMA: f64 = 100.0;
+pub const DEFAULT_SATURATION: f64 = 100.0;
+pub const DEFAULT_BRIGHTNESS: f64 = 100.0;
 pub const DEFAULT_TRANSITION_DURATION_MIN: u64 = 45;
 pub const DEFAULT_UPDATE_INTERVAL_SEC: u64 = 60;
+pub const DEFAULT_POWER_SAVING: PowerSaving = PowerSaving::Auto;
+pub const DEFAULT_OVERRIDE_EXPIRY: OverrideExpiry = OverrideExpiry::Never;
+pub const DEFAULT_DARKMAN_EXPORT: bool = false;
+pub const DEFAULT_ADJUST_UPDATE_INTERVAL: bool = false;
 pub const DEFAULT_TRANSITION_MODE: TransitionMode = TransitionMode::Geo;
 pub const FALLBACK_DEFAULT_TRANSITION_MODE: TransitionMode = TransitionMode::FinishBy;
 
@@ -40,9 +58,58 @@ pub const MAXIMUM_GAMMA: f64 = 200.0;
 pub const MINIMUM_TRANSITION_DURATION_MIN: u64 = 5;
 pub const MAXIMUM_TRANSITION_DURATION_MIN: u64 = 120;
 
+// Stable day a manual schedule leaves below which it is likely a typo, and the range
+// min_day_duration may ask for
+pub const SHORT_DAY_WARNING_MIN: u64 = 240;
+pub const MINIMUM_DAY_DURATION_MIN: u64 = 60;
+pub const MAXIMUM_DAY_DURATION_MIN: u64 = 720;
+
 pub const MINIMUM_UPDATE_INTERVAL_SEC: u64 = 10;
 pub const MAXIMUM_UPDATE_INTERVAL_SEC: u64 = 300;
 
+// Fewest updates a transition should take with a fixed update_interval before it reads
+// as a few jumps rather than a fade
+pub const MINIMUM_TRANSITION_STEPS: u64 = 10;
+
+// While power saving, transitions update no more often than this and hotplug
+// is polled at this rate instead of every few milliseconds
+pub const POWER_SAVING_UPDATE_INTERVAL_SEC: u64 = MAXIMUM_UPDATE_INTERVAL_SEC;
+pub const POWER_SAVING_POLL_INTERVAL_MS: u64 = 1000;
+
+// How far the wind-down may take the night values
+pub const MAXIMUM_WINDDOWN_TEMP_DROP: u32 = 3000;
+pub const MAXIMUM_WINDDOWN_GAMMA_DROP: f64 = 50.0;
+
+// How much of the night's gamma drop media playback may hold back, and how long input has
+// to be idle before an idle inhibitor counts as media playing
+pub const MAXIMUM_MEDIA_GAMMA_HOLD: f64 = 100.0;
+pub const DEFAULT_MEDIA_DETECT_AFTER_SEC: u64 = 60;
+pub const MINIMUM_MEDIA_DETECT_AFTER_SEC: u64 = 10;
+pub const MAXIMUM_MEDIA_DETECT_AFTER_SEC: u64 = 3600;
+
+// Percentage. The Hyprland CTM rejects the negative entries that boosting saturation needs
+pub const MINIMUM_SATURATION: f64 = 0.0;
+pub const MAXIMUM_SATURATION: f64 = 100.0;
+
+// Percentage. Ramps and color matrices can dim but not brighten past full output
+pub const MINIMUM_BRIGHTNESS: f64 = 10.0;
+pub const MAXIMUM_BRIGHTNESS: f64 = 100.0;
+
+// Exponents, as accepted by xgamma
+pub const MINIMUM_CHANNEL_GAMMA: f64 = 0.1;
+pub const MAXIMUM_CHANNEL_GAMMA: f64 = 10.0;
+
+// Multipliers on a channel's temperature factor
+pub const MINIMUM_CHANNEL_TRIM: f64 = 0.5;
+pub const MAXIMUM_CHANNEL_TRIM: f64 = 1.5;
+
+// 0 disables the heartbeat
+pub const MAXIMUM_HEARTBEAT_INTERVAL_SEC: u64 = 3600;
+
+// 0 reports every change
+pub const MAXIMUM_VALUES_CHANGED_TEMP_STEP: u32 = 1000;
+pub const MAXIMUM_VALUES_CHANGED_GAMMA_STEP: f64 = 10.0;
+
 // Test Constants
 #[cfg(test)]
 pub mod test_constants {
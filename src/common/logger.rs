@@ -2,10 +2,11 @@
 //!
 //! Supports runtime enable/disable for quiet operation and optional file logging.
 
+use std::collections::VecDeque;
 use std::io::Write;
-use std::sync::OnceLock;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Sender, channel};
+use std::sync::{Mutex, OnceLock};
 
 // Use an AtomicBool instead of thread_local for thread safety
 static LOGGING_ENABLED: AtomicBool = AtomicBool::new(true);
@@ -16,6 +17,12 @@ static GEO_TIMEZONE: OnceLock<Option<chrono_tz::Tz>> = OnceLock::new();
 // Channel for routing output to file when --log is active
 static LOG_CHANNEL: OnceLock<Option<Sender<LogMessage>>> = OnceLock::new();
 
+// The most recent lines, without colors, for crash reports
+static RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// How many lines [`recent_lines`] keeps.
+pub const RECENT_LINE_COUNT: usize = 100;
+
 enum LogMessage {
     Formatted(String),
     Shutdown,
@@ -98,6 +105,11 @@ impl Log {
         })
     }
 
+    /// Whether output is going to a `--log` file.
+    pub fn is_file_logging() -> bool {
+        matches!(LOG_CHANNEL.get(), Some(Some(_)))
+    }
+
     /// Timestamp prefix for simulation mode, or an empty string outside it.
     ///
     /// Geo mode shows coordinate and local times as `[HH:MM:SSC] [HH:MM:SSL]`.
@@ -150,7 +162,7 @@ impl Drop for LoggerGuard {
     }
 }
 
-fn strip_ansi_codes(text: &str) -> String {
+pub fn strip_ansi_codes(text: &str) -> String {
     // Strip ANSI CSI sequences of the form ESC [ ... m.
     let mut result = String::with_capacity(text.len());
     let mut chars = text.chars().peekable();
@@ -175,8 +187,32 @@ fn strip_ansi_codes(text: &str) -> String {
     result
 }
 
+/// The last [`RECENT_LINE_COUNT`] lines logged, oldest first.
+///
+/// Returns nothing while another thread holds the buffer, as when a panic hook runs
+/// in the middle of logging.
+pub fn recent_lines() -> Vec<String> {
+    RECENT_LINES
+        .try_lock()
+        .map(|lines| lines.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn remember(text: &str) {
+    let Ok(mut lines) = RECENT_LINES.lock() else {
+        return;
+    };
+    for line in strip_ansi_codes(text).lines() {
+        if lines.len() == RECENT_LINE_COUNT {
+            lines.pop_front();
+        }
+        lines.push_back(line.to_string());
+    }
+}
+
 // Public so the exported macros can call it.
 pub fn write_output(text: &str) {
+    remember(text);
     if let Some(Some(tx)) = LOG_CHANNEL.get() {
         // Send to the file logger thread, stripping ANSI codes for clean file output
         let clean_text = strip_ansi_codes(text);
@@ -188,6 +224,13 @@ pub fn write_output(text: &str) {
     }
 }
 
+/// Write `text` to the `--log` file as is, dropping it when not logging to a file.
+pub fn write_file_only(text: &str) {
+    if let Some(Some(tx)) = LOG_CHANNEL.get() {
+        let _ = tx.send(LogMessage::Formatted(text.to_string()));
+    }
+}
+
 // Logging Macros
 
 #[macro_export]
@@ -2,6 +2,9 @@
 #[macro_use]
 pub mod logger;
 
+pub mod build_info;
 pub mod constants;
 pub mod error;
+pub mod profile;
+pub mod units;
 pub mod utils;
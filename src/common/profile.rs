@@ -0,0 +1,240 @@
+//! Opt-in self-profiling enabled with `--profile`.
+//!
+//! Records how long each main loop iteration spends working (sleep excluded) and how long
+//! each backend call takes, then prints a summary when sunsetr exits. Debug builds also
+//! install [`CountingAllocator`], so the report includes heap allocation counts per span.
+//! When profiling is disabled every entry point returns immediately.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SPANS: Mutex<BTreeMap<&'static str, SpanStats>> = Mutex::new(BTreeMap::new());
+static STARTED: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Aggregated timings for one named span.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpanStats {
+    pub calls: u64,
+    pub total: Duration,
+    pub max: Duration,
+    pub allocations: u64,
+}
+
+impl SpanStats {
+    fn record(&mut self, elapsed: Duration, allocations: u64) {
+        self.calls += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+        self.allocations += allocations;
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.calls as u32
+        }
+    }
+}
+
+/// Turn profiling on for the rest of the process.
+pub fn enable() {
+    *STARTED.lock().unwrap() = Some(Instant::now());
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Times a region of code and records it under `name` when dropped.
+pub struct Span {
+    name: &'static str,
+    start: Instant,
+    allocations_at_start: u64,
+}
+
+impl Span {
+    /// Start a span, or return `None` when profiling is disabled.
+    pub fn start(name: &'static str) -> Option<Self> {
+        is_enabled().then(|| Self {
+            name,
+            start: Instant::now(),
+            allocations_at_start: allocation_count().unwrap_or(0),
+        })
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let allocations = allocation_count()
+            .unwrap_or(0)
+            .saturating_sub(self.allocations_at_start);
+        SPANS
+            .lock()
+            .unwrap()
+            .entry(self.name)
+            .or_default()
+            .record(elapsed, allocations);
+    }
+}
+
+/// Snapshot of every recorded span, sorted by name.
+pub fn snapshot() -> Vec<(&'static str, SpanStats)> {
+    SPANS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, stats)| (*name, *stats))
+        .collect()
+}
+
+/// Print the profile report, if profiling is enabled.
+pub fn log_report() {
+    if !is_enabled() {
+        return;
+    }
+
+    let uptime = STARTED
+        .lock()
+        .unwrap()
+        .map(|started| started.elapsed())
+        .unwrap_or_default();
+
+    log_block_start!("Profile report ({:.1}s profiled):", uptime.as_secs_f64());
+    let spans = snapshot();
+    if spans.is_empty() {
+        log_indented!("No spans recorded");
+    }
+    for (name, stats) in spans {
+        log_indented!(
+            "{name}: {} calls, avg {}, max {}, total {}",
+            stats.calls,
+            format_duration(stats.average()),
+            format_duration(stats.max),
+            format_duration(stats.total)
+        );
+        if allocation_count().is_some() && stats.calls > 0 {
+            log_indented!(
+                "    {:.1} allocations per call",
+                stats.allocations as f64 / stats.calls as f64
+            );
+        }
+    }
+
+    match (allocation_count(), allocated_bytes()) {
+        (Some(count), Some(bytes)) => {
+            log_indented!(
+                "Heap: {count} allocations, {:.1} KiB requested in total",
+                bytes as f64 / 1024.0
+            );
+        }
+        _ => log_indented!("Heap: allocation counts are only tracked in debug builds"),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let micros = duration.as_micros();
+    if micros >= 1000 {
+        format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+    } else {
+        format!("{micros}µs")
+    }
+}
+
+#[cfg(debug_assertions)]
+mod allocator {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    pub(super) static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+    pub(super) static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+    /// System allocator wrapper that counts allocations for the profile report.
+    pub struct CountingAllocator;
+
+    // SAFETY: every call is forwarded unchanged to the system allocator.
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+            unsafe { System.alloc_zeroed(layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+pub use allocator::CountingAllocator;
+
+/// Total heap allocations so far, or `None` in release builds.
+pub fn allocation_count() -> Option<u64> {
+    #[cfg(debug_assertions)]
+    {
+        Some(allocator::ALLOCATIONS.load(Ordering::Relaxed))
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        None
+    }
+}
+
+/// Total bytes requested from the allocator so far, or `None` in release builds.
+pub fn allocated_bytes() -> Option<u64> {
+    #[cfg(debug_assertions)]
+    {
+        Some(allocator::ALLOCATED_BYTES.load(Ordering::Relaxed))
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn span_stats_track_calls_and_extremes() {
+        let mut stats = SpanStats::default();
+        stats.record(Duration::from_millis(2), 3);
+        stats.record(Duration::from_millis(6), 1);
+
+        assert_eq!(stats.calls, 2);
+        assert_eq!(stats.total, Duration::from_millis(8));
+        assert_eq!(stats.max, Duration::from_millis(6));
+        assert_eq!(stats.average(), Duration::from_millis(4));
+        assert_eq!(stats.allocations, 4);
+    }
+
+    #[test]
+    fn span_is_inert_when_disabled() {
+        assert!(Span::start("disabled").is_none());
+    }
+
+    #[test]
+    fn format_duration_switches_to_milliseconds() {
+        assert_eq!(format_duration(Duration::from_micros(250)), "250µs");
+        assert_eq!(format_duration(Duration::from_micros(1500)), "1.50ms");
+    }
+}
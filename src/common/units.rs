@@ -0,0 +1,200 @@
+//! Color temperature and gamma that are known to be in range.
+//!
+//! Values reach sunsetr from the command line, IPC clients and other programs, and each
+//! entry point used to check the ranges its own way. [`Kelvin`] and [`GammaPercent`] can
+//! only be built through a range check or a clamp, so whatever holds one can pass it on
+//! without checking again. Both deserialize through the same check, so an IPC payload out
+//! of range fails to parse instead of reaching Core.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::common::constants::{MAXIMUM_GAMMA, MAXIMUM_TEMP, MINIMUM_GAMMA, MINIMUM_TEMP};
+use crate::common::utils::{interpolate_f64, interpolate_inverse_u32};
+
+/// A value outside the range of [`Kelvin`] or [`GammaPercent`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeError {
+    Temperature(u32),
+    Gamma(f64),
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeError::Temperature(temp) => write!(
+                f,
+                "temperature {temp}K must be between {MINIMUM_TEMP}K and {MAXIMUM_TEMP}K"
+            ),
+            RangeError::Gamma(gamma) => write!(
+                f,
+                "gamma {gamma}% must be between {MINIMUM_GAMMA}% and {MAXIMUM_GAMMA}%"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+/// A color temperature between [`MINIMUM_TEMP`] and [`MAXIMUM_TEMP`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub struct Kelvin(u32);
+
+impl Kelvin {
+    pub fn new(temp: u32) -> Result<Self, RangeError> {
+        if (MINIMUM_TEMP..=MAXIMUM_TEMP).contains(&temp) {
+            Ok(Self(temp))
+        } else {
+            Err(RangeError::Temperature(temp))
+        }
+    }
+
+    /// The nearest temperature in range. Takes an `i64` so offsets can go below zero.
+    pub fn clamped(temp: i64) -> Self {
+        Self(temp.clamp(i64::from(MINIMUM_TEMP), i64::from(MAXIMUM_TEMP)) as u32)
+    }
+
+    /// The temperature `progress` of the way to `target`, evenly spaced in mired. Stays
+    /// between the two, so it needs no check.
+    pub fn interpolate(self, target: Self, progress: f32) -> Self {
+        Self(interpolate_inverse_u32(self.0, target.0, progress))
+    }
+
+    /// The temperature moved by `offset`, stopping at the ends of the range.
+    pub fn offset(self, offset: i32) -> Self {
+        Self::clamped(i64::from(self.0) + i64::from(offset))
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl TryFrom<u32> for Kelvin {
+    type Error = RangeError;
+
+    fn try_from(temp: u32) -> Result<Self, RangeError> {
+        Self::new(temp)
+    }
+}
+
+impl From<Kelvin> for u32 {
+    fn from(temp: Kelvin) -> u32 {
+        temp.0
+    }
+}
+
+impl fmt::Display for Kelvin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A gamma percentage between [`MINIMUM_GAMMA`] and [`MAXIMUM_GAMMA`], never NaN.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct GammaPercent(f64);
+
+impl GammaPercent {
+    pub fn new(gamma: f64) -> Result<Self, RangeError> {
+        if (MINIMUM_GAMMA..=MAXIMUM_GAMMA).contains(&gamma) {
+            Ok(Self(gamma))
+        } else {
+            Err(RangeError::Gamma(gamma))
+        }
+    }
+
+    /// The nearest gamma in range, with NaN taken as the lowest.
+    pub fn clamped(gamma: f64) -> Self {
+        if gamma.is_nan() {
+            return Self(MINIMUM_GAMMA);
+        }
+        Self(gamma.clamp(MINIMUM_GAMMA, MAXIMUM_GAMMA))
+    }
+
+    /// The gamma `progress` of the way to `target`. Held between the two, which rounding
+    /// could otherwise step past by a hair.
+    pub fn interpolate(self, target: Self, progress: f32) -> Self {
+        let gamma = interpolate_f64(self.0, target.0, progress);
+        Self(gamma.clamp(self.0.min(target.0), self.0.max(target.0)))
+    }
+
+    /// The gamma moved by `offset`, stopping at the ends of the range.
+    pub fn offset(self, offset: f64) -> Self {
+        Self::clamped(self.0 + offset)
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl TryFrom<f64> for GammaPercent {
+    type Error = RangeError;
+
+    fn try_from(gamma: f64) -> Result<Self, RangeError> {
+        Self::new(gamma)
+    }
+}
+
+impl From<GammaPercent> for f64 {
+    fn from(gamma: GammaPercent) -> f64 {
+        gamma.0
+    }
+}
+
+impl fmt::Display for GammaPercent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// `temp` and `gamma` as known-good values, for tests that spell them out.
+#[cfg(test)]
+pub fn values(temp: u32, gamma: f64) -> (Kelvin, GammaPercent) {
+    (
+        Kelvin::new(temp).unwrap(),
+        GammaPercent::new(gamma).unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_out_of_range_are_rejected_or_clamped() {
+        assert_eq!(Kelvin::new(3300).map(Kelvin::get), Ok(3300));
+        assert_eq!(Kelvin::new(500), Err(RangeError::Temperature(500)));
+        assert_eq!(Kelvin::clamped(-200).get(), MINIMUM_TEMP);
+        assert_eq!(Kelvin::clamped(25000).get(), MAXIMUM_TEMP);
+
+        assert_eq!(GammaPercent::new(90.0).map(GammaPercent::get), Ok(90.0));
+        assert!(GammaPercent::new(f64::NAN).is_err());
+        assert_eq!(GammaPercent::clamped(250.0).get(), MAXIMUM_GAMMA);
+        assert_eq!(GammaPercent::clamped(f64::NAN).get(), MINIMUM_GAMMA);
+
+        assert_eq!(
+            RangeError::Temperature(500).to_string(),
+            "temperature 500K must be between 1000K and 20000K"
+        );
+    }
+
+    #[test]
+    fn deserializing_checks_the_range() {
+        assert_eq!(serde_json::from_str::<Kelvin>("4000").unwrap().get(), 4000);
+        assert_eq!(
+            serde_json::to_string(&GammaPercent::new(92.5).unwrap()).unwrap(),
+            "92.5"
+        );
+
+        let error = serde_json::from_str::<Kelvin>("25000").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("must be between 1000K and 20000K")
+        );
+        assert!(serde_json::from_str::<GammaPercent>("5.0").is_err());
+    }
+}
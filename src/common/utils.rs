@@ -43,6 +43,45 @@ pub fn format_duration_seconds_ceil(duration: std::time::Duration) -> u64 {
     }
 }
 
+/// Parse a length of time such as `90m`, `3h` or `1h30m`, as taken by `--for`. The units
+/// are `s`, `m`, `h` and `d`, and a bare number counts minutes.
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let invalid = || anyhow::anyhow!("Invalid duration '{text}', use e.g. 90m, 3h or 1h30m");
+    let text = text.trim();
+    if let Ok(minutes) = text.parse::<u64>() {
+        return match minutes.checked_mul(60) {
+            Some(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+            _ => Err(invalid()),
+        };
+    }
+
+    let mut total: u64 = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let amount: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+        let unit_secs = match rest[digits..].chars().next() {
+            Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 3600,
+            Some('d') => 86_400,
+            _ => return Err(invalid()),
+        };
+        total = amount
+            .checked_mul(unit_secs)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(invalid)?;
+        rest = &rest[digits + 1..];
+    }
+
+    if total == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
 /// Round a chrono duration up to whole seconds, returning 0 for negative durations.
 pub fn format_chrono_duration_seconds_ceil(duration: chrono::Duration) -> u64 {
     if duration.num_seconds() <= 0 {
@@ -532,6 +571,21 @@ mod tests {
     use super::*;
     use std::cmp::Ordering;
 
+    #[test]
+    fn parse_duration_takes_units_and_bare_minutes() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90 * 60));
+        assert_eq!(parse_duration("3h").unwrap(), Duration::from_secs(3 * 3600));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(
+            parse_duration("2d").unwrap(),
+            Duration::from_secs(2 * 86_400)
+        );
+        for text in ["", "0", "0m", "h", "3x", "1.5h", "-2h", "3h30"] {
+            assert!(parse_duration(text).is_err(), "{text}");
+        }
+    }
+
     #[test]
     fn test_interpolate_inverse_u32_basic() {
         assert_eq!(interpolate_inverse_u32(1000, 2000, 0.0), 1000);
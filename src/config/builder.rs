@@ -87,6 +87,7 @@ transition_mode = "{transition_mode}"
 smoothing = {DEFAULT_SMOOTHING}
 startup_duration = {DEFAULT_STARTUP_DURATION_SEC}
 shutdown_duration = {DEFAULT_SHUTDOWN_DURATION_SEC}
+shutdown_target = "{DEFAULT_SHUTDOWN_TARGET}"
 adaptive_interval = {DEFAULT_ADAPTIVE_INTERVAL_MS}
 
 #[Time-based config]
@@ -0,0 +1,220 @@
+//! [`Config`] values for tests, built from defaults plus the few fields a test cares about.
+//!
+//! Spelling out every field of a `Config` literal means touching each test whenever a
+//! field is added. [`Config::builder`] starts from what a config file with only
+//! `transition_mode = "finish_by"` resolves to, and each setter replaces one field. Setters
+//! for optional fields take the value itself; the built `Config` has public fields for the
+//! rare test that needs one unset again.
+
+use std::collections::BTreeMap;
+
+use super::{
+    Backend, ColorMatrix, Config, HyprsunsetMode, MediaConfig, OutputConfig, OverrideExpiry,
+    PlaceholderCoordinates, PowerSaving, ShutdownTarget, TransitionMode, UpdateInterval,
+    WinddownConfig,
+};
+use crate::common::constants::*;
+use crate::state::presentation::Presentation;
+use crate::state::value_offset::ValueOffset;
+
+/// Setters replacing a field with the value given.
+macro_rules! setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: $ty) -> Self {
+                self.config.$field = $field;
+                self
+            }
+        )*
+    };
+}
+
+/// Setters replacing an optional field with `Some` of the value given.
+macro_rules! optional_setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: $ty) -> Self {
+                self.config.$field = Some($field);
+                self
+            }
+        )*
+    };
+}
+
+/// Builds a [`Config`] without validating it, so tests can also build invalid ones.
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl Config {
+    /// A builder starting from the defaults of a `finish_by` config, see [`ConfigBuilder`].
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self {
+            config: Config {
+                backend: DEFAULT_BACKEND,
+                transition_mode: TransitionMode::FinishBy,
+                verify_gamma: DEFAULT_VERIFY_GAMMA,
+                hyprsunset_mode: DEFAULT_HYPRSUNSET_MODE,
+                readonly_config: DEFAULT_READONLY_CONFIG,
+                smoothing: DEFAULT_SMOOTHING,
+                reduce_motion: DEFAULT_REDUCE_MOTION,
+                startup_duration: DEFAULT_STARTUP_DURATION_SEC,
+                shutdown_duration: DEFAULT_SHUTDOWN_DURATION_SEC,
+                shutdown_target: DEFAULT_SHUTDOWN_TARGET,
+                adaptive_interval: DEFAULT_ADAPTIVE_INTERVAL_MS,
+                night_temp: DEFAULT_NIGHT_TEMP,
+                day_temp: DEFAULT_DAY_TEMP,
+                night_gamma: DEFAULT_NIGHT_GAMMA,
+                day_gamma: DEFAULT_DAY_GAMMA,
+                night_saturation: DEFAULT_SATURATION,
+                night_brightness: DEFAULT_BRIGHTNESS,
+                day_brightness: DEFAULT_BRIGHTNESS,
+                update_interval: UpdateInterval::Adaptive,
+                adjust_update_interval: DEFAULT_ADJUST_UPDATE_INTERVAL,
+                power_saving: DEFAULT_POWER_SAVING,
+                override_expiry: DEFAULT_OVERRIDE_EXPIRY,
+                winddown: None,
+                media: None,
+                darkman_export: DEFAULT_DARKMAN_EXPORT,
+                static_temp: None,
+                static_gamma: None,
+                sunset: Some(DEFAULT_SUNSET.to_string()),
+                sunrise: Some(DEFAULT_SUNRISE.to_string()),
+                transition_duration: DEFAULT_TRANSITION_DURATION_MIN,
+                sunset_duration: None,
+                sunrise_duration: None,
+                night_latest_start: None,
+                min_day_duration: None,
+                latitude: None,
+                longitude: None,
+                placeholder_coordinates: None,
+                outputs: BTreeMap::new(),
+                include_outputs: Vec::new(),
+                exclude_outputs: Vec::new(),
+                presentation: None,
+                value_offset: None,
+                neutral_apps: Vec::new(),
+                activate_when_outputs: Vec::new(),
+                gamma_r: DEFAULT_CHANNEL_GAMMA,
+                gamma_g: DEFAULT_CHANNEL_GAMMA,
+                gamma_b: DEFAULT_CHANNEL_GAMMA,
+                red_trim: DEFAULT_CHANNEL_TRIM,
+                green_trim: DEFAULT_CHANNEL_TRIM,
+                blue_trim: DEFAULT_CHANNEL_TRIM,
+                matrix: None,
+                heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL_SEC,
+                values_changed_temp_step: DEFAULT_VALUES_CHANGED_TEMP_STEP,
+                values_changed_gamma_step: DEFAULT_VALUES_CHANGED_GAMMA_STEP,
+                ipc_allowed_gids: Vec::new(),
+            },
+        }
+    }
+}
+
+// A setter for every field, whether or not a test uses it yet
+#[allow(dead_code)]
+impl ConfigBuilder {
+    setters! {
+        backend: Backend,
+        transition_mode: TransitionMode,
+        verify_gamma: bool,
+        hyprsunset_mode: HyprsunsetMode,
+        readonly_config: bool,
+        smoothing: bool,
+        reduce_motion: bool,
+        startup_duration: f64,
+        shutdown_duration: f64,
+        shutdown_target: ShutdownTarget,
+        adaptive_interval: u64,
+        night_temp: u32,
+        day_temp: u32,
+        night_gamma: f64,
+        day_gamma: f64,
+        night_saturation: f64,
+        night_brightness: f64,
+        day_brightness: f64,
+        update_interval: UpdateInterval,
+        adjust_update_interval: bool,
+        power_saving: PowerSaving,
+        override_expiry: OverrideExpiry,
+        darkman_export: bool,
+        transition_duration: u64,
+        include_outputs: Vec<String>,
+        exclude_outputs: Vec<String>,
+        neutral_apps: Vec<String>,
+        activate_when_outputs: Vec<String>,
+        gamma_r: f64,
+        gamma_g: f64,
+        gamma_b: f64,
+        red_trim: f64,
+        green_trim: f64,
+        blue_trim: f64,
+        heartbeat_interval: u64,
+        values_changed_temp_step: u32,
+        values_changed_gamma_step: f64,
+        ipc_allowed_gids: Vec<u32>,
+    }
+
+    optional_setters! {
+        winddown: WinddownConfig,
+        media: MediaConfig,
+        static_temp: u32,
+        static_gamma: f64,
+        sunset_duration: u64,
+        sunrise_duration: u64,
+        latitude: f64,
+        longitude: f64,
+        placeholder_coordinates: PlaceholderCoordinates,
+        presentation: Presentation,
+        value_offset: ValueOffset,
+        matrix: ColorMatrix,
+    }
+
+    pub fn sunset(mut self, sunset: &str) -> Self {
+        self.config.sunset = Some(sunset.to_string());
+        self
+    }
+
+    pub fn sunrise(mut self, sunrise: &str) -> Self {
+        self.config.sunrise = Some(sunrise.to_string());
+        self
+    }
+
+    pub fn night_latest_start(mut self, latest: &str) -> Self {
+        self.config.night_latest_start = Some(latest.to_string());
+        self
+    }
+
+    pub fn min_day_duration(mut self, minutes: u64) -> Self {
+        self.config.min_day_duration = Some(minutes);
+        self
+    }
+
+    /// Static mode holding `temp` and `gamma`, without the manual times static mode
+    /// doesn't resolve.
+    pub fn static_values(mut self, temp: u32, gamma: f64) -> Self {
+        self.config.transition_mode = TransitionMode::Static;
+        self.config.static_temp = Some(temp);
+        self.config.static_gamma = Some(gamma);
+        self.config.sunset = None;
+        self.config.sunrise = None;
+        self
+    }
+
+    /// Settings for one output, on top of any added before.
+    pub fn output(mut self, name: &str, output: OutputConfig) -> Self {
+        self.config.outputs.insert(name.to_string(), output);
+        self
+    }
+
+    pub fn build(self) -> Config {
+        self.config
+    }
+}
@@ -5,14 +5,19 @@ use chrono::NaiveTime;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use super::validation::validate_config;
-use super::{Config, GeoConfig, RawConfig, TransitionMode};
+use super::{Config, GeoConfig, PlaceholderCoordinates, RawConfig, TransitionMode};
 use crate::common::constants::*;
 use crate::common::utils::private_path;
 
 static CONFIG_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
 
+/// Set while the resolved coordinates are placeholders, so the fallback is announced once
+/// rather than on every reload.
+static PLACEHOLDER_WARNED: AtomicBool = AtomicBool::new(false);
+
 /// Set once and return an error if already set.
 pub fn set_config_dir(dir: Option<String>) -> Result<()> {
     #[cfg(debug_assertions)]
@@ -36,23 +41,87 @@ pub fn get_config_base_dir() -> Result<PathBuf> {
         .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))
 }
 
+/// Geo mode is selected but neither the config nor geo.toml has coordinates yet.
+///
+/// Startup waits for them instead of failing, since a secrets manager may create geo.toml
+/// only after sunsetr starts.
+#[derive(Debug)]
+pub struct MissingCoordinates;
+
+impl std::fmt::Display for MissingCoordinates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            "Geo mode requires coordinates but none are configured\n\
+             Please run 'sunsetr geo' to select your location\n\
+             Or add latitude and longitude to your configuration",
+        )
+    }
+}
+
+impl std::error::Error for MissingCoordinates {}
+
 fn validate_geo_mode_coordinates(config: &RawConfig) -> Result<()> {
     if config.transition_mode == TransitionMode::Geo
         && (config.latitude.is_none() || config.longitude.is_none())
     {
-        anyhow::bail!(
-            "Geo mode requires coordinates but none are configured\n\
-             Please run 'sunsetr geo' to select your location\n\
-             Or add latitude and longitude to your configuration"
-        );
+        return Err(MissingCoordinates.into());
     }
     Ok(())
 }
 
+/// Why `latitude` and `longitude` look like a templated default rather than a location.
+pub fn placeholder_reason(latitude: f64, longitude: f64) -> Option<&'static str> {
+    if latitude == 0.0 && longitude == 0.0 {
+        Some("0,0 is open ocean in the Gulf of Guinea")
+    } else if latitude == longitude {
+        Some("latitude equals longitude")
+    } else {
+        None
+    }
+}
+
+/// Move geo mode with placeholder coordinates over to manual times, warning the first time.
+fn fall_back_from_placeholder_coordinates(
+    config: &mut RawConfig,
+) -> Option<PlaceholderCoordinates> {
+    if config.transition_mode != TransitionMode::Geo {
+        return None;
+    }
+    let (latitude, longitude) = (config.latitude?, config.longitude?);
+    let Some(reason) = placeholder_reason(latitude, longitude) else {
+        PLACEHOLDER_WARNED.store(false, Ordering::Relaxed);
+        return None;
+    };
+
+    config.transition_mode = FALLBACK_DEFAULT_TRANSITION_MODE;
+    if !PLACEHOLDER_WARNED.swap(true, Ordering::Relaxed) {
+        log_pipe!();
+        log_warning!("Ignoring placeholder coordinates {latitude}, {longitude}: {reason}");
+        log_indented!("Using manual sunset and sunrise times until a real location is set");
+        log_indented!("Run 'sunsetr geo' to select your location");
+    }
+    Some(PlaceholderCoordinates {
+        latitude,
+        longitude,
+        reason,
+    })
+}
+
 /// Load the active configuration, creating a default file if none exists and preferring an active
-/// preset's config when one is set.
+/// preset's config when one is set. Presets stacked on top are layered over the result, and
+/// presentation mode and the value offset are attached so they carry through every reload.
 pub(super) fn load() -> Result<Config> {
+    let mut config = load_files()?;
+    config.presentation = crate::state::presentation::load()?;
+    config.value_offset = crate::state::value_offset::load()?;
+    Ok(config)
+}
+
+fn load_files() -> Result<Config> {
     let config_path = get_config_path()?;
+    let config_dir = config_path
+        .parent()
+        .context("Failed to get config directory")?;
 
     #[cfg(debug_assertions)]
     eprintln!(
@@ -63,12 +132,7 @@ pub(super) fn load() -> Result<Config> {
     if let Some(preset_name) = crate::state::preset::get_active_preset()? {
         #[cfg(debug_assertions)]
         eprintln!("DEBUG: Config::load() found active preset: {}", preset_name);
-        let preset_config = config_path
-            .parent()
-            .context("Failed to get config directory")?
-            .join("presets")
-            .join(&preset_name)
-            .join("sunsetr.toml");
+        let preset_config = preset_config_path(config_dir, &preset_name);
 
         if preset_config.exists() {
             #[cfg(debug_assertions)]
@@ -76,7 +140,10 @@ pub(super) fn load() -> Result<Config> {
                 "DEBUG: Config::load() loading preset config from: {}",
                 private_path(&preset_config)
             );
-            return load_from_path(&preset_config);
+            let layers = layer_paths(config_dir)?;
+            let mut config = load_layered(&preset_config, &layers)?;
+            config.reduce_motion |= main_config_reduces_motion(&config_path)?;
+            return Ok(config);
         } else {
             log_warning!(
                 "Active preset '{}' not found, falling back to default config",
@@ -91,24 +158,126 @@ pub(super) fn load() -> Result<Config> {
             .context("Failed to create default config during load")?;
     }
 
-    load_from_path(&config_path).with_context(|| private_path(&config_path))
+    let layers = layer_paths(config_dir)?;
+    load_layered(&config_path, &layers).with_context(|| private_path(&config_path))
+}
+
+/// Whether the main `sunsetr.toml` at `config_path` sets `reduce_motion = true`, which a
+/// preset cannot turn off again.
+fn main_config_reduces_motion(config_path: &Path) -> Result<bool> {
+    if !config_path.exists() {
+        return Ok(false);
+    }
+    Ok(read_table(config_path)?
+        .get("reduce_motion")
+        .and_then(toml::Value::as_bool)
+        .unwrap_or(DEFAULT_REDUCE_MOTION))
+}
+
+/// Path to a preset's `sunsetr.toml` under `config_dir`.
+fn preset_config_path(config_dir: &Path, preset_name: &str) -> PathBuf {
+    config_dir
+        .join("presets")
+        .join(preset_name)
+        .join("sunsetr.toml")
+}
+
+/// Files merged over the loaded config: the stacked presets, then any runtime overrides.
+fn layer_paths(config_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut layers: Vec<PathBuf> = crate::state::preset::get_preset_stack()?
+        .iter()
+        .map(|name| preset_config_path(config_dir, name))
+        .collect();
+    let overrides = crate::state::overrides::overrides_path()?;
+    if overrides.exists() {
+        layers.push(overrides);
+    }
+    Ok(layers)
 }
 
 /// Load configuration from `path`, without creating a default when it is missing (unlike [`load`]).
 pub(super) fn load_from_path(path: &Path) -> Result<Config> {
+    load_layered(path, &[])
+}
+
+/// Resolve a config document on its own: no geo.toml, presets or runtime overrides are
+/// merged in, so it must carry everything it needs. Presentation mode and the value offset
+/// still apply.
+pub(super) fn from_toml(content: &str) -> Result<Config> {
+    let mut raw: RawConfig = toml::from_str(content).context("Failed to parse config")?;
+    raw.migrate_legacy_fields();
+    let mut config = raw.resolve()?;
+    config.presentation = crate::state::presentation::load()?;
+    config.value_offset = crate::state::value_offset::load()?;
+    Ok(config)
+}
+
+/// Load configuration from `path` with each of `layers` applied on top in order.
+///
+/// A layer only overrides the top-level settings it sets itself. Coordinates from
+/// `geo.toml` are taken from the base file's directory.
+pub(super) fn load_layered(path: &Path, layers: &[PathBuf]) -> Result<Config> {
+    let mut raw: RawConfig = if layers.is_empty() {
+        // Parsed straight from the text so errors keep their line numbers
+        let content = fs::read_to_string(require_file(path)?)
+            .with_context(|| format!("Failed to read config from {}", private_path(path)))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config from {}", private_path(path)))?
+    } else {
+        let mut table = read_table(path)?;
+        for layer in layers {
+            table.extend(read_table(layer)?);
+        }
+        toml::Value::Table(table).try_into().with_context(|| {
+            format!(
+                "Failed to parse config from {} with stacked presets or runtime overrides",
+                private_path(path)
+            )
+        })?
+    };
+
+    raw.migrate_legacy_fields();
+    load_geo_override_from_path(&mut raw, path)?;
+    raw.resolve()
+}
+
+fn require_file(path: &Path) -> Result<&Path> {
     if !path.exists() {
         anyhow::bail!("Configuration file not found at {}", private_path(path));
     }
+    Ok(path)
+}
 
-    let content = fs::read_to_string(path)
+fn read_table(path: &Path) -> Result<toml::Table> {
+    let content = fs::read_to_string(require_file(path)?)
         .with_context(|| format!("Failed to read config from {}", private_path(path)))?;
 
-    let mut raw: RawConfig = toml::from_str(&content)
-        .with_context(|| format!("Failed to parse config from {}", private_path(path)))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config from {}", private_path(path)))
+}
 
-    raw.migrate_legacy_fields();
-    load_geo_override_from_path(&mut raw, path)?;
-    raw.resolve()
+/// Fail when the main `sunsetr.toml` sets `readonly_config = true`, before a command writes
+/// anything under the config directory.
+///
+/// Only the key is read, so a config that fails validation can still be fixed with `set`
+/// unless it is read-only. Presets cannot lift the flag.
+pub fn ensure_config_writable() -> Result<()> {
+    let config_path = get_config_path()?;
+    if !config_path.exists() {
+        return Ok(());
+    }
+    let readonly = read_table(&config_path)?
+        .get("readonly_config")
+        .and_then(toml::Value::as_bool)
+        .unwrap_or(DEFAULT_READONLY_CONFIG);
+    if readonly {
+        anyhow::bail!(
+            "The configuration is read-only (readonly_config = true in {})\n   \
+             Change it where it is managed, e.g. your home-manager module or dotfiles",
+            private_path(&config_path)
+        );
+    }
+    Ok(())
 }
 
 /// Path to the configuration file, under the custom directory when set or the default location.
@@ -132,11 +301,12 @@ pub(super) fn get_config_path() -> Result<PathBuf> {
 impl RawConfig {
     /// Validate and apply defaults exactly once, producing the runtime [`Config`].
     ///
-    /// Runs raw validation, the latitude cap and time-format checks, the
-    /// mode-conditional sunset/sunrise defaults, and the geo coordinate check,
-    /// in that order.
+    /// Runs raw validation, the placeholder coordinate fallback, the latitude cap and
+    /// time-format checks, the mode-conditional sunset/sunrise defaults, and the geo
+    /// coordinate check, in that order.
     pub(crate) fn resolve(mut self) -> Result<Config> {
         validate_config(&self)?;
+        let placeholder_coordinates = fall_back_from_placeholder_coordinates(&mut self);
         apply_modifications(&mut self)?;
 
         if self.transition_mode != TransitionMode::Static {
@@ -151,32 +321,78 @@ impl RawConfig {
         Ok(Config {
             backend: self.backend.unwrap_or(DEFAULT_BACKEND),
             transition_mode: self.transition_mode,
+            verify_gamma: self.verify_gamma.unwrap_or(DEFAULT_VERIFY_GAMMA),
+            hyprsunset_mode: self.hyprsunset_mode.unwrap_or(DEFAULT_HYPRSUNSET_MODE),
+            readonly_config: self.readonly_config.unwrap_or(DEFAULT_READONLY_CONFIG),
             smoothing: self.smoothing.unwrap_or(DEFAULT_SMOOTHING),
+            reduce_motion: self.reduce_motion.unwrap_or(DEFAULT_REDUCE_MOTION),
             startup_duration: self
                 .startup_duration
                 .unwrap_or(DEFAULT_STARTUP_DURATION_SEC),
             shutdown_duration: self
                 .shutdown_duration
                 .unwrap_or(DEFAULT_SHUTDOWN_DURATION_SEC),
+            shutdown_target: self.shutdown_target.unwrap_or(DEFAULT_SHUTDOWN_TARGET),
             adaptive_interval: self
                 .adaptive_interval
                 .unwrap_or(DEFAULT_ADAPTIVE_INTERVAL_MS),
             night_temp: self.night_temp.unwrap_or(DEFAULT_NIGHT_TEMP),
             day_temp: self.day_temp.unwrap_or(DEFAULT_DAY_TEMP),
             night_gamma: self.night_gamma.unwrap_or(DEFAULT_NIGHT_GAMMA),
+            night_saturation: self.night_saturation.unwrap_or(DEFAULT_SATURATION),
+            night_brightness: self.night_brightness.unwrap_or(DEFAULT_BRIGHTNESS),
+            day_brightness: self.day_brightness.unwrap_or(DEFAULT_BRIGHTNESS),
             day_gamma: self.day_gamma.unwrap_or(DEFAULT_DAY_GAMMA),
             update_interval: self
                 .update_interval
                 .unwrap_or(crate::config::UpdateInterval::Adaptive),
+            adjust_update_interval: self
+                .adjust_update_interval
+                .unwrap_or(DEFAULT_ADJUST_UPDATE_INTERVAL),
+            power_saving: self.power_saving.unwrap_or(DEFAULT_POWER_SAVING),
+            override_expiry: self.override_expiry.unwrap_or(DEFAULT_OVERRIDE_EXPIRY),
+            winddown: self.winddown,
+            media: self.media,
+            darkman_export: self.darkman_export.unwrap_or(DEFAULT_DARKMAN_EXPORT),
             transition_duration: self
                 .transition_duration
                 .unwrap_or(DEFAULT_TRANSITION_DURATION_MIN),
+            sunset_duration: self.sunset_duration,
+            sunrise_duration: self.sunrise_duration,
+            night_latest_start: self.night_latest_start,
+            min_day_duration: self.min_day_duration,
             static_temp: self.static_temp,
             static_gamma: self.static_gamma,
             sunset: self.sunset,
             sunrise: self.sunrise,
             latitude: self.latitude,
             longitude: self.longitude,
+            placeholder_coordinates,
+            outputs: self.outputs.unwrap_or_default(),
+            include_outputs: self.include_outputs.unwrap_or_default(),
+            exclude_outputs: self.exclude_outputs.unwrap_or_default(),
+            // Attached by `load`, it is not part of the config files
+            presentation: None,
+            value_offset: None,
+            neutral_apps: self.neutral_apps.unwrap_or_default(),
+            activate_when_outputs: self.activate_when_outputs.unwrap_or_default(),
+            gamma_r: self.gamma_r.unwrap_or(DEFAULT_CHANNEL_GAMMA),
+            gamma_g: self.gamma_g.unwrap_or(DEFAULT_CHANNEL_GAMMA),
+            gamma_b: self.gamma_b.unwrap_or(DEFAULT_CHANNEL_GAMMA),
+            red_trim: self.red_trim.unwrap_or(DEFAULT_CHANNEL_TRIM),
+            green_trim: self.green_trim.unwrap_or(DEFAULT_CHANNEL_TRIM),
+            blue_trim: self.blue_trim.unwrap_or(DEFAULT_CHANNEL_TRIM),
+            matrix: self.matrix,
+            heartbeat_interval: self
+                .heartbeat_interval
+                .unwrap_or(DEFAULT_HEARTBEAT_INTERVAL_SEC),
+            values_changed_temp_step: self
+                .values_changed_temp_step
+                .unwrap_or(DEFAULT_VALUES_CHANGED_TEMP_STEP),
+            values_changed_gamma_step: self
+                .values_changed_gamma_step
+                .unwrap_or(DEFAULT_VALUES_CHANGED_GAMMA_STEP),
+            ipc_allowed_gids: self.ipc_allowed_gids.unwrap_or_default(),
         })
     }
 }
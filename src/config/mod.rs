@@ -5,16 +5,22 @@
 //! `XDG_CONFIG_HOME/sunsetr/sunsetr.toml`. A default is created there if none exists.
 
 pub mod builder;
+#[cfg(test)]
+pub mod fixture;
 pub mod loading;
+pub mod schema;
 pub mod validation;
 pub mod watcher;
 
 use anyhow::Result;
-use serde::Deserialize;
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::path::{Path, PathBuf};
 
 use crate::common::constants::*;
+use crate::common::units::{GammaPercent, Kelvin};
 
 /// Update interval strategy for sunset/sunrise transitions.
 ///
@@ -92,13 +98,15 @@ impl<'de> Deserialize<'de> for UpdateInterval {
     }
 }
 
-pub use loading::{get_custom_config_dir, set_config_dir};
+pub use loading::{ensure_config_writable, get_custom_config_dir, set_config_dir};
 pub use watcher::start_config_watcher;
 
 /// Which configuration fields `log_config` shows, based on `transition_mode`.
 #[derive(Debug, Clone, PartialEq)]
 enum DisplayMode {
     Static,
+    External,
+    Darkman,
     TimeBasedGeo,
     TimeBasedManual { mode: TransitionMode },
 }
@@ -112,13 +120,19 @@ pub(crate) struct GeoConfig {
 }
 
 /// Backend used to control display color temperature.
-#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Backend {
     Auto,
     Hyprland,
     Hyprsunset,
     Wayland,
+    X11,
+    Drm,
+    Kde,
+    Gnome,
+    /// Apply nothing, running everything else as usual.
+    None,
 }
 
 impl fmt::Display for Backend {
@@ -128,6 +142,11 @@ impl fmt::Display for Backend {
             Backend::Hyprland => "hyprland",
             Backend::Hyprsunset => "hyprsunset",
             Backend::Wayland => "wayland",
+            Backend::X11 => "x11",
+            Backend::Drm => "drm",
+            Backend::Kde => "kde",
+            Backend::Gnome => "gnome",
+            Backend::None => "none",
         })
     }
 }
@@ -141,14 +160,434 @@ impl std::str::FromStr for Backend {
             "hyprland" => Backend::Hyprland,
             "hyprsunset" => Backend::Hyprsunset,
             "wayland" => Backend::Wayland,
+            "x11" => Backend::X11,
+            "drm" => Backend::Drm,
+            "kde" => Backend::Kde,
+            "gnome" => Backend::Gnome,
+            "none" => Backend::None,
             _ => anyhow::bail!(
-                "'{s}' is not a valid backend\nUse: auto, hyprland, hyprsunset, or wayland"
+                "'{s}' is not a valid backend\nUse: auto, hyprland, hyprsunset, wayland, x11, drm, kde, gnome, or none"
             ),
         })
     }
 }
 
-/// How transitions are placed around sunset and sunrise, or a fixed static color.
+/// Where the smooth shutdown animation ends before sunsetr exits.
+///
+/// The compositor restores its own gamma once sunsetr disconnects, so this only
+/// shapes the fade-out: `day` eases to the configured day values, `reset` eases to
+/// neutral 6500K at 100% gamma, and `current` skips the animation entirely.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShutdownTarget {
+    Day,
+    Reset,
+    Current,
+}
+
+impl fmt::Display for ShutdownTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ShutdownTarget::Day => "day",
+            ShutdownTarget::Reset => "reset",
+            ShutdownTarget::Current => "current",
+        })
+    }
+}
+
+impl std::str::FromStr for ShutdownTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "day" => ShutdownTarget::Day,
+            "reset" => ShutdownTarget::Reset,
+            "current" => ShutdownTarget::Current,
+            _ => anyhow::bail!("'{s}' is not a valid shutdown target\nUse: day, reset, or current"),
+        })
+    }
+}
+
+/// How the hyprsunset backend gets its hyprsunset.
+///
+/// `managed` starts hyprsunset as a child and stops it on exit, refusing to run next to one
+/// started elsewhere. `attach` talks to one that is already running, such as a systemd user
+/// service, and leaves it running on exit.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HyprsunsetMode {
+    Managed,
+    Attach,
+}
+
+impl fmt::Display for HyprsunsetMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HyprsunsetMode::Managed => "managed",
+            HyprsunsetMode::Attach => "attach",
+        })
+    }
+}
+
+impl std::str::FromStr for HyprsunsetMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "managed" => HyprsunsetMode::Managed,
+            "attach" => HyprsunsetMode::Attach,
+            _ => anyhow::bail!("'{s}' is not a valid hyprsunset mode\nUse: managed or attach"),
+        })
+    }
+}
+
+/// A color matrix for `matrix`: nine row-major entries, or the name of a preset.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum ColorMatrix {
+    Preset(MatrixPreset),
+    Custom([f64; 9]),
+}
+
+impl ColorMatrix {
+    /// The row-major entries.
+    pub fn values(&self) -> [f64; 9] {
+        match self {
+            ColorMatrix::Preset(preset) => preset.values(),
+            ColorMatrix::Custom(values) => *values,
+        }
+    }
+}
+
+impl fmt::Display for ColorMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorMatrix::Preset(preset) => preset.fmt(f),
+            ColorMatrix::Custom(values) => write!(f, "{values:?}"),
+        }
+    }
+}
+
+/// Named matrices for `matrix`. Their entries are all non-negative, since Hyprland's CTM
+/// protocol rejects negative ones, which rules out boosting saturation and makes the color
+/// vision matrices approximations.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatrixPreset {
+    /// Every channel shows the Rec. 709 luminance.
+    Grayscale,
+    /// Halfway between the colors and grayscale.
+    Muted,
+    /// The classic sepia tone, scaled down so white doesn't clip.
+    Sepia,
+    /// Only the red channel lit, with the luminance, to keep dark adaptation.
+    Red,
+    /// Daltonization for missing red cones: the red-green detail they can't see is moved
+    /// into contrasts they can.
+    Protanopia,
+    /// Daltonization for missing green cones.
+    Deuteranopia,
+    /// Daltonization for missing blue cones, moving blue-yellow detail into red and green.
+    Tritanopia,
+    /// How the screen looks with protanopia, for checking designs.
+    SimulateProtanopia,
+    /// How the screen looks with deuteranopia.
+    SimulateDeuteranopia,
+    /// How the screen looks with tritanopia.
+    SimulateTritanopia,
+}
+
+// Dichromacy simulation at full severity from Machado, Oliveira and Fernandes (2009)
+const PROTANOPIA_SIMULATION: [f64; 9] = [
+    0.152286, 1.052583, -0.204868, 0.114503, 0.786281, 0.099216, -0.003882, -0.048116, 1.051998,
+];
+const DEUTERANOPIA_SIMULATION: [f64; 9] = [
+    0.367322, 0.860646, -0.227968, 0.280085, 0.672501, 0.047413, -0.011820, 0.042940, 0.968881,
+];
+const TRITANOPIA_SIMULATION: [f64; 9] = [
+    1.255528, -0.076749, -0.178779, -0.078411, 0.930809, 0.147602, 0.004733, 0.691367, 0.303900,
+];
+
+// Where daltonization moves the error: from red onto green and blue for the red-green
+// deficiencies, and from blue onto red and green for tritanopia
+const RED_GREEN_SHIFT: [f64; 9] = [0.0, 0.0, 0.0, 0.7, 1.0, 0.0, 0.7, 0.0, 1.0];
+const BLUE_YELLOW_SHIFT: [f64; 9] = [1.0, 0.0, 0.7, 0.0, 1.0, 0.7, 0.0, 0.0, 0.0];
+
+/// The daltonization matrix `I + shift * (I - simulation)`, which adds the difference
+/// between the colors and their simulation back onto the channels in `shift`.
+fn daltonize(simulation: [f64; 9], shift: [f64; 9]) -> [f64; 9] {
+    let identity = |row: usize, col: usize| if row == col { 1.0 } else { 0.0 };
+    std::array::from_fn(|i| {
+        let (row, col) = (i / 3, i % 3);
+        identity(row, col)
+            + (0..3)
+                .map(|k| shift[row * 3 + k] * (identity(k, col) - simulation[k * 3 + col]))
+                .sum::<f64>()
+    })
+}
+
+/// `matrix` with its negative entries dropped, and each row that then sums above one scaled
+/// back down so white doesn't clip.
+fn non_negative(matrix: [f64; 9]) -> [f64; 9] {
+    let clamped = matrix.map(|value| value.max(0.0));
+    std::array::from_fn(|i| {
+        let row = i / 3 * 3;
+        let sum: f64 = clamped[row..row + 3].iter().sum();
+        clamped[i] / sum.max(1.0)
+    })
+}
+
+/// Values a loaded config holds, which validation has already kept in range. Clamping
+/// only makes the conversion total for a config built by hand.
+fn in_range(temp: u32, gamma: f64) -> (Kelvin, GammaPercent) {
+    (
+        Kelvin::clamped(i64::from(temp)),
+        GammaPercent::clamped(gamma),
+    )
+}
+
+impl MatrixPreset {
+    pub fn values(self) -> [f64; 9] {
+        const LUMA: [f64; 3] = [0.2126, 0.7152, 0.0722];
+        // The largest row sum of the sepia matrix
+        const SEPIA_SCALE: f64 = 1.351;
+        match self {
+            MatrixPreset::Grayscale => std::array::from_fn(|i| LUMA[i % 3]),
+            MatrixPreset::Muted => {
+                std::array::from_fn(|i| 0.5 * LUMA[i % 3] + if i / 3 == i % 3 { 0.5 } else { 0.0 })
+            }
+            MatrixPreset::Sepia => [
+                0.393, 0.769, 0.189, 0.349, 0.686, 0.168, 0.272, 0.534, 0.131,
+            ]
+            .map(|value| value / SEPIA_SCALE),
+            MatrixPreset::Red => std::array::from_fn(|i| if i < 3 { LUMA[i] } else { 0.0 }),
+            MatrixPreset::Protanopia => {
+                non_negative(daltonize(PROTANOPIA_SIMULATION, RED_GREEN_SHIFT))
+            }
+            MatrixPreset::Deuteranopia => {
+                non_negative(daltonize(DEUTERANOPIA_SIMULATION, RED_GREEN_SHIFT))
+            }
+            MatrixPreset::Tritanopia => {
+                non_negative(daltonize(TRITANOPIA_SIMULATION, BLUE_YELLOW_SHIFT))
+            }
+            MatrixPreset::SimulateProtanopia => non_negative(PROTANOPIA_SIMULATION),
+            MatrixPreset::SimulateDeuteranopia => non_negative(DEUTERANOPIA_SIMULATION),
+            MatrixPreset::SimulateTritanopia => non_negative(TRITANOPIA_SIMULATION),
+        }
+    }
+}
+
+impl fmt::Display for MatrixPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MatrixPreset::Grayscale => "grayscale",
+            MatrixPreset::Muted => "muted",
+            MatrixPreset::Sepia => "sepia",
+            MatrixPreset::Red => "red",
+            MatrixPreset::Protanopia => "protanopia",
+            MatrixPreset::Deuteranopia => "deuteranopia",
+            MatrixPreset::Tritanopia => "tritanopia",
+            MatrixPreset::SimulateProtanopia => "simulate-protanopia",
+            MatrixPreset::SimulateDeuteranopia => "simulate-deuteranopia",
+            MatrixPreset::SimulateTritanopia => "simulate-tritanopia",
+        })
+    }
+}
+
+/// Per-output adjustments from an `[outputs.<name>]` table, keyed by connector name
+/// (e.g. `DP-1`). Honored by the Hyprland backend, which sets a CTM per output. `hdr` and
+/// the trims are honored by the Wayland backend too.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Kelvin added to the scheduled temperature on this output.
+    pub temp_offset: i32,
+    /// Percentage points added to the scheduled gamma on this output.
+    pub gamma_offset: f64,
+    /// Leave this output at the identity matrix.
+    pub exclude: bool,
+    /// Row-major 3x3 matrix or preset applied before the temperature tint, e.g. for color
+    /// vision correction, replacing the top-level `matrix`. Entries must be non-negative.
+    pub matrix: Option<ColorMatrix>,
+    /// What this output gets while the compositor drives it in HDR.
+    pub hdr: HdrBehavior,
+    /// Replace the top-level `red_trim`, `green_trim` and `blue_trim` on this output.
+    pub red_trim: Option<f64>,
+    pub green_trim: Option<f64>,
+    pub blue_trim: Option<f64>,
+}
+
+impl OutputConfig {
+    /// The scheduled values with this output's offsets applied, clamped to the valid ranges.
+    pub fn adjust(&self, temp: u32, gamma: f64) -> (u32, f64) {
+        let temp = Kelvin::clamped(i64::from(temp) + i64::from(self.temp_offset));
+        let gamma = GammaPercent::clamped(gamma + self.gamma_offset);
+        (temp.get(), gamma.get())
+    }
+
+    /// This output's settings, taking the matrix and trims it leaves unset from `defaults`,
+    /// as from [`Config::output_defaults`].
+    pub fn with_defaults(&self, defaults: &OutputConfig) -> OutputConfig {
+        OutputConfig {
+            matrix: self.matrix.or(defaults.matrix),
+            red_trim: self.red_trim.or(defaults.red_trim),
+            green_trim: self.green_trim.or(defaults.green_trim),
+            blue_trim: self.blue_trim.or(defaults.blue_trim),
+            ..self.clone()
+        }
+    }
+
+    /// The red, green and blue trims, 1.0 when unset.
+    pub fn trim(&self) -> (f64, f64, f64) {
+        (
+            self.red_trim.unwrap_or(DEFAULT_CHANNEL_TRIM),
+            self.green_trim.unwrap_or(DEFAULT_CHANNEL_TRIM),
+            self.blue_trim.unwrap_or(DEFAULT_CHANNEL_TRIM),
+        )
+    }
+
+    fn has_trim(&self) -> bool {
+        self.red_trim.is_some() || self.green_trim.is_some() || self.blue_trim.is_some()
+    }
+}
+
+/// Which outputs a backend may tint. Exclusion wins over inclusion.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputFilter {
+    /// Only these outputs, or all when `None`.
+    include: Option<BTreeSet<String>>,
+    exclude: BTreeSet<String>,
+}
+
+impl OutputFilter {
+    pub fn allows(&self, name: &str) -> bool {
+        !self.exclude.contains(name)
+            && self
+                .include
+                .as_ref()
+                .is_none_or(|include| include.contains(name))
+    }
+
+    /// Whether every output may be tinted, so output names don't matter.
+    pub fn allows_all(&self) -> bool {
+        self.include.is_none() && self.exclude.is_empty()
+    }
+}
+
+/// The `[winddown]` table: nights that keep getting warmer toward bedtime.
+///
+/// From `start` the night values drop gradually, reaching the full drops at `bedtime` and
+/// holding them until the night ends.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WinddownConfig {
+    /// When the drop begins, as HH:MM:SS.
+    pub start: String,
+    /// When the full drop is reached, as HH:MM:SS.
+    pub bedtime: String,
+    /// Kelvin below night_temp at bedtime.
+    #[serde(default)]
+    pub temp_drop: u32,
+    /// Percentage points below night_gamma at bedtime.
+    #[serde(default)]
+    pub gamma_drop: f64,
+}
+
+/// The `[media]` table: a gentler gamma while something holds an idle inhibitor, as video
+/// players do.
+///
+/// The scheduled gamma is raised by `gamma_hold` and to at least `min_gamma`, but never
+/// above `day_gamma`, so only the night's drop is softened.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MediaConfig {
+    /// Percentage points of the gamma drop held back while media plays.
+    #[serde(default)]
+    pub gamma_hold: f64,
+    /// Lowest gamma while media plays, as a percentage.
+    #[serde(default)]
+    pub min_gamma: Option<f64>,
+    /// Seconds without input before an idle inhibitor counts as media playing.
+    #[serde(default = "default_media_detect_after")]
+    pub detect_after: u64,
+}
+
+fn default_media_detect_after() -> u64 {
+    DEFAULT_MEDIA_DETECT_AFTER_SEC
+}
+
+/// What an output gets while the compositor drives it in HDR, where gamma ramps can
+/// distort the image.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HdrBehavior {
+    /// Leave the output neutral.
+    #[default]
+    Skip,
+    /// Apply half the tint.
+    Reduced,
+    /// Apply the tint through a color matrix, leaving the output neutral on backends that
+    /// only have gamma ramps.
+    CtmOnly,
+}
+
+impl fmt::Display for HdrBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HdrBehavior::Skip => "skip",
+            HdrBehavior::Reduced => "reduced",
+            HdrBehavior::CtmOnly => "ctm_only",
+        })
+    }
+}
+
+/// When to trade smoothness for fewer wakeups.
+///
+/// While power saving is active, transitions update at most every
+/// [`POWER_SAVING_UPDATE_INTERVAL_SEC`](crate::common::constants::POWER_SAVING_UPDATE_INTERVAL_SEC)
+/// seconds and smoothing animations are skipped.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerSaving {
+    /// Follow power-profiles-daemon's `power-saver` profile.
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+impl fmt::Display for PowerSaving {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PowerSaving::Auto => "auto",
+            PowerSaving::On => "on",
+            PowerSaving::Off => "off",
+        })
+    }
+}
+
+/// When manual overrides, `set --runtime` fields and test mode, give way to the schedule.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverrideExpiry {
+    /// Keep them until cleared by hand.
+    #[default]
+    Never,
+    /// Drop them when the next sunset or sunrise begins.
+    NextTransition,
+}
+
+impl fmt::Display for OverrideExpiry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OverrideExpiry::Never => "never",
+            OverrideExpiry::NextTransition => "next_transition",
+        })
+    }
+}
+
+/// How transitions are placed around sunset and sunrise, or a fixed static color, or
+/// another program deciding: targets pushed by an external scheduler, or darkman's mode.
 #[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum TransitionMode {
@@ -158,6 +597,8 @@ pub enum TransitionMode {
     StartAt,
     Center,
     Static,
+    External,
+    Darkman,
 }
 
 impl fmt::Display for TransitionMode {
@@ -168,6 +609,8 @@ impl fmt::Display for TransitionMode {
             TransitionMode::StartAt => "start_at",
             TransitionMode::Center => "center",
             TransitionMode::Static => "static",
+            TransitionMode::External => "external",
+            TransitionMode::Darkman => "darkman",
         })
     }
 }
@@ -182,8 +625,10 @@ impl std::str::FromStr for TransitionMode {
             "start_at" => TransitionMode::StartAt,
             "center" => TransitionMode::Center,
             "static" => TransitionMode::Static,
+            "external" => TransitionMode::External,
+            "darkman" => TransitionMode::Darkman,
             _ => anyhow::bail!(
-                "'{s}' is not a valid transition mode\nUse: geo, finish_by, start_at, center, or static"
+                "'{s}' is not a valid transition mode\nUse: geo, finish_by, start_at, center, static, external, or darkman"
             ),
         })
     }
@@ -194,17 +639,22 @@ impl std::str::FromStr for TransitionMode {
 /// The sole serde target. `None` means the key was absent in the TOML.
 /// [`RawConfig::resolve`] validates and applies defaults once, producing the
 /// runtime [`Config`].
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
 pub struct RawConfig {
     // Backend
     pub backend: Option<Backend>,
     #[serde(default)]
     pub transition_mode: TransitionMode,
+    pub verify_gamma: Option<bool>,
+    pub hyprsunset_mode: Option<HyprsunsetMode>,
+    pub readonly_config: Option<bool>,
 
     // Smoothing
     pub smoothing: Option<bool>,
+    pub reduce_motion: Option<bool>,
     pub startup_duration: Option<f64>,
     pub shutdown_duration: Option<f64>,
+    pub shutdown_target: Option<ShutdownTarget>,
     pub adaptive_interval: Option<u64>,
 
     // Time-based
@@ -212,7 +662,16 @@ pub struct RawConfig {
     pub day_temp: Option<u32>,
     pub night_gamma: Option<f64>,
     pub day_gamma: Option<f64>,
+    pub night_saturation: Option<f64>,
+    pub night_brightness: Option<f64>,
+    pub day_brightness: Option<f64>,
     pub update_interval: Option<UpdateInterval>,
+    pub adjust_update_interval: Option<bool>,
+    pub power_saving: Option<PowerSaving>,
+    pub override_expiry: Option<OverrideExpiry>,
+    pub winddown: Option<WinddownConfig>,
+    pub media: Option<MediaConfig>,
+    pub darkman_export: Option<bool>,
 
     // Static
     pub static_temp: Option<u32>,
@@ -222,17 +681,59 @@ pub struct RawConfig {
     pub sunset: Option<String>,
     pub sunrise: Option<String>,
     pub transition_duration: Option<u64>,
+    pub sunset_duration: Option<u64>,
+    pub sunrise_duration: Option<u64>,
+    pub night_latest_start: Option<String>,
+    pub min_day_duration: Option<u64>,
 
     // Geolocation
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
 
+    // Per-output
+    pub outputs: Option<BTreeMap<String, OutputConfig>>,
+    pub include_outputs: Option<Vec<String>>,
+    pub exclude_outputs: Option<Vec<String>>,
+
+    // Per-application
+    pub neutral_apps: Option<Vec<String>>,
+
+    // Docking
+    pub activate_when_outputs: Option<Vec<String>>,
+
+    // Manual gamma curve
+    pub gamma_r: Option<f64>,
+    pub gamma_g: Option<f64>,
+    pub gamma_b: Option<f64>,
+    pub red_trim: Option<f64>,
+    pub green_trim: Option<f64>,
+    pub blue_trim: Option<f64>,
+    pub matrix: Option<ColorMatrix>,
+
+    // IPC
+    pub heartbeat_interval: Option<u64>,
+    pub values_changed_temp_step: Option<u32>,
+    pub values_changed_gamma_step: Option<f64>,
+    pub ipc_allowed_gids: Option<Vec<u32>>,
+
     // Deprecated and ignored
     pub start_hyprsunset: Option<bool>,
     pub startup_transition: Option<bool>,
     pub startup_transition_duration: Option<f64>,
 }
 
+/// Geo coordinates that look like a provisioning placeholder rather than a location.
+///
+/// Geo mode with these falls back to the manual default times instead of computing a
+/// schedule for a point in the ocean.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaceholderCoordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Why the coordinates were not trusted, e.g. "latitude equals longitude".
+    pub reason: &'static str,
+}
+
 /// Resolved runtime configuration, produced by [`RawConfig::resolve`].
 ///
 /// Always-defaulted fields are concrete values. Mode-conditional fields stay
@@ -243,11 +744,21 @@ pub struct Config {
     // Backend
     pub backend: Backend,
     pub transition_mode: TransitionMode,
+    /// Debugging aid: watch for other clients overwriting gamma and reapply (Wayland only).
+    pub verify_gamma: bool,
+    /// Whether the hyprsunset backend starts its own hyprsunset or uses a running one.
+    pub hyprsunset_mode: HyprsunsetMode,
+    /// Never write under the config directory, for configs managed declaratively.
+    pub readonly_config: bool,
 
     // Smoothing
     pub smoothing: bool,
+    /// Apply every change at once whatever `smoothing` says. Set in the main `sunsetr.toml`,
+    /// it also holds under presets that turn smoothing on.
+    pub reduce_motion: bool,
     pub startup_duration: f64,
     pub shutdown_duration: f64,
+    pub shutdown_target: ShutdownTarget,
     pub adaptive_interval: u64,
 
     // Time-based
@@ -255,7 +766,27 @@ pub struct Config {
     pub day_temp: u32,
     pub night_gamma: f64,
     pub day_gamma: f64,
+    /// Night saturation in percent, 100 leaving colors alone (Hyprland only).
+    pub night_saturation: f64,
+    /// Night brightness in percent, scaling output apart from gamma.
+    pub night_brightness: f64,
+    /// Day brightness in percent, also used in static mode.
+    pub day_brightness: f64,
     pub update_interval: UpdateInterval,
+    /// Whether a fixed `update_interval` is shortened during transitions too short for
+    /// [`MINIMUM_TRANSITION_STEPS`](crate::common::constants::MINIMUM_TRANSITION_STEPS)
+    /// updates at it.
+    pub adjust_update_interval: bool,
+    /// Whether to update less often and skip smoothing, e.g. on battery saver.
+    pub power_saving: PowerSaving,
+    /// Whether runtime overrides and test mode end at the next sunset or sunrise.
+    pub override_expiry: OverrideExpiry,
+    /// Extra warmth toward bedtime during the night, see [`WinddownConfig`].
+    pub winddown: Option<WinddownConfig>,
+    /// A gentler gamma during media playback, see [`MediaConfig`].
+    pub media: Option<MediaConfig>,
+    /// Set darkman's mode from the schedule, see [`crate::io::darkman`].
+    pub darkman_export: bool,
 
     // Static
     pub static_temp: Option<u32>,
@@ -265,10 +796,72 @@ pub struct Config {
     pub sunset: Option<String>,
     pub sunrise: Option<String>,
     pub transition_duration: u64,
+    /// Minutes of the sunset transition when it differs from `transition_duration`.
+    pub sunset_duration: Option<u64>,
+    /// Minutes of the sunrise transition when it differs from `transition_duration`.
+    pub sunrise_duration: Option<u64>,
+    /// Latest wall-clock time night may begin, as HH:MM:SS. A sunset transition that would
+    /// end later is cut short or moved earlier, in geo mode too.
+    pub night_latest_start: Option<String>,
+    /// Minutes of day kept between sunrise and sunset, moving the sunrise transition
+    /// earlier when the schedule leaves less. Also applies in geo mode.
+    pub min_day_duration: Option<u64>,
 
     // Geolocation
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
+    /// Set when geo mode fell back to manual times over coordinates that look templated.
+    pub placeholder_coordinates: Option<PlaceholderCoordinates>,
+
+    // Per-output
+    pub outputs: BTreeMap<String, OutputConfig>,
+    /// Outputs sunsetr may tint, all of them when empty.
+    pub include_outputs: Vec<String>,
+    /// Outputs sunsetr never tints, on top of `[outputs.<name>]` tables with `exclude`.
+    pub exclude_outputs: Vec<String>,
+    /// Outputs held neutral by `sunsetr presentation`, read from the state directory
+    /// rather than the config files.
+    pub presentation: Option<crate::state::presentation::Presentation>,
+    /// Offsets from the scheduled values set with `sunsetr adjust`, read from the state
+    /// directory like presentation mode.
+    pub value_offset: Option<crate::state::value_offset::ValueOffset>,
+
+    // Per-application
+    /// Experimental: app ids or window classes whose output is left neutral while
+    /// they have focus. Needs Hyprland, sway or niri IPC.
+    pub neutral_apps: Vec<String>,
+
+    // Docking
+    /// Outputs that, once all connected, make this preset the active one. Only read from
+    /// preset configs.
+    pub activate_when_outputs: Vec<String>,
+
+    // Manual gamma curve
+    /// Danger zone: per-channel gamma exponents applied on top of the temperature and
+    /// gamma, 1.0 when unset (Wayland only).
+    pub gamma_r: f64,
+    pub gamma_g: f64,
+    pub gamma_b: f64,
+    /// Multipliers on the temperature's red, green and blue factors, for correcting a
+    /// color cast, 1.0 when unset.
+    pub red_trim: f64,
+    pub green_trim: f64,
+    pub blue_trim: f64,
+    /// Color matrix applied before the temperature tint on every output without a matrix
+    /// of its own (Hyprland only).
+    pub matrix: Option<ColorMatrix>,
+
+    // IPC
+    /// Seconds between IPC heartbeat events, 0 when disabled.
+    pub heartbeat_interval: u64,
+    /// Smallest temperature change in Kelvin reported by a values_changed event.
+    pub values_changed_temp_step: u32,
+    /// Smallest gamma change in percent reported by a values_changed event.
+    pub values_changed_gamma_step: f64,
+    /// Primary groups whose processes may subscribe to the IPC socket and read from it
+    /// besides the user running sunsetr, whose processes alone may send commands. Read once
+    /// at startup.
+    pub ipc_allowed_gids: Vec<u32>,
 }
 
 impl RawConfig {
@@ -331,6 +924,17 @@ impl Config {
         loading::load_from_path(path)
     }
 
+    /// Parse and resolve a complete config document without touching the disk, as for the
+    /// `apply_config` IPC request.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        loading::from_toml(content)
+    }
+
+    /// Load `path` with the presets at `layers` stacked on top, as for `preset push`.
+    pub fn load_layered(path: &Path, layers: &[PathBuf]) -> Result<Self> {
+        loading::load_layered(path, layers)
+    }
+
     pub fn get_config_path() -> Result<PathBuf> {
         loading::get_config_path()
     }
@@ -343,6 +947,90 @@ impl Config {
         builder::update_coordinates(latitude, longitude)
     }
 
+    /// Minutes of the manual sunset transition, `transition_duration` unless overridden.
+    pub fn sunset_duration_mins(&self) -> u64 {
+        self.sunset_duration.unwrap_or(self.transition_duration)
+    }
+
+    /// Minutes of the manual sunrise transition, `transition_duration` unless overridden.
+    pub fn sunrise_duration_mins(&self) -> u64 {
+        self.sunrise_duration.unwrap_or(self.transition_duration)
+    }
+
+    /// `night_latest_start` as a time of day.
+    pub fn night_latest_start_time(&self) -> Option<NaiveTime> {
+        self.night_latest_start
+            .as_deref()
+            .and_then(|time| NaiveTime::parse_from_str(time, "%H:%M:%S").ok())
+    }
+
+    /// The outputs left alone, from `include_outputs`, `exclude_outputs` and the
+    /// `exclude` key of `[outputs.<name>]` tables.
+    pub fn output_filter(&self) -> OutputFilter {
+        OutputFilter {
+            include: (!self.include_outputs.is_empty())
+                .then(|| self.include_outputs.iter().cloned().collect()),
+            exclude: self
+                .exclude_outputs
+                .iter()
+                .cloned()
+                .chain(
+                    self.outputs
+                        .iter()
+                        .filter(|(_, output)| output.exclude)
+                        .map(|(name, _)| name.clone()),
+                )
+                .collect(),
+        }
+    }
+
+    /// The manual `gamma_r`, `gamma_g` and `gamma_b` exponents and the channel trims together.
+    pub fn channel_gamma(&self) -> crate::backend::gamma::ChannelGamma {
+        crate::backend::gamma::ChannelGamma {
+            red: self.gamma_r,
+            green: self.gamma_g,
+            blue: self.gamma_b,
+            trim: self.trim(),
+        }
+    }
+
+    /// The top-level `red_trim`, `green_trim` and `blue_trim`.
+    pub fn trim(&self) -> (f64, f64, f64) {
+        (self.red_trim, self.green_trim, self.blue_trim)
+    }
+
+    /// The top-level settings an `[outputs.<name>]` table can replace, for outputs that
+    /// leave them unset.
+    pub fn output_defaults(&self) -> OutputConfig {
+        OutputConfig {
+            matrix: self.matrix,
+            red_trim: Some(self.red_trim),
+            green_trim: Some(self.green_trim),
+            blue_trim: Some(self.blue_trim),
+            ..Default::default()
+        }
+    }
+
+    /// [`Self::channel_gamma`] for each output whose table sets trims of its own.
+    pub fn output_channel_gamma(&self) -> BTreeMap<String, crate::backend::gamma::ChannelGamma> {
+        let channel_gamma = self.channel_gamma();
+        let defaults = self.output_defaults();
+        self.outputs
+            .iter()
+            .filter(|(_, output)| output.has_trim())
+            .map(|(name, output)| {
+                let trim = output.with_defaults(&defaults).trim();
+                (
+                    name.clone(),
+                    crate::backend::gamma::ChannelGamma {
+                        trim,
+                        ..channel_gamma
+                    },
+                )
+            })
+            .collect()
+    }
+
     pub fn log_config(&self, resolved_backend: Option<crate::backend::BackendType>) {
         let active_preset = crate::state::preset::get_active_preset().ok().flatten();
         let (config_source, is_preset) = if let Some(ref preset_name) = active_preset {
@@ -354,6 +1042,18 @@ impl Config {
         let display_mode = self.detect_display_mode();
 
         log_block_start!("Loaded {}", config_source);
+        let stack = crate::state::preset::get_preset_stack().unwrap_or_default();
+        if !stack.is_empty() {
+            log_indented!("Stacked presets: {}", stack.join(" + "));
+        }
+        let overrides = crate::state::overrides::load().unwrap_or_default();
+        if !overrides.is_empty() {
+            let names: Vec<&str> = overrides.keys().map(String::as_str).collect();
+            log_indented!("Runtime overrides: {}", names.join(", "));
+        }
+        if self.readonly_config {
+            log_indented!("Read-only: commands will not write to the config directory");
+        }
 
         if matches!(display_mode, DisplayMode::TimeBasedGeo) {
             let geo_path = if is_preset {
@@ -391,9 +1091,15 @@ impl Config {
                         match resolved {
                             crate::backend::BackendType::Hyprland => "Auto (Hyprland)",
                             crate::backend::BackendType::Wayland => "Auto (Wayland)",
-                            crate::backend::BackendType::Hyprsunset => {
+                            crate::backend::BackendType::X11 => "Auto (X11)",
+                            crate::backend::BackendType::Kde => "Auto (KDE)",
+                            crate::backend::BackendType::Gnome => "Auto (GNOME)",
+                            crate::backend::BackendType::Hyprsunset
+                            | crate::backend::BackendType::Drm
+                            | crate::backend::BackendType::Mock => {
                                 unreachable!(
-                                    "Auto-detection should never select Hyprsunset backend"
+                                    "Auto-detection should never select the {} backend",
+                                    resolved.name()
                                 )
                             }
                         }
@@ -404,19 +1110,143 @@ impl Config {
                 Backend::Hyprland => "Hyprland",
                 Backend::Hyprsunset => "Hyprsunset",
                 Backend::Wayland => "Wayland",
+                Backend::X11 => "X11",
+                Backend::Drm => "DRM",
+                Backend::Kde => "KDE",
+                Backend::Gnome => "GNOME",
+                Backend::None => "None (nothing is applied)",
             }
         );
 
         log_indented!("{}", backend_display);
+        let uses_wayland = matches!(backend, Backend::Wayland)
+            || matches!(resolved_backend, Some(crate::backend::BackendType::Wayland));
+        if self.verify_gamma && uses_wayland {
+            log_indented!("Gamma verification: enabled");
+        }
+        let uses_hyprsunset = matches!(backend, Backend::Hyprsunset)
+            || matches!(
+                resolved_backend,
+                Some(crate::backend::BackendType::Hyprsunset)
+            );
+        if uses_hyprsunset && self.hyprsunset_mode == HyprsunsetMode::Attach {
+            log_indented!("hyprsunset: attached to a running instance");
+        }
+        if !self.outputs.is_empty() {
+            let names: Vec<&str> = self.outputs.keys().map(String::as_str).collect();
+            log_indented!("Per-output settings: {}", names.join(", "));
+        }
+        if !self.include_outputs.is_empty() {
+            log_indented!("Only outputs: {}", self.include_outputs.join(", "));
+        }
+        if !self.exclude_outputs.is_empty() {
+            log_indented!("Excluded outputs: {}", self.exclude_outputs.join(", "));
+        }
+        if !self.neutral_apps.is_empty() {
+            log_indented!("Neutral while focused: {}", self.neutral_apps.join(", "));
+        }
+        if !self.activate_when_outputs.is_empty() {
+            log_indented!(
+                "Activates with outputs: {}",
+                self.activate_when_outputs.join(", ")
+            );
+        }
+        if !self.ipc_allowed_gids.is_empty() {
+            let gids: Vec<String> = self.ipc_allowed_gids.iter().map(u32::to_string).collect();
+            log_indented!("IPC open to groups: {}", gids.join(", "));
+        }
+        let uses_kde = matches!(backend, Backend::Kde)
+            || matches!(resolved_backend, Some(crate::backend::BackendType::Kde));
+        if uses_kde && (self.night_gamma != 100.0 || self.day_gamma != 100.0) {
+            log_indented!("Gamma: ignored, KDE's Night Light only sets temperature");
+        }
+        let (red_trim, green_trim, blue_trim) = self.trim();
+        if self.trim()
+            != (
+                DEFAULT_CHANNEL_TRIM,
+                DEFAULT_CHANNEL_TRIM,
+                DEFAULT_CHANNEL_TRIM,
+            )
+        {
+            if uses_kde || uses_hyprsunset {
+                log_indented!("Channel trim: ignored, the backend has no per-channel control");
+            } else {
+                log_indented!("Channel trim: R {red_trim} G {green_trim} B {blue_trim}");
+            }
+        }
+        let uses_hyprland = matches!(backend, Backend::Hyprland)
+            || matches!(
+                resolved_backend,
+                Some(crate::backend::BackendType::Hyprland)
+            );
+        if let Some(matrix) = &self.matrix {
+            if uses_hyprland {
+                log_indented!("Color matrix: {matrix}");
+            } else {
+                log_indented!("Color matrix: ignored, only the Hyprland backend applies it");
+            }
+        }
+        if self.night_saturation != crate::common::constants::DEFAULT_SATURATION {
+            if uses_hyprland {
+                log_indented!("Night saturation: {}%", self.night_saturation);
+            } else {
+                log_indented!("Night saturation: ignored, only the Hyprland backend applies it");
+            }
+        }
+        if self.night_brightness != crate::common::constants::DEFAULT_BRIGHTNESS
+            || self.day_brightness != crate::common::constants::DEFAULT_BRIGHTNESS
+        {
+            if uses_kde {
+                log_indented!("Brightness: ignored, KDE's Night Light only sets temperature");
+            } else {
+                log_indented!(
+                    "Brightness: {}% night, {}% day",
+                    self.night_brightness,
+                    self.day_brightness
+                );
+            }
+        }
+        let channel_gamma = self.channel_gamma();
+        if !channel_gamma.has_identity_curve() {
+            log_pipe!();
+            log_warning!(
+                "Manual gamma curve override active: R {} G {} B {}",
+                channel_gamma.red,
+                channel_gamma.green,
+                channel_gamma.blue
+            );
+            let uses_ramps = uses_wayland
+                || matches!(backend, Backend::X11 | Backend::Drm | Backend::Gnome)
+                || matches!(
+                    resolved_backend,
+                    Some(crate::backend::BackendType::X11 | crate::backend::BackendType::Gnome)
+                );
+            if uses_ramps {
+                log_indented!("Remove gamma_r, gamma_g and gamma_b if colors look wrong");
+            } else {
+                log_indented!(
+                    "Only the Wayland, X11, DRM and GNOME backends apply it, so it is ignored here"
+                );
+            }
+        }
 
         let mode_display = match display_mode {
             DisplayMode::Static => "Mode: Static (constant values)".to_string(),
+            DisplayMode::External => "Mode: External (targets pushed over IPC)".to_string(),
+            DisplayMode::Darkman => "Mode: Darkman (light is day, dark is night)".to_string(),
             DisplayMode::TimeBasedGeo => "Mode: Time-based (geo)".to_string(),
             DisplayMode::TimeBasedManual { mode } => {
                 format!("Mode: Time-based manual ({})", mode)
             }
         };
         log_indented!("{}", mode_display);
+        if let Some(placeholder) = &self.placeholder_coordinates {
+            log_indented!(
+                "Geo mode paused: {}, {} look like a placeholder",
+                placeholder.latitude,
+                placeholder.longitude
+            );
+        }
 
         match display_mode {
             DisplayMode::Static => {
@@ -426,6 +1256,17 @@ impl Config {
                     self.static_gamma.unwrap_or(DEFAULT_DAY_GAMMA)
                 );
             }
+            DisplayMode::External => {
+                log_indented!(
+                    "Until targets arrive: {}K @ {}% gamma",
+                    self.static_temp.unwrap_or(DEFAULT_DAY_TEMP),
+                    self.static_gamma.unwrap_or(DEFAULT_DAY_GAMMA)
+                );
+            }
+            DisplayMode::Darkman => {
+                log_indented!("Night: {}K @ {}% gamma", self.night_temp, self.night_gamma);
+                log_indented!("Day: {}K @ {}% gamma", self.day_temp, self.day_gamma);
+            }
             DisplayMode::TimeBasedGeo => {
                 if let (Some(lat), Some(lon)) = (self.latitude, self.longitude) {
                     let lat_dir = if lat >= 0.0 { "N" } else { "S" };
@@ -441,7 +1282,12 @@ impl Config {
 
                 log_indented!("Night: {}K @ {}% gamma", self.night_temp, self.night_gamma);
                 log_indented!("Day: {}K @ {}% gamma", self.day_temp, self.day_gamma);
-                log_indented!("Update interval: {}", self.update_interval);
+                self.log_update_interval();
+                self.log_day_limits();
+                self.log_power_saving();
+                self.log_winddown();
+                self.log_media();
+                self.log_darkman_export();
             }
             DisplayMode::TimeBasedManual { .. } => {
                 if let Some(ref sunset) = self.sunset {
@@ -450,16 +1296,33 @@ impl Config {
                 if let Some(ref sunrise) = self.sunrise {
                     log_indented!("Sunrise: {}", sunrise);
                 }
-                log_indented!("Transition duration: {} minutes", self.transition_duration);
+                let (sunset_mins, sunrise_mins) =
+                    (self.sunset_duration_mins(), self.sunrise_duration_mins());
+                if sunset_mins == sunrise_mins {
+                    log_indented!("Transition duration: {} minutes", sunset_mins);
+                } else {
+                    log_indented!(
+                        "Transition duration: {} minutes (sunset), {} minutes (sunrise)",
+                        sunset_mins,
+                        sunrise_mins
+                    );
+                }
                 log_indented!("Night: {}K @ {}% gamma", self.night_temp, self.night_gamma);
                 log_indented!("Day: {}K @ {}% gamma", self.day_temp, self.day_gamma);
-                log_indented!("Update interval: {}", self.update_interval);
+                self.log_update_interval();
+                self.log_day_limits();
+                self.log_power_saving();
+                self.log_winddown();
+                self.log_media();
+                self.log_darkman_export();
             }
         }
 
         let backend_supports_smoothing = matches!(backend, Backend::Wayland);
 
-        if backend_supports_smoothing && self.smoothing {
+        if backend_supports_smoothing && self.smoothing && self.reduce_motion {
+            log_indented!("Reduced motion: changes apply at once");
+        } else if backend_supports_smoothing && self.smoothing {
             let startup_duration = self.startup_duration;
             let shutdown_duration = self.shutdown_duration;
             let show_startup = startup_duration >= 0.1;
@@ -491,17 +1354,129 @@ impl Config {
                     "seconds"
                 };
                 log_indented!("Shutdown duration: {} {}", duration_str, duration_label);
+                log_indented!("Shutdown target: {}", self.shutdown_target);
             }
 
             if show_startup || show_shutdown {
                 log_indented!("Adaptive interval: {}ms", self.adaptive_interval);
             }
         }
+
+        if self.override_expiry != OverrideExpiry::Never {
+            log_indented!("Override expiry: {}", self.override_expiry);
+        }
+
+        if let Some(ref presentation) = self.presentation {
+            log_indented!("Presentation mode: {}", presentation.describe());
+        }
+
+        if let Some(offset) = self.value_offset
+            && offset.is_active(crate::time::source::now())
+        {
+            log_indented!(
+                "Adjustment: {} until {}",
+                offset.describe(),
+                offset.expires_at.format("%Y-%m-%d %H:%M")
+            );
+        }
+    }
+
+    fn log_update_interval(&self) {
+        if self.adjust_update_interval && matches!(self.update_interval, UpdateInterval::Fixed(_)) {
+            log_indented!(
+                "Update interval: {}, shortened in transitions under {} updates",
+                self.update_interval,
+                crate::common::constants::MINIMUM_TRANSITION_STEPS
+            );
+        } else {
+            log_indented!("Update interval: {}", self.update_interval);
+        }
+    }
+
+    fn log_power_saving(&self) {
+        match self.power_saving {
+            PowerSaving::Auto => {}
+            PowerSaving::On => log_indented!("Power saving: on"),
+            PowerSaving::Off => log_indented!("Power saving: off"),
+        }
+    }
+
+    fn log_day_limits(&self) {
+        if let Some(ref latest) = self.night_latest_start {
+            log_indented!("Night begins by: {latest}");
+        }
+        if let Some(minutes) = self.min_day_duration {
+            log_indented!("Minimum day: {minutes} minutes");
+        }
+    }
+
+    fn log_darkman_export(&self) {
+        if self.darkman_export {
+            log_indented!("Darkman: switched to dark halfway through sunset");
+        }
+    }
+
+    fn log_winddown(&self) {
+        if let Some(ref winddown) = self.winddown {
+            log_indented!(
+                "Wind-down: {} to {}, -{}K @ -{}% gamma",
+                winddown.start,
+                winddown.bedtime,
+                winddown.temp_drop,
+                winddown.gamma_drop
+            );
+        }
+    }
+
+    fn log_media(&self) {
+        if let Some(ref media) = self.media {
+            let floor = media
+                .min_gamma
+                .map_or(String::new(), |gamma| format!(", at least {gamma}%"));
+            log_indented!(
+                "Media playback: +{}% gamma{floor} after {}s idle",
+                media.gamma_hold,
+                media.detect_after
+            );
+        }
+    }
+
+    /// Values the smooth shutdown animates toward from `current`, per `shutdown_target`.
+    pub fn shutdown_values(&self, current: (Kelvin, GammaPercent)) -> (Kelvin, GammaPercent) {
+        match self.shutdown_target {
+            ShutdownTarget::Day => self.day_values(),
+            ShutdownTarget::Reset => in_range(DEFAULT_DAY_TEMP, DEFAULT_DAY_GAMMA),
+            ShutdownTarget::Current => current,
+        }
+    }
+
+    /// `day_temp` and `day_gamma`.
+    pub fn day_values(&self) -> (Kelvin, GammaPercent) {
+        in_range(self.day_temp, self.day_gamma)
+    }
+
+    /// `night_temp` and `night_gamma`.
+    pub fn night_values(&self) -> (Kelvin, GammaPercent) {
+        in_range(self.night_temp, self.night_gamma)
+    }
+
+    /// Neutral static config that `--safe-mode` runs with while the real one fails to load.
+    pub fn safe_mode() -> Self {
+        RawConfig {
+            transition_mode: TransitionMode::Static,
+            static_temp: Some(DEFAULT_DAY_TEMP),
+            static_gamma: Some(DEFAULT_DAY_GAMMA),
+            ..Default::default()
+        }
+        .resolve()
+        .expect("neutral static config is valid")
     }
 
     fn detect_display_mode(&self) -> DisplayMode {
         match self.transition_mode {
             TransitionMode::Static => DisplayMode::Static,
+            TransitionMode::External => DisplayMode::External,
+            TransitionMode::Darkman => DisplayMode::Darkman,
             TransitionMode::Geo => DisplayMode::TimeBasedGeo,
             mode => DisplayMode::TimeBasedManual { mode },
         }
@@ -0,0 +1,642 @@
+//! JSON Schema for `sunsetr.toml`, printed by `sunsetr config schema`.
+//!
+//! The property list comes from [`RawConfig`] and [`OutputConfig`] themselves: serde hands
+//! a struct's field names to the deserializer, so [`field_names`] asks for them and every
+//! field needs an entry in [`property`]. A field added to the config without one fails the
+//! tests instead of silently missing from the schema. Ranges and defaults are the same
+//! constants validation and [`RawConfig::resolve`] use.
+
+use serde::Deserialize;
+use serde::de::{self, Visitor};
+use serde_json::{Map, Value, json};
+
+use super::{
+    Backend, HdrBehavior, HyprsunsetMode, MatrixPreset, MediaConfig, OutputConfig, OverrideExpiry,
+    PowerSaving, RawConfig, ShutdownTarget, TransitionMode, WinddownConfig,
+};
+use crate::common::constants::*;
+
+/// Dialect of the generated schema.
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Bump when a change to the schema could reject configs it accepted before.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The full configuration schema.
+pub fn schema() -> Value {
+    json!({
+        "$schema": DIALECT,
+        "title": "sunsetr.toml",
+        "description": format!(
+            "Configuration for sunsetr {}, schema version {SCHEMA_VERSION}",
+            env!("CARGO_PKG_VERSION")
+        ),
+        "type": "object",
+        "properties": properties::<RawConfig>(),
+        "$defs": { "output": output_schema() },
+        // Static mode has no fallback for its values
+        "if": {
+            "properties": { "transition_mode": { "const": TransitionMode::Static.to_string() } },
+            "required": ["transition_mode"]
+        },
+        "then": { "required": ["static_temp", "static_gamma"] },
+        "x-sunsetr-schema-version": SCHEMA_VERSION
+    })
+}
+
+fn output_schema() -> Value {
+    json!({
+        "description": "Per-output adjustments, keyed by connector name (e.g. DP-1)",
+        "type": "object",
+        "properties": properties::<OutputConfig>(),
+        // OutputConfig denies unknown fields
+        "additionalProperties": false
+    })
+}
+
+fn winddown_schema() -> Value {
+    json!({
+        "description": "Nights that keep getting warmer from start until bedtime",
+        "type": "object",
+        "properties": properties::<WinddownConfig>(),
+        "required": ["start", "bedtime"],
+        "additionalProperties": false
+    })
+}
+
+fn media_schema() -> Value {
+    json!({
+        "description": "A gentler gamma while an idle inhibitor shows media is playing",
+        "type": "object",
+        "properties": properties::<MediaConfig>(),
+        "additionalProperties": false
+    })
+}
+
+fn properties<'de, T: Deserialize<'de>>() -> Map<String, Value> {
+    field_names::<T>()
+        .iter()
+        .map(|name| {
+            let schema = property(name)
+                .unwrap_or_else(|| panic!("config field '{name}' has no schema entry"));
+            (name.to_string(), schema)
+        })
+        .collect()
+}
+
+/// Schema of one config field, by its TOML key.
+fn property(name: &str) -> Option<Value> {
+    let temp = |description: &str, default: Option<u32>| {
+        with_default(
+            json!({
+                "description": description,
+                "type": "integer",
+                "minimum": MINIMUM_TEMP,
+                "maximum": MAXIMUM_TEMP
+            }),
+            default,
+        )
+    };
+    let gamma = |description: &str, default: Option<f64>| {
+        with_default(
+            json!({
+                "description": description,
+                "type": "number",
+                "minimum": MINIMUM_GAMMA,
+                "maximum": MAXIMUM_GAMMA
+            }),
+            default,
+        )
+    };
+    let brightness = |description: &str| {
+        json!({
+            "description": description,
+            "type": "number",
+            "minimum": MINIMUM_BRIGHTNESS,
+            "maximum": MAXIMUM_BRIGHTNESS,
+            "default": DEFAULT_BRIGHTNESS
+        })
+    };
+    let smooth_duration = |description: &str, default: Option<f64>| {
+        with_default(
+            json!({
+                "description": description,
+                "type": "number",
+                "minimum": MINIMUM_SMOOTH_TRANSITION_DURATION_SEC,
+                "maximum": MAXIMUM_SMOOTH_TRANSITION_DURATION_SEC
+            }),
+            default,
+        )
+    };
+    let transition_minutes = |description: &str, default: Option<u64>| {
+        with_default(
+            json!({
+                "description": description,
+                "type": "integer",
+                "minimum": MINIMUM_TRANSITION_DURATION_MIN,
+                "maximum": MAXIMUM_TRANSITION_DURATION_MIN
+            }),
+            default,
+        )
+    };
+    let time = |description: &str, default: Option<&str>| {
+        with_default(
+            json!({
+                "description": description,
+                "type": "string",
+                "pattern": "^\\d{1,2}:\\d{2}:\\d{2}$"
+            }),
+            default,
+        )
+    };
+    let channel_gamma = |channel: &str| {
+        json!({
+            "description": format!(
+                "Exponent applied to the {channel} channel on top of gamma (Wayland only)"
+            ),
+            "type": "number",
+            "minimum": MINIMUM_CHANNEL_GAMMA,
+            "maximum": MAXIMUM_CHANNEL_GAMMA,
+            "default": DEFAULT_CHANNEL_GAMMA
+        })
+    };
+    let channel_trim = |channel: &str| {
+        json!({
+            "description": format!(
+                "Multiplier on the temperature's {channel} factor, for correcting a color cast"
+            ),
+            "type": "number",
+            "minimum": MINIMUM_CHANNEL_TRIM,
+            "maximum": MAXIMUM_CHANNEL_TRIM,
+            "default": DEFAULT_CHANNEL_TRIM
+        })
+    };
+    let deprecated = |description: &str, kind: &str| json!({ "description": description, "type": kind, "deprecated": true });
+
+    Some(match name {
+        "backend" => json!({
+            "description": "Backend that applies the color adjustment",
+            "enum": variants([
+                Backend::Auto,
+                Backend::Hyprland,
+                Backend::Hyprsunset,
+                Backend::Wayland,
+                Backend::X11,
+                Backend::Drm,
+                Backend::Kde,
+                Backend::Gnome,
+                Backend::None,
+            ]),
+            "default": DEFAULT_BACKEND.to_string()
+        }),
+        "transition_mode" => json!({
+            "description": "How sunset and sunrise times are determined",
+            "enum": variants([
+                TransitionMode::Geo,
+                TransitionMode::FinishBy,
+                TransitionMode::StartAt,
+                TransitionMode::Center,
+                TransitionMode::Static,
+                TransitionMode::External,
+                TransitionMode::Darkman,
+            ]),
+            "default": DEFAULT_TRANSITION_MODE.to_string()
+        }),
+        "verify_gamma" => json!({
+            "description": "Reapply gamma when another client overwrites it (Wayland only)",
+            "type": "boolean",
+            "default": DEFAULT_VERIFY_GAMMA
+        }),
+        "hyprsunset_mode" => json!({
+            "description": "Start hyprsunset for the hyprsunset backend, or attach to a running one",
+            "enum": variants([HyprsunsetMode::Managed, HyprsunsetMode::Attach]),
+            "default": DEFAULT_HYPRSUNSET_MODE.to_string()
+        }),
+        "readonly_config" => json!({
+            "description": "Refuse commands that would write under the config directory",
+            "type": "boolean",
+            "default": DEFAULT_READONLY_CONFIG
+        }),
+        "smoothing" => json!({
+            "description": "Fade into the scheduled values on startup and out on shutdown",
+            "type": "boolean",
+            "default": DEFAULT_SMOOTHING
+        }),
+        "reduce_motion" => json!({
+            "description": "Apply every change at once, overriding smoothing in presets too",
+            "type": "boolean",
+            "default": DEFAULT_REDUCE_MOTION
+        }),
+        "startup_duration" => smooth_duration(
+            "Seconds of the startup fade",
+            Some(DEFAULT_STARTUP_DURATION_SEC),
+        ),
+        "shutdown_duration" => smooth_duration(
+            "Seconds of the shutdown fade, defaults to startup_duration",
+            None,
+        ),
+        "shutdown_target" => json!({
+            "description": "Values the shutdown fade ends on",
+            "enum": variants([ShutdownTarget::Day, ShutdownTarget::Reset, ShutdownTarget::Current]),
+            "default": DEFAULT_SHUTDOWN_TARGET.to_string()
+        }),
+        "adaptive_interval" => json!({
+            "description": "Minimum milliseconds between smoothing steps",
+            "type": "integer",
+            "minimum": MINIMUM_ADAPTIVE_INTERVAL_MS,
+            "maximum": MAXIMUM_ADAPTIVE_INTERVAL_MS,
+            "default": DEFAULT_ADAPTIVE_INTERVAL_MS
+        }),
+        "night_temp" => temp(
+            "Night color temperature in Kelvin",
+            Some(DEFAULT_NIGHT_TEMP),
+        ),
+        "day_temp" => temp("Day color temperature in Kelvin", Some(DEFAULT_DAY_TEMP)),
+        "night_gamma" => gamma("Night gamma in percent", Some(DEFAULT_NIGHT_GAMMA)),
+        "day_gamma" => gamma("Day gamma in percent", Some(DEFAULT_DAY_GAMMA)),
+        "night_saturation" => json!({
+            "description": "Night saturation in percent, 100 leaves colors alone (Hyprland only)",
+            "type": "number",
+            "minimum": MINIMUM_SATURATION,
+            "maximum": MAXIMUM_SATURATION,
+            "default": DEFAULT_SATURATION
+        }),
+        "night_brightness" => {
+            brightness("Night brightness in percent, scaling output apart from gamma")
+        }
+        "day_brightness" => brightness("Day brightness in percent, also used in static mode"),
+        "update_interval" => json!({
+            "description": "Seconds between updates during a transition, or \"auto\"",
+            "oneOf": [
+                {
+                    "type": "integer",
+                    "minimum": MINIMUM_UPDATE_INTERVAL_SEC,
+                    "maximum": MAXIMUM_UPDATE_INTERVAL_SEC
+                },
+                { "const": "auto" }
+            ],
+            "default": DEFAULT_UPDATE_INTERVAL_SEC
+        }),
+        "adjust_update_interval" => json!({
+            "description": format!(
+                "Shorten a fixed update_interval in transitions too short for {MINIMUM_TRANSITION_STEPS} updates at it"
+            ),
+            "type": "boolean",
+            "default": DEFAULT_ADJUST_UPDATE_INTERVAL
+        }),
+        "winddown" => winddown_schema(),
+        "media" => media_schema(),
+        "gamma_hold" => json!({
+            "description": "Percentage points of the night's gamma drop held back while media plays",
+            "type": "number",
+            "minimum": 0.0,
+            "maximum": MAXIMUM_MEDIA_GAMMA_HOLD,
+            "default": 0.0
+        }),
+        "min_gamma" => json!({
+            "description": "Lowest gamma percentage while media plays",
+            "type": "number",
+            "minimum": MINIMUM_GAMMA,
+            "maximum": MAXIMUM_GAMMA
+        }),
+        "detect_after" => json!({
+            "description": "Seconds without input before an idle inhibitor counts as media playing",
+            "type": "integer",
+            "minimum": MINIMUM_MEDIA_DETECT_AFTER_SEC,
+            "maximum": MAXIMUM_MEDIA_DETECT_AFTER_SEC,
+            "default": DEFAULT_MEDIA_DETECT_AFTER_SEC
+        }),
+        "power_saving" => json!({
+            "description": "Update less often and skip smoothing, \"auto\" follows the power-saver profile",
+            "enum": variants([PowerSaving::Auto, PowerSaving::On, PowerSaving::Off]),
+            "default": DEFAULT_POWER_SAVING.to_string()
+        }),
+        "override_expiry" => json!({
+            "description": "When set --runtime overrides and test mode give way to the schedule",
+            "enum": variants([OverrideExpiry::Never, OverrideExpiry::NextTransition]),
+            "default": DEFAULT_OVERRIDE_EXPIRY.to_string()
+        }),
+        "darkman_export" => json!({
+            "description": "Switch darkman to dark halfway through sunset and to light halfway through sunrise",
+            "type": "boolean",
+            "default": DEFAULT_DARKMAN_EXPORT
+        }),
+        "static_temp" => temp("Color temperature in Kelvin for static mode", None),
+        "static_gamma" => gamma("Gamma in percent for static mode", None),
+        "sunset" => time(
+            "Sunset time as HH:MM:SS (manual modes)",
+            Some(DEFAULT_SUNSET),
+        ),
+        "sunrise" => time(
+            "Sunrise time as HH:MM:SS (manual modes)",
+            Some(DEFAULT_SUNRISE),
+        ),
+        "transition_duration" => transition_minutes(
+            "Minutes a manual transition lasts",
+            Some(DEFAULT_TRANSITION_DURATION_MIN),
+        ),
+        "sunset_duration" => transition_minutes(
+            "Minutes the manual sunset transition lasts, defaults to transition_duration",
+            None,
+        ),
+        "sunrise_duration" => transition_minutes(
+            "Minutes the manual sunrise transition lasts, defaults to transition_duration",
+            None,
+        ),
+        "night_latest_start" => time(
+            "Latest time night may begin as HH:MM:SS, ending the sunset transition by then",
+            None,
+        ),
+        "min_day_duration" => json!({
+            "description": "Minutes of day to keep, moving sunrise earlier when the schedule leaves less",
+            "type": "integer",
+            "minimum": MINIMUM_DAY_DURATION_MIN,
+            "maximum": MAXIMUM_DAY_DURATION_MIN
+        }),
+        "latitude" => json!({
+            "description": "Latitude in degrees for geo mode",
+            "type": "number",
+            "minimum": -90.0,
+            "maximum": 90.0
+        }),
+        "longitude" => json!({
+            "description": "Longitude in degrees for geo mode",
+            "type": "number",
+            "minimum": -180.0,
+            "maximum": 180.0
+        }),
+        "outputs" => json!({
+            "description": "Per-output tables, keyed by connector name",
+            "type": "object",
+            "additionalProperties": { "$ref": "#/$defs/output" }
+        }),
+        "include_outputs" => json!({
+            "description": "Connector names of the only outputs to tint, all when empty",
+            "type": "array",
+            "items": { "type": "string", "pattern": "\\S" },
+            "default": []
+        }),
+        "exclude_outputs" => json!({
+            "description": "Connector names of outputs never to tint",
+            "type": "array",
+            "items": { "type": "string", "pattern": "\\S" },
+            "default": []
+        }),
+        "neutral_apps" => json!({
+            "description": "App ids or window classes whose output stays neutral while focused",
+            "type": "array",
+            "items": { "type": "string", "pattern": "\\S" },
+            "default": []
+        }),
+        "activate_when_outputs" => json!({
+            "description": "In a preset, connector names whose connection activates the preset",
+            "type": "array",
+            "items": { "type": "string", "pattern": "\\S" },
+            "default": []
+        }),
+        "gamma_r" => channel_gamma("red"),
+        "gamma_g" => channel_gamma("green"),
+        "gamma_b" => channel_gamma("blue"),
+        "red_trim" => channel_trim("red"),
+        "green_trim" => channel_trim("green"),
+        "blue_trim" => channel_trim("blue"),
+        "heartbeat_interval" => json!({
+            "description": "Seconds between IPC heartbeat events, 0 to disable",
+            "type": "integer",
+            "minimum": 0,
+            "maximum": MAXIMUM_HEARTBEAT_INTERVAL_SEC,
+            "default": DEFAULT_HEARTBEAT_INTERVAL_SEC
+        }),
+        "values_changed_temp_step" => json!({
+            "description": "Smallest temperature change in Kelvin reported by values_changed",
+            "type": "integer",
+            "minimum": 0,
+            "maximum": MAXIMUM_VALUES_CHANGED_TEMP_STEP,
+            "default": DEFAULT_VALUES_CHANGED_TEMP_STEP
+        }),
+        "values_changed_gamma_step" => json!({
+            "description": "Smallest gamma change in percent reported by values_changed",
+            "type": "number",
+            "minimum": 0.0,
+            "maximum": MAXIMUM_VALUES_CHANGED_GAMMA_STEP,
+            "default": DEFAULT_VALUES_CHANGED_GAMMA_STEP
+        }),
+        "ipc_allowed_gids" => json!({
+            "description": "Primary group ids whose processes may subscribe to the IPC socket besides the user running sunsetr. Commands stay limited to that user",
+            "type": "array",
+            "items": { "type": "integer", "minimum": 0 },
+            "default": []
+        }),
+        "start_hyprsunset" => deprecated("Ignored", "boolean"),
+        "startup_transition" => deprecated("Use smoothing instead", "boolean"),
+        "startup_transition_duration" => deprecated("Use startup_duration instead", "number"),
+
+        // [outputs.<name>]
+        "temp_offset" => json!({
+            "description": "Kelvin added to the scheduled temperature on this output",
+            "type": "integer",
+            "default": 0
+        }),
+        "gamma_offset" => json!({
+            "description": "Percentage points added to the scheduled gamma on this output",
+            "type": "number",
+            "default": 0.0
+        }),
+        "exclude" => json!({
+            "description": "Leave this output untouched",
+            "type": "boolean",
+            "default": false
+        }),
+        "matrix" => json!({
+            "description": "Row-major 3x3 matrix or named preset applied before the tint (Hyprland only)",
+            "oneOf": [
+                {
+                    "type": "array",
+                    "items": { "type": "number", "minimum": 0.0 },
+                    "minItems": 9,
+                    "maxItems": 9
+                },
+                {
+                    "enum": variants([
+                        MatrixPreset::Grayscale,
+                        MatrixPreset::Muted,
+                        MatrixPreset::Sepia,
+                        MatrixPreset::Red,
+                        MatrixPreset::Protanopia,
+                        MatrixPreset::Deuteranopia,
+                        MatrixPreset::Tritanopia,
+                        MatrixPreset::SimulateProtanopia,
+                        MatrixPreset::SimulateDeuteranopia,
+                        MatrixPreset::SimulateTritanopia,
+                    ])
+                }
+            ]
+        }),
+        "start" => time("When the wind-down begins, as HH:MM:SS", None),
+        "bedtime" => time("When the full wind-down drop is reached, as HH:MM:SS", None),
+        "temp_drop" => json!({
+            "description": "Kelvin below night_temp at bedtime",
+            "type": "integer",
+            "minimum": 0,
+            "maximum": MAXIMUM_WINDDOWN_TEMP_DROP,
+            "default": 0
+        }),
+        "gamma_drop" => json!({
+            "description": "Percentage points below night_gamma at bedtime",
+            "type": "number",
+            "minimum": 0.0,
+            "maximum": MAXIMUM_WINDDOWN_GAMMA_DROP,
+            "default": 0.0
+        }),
+        "hdr" => json!({
+            "description": "What this output gets while it is in HDR",
+            "enum": variants([HdrBehavior::Skip, HdrBehavior::Reduced, HdrBehavior::CtmOnly]),
+            "default": HdrBehavior::default().to_string()
+        }),
+        _ => return None,
+    })
+}
+
+fn with_default<T: Into<Value>>(mut schema: Value, default: Option<T>) -> Value {
+    if let Some(default) = default {
+        schema["default"] = default.into();
+    }
+    schema
+}
+
+fn variants<T: ToString, const N: usize>(variants: [T; N]) -> Vec<String> {
+    variants.iter().map(ToString::to_string).collect()
+}
+
+/// The TOML keys of a config struct, in declaration order.
+fn field_names<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    let mut fields = None;
+    // The recorder always fails after capturing the names, so the result is irrelevant
+    let _ = T::deserialize(FieldRecorder {
+        fields: &mut fields,
+    });
+    fields.expect("config sections are structs")
+}
+
+/// A deserializer that only records the field list serde passes to `deserialize_struct`.
+struct FieldRecorder<'a> {
+    fields: &'a mut Option<&'static [&'static str]>,
+}
+
+impl<'de> de::Deserializer<'de> for FieldRecorder<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.fields = Some(fields);
+        Err(de::Error::custom("field names recorded"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn every_config_field_is_described() {
+        let schema = schema();
+        let described = |properties: &Value| -> BTreeSet<String> {
+            properties.as_object().unwrap().keys().cloned().collect()
+        };
+        let fields = |names: &[&str]| -> BTreeSet<String> {
+            names.iter().map(|name| name.to_string()).collect()
+        };
+
+        let top = field_names::<RawConfig>();
+        assert!(top.contains(&"heartbeat_interval"));
+        assert_eq!(described(&schema["properties"]), fields(top));
+        assert_eq!(
+            described(&schema["$defs"]["output"]["properties"]),
+            fields(field_names::<OutputConfig>())
+        );
+        assert_eq!(
+            described(&schema["properties"]["winddown"]["properties"]),
+            fields(field_names::<WinddownConfig>())
+        );
+        assert_eq!(
+            described(&schema["properties"]["media"]["properties"]),
+            fields(field_names::<MediaConfig>())
+        );
+    }
+
+    #[test]
+    fn ranges_and_defaults_follow_the_constants() {
+        let schema = schema();
+        let night_temp = &schema["properties"]["night_temp"];
+        assert_eq!(night_temp["minimum"], MINIMUM_TEMP);
+        assert_eq!(night_temp["maximum"], MAXIMUM_TEMP);
+        assert_eq!(night_temp["default"], DEFAULT_NIGHT_TEMP);
+
+        assert_eq!(schema["properties"]["transition_mode"]["default"], "geo");
+        assert_eq!(
+            schema["properties"]["backend"]["enum"],
+            json!([
+                "auto",
+                "hyprland",
+                "hyprsunset",
+                "wayland",
+                "x11",
+                "drm",
+                "kde",
+                "gnome",
+                "none"
+            ])
+        );
+        assert!(schema["properties"]["static_temp"].get("default").is_none());
+        assert_eq!(
+            schema["properties"]["startup_transition"]["deprecated"],
+            true
+        );
+        assert_eq!(
+            schema["$defs"]["output"]["properties"]["hdr"]["default"],
+            "skip"
+        );
+    }
+
+    #[test]
+    fn enum_values_deserialize() {
+        // Every value the schema offers must be one serde accepts
+        let schema = schema();
+        for (field, value) in [
+            ("backend", &schema["properties"]["backend"]),
+            ("transition_mode", &schema["properties"]["transition_mode"]),
+            ("shutdown_target", &schema["properties"]["shutdown_target"]),
+            ("hyprsunset_mode", &schema["properties"]["hyprsunset_mode"]),
+            ("power_saving", &schema["properties"]["power_saving"]),
+            ("override_expiry", &schema["properties"]["override_expiry"]),
+            ("matrix", &schema["properties"]["matrix"]["oneOf"][1]),
+        ] {
+            for variant in value["enum"].as_array().unwrap() {
+                let toml = format!("{field} = {variant}");
+                toml::from_str::<RawConfig>(&toml).unwrap_or_else(|e| panic!("{toml}: {e}"));
+            }
+        }
+        for variant in schema["$defs"]["output"]["properties"]["hdr"]["enum"]
+            .as_array()
+            .unwrap()
+        {
+            let toml = format!("[outputs.DP-1]\nhdr = {variant}");
+            toml::from_str::<RawConfig>(&toml).unwrap_or_else(|e| panic!("{toml}: {e}"));
+        }
+    }
+}
@@ -2,9 +2,11 @@ use super::validation::validate_config;
 use super::*;
 use crate::common::constants::test_constants::*;
 use crate::common::constants::{
-    MAXIMUM_GAMMA, MAXIMUM_TEMP, MAXIMUM_TRANSITION_DURATION_MIN, MAXIMUM_UPDATE_INTERVAL_SEC,
-    MINIMUM_GAMMA, MINIMUM_TEMP, MINIMUM_TRANSITION_DURATION_MIN, MINIMUM_UPDATE_INTERVAL_SEC,
+    DEFAULT_DAY_GAMMA, DEFAULT_DAY_TEMP, DEFAULT_HEARTBEAT_INTERVAL_SEC, MAXIMUM_GAMMA,
+    MAXIMUM_TEMP, MAXIMUM_TRANSITION_DURATION_MIN, MAXIMUM_UPDATE_INTERVAL_SEC, MINIMUM_GAMMA,
+    MINIMUM_TEMP, MINIMUM_TRANSITION_DURATION_MIN, MINIMUM_UPDATE_INTERVAL_SEC,
 };
+use crate::common::units::values;
 use serial_test::serial;
 use std::fs;
 use tempfile::tempdir;
@@ -24,8 +26,42 @@ fn create_test_config(
     RawConfig {
         backend: Some(Backend::Auto),
         smoothing: Some(false),
+        reduce_motion: None,
         startup_duration: Some(10.0),
         shutdown_duration: Some(10.0),
+        shutdown_target: None,
+        verify_gamma: None,
+        hyprsunset_mode: None,
+        outputs: None,
+        neutral_apps: None,
+        activate_when_outputs: None,
+        gamma_r: None,
+        gamma_g: None,
+        gamma_b: None,
+        red_trim: None,
+        green_trim: None,
+        blue_trim: None,
+        matrix: None,
+        adjust_update_interval: None,
+        heartbeat_interval: None,
+        power_saving: None,
+        override_expiry: None,
+        night_saturation: None,
+        night_brightness: None,
+        day_brightness: None,
+        readonly_config: None,
+        include_outputs: None,
+        exclude_outputs: None,
+        media: None,
+        darkman_export: None,
+        winddown: None,
+        sunset_duration: None,
+        sunrise_duration: None,
+        night_latest_start: None,
+        min_day_duration: None,
+        values_changed_temp_step: None,
+        values_changed_gamma_step: None,
+        ipc_allowed_gids: None,
         startup_transition: Some(false),
         startup_transition_duration: Some(10.0),
         start_hyprsunset: None,
@@ -76,6 +112,134 @@ fn test_config_load_default_creation() {
     assert!(config_path.exists());
 }
 
+#[test]
+#[serial]
+fn test_readonly_config_refuses_writes() {
+    let temp_dir = tempdir().unwrap();
+    let config_dir = temp_dir.path().join("sunsetr");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config_path = config_dir.join("sunsetr.toml");
+
+    let original = std::env::var("XDG_CONFIG_HOME").ok();
+    unsafe {
+        std::env::set_var("XDG_CONFIG_HOME", temp_dir.path());
+    }
+
+    let no_config = crate::config::ensure_config_writable();
+    // Checked without validating the rest of the file
+    fs::write(&config_path, "readonly_config = true\nnight_temp = 99999\n").unwrap();
+    let readonly = crate::config::ensure_config_writable();
+    fs::write(
+        &config_path,
+        "readonly_config = false\nnight_temp = 99999\n",
+    )
+    .unwrap();
+    let writable = crate::config::ensure_config_writable();
+
+    unsafe {
+        match original {
+            Some(val) => std::env::set_var("XDG_CONFIG_HOME", val),
+            None => std::env::remove_var("XDG_CONFIG_HOME"),
+        }
+    }
+
+    assert!(no_config.is_ok());
+    let err = readonly.unwrap_err().to_string();
+    assert!(err.contains("read-only"), "{err}");
+    assert!(writable.is_ok());
+}
+
+#[test]
+#[serial]
+fn test_runtime_overrides_are_merged_over_the_config() {
+    let temp_dir = tempdir().unwrap();
+    let config_dir = temp_dir.path().join("config").join("sunsetr");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("sunsetr.toml"),
+        "transition_mode = \"static\"\nstatic_temp = 6500\nstatic_gamma = 100.0\n",
+    )
+    .unwrap();
+
+    let original_config = std::env::var("XDG_CONFIG_HOME").ok();
+    let original_state = std::env::var("XDG_STATE_HOME").ok();
+    unsafe {
+        std::env::set_var("XDG_CONFIG_HOME", temp_dir.path().join("config"));
+        std::env::set_var("XDG_STATE_HOME", temp_dir.path().join("state"));
+    }
+
+    let mut values = toml::Table::new();
+    values.insert("static_temp".to_string(), toml::Value::Integer(4000));
+    crate::state::overrides::set(values).unwrap();
+    let overridden = Config::load();
+    let cleared = crate::state::overrides::clear().unwrap();
+    let restored = Config::load();
+
+    unsafe {
+        match original_config {
+            Some(val) => std::env::set_var("XDG_CONFIG_HOME", val),
+            None => std::env::remove_var("XDG_CONFIG_HOME"),
+        }
+        match original_state {
+            Some(val) => std::env::set_var("XDG_STATE_HOME", val),
+            None => std::env::remove_var("XDG_STATE_HOME"),
+        }
+    }
+
+    let overridden = overridden.unwrap();
+    assert_eq!(overridden.static_temp, Some(4000));
+    assert_eq!(overridden.static_gamma, Some(100.0));
+    assert!(cleared);
+    assert_eq!(restored.unwrap().static_temp, Some(6500));
+}
+
+#[test]
+#[serial]
+fn test_presentation_mode_is_attached_to_every_load() {
+    let temp_dir = tempdir().unwrap();
+    let config_dir = temp_dir.path().join("config").join("sunsetr");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("sunsetr.toml"),
+        "transition_mode = \"static\"\nstatic_temp = 4000\nstatic_gamma = 100.0\n",
+    )
+    .unwrap();
+
+    let original_config = std::env::var("XDG_CONFIG_HOME").ok();
+    let original_state = std::env::var("XDG_STATE_HOME").ok();
+    unsafe {
+        std::env::set_var("XDG_CONFIG_HOME", temp_dir.path().join("config"));
+        std::env::set_var("XDG_STATE_HOME", temp_dir.path().join("state"));
+    }
+
+    let before = Config::load();
+    let enabled = crate::state::presentation::enable(vec!["DP-1".to_string()]).unwrap();
+    let presenting = Config::load();
+    let disabled = crate::state::presentation::disable().unwrap();
+    let disabled_again = crate::state::presentation::disable().unwrap();
+    let after = Config::load();
+
+    unsafe {
+        match original_config {
+            Some(val) => std::env::set_var("XDG_CONFIG_HOME", val),
+            None => std::env::remove_var("XDG_CONFIG_HOME"),
+        }
+        match original_state {
+            Some(val) => std::env::set_var("XDG_STATE_HOME", val),
+            None => std::env::remove_var("XDG_STATE_HOME"),
+        }
+    }
+
+    assert_eq!(before.unwrap().presentation, None);
+    let presentation = presenting.unwrap().presentation.unwrap();
+    assert_eq!(presentation, enabled);
+    assert!(presentation.covers("DP-1"));
+    assert!(!presentation.covers("HDMI-A-1"));
+    assert!(disabled);
+    assert!(!disabled_again);
+    assert_eq!(after.unwrap().presentation, None);
+}
+
 #[test]
 fn test_config_validation_basic() {
     let config = create_test_config(
@@ -92,6 +256,14 @@ fn test_config_validation_basic() {
     assert!(validate_config(&config).is_ok());
 }
 
+#[test]
+fn safe_mode_config_is_neutral_and_static() {
+    let config = Config::safe_mode();
+    assert_eq!(config.transition_mode, TransitionMode::Static);
+    assert_eq!(config.static_temp, Some(DEFAULT_DAY_TEMP));
+    assert_eq!(config.static_gamma, Some(DEFAULT_DAY_GAMMA));
+}
+
 #[test]
 fn transition_mode_display_serde_and_fromstr_agree() {
     #[derive(serde::Deserialize)]
@@ -104,6 +276,8 @@ fn transition_mode_display_serde_and_fromstr_agree() {
         (TransitionMode::StartAt, "start_at"),
         (TransitionMode::Center, "center"),
         (TransitionMode::Static, "static"),
+        (TransitionMode::External, "external"),
+        (TransitionMode::Darkman, "darkman"),
     ] {
         assert_eq!(mode.to_string(), token);
         assert_eq!(token.parse::<TransitionMode>().unwrap(), mode);
@@ -692,6 +866,644 @@ day_temp = 6500
     assert_eq!(config.shutdown_duration, 2.0);
 }
 
+#[test]
+fn test_shutdown_target_defaults_to_day() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    fs::write(
+        &config_path,
+        r#"
+transition_mode = "static"
+static_temp = 4000
+static_gamma = 90
+"#,
+    )
+    .unwrap();
+    let config = Config::load_from_path(&config_path).unwrap();
+
+    assert_eq!(config.shutdown_target, ShutdownTarget::Day);
+    assert_eq!(
+        config.shutdown_values(values(4000, 90.0)),
+        values(DEFAULT_DAY_TEMP, DEFAULT_DAY_GAMMA)
+    );
+}
+
+#[test]
+fn test_shutdown_target_values() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    for (target, expected) in [
+        ("reset", (6500, 100.0)),
+        ("current", (3300, 85.0)),
+        ("day", (5500, 95.0)),
+    ] {
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+transition_mode = "finish_by"
+day_temp = 5500
+day_gamma = 95
+shutdown_target = "{target}"
+"#
+            ),
+        )
+        .unwrap();
+        let config = Config::load_from_path(&config_path).unwrap();
+
+        assert_eq!(
+            config.shutdown_values(values(3300, 85.0)),
+            values(expected.0, expected.1),
+            "{target}"
+        );
+    }
+}
+
+#[test]
+fn test_shutdown_target_rejects_unknown_value() {
+    let result: Result<RawConfig, _> = toml::from_str(r#"shutdown_target = "night""#);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_hyprsunset_mode_values() {
+    let raw: RawConfig = toml::from_str("").unwrap();
+    assert_eq!(raw.hyprsunset_mode, None);
+    let raw: RawConfig = toml::from_str(r#"hyprsunset_mode = "attach""#).unwrap();
+    assert_eq!(raw.hyprsunset_mode, Some(HyprsunsetMode::Attach));
+    assert!(toml::from_str::<RawConfig>(r#"hyprsunset_mode = "external""#).is_err());
+}
+
+#[test]
+fn test_output_tables_are_loaded() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    fs::write(
+        &config_path,
+        r#"
+transition_mode = "static"
+static_temp = 4000
+static_gamma = 90
+
+[outputs.DP-1]
+temp_offset = -300
+gamma_offset = -5
+
+[outputs.HDMI-A-1]
+exclude = true
+matrix = [1, 0, 0, 0, 0.8, 0.2, 0, 0, 1]
+"#,
+    )
+    .unwrap();
+    let config = Config::load_from_path(&config_path).unwrap();
+
+    assert_eq!(config.outputs.len(), 2);
+    assert_eq!(config.outputs["DP-1"].adjust(4000, 90.0), (3700, 85.0));
+    assert!(config.outputs["HDMI-A-1"].exclude);
+    assert_eq!(
+        config.outputs["HDMI-A-1"].matrix,
+        Some(ColorMatrix::Custom([
+            1.0, 0.0, 0.0, 0.0, 0.8, 0.2, 0.0, 0.0, 1.0
+        ]))
+    );
+}
+
+#[test]
+fn test_matrix_takes_presets_and_entries() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    for (line, expected) in [
+        ("", Some(None)),
+        (
+            "matrix = \"sepia\"",
+            Some(Some(ColorMatrix::Preset(MatrixPreset::Sepia))),
+        ),
+        (
+            "matrix = [1, 0, 0, 0, 1, 0, 0.1, 0, 0.9]",
+            Some(Some(ColorMatrix::Custom([
+                1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.1, 0.0, 0.9,
+            ]))),
+        ),
+        (
+            "matrix = \"simulate-deuteranopia\"",
+            Some(Some(ColorMatrix::Preset(
+                MatrixPreset::SimulateDeuteranopia,
+            ))),
+        ),
+        ("matrix = \"vibrant\"", None),
+        ("matrix = [1, 0, 0, 0, 1, 0, 0, 0]", None),
+        ("matrix = [1, 0, 0, 0, 1, 0, 0, -0.5, 1]", None),
+    ] {
+        fs::write(
+            &config_path,
+            format!(
+                "transition_mode = \"static\"\nstatic_temp = 4000\nstatic_gamma = 90\n{line}\n"
+            ),
+        )
+        .unwrap();
+        let config = Config::load_from_path(&config_path).ok();
+
+        assert_eq!(config.map(|c| c.matrix), expected, "{line}");
+    }
+
+    // White stays within range and no entry is negative
+    for preset in [
+        MatrixPreset::Grayscale,
+        MatrixPreset::Muted,
+        MatrixPreset::Sepia,
+        MatrixPreset::Red,
+        MatrixPreset::Protanopia,
+        MatrixPreset::Deuteranopia,
+        MatrixPreset::Tritanopia,
+        MatrixPreset::SimulateProtanopia,
+        MatrixPreset::SimulateDeuteranopia,
+        MatrixPreset::SimulateTritanopia,
+    ] {
+        let values = preset.values();
+        assert!(values.iter().all(|v| *v >= 0.0), "{preset}");
+        assert!(
+            values
+                .chunks(3)
+                .all(|row| row.iter().sum::<f64>() <= 1.0 + 1e-9),
+            "{preset}"
+        );
+    }
+
+    // Daltonization leaves the channels the shift doesn't touch alone
+    let protanopia = MatrixPreset::Protanopia.values();
+    assert_eq!(protanopia[..3], [1.0, 0.0, 0.0]);
+    let tritanopia = MatrixPreset::Tritanopia.values();
+    assert_eq!(tritanopia[6..], [0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn test_output_tables_reject_invalid_entries() {
+    let unknown: Result<RawConfig, _> = toml::from_str("[outputs.DP-1]\ntemp = 3000");
+    assert!(unknown.is_err());
+
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+    fs::write(
+        &config_path,
+        r#"
+transition_mode = "static"
+static_temp = 4000
+static_gamma = 90
+
+[outputs.DP-1]
+matrix = [1, 0, 0, 0, 1, 0, 0, -0.5, 1]
+"#,
+    )
+    .unwrap();
+    assert!(Config::load_from_path(&config_path).is_err());
+}
+
+#[test]
+fn test_channel_gamma_defaults_and_limits() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    for (line, expected) in [
+        ("", Some((1.0, 1.0, 1.0))),
+        ("gamma_r = 0.8\ngamma_b = 1.2", Some((0.8, 1.0, 1.2))),
+        ("gamma_g = 0.1\ngamma_b = 10.0", Some((1.0, 0.1, 10.0))),
+        ("gamma_r = 0.05", None),
+        ("gamma_g = 10.5", None),
+        ("gamma_b = -1.0", None),
+        ("gamma_b = nan", None),
+    ] {
+        fs::write(
+            &config_path,
+            format!(
+                "transition_mode = \"static\"\nstatic_temp = 4000\nstatic_gamma = 90\n{line}\n"
+            ),
+        )
+        .unwrap();
+        let config = Config::load_from_path(&config_path).ok();
+
+        assert_eq!(
+            config.map(|c| (c.gamma_r, c.gamma_g, c.gamma_b)),
+            expected,
+            "{line}"
+        );
+    }
+}
+
+#[test]
+fn test_night_saturation_defaults_and_limits() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    for (line, expected) in [
+        ("", Some(100.0)),
+        ("night_saturation = 60", Some(60.0)),
+        ("night_saturation = 0.0", Some(0.0)),
+        ("night_saturation = 120", None),
+        ("night_saturation = -5", None),
+        ("night_saturation = nan", None),
+    ] {
+        fs::write(
+            &config_path,
+            format!("transition_mode = \"finish_by\"\n{line}\n"),
+        )
+        .unwrap();
+        let config = Config::load_from_path(&config_path).ok();
+
+        assert_eq!(config.map(|c| c.night_saturation), expected, "{line}");
+    }
+}
+
+#[test]
+fn test_brightness_defaults_and_limits() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    for (line, expected) in [
+        ("", Some((100.0, 100.0))),
+        ("night_brightness = 60", Some((60.0, 100.0))),
+        (
+            "night_brightness = 40.5\nday_brightness = 90",
+            Some((40.5, 90.0)),
+        ),
+        ("night_brightness = 5", None),
+        ("day_brightness = 120", None),
+        ("day_brightness = nan", None),
+    ] {
+        fs::write(
+            &config_path,
+            format!("transition_mode = \"finish_by\"\n{line}\n"),
+        )
+        .unwrap();
+        let config = Config::load_from_path(&config_path).ok();
+
+        assert_eq!(
+            config.map(|c| (c.night_brightness, c.day_brightness)),
+            expected,
+            "{line}"
+        );
+    }
+}
+
+#[test]
+fn test_channel_trim_defaults_and_limits() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    for (line, expected) in [
+        ("", Some((1.0, 1.0, 1.0))),
+        ("green_trim = 0.9", Some((1.0, 0.9, 1.0))),
+        ("red_trim = 0.5\nblue_trim = 1.5", Some((0.5, 1.0, 1.5))),
+        ("red_trim = 0.4", None),
+        ("blue_trim = 1.6", None),
+        ("[outputs.DP-1]\ngreen_trim = 2.0", None),
+    ] {
+        fs::write(
+            &config_path,
+            format!(
+                "transition_mode = \"static\"\nstatic_temp = 4000\nstatic_gamma = 90\n{line}\n"
+            ),
+        )
+        .unwrap();
+        let config = Config::load_from_path(&config_path).ok();
+
+        assert_eq!(config.map(|c| c.trim()), expected, "{line}");
+    }
+}
+
+#[test]
+fn test_values_changed_steps_defaults_and_limits() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    for (line, expected) in [
+        (
+            "",
+            Some((
+                DEFAULT_VALUES_CHANGED_TEMP_STEP,
+                DEFAULT_VALUES_CHANGED_GAMMA_STEP,
+            )),
+        ),
+        (
+            "values_changed_temp_step = 0\nvalues_changed_gamma_step = 0.0",
+            Some((0, 0.0)),
+        ),
+        (
+            "values_changed_temp_step = 1000\nvalues_changed_gamma_step = 10.0",
+            Some((1000, 10.0)),
+        ),
+        ("values_changed_temp_step = 1001", None),
+        ("values_changed_gamma_step = 10.5", None),
+        ("values_changed_gamma_step = -1.0", None),
+    ] {
+        fs::write(
+            &config_path,
+            format!(
+                "transition_mode = \"static\"\nstatic_temp = 4000\nstatic_gamma = 90\n{line}\n"
+            ),
+        )
+        .unwrap();
+        let config = Config::load_from_path(&config_path).ok();
+
+        assert_eq!(
+            config.map(|c| (c.values_changed_temp_step, c.values_changed_gamma_step)),
+            expected,
+            "{line}"
+        );
+    }
+}
+
+#[test]
+fn test_heartbeat_interval_defaults_and_limits() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    for (line, expected) in [
+        ("", Some(DEFAULT_HEARTBEAT_INTERVAL_SEC)),
+        ("heartbeat_interval = 0", Some(0)),
+        ("heartbeat_interval = 3600", Some(3600)),
+        ("heartbeat_interval = 3601", None),
+    ] {
+        fs::write(
+            &config_path,
+            format!(
+                "transition_mode = \"static\"\nstatic_temp = 4000\nstatic_gamma = 90\n{line}\n"
+            ),
+        )
+        .unwrap();
+        let config = Config::load_from_path(&config_path).ok();
+
+        assert_eq!(config.map(|c| c.heartbeat_interval), expected, "{line}");
+    }
+}
+
+#[test]
+fn test_direction_durations_fall_back_to_transition_duration() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+    let load = |lines: &str| {
+        fs::write(
+            &config_path,
+            format!(
+                "transition_mode = \"finish_by\"\nsunset = \"19:00:00\"\nsunrise = \"06:00:00\"\n{lines}\n"
+            ),
+        )
+        .unwrap();
+        Config::load_from_path(&config_path)
+    };
+
+    let config = load("transition_duration = 30\nsunrise_duration = 15").unwrap();
+    assert_eq!(config.sunset_duration_mins(), 30);
+    assert_eq!(config.sunrise_duration_mins(), 15);
+
+    let config = load("sunset_duration = 60").unwrap();
+    assert_eq!(config.sunset_duration_mins(), 60);
+    assert_eq!(
+        config.sunrise_duration_mins(),
+        DEFAULT_TRANSITION_DURATION_MIN
+    );
+
+    let err = load("sunset_duration = 121").unwrap_err();
+    assert!(format!("{err:#}").contains("sunset_duration (121 minutes)"));
+
+    // Only the direction that is too short for the interval is named
+    let err =
+        load("update_interval = 600\nsunset_duration = 60\nsunrise_duration = 5").unwrap_err();
+    assert!(
+        format!("{err:#}").contains("longer than sunrise_duration"),
+        "{err:#}"
+    );
+}
+
+#[test]
+fn test_direction_durations_are_checked_separately_for_overlaps() {
+    // Two hours of night fit a 60 minute evening and a 30 minute morning, not two 75s
+    let mut config = create_test_config(
+        "22:00:00",
+        "00:00:00",
+        Some(75),
+        Some(TEST_STANDARD_UPDATE_INTERVAL),
+        TransitionMode::StartAt,
+        Some(TEST_STANDARD_NIGHT_TEMP),
+        Some(TEST_STANDARD_DAY_TEMP),
+        Some(TEST_STANDARD_NIGHT_GAMMA),
+        Some(TEST_STANDARD_DAY_GAMMA),
+    );
+    config.sunset_duration = Some(60);
+    config.sunrise_duration = Some(30);
+    assert!(validate_config(&config).is_ok());
+
+    config.sunset_duration = Some(120);
+    let err = validate_config(&config).unwrap_err().to_string();
+    assert!(
+        err.contains("sunset_duration (120 min) and sunrise_duration (30 min)"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_night_latest_start_cannot_reach_sunrise() {
+    let mut config = create_test_config(
+        "23:00:00",
+        "20:00:00",
+        Some(30),
+        Some(TEST_STANDARD_UPDATE_INTERVAL),
+        TransitionMode::FinishBy,
+        Some(TEST_STANDARD_NIGHT_TEMP),
+        Some(TEST_STANDARD_DAY_TEMP),
+        Some(TEST_STANDARD_NIGHT_GAMMA),
+        Some(TEST_STANDARD_DAY_GAMMA),
+    );
+    config.night_latest_start = Some("22:45:00".to_string());
+    assert!(validate_config(&config).is_ok());
+
+    // Pulls the 22:30 → 23:00 sunset back to 19:15 → 19:45, inside the sunrise transition
+    config.night_latest_start = Some("19:45:00".to_string());
+    let err = validate_config(&config).unwrap_err().to_string();
+    assert!(err.contains("19:15:00 → 19:45:00"), "{err}");
+
+    config.night_latest_start = Some("late".to_string());
+    assert!(validate_config(&config).is_err());
+
+    // Presets switching to static can inherit it
+    config.transition_mode = TransitionMode::Static;
+    config.static_temp = Some(TEST_STANDARD_NIGHT_TEMP);
+    config.static_gamma = Some(TEST_STANDARD_NIGHT_GAMMA);
+    config.night_latest_start = Some("19:45:00".to_string());
+    assert!(validate_config(&config).is_ok());
+}
+
+#[test]
+fn test_min_day_duration_range() {
+    let mut config = create_test_config(
+        "19:00:00",
+        "16:00:00",
+        Some(30),
+        Some(TEST_STANDARD_UPDATE_INTERVAL),
+        TransitionMode::FinishBy,
+        Some(TEST_STANDARD_NIGHT_TEMP),
+        Some(TEST_STANDARD_DAY_TEMP),
+        Some(TEST_STANDARD_NIGHT_GAMMA),
+        Some(TEST_STANDARD_DAY_GAMMA),
+    );
+    // A short day only warns
+    assert!(validate_config(&config).is_ok());
+
+    config.min_day_duration = Some(MAXIMUM_DAY_DURATION_MIN);
+    assert!(validate_config(&config).is_ok());
+
+    config.min_day_duration = Some(MINIMUM_DAY_DURATION_MIN - 1);
+    let err = validate_config(&config).unwrap_err().to_string();
+    assert!(err.contains("min_day_duration (59 minutes)"), "{err}");
+}
+
+#[test]
+fn test_winddown_table() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+    let load = |winddown: &str| {
+        fs::write(
+            &config_path,
+            format!("transition_mode = \"finish_by\"\n[winddown]\n{winddown}\n"),
+        )
+        .unwrap();
+        Config::load_from_path(&config_path)
+    };
+
+    let config = load("start = \"22:00:00\"\nbedtime = \"23:30:00\"\ntemp_drop = 500").unwrap();
+    let winddown = config.winddown.unwrap();
+    assert_eq!(winddown.bedtime, "23:30:00");
+    assert_eq!((winddown.temp_drop, winddown.gamma_drop), (500, 0.0));
+
+    let err = load("start = \"22:00:00\"\nbedtime = \"22:00:00\"").unwrap_err();
+    assert!(
+        format!("{err:#}").contains("cannot be the same time"),
+        "{err:#}"
+    );
+    let err = load("start = \"22:00\"\nbedtime = \"23:00:00\"").unwrap_err();
+    assert!(format!("{err:#}").contains("winddown start"), "{err:#}");
+    let err = load("start = \"22:00:00\"\nbedtime = \"23:00:00\"\ngamma_drop = 60.0").unwrap_err();
+    assert!(format!("{err:#}").contains("gamma_drop (60%)"), "{err:#}");
+    assert!(load("start = \"22:00:00\"\nbedtime = \"23:00:00\"\ndrop = 5").is_err());
+}
+
+#[test]
+fn test_builder_defaults_match_a_resolved_config() {
+    let resolved = toml::from_str::<RawConfig>("transition_mode = \"finish_by\"")
+        .unwrap()
+        .resolve()
+        .unwrap();
+    assert_eq!(Config::builder().build(), resolved);
+}
+
+#[test]
+fn test_media_table() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+    let load = |media: &str| {
+        fs::write(
+            &config_path,
+            format!("transition_mode = \"finish_by\"\n[media]\n{media}\n"),
+        )
+        .unwrap();
+        Config::load_from_path(&config_path)
+    };
+
+    let media = load("min_gamma = 95.0").unwrap().media.unwrap();
+    assert_eq!(media.gamma_hold, 0.0);
+    assert_eq!(media.min_gamma, Some(95.0));
+    assert_eq!(media.detect_after, DEFAULT_MEDIA_DETECT_AFTER_SEC);
+
+    let err = load("detect_after = 120").unwrap_err();
+    assert!(format!("{err:#}").contains("changes nothing"), "{err:#}");
+    let err = load("gamma_hold = 10.0\ndetect_after = 5").unwrap_err();
+    assert!(format!("{err:#}").contains("detect_after (5)"), "{err:#}");
+    assert!(load("gamma_hold = 10.0\nhold = 5").is_err());
+}
+
+#[test]
+fn output_lists_filter_outputs() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+    let load = |lists: &str| {
+        fs::write(
+            &config_path,
+            format!("transition_mode = \"finish_by\"\n{lists}\n"),
+        )
+        .unwrap();
+        Config::load_from_path(&config_path)
+    };
+
+    // An `exclude = true` table wins over include_outputs
+    let filter = load("include_outputs = [\"DP-1\", \"DP-2\"]\n[outputs.DP-1]\nexclude = true")
+        .unwrap()
+        .output_filter();
+    assert!(!filter.allows("DP-1"));
+    assert!(filter.allows("DP-2"));
+    assert!(!filter.allows("HDMI-A-1"));
+
+    let filter = load("exclude_outputs = [\"DP-3\"]")
+        .unwrap()
+        .output_filter();
+    assert!(filter.allows("DP-1"));
+    assert!(!filter.allows("DP-3"));
+    assert!(load("").unwrap().output_filter().allows_all());
+
+    let err = load("include_outputs = [\"DP-1\"]\nexclude_outputs = [\"DP-1\"]").unwrap_err();
+    assert!(format!("{err:#}").contains("'DP-1' is in both"), "{err:#}");
+    assert!(load("exclude_outputs = [\" \"]").is_err());
+}
+
+#[test]
+fn darkman_export_needs_a_night_to_export() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+    let load = |mode: &str| {
+        fs::write(
+            &config_path,
+            format!("transition_mode = \"{mode}\"\ndarkman_export = true\n"),
+        )
+        .unwrap();
+        Config::load_from_path(&config_path)
+    };
+
+    assert!(load("finish_by").unwrap().darkman_export);
+    for mode in ["static", "external", "darkman"] {
+        let err = load(mode).unwrap_err();
+        assert!(format!("{err:#}").contains("darkman_export"), "{err:#}");
+    }
+}
+
+#[test]
+fn stacked_presets_override_only_the_fields_they_set() {
+    let temp_dir = tempdir().unwrap();
+    let base = temp_dir.path().join("sunsetr.toml");
+    let movie = temp_dir.path().join("movie.toml");
+    let dim = temp_dir.path().join("dim.toml");
+    fs::write(
+        &base,
+        "transition_mode = \"static\"\nstatic_temp = 4000\nstatic_gamma = 90\nsmoothing = false\n",
+    )
+    .unwrap();
+    fs::write(&movie, "static_gamma = 70\n").unwrap();
+    fs::write(&dim, "static_gamma = 60\nstatic_temp = 3500\n").unwrap();
+
+    let config = Config::load_layered(&base, std::slice::from_ref(&movie)).unwrap();
+    assert_eq!(config.static_temp, Some(4000));
+    assert_eq!(config.static_gamma, Some(70.0));
+    assert!(!config.smoothing);
+
+    let config = Config::load_layered(&base, &[movie, dim]).unwrap();
+    assert_eq!(config.static_temp, Some(3500));
+    assert_eq!(config.static_gamma, Some(60.0));
+
+    fs::write(temp_dir.path().join("bad.toml"), "static_gamma = 500\n").unwrap();
+    assert!(Config::load_layered(&base, &[temp_dir.path().join("bad.toml")]).is_err());
+}
+
 #[test]
 fn test_extreme_latitude_capped_on_load() {
     for (configured, capped) in [(85.0, 65.0), (-75.0, -65.0)] {
@@ -751,6 +1563,70 @@ longitude = -0.1278
     assert_eq!(config.longitude, Some(-0.1278));
 }
 
+#[test]
+fn test_geo_mode_without_coordinates_is_recognizable() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+    fs::write(&config_path, "transition_mode = \"geo\"\n").unwrap();
+
+    // Startup waits for geo.toml on this error rather than failing
+    let err = Config::load_from_path(&config_path).unwrap_err();
+    assert!(
+        err.downcast_ref::<crate::config::loading::MissingCoordinates>()
+            .is_some()
+    );
+
+    fs::write(
+        temp_dir.path().join("geo.toml"),
+        "latitude = 51.5074\nlongitude = -0.1278\n",
+    )
+    .unwrap();
+    assert!(Config::load_from_path(&config_path).is_ok());
+}
+
+#[test]
+fn test_placeholder_coordinates_fall_back_to_manual_times() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("sunsetr.toml");
+
+    for (latitude, longitude) in [(0.0, 0.0), (42.5, 42.5)] {
+        fs::write(
+            &config_path,
+            format!(
+                "transition_mode = \"geo\"\nlatitude = {latitude:?}\nlongitude = {longitude:?}\n"
+            ),
+        )
+        .unwrap();
+        let config = Config::load_from_path(&config_path).unwrap();
+        assert_eq!(config.transition_mode, TransitionMode::FinishBy);
+        assert_eq!(config.sunset.as_deref(), Some(DEFAULT_SUNSET));
+        let placeholder = config.placeholder_coordinates.unwrap();
+        assert_eq!(
+            (placeholder.latitude, placeholder.longitude),
+            (latitude, longitude)
+        );
+    }
+
+    // Real places near the sentinels are kept, as are placeholders outside geo mode
+    fs::write(
+        &config_path,
+        "transition_mode = \"geo\"\nlatitude = 0.0\nlongitude = 32.58\n",
+    )
+    .unwrap();
+    let config = Config::load_from_path(&config_path).unwrap();
+    assert_eq!(config.transition_mode, TransitionMode::Geo);
+    assert!(config.placeholder_coordinates.is_none());
+
+    fs::write(
+        &config_path,
+        "transition_mode = \"center\"\nlatitude = 0.0\nlongitude = 0.0\n",
+    )
+    .unwrap();
+    let config = Config::load_from_path(&config_path).unwrap();
+    assert_eq!(config.transition_mode, TransitionMode::Center);
+    assert!(config.placeholder_coordinates.is_none());
+}
+
 #[test]
 fn test_geo_toml_overrides_main_config() {
     let temp_dir = tempdir().unwrap();
@@ -1140,8 +2016,42 @@ mod property_tests {
             RawConfig {
                 backend: Some(self.backend),
                 smoothing: self.smoothing,
+                reduce_motion: None,
                 startup_duration: self.startup_duration,
                 shutdown_duration: self.shutdown_duration,
+                shutdown_target: None,
+                verify_gamma: None,
+                hyprsunset_mode: None,
+                outputs: None,
+                neutral_apps: None,
+                activate_when_outputs: None,
+                gamma_r: None,
+                gamma_g: None,
+                gamma_b: None,
+                red_trim: None,
+                green_trim: None,
+                blue_trim: None,
+                matrix: None,
+                adjust_update_interval: None,
+                heartbeat_interval: None,
+                power_saving: None,
+                override_expiry: None,
+                night_saturation: None,
+                night_brightness: None,
+                day_brightness: None,
+                readonly_config: None,
+                include_outputs: None,
+                exclude_outputs: None,
+                media: None,
+                darkman_export: None,
+                winddown: None,
+                sunset_duration: None,
+                sunrise_duration: None,
+                night_latest_start: None,
+                min_day_duration: None,
+                values_changed_temp_step: None,
+                values_changed_gamma_step: None,
+                ipc_allowed_gids: None,
                 startup_transition: self.smoothing, // For backwards compatibility
                 startup_transition_duration: self.startup_duration,
                 start_hyprsunset: None,
@@ -1211,6 +2121,11 @@ mod property_tests {
                 Just(Backend::Hyprland),
                 Just(Backend::Hyprsunset),
                 Just(Backend::Wayland),
+                Just(Backend::X11),
+                Just(Backend::Drm),
+                Just(Backend::Kde),
+                Just(Backend::Gnome),
+                Just(Backend::None),
             ],
             smoothing in any::<bool>(),
         ) {
@@ -1623,18 +2538,23 @@ mod property_tests {
                 TransitionMode::Manual("center".to_string()),
             ];
 
-            // All possible backend combinations (4 combinations)
+            // All possible backend combinations (9 combinations)
             let backends = [
                 Backend::Auto,
                 Backend::Hyprland,
                 Backend::Hyprsunset,
                 Backend::Wayland,
+                Backend::X11,
+                Backend::Drm,
+                Backend::Kde,
+                Backend::Gnome,
+                Backend::None,
             ];
 
             // All possible smoothing combinations (2 combinations)
             let smoothing_options = [true, false];
 
-            // Test all combinations: 5 × 4 × 2 = 40 total combinations
+            // Test all combinations: 5 × 9 × 2 = 90 total combinations
             for mode in &transition_modes {
                 for backend in &backends {
                     for smoothing in &smoothing_options {
@@ -1657,7 +2577,7 @@ mod property_tests {
                 }
             }
 
-            println!("✅ All 40 mode/backend/smoothing combinations tested successfully!");
+            println!("✅ All 60 mode/backend/smoothing combinations tested successfully!");
         }
 
         #[test]
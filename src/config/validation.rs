@@ -5,8 +5,9 @@ use anyhow::{Context, Result};
 use chrono::{NaiveTime, Timelike};
 use std::time::Duration;
 
-use super::{RawConfig, TransitionMode};
+use super::{ColorMatrix, MediaConfig, RawConfig, TransitionMode, WinddownConfig};
 use crate::common::constants::*;
+use crate::core::period::calculations::{night_start_overrun, pull_sunset_back};
 
 fn validate_basic_ranges(config: &RawConfig) -> Result<()> {
     if let Some(temp) = config.night_temp
@@ -53,30 +54,81 @@ fn validate_basic_ranges(config: &RawConfig) -> Result<()> {
         );
     }
 
-    if let Some(duration_minutes) = config.transition_duration
-        && !(MINIMUM_TRANSITION_DURATION_MIN..=MAXIMUM_TRANSITION_DURATION_MIN)
-            .contains(&duration_minutes)
+    if let Some(saturation) = config.night_saturation
+        && !(MINIMUM_SATURATION..=MAXIMUM_SATURATION).contains(&saturation)
     {
         anyhow::bail!(
-            "transition_duration ({} minutes) must be between {} and {} minutes",
-            duration_minutes,
-            MINIMUM_TRANSITION_DURATION_MIN,
-            MAXIMUM_TRANSITION_DURATION_MIN
+            "night_saturation ({}%) must be between {}% and {}%",
+            saturation,
+            MINIMUM_SATURATION,
+            MAXIMUM_SATURATION
+        );
+    }
+
+    for (name, brightness) in [
+        ("night_brightness", config.night_brightness),
+        ("day_brightness", config.day_brightness),
+    ] {
+        if let Some(brightness) = brightness
+            && !(MINIMUM_BRIGHTNESS..=MAXIMUM_BRIGHTNESS).contains(&brightness)
+        {
+            anyhow::bail!(
+                "{} ({}%) must be between {}% and {}%",
+                name,
+                brightness,
+                MINIMUM_BRIGHTNESS,
+                MAXIMUM_BRIGHTNESS
+            );
+        }
+    }
+
+    for (name, duration_minutes) in [
+        ("transition_duration", config.transition_duration),
+        ("sunset_duration", config.sunset_duration),
+        ("sunrise_duration", config.sunrise_duration),
+    ] {
+        if let Some(duration_minutes) = duration_minutes
+            && !(MINIMUM_TRANSITION_DURATION_MIN..=MAXIMUM_TRANSITION_DURATION_MIN)
+                .contains(&duration_minutes)
+        {
+            anyhow::bail!(
+                "{} ({} minutes) must be between {} and {} minutes",
+                name,
+                duration_minutes,
+                MINIMUM_TRANSITION_DURATION_MIN,
+                MAXIMUM_TRANSITION_DURATION_MIN
+            );
+        }
+    }
+
+    if let Some(minutes) = config.min_day_duration
+        && !(MINIMUM_DAY_DURATION_MIN..=MAXIMUM_DAY_DURATION_MIN).contains(&minutes)
+    {
+        anyhow::bail!(
+            "min_day_duration ({} minutes) must be between {} and {} minutes",
+            minutes,
+            MINIMUM_DAY_DURATION_MIN,
+            MAXIMUM_DAY_DURATION_MIN
         );
     }
 
     // Must run before the range check below to match test expectations.
     if let Some(crate::config::UpdateInterval::Fixed(update_interval_secs)) = config.update_interval
     {
-        if let Some(transition_duration_mins) = config.transition_duration {
+        for (name, duration_minutes) in transition_durations(config) {
+            let Some(transition_duration_mins) = duration_minutes else {
+                continue;
+            };
             let transition_duration_secs = transition_duration_mins * 60;
             if update_interval_secs > transition_duration_secs {
                 anyhow::bail!(
-                    "update_interval ({} seconds) is longer than transition_duration ({} seconds). \
+                    "update_interval ({} seconds) is longer than {} ({} seconds). \
                     update_interval should be shorter to allow smooth transitions. \
-                    Reduce update_interval or increase transition_duration.",
+                    Reduce update_interval or increase {}.",
                     update_interval_secs,
-                    transition_duration_secs
+                    name,
+                    transition_duration_secs,
+                    name
                 );
             }
         }
@@ -134,14 +186,243 @@ fn validate_basic_ranges(config: &RawConfig) -> Result<()> {
         );
     }
 
+    if let Some(ColorMatrix::Custom(matrix)) = config.matrix
+        && matrix.iter().any(|v| !v.is_finite() || *v < 0.0)
+    {
+        anyhow::bail!("matrix entries must be finite and non-negative");
+    }
+
+    for (name, output) in config.outputs.iter().flatten() {
+        if !output.gamma_offset.is_finite() {
+            anyhow::bail!("outputs.{name}.gamma_offset must be a finite number");
+        }
+        if let Some(ColorMatrix::Custom(matrix)) = output.matrix
+            && matrix.iter().any(|v| !v.is_finite() || *v < 0.0)
+        {
+            anyhow::bail!("outputs.{name}.matrix entries must be finite and non-negative");
+        }
+    }
+
+    if config
+        .neutral_apps
+        .iter()
+        .flatten()
+        .any(|app| app.trim().is_empty())
+    {
+        anyhow::bail!("neutral_apps entries must not be empty");
+    }
+    if config
+        .activate_when_outputs
+        .iter()
+        .flatten()
+        .any(|output| output.trim().is_empty())
+    {
+        anyhow::bail!("activate_when_outputs entries must not be empty");
+    }
+
+    validate_output_lists(config)?;
+
+    if let Some(ref winddown) = config.winddown {
+        validate_winddown(winddown)?;
+    }
+    if let Some(ref media) = config.media {
+        validate_media(media)?;
+    }
+
+    for (name, value) in [
+        ("gamma_r", config.gamma_r),
+        ("gamma_g", config.gamma_g),
+        ("gamma_b", config.gamma_b),
+    ] {
+        if let Some(value) = value
+            && !(MINIMUM_CHANNEL_GAMMA..=MAXIMUM_CHANNEL_GAMMA).contains(&value)
+        {
+            anyhow::bail!(
+                "{} ({}) must be between {} and {}",
+                name,
+                value,
+                MINIMUM_CHANNEL_GAMMA,
+                MAXIMUM_CHANNEL_GAMMA
+            );
+        }
+    }
+
+    let output_trims = config.outputs.iter().flatten().flat_map(|(name, output)| {
+        [
+            (format!("outputs.{name}.red_trim"), output.red_trim),
+            (format!("outputs.{name}.green_trim"), output.green_trim),
+            (format!("outputs.{name}.blue_trim"), output.blue_trim),
+        ]
+    });
+    for (name, value) in [
+        ("red_trim".to_string(), config.red_trim),
+        ("green_trim".to_string(), config.green_trim),
+        ("blue_trim".to_string(), config.blue_trim),
+    ]
+    .into_iter()
+    .chain(output_trims)
+    {
+        if let Some(value) = value
+            && !(MINIMUM_CHANNEL_TRIM..=MAXIMUM_CHANNEL_TRIM).contains(&value)
+        {
+            anyhow::bail!(
+                "{} ({}) must be between {} and {}",
+                name,
+                value,
+                MINIMUM_CHANNEL_TRIM,
+                MAXIMUM_CHANNEL_TRIM
+            );
+        }
+    }
+
+    if let Some(interval) = config.heartbeat_interval
+        && interval > MAXIMUM_HEARTBEAT_INTERVAL_SEC
+    {
+        anyhow::bail!(
+            "heartbeat_interval ({} seconds) must be at most {} seconds, or 0 to disable it",
+            interval,
+            MAXIMUM_HEARTBEAT_INTERVAL_SEC
+        );
+    }
+
+    if let Some(step) = config.values_changed_temp_step
+        && step > MAXIMUM_VALUES_CHANGED_TEMP_STEP
+    {
+        anyhow::bail!(
+            "values_changed_temp_step ({}K) must be at most {}K",
+            step,
+            MAXIMUM_VALUES_CHANGED_TEMP_STEP
+        );
+    }
+
+    if let Some(step) = config.values_changed_gamma_step
+        && !(0.0..=MAXIMUM_VALUES_CHANGED_GAMMA_STEP).contains(&step)
+    {
+        anyhow::bail!(
+            "values_changed_gamma_step ({}%) must be between 0% and {}%",
+            step,
+            MAXIMUM_VALUES_CHANGED_GAMMA_STEP
+        );
+    }
+
+    Ok(())
+}
+
+fn validate_output_lists(config: &RawConfig) -> Result<()> {
+    let include = config.include_outputs.as_deref().unwrap_or_default();
+    let exclude = config.exclude_outputs.as_deref().unwrap_or_default();
+    if include
+        .iter()
+        .chain(exclude)
+        .any(|name| name.trim().is_empty())
+    {
+        anyhow::bail!("include_outputs and exclude_outputs entries must not be empty");
+    }
+    if let Some(name) = include.iter().find(|name| exclude.contains(name)) {
+        anyhow::bail!("Output '{name}' is in both include_outputs and exclude_outputs");
+    }
+    Ok(())
+}
+
+fn validate_media(media: &MediaConfig) -> Result<()> {
+    if !(0.0..=MAXIMUM_MEDIA_GAMMA_HOLD).contains(&media.gamma_hold) {
+        anyhow::bail!(
+            "media gamma_hold ({}%) must be between 0% and {}%",
+            media.gamma_hold,
+            MAXIMUM_MEDIA_GAMMA_HOLD
+        );
+    }
+    if let Some(min_gamma) = media.min_gamma
+        && !(MINIMUM_GAMMA..=MAXIMUM_GAMMA).contains(&min_gamma)
+    {
+        anyhow::bail!(
+            "media min_gamma ({}%) must be between {}% and {}%",
+            min_gamma,
+            MINIMUM_GAMMA,
+            MAXIMUM_GAMMA
+        );
+    }
+    if media.gamma_hold == 0.0 && media.min_gamma.is_none() {
+        anyhow::bail!("[media] needs gamma_hold or min_gamma, or it changes nothing");
+    }
+    if !(MINIMUM_MEDIA_DETECT_AFTER_SEC..=MAXIMUM_MEDIA_DETECT_AFTER_SEC)
+        .contains(&media.detect_after)
+    {
+        anyhow::bail!(
+            "media detect_after ({}) must be between {} and {} seconds",
+            media.detect_after,
+            MINIMUM_MEDIA_DETECT_AFTER_SEC,
+            MAXIMUM_MEDIA_DETECT_AFTER_SEC
+        );
+    }
+    Ok(())
+}
+
+fn validate_winddown(winddown: &WinddownConfig) -> Result<()> {
+    let start = NaiveTime::parse_from_str(&winddown.start, "%H:%M:%S")
+        .context("Invalid winddown start time format, use HH:MM:SS")?;
+    let bedtime = NaiveTime::parse_from_str(&winddown.bedtime, "%H:%M:%S")
+        .context("Invalid winddown bedtime format, use HH:MM:SS")?;
+    if start == bedtime {
+        anyhow::bail!("winddown start and bedtime cannot be the same time ({start})");
+    }
+    if winddown.temp_drop > MAXIMUM_WINDDOWN_TEMP_DROP {
+        anyhow::bail!(
+            "winddown temp_drop ({}) must be between 0 and {} Kelvin",
+            winddown.temp_drop,
+            MAXIMUM_WINDDOWN_TEMP_DROP
+        );
+    }
+    if !(0.0..=MAXIMUM_WINDDOWN_GAMMA_DROP).contains(&winddown.gamma_drop) {
+        anyhow::bail!(
+            "winddown gamma_drop ({}%) must be between 0% and {}%",
+            winddown.gamma_drop,
+            MAXIMUM_WINDDOWN_GAMMA_DROP
+        );
+    }
     Ok(())
 }
 
+/// The configured minutes of the sunset and sunrise transitions, in that order, each with
+/// the key it came from for error messages: the direction's own key or else
+/// `transition_duration`. `None` when neither key is set.
+fn transition_durations(config: &RawConfig) -> [(&'static str, Option<u64>); 2] {
+    let pick = |name, minutes: Option<u64>| match minutes {
+        Some(minutes) => (name, Some(minutes)),
+        None => ("transition_duration", config.transition_duration),
+    };
+    [
+        pick("sunset_duration", config.sunset_duration),
+        pick("sunrise_duration", config.sunrise_duration),
+    ]
+}
+
 pub fn validate_config(config: &RawConfig) -> Result<()> {
     let mode = config.transition_mode;
 
     validate_basic_ranges(config)?;
 
+    if config.darkman_export == Some(true)
+        && matches!(
+            mode,
+            TransitionMode::Static | TransitionMode::External | TransitionMode::Darkman
+        )
+    {
+        anyhow::bail!(
+            "darkman_export needs a schedule with day and night, not transition_mode = \"{mode}\""
+        );
+    }
+
+    // Outside the modes with a sunset it does nothing, as when a static preset inherits it
+    let night_latest_start = config
+        .night_latest_start
+        .as_deref()
+        .map(|time| {
+            NaiveTime::parse_from_str(time, "%H:%M:%S")
+                .context("Invalid night_latest_start time format, use HH:MM:SS")
+        })
+        .transpose()?;
+
     if mode == TransitionMode::Static {
         if config.static_temp.is_none() {
             anyhow::bail!("Static mode requires static_temp to be specified");
@@ -183,9 +464,8 @@ pub fn validate_config(config: &RawConfig) -> Result<()> {
     let sunrise = NaiveTime::parse_from_str(sunrise_str, "%H:%M:%S")
         .context("Invalid sunrise time format")?;
 
-    let transition_duration_mins = config
-        .transition_duration
-        .unwrap_or(DEFAULT_TRANSITION_DURATION_MIN);
+    let durations = transition_durations(config)
+        .map(|(name, minutes)| (name, minutes.unwrap_or(DEFAULT_TRANSITION_DURATION_MIN)));
     let update_interval_secs = match config.update_interval {
         Some(crate::config::UpdateInterval::Fixed(secs)) => Some(secs),
         _ => None,
@@ -223,9 +503,32 @@ pub fn validate_config(config: &RawConfig) -> Result<()> {
         );
     }
 
-    validate_transitions_fit_periods(sunset, sunrise, transition_duration_mins, mode)?;
-    validate_no_transition_overlaps(sunset, sunrise, transition_duration_mins, mode)?;
+    validate_transitions_fit_periods(sunset, sunrise, durations, mode)?;
+    validate_no_transition_overlaps(sunset, sunrise, durations, mode)?;
+    if matches!(
+        mode,
+        TransitionMode::Center | TransitionMode::StartAt | TransitionMode::FinishBy
+    ) {
+        let mut windows = transition_windows(sunset, sunrise, durations, mode);
+        if let Some(latest) = night_latest_start {
+            windows = validate_night_latest_start(latest, windows, sunrise)?;
+        }
+        let (sunset_start, _, _, sunrise_end) = windows;
+        let day_secs = (sunset_start - sunrise_end)
+            .num_seconds()
+            .rem_euclid(24 * 3600) as u64;
+        if config.min_day_duration.is_none() && day_secs < SHORT_DAY_WARNING_MIN * 60 {
+            log_warning!(
+                "Only {} of day between the end of sunrise ({sunrise_end}) and the start of sunset ({sunset_start}).",
+                format_duration_secs(day_secs)
+            );
+            log_indented!(
+                "Check sunrise and sunset for a typo, or set min_day_duration to keep more"
+            );
+        }
+    }
 
+    let transition_duration_mins = durations[0].1.min(durations[1].1);
     let transition_duration_secs = transition_duration_mins * 60;
     if let Some(interval_secs) = update_interval_secs
         && transition_duration_secs < 300
@@ -236,9 +539,42 @@ pub fn validate_config(config: &RawConfig) -> Result<()> {
         );
     }
 
+    if let Some(interval_secs) = update_interval_secs
+        && config.adjust_update_interval != Some(true)
+        && matches!(
+            mode,
+            TransitionMode::Center | TransitionMode::StartAt | TransitionMode::FinishBy
+        )
+    {
+        warn_few_transition_steps(durations, interval_secs);
+    }
+
     Ok(())
 }
 
+/// Warn about each transition that takes fewer than `MINIMUM_TRANSITION_STEPS` updates
+/// at a fixed `interval_secs`, once when both share a duration.
+fn warn_few_transition_steps(durations: [(&str, u64); 2], interval_secs: u64) {
+    let distinct = if durations[0] == durations[1] {
+        &durations[..1]
+    } else {
+        &durations[..]
+    };
+    for &(name, minutes) in distinct {
+        let steps = minutes * 60 / interval_secs;
+        if steps < MINIMUM_TRANSITION_STEPS {
+            log_warning!(
+                "{name} ({minutes} min) at update_interval = {interval_secs} takes only {steps} update{}.",
+                if steps == 1 { "" } else { "s" }
+            );
+            log_indented!(
+                "Lower update_interval to {} or less, use \"auto\", or set adjust_update_interval = true",
+                (minutes * 60 / MINIMUM_TRANSITION_STEPS).max(MINIMUM_UPDATE_INTERVAL_SEC)
+            );
+        }
+    }
+}
+
 /// Day and night durations in seconds, in that order.
 pub(crate) fn calculate_day_night_durations(sunset: NaiveTime, sunrise: NaiveTime) -> (u32, u32) {
     let sunset_secs = sunset.num_seconds_from_midnight();
@@ -267,85 +603,63 @@ fn format_duration_secs(secs: u64) -> String {
     }
 }
 
-/// Validate that a center-mode transition fits within both day and night periods.
+/// Validate that center-mode transitions fit within both day and night periods.
+///
+/// `durations` holds the sunset and sunrise transition minutes with the key each came from.
 pub(crate) fn validate_transitions_fit_periods(
     sunset: NaiveTime,
     sunrise: NaiveTime,
-    transition_duration_mins: u64,
+    durations: [(&str, u64); 2],
     mode: TransitionMode,
 ) -> Result<()> {
     if mode == TransitionMode::Center {
         let (day_duration_secs, night_duration_secs) =
             calculate_day_night_durations(sunset, sunrise);
-        let half_transition_secs = transition_duration_mins * 60 / 2;
 
-        if half_transition_secs >= day_duration_secs.into()
-            || half_transition_secs >= night_duration_secs.into()
-        {
-            anyhow::bail!(
-                "transition_duration ({} minutes) is too long for 'center' mode. \
-                With centered transitions, half the duration ({}) must fit in both \
-                day period ({}) and night period ({}). \
-                Reduce transition_duration or adjust sunset/sunrise times.",
-                transition_duration_mins,
-                format_duration_secs(half_transition_secs),
-                format_duration_secs(day_duration_secs.into()),
-                format_duration_secs(night_duration_secs.into())
-            );
+        for (name, transition_duration_mins) in durations {
+            let half_transition_secs = transition_duration_mins * 60 / 2;
+
+            if half_transition_secs >= day_duration_secs.into()
+                || half_transition_secs >= night_duration_secs.into()
+            {
+                anyhow::bail!(
+                    "{} ({} minutes) is too long for 'center' mode. \
+                    With centered transitions, half the duration ({}) must fit in both \
+                    day period ({}) and night period ({}). \
+                    Reduce {} or adjust sunset/sunrise times.",
+                    name,
+                    transition_duration_mins,
+                    format_duration_secs(half_transition_secs),
+                    format_duration_secs(day_duration_secs.into()),
+                    format_duration_secs(night_duration_secs.into()),
+                    name
+                );
+            }
         }
     }
 
     Ok(())
 }
 
+/// Name the transition durations for a message, once when both directions share a key.
+fn describe_durations(durations: [(&str, u64); 2]) -> String {
+    let [(sunset_name, sunset_mins), (sunrise_name, sunrise_mins)] = durations;
+    if sunset_name == sunrise_name {
+        format!("{sunset_name} ({sunset_mins} min)")
+    } else {
+        format!("{sunset_name} ({sunset_mins} min) and {sunrise_name} ({sunrise_mins} min)")
+    }
+}
+
 /// Reject transition windows that overlap or leave no stable day or night period between them.
 pub(crate) fn validate_no_transition_overlaps(
     sunset: NaiveTime,
     sunrise: NaiveTime,
-    transition_duration_mins: u64,
+    durations: [(&str, u64); 2],
     mode: TransitionMode,
 ) -> Result<()> {
-    let transition_duration = Duration::from_secs(transition_duration_mins * 60);
-
-    let (sunset_start, sunset_end, sunrise_start, sunrise_end) = match mode {
-        TransitionMode::Center => {
-            let half_transition = transition_duration / 2;
-            let half_chrono = chrono::Duration::from_std(half_transition).unwrap();
-            (
-                sunset - half_chrono,
-                sunset + half_chrono,
-                sunrise - half_chrono,
-                sunrise + half_chrono,
-            )
-        }
-        TransitionMode::StartAt => {
-            let full_transition = chrono::Duration::from_std(transition_duration).unwrap();
-            (
-                sunset,
-                sunset + full_transition,
-                sunrise,
-                sunrise + full_transition,
-            )
-        }
-        TransitionMode::FinishBy => {
-            let full_transition = chrono::Duration::from_std(transition_duration).unwrap();
-            (
-                sunset - full_transition,
-                sunset,
-                sunrise - full_transition,
-                sunrise,
-            )
-        }
-        _ => {
-            let full_transition = chrono::Duration::from_std(transition_duration).unwrap();
-            (
-                sunset - full_transition,
-                sunset,
-                sunrise - full_transition,
-                sunrise,
-            )
-        }
-    };
+    let (sunset_start, sunset_end, sunrise_start, sunrise_end) =
+        transition_windows(sunset, sunrise, durations, mode);
 
     let sunset_start_secs = sunset_start.num_seconds_from_midnight();
     let sunset_end_secs = sunset_end.num_seconds_from_midnight();
@@ -365,14 +679,14 @@ pub(crate) fn validate_no_transition_overlaps(
             Sunset transition: {:?} → {:?}, Sunrise transition: {:?} → {:?}. \
             \nThis configuration is impossible because transitions would conflict. \
             \nSolutions: \
-            \n  1. Reduce transition_duration from {} to {} minutes or less \
+            \n  1. Reduce {} to {} minutes or less \
             \n  2. Increase time between sunset ({:?}) and sunrise ({:?}) \
             \n  3. Change transition_mode from '{}' to a different mode",
             sunset_start,
             sunset_end,
             sunrise_start,
             sunrise_end,
-            transition_duration_mins,
+            describe_durations(durations),
             suggest_max_transition_duration(sunset, sunrise, mode),
             sunset,
             sunrise,
@@ -391,13 +705,87 @@ pub(crate) fn validate_no_transition_overlaps(
         anyhow::bail!(
             "Transitions leave no stable {collapsed} period. \
             \nThe sunset and sunrise transitions meet with no {collapsed} between them. \
-            \nUse static mode for a constant setting, or reduce transition_duration ({transition_duration_mins} min)."
+            \nUse static mode for a constant setting, or reduce {}.",
+            describe_durations(durations)
         );
     }
 
     Ok(())
 }
 
+/// The sunset and sunrise windows `mode` places around the configured times, as
+/// (sunset_start, sunset_end, sunrise_start, sunrise_end).
+fn transition_windows(
+    sunset: NaiveTime,
+    sunrise: NaiveTime,
+    durations: [(&str, u64); 2],
+    mode: TransitionMode,
+) -> (NaiveTime, NaiveTime, NaiveTime, NaiveTime) {
+    let sunset_duration = Duration::from_secs(durations[0].1 * 60);
+    let sunrise_duration = Duration::from_secs(durations[1].1 * 60);
+    let full_sunset = chrono::Duration::from_std(sunset_duration).unwrap();
+    let full_sunrise = chrono::Duration::from_std(sunrise_duration).unwrap();
+
+    match mode {
+        TransitionMode::Center => {
+            let sunset_half = chrono::Duration::from_std(sunset_duration / 2).unwrap();
+            let sunrise_half = chrono::Duration::from_std(sunrise_duration / 2).unwrap();
+            (
+                sunset - sunset_half,
+                sunset + sunset_half,
+                sunrise - sunrise_half,
+                sunrise + sunrise_half,
+            )
+        }
+        TransitionMode::StartAt => (
+            sunset,
+            sunset + full_sunset,
+            sunrise,
+            sunrise + full_sunrise,
+        ),
+        _ => (
+            sunset - full_sunset,
+            sunset,
+            sunrise - full_sunrise,
+            sunrise,
+        ),
+    }
+}
+
+/// Reject a `night_latest_start` that pulls the sunset transition back into the sunrise
+/// transition, leaving no day between them. Returns the windows with sunset pulled back.
+fn validate_night_latest_start(
+    latest: NaiveTime,
+    windows: (NaiveTime, NaiveTime, NaiveTime, NaiveTime),
+    sunrise: NaiveTime,
+) -> Result<(NaiveTime, NaiveTime, NaiveTime, NaiveTime)> {
+    let (sunset_start, sunset_end, sunrise_start, sunrise_end) = windows;
+    let Some(overrun) = night_start_overrun(sunset_end, latest) else {
+        return Ok(windows);
+    };
+    let length = chrono::Duration::seconds(
+        (sunset_end - sunset_start)
+            .num_seconds()
+            .rem_euclid(24 * 3600),
+    );
+    let (start, end) = pull_sunset_back(sunset_start, sunset_end, length, overrun);
+
+    let overlap = check_time_ranges_overlap(
+        start.num_seconds_from_midnight(),
+        end.num_seconds_from_midnight(),
+        sunrise_start.num_seconds_from_midnight(),
+        sunrise_end.num_seconds_from_midnight(),
+    );
+    if overlap || start == sunrise_end {
+        anyhow::bail!(
+            "night_latest_start ({latest}) pulls the sunset transition back to {start} → {end}, \
+            leaving no day after the sunrise transition ({sunrise_start} → {sunrise_end}). \
+            \nSet night_latest_start later in the evening or move sunrise ({sunrise}) earlier."
+        );
+    }
+    Ok((start, end, sunrise_start, sunrise_end))
+}
+
 /// Whether two second-of-day ranges overlap, accounting for ranges that wrap past midnight.
 pub(crate) fn check_time_ranges_overlap(
     start1_secs: u32,
@@ -116,10 +116,10 @@ impl ConfigWatcher {
         thread::spawn(move || {
             let _watcher = watcher;
 
-            // Cache the active preset to avoid repeated filesystem queries that can
+            // Cache the active presets to avoid repeated filesystem queries that can
             // fail transiently during rapid editor save operations. This cache is
             // invalidated when we actually process a reload.
-            let mut cached_active_preset: Option<Option<String>> = None;
+            let mut cached_active_presets: Option<Vec<String>> = None;
 
             // One editor save emits a burst of filesystem events, so a failed
             // reload would otherwise log the same error repeatedly. Log a given
@@ -136,14 +136,15 @@ impl ConfigWatcher {
             eprintln!("DEBUG: Config watcher thread started");
 
             for event in rx {
-                let active_preset = cached_active_preset.clone().unwrap_or_else(|| {
-                    let preset = crate::state::preset::get_active_preset().ok().flatten();
-                    cached_active_preset = Some(preset.clone());
-                    preset
+                let active_presets = cached_active_presets.clone().unwrap_or_else(|| {
+                    let state = crate::state::preset::PresetState::load().unwrap_or_default();
+                    let presets: Vec<String> = state.base.into_iter().chain(state.stack).collect();
+                    cached_active_presets = Some(presets.clone());
+                    presets
                 });
 
                 let affects_config =
-                    event_affects_config(&event.paths, &watched_paths, active_preset.as_deref());
+                    event_affects_config(&event.paths, &watched_paths, &active_presets);
 
                 if !affects_config {
                     #[cfg(debug_assertions)]
@@ -216,7 +217,7 @@ impl ConfigWatcher {
                 match signal_sender.send(SignalMessage::Reload(Box::new(new_config.clone()))) {
                     Ok(()) => {
                         last_sent = Some((current_preset, new_config));
-                        cached_active_preset = None;
+                        cached_active_presets = None;
                         last_reload_error = None;
                         if debug_enabled {
                             log_indented!("Triggering automatic configuration reload");
@@ -261,10 +262,26 @@ impl ConfigWatcher {
             if active_preset_path.exists() {
                 paths.push(active_preset_path);
             }
+            let stack_path = state_dir.join("preset_stack");
+            if stack_path.exists() {
+                paths.push(stack_path);
+            }
             let dir_id_path = state_dir.join("dir_id");
             if dir_id_path.exists() {
                 paths.push(dir_id_path);
             }
+            let overrides_path = state_dir.join("overrides.toml");
+            if overrides_path.exists() {
+                paths.push(overrides_path);
+            }
+            let presentation_path = state_dir.join("presentation.toml");
+            if presentation_path.exists() {
+                paths.push(presentation_path);
+            }
+            let adjustment_path = state_dir.join("adjustment.toml");
+            if adjustment_path.exists() {
+                paths.push(adjustment_path);
+            }
         }
 
         let geo_path = Config::get_geo_path()?;
@@ -286,13 +303,13 @@ pub fn start_config_watcher(
 }
 
 /// Whether a filesystem event touches configuration the watcher should reload.
-/// This covers watched config files, the active preset's config files under a
-/// presets directory, and the state files and namespace directories under the
-/// state directory.
+/// This covers watched config files, the config files of the active and stacked
+/// presets under a presets directory, and the state files and namespace
+/// directories under the state directory.
 fn event_affects_config(
     event_paths: &[PathBuf],
     watched_paths: &[PathBuf],
-    active_preset: Option<&str>,
+    active_presets: &[String],
 ) -> bool {
     event_paths.iter().any(|event_path| {
         watched_paths.iter().any(|watched| {
@@ -309,15 +326,21 @@ fn event_affects_config(
                                     || event_name.ends_with("sunsetr.toml")
                                     || event_name.ends_with("geo.toml")
                                     || event_name == "active_preset"
+                                    || event_name == "preset_stack"
                                     || event_name == "dir_id"
+                                    || event_name == "overrides.toml"
+                                    || event_name == "presentation.toml"
+                                    || event_name == "adjustment.toml"
                             })
                             .unwrap_or(false))
             } else if watched.ends_with("presets") {
-                if let Some(preset_name) = active_preset {
+                if !active_presets.is_empty() {
                     event_path.starts_with(watched)
-                        && event_path
-                            .components()
-                            .any(|c| c.as_os_str() == preset_name)
+                        && event_path.components().any(|c| {
+                            active_presets
+                                .iter()
+                                .any(|preset| c.as_os_str() == preset.as_str())
+                        })
                         && event_path
                             .file_name()
                             .and_then(|n| n.to_str())
@@ -347,8 +370,12 @@ fn event_affects_config(
                         .and_then(|n| n.to_str())
                         .map(|name| {
                             name == "active_preset"
+                                || name == "preset_stack"
                                 || name == "dir_id"
-                                || (active_preset.is_none()
+                                || name == "overrides.toml"
+                                || name == "presentation.toml"
+                                || name == "adjustment.toml"
+                                || (active_presets.is_empty()
                                     && (name == "sunsetr.toml" || name == "geo.toml"))
                         })
                         .unwrap_or(false)
@@ -0,0 +1,175 @@
+//! Forced advances past the end of a transition.
+//!
+//! When a transition ends within one update interval, the main loop sleeps exactly to its
+//! end and then moves to the next period without re-reading the clock: a fresh read right at
+//! the boundary can still land inside the transition and leave it unfinished. That advance is
+//! only right if the sleep actually reached the end of the same transition. A signal can wake
+//! the loop early, and a suspend or a recovery can leave it in another period or in a later
+//! occurrence of the same one, so the scheduler remembers which boundary it armed for and
+//! checks it again before letting the loop advance.
+
+use chrono::{DateTime, Local, TimeDelta};
+
+use crate::core::period::Period;
+
+/// Slack for the wall clock against the monotonic sleep and for geo times recalculated
+/// between arming and checking.
+const TOLERANCE: TimeDelta = TimeDelta::seconds(1);
+
+#[derive(Debug, Clone, Copy)]
+struct Boundary {
+    period: Period,
+    at: DateTime<Local>,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct BoundaryScheduler {
+    pending: Option<Boundary>,
+}
+
+impl BoundaryScheduler {
+    /// Record that the coming sleep ends `period` at `at`.
+    pub(super) fn arm(&mut self, period: Period, at: DateTime<Local>) {
+        self.pending = Some(Boundary { period, at });
+        #[cfg(debug_assertions)]
+        eprintln!("DEBUG [BoundaryScheduler]: Armed for end of {period:?} at {at}");
+    }
+
+    pub(super) fn disarm(&mut self) {
+        self.pending = None;
+    }
+
+    #[cfg(test)]
+    fn is_armed(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Consume the armed boundary, returning whether the loop should advance past it now.
+    ///
+    /// `period` and `ends_at` describe the current state. The advance is due only when that
+    /// is still the transition the scheduler armed for and `now` has reached its end. In
+    /// every other case the boundary is dropped and the loop re-evaluates the clock normally.
+    pub(super) fn take_due(
+        &mut self,
+        period: Period,
+        ends_at: Option<DateTime<Local>>,
+        now: DateTime<Local>,
+    ) -> bool {
+        let Some(boundary) = self.pending.take() else {
+            return false;
+        };
+
+        let same_transition = boundary.period == period
+            && ends_at.is_some_and(|ends_at| (ends_at - boundary.at).abs() <= TOLERANCE);
+        let reached = now + TOLERANCE >= boundary.at;
+
+        #[cfg(debug_assertions)]
+        if !(same_transition && reached) {
+            eprintln!(
+                "DEBUG [BoundaryScheduler]: Dropped boundary for {:?} at {} \
+                 (same_transition={same_transition}, reached={reached})",
+                boundary.period, boundary.at
+            );
+        }
+
+        same_transition && reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2025, 6, 1, hour, minute, second)
+            .single()
+            .unwrap()
+    }
+
+    fn armed(period: Period, end: DateTime<Local>) -> BoundaryScheduler {
+        let mut scheduler = BoundaryScheduler::default();
+        scheduler.arm(period, end);
+        scheduler
+    }
+
+    #[test]
+    fn sleeping_to_the_boundary_advances_once() {
+        let end = at(19, 0, 0);
+        let mut scheduler = armed(Period::Sunset, end);
+
+        assert!(scheduler.take_due(Period::Sunset, Some(end), end));
+        assert!(!scheduler.is_armed());
+        assert!(!scheduler.take_due(Period::Sunset, Some(end), end));
+    }
+
+    #[test]
+    fn nothing_is_due_unless_armed() {
+        let end = at(19, 0, 0);
+        let mut scheduler = BoundaryScheduler::default();
+        assert!(!scheduler.take_due(Period::Sunset, Some(end), end));
+    }
+
+    #[test]
+    fn clock_noise_at_the_boundary_still_advances() {
+        let end = at(19, 0, 0);
+        let early = end - TimeDelta::milliseconds(300);
+        assert!(armed(Period::Sunset, end).take_due(Period::Sunset, Some(end), early));
+
+        // Geo times recalculated in between may move the end by a fraction of a second
+        let moved = end + TimeDelta::milliseconds(500);
+        assert!(armed(Period::Sunset, end).take_due(Period::Sunset, Some(moved), end));
+    }
+
+    #[test]
+    fn early_wake_by_signal_does_not_advance() {
+        let end = at(19, 0, 0);
+        let mut scheduler = armed(Period::Sunset, end);
+
+        // A reload arrived 40 seconds before the end of sunset
+        let woke = end - TimeDelta::seconds(40);
+        assert!(!scheduler.take_due(Period::Sunset, Some(end), woke));
+        assert!(!scheduler.is_armed());
+    }
+
+    #[test]
+    fn reload_that_moves_the_transition_end_does_not_advance() {
+        let end = at(19, 0, 0);
+        let mut scheduler = armed(Period::Sunset, end);
+
+        let new_end = at(19, 30, 0);
+        assert!(!scheduler.take_due(Period::Sunset, Some(new_end), end));
+    }
+
+    #[test]
+    fn suspend_past_the_boundary_into_another_period_does_not_advance() {
+        let end = at(19, 0, 0);
+        let mut scheduler = armed(Period::Sunset, end);
+
+        // Recovery after resume already moved the state on to night
+        let next_sunrise = at(23, 0, 0) + TimeDelta::hours(7);
+        assert!(!scheduler.take_due(Period::Night, Some(next_sunrise), at(23, 0, 0)));
+    }
+
+    #[test]
+    fn suspend_into_the_same_transition_a_day_later_does_not_advance() {
+        let end = at(19, 0, 0);
+        let mut scheduler = armed(Period::Sunset, end);
+
+        // Resumed halfway through the next day's sunset, which ends a day after the armed one
+        let resumed = end + TimeDelta::hours(24) - TimeDelta::minutes(15);
+        let next_end = end + TimeDelta::hours(24);
+        assert!(!scheduler.take_due(Period::Sunset, Some(next_end), resumed));
+    }
+
+    #[test]
+    fn short_oversleep_without_recovery_still_advances() {
+        let end = at(19, 0, 0);
+        let mut scheduler = armed(Period::Sunset, end);
+
+        // Woke 20 seconds late, below the oversleep threshold, so the state was not recovered
+        let woke = end + TimeDelta::seconds(20);
+        assert!(scheduler.take_due(Period::Sunset, Some(end), woke));
+    }
+}
@@ -1,35 +1,75 @@
 //! Per-iteration state tracking for the main loop.
+//!
+//! The tracker is a small state machine with two independent parts:
+//!
+//! - [`LoopState`] decides whether an iteration checks the schedule at all. The loop starts
+//!   in `Starting`, since the initial state was just applied, and moves to `Running` on its
+//!   first iteration. A reload in a stable period moves it to `ReloadApplied`, which skips
+//!   exactly one check so the reload isn't announced twice, and the next iteration returns
+//!   it to `Running`.
+//! - [`TransitionLog`] remembers what the current transition has logged: the last progress,
+//!   for the rate of change, and whether the block-style header was printed. Entering a
+//!   stable period or reloading starts it over.
+//!
+//! Update timing, the previous period for IPC and the pending boundary sleep sit beside them.
 
 use chrono::{DateTime, Local};
 
+use crate::core::boundary::BoundaryScheduler;
 use crate::core::period::Period;
 
+/// Whether the next iteration checks the schedule, and why not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LoopState {
+    /// Before the first iteration. The initial state is already applied.
+    Starting,
+    /// Iterations check the schedule.
+    Running,
+    /// A stable-period reload was just applied and announced.
+    ReloadApplied,
+}
+
+/// Logging for the transition in progress.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct TransitionLog {
+    previous_progress: Option<f32>,
+    announced: bool,
+}
+
 #[derive(Debug)]
 pub(super) struct Context {
+    state: LoopState,
+    log: TransitionLog,
     last_update_time: Option<DateTime<Local>>,
-    previous_progress: Option<f32>,
     previous_period: Option<Period>,
-    first_transition_logged: bool,
-    is_first_iteration: bool,
-    config_reload_pending: bool,
-    sleeping_to_boundary: bool,
+    boundary: BoundaryScheduler,
 }
 
 impl Context {
     pub(super) fn new() -> Self {
         Self {
+            state: LoopState::Starting,
+            log: TransitionLog::default(),
             last_update_time: None,
-            previous_progress: None,
             previous_period: None,
-            first_transition_logged: false,
-            is_first_iteration: true,
-            config_reload_pending: false,
-            sleeping_to_boundary: false,
+            boundary: BoundaryScheduler::default(),
         }
     }
 
+    /// Advance to the next iteration, returning the state that decides it. Anything but
+    /// `Running` means the iteration skips the schedule check.
+    pub(super) fn next_iteration(&mut self) -> LoopState {
+        let state = self.state;
+        self.state = LoopState::Running;
+        #[cfg(debug_assertions)]
+        if state == LoopState::ReloadApplied {
+            eprintln!("DEBUG [Context]: Handling config reload skip");
+        }
+        state
+    }
+
     pub(super) fn record_state_update(&mut self) {
-        self.last_update_time = Some(crate::time::source::now());
+        self.record_update_at(crate::time::source::now());
         #[cfg(debug_assertions)]
         eprintln!(
             "DEBUG [Context]: Recorded state update at {:?}",
@@ -37,26 +77,28 @@ impl Context {
         );
     }
 
-    /// Flags the next iteration to be skipped so a stable-period config reload
-    /// does not emit a duplicate event.
+    fn record_update_at(&mut self, now: DateTime<Local>) {
+        self.last_update_time = Some(now);
+    }
+
+    /// Skip the next iteration's check so a stable-period config reload does not emit a
+    /// duplicate event, and start the transition log over.
     pub(super) fn record_config_reload(&mut self) {
-        self.last_update_time = Some(crate::time::source::now());
-        self.config_reload_pending = true;
-        self.previous_progress = None;
-        self.first_transition_logged = false;
+        self.record_update_at(crate::time::source::now());
+        self.state = LoopState::ReloadApplied;
+        self.log = TransitionLog::default();
         #[cfg(debug_assertions)]
-        eprintln!("DEBUG [Context]: Recorded config reload for stable period, pending skip = true");
+        eprintln!("DEBUG [Context]: Recorded config reload for stable period, pending skip");
     }
 
     pub(super) fn should_update_during_transition(&self, update_interval_secs: u64) -> bool {
-        match self.last_update_time {
-            None => true,
-            Some(last) => {
-                let now = crate::time::source::now();
-                let elapsed = now.signed_duration_since(last);
-                elapsed.num_seconds() >= update_interval_secs as i64
-            }
-        }
+        self.update_due_at(update_interval_secs, crate::time::source::now())
+    }
+
+    fn update_due_at(&self, update_interval_secs: u64, now: DateTime<Local>) -> bool {
+        self.last_update_time.is_none_or(|last| {
+            now.signed_duration_since(last).num_seconds() >= update_interval_secs as i64
+        })
     }
 
     pub(super) fn should_log_progress(&self, period: Period, state_was_just_applied: bool) -> bool {
@@ -65,37 +107,18 @@ impl Context {
 
     pub(super) fn update_progress(&mut self, progress: Option<f32>) {
         if let Some(p) = progress {
-            self.previous_progress = Some(p);
+            self.log.previous_progress = Some(p);
         }
     }
 
     pub(super) fn reset_for_stable_period(&mut self) {
-        self.previous_progress = None;
-        self.first_transition_logged = false;
-    }
-
-    pub(super) fn handle_first_iteration(&mut self) -> bool {
-        if self.is_first_iteration {
-            self.is_first_iteration = false;
-            true
-        } else {
-            false
-        }
-    }
-
-    pub(super) fn handle_config_reload_skip(&mut self) -> bool {
-        if self.config_reload_pending {
-            self.config_reload_pending = false;
-            #[cfg(debug_assertions)]
-            eprintln!("DEBUG [Context]: Handling config reload skip, clearing pending flag");
-            true
-        } else {
-            false
-        }
+        self.log = TransitionLog::default();
     }
 
-    pub(super) fn is_period_change(&self, current: Period) -> bool {
-        self.previous_period.is_some_and(|prev| prev != current)
+    /// Claim the transition's block-style header. True only for the first call since the
+    /// transition log started over, so the header is printed once per transition.
+    pub(super) fn announce_transition(&mut self) -> bool {
+        !std::mem::replace(&mut self.log.announced, true)
     }
 
     pub(super) fn record_current_period(&mut self, period: Period) {
@@ -103,7 +126,7 @@ impl Context {
     }
 
     pub(super) fn previous_progress(&self) -> Option<f32> {
-        self.previous_progress
+        self.log.previous_progress
     }
 
     pub(super) fn previous_period(&self) -> Option<Period> {
@@ -114,25 +137,113 @@ impl Context {
         self.last_update_time.is_some()
     }
 
-    pub(super) fn first_transition_logged(&self) -> bool {
-        self.first_transition_logged
+    /// The transition end the current sleep is heading for, if any. The next iteration
+    /// force advances to the next period when it is due.
+    pub(super) fn boundary(&mut self) -> &mut BoundaryScheduler {
+        &mut self.boundary
     }
+}
 
-    pub(super) fn set_first_transition_logged(&mut self, value: bool) {
-        self.first_transition_logged = value;
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use proptest::prelude::*;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Iterate,
+        Reload,
+        Progress(f32),
+        Announce,
+        Stable,
+    }
+
+    fn op_strategy() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            Just(Op::Iterate),
+            Just(Op::Reload),
+            (0.0f32..=1.0).prop_map(Op::Progress),
+            Just(Op::Announce),
+            Just(Op::Stable),
+        ]
+    }
+
+    #[test]
+    fn first_iteration_is_skipped_once() {
+        let mut tracker = Context::new();
+        assert_eq!(tracker.next_iteration(), LoopState::Starting);
+        assert_eq!(tracker.next_iteration(), LoopState::Running);
+    }
+
+    #[test]
+    fn reload_resets_the_transition_log() {
+        let mut tracker = Context::new();
+        tracker.update_progress(Some(0.4));
+        assert!(tracker.announce_transition());
+
+        tracker.record_config_reload();
+        assert_eq!(tracker.previous_progress(), None);
+        assert!(tracker.announce_transition());
+    }
+
+    proptest! {
+        /// Reload skips fire exactly once, progress is the last one seen since the log
+        /// started over, and each transition is announced at most once.
+        #[test]
+        fn tracker_follows_its_model(ops in prop::collection::vec(op_strategy(), 0..64)) {
+            let mut tracker = Context::new();
+            let mut expected_state = LoopState::Starting;
+            let mut expected_progress = None;
+            let mut announced = false;
+
+            for op in ops {
+                match op {
+                    Op::Iterate => {
+                        prop_assert_eq!(tracker.next_iteration(), expected_state);
+                        expected_state = LoopState::Running;
+                    }
+                    Op::Reload => {
+                        tracker.record_config_reload();
+                        expected_state = LoopState::ReloadApplied;
+                        expected_progress = None;
+                        announced = false;
+                    }
+                    Op::Progress(progress) => {
+                        tracker.update_progress(Some(progress));
+                        expected_progress = Some(progress);
+                    }
+                    Op::Announce => {
+                        prop_assert_eq!(tracker.announce_transition(), !announced);
+                        announced = true;
+                    }
+                    Op::Stable => {
+                        tracker.reset_for_stable_period();
+                        expected_progress = None;
+                        announced = false;
+                    }
+                }
+                prop_assert_eq!(tracker.previous_progress(), expected_progress);
+            }
 
-    /// Whether the previous sleep landed exactly on a transition boundary. When
-    /// true, the next iteration force advances to the next period.
-    pub(super) fn slept_to_transition_boundary(&self) -> bool {
-        self.sleeping_to_boundary
-    }
+            // Once an iteration has run and no reload followed, nothing is skipped
+            tracker.next_iteration();
+            prop_assert_eq!(tracker.next_iteration(), LoopState::Running);
+        }
 
-    /// Set to true when a sleep will land exactly on a transition boundary, and
-    /// back to false after the forced transition.
-    pub(super) fn set_sleeping_to_boundary(&mut self, value: bool) {
-        self.sleeping_to_boundary = value;
-        #[cfg(debug_assertions)]
-        eprintln!("DEBUG [Context]: Set sleeping_to_boundary = {}", value);
+        /// After an update, transition updates wait out the interval and resume right at it.
+        #[test]
+        fn updates_are_debounced_for_one_interval(
+            interval in 1u64..=3600,
+            elapsed in 0u64..=7200,
+        ) {
+            let start = Local.with_ymd_and_hms(2025, 6, 1, 18, 0, 0).single().unwrap();
+            let mut tracker = Context::new();
+            prop_assert!(tracker.update_due_at(interval, start));
+
+            tracker.record_update_at(start);
+            let now = start + chrono::Duration::seconds(elapsed as i64);
+            prop_assert_eq!(tracker.update_due_at(interval, now), elapsed >= interval);
+        }
     }
 }
@@ -0,0 +1,112 @@
+//! Limits on what other programs can make the display do.
+//!
+//! Previews, pushed targets, test values and pauses arrive from IPC clients and scripts,
+//! which can be wrong or stuck in a loop. Their values are range checked as they are parsed into
+//! [`Kelvin`] and [`GammaPercent`], and changes are applied at most once per
+//! [`MIN_REQUEST_INTERVAL`]. Requests arriving faster are held back, only the latest of
+//! each kind is kept, and it is applied once the interval has passed. A script flipping
+//! between two values ten times a second then shows at most five changes a second, which
+//! is 2.5 on/off cycles. That stays below the three flashes a second accessibility
+//! guidelines allow, and the compositor isn't flooded.
+
+use std::time::{Duration, Instant};
+
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::core::pause::PauseUntil;
+
+/// Shortest time between two changes requested from outside the schedule.
+pub const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Requests held back until [`MIN_REQUEST_INTERVAL`] has passed, the latest of each kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HeldBack {
+    pub preview: Option<(Kelvin, GammaPercent)>,
+    pub external: Option<(Kelvin, GammaPercent)>,
+    pub test: Option<(Kelvin, GammaPercent)>,
+    pub pause: Option<(bool, Option<PauseUntil>)>,
+}
+
+impl HeldBack {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Take the requests in `newer` over the ones of the same kind already held back.
+    pub fn merge(&mut self, newer: HeldBack) {
+        self.preview = newer.preview.or(self.preview);
+        self.external = newer.external.or(self.external);
+        self.test = newer.test.or(self.test);
+        self.pause = newer.pause.or(self.pause);
+    }
+}
+
+/// Tracks when a requested change was last applied and what is waiting for its turn.
+#[derive(Debug, Default)]
+pub struct RequestGuard {
+    last_applied: Option<Instant>,
+    pub held_back: HeldBack,
+}
+
+impl RequestGuard {
+    /// How long a change requested at `now` has to wait, `None` when it may go ahead.
+    pub fn wait(&self, now: Instant) -> Option<Duration> {
+        let ready_at = self.last_applied? + MIN_REQUEST_INTERVAL;
+        (ready_at > now).then(|| ready_at - now)
+    }
+
+    /// Note a requested change applied at `now`.
+    pub fn record(&mut self, now: Instant) {
+        self.last_applied = Some(now);
+    }
+
+    /// The held back requests once their wait is over, leaving none behind. Otherwise the
+    /// time left, or `None` and nothing when none are held back.
+    pub fn release(&mut self, now: Instant) -> Result<Option<HeldBack>, Duration> {
+        if self.held_back.is_empty() {
+            return Ok(None);
+        }
+        match self.wait(now) {
+            Some(remaining) => Err(remaining),
+            None => Ok(Some(std::mem::take(&mut self.held_back))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::units::values;
+
+    #[test]
+    fn changes_wait_out_the_interval_after_the_last_one() {
+        let start = Instant::now();
+        let mut guard = RequestGuard::default();
+        assert_eq!(guard.wait(start), None);
+
+        guard.record(start);
+        let soon = start + Duration::from_millis(50);
+        assert_eq!(
+            guard.wait(soon),
+            Some(MIN_REQUEST_INTERVAL - Duration::from_millis(50))
+        );
+        assert_eq!(guard.wait(start + MIN_REQUEST_INTERVAL), None);
+    }
+
+    #[test]
+    fn held_back_requests_are_released_once() {
+        let start = Instant::now();
+        let mut guard = RequestGuard::default();
+        assert_eq!(guard.release(start), Ok(None));
+
+        guard.record(start);
+        guard.held_back.pause = Some((true, None));
+        guard.held_back.external = Some(values(3000, 90.0));
+        assert!(guard.release(start).is_err());
+
+        let later = start + MIN_REQUEST_INTERVAL;
+        let released = guard.release(later).unwrap().unwrap();
+        assert_eq!(released.pause, Some((true, None)));
+        assert_eq!(released.external, Some(values(3000, 90.0)));
+        assert_eq!(guard.release(later), Ok(None));
+    }
+}
@@ -0,0 +1,77 @@
+//! The states applied most recently, kept to line up "my screen flashed at 03:12" reports
+//! with what sunsetr did at the time.
+//!
+//! Core records every state it applies and announces, and the IPC server hands the record
+//! to `history` requests. Steps of a smooth startup, reload or shutdown aren't recorded on
+//! their own, only the state they lead to.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::core::period::ChangeReason;
+
+/// How many applied states are kept.
+pub const HISTORY_LEN: usize = 100;
+
+/// One applied state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedEntry {
+    pub timestamp: DateTime<Local>,
+    pub temp: Kelvin,
+    pub gamma: GammaPercent,
+    pub reason: ChangeReason,
+    /// Outputs connected at the time, left out when the backend can't list them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Vec<String>>,
+}
+
+/// The last [`HISTORY_LEN`] applied states, oldest first. Clones share the record.
+#[derive(Debug, Clone, Default)]
+pub struct AppliedHistory {
+    entries: Arc<Mutex<VecDeque<AppliedEntry>>>,
+}
+
+impl AppliedHistory {
+    pub fn record(&self, entry: AppliedEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == HISTORY_LEN {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    pub fn entries(&self) -> Vec<AppliedEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oldest_entries_make_room() {
+        let history = AppliedHistory::default();
+        let shared = history.clone();
+        for temp in 1000..1000 + HISTORY_LEN as u32 + 5 {
+            history.record(AppliedEntry {
+                timestamp: Local::now(),
+                temp: Kelvin::new(temp).unwrap(),
+                gamma: GammaPercent::new(100.0).unwrap(),
+                reason: ChangeReason::Boundary,
+                outputs: None,
+            });
+        }
+
+        let entries = shared.entries();
+        assert_eq!(entries.len(), HISTORY_LEN);
+        assert_eq!(entries.first().unwrap().temp.get(), 1005);
+        assert_eq!(
+            entries.last().unwrap().temp.get(),
+            1000 + HISTORY_LEN as u32 + 4
+        );
+    }
+}
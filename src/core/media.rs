@@ -0,0 +1,56 @@
+//! A gentler gamma while media plays.
+//!
+//! Layered on the scheduled values by
+//! [`RuntimeState::values`](crate::core::runtime_state::RuntimeState::values) while
+//! [`crate::io::idle`] reports that something holds an idle inhibitor with nobody touching
+//! the input, which is what watching a film looks like. Only gamma changes, and never past
+//! the day gamma, so the screen doesn't dim mid-film while the warmth stays on schedule.
+
+use crate::config::MediaConfig;
+
+/// `values` with the gamma raised by `gamma_hold` and to at least `min_gamma`, capped at
+/// `day_gamma` unless the scheduled gamma is already above it.
+pub fn apply(values: (u32, f64), media: &MediaConfig, day_gamma: f64) -> (u32, f64) {
+    let (temp, gamma) = values;
+    let held = (gamma + media.gamma_hold).max(media.min_gamma.unwrap_or(gamma));
+    (temp, held.min(day_gamma.max(gamma)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn media(gamma_hold: f64, min_gamma: Option<f64>) -> MediaConfig {
+        MediaConfig {
+            gamma_hold,
+            min_gamma,
+            detect_after: 60,
+        }
+    }
+
+    #[test]
+    fn the_night_drop_is_softened_but_never_past_the_day() {
+        assert_eq!(apply((3300, 80.0), &media(10.0, None), 100.0), (3300, 90.0));
+        assert_eq!(
+            apply((3300, 80.0), &media(0.0, Some(95.0)), 100.0),
+            (3300, 95.0)
+        );
+        assert_eq!(
+            apply((3300, 80.0), &media(5.0, Some(95.0)), 100.0),
+            (3300, 95.0)
+        );
+        assert_eq!(
+            apply((3300, 95.0), &media(10.0, None), 100.0),
+            (3300, 100.0)
+        );
+        // The day is left alone, even above 100%
+        assert_eq!(
+            apply((6500, 110.0), &media(10.0, None), 110.0),
+            (6500, 110.0)
+        );
+        assert_eq!(
+            apply((6500, 110.0), &media(0.0, Some(90.0)), 100.0),
+            (6500, 110.0)
+        );
+    }
+}
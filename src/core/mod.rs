@@ -4,33 +4,97 @@
 //! color changes over time and handling wake, clock-jump, reload, test-mode,
 //! and shutdown signals.
 
+mod boundary;
 mod context;
+mod guard;
+pub mod history;
+pub mod media;
+pub mod pause;
 pub mod period;
+pub mod preview;
 pub mod runtime_state;
 pub mod schedule;
 pub mod smoothing;
+pub mod values;
+pub mod winddown;
 
 #[cfg(test)]
 mod tests;
 
 use anyhow::Result;
-use std::{path::PathBuf, sync::atomic::Ordering, time::Duration};
+use std::{borrow::Cow, path::PathBuf, sync::atomic::Ordering, time::Duration};
 
 use crate::{
     backend::ColorTemperatureBackend,
-    common::utils,
-    config::{self, Config},
+    common::{
+        constants::POWER_SAVING_POLL_INTERVAL_MS,
+        profile,
+        profile::Span,
+        units::{GammaPercent, Kelvin},
+        utils,
+    },
+    config::{self, Config, OverrideExpiry, PowerSaving, ShutdownTarget, TransitionMode},
     core::{
-        context::Context,
-        period::{Period, StateChange},
+        context::{Context, LoopState},
+        guard::{HeldBack, RequestGuard},
+        history::{AppliedEntry, AppliedHistory},
+        pause::{Pause, PauseEvent, PauseUntil},
+        period::{ChangeReason, Period, StateChange},
+        preview::{Preview, PreviewRequest},
         runtime_state::RuntimeState,
         smoothing::{SmoothTransition, TransitionResult},
     },
     io::lock::LockFile,
     io::signals::SignalState,
-    state::ipc::IpcNotifier,
+    state::dock::Dock,
+    state::ipc::{
+        IpcNotifier,
+        events::{OverrideKind, PreviewAction},
+    },
+    state::presentation::Presentation,
+    time::simulate::{AppliedValues, Decision, IterationRecord, record_iteration},
 };
 
+/// How late the main loop may wake before it stops catching up period by period and jumps
+/// straight to the current state. Timers under suspend-to-idle can fire long after their
+/// deadline without a logind resume signal.
+const OVERSLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Whether moving from `from` to `to` begins a sunset or sunrise, where `override_expiry`
+/// drops manual overrides.
+fn begins_transition(from: Option<Period>, to: Period) -> bool {
+    matches!(from, Some(Period::Day | Period::Night)) && from != Some(to)
+}
+
+/// How far past `deadline` the loop woke, when that exceeds [`OVERSLEEP_THRESHOLD`].
+fn oversleep(
+    deadline: Option<chrono::DateTime<chrono::Local>>,
+    now: chrono::DateTime<chrono::Local>,
+) -> Option<Duration> {
+    let late = (now - deadline?).to_std().ok()?;
+    (late > OVERSLEEP_THRESHOLD).then_some(late)
+}
+
+/// Whether `values` moved far enough from the `last` reported ones for a values_changed
+/// event. Outside a transition any difference counts, so clients always end on the exact
+/// stable values even when the final step of a transition was a small one.
+fn values_worth_reporting(
+    last: Option<(Kelvin, GammaPercent)>,
+    values: (Kelvin, GammaPercent),
+    config: &Config,
+    transitioning: bool,
+) -> bool {
+    let Some((last_temp, last_gamma)) = last else {
+        return true;
+    };
+    if (last_temp, last_gamma) == values {
+        return false;
+    }
+    !transitioning
+        || last_temp.get().abs_diff(values.0.get()) >= config.values_changed_temp_step
+        || (last_gamma.get() - values.1.get()).abs() >= config.values_changed_gamma_step
+}
+
 pub(crate) struct CoreParams {
     pub backend: Box<dyn ColorTemperatureBackend>,
     pub runtime_state: RuntimeState,
@@ -39,6 +103,7 @@ pub(crate) struct CoreParams {
     pub lock_info: Option<(LockFile, PathBuf)>,
     pub bypass_smoothing: bool,
     pub ipc_notifier: Option<IpcNotifier>,
+    pub history: AppliedHistory,
 }
 
 /// State machine that owns the runtime state and drives the main loop.
@@ -51,6 +116,25 @@ pub(crate) struct Core {
     ipc_notifier: Option<IpcNotifier>,
     runtime_state: RuntimeState,
     previous_runtime_state: Option<RuntimeState>,
+    /// Presets stacked on the active one when the current config was applied.
+    applied_preset_stack: Vec<String>,
+    last_heartbeat: std::time::Instant,
+    /// Values in the last values_changed event.
+    reported_values: Option<(Kelvin, GammaPercent)>,
+    /// Values an IPC client is previewing, shown instead of the scheduled ones.
+    preview: Option<Preview>,
+    /// darkman mode last set by `darkman_export`.
+    exported_dark: Option<bool>,
+    /// Presets switched by the connected outputs.
+    dock: Dock,
+    /// States applied recently, shared with the IPC server.
+    history: AppliedHistory,
+    /// Every output is held neutral while an IPC client takes a screenshot.
+    neutral_hold: bool,
+    /// Color shifting is paused, every output neutral until resumed.
+    pause: Option<Pause>,
+    /// Paces previews, pushed targets and pauses requested by other programs.
+    guard: RequestGuard,
 }
 
 impl Core {
@@ -64,6 +148,32 @@ impl Core {
             ipc_notifier: params.ipc_notifier,
             runtime_state: params.runtime_state,
             previous_runtime_state: None,
+            applied_preset_stack: crate::state::preset::get_preset_stack().unwrap_or_default(),
+            last_heartbeat: std::time::Instant::now(),
+            reported_values: None,
+            preview: None,
+            exported_dark: None,
+            dock: Dock::default(),
+            history: params.history,
+            neutral_hold: false,
+            pause: None,
+            guard: RequestGuard::default(),
+        }
+    }
+
+    /// Send an IPC heartbeat once `heartbeat_interval` has passed since the last one.
+    ///
+    /// Driven from the main loop's wait so heartbeats stop if the loop itself hangs.
+    fn send_heartbeat_if_due(&mut self) {
+        let interval_secs = self.runtime_state.config().heartbeat_interval;
+        if interval_secs == 0 || self.last_heartbeat.elapsed() < Duration::from_secs(interval_secs)
+        {
+            return;
+        }
+
+        self.last_heartbeat = std::time::Instant::now();
+        if let Some(ref ipc_notifier) = self.ipc_notifier {
+            ipc_notifier.send_heartbeat(interval_secs);
         }
     }
 
@@ -84,16 +194,62 @@ impl Core {
     /// Apply a new config, returning `Ok(true)` when the reload moves from a
     /// stable period into a transitioning period.
     pub fn handle_config_reload(&mut self, new_config: Config) -> Result<bool> {
+        self.reload_from(new_config, None)
+    }
+
+    /// [`Self::handle_config_reload`] with `start` on screen instead of the current
+    /// runtime state's values, as when a smooth startup was cut short.
+    fn reload_from(
+        &mut self,
+        new_config: Config,
+        start: Option<(Kelvin, GammaPercent)>,
+    ) -> Result<bool> {
         self.signal_state.interrupt.store(false, Ordering::SeqCst);
 
         let previous_preset = { self.signal_state.current_preset.lock().unwrap().clone() };
+        if new_config.transition_mode == TransitionMode::Darkman
+            && !crate::time::source::is_simulated()
+        {
+            crate::io::darkman::start_mode_monitor(
+                self.signal_state.signal_sender.clone(),
+                self.debug_enabled,
+            );
+        }
+        if let Some(media) = &new_config.media
+            && !crate::time::source::is_simulated()
+        {
+            crate::io::idle::start_media_monitor(
+                self.signal_state.signal_sender.clone(),
+                media.detect_after,
+                self.debug_enabled,
+            );
+        }
         let target_state = self.runtime_state.with_config(&new_config)?;
-        let values_changed = !self.runtime_state.has_same_effective_values(&target_state);
+        // A reload always ends a preview, and the display has to leave the previewed
+        // values even when the scheduled ones stay the same
+        let preview = self.preview.take();
+        let start = start.or(preview.map(|preview| preview.values()));
+        let values_changed = !self.runtime_state.has_same_effective_values(&target_state)
+            || start.is_some_and(|start| start != target_state.values());
         let period_changed = self.runtime_state.period() != target_state.period();
         let current_preset = crate::state::preset::get_active_preset().ok().flatten();
         let preset_changed = previous_preset != current_preset;
+        let reason = if preset_changed {
+            ChangeReason::Preset
+        } else {
+            ChangeReason::Reload
+        };
         let config_changed = *self.runtime_state.config() != new_config;
 
+        if config_changed
+            && let Err(e) = self
+                .backend
+                .reload_config(&self.backend_config(&new_config))
+        {
+            log_pipe!();
+            log_warning!("Failed to apply backend settings from reloaded config: {e}");
+        }
+
         if !values_changed && !period_changed && !preset_changed && !config_changed {
             #[cfg(debug_assertions)]
             eprintln!("DEBUG: Config reload skipped. No changes detected.");
@@ -108,7 +264,7 @@ impl Core {
             let new_preset = crate::state::preset::get_active_preset().ok().flatten();
 
             log_pipe!();
-            log_debug!("Reload state change detection:");
+            log_debug!("Reload state change detection ({reason}):");
             log_indented!(
                 "State: {:?} → {:?}",
                 self.runtime_state.period(),
@@ -121,7 +277,7 @@ impl Core {
                 log_indented!("Preset: {:?} → {:?}", previous_preset, new_preset);
             }
 
-            let smoothing_enabled = target_state.config().smoothing;
+            let smoothing_enabled = target_state.smoothing();
             if smoothing_enabled {
                 log_indented!("Smooth transition: enabled");
             } else {
@@ -129,289 +285,258 @@ impl Core {
             }
         }
 
-        if !self.runtime_state.has_same_effective_values(&target_state) {
-            let smoothing_enabled = target_state.config().smoothing;
-            let is_wayland_backend = self.backend.backend_name() == "Wayland";
-
-            if smoothing_enabled && is_wayland_backend {
-                self.previous_runtime_state = Some(self.runtime_state.clone());
-                self.runtime_state = target_state;
-
-                if let Some(ref ipc_notifier) = self.ipc_notifier {
-                    let current_preset = crate::state::preset::get_active_preset().ok().flatten();
-                    if previous_preset != current_preset {
-                        let (target_temp, target_gamma) = self.runtime_state.values();
-                        let target_period = self.runtime_state.period();
-                        ipc_notifier.send_preset_changed(
-                            previous_preset.clone(),
-                            current_preset.clone(),
-                            target_period,
-                            target_temp,
-                            target_gamma,
-                        );
-                        *self.signal_state.current_preset.lock().unwrap() = current_preset;
-                    } else if values_changed {
-                        let (target_temp, target_gamma) = self.runtime_state.values();
-                        let target_period = self.runtime_state.period();
-                        ipc_notifier.send_config_changed(target_period, target_temp, target_gamma);
-                    }
-                }
-
-                let mut start_override: Option<(u32, f64)> = None;
-
-                loop {
-                    let prev_period = self
-                        .previous_runtime_state
-                        .as_ref()
-                        .map(|s| s.period())
-                        .unwrap_or(Period::Day);
-                    let mut transition = SmoothTransition::reload(
-                        self.previous_runtime_state.as_ref().unwrap(),
-                        &self.runtime_state,
-                    );
-                    transition = transition.silent();
-
-                    if let Some((temp, gamma)) = start_override.take() {
-                        transition = transition.with_start_values(temp, gamma);
-                    }
-
-                    match transition.execute(
-                        self.backend.as_mut(),
-                        &self.runtime_state,
-                        &self.signal_state.running,
-                        Some(&self.signal_state.interrupt),
-                    ) {
-                        Ok(TransitionResult::Completed) => {
-                            let current_period = self.runtime_state.period();
-
-                            if let Some(ref ipc_notifier) = self.ipc_notifier {
-                                if prev_period != current_period {
-                                    #[cfg(debug_assertions)]
-                                    eprintln!(
-                                        "DEBUG: Sending PeriodChanged event from config reload: {:?} -> {:?}",
-                                        prev_period, current_period
-                                    );
-                                    ipc_notifier.send_period_changed(prev_period, current_period);
-                                }
-
-                                #[cfg(debug_assertions)]
-                                eprintln!(
-                                    "DEBUG: Sending StateApplied event from config reload ({})",
-                                    if current_period.is_static() {
-                                        "static period"
-                                    } else if current_period.is_stable() {
-                                        "stable period"
-                                    } else if !prev_period.is_transitioning() {
-                                        "entering transition from stable"
-                                    } else {
-                                        "continuing transition"
-                                    }
-                                );
-                                ipc_notifier.send_state_applied(&self.runtime_state);
-                            }
+        let prev_period = self.runtime_state.period();
 
-                            let entering_transition = !prev_period.is_transitioning()
-                                && current_period.is_transitioning();
+        if values_changed {
+            self.previous_runtime_state = Some(self.runtime_state.clone());
+            self.runtime_state = target_state;
 
-                            log_pipe!();
-                            log_info!("Configuration reloaded and state applied successfully");
-                            break Ok(entering_transition);
-                        }
-                        Ok(TransitionResult::Interrupted {
-                            current_temp,
-                            current_gamma,
-                        }) => {
-                            let latest_config = self.signal_state.drain_to_latest_reload();
-                            self.signal_state.interrupt.store(false, Ordering::SeqCst);
-
-                            match latest_config {
-                                Some(new_config) => {
-                                    let new_target = self.runtime_state.with_config(&new_config)?;
-                                    self.previous_runtime_state = Some(self.runtime_state.clone());
-                                    self.runtime_state = new_target;
-                                    start_override = Some((current_temp, current_gamma));
-
-                                    if let Some(ref ipc_notifier) = self.ipc_notifier {
-                                        let (target_temp, target_gamma) =
-                                            self.runtime_state.values();
-                                        let target_period = self.runtime_state.period();
-                                        ipc_notifier.send_config_changed(
-                                            target_period,
-                                            target_temp,
-                                            target_gamma,
-                                        );
-                                    }
-
-                                    #[cfg(debug_assertions)]
-                                    eprintln!(
-                                        "DEBUG: Smooth transition interrupted, retrying with newer config (start: {}K/{:.1}%)",
-                                        current_temp, current_gamma
-                                    );
-
-                                    continue;
-                                }
-                                None => {
-                                    #[cfg(debug_assertions)]
-                                    eprintln!(
-                                        "DEBUG: Smooth transition interrupted but no Reload in channel, exiting retry loop"
-                                    );
-                                    break Ok(false);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            log_warning!("Failed to apply transition after config reload: {e}");
-                            if let Some(prev_state) = self.previous_runtime_state.take() {
-                                self.runtime_state = prev_state;
-                            }
-                            break Err(e);
-                        }
-                    }
+            let smoothing_enabled = self.runtime_state.smoothing();
+            let supports_smoothing = self.backend.capabilities().smoothing;
+            if smoothing_enabled && supports_smoothing {
+                // Clients learn the target values before the transition gets there
+                self.notify_reload_targets(preset_changed.then(|| current_preset.clone()));
+                if !self.run_reload_transition(start)? {
+                    return Ok(false);
                 }
             } else {
-                self.previous_runtime_state = Some(self.runtime_state.clone());
-                self.runtime_state = target_state;
-
-                match self
+                if let Err(e) = self
                     .backend
                     .apply_startup_state(&self.runtime_state, &self.signal_state.running)
                 {
-                    Ok(_) => {
-                        let prev_period = self
-                            .previous_runtime_state
-                            .as_ref()
-                            .map(|s| s.period())
-                            .unwrap_or(Period::Day);
-                        let current_period = self.runtime_state.period();
-
-                        if let Some(ref ipc_notifier) = self.ipc_notifier {
-                            let current_preset =
-                                crate::state::preset::get_active_preset().ok().flatten();
-                            if previous_preset != current_preset {
-                                let (target_temp, target_gamma) = self.runtime_state.values();
-                                let target_period = self.runtime_state.period();
-                                #[cfg(debug_assertions)]
-                                eprintln!(
-                                    "DEBUG: Sending PresetChanged event from config reload (non-smooth)"
-                                );
-                                ipc_notifier.send_preset_changed(
-                                    previous_preset.clone(),
-                                    current_preset.clone(),
-                                    target_period,
-                                    target_temp,
-                                    target_gamma,
-                                );
-                                *self.signal_state.current_preset.lock().unwrap() = current_preset;
-                            } else if values_changed {
-                                let (target_temp, target_gamma) = self.runtime_state.values();
-                                let target_period = self.runtime_state.period();
-                                #[cfg(debug_assertions)]
-                                eprintln!(
-                                    "DEBUG: Sending ConfigChanged event from config reload (non-smooth)"
-                                );
-                                ipc_notifier.send_config_changed(
-                                    target_period,
-                                    target_temp,
-                                    target_gamma,
-                                );
-                            }
-
-                            if prev_period != current_period {
-                                #[cfg(debug_assertions)]
-                                eprintln!(
-                                    "DEBUG: Sending PeriodChanged event from config reload (non-smooth): {:?} -> {:?}",
-                                    prev_period, current_period
-                                );
-                                ipc_notifier.send_period_changed(prev_period, current_period);
-                            }
-
-                            #[cfg(debug_assertions)]
-                            eprintln!(
-                                "DEBUG: Sending StateApplied event from config reload (non-smooth, {})",
-                                if current_period.is_static() {
-                                    "static period"
-                                } else if current_period.is_stable() {
-                                    "stable period"
-                                } else if !prev_period.is_transitioning() {
-                                    "entering transition from stable"
-                                } else {
-                                    "continuing transition"
-                                }
-                            );
-                            ipc_notifier.send_state_applied(&self.runtime_state);
-                        }
-
-                        let entering_transition =
-                            !prev_period.is_transitioning() && current_period.is_transitioning();
-
-                        log_pipe!();
-                        log_info!("Configuration reloaded and state applied successfully");
-                        Ok(entering_transition)
-                    }
-                    Err(e) => {
-                        log_warning!("Failed to apply new state after config reload: {e}");
-                        if let Some(prev_state) = self.previous_runtime_state.take() {
-                            self.runtime_state = prev_state;
-                        }
-                        Err(e)
+                    log_warning!("Failed to apply new state after config reload: {e}");
+                    if let Some(prev_state) = self.previous_runtime_state.take() {
+                        self.runtime_state = prev_state;
                     }
+                    return Err(e);
                 }
+                self.notify_reload_targets(preset_changed.then(|| current_preset.clone()));
             }
         } else {
-            let prev_period = self.runtime_state.period();
             self.runtime_state = target_state;
-            let current_period = self.runtime_state.period();
+            if prev_period != self.runtime_state.period() {
+                period::log_state_announcement(self.runtime_state.period());
+            }
+            if preset_changed {
+                self.notify_reload_targets(Some(current_preset.clone()));
+            }
+        }
+
+        if preset_changed {
+            *self.signal_state.current_preset.lock().unwrap() = current_preset;
+        }
+        let announced_period = preview.map_or(prev_period, |preview| preview.announced_period);
+        self.notify_applied(Some(announced_period), reason);
+        self.notify_placeholder_coordinates();
+
+        log_pipe!();
+        if values_changed {
+            log_info!("Configuration reloaded and state applied successfully");
+        } else {
+            log_info!("Configuration reloaded (no state change needed)");
+        }
+        Ok(!prev_period.is_transitioning() && self.runtime_state.period().is_transitioning())
+    }
+
+    /// Smoothly transition from the previous runtime state, or from `start` when other
+    /// values are on screen, to the reloaded one, restarting from the values reached
+    /// whenever a newer reload interrupts it. Returns `Ok(false)` when interrupted with no
+    /// newer config to move to.
+    fn run_reload_transition(&mut self, start: Option<(Kelvin, GammaPercent)>) -> Result<bool> {
+        let mut start_override = start;
+
+        loop {
+            let mut transition = SmoothTransition::reload(
+                self.previous_runtime_state.as_ref().unwrap(),
+                &self.runtime_state,
+            )
+            .silent();
 
-            if prev_period != current_period {
-                period::log_state_announcement(current_period);
+            if let Some((temp, gamma)) = start_override.take() {
+                transition = transition.with_start_values(temp, gamma);
             }
 
-            if let Some(ref ipc_notifier) = self.ipc_notifier {
-                let current_preset = crate::state::preset::get_active_preset().ok().flatten();
-                if previous_preset != current_preset {
-                    let (target_temp, target_gamma) = self.runtime_state.values();
-                    let target_period = self.runtime_state.period();
-                    ipc_notifier.send_preset_changed(
-                        previous_preset.clone(),
-                        current_preset.clone(),
-                        target_period,
-                        target_temp,
-                        target_gamma,
-                    );
-                    *self.signal_state.current_preset.lock().unwrap() = current_preset;
-                }
+            match transition.execute(
+                self.backend.as_mut(),
+                &self.runtime_state,
+                &self.signal_state.running,
+                Some(&self.signal_state.interrupt),
+            ) {
+                Ok(TransitionResult::Completed) => return Ok(true),
+                Ok(TransitionResult::Interrupted {
+                    current_temp,
+                    current_gamma,
+                }) => {
+                    let latest_config = self.signal_state.drain_to_latest_reload();
+                    self.signal_state.interrupt.store(false, Ordering::SeqCst);
+
+                    let Some(new_config) = latest_config else {
+                        #[cfg(debug_assertions)]
+                        eprintln!(
+                            "DEBUG: Smooth transition interrupted but no Reload in channel, exiting retry loop"
+                        );
+                        return Ok(false);
+                    };
+
+                    let new_target = self.runtime_state.with_config(&new_config)?;
+                    self.previous_runtime_state = Some(self.runtime_state.clone());
+                    self.runtime_state = new_target;
+                    start_override = Some((current_temp, current_gamma));
+                    self.notify_reload_targets(None);
 
-                if prev_period != current_period {
                     #[cfg(debug_assertions)]
                     eprintln!(
-                        "DEBUG: Sending PeriodChanged event from config reload (no value change): {:?} -> {:?}",
-                        prev_period, current_period
+                        "DEBUG: Smooth transition interrupted, retrying with newer config (start: {}K/{:.1}%)",
+                        current_temp, current_gamma
                     );
-                    ipc_notifier.send_period_changed(prev_period, current_period);
                 }
+                Err(e) => {
+                    log_warning!("Failed to apply transition after config reload: {e}");
+                    if let Some(prev_state) = self.previous_runtime_state.take() {
+                        self.runtime_state = prev_state;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
 
+    /// Tell IPC clients where a reload is heading: `preset_changed` when `to_preset` is
+    /// given, `config_changed` otherwise.
+    fn notify_reload_targets(&self, to_preset: Option<Option<String>>) {
+        let Some(ref ipc_notifier) = self.ipc_notifier else {
+            return;
+        };
+        let (target_temp, target_gamma) = self.runtime_state.values();
+        let target_period = self.runtime_state.period();
+
+        match to_preset {
+            Some(to_preset) => {
+                let from_preset = self.signal_state.current_preset.lock().unwrap().clone();
                 #[cfg(debug_assertions)]
-                eprintln!(
-                    "DEBUG: Sending StateApplied event from config reload (no value change, {})",
-                    if current_period.is_static() {
-                        "static period"
-                    } else if current_period.is_stable() {
-                        "stable period"
-                    } else if !prev_period.is_transitioning() {
-                        "entering transition from stable"
-                    } else {
-                        "continuing transition"
-                    }
+                eprintln!("DEBUG: Sending PresetChanged event: {from_preset:?} -> {to_preset:?}");
+                ipc_notifier.send_preset_changed(
+                    from_preset,
+                    to_preset,
+                    target_period,
+                    target_temp,
+                    target_gamma,
                 );
-                ipc_notifier.send_state_applied(&self.runtime_state);
             }
+            None => {
+                #[cfg(debug_assertions)]
+                eprintln!("DEBUG: Sending ConfigChanged event");
+                ipc_notifier.send_config_changed(target_period, target_temp, target_gamma);
+            }
+        }
+    }
+
+    /// Tell IPC clients that geo mode fell back to manual times over placeholder
+    /// coordinates. Sent after every startup or reload state while they remain.
+    fn notify_placeholder_coordinates(&self) {
+        let config = self.runtime_state.config();
+        if let (Some(placeholder), Some(ipc_notifier)) =
+            (&config.placeholder_coordinates, &self.ipc_notifier)
+        {
+            ipc_notifier.send_coordinates_rejected(placeholder, config.transition_mode);
+        }
+    }
 
-            let entering_transition =
-                !prev_period.is_transitioning() && current_period.is_transitioning();
+    /// Announce the runtime state as applied: `period_changed` first when it moved on from
+    /// `from`, then `state_applied`. Every path that applies a state reports it through here.
+    fn notify_applied(&mut self, from: Option<Period>, reason: ChangeReason) {
+        self.export_darkman_mode();
+        let (temp, gamma) = self.runtime_state.values();
+        self.history.record(AppliedEntry {
+            timestamp: crate::time::source::now(),
+            temp,
+            gamma,
+            reason,
+            outputs: self
+                .backend
+                .output_names()
+                .map(|names| names.into_iter().collect()),
+        });
+
+        let Some(ref ipc_notifier) = self.ipc_notifier else {
+            return;
+        };
+        let current_period = self.runtime_state.period();
+        let values = self.runtime_state.values();
+        let report_values = values_worth_reporting(
+            self.reported_values,
+            values,
+            self.runtime_state.config(),
+            current_period.is_transitioning(),
+        );
 
+        if let Some(from) = from.filter(|from| *from != current_period) {
+            #[cfg(debug_assertions)]
+            eprintln!(
+                "DEBUG: Sending PeriodChanged event from {reason}: {:?} -> {:?}",
+                from, current_period
+            );
+            ipc_notifier.send_period_changed(from, current_period, reason);
+        }
+
+        #[cfg(debug_assertions)]
+        eprintln!("DEBUG: Sending StateApplied event from {reason}");
+        ipc_notifier.send_state_applied(&self.runtime_state, self.pause, reason);
+
+        if report_values {
+            ipc_notifier.send_values_changed(current_period, values.0, values.1);
+            self.reported_values = Some(values);
+        }
+    }
+
+    /// Apply the runtime state the schedule moved to and announce it, as the main loop
+    /// does for regular updates and forced boundary transitions.
+    fn apply_scheduled_state(&mut self, tracker: &mut Context, reason: ChangeReason) -> Result<()> {
+        self.expire_runtime_overrides(tracker.previous_period());
+        if self.preview.is_some() {
+            // Held back until the preview ends, which applies and announces the state then
+            tracker.record_state_update();
+            return Ok(());
+        }
+        self.backend
+            .apply_transition_state(&self.runtime_state, &self.signal_state.running)?;
+        tracker.record_state_update();
+        self.notify_applied(tracker.previous_period(), reason);
+        Ok(())
+    }
+
+    /// Drop the `set --runtime` overrides when `override_expiry` ends them at the sunset or
+    /// sunrise the schedule just entered. Removing the file reloads the config through the
+    /// watcher, which applies the scheduled values. Simulations leave the real state alone.
+    fn expire_runtime_overrides(&self, from: Option<Period>) {
+        if self.runtime_state.config().override_expiry != OverrideExpiry::NextTransition
+            || !begins_transition(from, self.runtime_state.period())
+            || crate::time::source::is_simulated()
+        {
+            return;
+        }
+        let fields: Vec<String> = match crate::state::overrides::load() {
+            Ok(overrides) if !overrides.is_empty() => overrides.keys().cloned().collect(),
+            Ok(_) => return,
+            Err(e) => {
+                log_pipe!();
+                log_warning!("Failed to read runtime overrides: {e}");
+                return;
+            }
+        };
+        if let Err(e) = crate::state::overrides::clear() {
             log_pipe!();
-            log_info!("Configuration reloaded (no state change needed)");
-            Ok(entering_transition)
+            log_warning!("Failed to drop expired runtime overrides: {e}");
+            return;
+        }
+
+        log_block_start!(
+            "{} began, dropping runtime overrides: {}",
+            self.runtime_state.period().display_name(),
+            fields.join(", ")
+        );
+        if let Some(ref ipc_notifier) = self.ipc_notifier {
+            ipc_notifier.send_override_expired(OverrideKind::Runtime, fields);
         }
     }
 
@@ -429,6 +554,7 @@ impl Core {
         );
 
         self.apply_initial_state()?;
+        crate::io::readiness::report_ready();
 
         if self.debug_enabled
             && self.runtime_state.is_geo_mode()
@@ -442,36 +568,45 @@ impl Core {
 
         self.main_loop()?;
         log_block_start!("Shutting down sunsetr...");
-        let is_wayland_backend = self.backend.backend_name() == "Wayland";
+        let supports_smoothing = self.backend.capabilities().smoothing;
         let is_instant_shutdown = self.signal_state.instant_shutdown.load(Ordering::SeqCst);
 
-        let smooth_shutdown_performed = if self.runtime_state.config().smoothing
-            && is_wayland_backend
-            && !is_instant_shutdown
-        {
-            if let Some(mut transition) = SmoothTransition::shutdown(&self.runtime_state) {
-                transition = transition.silent();
-                transition
-                    .execute(
-                        &mut *self.backend,
-                        &self.runtime_state,
-                        &self.signal_state.running,
-                        None,
-                    )
-                    .is_ok()
+        let smooth_shutdown_performed =
+            if self.runtime_state.smoothing() && supports_smoothing && !is_instant_shutdown {
+                if let Some(mut transition) = SmoothTransition::shutdown(&self.runtime_state) {
+                    transition = transition.silent();
+                    if let Some(preview) = self.preview {
+                        let (temp, gamma) = preview.values();
+                        transition = transition.with_start_values(temp, gamma);
+                    }
+                    transition
+                        .execute(
+                            &mut *self.backend,
+                            &self.runtime_state,
+                            &self.signal_state.running,
+                            None,
+                        )
+                        .is_ok()
+                } else {
+                    false
+                }
             } else {
                 false
-            }
-        } else {
-            false
-        };
+            };
 
-        if !smooth_shutdown_performed && self.backend.backend_name() == "Wayland" {
+        if !smooth_shutdown_performed
+            && self.backend.capabilities().keeps_gamma_on_exit
+            && self.runtime_state.config().shutdown_target != ShutdownTarget::Current
+        {
             if self.debug_enabled {
                 log_decorated!("Resetting color temperature and gamma...");
             }
             let running = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
-            if let Err(e) = self.backend.apply_temperature_gamma(6500, 100.0, &running) {
+            let (temp, gamma) = self
+                .runtime_state
+                .config()
+                .shutdown_values(self.runtime_state.values());
+            if let Err(e) = self.backend.apply_temperature_gamma(temp, gamma, &running) {
                 log_warning!("Failed to reset color temperature: {e}");
             } else if self.debug_enabled {
                 log_decorated!("Gamma reset completed successfully");
@@ -483,23 +618,24 @@ impl Core {
         } else {
             self.backend.cleanup(self.debug_enabled);
         }
+        profile::log_report();
         log_end!();
 
         Ok(())
     }
 
-    /// Smooth transitions run only on the Wayland backend. Hyprland-based backends
-    /// animate via CTM instead, so this applies their state immediately.
+    /// Smooth transitions run only on backends with the smoothing capability. The others,
+    /// such as the Hyprland-based ones that animate via CTM, get their state immediately.
     fn apply_initial_state(&mut self) -> Result<()> {
         if !self.signal_state.running.load(Ordering::SeqCst) {
             return Ok(());
         }
 
-        let is_wayland_backend = self.backend.backend_name() == "Wayland";
-        let smoothing = self.runtime_state.config().smoothing;
+        let supports_smoothing = self.backend.capabilities().smoothing;
+        let smoothing = self.runtime_state.smoothing();
         let startup_duration = self.runtime_state.config().startup_duration;
 
-        let should_transition = smoothing && is_wayland_backend && !self.bypass_smoothing;
+        let should_transition = smoothing && supports_smoothing && !self.bypass_smoothing;
 
         if should_transition && startup_duration >= 0.1 {
             let mut transition = if let Some(ref prev_runtime_state) = self.previous_runtime_state {
@@ -518,11 +654,26 @@ impl Core {
                 &self.signal_state.running,
                 Some(&self.signal_state.interrupt),
             );
-            if matches!(result, Ok(TransitionResult::Interrupted { .. })) {
-                self.signal_state.interrupt.store(false, Ordering::SeqCst);
-            }
             match result {
-                Ok(_) => {}
+                Ok(TransitionResult::Completed) => {}
+                Ok(TransitionResult::Interrupted {
+                    current_temp,
+                    current_gamma,
+                }) => {
+                    self.signal_state.interrupt.store(false, Ordering::SeqCst);
+                    // A config or preset change made during the startup takes over from
+                    // the values reached, instead of waiting for the main loop
+                    if let Some(new_config) = self.signal_state.drain_to_latest_reload() {
+                        log_block_start!("Configuration changed during startup, retargeting");
+                        match self.reload_from(*new_config, Some((current_temp, current_gamma))) {
+                            Ok(_) => return Ok(()),
+                            Err(e) => {
+                                log_warning!("Failed to apply the changed configuration: {e}");
+                                self.apply_immediate_state(self.runtime_state.period())?;
+                            }
+                        }
+                    }
+                }
                 Err(e) => {
                     log_warning!("Failed to apply smooth startup transition: {e}");
                     log_decorated!("Falling back to immediate transition...");
@@ -533,9 +684,8 @@ impl Core {
             self.apply_immediate_state(self.runtime_state.period())?;
         }
 
-        if let Some(ref ipc_notifier) = self.ipc_notifier {
-            ipc_notifier.send_state_applied(&self.runtime_state);
-        }
+        self.notify_applied(None, ChangeReason::Startup);
+        self.notify_placeholder_coordinates();
 
         Ok(())
     }
@@ -559,32 +709,54 @@ impl Core {
         Ok(())
     }
 
-    /// Recover state after a wake or clock-jump signal.
+    /// Set darkman's mode when `darkman_export` is on and the applied phase crossed the
+    /// middle of a transition. A simulation never touches the real darkman.
+    fn export_darkman_mode(&mut self) {
+        if !self.runtime_state.config().darkman_export || crate::time::source::is_simulated() {
+            return;
+        }
+        let Some(dark) = crate::io::darkman::dark_at(self.runtime_state.phase()) else {
+            return;
+        };
+        if self.exported_dark != Some(dark) {
+            self.exported_dark = Some(dark);
+            crate::io::darkman::export(dark, self.debug_enabled);
+        }
+    }
+
+    /// Recover state after a wake, clock-jump, darkman mode or media playback signal.
     ///
     /// Recomputes the period for the current time, applies new values to the
     /// backend (smoothly when smoothing is enabled and the values changed,
     /// instantly otherwise), updates the Context tracker, and emits IPC
     /// events. Backend errors are logged and the main loop continues on the
     /// next cycle.
-    fn recover_state(&mut self, tracker: &mut Context, cause: &str) -> Result<()> {
+    fn recover_state(&mut self, tracker: &mut Context, reason: ChangeReason) -> Result<()> {
         self.signal_state.interrupt.store(false, Ordering::SeqCst);
+        // The recovered state replaces whatever boundary the interrupted sleep was heading for
+        tracker.boundary().disarm();
 
         let prev_snapshot = self.runtime_state.clone();
         let prev_period = self.runtime_state.period();
-        // The StateChange return is discarded on purpose. We compare periods
-        // directly below because StateChange::TransitionProgress is non-None
-        // yet only means the period variant is unchanged (still Sunset or still
+        // The StateChange return is discarded on purpose. notify_applied compares
+        // periods directly because StateChange::TransitionProgress is non-None yet
+        // only means the period variant is unchanged (still Sunset or still
         // Sunrise), not that a period boundary was crossed.
         let _ = self.update_runtime_state();
         let current_period = self.runtime_state.period();
-        let period_changed = prev_period != current_period;
+        self.expire_runtime_overrides(Some(prev_period));
 
-        let smoothing_enabled = self.runtime_state.config().smoothing;
-        let is_wayland_backend = self.backend.backend_name() == "Wayland";
+        let smoothing_enabled = self.runtime_state.smoothing();
+        let supports_smoothing = self.backend.capabilities().smoothing;
         let values_changed = prev_snapshot.values() != self.runtime_state.values();
 
-        let apply_result = if smoothing_enabled
-            && is_wayland_backend
+        let apply_result = if let Some(preview) = self.preview {
+            // The compositor may have reset gamma meanwhile, so show the preview again
+            let (temp, gamma) = preview.values();
+            self.backend
+                .apply_temperature_gamma(temp, gamma, &self.signal_state.running)
+        } else if smoothing_enabled
+            && supports_smoothing
             && !self.bypass_smoothing
             && values_changed
         {
@@ -610,25 +782,13 @@ impl Core {
             Ok(_) => {
                 tracker.record_state_update();
                 tracker.record_current_period(current_period);
-
-                if let Some(ref ipc_notifier) = self.ipc_notifier {
-                    if period_changed {
-                        #[cfg(debug_assertions)]
-                        eprintln!(
-                            "DEBUG: Sending PeriodChanged event from {}: {:?} -> {:?}",
-                            cause, prev_period, current_period
-                        );
-                        ipc_notifier.send_period_changed(prev_period, current_period);
-                    }
-
-                    #[cfg(debug_assertions)]
-                    eprintln!("DEBUG: Sending StateApplied event from {}", cause);
-                    ipc_notifier.send_state_applied(&self.runtime_state);
+                if self.preview.is_none() {
+                    self.notify_applied(Some(prev_period), reason);
                 }
             }
             Err(e) => {
                 log_pipe!();
-                log_error!("Failed to re-apply state after {cause}: {e}");
+                log_error!("Failed to re-apply state after {reason}: {e}");
                 log_indented!("Will retry on next cycle...");
             }
         }
@@ -651,7 +811,7 @@ impl Core {
             Ok(entering_transition) => {
                 if entering_transition {
                     tracker.record_state_update();
-                    if let Some(progress) = self.runtime_state.progress() {
+                    if let Some(progress) = self.runtime_state.phase().progress() {
                         let percentage_str = utils::format_progress_percentage(
                             progress,
                             tracker.previous_progress(),
@@ -672,7 +832,7 @@ impl Core {
                             }
                         );
                         tracker.update_progress(Some(progress));
-                        tracker.set_first_transition_logged(true);
+                        tracker.announce_transition();
                     }
                 } else if self.runtime_state.period().is_transitioning() {
                     tracker.record_state_update();
@@ -680,6 +840,12 @@ impl Core {
                     tracker.record_config_reload();
                 }
 
+                // The presets now in effect are the ones to fall back to if a later switch fails
+                if let Ok(applied) = crate::state::preset::PresetState::load() {
+                    *self.signal_state.current_preset.lock().unwrap() = applied.base;
+                    self.applied_preset_stack = applied.stack;
+                }
+
                 #[cfg(debug_assertions)]
                 eprintln!(
                     "DEBUG: Config reload complete, entering_transition={}",
@@ -690,12 +856,513 @@ impl Core {
                 log_pipe!();
                 log_error!("Failed to apply config changes: {e}");
                 log_indented!("Continuing with previous configuration");
+                self.restore_applied_preset();
             }
         }
 
         Ok(())
     }
 
+    /// Point the preset state back at the presets whose values are still
+    /// applied, so a switch that failed to apply doesn't stay marked active.
+    fn restore_applied_preset(&self) {
+        let applied = crate::state::preset::PresetState {
+            base: self.signal_state.current_preset.lock().unwrap().clone(),
+            stack: self.applied_preset_stack.clone(),
+        };
+        if crate::state::preset::PresetState::load().ok().as_ref() == Some(&applied) {
+            return;
+        }
+
+        match applied.restore() {
+            Ok(()) => log_indented!("Restored active preset: {}", applied.label()),
+            Err(e) => log_warning!("Failed to restore the previous preset: {e}"),
+        }
+    }
+
+    /// Carry out a preview request an IPC client sent. Failures are logged and leave the
+    /// preview as it was.
+    fn handle_preview(&mut self, tracker: &mut Context, request: PreviewRequest) -> Result<()> {
+        match request {
+            PreviewRequest::Set { temp, gamma } => {
+                if let Err(e) =
+                    self.backend
+                        .apply_temperature_gamma(temp, gamma, &self.signal_state.running)
+                {
+                    log_pipe!();
+                    log_warning!("Failed to apply preview: {e}");
+                    return Ok(());
+                }
+                if self.preview.is_none() {
+                    log_pipe!();
+                    log_info!("Previewing {temp}K @ {gamma}%, scheduled updates paused");
+                } else if self.debug_enabled {
+                    log_pipe!();
+                    log_debug!("Preview moved to {temp}K @ {gamma}%");
+                }
+                let announced_period =
+                    self.preview.map_or(self.runtime_state.period(), |preview| {
+                        preview.announced_period
+                    });
+                self.preview = Some(Preview {
+                    temp,
+                    gamma,
+                    announced_period,
+                });
+                self.notify_preview(PreviewAction::Set);
+            }
+            PreviewRequest::Cancel => {
+                self.guard.held_back.preview = None;
+                let Some(preview) = self.preview else {
+                    return Ok(());
+                };
+                if let Err(e) = self
+                    .backend
+                    .apply_transition_state(&self.runtime_state, &self.signal_state.running)
+                {
+                    log_pipe!();
+                    log_warning!("Failed to restore the scheduled values: {e}");
+                    return Ok(());
+                }
+                self.notify_preview(PreviewAction::Cancel);
+                self.preview = None;
+                tracker.record_state_update();
+                self.notify_applied(Some(preview.announced_period), ChangeReason::Preview);
+                log_pipe!();
+                log_info!("Preview cancelled, scheduled values restored");
+            }
+            PreviewRequest::Commit => {
+                // A position still held back is the one the client means to keep
+                if let Some((temp, gamma)) = self.guard.held_back.preview.take() {
+                    let announced_period =
+                        self.preview.map_or(self.runtime_state.period(), |preview| {
+                            preview.announced_period
+                        });
+                    self.preview = Some(Preview {
+                        temp,
+                        gamma,
+                        announced_period,
+                    });
+                }
+                let Some(preview) = self.preview else {
+                    log_pipe!();
+                    log_warning!("Nothing to commit, no preview is active");
+                    return Ok(());
+                };
+                let period = self.runtime_state.period();
+                let (temp_field, gamma_field) = preview::fields_for(period);
+                let config_path =
+                    match preview::write_to_active_config(period, preview.temp, preview.gamma) {
+                        Ok(path) => path,
+                        Err(e) => {
+                            log_pipe!();
+                            log_error!("Failed to commit preview: {e}");
+                            return Ok(());
+                        }
+                    };
+                log_block_start!("Committed preview to {}", utils::private_path(&config_path));
+                log_indented!("{temp_field} = {}", preview.temp.get());
+                log_indented!("{gamma_field} = {}", preview.gamma.get());
+                self.notify_preview(PreviewAction::Commit);
+
+                // Reload right away instead of waiting for the watcher, which may be off.
+                // The reload ends the preview from the committed values.
+                match Config::load() {
+                    Ok(config) => self.apply_reload(tracker, config)?,
+                    Err(e) => {
+                        log_pipe!();
+                        log_error!("Failed to reload the committed config: {e}");
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply targets an external scheduler pushed, with a smooth transition when smoothing
+    /// is on. Ignored outside external mode. Backend errors are logged and the next push or
+    /// wake applies again.
+    fn handle_external_targets(
+        &mut self,
+        tracker: &mut Context,
+        temp: Kelvin,
+        gamma: GammaPercent,
+    ) -> Result<()> {
+        let Some(target_state) = self.runtime_state.with_external_targets(temp, gamma) else {
+            log_pipe!();
+            log_warning!("Ignoring pushed targets, transition_mode is not \"external\"");
+            return Ok(());
+        };
+
+        log_pipe!();
+        log_decorated!("External targets: {temp}K @ {gamma}%");
+
+        let prev_period = self.runtime_state.period();
+        let previous = std::mem::replace(&mut self.runtime_state, target_state);
+        if self.preview.is_some() {
+            // Held back until the preview ends, which applies and announces the state then
+            return Ok(());
+        }
+
+        let smoothing_enabled = self.runtime_state.smoothing();
+        let supports_smoothing = self.backend.capabilities().smoothing;
+        let apply_result = if smoothing_enabled && supports_smoothing && !self.bypass_smoothing {
+            let mut transition = SmoothTransition::reload(&previous, &self.runtime_state)
+                .silent()
+                .no_announce();
+            let result = transition.execute(
+                self.backend.as_mut(),
+                &self.runtime_state,
+                &self.signal_state.running,
+                Some(&self.signal_state.interrupt),
+            );
+            if matches!(result, Ok(TransitionResult::Interrupted { .. })) {
+                self.signal_state.interrupt.store(false, Ordering::SeqCst);
+            }
+            result.map(|_| ())
+        } else {
+            self.backend
+                .apply_transition_state(&self.runtime_state, &self.signal_state.running)
+        };
+
+        match apply_result {
+            Ok(()) => {
+                tracker.record_state_update();
+                self.notify_applied(Some(prev_period), ChangeReason::External);
+            }
+            Err(e) => {
+                log_pipe!();
+                log_error!("Failed to apply external targets: {e}");
+                log_indented!("Will retry on the next push or wake...");
+            }
+        }
+        Ok(())
+    }
+
+    /// Log a power profile change and, mid-transition, update right away so the new
+    /// update interval applies from now on rather than after the current sleep.
+    fn handle_power_profile_change(&mut self, tracker: &mut Context) -> Result<()> {
+        if self.runtime_state.config().power_saving != PowerSaving::Auto {
+            return Ok(());
+        }
+        log_pipe!();
+        if self.runtime_state.power_saving() {
+            log_info!("Power saver profile active, updating less often without smoothing");
+        } else {
+            log_info!("Power saver profile off, resuming regular updates");
+        }
+
+        if self.runtime_state.period().is_transitioning() {
+            self.update_runtime_state();
+            self.apply_scheduled_state(tracker, ChangeReason::Schedule)?;
+        }
+        Ok(())
+    }
+
+    /// Rebuild the backend's outputs for `sunsetr refresh` and apply the current values
+    /// again. A failed rebuild still reapplies to whatever outputs are left.
+    fn handle_refresh(&mut self, tracker: &mut Context) -> Result<()> {
+        log_block_start!("Refreshing all outputs");
+        if let Err(e) = self.backend.refresh() {
+            log_pipe!();
+            log_warning!(
+                "Failed to refresh the {} backend: {e}",
+                self.backend.backend_name()
+            );
+        }
+        self.recover_state(tracker, ChangeReason::Refresh)
+    }
+
+    /// `config` as the backend should see it: presentation mode on every output while a
+    /// neutral hold is on or color shifting is paused.
+    fn backend_config<'a>(&self, config: &'a Config) -> Cow<'a, Config> {
+        if !self.neutral_hold && self.pause.is_none() {
+            return Cow::Borrowed(config);
+        }
+        let mut held = config.clone();
+        held.presentation = Some(Presentation::default());
+        Cow::Owned(held)
+    }
+
+    /// Hold every output neutral for a screenshot, or go back to what the config says, and
+    /// tell IPC clients once the backend has it. The schedule carries on underneath.
+    fn handle_neutral_hold(&mut self, held: bool) {
+        self.neutral_hold = held;
+        if self.debug_enabled {
+            log_pipe!();
+            if held {
+                log_debug!("Holding every output neutral for a screenshot");
+            } else {
+                log_debug!("Neutral hold released");
+            }
+        }
+        let config = self
+            .backend_config(self.runtime_state.config())
+            .into_owned();
+        if let Err(e) = self.backend.reload_config(&config) {
+            log_pipe!();
+            log_warning!("Failed to hold the outputs neutral: {e}");
+        }
+        if let Some(ipc_notifier) = &self.ipc_notifier {
+            ipc_notifier.send_neutral_held(held);
+        }
+    }
+
+    /// Carry out a pause request, flipping the pause when `paused` is `None`. Requests that
+    /// would change nothing are answered at once, the rest pass through the guard.
+    fn request_pause(
+        &mut self,
+        tracker: &mut Context,
+        paused: Option<bool>,
+        until: Option<PauseUntil>,
+    ) -> Result<()> {
+        // A toggle flips whatever the last request left it at
+        let pending = self.guard.held_back.pause;
+        let paused = paused.unwrap_or(!pending.map_or(self.pause.is_some(), |(paused, _)| paused));
+        let unchanged = if paused {
+            until.is_none() && self.pause == Some(Pause { until: None })
+        } else {
+            self.pause.is_none()
+        };
+        if pending.is_none() && unchanged {
+            // Only the answer to send
+            self.handle_pause(paused, None);
+            return Ok(());
+        }
+        let requested = HeldBack {
+            pause: Some((paused, until)),
+            ..HeldBack::default()
+        };
+        self.handle_request(tracker, requested)
+    }
+
+    /// Pause color shifting, until `until` if given, or resume it. The schedule carries on
+    /// underneath, so resuming shows the values it has reached. Answered by a state_applied
+    /// event even when nothing changed, which `sunsetr toggle` and `pause` wait for.
+    fn handle_pause(&mut self, paused: bool, until: Option<PauseUntil>) {
+        let now = crate::time::source::now();
+        let pause = paused.then(|| Pause {
+            until: until.and_then(|until| until.resolve(&self.runtime_state, now)),
+        });
+        if pause != self.pause {
+            let was_paused = self.pause.is_some();
+            self.pause = pause;
+            if was_paused != paused {
+                let config = self
+                    .backend_config(self.runtime_state.config())
+                    .into_owned();
+                if let Err(e) = self.backend.reload_config(&config) {
+                    log_pipe!();
+                    log_warning!("Failed to apply the pause: {e}");
+                }
+            }
+            match pause {
+                Some(_) if was_paused => log_block_start!("Pause changed"),
+                Some(_) => log_block_start!("Color shifting paused, outputs at 6500K and 100%"),
+                None => {
+                    let (temp, gamma) = self.runtime_state.values();
+                    log_block_start!("Color shifting resumed at {temp}K @ {gamma}%");
+                }
+            }
+            match pause.and_then(|pause| pause.until) {
+                Some(until) => log_indented!("Resuming at {}", until.format("%Y-%m-%d %H:%M")),
+                None if paused => log_indented!("Until resumed with 'sunsetr toggle'"),
+                None => {}
+            }
+            if let Some(ipc_notifier) = &self.ipc_notifier {
+                ipc_notifier.send_pause_changed(pause);
+            }
+        }
+        if until == Some(PauseUntil::Event(PauseEvent::Sunrise))
+            && self.pause == Some(Pause { until: None })
+        {
+            log_pipe!();
+            log_warning!("The current schedule has no sunrise to resume at");
+        }
+        self.notify_applied(None, ChangeReason::Pause);
+    }
+
+    /// Resume color shifting once a pause with an end reaches it. Returns the time left
+    /// otherwise so the loop wakes for it.
+    fn end_pause(&mut self) -> Option<Duration> {
+        let until = self.pause?.until?;
+        let remaining = (until - crate::time::source::now())
+            .to_std()
+            .unwrap_or_default();
+        if !remaining.is_zero() {
+            return Some(remaining);
+        }
+        log_block_start!("Pause time is up");
+        self.handle_pause(false, None);
+        None
+    }
+
+    /// Apply a preview position, pushed targets, test values or a pause another program
+    /// asked for, or hold it back until the last requested change is
+    /// [`guard::MIN_REQUEST_INTERVAL`] old. Held back requests of the same kind are
+    /// replaced, so only the latest shows.
+    fn handle_request(&mut self, tracker: &mut Context, requested: HeldBack) -> Result<()> {
+        let now = std::time::Instant::now();
+        if let Some(wait) = self.guard.wait(now) {
+            self.guard.held_back.merge(requested);
+            if self.debug_enabled {
+                log_pipe!();
+                log_debug!("Holding back a requested change for {}ms", wait.as_millis());
+            }
+            return Ok(());
+        }
+        self.guard.record(now);
+        self.apply_requested(tracker, requested)
+    }
+
+    /// Apply requests held back by [`Self::handle_request`] whose wait is over. Returns how
+    /// long the rest still have to wait so the loop wakes for them.
+    fn release_held_back(&mut self, tracker: &mut Context) -> Result<Option<Duration>> {
+        let now = std::time::Instant::now();
+        match self.guard.release(now) {
+            Ok(Some(requested)) => {
+                self.guard.record(now);
+                self.apply_requested(tracker, requested)?;
+                Ok(None)
+            }
+            Ok(None) => Ok(None),
+            Err(remaining) => Ok(Some(remaining)),
+        }
+    }
+
+    fn apply_requested(&mut self, tracker: &mut Context, requested: HeldBack) -> Result<()> {
+        if let Some((temp, gamma)) = requested.preview {
+            self.handle_preview(tracker, PreviewRequest::Set { temp, gamma })?;
+        }
+        if let Some((temp, gamma)) = requested.external {
+            self.handle_external_targets(tracker, temp, gamma)?;
+        }
+        if let Some((paused, until)) = requested.pause {
+            self.handle_pause(paused, until);
+        }
+        if let Some((temperature, gamma)) = requested.test {
+            self.handle_test_mode(crate::io::signals::TestModeParams { temperature, gamma })?;
+        }
+        Ok(())
+    }
+
+    /// Show the test values until `sunsetr test` ends, then go back to the schedule.
+    fn handle_test_mode(&mut self, test_params: crate::io::signals::TestModeParams) -> Result<()> {
+        if self.signal_state.in_test_mode.load(Ordering::Relaxed) {
+            log_pipe!();
+            log_warning!("Already in test mode, ignoring new test request");
+            log_indented!("Exit the current test mode first (press Escape)");
+            log_end!();
+            return Ok(());
+        }
+
+        #[cfg(debug_assertions)]
+        eprintln!(
+            "DEBUG: Main loop received test signal: {}K @ {}%",
+            test_params.temperature, test_params.gamma
+        );
+
+        self.signal_state
+            .in_test_mode
+            .store(true, Ordering::Relaxed);
+        // Test mode restores the scheduled values when it exits
+        self.notify_preview(PreviewAction::Cancel);
+        self.preview = None;
+        self.guard.held_back.preview = None;
+
+        let result = crate::commands::test::run_test_mode_loop(
+            test_params,
+            &mut self.backend,
+            &self.signal_state,
+            &self.runtime_state,
+            self.debug_enabled,
+        );
+
+        self.signal_state
+            .in_test_mode
+            .store(false, Ordering::Relaxed);
+        // Restoring the schedule is a change too, so the next request waits its turn
+        self.guard.record(std::time::Instant::now());
+
+        #[cfg(debug_assertions)]
+        eprintln!("DEBUG: Returned from test mode loop, resuming main loop");
+
+        if result? && let Some(ref ipc_notifier) = self.ipc_notifier {
+            ipc_notifier.send_override_expired(OverrideKind::Test, Vec::new());
+        }
+        Ok(())
+    }
+
+    /// Tell IPC clients what happened to the preview, if one is active.
+    fn notify_preview(&self, action: PreviewAction) {
+        if let (Some(preview), Some(ipc_notifier)) = (self.preview, &self.ipc_notifier) {
+            let (temp, gamma) = preview.values();
+            ipc_notifier.send_preview_changed(action, temp, gamma);
+        }
+    }
+
+    /// Undo a `preset --for` switch whose time is up. Writing the restored presets reloads
+    /// the config through the watcher. Returns the time left on a pending expiry so the loop
+    /// wakes for it. Simulations leave the real state alone.
+    fn expire_preset(&self) -> Option<Duration> {
+        if crate::time::source::is_simulated() {
+            return None;
+        }
+        let expiry = match crate::state::preset::get_preset_expiry() {
+            Ok(Some(expiry)) => expiry,
+            Ok(None) => return None,
+            Err(e) => {
+                log_pipe!();
+                log_warning!("Failed to read preset expiry: {e}");
+                return None;
+            }
+        };
+
+        let remaining = expiry.remaining(crate::time::source::now());
+        if !remaining.is_zero() {
+            return Some(remaining);
+        }
+        log_block_start!("Preset time is up, back to {}", expiry.restore.label());
+        if let Err(e) = expiry.expire() {
+            log_pipe!();
+            log_warning!("Failed to restore presets: {e}");
+        }
+        None
+    }
+
+    /// Drop a `sunsetr adjust` offset once the day it was meant for is over. The values stop
+    /// including it right then, and removing the file reloads the config through the
+    /// watcher. Returns the time left so the loop wakes for it. Simulations leave the real
+    /// state alone.
+    fn expire_value_offset(&self) -> Option<Duration> {
+        let offset = self.runtime_state.config().value_offset?;
+        if crate::time::source::is_simulated() {
+            return None;
+        }
+        let remaining = (offset.expires_at - crate::time::source::now())
+            .to_std()
+            .unwrap_or_default();
+        if !remaining.is_zero() {
+            return Some(remaining);
+        }
+        match crate::state::value_offset::clear() {
+            Ok(true) => {}
+            // Already dropped, the reload is on its way
+            Ok(false) => return None,
+            Err(e) => {
+                log_pipe!();
+                log_warning!("Failed to drop the expired adjustment: {e}");
+                return None;
+            }
+        }
+
+        log_block_start!("Adjustment of {} ended", offset.describe());
+        if let Some(ref ipc_notifier) = self.ipc_notifier {
+            ipc_notifier.send_override_expired(OverrideKind::Adjustment, Vec::new());
+        }
+        None
+    }
+
     /// Monitor the time-based state and apply changes until a shutdown signal
     /// or the end of a simulation.
     fn main_loop(&mut self) -> Result<()> {
@@ -706,6 +1373,7 @@ impl Core {
 
         #[cfg(debug_assertions)]
         let mut debug_loop_count: u64 = 0;
+        let mut iteration: u64 = 0;
 
         'main_loop: while self.signal_state.running.load(Ordering::SeqCst)
             && !crate::time::source::simulation_ended()
@@ -716,11 +1384,21 @@ impl Core {
                 eprintln!("DEBUG: Main loop iteration {debug_loop_count} starting");
             }
 
+            iteration += 1;
+            let iteration_span = Span::start("main loop iteration");
+            let preset_expires_in = self.expire_preset();
+            let pause_ends_in = self.end_pause();
+            let offset_ends_in = self.expire_value_offset();
+            let requests_wait = self.release_held_back(&mut tracker)?;
+
             // CRITICAL: this must run before any time-based re-evaluation to
             // prevent race conditions when we just slept to the end of a
             // transitioning period.
-            if tracker.slept_to_transition_boundary() {
-                tracker.set_sleeping_to_boundary(false);
+            if tracker.boundary().take_due(
+                self.runtime_state.period(),
+                self.runtime_state.next_period_start(),
+                crate::time::source::now(),
+            ) {
                 let (new_state, change) = self.runtime_state.with_next_period();
 
                 #[cfg(debug_assertions)]
@@ -732,48 +1410,31 @@ impl Core {
                 );
 
                 self.runtime_state = new_state;
-                if change != crate::core::period::StateChange::None {
-                    self.backend
-                        .apply_transition_state(&self.runtime_state, &self.signal_state.running)?;
-                    tracker.record_state_update();
-
-                    if let Some(ref ipc_notifier) = self.ipc_notifier {
-                        let current_period = self.runtime_state.period();
-
-                        if tracker.is_period_change(current_period) {
-                            #[cfg(debug_assertions)]
-                            eprintln!(
-                                "DEBUG [forced_transition]: Sending PeriodChanged event: {:?} -> {:?}",
-                                tracker.previous_period().unwrap_or(current_period),
-                                current_period
-                            );
-                            ipc_notifier.send_period_changed(
-                                tracker.previous_period().unwrap_or(current_period),
-                                current_period,
-                            );
-                        }
-
-                        #[cfg(debug_assertions)]
-                        eprintln!("DEBUG [forced_transition]: Sending StateApplied event");
-                        ipc_notifier.send_state_applied(&self.runtime_state);
-                    }
+                let applied = change != crate::core::period::StateChange::None;
+                if applied {
+                    self.apply_scheduled_state(&mut tracker, ChangeReason::Boundary)?;
                 }
+                self.record_iteration(iteration, Decision::Boundary, applied, None);
 
                 tracker.record_current_period(self.runtime_state.period());
-                tracker.update_progress(self.runtime_state.progress());
+                tracker.update_progress(self.runtime_state.phase().progress());
                 if !self.runtime_state.period().is_transitioning() {
                     tracker.reset_for_stable_period();
                 }
                 continue 'main_loop;
             }
 
-            let should_update = if tracker.handle_first_iteration() {
+            let loop_state = tracker.next_iteration();
+            let mut decision = Decision::NoChange;
+            let should_update = if loop_state == LoopState::Starting {
                 #[cfg(debug_assertions)]
                 eprintln!("DEBUG: First iteration, skipping state update check");
+                decision = Decision::Starting;
                 false
-            } else if tracker.handle_config_reload_skip() {
+            } else if loop_state == LoopState::ReloadApplied {
                 #[cfg(debug_assertions)]
                 eprintln!("DEBUG: Config reload handled, skipping redundant state update");
+                decision = Decision::ReloadApplied;
                 false
             } else if self.runtime_state.period().is_transitioning() {
                 let update_interval_secs = self.runtime_state.effective_update_interval_secs();
@@ -781,6 +1442,7 @@ impl Core {
                 if !tracker.should_update_during_transition(update_interval_secs) {
                     #[cfg(debug_assertions)]
                     eprintln!("DEBUG: Skipping update. Not time yet.");
+                    decision = Decision::Waiting;
                     false
                 } else {
                     let state_change = self.update_runtime_state();
@@ -794,8 +1456,21 @@ impl Core {
                     update_needed
                 }
             } else {
+                let previous_values = self.runtime_state.values();
                 let state_change = self.update_runtime_state();
-                let update_needed = !matches!(state_change, StateChange::None);
+                // The wind-down and an ending adjustment move the values without a period change
+                let winding_down = matches!(state_change, StateChange::None)
+                    && self.runtime_state.values() != previous_values;
+                if winding_down && let Some(progress) = self.runtime_state.winddown_progress() {
+                    let (temp, gamma) = self.runtime_state.values();
+                    log_decorated!(
+                        "Wind-down {:.0}% complete: {}K @ {:.1}%",
+                        progress * 100.0,
+                        temp,
+                        gamma
+                    );
+                }
+                let update_needed = !matches!(state_change, StateChange::None) || winding_down;
 
                 #[cfg(debug_assertions)]
                 eprintln!(
@@ -812,42 +1487,16 @@ impl Core {
                     self.runtime_state.period()
                 );
 
-                match self
-                    .backend
-                    .apply_transition_state(&self.runtime_state, &self.signal_state.running)
-                {
-                    Ok(_) => {
+                match self.apply_scheduled_state(&mut tracker, ChangeReason::Schedule) {
+                    Ok(()) => {
                         #[cfg(debug_assertions)]
                         eprintln!("DEBUG: State application successful");
-
-                        tracker.record_state_update();
-
-                        if let Some(ref ipc_notifier) = self.ipc_notifier {
-                            let current_period = self.runtime_state.period();
-
-                            if tracker.is_period_change(current_period) {
-                                #[cfg(debug_assertions)]
-                                eprintln!(
-                                    "DEBUG: Sending PeriodChanged event: {:?} -> {:?}",
-                                    tracker.previous_period().unwrap_or(current_period),
-                                    current_period
-                                );
-                                ipc_notifier.send_period_changed(
-                                    tracker.previous_period().unwrap_or(current_period),
-                                    current_period,
-                                );
-                            }
-
-                            #[cfg(debug_assertions)]
-                            eprintln!(
-                                "DEBUG: Sending StateApplied event from main loop (state was applied)"
-                            );
-                            ipc_notifier.send_state_applied(&self.runtime_state);
-                        }
+                        decision = Decision::Update;
                     }
                     Err(e) => {
                         #[cfg(debug_assertions)]
                         eprintln!("DEBUG: State application failed: {e}");
+                        decision = Decision::ApplyFailed;
 
                         log_pipe!();
                         log_error!("Failed to apply state: {e}");
@@ -863,34 +1512,70 @@ impl Core {
                 tracker.record_state_update();
             }
 
-            let calculated_sleep_duration = Self::determine_sleep_duration(
+            let mut calculated_sleep_duration = Self::determine_sleep_duration(
                 &self.runtime_state,
                 &mut tracker,
                 self.debug_enabled,
                 should_log_progress,
             )?;
+            if let Some(expires_in) = preset_expires_in {
+                calculated_sleep_duration = calculated_sleep_duration.min(expires_in);
+            }
+            if let Some(ends_in) = pause_ends_in {
+                calculated_sleep_duration = calculated_sleep_duration.min(ends_in);
+            }
+            if let Some(ends_in) = offset_ends_in {
+                calculated_sleep_duration = calculated_sleep_duration.min(ends_in);
+            }
+            if let Some(wait) = requests_wait {
+                calculated_sleep_duration = calculated_sleep_duration.min(wait);
+            }
+            self.record_iteration(
+                iteration,
+                decision,
+                decision == Decision::Update,
+                Some(calculated_sleep_duration),
+            );
+            drop(iteration_span);
 
             use std::sync::mpsc::RecvTimeoutError;
 
             // Poll backend hotplug periodically during long sleeps.
-            let mut poll_interval = Duration::from_millis(10);
+            let mut poll_interval = if self.runtime_state.power_saving() {
+                Duration::from_millis(POWER_SAVING_POLL_INTERVAL_MS)
+            } else {
+                Duration::from_millis(10)
+            };
             if poll_interval > calculated_sleep_duration {
                 poll_interval = calculated_sleep_duration;
             }
 
-            // In simulation mode crate::time::source::sleep already applies the time
+            // The monotonic clock stops while suspended, so the wall clock also decides when
+            // a real sleep is over. Simulated time is left out: under a high multiplier a
+            // few milliseconds of lag already look like an oversleep.
+            let deadline = if crate::time::source::is_simulated() {
+                None
+            } else {
+                chrono::Duration::from_std(calculated_sleep_duration)
+                    .ok()
+                    .and_then(|duration| crate::time::source::now().checked_add_signed(duration))
+            };
+
+            // In simulation mode the time source's sleep already applies the time
             // scaling, so we cannot pass the full duration to recv_timeout. Handle the
             // simulated sleep on a separate thread instead.
             let recv_result = if crate::time::source::is_simulated() {
                 let sleep_handle = std::thread::spawn({
                     let duration = calculated_sleep_duration;
+                    let time_source = crate::time::source::current();
                     move || {
-                        crate::time::source::sleep(duration);
+                        time_source.sleep(duration);
                     }
                 });
 
                 loop {
                     let _ = self.backend.poll_hotplug();
+                    self.send_heartbeat_if_due();
 
                     match self
                         .signal_state
@@ -920,7 +1605,11 @@ impl Core {
                         Ok(msg) => break Ok(msg),
                         Err(RecvTimeoutError::Timeout) => {
                             let _ = self.backend.poll_hotplug();
-                            if start.elapsed() >= calculated_sleep_duration {
+                            self.send_heartbeat_if_due();
+                            self.dock.poll(self.backend.output_names());
+                            if start.elapsed() >= calculated_sleep_duration
+                                || deadline.is_some_and(|d| crate::time::source::now() >= d)
+                            {
                                 break Err(RecvTimeoutError::Timeout);
                             }
                             remaining = calculated_sleep_duration.saturating_sub(start.elapsed());
@@ -933,49 +1622,90 @@ impl Core {
             match recv_result {
                 Ok(signal_msg) => match signal_msg {
                     crate::io::signals::SignalMessage::ResumeFromSleep => {
-                        self.recover_state(&mut tracker, "wake")?;
+                        self.recover_state(&mut tracker, ChangeReason::Wake)?;
                     }
                     crate::io::signals::SignalMessage::TimeChange => {
-                        self.recover_state(&mut tracker, "clock jump")?;
+                        self.recover_state(&mut tracker, ChangeReason::ClockJump)?;
                     }
                     crate::io::signals::SignalMessage::Reload(config) => {
+                        self.guard.held_back.preview = None;
+                        self.notify_preview(PreviewAction::Cancel);
                         self.apply_reload(&mut tracker, *config)?;
                     }
-                    crate::io::signals::SignalMessage::TestMode(test_params) => {
-                        if self.signal_state.in_test_mode.load(Ordering::Relaxed) {
-                            log_pipe!();
-                            log_warning!("Already in test mode, ignoring new test request");
-                            log_indented!("Exit the current test mode first (press Escape)");
-                            log_end!();
+                    crate::io::signals::SignalMessage::Preview(PreviewRequest::Set {
+                        temp,
+                        gamma,
+                    }) => {
+                        let requested = HeldBack {
+                            preview: Some((temp, gamma)),
+                            ..HeldBack::default()
+                        };
+                        self.handle_request(&mut tracker, requested)?;
+                    }
+                    crate::io::signals::SignalMessage::Preview(request) => {
+                        self.handle_preview(&mut tracker, request)?;
+                    }
+                    crate::io::signals::SignalMessage::ExternalTargets { temp, gamma } => {
+                        let requested = HeldBack {
+                            external: Some((temp, gamma)),
+                            ..HeldBack::default()
+                        };
+                        self.handle_request(&mut tracker, requested)?;
+                    }
+                    crate::io::signals::SignalMessage::PowerProfileChanged => {
+                        self.handle_power_profile_change(&mut tracker)?;
+                    }
+                    crate::io::signals::SignalMessage::DarkModeChanged => {
+                        // The monitor keeps running after a reload to another mode
+                        if self.runtime_state.config().transition_mode == TransitionMode::Darkman {
+                            let mode = match crate::io::darkman::is_dark() {
+                                Some(true) => "dark",
+                                _ => "light",
+                            };
+                            log_decorated!("darkman switched to {mode} mode");
+                            self.recover_state(&mut tracker, ChangeReason::Darkman)?;
+                        }
+                    }
+                    crate::io::signals::SignalMessage::MediaPlaybackChanged => {
+                        // The monitor keeps running after a reload drops [media]
+                        if self.runtime_state.config().media.is_some() {
+                            if crate::io::idle::media_playing() {
+                                log_decorated!("Media playing, holding back the gamma drop");
+                            } else {
+                                log_decorated!("Media stopped, back to the scheduled gamma");
+                            }
+                            self.recover_state(&mut tracker, ChangeReason::Media)?;
+                        }
+                    }
+                    crate::io::signals::SignalMessage::Refresh => {
+                        self.handle_refresh(&mut tracker)?;
+                    }
+                    crate::io::signals::SignalMessage::NeutralHold(held) => {
+                        self.handle_neutral_hold(held);
+                    }
+                    crate::io::signals::SignalMessage::Pause { paused, until } => {
+                        self.request_pause(&mut tracker, paused, until)?;
+                    }
+                    crate::io::signals::SignalMessage::ReduceMotion(enabled) => {
+                        crate::core::smoothing::set_reduce_motion(enabled);
+                        let configured = self.runtime_state.config().reduce_motion;
+                        if crate::core::smoothing::reduce_motion(configured) {
+                            log_decorated!("Reduced motion on, changes now apply at once");
                         } else {
-                            #[cfg(debug_assertions)]
-                            eprintln!(
-                                "DEBUG: Main loop received test signal: {}K @ {}%",
-                                test_params.temperature, test_params.gamma
-                            );
-
-                            self.signal_state
-                                .in_test_mode
-                                .store(true, Ordering::Relaxed);
-
-                            let result = crate::commands::test::run_test_mode_loop(
-                                test_params,
-                                &mut self.backend,
-                                &self.signal_state,
-                                &self.runtime_state,
-                                self.debug_enabled,
-                            );
-
-                            self.signal_state
-                                .in_test_mode
-                                .store(false, Ordering::Relaxed);
-
-                            #[cfg(debug_assertions)]
-                            eprintln!("DEBUG: Returned from test mode loop, resuming main loop");
-
-                            result?;
+                            log_decorated!("Reduced motion off, smoothing follows the config");
                         }
                     }
+                    crate::io::signals::SignalMessage::TestMode(None) => {
+                        // Exit request for a test mode that already ended, e.g. expired
+                        log_decorated!("Test mode already ended");
+                    }
+                    crate::io::signals::SignalMessage::TestMode(Some(test_params)) => {
+                        let requested = HeldBack {
+                            test: Some((test_params.temperature, test_params.gamma)),
+                            ..HeldBack::default()
+                        };
+                        self.handle_request(&mut tracker, requested)?;
+                    }
                     crate::io::signals::SignalMessage::Shutdown => {
                         #[cfg(debug_assertions)]
                         eprintln!(
@@ -987,8 +1717,20 @@ impl Core {
                     }
                 },
                 Err(RecvTimeoutError::Timeout) => {
-                    #[cfg(debug_assertions)]
-                    eprintln!("DEBUG: Sleep duration elapsed naturally");
+                    if let Some(late) = oversleep(deadline, crate::time::source::now()) {
+                        if self.debug_enabled {
+                            log_pipe!();
+                            log_debug!(
+                                "Woke {}s late, jumping to the current state",
+                                late.as_secs()
+                            );
+                        }
+                        // Periods passed while asleep are skipped, not replayed one by one
+                        self.recover_state(&mut tracker, ChangeReason::LateWake)?;
+                    } else {
+                        #[cfg(debug_assertions)]
+                        eprintln!("DEBUG: Sleep duration elapsed naturally");
+                    }
                 }
                 Err(RecvTimeoutError::Disconnected) => {
                     if !self.signal_state.running.load(Ordering::SeqCst) {
@@ -1013,10 +1755,37 @@ impl Core {
         Ok(())
     }
 
+    /// Write the iteration to the simulation log when running `--simulate --log`. Values held
+    /// back by a preview were not applied.
+    fn record_iteration(
+        &self,
+        iteration: u64,
+        decision: Decision,
+        applied: bool,
+        sleep: Option<Duration>,
+    ) {
+        if !crate::time::source::is_simulated() {
+            return;
+        }
+        let applied = (applied && self.preview.is_none()).then(|| {
+            let (temp, gamma) = self.runtime_state.values();
+            AppliedValues { temp, gamma }
+        });
+        record_iteration(&IterationRecord {
+            iteration,
+            time: crate::time::source::now(),
+            decision,
+            period: self.runtime_state.period(),
+            progress: self.runtime_state.phase().progress(),
+            applied,
+            sleep_secs: sleep.map(|sleep| sleep.as_secs_f64()),
+        });
+    }
+
     /// Compute how long to sleep before the next loop iteration.
     ///
     /// When a transitioning period would end within one update interval, this
-    /// flags the tracker so the next iteration advances to the next period. It
+    /// arms the tracker's boundary so the next iteration advances to the next period. It
     /// also emits the progress and next-period log lines as a side effect.
     fn determine_sleep_duration(
         runtime_state: &RuntimeState,
@@ -1024,32 +1793,23 @@ impl Core {
         debug_enabled: bool,
         should_log: bool,
     ) -> Result<Duration> {
-        let sleep_duration = if runtime_state.period().is_transitioning() {
-            let update_interval =
-                Duration::from_secs(runtime_state.effective_update_interval_secs());
-
-            if let Some(time_remaining) = runtime_state.time_until_transition_end() {
-                if time_remaining < update_interval {
-                    tracker.set_sleeping_to_boundary(true);
+        let sleep_duration = runtime_state.time_until_next_update();
 
-                    #[cfg(debug_assertions)]
-                    eprintln!(
-                        "DEBUG [determine_sleep_duration]: Sleeping to boundary, time_remaining={:.3}s",
-                        time_remaining.as_secs_f64()
-                    );
+        // Shorter than an update interval only when the transition ends first
+        if runtime_state.period().is_transitioning()
+            && sleep_duration < Duration::from_secs(runtime_state.effective_update_interval_secs())
+            && let Some(end) = runtime_state.next_period_start()
+        {
+            tracker.boundary().arm(runtime_state.period(), end);
 
-                    time_remaining
-                } else {
-                    update_interval
-                }
-            } else {
-                update_interval
-            }
-        } else {
-            runtime_state.time_until_next_event()
-        };
+            #[cfg(debug_assertions)]
+            eprintln!(
+                "DEBUG [determine_sleep_duration]: Sleeping to boundary, time_remaining={:.3}s",
+                sleep_duration.as_secs_f64()
+            );
+        }
 
-        if let Some(progress) = runtime_state.progress() {
+        if let Some(progress) = runtime_state.phase().progress() {
             #[cfg(debug_assertions)]
             {
                 let current_percentage = progress * 100.0;
@@ -1077,11 +1837,9 @@ impl Core {
                     percentage_str, display_secs
                 );
 
-                if debug_enabled {
-                    log_block_start!("{}", log_message);
-                } else if !tracker.first_transition_logged() {
+                // Debug output keeps every progress line a block of its own
+                if debug_enabled || tracker.announce_transition() {
                     log_block_start!("{}", log_message);
-                    tracker.set_first_transition_logged(true);
                 } else {
                     log_decorated!("{}", log_message);
                 }
@@ -1091,10 +1849,13 @@ impl Core {
         } else {
             tracker.reset_for_stable_period();
 
+            // The wind-down can wake the loop before the next transition
+            let until_transition = runtime_state.time_until_next_event();
+
             if debug_enabled && !runtime_state.period().is_static() {
                 let now = crate::time::source::now();
                 let next_transition_time_raw =
-                    now + chrono::Duration::milliseconds(sleep_duration.as_millis() as i64);
+                    now + chrono::Duration::milliseconds(until_transition.as_millis() as i64);
 
                 let millis = next_transition_time_raw.timestamp_millis();
                 let remainder_millis = millis % 1000;
@@ -1162,10 +1923,10 @@ impl Core {
                 .unwrap_or(true);
 
             if just_entered_stable
-                && sleep_duration >= Duration::from_secs(1)
+                && until_transition >= Duration::from_secs(1)
                 && !runtime_state.period().is_static()
             {
-                let total_seconds = utils::format_duration_seconds_ceil(sleep_duration);
+                let total_seconds = utils::format_duration_seconds_ceil(until_transition);
                 let hours = total_seconds / 3600;
                 let minutes = (total_seconds % 3600) / 60;
                 let seconds = total_seconds % 60;
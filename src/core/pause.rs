@@ -0,0 +1,121 @@
+//! Pausing color shifting, until resumed or until a set time.
+//!
+//! While paused every output shows 6500K at 100%. The schedule keeps running underneath,
+//! and a pause with an end resumes by itself once Core's main loop reaches it. Ends are
+//! resolved when the request arrives: `sunrise` against the schedule running then, and
+//! `tomorrow` as the coming local midnight.
+
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::core::runtime_state::RuntimeState;
+
+/// A moment a pause can last until, named rather than given as a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseEvent {
+    /// The start of the next sunrise.
+    Sunrise,
+    /// The coming midnight.
+    Tomorrow,
+}
+
+impl PauseEvent {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "sunrise" => Some(Self::Sunrise),
+            "tomorrow" => Some(Self::Tomorrow),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PauseEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sunrise => write!(f, "sunrise"),
+            Self::Tomorrow => write!(f, "tomorrow"),
+        }
+    }
+}
+
+/// When a requested pause ends by itself, sent as `"sunrise"`, `"tomorrow"` or a time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PauseUntil {
+    Event(PauseEvent),
+    Time(DateTime<Local>),
+}
+
+impl PauseUntil {
+    /// The time this end falls on as seen from `now`, or `None` for a sunrise the schedule
+    /// in `runtime_state` doesn't have.
+    pub fn resolve(
+        self,
+        runtime_state: &RuntimeState,
+        now: DateTime<Local>,
+    ) -> Option<DateTime<Local>> {
+        match self {
+            Self::Time(time) => Some(time),
+            Self::Event(PauseEvent::Sunrise) => runtime_state.next_sunrise_start(),
+            Self::Event(PauseEvent::Tomorrow) => Some(next_midnight(now)),
+        }
+    }
+}
+
+/// Color shifting is paused, with the time it resumes by itself if any.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pause {
+    pub until: Option<DateTime<Local>>,
+}
+
+/// The first midnight after `now`. On a day that skips it for daylight saving, the first
+/// time that exists after it.
+pub(crate) fn next_midnight(now: DateTime<Local>) -> DateTime<Local> {
+    let tomorrow = now.date_naive() + TimeDelta::days(1);
+    (0..24)
+        .find_map(|hour| {
+            tomorrow
+                .and_hms_opt(hour, 0, 0)?
+                .and_local_timezone(Local)
+                .earliest()
+        })
+        .unwrap_or(now + TimeDelta::days(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn ends_are_sent_as_names_or_times() {
+        let until: PauseUntil = serde_json::from_str(r#""sunrise""#).unwrap();
+        assert_eq!(until, PauseUntil::Event(PauseEvent::Sunrise));
+        let until: PauseUntil = serde_json::from_str(r#""tomorrow""#).unwrap();
+        assert_eq!(until, PauseUntil::Event(PauseEvent::Tomorrow));
+
+        let time = Local.with_ymd_and_hms(2024, 6, 21, 18, 30, 0).unwrap();
+        let json = serde_json::to_string(&PauseUntil::Time(time)).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PauseUntil>(&json).unwrap(),
+            PauseUntil::Time(time)
+        );
+        assert!(serde_json::from_str::<PauseUntil>(r#""noon""#).is_err());
+    }
+
+    #[test]
+    fn tomorrow_is_the_coming_midnight() {
+        let evening = Local.with_ymd_and_hms(2024, 6, 21, 18, 30, 0).unwrap();
+        assert_eq!(
+            next_midnight(evening),
+            Local.with_ymd_and_hms(2024, 6, 22, 0, 0, 0).unwrap()
+        );
+        let past_midnight = Local.with_ymd_and_hms(2024, 6, 22, 0, 10, 0).unwrap();
+        assert_eq!(
+            next_midnight(past_midnight),
+            Local.with_ymd_and_hms(2024, 6, 23, 0, 0, 0).unwrap()
+        );
+    }
+}
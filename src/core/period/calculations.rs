@@ -5,7 +5,9 @@ use chrono::{DateTime, Local, NaiveTime};
 use chrono_tz::Tz;
 use std::time::Duration as StdDuration;
 
-use crate::common::constants::{DEFAULT_SUNRISE, DEFAULT_SUNSET};
+use crate::common::constants::{
+    DEFAULT_SUNRISE, DEFAULT_SUNSET, MINIMUM_TRANSITION_STEPS, MINIMUM_UPDATE_INTERVAL_SEC,
+};
 use crate::config::{Config, TransitionMode};
 
 // Just Noticeable Difference in mireds for adaptive interval calculation.
@@ -21,10 +23,85 @@ const ADAPTIVE_JND_GAMMA: f64 = 0.6;
 ///
 /// Geo and static modes short-circuit to their own paths before reaching this.
 ///
+/// The sunset window ends by `night_latest_start` when one is set, and the sunrise window
+/// moves earlier to keep `min_day_duration` of day.
+///
 /// Returns (sunset_start, sunset_end, sunrise_start, sunrise_end).
 pub fn calculate_transition_windows(
     config: &Config,
 ) -> (NaiveTime, NaiveTime, NaiveTime, NaiveTime) {
+    let (sunset_start, sunset_end, sunrise_start, sunrise_end) = configured_windows(config);
+    let (sunset_start, sunset_end) = match config
+        .night_latest_start_time()
+        .and_then(|latest| night_start_overrun(sunset_end, latest))
+    {
+        Some(overrun) => {
+            let length = chrono::Duration::seconds(forward_secs(sunset_start, sunset_end) as i64);
+            pull_sunset_back(sunset_start, sunset_end, length, overrun)
+        }
+        None => (sunset_start, sunset_end),
+    };
+    let advance = config.min_day_duration.and_then(|minutes| {
+        sunrise_advance(
+            (sunset_start, sunset_end),
+            (sunrise_start, sunrise_end),
+            chrono::Duration::minutes(minutes as i64),
+        )
+    });
+    match advance {
+        Some(advance) => (
+            sunset_start,
+            sunset_end,
+            sunrise_start - advance,
+            sunrise_end - advance,
+        ),
+        None => (sunset_start, sunset_end, sunrise_start, sunrise_end),
+    }
+}
+
+/// How far a sunset transition ending at `end` runs past `latest`, the time night has to
+/// begin by. The occurrence of `latest` nearest to `end` is the one that counts, so a limit
+/// just after midnight still belongs to the evening before it. `None` when the transition
+/// ends in time.
+pub fn night_start_overrun(end: NaiveTime, latest: NaiveTime) -> Option<chrono::Duration> {
+    let secs = (end - latest).num_seconds().rem_euclid(24 * 3600);
+    (secs > 0 && secs <= 12 * 3600).then(|| chrono::Duration::seconds(secs))
+}
+
+/// Move the end of a sunset transition of `length` back by `overrun`. One still starting
+/// before its new end is cut short, any other moves earlier whole.
+pub fn pull_sunset_back<T>(
+    start: T,
+    end: T,
+    length: chrono::Duration,
+    overrun: chrono::Duration,
+) -> (T, T)
+where
+    T: Copy + std::ops::Sub<chrono::Duration, Output = T>,
+{
+    if overrun < length {
+        (start, end - overrun)
+    } else {
+        (start - overrun, end - overrun)
+    }
+}
+
+/// How much earlier the sunrise window has to move to leave `min_day` before the sunset
+/// window starts, taken from the night between the two and never more than all of it.
+/// `None` when the day is long enough already.
+pub fn sunrise_advance(
+    sunset: (NaiveTime, NaiveTime),
+    sunrise: (NaiveTime, NaiveTime),
+    min_day: chrono::Duration,
+) -> Option<chrono::Duration> {
+    let day = chrono::Duration::seconds(forward_secs(sunrise.1, sunset.0) as i64);
+    let night = chrono::Duration::seconds(forward_secs(sunset.1, sunrise.0) as i64);
+    let advance = (min_day - day).min(night);
+    (advance > chrono::Duration::zero()).then_some(advance)
+}
+
+/// The windows as `sunset`, `sunrise` and the transition lengths place them.
+fn configured_windows(config: &Config) -> (NaiveTime, NaiveTime, NaiveTime, NaiveTime) {
     let mode = config.transition_mode;
 
     let sunset_str = config.sunset.as_deref().unwrap_or(DEFAULT_SUNSET);
@@ -35,12 +112,13 @@ pub fn calculate_transition_windows(
         NaiveTime::parse_from_str(sunrise_str, "%H:%M:%S").unwrap(),
     );
 
-    let transition_duration = StdDuration::from_secs(config.transition_duration * 60);
+    let sunset_duration = StdDuration::from_secs(config.sunset_duration_mins() * 60);
+    let sunrise_duration = StdDuration::from_secs(config.sunrise_duration_mins() * 60);
 
     match mode {
         TransitionMode::Center => {
-            let sunset_half = chrono::Duration::from_std(transition_duration / 2).unwrap();
-            let sunrise_half = chrono::Duration::from_std(transition_duration / 2).unwrap();
+            let sunset_half = chrono::Duration::from_std(sunset_duration / 2).unwrap();
+            let sunrise_half = chrono::Duration::from_std(sunrise_duration / 2).unwrap();
 
             (
                 sunset - sunset_half,
@@ -50,24 +128,29 @@ pub fn calculate_transition_windows(
             )
         }
         TransitionMode::StartAt => {
-            let full_transition = chrono::Duration::from_std(transition_duration).unwrap();
+            let full_sunset = chrono::Duration::from_std(sunset_duration).unwrap();
+            let full_sunrise = chrono::Duration::from_std(sunrise_duration).unwrap();
             (
                 sunset,
-                sunset + full_transition,
+                sunset + full_sunset,
                 sunrise,
-                sunrise + full_transition,
+                sunrise + full_sunrise,
             )
         }
         TransitionMode::FinishBy => {
-            let full_transition = chrono::Duration::from_std(transition_duration).unwrap();
+            let full_sunset = chrono::Duration::from_std(sunset_duration).unwrap();
+            let full_sunrise = chrono::Duration::from_std(sunrise_duration).unwrap();
             (
-                sunset - full_transition,
+                sunset - full_sunset,
                 sunset,
-                sunrise - full_transition,
+                sunrise - full_sunrise,
                 sunrise,
             )
         }
-        TransitionMode::Geo | TransitionMode::Static => {
+        TransitionMode::Geo
+        | TransitionMode::Static
+        | TransitionMode::External
+        | TransitionMode::Darkman => {
             unreachable!(
                 "calculate_transition_windows is clock-modes-only. Got '{}'",
                 mode
@@ -188,6 +271,24 @@ fn forward_secs(from: NaiveTime, to: NaiveTime) -> f64 {
     if diff < 0.0 { diff + 86_400.0 } else { diff }
 }
 
+/// Fixed update interval for a transition `transition_secs` long.
+///
+/// `interval_secs` as configured, unless `adjust_update_interval` is set and the
+/// transition would take fewer than `MINIMUM_TRANSITION_STEPS` updates at it. Then it
+/// shrinks to fit that many, though never below the minimum update interval.
+pub fn fixed_interval_for_transition(
+    config: &Config,
+    interval_secs: u64,
+    transition_secs: u64,
+) -> u64 {
+    if !config.adjust_update_interval {
+        return interval_secs;
+    }
+    (transition_secs / MINIMUM_TRANSITION_STEPS)
+        .max(MINIMUM_UPDATE_INTERVAL_SEC)
+        .min(interval_secs)
+}
+
 /// Adaptive update interval for clock-based modes whose window is a `NaiveTime`.
 ///
 /// The window may cross midnight, so `forward_secs` reconstructs the forward
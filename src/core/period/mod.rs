@@ -1,6 +1,6 @@
 //! Time-based and static period types and helpers.
 //!
-//! Defines the `Period` and `PeriodType` enums plus the stable-period
+//! Defines the `Period`, `Phase` and `PeriodType` enums plus the stable-period
 //! helper. Transition progress lives in [`calculations`] and state-change
 //! detection lives in [`state_detection`].
 
@@ -10,7 +10,7 @@ pub mod state_detection;
 #[cfg(test)]
 mod tests;
 
-pub use state_detection::{StateChange, log_state_announcement, should_update_state};
+pub use state_detection::{ChangeReason, StateChange, log_state_announcement, should_update_state};
 
 use chrono::NaiveTime;
 use serde::{Deserialize, Serialize};
@@ -100,6 +100,61 @@ impl Period {
     }
 }
 
+/// A period together with how far through it the schedule is.
+///
+/// Only transitions have progress, so it is carried by the `Sunset` and `Sunrise`
+/// variants instead of being queried beside the period as an `Option`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Phase {
+    Day,
+    Night,
+    Sunset { progress: f32 },
+    Sunrise { progress: f32 },
+    Static,
+}
+
+impl Phase {
+    /// Pair `period` with the schedule's progress. A transition without progress, as when
+    /// one is forced at its boundary, is at its start.
+    pub fn new(period: Period, progress: Option<f32>) -> Self {
+        let progress = progress.unwrap_or(0.0);
+        match period {
+            Period::Day => Self::Day,
+            Period::Night => Self::Night,
+            Period::Sunset => Self::Sunset { progress },
+            Period::Sunrise => Self::Sunrise { progress },
+            Period::Static => Self::Static,
+        }
+    }
+
+    pub fn period(&self) -> Period {
+        match self {
+            Self::Day => Period::Day,
+            Self::Night => Period::Night,
+            Self::Sunset { .. } => Period::Sunset,
+            Self::Sunrise { .. } => Period::Sunrise,
+            Self::Static => Period::Static,
+        }
+    }
+
+    /// Progress through a transition, None for every other period.
+    pub fn progress(&self) -> Option<f32> {
+        match self {
+            Self::Sunset { progress } | Self::Sunrise { progress } => Some(*progress),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.progress() {
+            Some(progress) => write!(f, "{} ({:.1}%)", self.period(), progress * 100.0),
+            None => write!(f, "{}", self.period()),
+        }
+    }
+}
+
 /// Day or Night for a time outside any transition window, handling windows
 /// that cross midnight or span extreme day/night lengths.
 pub(crate) fn get_stable_period(
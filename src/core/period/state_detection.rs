@@ -1,4 +1,7 @@
-//! Detect and log the kind of change between two periods.
+//! Detect and log the kind of change between two periods, and why it happened.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
 use crate::core::period::Period;
 
@@ -17,6 +20,69 @@ pub enum StateChange {
     StableJump { from: Period, to: Period },
 }
 
+/// What made sunsetr re-evaluate and apply its state.
+///
+/// [`StateChange`] says what changed; this says why, and travels with the IPC events and
+/// debug logs so a report can be traced back to its trigger.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeReason {
+    /// The initial state applied when the process starts.
+    Startup,
+    /// A regular update as the schedule advanced.
+    Schedule,
+    /// The end of a transition reached by sleeping exactly to its boundary.
+    Boundary,
+    /// The config file changed or a reload was requested.
+    Reload,
+    /// A preset was activated or cleared.
+    Preset,
+    /// The system resumed from sleep.
+    Wake,
+    /// The system clock jumped.
+    ClockJump,
+    /// A sleep overran its deadline because the monotonic clock stopped while suspended.
+    LateWake,
+    /// A live preview was cancelled and the scheduled values came back.
+    Preview,
+    /// An external scheduler pushed new targets.
+    External,
+    /// darkman switched between light and dark mode.
+    Darkman,
+    /// Media playback started or stopped while `[media]` is set.
+    Media,
+    /// `sunsetr refresh` rebuilt the backend's outputs.
+    Refresh,
+    /// Color shifting was paused or resumed.
+    Pause,
+    /// A reason this version doesn't know, sent by a newer or older daemon.
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+impl fmt::Display for ChangeReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ChangeReason::Startup => "startup",
+            ChangeReason::Schedule => "schedule",
+            ChangeReason::Boundary => "transition boundary",
+            ChangeReason::Reload => "config reload",
+            ChangeReason::Preset => "preset change",
+            ChangeReason::Wake => "wake",
+            ChangeReason::ClockJump => "clock jump",
+            ChangeReason::LateWake => "late wake",
+            ChangeReason::Preview => "preview",
+            ChangeReason::External => "external targets",
+            ChangeReason::Darkman => "darkman mode change",
+            ChangeReason::Media => "media playback",
+            ChangeReason::Refresh => "refresh",
+            ChangeReason::Pause => "pause",
+            ChangeReason::Unknown => "unknown",
+        })
+    }
+}
+
 /// Classify the change between the two periods, logging it as a side effect.
 pub fn should_update_state(current_period: &Period, new_period: &Period) -> StateChange {
     let change = detect_state_change(current_period, new_period);
@@ -1,12 +1,10 @@
 use super::*;
-use crate::common::constants::{
-    DEFAULT_ADAPTIVE_INTERVAL_MS, DEFAULT_DAY_GAMMA, DEFAULT_DAY_TEMP, DEFAULT_NIGHT_GAMMA,
-    DEFAULT_NIGHT_TEMP, DEFAULT_TRANSITION_DURATION_MIN, DEFAULT_UPDATE_INTERVAL_SEC,
-};
+use crate::common::constants::DEFAULT_UPDATE_INTERVAL_SEC;
 use crate::config::Config;
 use crate::core::period::calculations::{
-    calculate_progress, calculate_transition_windows, is_time_in_range,
+    calculate_progress, calculate_transition_windows, is_time_in_range, night_start_overrun,
 };
+use crate::core::schedule::Scheduler;
 use crate::geo::times::GeoTimes;
 
 /// Sleep duration the main loop would pick from a bare config: the
@@ -23,26 +21,18 @@ fn time_until_next_event(config: &Config, geo_times: Option<&GeoTimes>) -> std::
 }
 
 fn create_test_config(sunset: &str, sunrise: &str, mode: &str, duration_mins: u64) -> Config {
-    Config {
-        backend: crate::config::Backend::Auto,
-        smoothing: false,
-        startup_duration: 10.0,
-        shutdown_duration: 10.0,
-        adaptive_interval: DEFAULT_ADAPTIVE_INTERVAL_MS,
-        latitude: None,
-        longitude: None,
-        sunset: Some(sunset.to_string()),
-        sunrise: Some(sunrise.to_string()),
-        night_temp: DEFAULT_NIGHT_TEMP,
-        day_temp: DEFAULT_DAY_TEMP,
-        night_gamma: DEFAULT_NIGHT_GAMMA,
-        day_gamma: DEFAULT_DAY_GAMMA,
-        static_temp: None,
-        static_gamma: None,
-        transition_duration: duration_mins,
-        update_interval: crate::config::UpdateInterval::Fixed(DEFAULT_UPDATE_INTERVAL_SEC),
-        transition_mode: mode.parse().unwrap(),
-    }
+    Config::builder()
+        .transition_mode(mode.parse().unwrap())
+        .sunset(sunset)
+        .sunrise(sunrise)
+        .smoothing(false)
+        .startup_duration(10.0)
+        .shutdown_duration(10.0)
+        .transition_duration(duration_mins)
+        .update_interval(crate::config::UpdateInterval::Fixed(
+            DEFAULT_UPDATE_INTERVAL_SEC,
+        ))
+        .build()
 }
 
 #[test]
@@ -81,6 +71,71 @@ fn test_calculate_transition_windows_center() {
     assert_eq!(sunrise_end, NaiveTime::from_hms_opt(6, 15, 0).unwrap());
 }
 
+#[test]
+fn test_calculate_transition_windows_per_direction() {
+    let mut config = create_test_config("19:00:00", "06:00:00", "finish_by", 30);
+    config.sunset_duration = Some(60);
+    let (sunset_start, sunset_end, sunrise_start, sunrise_end) =
+        calculate_transition_windows(&config);
+    assert_eq!(sunset_start, NaiveTime::from_hms_opt(18, 0, 0).unwrap());
+    assert_eq!(sunset_end, NaiveTime::from_hms_opt(19, 0, 0).unwrap());
+    assert_eq!(sunrise_start, NaiveTime::from_hms_opt(5, 30, 0).unwrap());
+    assert_eq!(sunrise_end, NaiveTime::from_hms_opt(6, 0, 0).unwrap());
+
+    config.transition_mode = crate::config::TransitionMode::Center;
+    config.sunrise_duration = Some(10);
+    let (sunset_start, sunset_end, sunrise_start, sunrise_end) =
+        calculate_transition_windows(&config);
+    assert_eq!(sunset_start, NaiveTime::from_hms_opt(18, 30, 0).unwrap());
+    assert_eq!(sunset_end, NaiveTime::from_hms_opt(19, 30, 0).unwrap());
+    assert_eq!(sunrise_start, NaiveTime::from_hms_opt(5, 55, 0).unwrap());
+    assert_eq!(sunrise_end, NaiveTime::from_hms_opt(6, 5, 0).unwrap());
+}
+
+#[test]
+fn test_night_latest_start_ends_sunset_in_time() {
+    let hms = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    let windows = |latest: &str| {
+        let mut config = create_test_config("23:00:00", "06:00:00", "finish_by", 60);
+        config.night_latest_start = Some(latest.to_string());
+        let (sunset_start, sunset_end, _, _) = calculate_transition_windows(&config);
+        (sunset_start, sunset_end)
+    };
+
+    // Started before the limit, so cut short
+    assert_eq!(windows("22:30:00"), (hms(22, 0), hms(22, 30)));
+    // Would start after it, so moved earlier whole
+    assert_eq!(windows("21:30:00"), (hms(20, 30), hms(21, 30)));
+    // Already ends in time, including a limit just after midnight
+    assert_eq!(windows("23:00:00"), (hms(22, 0), hms(23, 0)));
+    assert_eq!(windows("00:30:00"), (hms(22, 0), hms(23, 0)));
+
+    // A limit after midnight still pulls back a sunset ending later that night
+    assert_eq!(
+        night_start_overrun(hms(1, 15), hms(0, 30)),
+        Some(chrono::Duration::minutes(45))
+    );
+}
+
+#[test]
+fn test_min_day_duration_moves_sunrise_earlier() {
+    let hms = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    // Sunrise typed as 16:00 leaves two and a half hours of day before sunset at 18:30
+    let mut config = create_test_config("19:00:00", "16:00:00", "finish_by", 30);
+    let (_, _, sunrise_start, sunrise_end) = calculate_transition_windows(&config);
+    assert_eq!((sunrise_start, sunrise_end), (hms(15, 30), hms(16, 0)));
+
+    config.min_day_duration = Some(120);
+    let (_, _, sunrise_start, sunrise_end) = calculate_transition_windows(&config);
+    assert_eq!((sunrise_start, sunrise_end), (hms(15, 30), hms(16, 0)));
+
+    config.min_day_duration = Some(240);
+    let (sunset_start, sunset_end, sunrise_start, sunrise_end) =
+        calculate_transition_windows(&config);
+    assert_eq!((sunset_start, sunset_end), (hms(18, 30), hms(19, 0)));
+    assert_eq!((sunrise_start, sunrise_end), (hms(14, 0), hms(14, 30)));
+}
+
 #[test]
 fn test_extreme_short_transition() {
     let config = create_test_config("19:00:00", "06:00:00", "finish_by", 5); // 5 minutes
@@ -384,39 +439,61 @@ fn test_time_until_next_event_bounded_for_clock_modes() {
 #[cfg(test)]
 mod static_tests {
     use super::*;
+    use crate::common::units::values;
     use crate::config::{Backend, Config};
     use crate::core::runtime_state::RuntimeState;
     use std::time::Duration as StdDuration;
 
     fn create_static_mode_config(temp: u32, gamma: f64) -> Config {
-        Config {
-            backend: Backend::Auto,
-            smoothing: false,
-            startup_duration: 10.0,
-            shutdown_duration: 10.0,
-            adaptive_interval: DEFAULT_ADAPTIVE_INTERVAL_MS,
-            latitude: None,
-            longitude: None,
-            sunset: None,
-            sunrise: None,
-            night_temp: DEFAULT_NIGHT_TEMP,
-            day_temp: DEFAULT_DAY_TEMP,
-            night_gamma: DEFAULT_NIGHT_GAMMA,
-            day_gamma: DEFAULT_DAY_GAMMA,
-            static_temp: Some(temp),
-            static_gamma: Some(gamma),
-            transition_duration: DEFAULT_TRANSITION_DURATION_MIN,
-            update_interval: crate::config::UpdateInterval::Fixed(60),
-            transition_mode: crate::config::TransitionMode::Static,
-        }
+        Config::builder()
+            .static_values(temp, gamma)
+            .smoothing(false)
+            .startup_duration(10.0)
+            .shutdown_duration(10.0)
+            .update_interval(crate::config::UpdateInterval::Fixed(60))
+            .build()
     }
 
     fn create_static_mode_raw(temp: u32, gamma: f64) -> crate::config::RawConfig {
         crate::config::RawConfig {
             backend: Some(Backend::Auto),
             smoothing: Some(false),
+            reduce_motion: None,
             startup_duration: Some(10.0),
             shutdown_duration: Some(10.0),
+            shutdown_target: None,
+            verify_gamma: None,
+            hyprsunset_mode: None,
+            outputs: None,
+            neutral_apps: None,
+            activate_when_outputs: None,
+            gamma_r: None,
+            gamma_g: None,
+            gamma_b: None,
+            red_trim: None,
+            green_trim: None,
+            blue_trim: None,
+            matrix: None,
+            adjust_update_interval: None,
+            heartbeat_interval: None,
+            power_saving: None,
+            override_expiry: None,
+            night_saturation: None,
+            night_brightness: None,
+            day_brightness: None,
+            readonly_config: None,
+            include_outputs: None,
+            exclude_outputs: None,
+            media: None,
+            darkman_export: None,
+            winddown: None,
+            sunset_duration: None,
+            sunrise_duration: None,
+            night_latest_start: None,
+            min_day_duration: None,
+            values_changed_temp_step: None,
+            values_changed_gamma_step: None,
+            ipc_allowed_gids: None,
             startup_transition: None,
             startup_transition_duration: None,
             start_hyprsunset: None,
@@ -458,8 +535,7 @@ mod static_tests {
             crate::core::schedule::Schedule::from_config(&config, None),
             crate::time::source::now(),
         );
-        assert_eq!(runtime_state.temperature(), 4000);
-        assert_eq!(runtime_state.gamma(), 85.0);
+        assert_eq!(runtime_state.values(), values(4000, 85.0));
     }
 
     #[test]
@@ -582,7 +658,7 @@ mod static_tests {
             crate::core::schedule::Schedule::from_config(&config, None),
             crate::time::source::now(),
         );
-        assert_eq!(runtime_state.progress(), None);
+        assert_eq!(runtime_state.phase(), Phase::Static);
         assert_eq!(state.display_name(), "Static");
         assert_eq!(state.symbol(), "󰋙 ");
 
@@ -596,8 +672,7 @@ mod static_tests {
             crate::core::schedule::Schedule::from_config(&config, None),
             crate::time::source::now(),
         );
-        assert_eq!(runtime_state.temperature(), 4500);
-        assert_eq!(runtime_state.gamma(), 92.0);
+        assert_eq!(runtime_state.values(), values(4500, 92.0));
     }
 
     #[test]
@@ -619,8 +694,7 @@ mod static_tests {
             crate::core::schedule::Schedule::from_config(&config, None),
             crate::time::source::now(),
         );
-        assert_eq!(runtime_state.temperature(), 4000);
-        assert_eq!(runtime_state.gamma(), 85.0);
+        assert_eq!(runtime_state.values(), values(4000, 85.0));
     }
 
     #[test]
@@ -646,8 +720,7 @@ mod static_tests {
                 crate::core::schedule::Schedule::from_config(&config, None),
                 crate::time::source::now(),
             );
-            assert_eq!(runtime_state.temperature(), temp);
-            assert_eq!(runtime_state.gamma(), gamma);
+            assert_eq!(runtime_state.values(), values(temp, gamma));
         }
     }
 }
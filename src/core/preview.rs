@@ -0,0 +1,109 @@
+//! Live previews requested over IPC.
+//!
+//! A frontend dragging a slider sends `preview_set` for every position. Core applies those
+//! values at once, without a smooth transition, and holds them on screen until the client
+//! commits or cancels. Scheduled updates keep advancing the runtime state meanwhile but are
+//! not applied or announced, so the schedule never yanks the display away mid-drag.
+//!
+//! `commit` writes the values into the active config, to the fields of the period they were
+//! previewed in, and reloads it. `cancel` puts the scheduled values back. Any other reload
+//! ends a preview too, starting from the previewed values rather than jumping back first.
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::core::period::Period;
+
+/// A preview operation, forwarded from the IPC server to the main loop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreviewRequest {
+    /// Show these values now, replacing any earlier preview.
+    Set { temp: Kelvin, gamma: GammaPercent },
+    /// Keep the previewed values by writing them to the config.
+    Commit,
+    /// Drop the preview and return to the scheduled values.
+    Cancel,
+}
+
+/// Values held on screen in place of the scheduled ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Preview {
+    pub temp: Kelvin,
+    pub gamma: GammaPercent,
+    /// The period clients last saw in a state_applied event. Period changes while the
+    /// preview is up are announced from it once the preview ends.
+    pub announced_period: Period,
+}
+
+impl Preview {
+    pub fn values(&self) -> (Kelvin, GammaPercent) {
+        (self.temp, self.gamma)
+    }
+}
+
+/// The config fields holding the values of `period`. Transitions map to the period they
+/// lead into, matching what the `current_temp` and `current_gamma` aliases of `set` resolve to.
+pub fn fields_for(period: Period) -> (&'static str, &'static str) {
+    match period {
+        Period::Static => ("static_temp", "static_gamma"),
+        Period::Day | Period::Sunrise => ("day_temp", "day_gamma"),
+        Period::Night | Period::Sunset => ("night_temp", "night_gamma"),
+    }
+}
+
+/// Write `temp` and `gamma` to the `period` fields of the active config, the active preset's
+/// when one is set, returning the file written.
+pub(crate) fn write_to_active_config(
+    period: Period,
+    temp: Kelvin,
+    gamma: GammaPercent,
+) -> Result<PathBuf> {
+    crate::config::ensure_config_writable()?;
+    let config_path = crate::commands::resolve_target_config_path(None)?;
+    let _lock =
+        crate::io::lock::LockFile::acquire(crate::io::lock::get_config_lock_path(&config_path))?;
+
+    let content = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read config from {}", config_path.display()))?;
+    let updated = with_values(&content, period, temp.get(), gamma.get())?;
+    crate::commands::set::atomic_write_file(&config_path, &updated)
+        .with_context(|| format!("Failed to write config to {}", config_path.display()))?;
+    Ok(config_path)
+}
+
+fn with_values(content: &str, period: Period, temp: u32, gamma: f64) -> Result<String> {
+    let mut doc: toml_edit::DocumentMut = content
+        .parse()
+        .context("Failed to parse configuration file")?;
+    let (temp_field, gamma_field) = fields_for(period);
+    crate::config::builder::set_field(&mut doc, temp_field, i64::from(temp).into());
+    crate::config::builder::set_field(&mut doc, gamma_field, gamma.into());
+    Ok(doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_go_to_the_period_being_approached() {
+        assert_eq!(fields_for(Period::Sunset), ("night_temp", "night_gamma"));
+        assert_eq!(fields_for(Period::Sunrise), ("day_temp", "day_gamma"));
+        assert_eq!(fields_for(Period::Static), ("static_temp", "static_gamma"));
+    }
+
+    #[test]
+    fn committed_values_keep_the_rest_of_the_file() {
+        let content = "# Night\nnight_temp = 3300 # warm\nnight_gamma = 90.0\nday_temp = 6500\n";
+        let updated = with_values(content, Period::Night, 2800, 85.5).unwrap();
+        assert_eq!(
+            updated,
+            "# Night\nnight_temp = 2800 # warm\nnight_gamma = 85.5\nday_temp = 6500\n"
+        );
+
+        let appended = with_values("day_temp = 6500\n", Period::Day, 5000, 100.0).unwrap();
+        let raw: crate::config::RawConfig = toml::from_str(&appended).unwrap();
+        assert_eq!((raw.day_temp, raw.day_gamma), (Some(5000), Some(100.0)));
+    }
+}
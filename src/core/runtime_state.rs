@@ -4,12 +4,15 @@ use anyhow::Context;
 use chrono::{DateTime, Local};
 use std::fmt;
 
-use crate::common::constants::{DEFAULT_DAY_GAMMA, DEFAULT_DAY_TEMP, DEFAULT_UPDATE_INTERVAL_SEC};
-use crate::common::utils::{interpolate_f64, interpolate_inverse_u32};
-use crate::config::{Config, TransitionMode};
-use crate::core::period::Period;
-use crate::core::schedule::Schedule;
+use crate::common::constants::{DEFAULT_UPDATE_INTERVAL_SEC, POWER_SAVING_UPDATE_INTERVAL_SEC};
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::{Config, PowerSaving, TransitionMode};
+use crate::core::period::calculations::fixed_interval_for_transition;
+use crate::core::period::{Period, Phase};
+use crate::core::schedule::{Schedule, Scheduler};
+use crate::core::{media, values, winddown};
 use crate::geo::times::GeoTimes;
+use crate::state::value_offset::ValueOffset;
 
 /// The primary application state, pairing a Period with the context (config,
 /// schedule, current_time) needed for all runtime calculations.
@@ -38,77 +41,103 @@ impl RuntimeState {
         }
     }
 
-    pub fn temperature(&self) -> u32 {
-        match self.period {
-            Period::Day => self.config.day_temp,
-            Period::Night => self.config.night_temp,
-            Period::Static => self.config.static_temp.unwrap_or(DEFAULT_DAY_TEMP),
-            Period::Sunset => {
-                let progress = self.progress().unwrap_or(0.0);
-                interpolate_inverse_u32(self.config.day_temp, self.config.night_temp, progress)
-            }
-            Period::Sunrise => {
-                let progress = self.progress().unwrap_or(0.0);
-                interpolate_inverse_u32(self.config.night_temp, self.config.day_temp, progress)
-            }
+    /// RuntimeState at `now`, in the period `schedule` gives for it, or static without a
+    /// schedule.
+    pub fn at(config: &Config, schedule: Option<Schedule>, now: DateTime<Local>) -> Self {
+        let period = schedule
+            .as_ref()
+            .map_or(Period::Static, |schedule| schedule.current_period(now));
+        Self::new(period, config, schedule, now)
+    }
+
+    /// Temperature and gamma for the current phase, see [`values`], lowered by the
+    /// wind-down during the night and softened while media plays. A scheduler that
+    /// supplies its own targets overrides both. An active `sunsetr adjust` offset moves
+    /// whichever results.
+    pub fn values(&self) -> (Kelvin, GammaPercent) {
+        let (temp, gamma) = self.scheduled_values();
+        // Validated config values, a pushed target or the wind-down and media floors, all in
+        // range already. The clamp only makes the conversion total.
+        let values = (
+            Kelvin::clamped(i64::from(temp)),
+            GammaPercent::clamped(gamma),
+        );
+        match self.value_offset() {
+            Some(offset) => offset.apply(values),
+            None => values,
         }
     }
 
-    pub fn gamma(&self) -> f64 {
-        match self.period {
-            Period::Day => self.config.day_gamma,
-            Period::Night => self.config.night_gamma,
-            Period::Static => self.config.static_gamma.unwrap_or(DEFAULT_DAY_GAMMA),
-            Period::Sunset => {
-                let progress = self.progress().unwrap_or(0.0);
-                interpolate_f64(self.config.day_gamma, self.config.night_gamma, progress)
-            }
-            Period::Sunrise => {
-                let progress = self.progress().unwrap_or(0.0);
-                interpolate_f64(self.config.night_gamma, self.config.day_gamma, progress)
+    /// The `sunsetr adjust` offsets in effect at this state's time.
+    pub fn value_offset(&self) -> Option<ValueOffset> {
+        self.config
+            .value_offset
+            .filter(|offset| offset.is_active(self.current_time))
+    }
+
+    fn scheduled_values(&self) -> (u32, f64) {
+        if let Some(targets) = self
+            .schedule
+            .as_ref()
+            .and_then(|schedule| schedule.targets(self.current_time))
+        {
+            return (targets.0.get(), targets.1.get());
+        }
+        let mut values = values::values(self.phase(), &self.config);
+        if let (Some(config), Some(progress)) = (&self.config.winddown, self.winddown_progress()) {
+            values = winddown::apply(values, config, progress);
+        }
+        match &self.config.media {
+            Some(config) if crate::io::idle::media_playing() => {
+                media::apply(values, config, self.config.day_gamma)
             }
+            _ => values,
         }
     }
 
-    pub fn values(&self) -> (u32, f64) {
-        (self.temperature(), self.gamma())
+    /// Brightness for the current phase, see [`values::brightness`].
+    pub fn brightness(&self) -> f64 {
+        values::brightness(self.phase(), &self.config)
     }
 
-    /// Progress through the current transitioning period, None when stable or
-    /// static.
-    pub fn progress(&self) -> Option<f32> {
-        self.schedule
+    /// Saturation for the current phase, see [`values::saturation`].
+    pub fn saturation(&self) -> f64 {
+        values::saturation(self.phase(), &self.config)
+    }
+
+    /// How far the night has wound down, None outside the wind-down.
+    pub fn winddown_progress(&self) -> Option<f32> {
+        if self.period != Period::Night {
+            return None;
+        }
+        winddown::progress(
+            self.config.winddown.as_ref()?,
+            self.current_time,
+            self.night()?,
+        )
+    }
+
+    /// The current period with its transition progress.
+    pub fn phase(&self) -> Phase {
+        let progress = self
+            .schedule
             .as_ref()
-            .and_then(|schedule| schedule.progress(self.period, self.current_time))
+            .and_then(|schedule| schedule.progress(self.period, self.current_time));
+        Phase::new(self.period, progress)
     }
 
-    /// Updated RuntimeState for the current instant, recalculating a geo
-    /// schedule across a day boundary when due.
+    /// Updated RuntimeState for the current instant, letting the scheduler catch up
+    /// first, such as a geo schedule moving on across a day boundary.
     pub fn with_current_period(&self) -> (RuntimeState, crate::core::period::StateChange) {
         let now = crate::time::source::now();
 
-        let updated_schedule = match &self.schedule {
-            Some(Schedule::Geo(times)) if times.needs_recalculation(now) => {
-                if let (Some(lat), Some(lon)) = (self.config.latitude, self.config.longitude) {
-                    let mut new_times = times.clone();
-                    if new_times.recalculate_for_next_period(lat, lon).is_ok() {
-                        Some(Schedule::Geo(new_times))
-                    } else {
-                        self.schedule.clone()
-                    }
-                } else {
-                    self.schedule.clone()
-                }
-            }
-            _ => self.schedule.clone(),
-        };
-
-        let new_period = updated_schedule
-            .as_ref()
-            .map_or(Period::Static, |schedule| schedule.current_period(now));
-        let change = crate::core::period::should_update_state(&self.period, &new_period);
+        let mut updated_schedule = self.schedule.clone();
+        if let Some(schedule) = &mut updated_schedule {
+            schedule.refresh(&self.config, now);
+        }
 
-        let new_state = RuntimeState::new(new_period, &self.config, updated_schedule, now);
+        let new_state = RuntimeState::at(&self.config, updated_schedule, now);
+        let change = crate::core::period::should_update_state(&self.period, &new_state.period);
 
         (new_state, change)
     }
@@ -186,19 +215,39 @@ impl RuntimeState {
             None
         };
 
-        let schedule = Schedule::from_config(new_config, updated_geo_times);
-        let now = crate::time::source::now();
-        let new_period = schedule
-            .as_ref()
-            .map_or(Period::Static, |schedule| schedule.current_period(now));
+        let mut schedule = Schedule::from_config(new_config, updated_geo_times);
+        // Pushed targets stay until the next push, not until the next reload
+        if let (Some(Schedule::External(new)), Some(Schedule::External(current))) =
+            (&mut schedule, &self.schedule)
+        {
+            *new = *current;
+        }
+        Ok(RuntimeState::at(
+            new_config,
+            schedule,
+            crate::time::source::now(),
+        ))
+    }
 
-        Ok(RuntimeState::new(new_period, new_config, schedule, now))
+    /// This state with `temp` and `gamma` as the external scheduler's targets, or None
+    /// when the schedule isn't external.
+    pub fn with_external_targets(&self, temp: Kelvin, gamma: GammaPercent) -> Option<RuntimeState> {
+        let mut schedule = self.schedule.clone();
+        let Some(Schedule::External(targets)) = &mut schedule else {
+            return None;
+        };
+        targets.set(temp, gamma);
+        Some(RuntimeState::at(
+            &self.config,
+            schedule,
+            crate::time::source::now(),
+        ))
     }
 
     pub fn has_same_effective_values(&self, other: &RuntimeState) -> bool {
         let (temp1, gamma1) = self.values();
         let (temp2, gamma2) = other.values();
-        temp1 == temp2 && (gamma1 - gamma2).abs() < 0.01
+        temp1 == temp2 && (gamma1.get() - gamma2.get()).abs() < 0.01
     }
 
     /// Time until the next state change the main loop must wake for, or
@@ -219,6 +268,50 @@ impl RuntimeState {
             .and_then(|schedule| schedule.time_until_transition_end(self.current_time))
     }
 
+    /// How long the main loop sleeps before its next update: one update interval during
+    /// a transition, or less when the transition ends sooner, and the time until the next
+    /// period otherwise, or the next wind-down step when sooner. `Duration::MAX` in static
+    /// mode.
+    pub fn time_until_next_update(&self) -> std::time::Duration {
+        if !self.period.is_transitioning() {
+            let next_event = self.time_until_next_event();
+            return match self.time_until_winddown_step() {
+                Some(step) => step.min(next_event),
+                None => next_event,
+            };
+        }
+
+        let update_interval = std::time::Duration::from_secs(self.effective_update_interval_secs());
+        match self.time_until_transition_end() {
+            Some(remaining) if remaining < update_interval => remaining,
+            _ => update_interval,
+        }
+    }
+
+    fn time_until_winddown_step(&self) -> Option<std::time::Duration> {
+        if self.period != Period::Night {
+            return None;
+        }
+        winddown::time_until_next_step(
+            self.config.winddown.as_ref()?,
+            self.current_time,
+            self.night()?,
+            std::time::Duration::from_secs(self.effective_update_interval_secs()),
+        )
+    }
+
+    /// Start and end of the current night.
+    fn night(&self) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        let start = self.schedule.as_ref()?.night_start(self.current_time)?;
+        Some((start, self.next_period_start()?))
+    }
+
+    /// Absolute time of the next scheduled update, or None in static mode.
+    pub fn next_update(&self) -> Option<DateTime<Local>> {
+        let duration = chrono::Duration::from_std(self.time_until_next_update()).ok()?;
+        self.current_time.checked_add_signed(duration)
+    }
+
     /// Absolute start of the next period, or None in static mode.
     pub fn next_period_start(&self) -> Option<DateTime<Local>> {
         self.schedule
@@ -226,9 +319,43 @@ impl RuntimeState {
             .and_then(|schedule| schedule.next_period_start(self.period, self.current_time))
     }
 
+    /// Absolute start of the next sunset or sunrise, looking past the end of one underway,
+    /// or None in static mode.
+    pub fn next_transition_start(&self) -> Option<DateTime<Local>> {
+        let schedule = self.schedule.as_ref()?;
+        let next = schedule.next_period_start(self.period, self.current_time)?;
+        if self.period.is_transitioning() {
+            schedule.next_period_start(self.period.next_period(), next)
+        } else {
+            Some(next)
+        }
+    }
+
+    /// Absolute start of the next sunrise, looking past one underway, or None when the
+    /// schedule has no sunrises.
+    pub fn next_sunrise_start(&self) -> Option<DateTime<Local>> {
+        let schedule = self.schedule.as_ref()?;
+        let (mut period, mut at) = (self.period, self.current_time);
+        // A sunrise is never more than a full cycle of periods away
+        for _ in 0..4 {
+            at = schedule.next_period_start(period, at)?;
+            period = period.next_period();
+            if period == Period::Sunrise {
+                return Some(at);
+            }
+        }
+        None
+    }
+
     pub fn effective_update_interval_secs(&self) -> u64 {
-        match &self.config.update_interval {
-            crate::config::UpdateInterval::Fixed(secs) => *secs,
+        let interval = match &self.config.update_interval {
+            crate::config::UpdateInterval::Fixed(secs) => self
+                .schedule
+                .as_ref()
+                .and_then(|schedule| schedule.transition_length(self.period))
+                .map_or(*secs, |length| {
+                    fixed_interval_for_transition(&self.config, *secs, length.as_secs())
+                }),
             crate::config::UpdateInterval::Adaptive => self
                 .schedule
                 .as_ref()
@@ -236,9 +363,30 @@ impl RuntimeState {
                     schedule.adaptive_interval(&self.config, self.period, self.current_time)
                 })
                 .unwrap_or(DEFAULT_UPDATE_INTERVAL_SEC),
+        };
+        if self.power_saving() {
+            interval.max(POWER_SAVING_UPDATE_INTERVAL_SEC)
+        } else {
+            interval
+        }
+    }
+
+    /// Whether `power_saving` is in effect, following the system power profile in `auto`.
+    pub fn power_saving(&self) -> bool {
+        match self.config.power_saving {
+            PowerSaving::On => true,
+            PowerSaving::Off => false,
+            PowerSaving::Auto => crate::io::power::power_saver_active(),
         }
     }
 
+    /// Whether smoothing animations run, which power saving and `reduce_motion` turn off.
+    pub fn smoothing(&self) -> bool {
+        self.config.smoothing
+            && !self.power_saving()
+            && !crate::core::smoothing::reduce_motion(self.config.reduce_motion)
+    }
+
     pub fn config(&self) -> &Config {
         &self.config
     }
@@ -262,24 +410,6 @@ impl RuntimeState {
 
 impl fmt::Display for RuntimeState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.period {
-            Period::Day => write!(f, "Day"),
-            Period::Night => write!(f, "Night"),
-            Period::Static => write!(f, "Static"),
-            Period::Sunset => {
-                if let Some(progress) = self.progress() {
-                    write!(f, "Sunset ({:.1}%)", progress * 100.0)
-                } else {
-                    write!(f, "Sunset")
-                }
-            }
-            Period::Sunrise => {
-                if let Some(progress) = self.progress() {
-                    write!(f, "Sunrise ({:.1}%)", progress * 100.0)
-                } else {
-                    write!(f, "Sunrise")
-                }
-            }
-        }
+        self.phase().fmt(f)
     }
 }
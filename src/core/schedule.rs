@@ -1,14 +1,24 @@
 //! Transition schedule built once from config.
 //!
-//! `Schedule::from_config` is the single place `transition_mode` is read. A geo
-//! schedule forwards to `GeoTimes` and a clock schedule evaluates the
-//! `ClockWindows` wall-clock edges. Static mode has no schedule and is
-//! represented as the absence of one (`None`).
+//! A [`Scheduler`] answers everything the runtime asks about time: which period is
+//! active, how far a transition has progressed, and when the next change is due. The
+//! runtime state and main loop only go through that trait, so they never depend on
+//! where the periods come from. A geo schedule forwards to `GeoTimes` and a clock
+//! schedule evaluates the `ClockWindows` wall-clock edges. A scheduler may also hand the
+//! runtime target values directly instead of having them derived from its periods, for
+//! schedules computed outside sunsetr, while smoothing, backends and state persistence
+//! stay the same.
+//!
+//! `Schedule::from_config` is the single place `transition_mode` is read. Static mode
+//! has no schedule and is represented as the absence of one (`None`). External mode is a
+//! schedule with no periods of its own that hands out the last targets an IPC client
+//! pushed. Darkman mode is day or night as darkman's mode says, with no transitions.
 
 use chrono::{DateTime, Duration, Local, NaiveDateTime, NaiveTime, TimeZone};
 use std::time::Duration as StdDuration;
 
 use crate::common::constants::DEFAULT_UPDATE_INTERVAL_SEC;
+use crate::common::units::{GammaPercent, Kelvin};
 use crate::config::{Config, TransitionMode};
 use crate::core::period::calculations::{
     adaptive_interval_for_geo, calculate_adaptive_interval, calculate_progress,
@@ -17,11 +27,69 @@ use crate::core::period::calculations::{
 use crate::core::period::{Period, get_stable_period};
 use crate::geo::times::GeoTimes;
 
+/// Source of periods and transition timing for the runtime.
+pub trait Scheduler {
+    /// Period active at `now`.
+    fn current_period(&self, now: DateTime<Local>) -> Period;
+
+    /// Transition progress for `period` at `now`, or None when not transitioning.
+    fn progress(&self, period: Period, now: DateTime<Local>) -> Option<f32>;
+
+    /// Time until the next transition begins.
+    ///
+    /// Assumes `now` is outside a transition.
+    fn time_until_next_transition(&self, now: DateTime<Local>) -> StdDuration;
+
+    /// Time until the current transition ends, or None when not transitioning.
+    fn time_until_transition_end(&self, now: DateTime<Local>) -> Option<StdDuration>;
+
+    /// Start of the period after `period` as an absolute local time.
+    fn next_period_start(&self, period: Period, now: DateTime<Local>) -> Option<DateTime<Local>>;
+
+    /// Most recent end of a sunset at or before `now`, where the current or last night
+    /// began.
+    fn night_start(&self, now: DateTime<Local>) -> Option<DateTime<Local>>;
+
+    /// Adaptive update interval in seconds for an in-progress transition.
+    ///
+    /// None outside a transition, since the quantity is only defined while
+    /// Sunset or Sunrise is interpolating.
+    fn adaptive_interval(
+        &self,
+        config: &Config,
+        period: Period,
+        now: DateTime<Local>,
+    ) -> Option<u64>;
+
+    /// Length of the transition window `period` interpolates over, or None for a stable
+    /// period. The default suits schedules without transitions.
+    fn transition_length(&self, period: Period) -> Option<StdDuration> {
+        let _ = period;
+        None
+    }
+
+    /// Temperature and gamma to apply in place of the configured values for the current
+    /// phase. `None`, the default, leaves them to the config as for the built-in
+    /// schedules.
+    fn targets(&self, now: DateTime<Local>) -> Option<(Kelvin, GammaPercent)> {
+        let _ = now;
+        None
+    }
+
+    /// Bring the schedule up to date before it is queried at `now`, such as moving solar
+    /// times on to the next day. The default is a no-op.
+    fn refresh(&mut self, config: &Config, now: DateTime<Local>) {
+        let _ = (config, now);
+    }
+}
+
 /// A generator of transitions: geo by coordinate, or clock by wall time.
 #[derive(Debug, Clone)]
 pub enum Schedule {
     Geo(GeoTimes),
     Clock(ClockWindows),
+    External(ExternalTargets),
+    Darkman(DarkmanSchedule),
 }
 
 /// The four clock-mode transition edges as wall-clock times.
@@ -36,6 +104,27 @@ pub struct ClockWindows {
     sunrise_end: NaiveTime,
 }
 
+/// Targets pushed by an external scheduler, held until the next push.
+///
+/// Reports a static period throughout, so the main loop only wakes for signals, and
+/// falls back to the static values before the first push.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ExternalTargets {
+    targets: Option<(Kelvin, GammaPercent)>,
+}
+
+/// Night while darkman is in dark mode, day otherwise. Changes arrive as signals rather
+/// than at times known in advance, so there is nothing to sleep toward.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DarkmanSchedule;
+
+impl ExternalTargets {
+    /// Replace the held targets.
+    pub fn set(&mut self, temp: Kelvin, gamma: GammaPercent) {
+        self.targets = Some((temp, gamma));
+    }
+}
+
 impl Schedule {
     /// Build the schedule from config, reading `transition_mode` once.
     ///
@@ -43,125 +132,248 @@ impl Schedule {
     pub fn from_config(config: &Config, geo_times: Option<GeoTimes>) -> Option<Schedule> {
         match (config.transition_mode, geo_times) {
             (TransitionMode::Static, _) => None,
-            (TransitionMode::Geo, Some(times)) => Some(Schedule::Geo(times)),
+            (TransitionMode::External, _) => Some(Schedule::External(ExternalTargets::default())),
+            (TransitionMode::Darkman, _) => Some(Schedule::Darkman(DarkmanSchedule)),
+            (TransitionMode::Geo, Some(mut times)) => {
+                times.apply_day_limits(config);
+                Some(Schedule::Geo(times))
+            }
             (TransitionMode::Geo, None) => None,
             _ => Some(Schedule::Clock(ClockWindows::from_config(config))),
         }
     }
 
-    /// Period active at `now`.
-    pub fn current_period(&self, now: DateTime<Local>) -> Period {
+    fn scheduler(&self) -> &dyn Scheduler {
         match self {
-            Schedule::Geo(times) => times.current_period(now),
-            Schedule::Clock(windows) => windows.current_period(now.time()),
+            Schedule::Geo(times) => times,
+            Schedule::Clock(windows) => windows,
+            Schedule::External(targets) => targets,
+            Schedule::Darkman(darkman) => darkman,
         }
     }
 
-    /// Transition progress for `period` at `now`, or None when not transitioning.
-    pub fn progress(&self, period: Period, now: DateTime<Local>) -> Option<f32> {
+    fn scheduler_mut(&mut self) -> &mut dyn Scheduler {
         match self {
-            Schedule::Geo(times) => match period {
-                Period::Sunset => times.sunset_progress(now),
-                Period::Sunrise => times.sunrise_progress(now),
-                _ => None,
-            },
-            Schedule::Clock(windows) => match period {
-                Period::Sunset => windows.sunset_progress(now.time()),
-                Period::Sunrise => windows.sunrise_progress(now.time()),
-                _ => None,
-            },
+            Schedule::Geo(times) => times,
+            Schedule::Clock(windows) => windows,
+            Schedule::External(targets) => targets,
+            Schedule::Darkman(darkman) => darkman,
         }
     }
 
-    /// Time until the next transition begins.
+    /// Time the main loop should sleep before its next wake.
     ///
-    /// Assumes `now` is outside a transition.
-    pub fn time_until_next_transition(&self, now: DateTime<Local>) -> StdDuration {
-        match self {
-            Schedule::Geo(times) => times.time_until_next_transition(now),
-            Schedule::Clock(windows) => windows.time_until_next_transition(now),
+    /// While transitioning this is the update-interval tick so progress stays
+    /// smooth. Otherwise it is the time until the next transition begins.
+    pub fn time_until_next_event(
+        &self,
+        config: &Config,
+        period: Period,
+        now: DateTime<Local>,
+    ) -> StdDuration {
+        if period.is_transitioning() {
+            let secs = match &config.update_interval {
+                crate::config::UpdateInterval::Fixed(s) => *s,
+                crate::config::UpdateInterval::Adaptive => DEFAULT_UPDATE_INTERVAL_SEC,
+            };
+            StdDuration::from_secs(secs)
+        } else {
+            self.time_until_next_transition(now)
         }
     }
+}
 
-    /// Time until the current transition ends, or None when not transitioning.
-    pub fn time_until_transition_end(&self, now: DateTime<Local>) -> Option<StdDuration> {
-        match self {
-            Schedule::Geo(times) => times.time_until_transition_end(now),
-            Schedule::Clock(windows) => windows.time_until_transition_end(now),
-        }
+impl Scheduler for Schedule {
+    fn current_period(&self, now: DateTime<Local>) -> Period {
+        self.scheduler().current_period(now)
+    }
+
+    fn progress(&self, period: Period, now: DateTime<Local>) -> Option<f32> {
+        self.scheduler().progress(period, now)
+    }
+
+    fn time_until_next_transition(&self, now: DateTime<Local>) -> StdDuration {
+        self.scheduler().time_until_next_transition(now)
+    }
+
+    fn time_until_transition_end(&self, now: DateTime<Local>) -> Option<StdDuration> {
+        self.scheduler().time_until_transition_end(now)
     }
 
-    /// Start of the next period as an absolute local time.
-    pub fn next_period_start(
+    fn next_period_start(&self, period: Period, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        self.scheduler().next_period_start(period, now)
+    }
+
+    fn night_start(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        self.scheduler().night_start(now)
+    }
+
+    fn adaptive_interval(
         &self,
+        config: &Config,
         period: Period,
         now: DateTime<Local>,
-    ) -> Option<DateTime<Local>> {
-        match self {
-            Schedule::Geo(times) => match period.next_period() {
-                Period::Sunset | Period::Sunrise => {
-                    let duration = times.time_until_next_transition(now);
-                    Some(now + Duration::from_std(duration).ok()?)
-                }
-                Period::Day | Period::Night => times
-                    .time_until_transition_end(now)
-                    .and_then(|duration| Duration::from_std(duration).ok())
-                    .map(|duration| now + duration),
-                Period::Static => None,
-            },
-            Schedule::Clock(windows) => windows.next_period_start(period, now),
+    ) -> Option<u64> {
+        self.scheduler().adaptive_interval(config, period, now)
+    }
+
+    fn transition_length(&self, period: Period) -> Option<StdDuration> {
+        self.scheduler().transition_length(period)
+    }
+
+    fn targets(&self, now: DateTime<Local>) -> Option<(Kelvin, GammaPercent)> {
+        self.scheduler().targets(now)
+    }
+
+    fn refresh(&mut self, config: &Config, now: DateTime<Local>) {
+        self.scheduler_mut().refresh(config, now);
+    }
+}
+
+impl Scheduler for GeoTimes {
+    fn current_period(&self, now: DateTime<Local>) -> Period {
+        GeoTimes::current_period(self, now)
+    }
+
+    fn progress(&self, period: Period, now: DateTime<Local>) -> Option<f32> {
+        match period {
+            Period::Sunset => self.sunset_progress(now),
+            Period::Sunrise => self.sunrise_progress(now),
+            _ => None,
         }
     }
 
-    /// Adaptive update interval in seconds for an in-progress transition.
-    ///
-    /// None outside a transition, since the quantity is only defined while
-    /// Sunset or Sunrise is interpolating.
-    pub fn adaptive_interval(
+    fn time_until_next_transition(&self, now: DateTime<Local>) -> StdDuration {
+        GeoTimes::time_until_next_transition(self, now)
+    }
+
+    fn time_until_transition_end(&self, now: DateTime<Local>) -> Option<StdDuration> {
+        GeoTimes::time_until_transition_end(self, now)
+    }
+
+    fn next_period_start(&self, period: Period, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        match period.next_period() {
+            Period::Sunset | Period::Sunrise => {
+                let duration = GeoTimes::time_until_next_transition(self, now);
+                Some(now + Duration::from_std(duration).ok()?)
+            }
+            Period::Day | Period::Night => GeoTimes::time_until_transition_end(self, now)
+                .and_then(|duration| Duration::from_std(duration).ok())
+                .map(|duration| now + duration),
+            Period::Static => None,
+        }
+    }
+
+    fn night_start(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        previous_occurrence(self.sunset_end.with_timezone(&Local).time(), now)
+    }
+
+    fn adaptive_interval(
         &self,
         config: &Config,
         period: Period,
         now: DateTime<Local>,
     ) -> Option<u64> {
-        match self {
-            Schedule::Geo(times) => {
-                let (start, end) = match period {
-                    Period::Sunset => (times.sunset_start, times.sunset_end),
-                    Period::Sunrise => (times.sunrise_start, times.sunrise_end),
-                    _ => return None,
-                };
-                Some(adaptive_interval_for_geo(config, start, end, now))
-            }
-            Schedule::Clock(windows) => {
-                let (start, end) = match period {
-                    Period::Sunset => (windows.sunset_start, windows.sunset_end),
-                    Period::Sunrise => (windows.sunrise_start, windows.sunrise_end),
-                    _ => return None,
-                };
-                Some(calculate_adaptive_interval(config, start, end, now.time()))
+        let (start, end) = match period {
+            Period::Sunset => (self.sunset_start, self.sunset_end),
+            Period::Sunrise => (self.sunrise_start, self.sunrise_end),
+            _ => return None,
+        };
+        Some(adaptive_interval_for_geo(config, start, end, now))
+    }
+
+    fn transition_length(&self, period: Period) -> Option<StdDuration> {
+        let (start, end) = match period {
+            Period::Sunset => (self.sunset_start, self.sunset_end),
+            Period::Sunrise => (self.sunrise_start, self.sunrise_end),
+            _ => return None,
+        };
+        (end - start).to_std().ok()
+    }
+
+    /// Solar times are computed for one day, so they move on once both of its
+    /// transitions have passed or the clock jumped. A failed recalculation keeps the
+    /// old times.
+    fn refresh(&mut self, config: &Config, now: DateTime<Local>) {
+        if let (Some(lat), Some(lon)) = (config.latitude, config.longitude)
+            && self.needs_recalculation(now)
+            && self.recalculate_for_next_period(lat, lon).is_ok()
+        {
+            self.apply_day_limits(config);
+        }
+    }
+}
+
+impl Scheduler for ClockWindows {
+    fn current_period(&self, now: DateTime<Local>) -> Period {
+        self.period_at(now.time())
+    }
+
+    fn progress(&self, period: Period, now: DateTime<Local>) -> Option<f32> {
+        match period {
+            Period::Sunset => self.sunset_progress(now.time()),
+            Period::Sunrise => self.sunrise_progress(now.time()),
+            _ => None,
+        }
+    }
+
+    fn time_until_next_transition(&self, now: DateTime<Local>) -> StdDuration {
+        let next = [self.sunset_start, self.sunrise_start]
+            .into_iter()
+            .filter_map(|edge| next_occurrence(edge, now))
+            .min();
+
+        match next {
+            Some(dt) => {
+                let millis = dt.signed_duration_since(now).num_milliseconds().max(0) as u64;
+                StdDuration::from_millis(millis)
             }
+            None => StdDuration::from_secs(0),
         }
     }
 
-    /// Time the main loop should sleep before its next wake.
-    ///
-    /// While transitioning this is the update-interval tick so progress stays
-    /// smooth. Otherwise it is the time until the next transition begins.
-    pub fn time_until_next_event(
+    fn time_until_transition_end(&self, now: DateTime<Local>) -> Option<StdDuration> {
+        self.transition_end_after(now)
+    }
+
+    fn next_period_start(&self, period: Period, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let edge = match period.next_period() {
+            Period::Sunset => self.sunset_start,
+            Period::Night => self.sunset_end,
+            Period::Sunrise => self.sunrise_start,
+            Period::Day => self.sunrise_end,
+            Period::Static => return None,
+        };
+        next_occurrence(edge, now)
+    }
+
+    fn night_start(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        previous_occurrence(self.sunset_end, now)
+    }
+
+    fn adaptive_interval(
         &self,
         config: &Config,
         period: Period,
         now: DateTime<Local>,
-    ) -> StdDuration {
-        if period.is_transitioning() {
-            let secs = match &config.update_interval {
-                crate::config::UpdateInterval::Fixed(s) => *s,
-                crate::config::UpdateInterval::Adaptive => DEFAULT_UPDATE_INTERVAL_SEC,
-            };
-            StdDuration::from_secs(secs)
-        } else {
-            self.time_until_next_transition(now)
-        }
+    ) -> Option<u64> {
+        let (start, end) = match period {
+            Period::Sunset => (self.sunset_start, self.sunset_end),
+            Period::Sunrise => (self.sunrise_start, self.sunrise_end),
+            _ => return None,
+        };
+        Some(calculate_adaptive_interval(config, start, end, now.time()))
+    }
+
+    fn transition_length(&self, period: Period) -> Option<StdDuration> {
+        let (start, end) = match period {
+            Period::Sunset => (self.sunset_start, self.sunset_end),
+            Period::Sunrise => (self.sunrise_start, self.sunrise_end),
+            _ => return None,
+        };
+        // A window may cross midnight
+        let secs = (end - start).num_seconds().rem_euclid(24 * 3600);
+        Some(StdDuration::from_secs(secs as u64))
     }
 }
 
@@ -178,7 +390,8 @@ impl ClockWindows {
         }
     }
 
-    fn current_period(&self, now: NaiveTime) -> Period {
+    /// Period at wall-clock time `now`.
+    fn period_at(&self, now: NaiveTime) -> Period {
         if is_time_in_range(now, self.sunset_start, self.sunset_end) {
             Period::Sunset
         } else if is_time_in_range(now, self.sunrise_start, self.sunrise_end) {
@@ -208,23 +421,9 @@ impl ClockWindows {
         }
     }
 
-    fn time_until_next_transition(&self, now: DateTime<Local>) -> StdDuration {
-        let next = [self.sunset_start, self.sunrise_start]
-            .into_iter()
-            .filter_map(|edge| next_occurrence(edge, now))
-            .min();
-
-        match next {
-            Some(dt) => {
-                let millis = dt.signed_duration_since(now).num_milliseconds().max(0) as u64;
-                StdDuration::from_millis(millis)
-            }
-            None => StdDuration::from_secs(0),
-        }
-    }
-
-    fn time_until_transition_end<Tz: TimeZone>(&self, now: DateTime<Tz>) -> Option<StdDuration> {
-        let end = match self.current_period(now.time()) {
+    /// Time until the transition underway at `now` ends, in any zone.
+    fn transition_end_after<Tz: TimeZone>(&self, now: DateTime<Tz>) -> Option<StdDuration> {
+        let end = match self.period_at(now.time()) {
             Period::Sunset => self.sunset_end,
             Period::Sunrise => self.sunrise_end,
             _ => return None,
@@ -241,16 +440,83 @@ impl ClockWindows {
         let millis = end_dt.signed_duration_since(now).num_milliseconds().max(0) as u64;
         Some(StdDuration::from_millis(millis))
     }
+}
 
-    fn next_period_start(&self, period: Period, now: DateTime<Local>) -> Option<DateTime<Local>> {
-        let edge = match period.next_period() {
-            Period::Sunset => self.sunset_start,
-            Period::Night => self.sunset_end,
-            Period::Sunrise => self.sunrise_start,
-            Period::Day => self.sunrise_end,
-            Period::Static => return None,
-        };
-        next_occurrence(edge, now)
+impl Scheduler for ExternalTargets {
+    fn current_period(&self, _now: DateTime<Local>) -> Period {
+        Period::Static
+    }
+
+    fn progress(&self, _period: Period, _now: DateTime<Local>) -> Option<f32> {
+        None
+    }
+
+    fn time_until_next_transition(&self, _now: DateTime<Local>) -> StdDuration {
+        StdDuration::MAX
+    }
+
+    fn time_until_transition_end(&self, _now: DateTime<Local>) -> Option<StdDuration> {
+        None
+    }
+
+    fn next_period_start(&self, _period: Period, _now: DateTime<Local>) -> Option<DateTime<Local>> {
+        None
+    }
+
+    fn night_start(&self, _now: DateTime<Local>) -> Option<DateTime<Local>> {
+        None
+    }
+
+    fn adaptive_interval(
+        &self,
+        _config: &Config,
+        _period: Period,
+        _now: DateTime<Local>,
+    ) -> Option<u64> {
+        None
+    }
+
+    fn targets(&self, _now: DateTime<Local>) -> Option<(Kelvin, GammaPercent)> {
+        self.targets
+    }
+}
+
+impl Scheduler for DarkmanSchedule {
+    fn current_period(&self, _now: DateTime<Local>) -> Period {
+        if crate::io::darkman::is_dark() == Some(true) {
+            Period::Night
+        } else {
+            Period::Day
+        }
+    }
+
+    fn progress(&self, _period: Period, _now: DateTime<Local>) -> Option<f32> {
+        None
+    }
+
+    fn time_until_next_transition(&self, _now: DateTime<Local>) -> StdDuration {
+        StdDuration::MAX
+    }
+
+    fn time_until_transition_end(&self, _now: DateTime<Local>) -> Option<StdDuration> {
+        None
+    }
+
+    fn next_period_start(&self, _period: Period, _now: DateTime<Local>) -> Option<DateTime<Local>> {
+        None
+    }
+
+    fn night_start(&self, _now: DateTime<Local>) -> Option<DateTime<Local>> {
+        None
+    }
+
+    fn adaptive_interval(
+        &self,
+        _config: &Config,
+        _period: Period,
+        _now: DateTime<Local>,
+    ) -> Option<u64> {
+        None
     }
 }
 
@@ -269,6 +535,18 @@ fn next_occurrence<Tz: TimeZone>(target: NaiveTime, now: DateTime<Tz>) -> Option
         .and_then(|naive_dt| resolve_local(&now.timezone(), naive_dt))
 }
 
+/// Most recent occurrence of `target` at or before `now`, today or yesterday.
+fn previous_occurrence<Tz: TimeZone>(target: NaiveTime, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+    let today = now.date_naive();
+    let yesterday = today - Duration::days(1);
+
+    [today.and_time(target), yesterday.and_time(target)]
+        .into_iter()
+        .filter(|dt| *dt <= now.naive_local())
+        .max()
+        .and_then(|naive_dt| resolve_local(&now.timezone(), naive_dt))
+}
+
 /// Resolve a wall-clock time to a concrete instant in `tz`.
 ///
 /// At a daylight-saving fold (a repeated hour) the time is ambiguous, so
@@ -282,29 +560,17 @@ fn resolve_local<Tz: TimeZone>(tz: &Tz, naive: NaiveDateTime) -> Option<DateTime
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::UpdateInterval;
 
     fn clock_config(mode: TransitionMode, sunset: &str, sunrise: &str) -> Config {
-        Config {
-            backend: crate::config::Backend::Auto,
-            smoothing: false,
-            startup_duration: 10.0,
-            shutdown_duration: 10.0,
-            adaptive_interval: crate::common::constants::DEFAULT_ADAPTIVE_INTERVAL_MS,
-            latitude: None,
-            longitude: None,
-            sunset: Some(sunset.to_string()),
-            sunrise: Some(sunrise.to_string()),
-            night_temp: 3300,
-            day_temp: 6500,
-            night_gamma: 90.0,
-            day_gamma: 100.0,
-            static_temp: None,
-            static_gamma: None,
-            transition_duration: 30,
-            update_interval: UpdateInterval::Adaptive,
-            transition_mode: mode,
-        }
+        Config::builder()
+            .transition_mode(mode)
+            .sunset(sunset)
+            .sunrise(sunrise)
+            .smoothing(false)
+            .startup_duration(10.0)
+            .shutdown_duration(10.0)
+            .transition_duration(30)
+            .build()
     }
 
     fn clock_schedule(mode: TransitionMode, sunset: &str, sunrise: &str) -> Schedule {
@@ -522,11 +788,11 @@ mod tests {
         )
         .expect("an instant inside the sunrise window");
 
-        // current_period reports the transition, and a bare single() on the end
+        // period_at reports the transition, and a bare single() on the end
         // edge drops it, yet the method still reports the time remaining.
-        assert_eq!(windows.current_period(now.time()), Period::Sunrise);
+        assert_eq!(windows.period_at(now.time()), Period::Sunrise);
         assert!(New_York.from_local_datetime(&fold).single().is_none());
-        assert!(windows.time_until_transition_end(now).is_some());
+        assert!(windows.transition_end_after(now).is_some());
     }
 
     #[test]
@@ -544,4 +810,23 @@ mod tests {
                 .is_none()
         );
     }
+
+    #[test]
+    fn transition_length_spans_midnight() {
+        let windows = ClockWindows {
+            sunset_start: NaiveTime::from_hms_opt(23, 50, 0).unwrap(),
+            sunset_end: NaiveTime::from_hms_opt(0, 20, 0).unwrap(),
+            sunrise_start: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+            sunrise_end: NaiveTime::from_hms_opt(6, 45, 0).unwrap(),
+        };
+        assert_eq!(
+            windows.transition_length(Period::Sunset),
+            Some(StdDuration::from_secs(30 * 60))
+        );
+        assert_eq!(
+            windows.transition_length(Period::Sunrise),
+            Some(StdDuration::from_secs(45 * 60))
+        );
+        assert_eq!(windows.transition_length(Period::Night), None);
+    }
 }
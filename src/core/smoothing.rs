@@ -8,19 +8,37 @@
 //! `startup_duration` or `shutdown_duration` is greater than `0.1` (a lower value disables
 //! smoothing for that phase). Reloading is treated as a startup transition. The Hyprland
 //! and Hyprsunset backends are unsupported because their CTM animations conflict.
+//!
+//! `reduce_motion` turns every animation off, and IPC clients can turn it on or off for
+//! the running instance with a `reduce_motion` request, which lasts until sunsetr exits.
 
 use std::io::{self, Write};
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::backend::ColorTemperatureBackend;
 use crate::common::logger::Log;
-use crate::common::utils::{ProgressBar, interpolate_f64, interpolate_inverse_u32};
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::common::utils::ProgressBar;
 use crate::core::period::Period;
 
 const PROGRESS_BAR_WIDTH: usize = 30;
 
+/// `reduce_motion` as set over IPC, taking precedence over the config's.
+static REDUCE_MOTION_OVERRIDE: Mutex<Option<bool>> = Mutex::new(None);
+
+/// Set `reduce_motion` for this process, or with `None` go back to the config's value.
+pub fn set_reduce_motion(enabled: Option<bool>) {
+    *REDUCE_MOTION_OVERRIDE.lock().unwrap() = enabled;
+}
+
+/// Whether changes are applied at once without animating, given the config's `reduce_motion`.
+pub fn reduce_motion(configured: bool) -> bool {
+    REDUCE_MOTION_OVERRIDE.lock().unwrap().unwrap_or(configured)
+}
+
 /// Type of smooth transition being performed.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TransitionType {
@@ -36,8 +54,8 @@ pub enum TransitionType {
 pub enum TransitionResult {
     Completed,
     Interrupted {
-        current_temp: u32,
-        current_gamma: f64,
+        current_temp: Kelvin,
+        current_gamma: GammaPercent,
     },
 }
 
@@ -63,16 +81,43 @@ fn high_precision_sleep(duration: Duration) {
     }
 }
 
+/// Round `interval` to a whole number of display frames, at least one.
+///
+/// A step takes effect on the next frame, so steps closer together than a frame are never
+/// seen, and an interval a fraction of a frame off the refresh period drifts through the
+/// refresh cycle, showing as an uneven pace where the two rates beat against each other.
+fn whole_frames(interval: Duration, frame: Duration) -> Duration {
+    let frames = (interval.as_secs_f64() / frame.as_secs_f64())
+        .round()
+        .max(1.0);
+    frame.mul_f64(frames)
+}
+
+/// Wait out `interval` on the backend's frame feedback, one frame of `frame` at a time.
+///
+/// Returns false as soon as a frame goes unreported, leaving what's left of the interval to
+/// the clock. A frame reported late still counts as one, so the steps follow the frames the
+/// compositor actually draws.
+fn wait_for_frames(
+    backend: &mut dyn ColorTemperatureBackend,
+    interval: Duration,
+    frame: Duration,
+) -> bool {
+    let frames = (interval.as_secs_f64() / frame.as_secs_f64()).round() as u32;
+    // Two frames of slack, so one the compositor drops doesn't count as no feedback
+    (0..frames.max(1)).all(|_| backend.wait_for_frame(frame * 2))
+}
+
 /// Manages smooth animated transitions during application startup and shutdown.
 ///
 /// Startup transitions from day settings to the current state, and shutdown transitions
-/// from current values back to day values. Supports both static targets (stable day/night)
+/// from current values to the configured shutdown target. Supports both static targets (stable day/night)
 /// and dynamic targets that track an ongoing sunrise/sunset.
 pub struct SmoothTransition {
-    start_temp: u32,
-    start_gamma: f64,
-    target_temp: u32,
-    target_gamma: f64,
+    start_temp: Kelvin,
+    start_gamma: GammaPercent,
+    target_temp: Kelvin,
+    target_gamma: GammaPercent,
     transition_type: TransitionType,
     start_time: Instant,
     duration: Duration,
@@ -158,8 +203,7 @@ impl SmoothTransition {
     /// Always starts from day values regardless of the target, giving a consistent
     /// "wake up" baseline that adjusts to the current time.
     pub fn startup(target_runtime_state: &crate::core::runtime_state::RuntimeState) -> Self {
-        let start_temp = target_runtime_state.config().day_temp;
-        let start_gamma = target_runtime_state.config().day_gamma;
+        let (start_temp, start_gamma) = target_runtime_state.config().day_values();
 
         let (target_temp, target_gamma) = target_runtime_state.values();
         let is_dynamic_target = target_runtime_state.period().is_transitioning();
@@ -222,7 +266,7 @@ impl SmoothTransition {
     /// Used when resuming from an interrupted transition. The start values
     /// are set to the last-applied temp/gamma from the interrupted transition,
     /// preventing a visual jump on the display.
-    pub fn with_start_values(mut self, temp: u32, gamma: f64) -> Self {
+    pub fn with_start_values(mut self, temp: Kelvin, gamma: GammaPercent) -> Self {
         self.start_temp = temp;
         self.start_gamma = gamma;
         self
@@ -245,8 +289,8 @@ impl SmoothTransition {
     /// timed by `startup_duration`.
     pub fn test_mode(
         current_runtime_state: &crate::core::runtime_state::RuntimeState,
-        test_temp: u32,
-        test_gamma: f64,
+        test_temp: Kelvin,
+        test_gamma: GammaPercent,
     ) -> Self {
         let (start_temp, start_gamma) = current_runtime_state.values();
         let target_temp = test_temp;
@@ -278,8 +322,8 @@ impl SmoothTransition {
     /// Counterpart to `test_mode` that restores normal values, timed by `shutdown_duration`.
     pub fn test_restore(
         target_runtime_state: &crate::core::runtime_state::RuntimeState,
-        current_test_temp: u32,
-        current_test_gamma: f64,
+        current_test_temp: Kelvin,
+        current_test_gamma: GammaPercent,
     ) -> Self {
         let start_temp = current_test_temp;
         let start_gamma = current_test_gamma;
@@ -308,10 +352,10 @@ impl SmoothTransition {
         }
     }
 
-    /// Create a shutdown transition from current values to day values.
+    /// Create a shutdown transition from current values to the configured `shutdown_target`.
     ///
     /// Returns `None` when the transition would be instant: `shutdown_duration` below 0.1,
-    /// or the current values already equal the day target.
+    /// or the current values already equal the target (always the case for `current`).
     pub fn shutdown(
         current_runtime_state: &crate::core::runtime_state::RuntimeState,
     ) -> Option<Self> {
@@ -323,10 +367,11 @@ impl SmoothTransition {
 
         let (start_temp, start_gamma) = current_runtime_state.values();
 
-        let target_temp = current_runtime_state.config().day_temp;
-        let target_gamma = current_runtime_state.config().day_gamma;
+        let (target_temp, target_gamma) = current_runtime_state
+            .config()
+            .shutdown_values((start_temp, start_gamma));
 
-        if start_temp == target_temp && (start_gamma - target_gamma).abs() < 0.01 {
+        if start_temp == target_temp && (start_gamma.get() - target_gamma.get()).abs() < 0.01 {
             return None;
         }
 
@@ -359,7 +404,7 @@ impl SmoothTransition {
     fn calculate_current_target(
         &self,
         current_runtime_state: &crate::core::runtime_state::RuntimeState,
-    ) -> (u32, f64) {
+    ) -> (Kelvin, GammaPercent) {
         match self.transition_type {
             TransitionType::Shutdown => (self.target_temp, self.target_gamma),
             TransitionType::Startup => {
@@ -478,11 +523,15 @@ impl SmoothTransition {
             let progress = crate::common::utils::smoothstep(linear_progress);
 
             let (target_temp, target_gamma) = self.calculate_current_target(current_runtime_state);
-            let current_temp = interpolate_inverse_u32(self.start_temp, target_temp, progress);
-            let current_gamma = interpolate_f64(self.start_gamma, target_gamma, progress);
+            let current_temp = self.start_temp.interpolate(target_temp, progress);
+            let current_gamma = self.start_gamma.interpolate(target_gamma, progress);
 
             if self.show_progress_bar {
-                let suffix = format!("(temp: {current_temp}K, gamma: {current_gamma:.1}%)");
+                let suffix = format!(
+                    "(temp: {}K, gamma: {:.1}%)",
+                    current_temp.get(),
+                    current_gamma.get()
+                );
                 self.progress_bar.update(progress, Some(&suffix));
             }
 
@@ -499,6 +548,11 @@ impl SmoothTransition {
             if let Some(signal) = reload_signal
                 && signal.load(Ordering::SeqCst)
             {
+                if self.show_progress_bar {
+                    self.progress_bar.finish();
+                    println!("┃");
+                    io::stdout().flush().ok();
+                }
                 if self.show_progress_bar || self.suppress_logs {
                     Log::set_enabled(true);
                 }
@@ -519,6 +573,11 @@ impl SmoothTransition {
 
             let work_latency = loop_start.elapsed();
             let update_interval = adaptive_interval.update(work_latency);
+            let frame = backend.refresh_interval();
+            let update_interval = match frame {
+                Some(frame) => whole_frames(update_interval, frame),
+                None => update_interval,
+            };
 
             #[cfg(debug_assertions)]
             {
@@ -541,14 +600,16 @@ impl SmoothTransition {
                 break;
             }
 
-            let time_since_last_update = loop_start.duration_since(last_update);
-            if time_since_last_update < update_interval {
-                let sleep_duration = update_interval - time_since_last_update;
-
-                if crate::time::source::is_simulated() {
-                    thread::sleep(Duration::from_millis(1));
-                } else {
-                    high_precision_sleep(sleep_duration);
+            if crate::time::source::is_simulated() {
+                thread::sleep(Duration::from_millis(1));
+            } else {
+                // Step on the compositor's frames where it reports them, else on the clock
+                let wait_start = Instant::now();
+                if !frame.is_some_and(|frame| wait_for_frames(backend, update_interval, frame)) {
+                    let waited = loop_start.duration_since(last_update) + wait_start.elapsed();
+                    if waited < update_interval {
+                        high_precision_sleep(update_interval - waited);
+                    }
                 }
             }
             last_update = Instant::now();
@@ -598,3 +659,91 @@ impl SmoothTransition {
         Ok(TransitionResult::Completed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Capabilities;
+    use crate::core::runtime_state::RuntimeState;
+
+    /// Reports `frames` frames, then none, recording how long each wait was allowed.
+    struct FrameBackend {
+        frames: u32,
+        timeouts: Vec<Duration>,
+    }
+
+    impl ColorTemperatureBackend for FrameBackend {
+        fn apply_transition_state(
+            &mut self,
+            _: &RuntimeState,
+            _: &AtomicBool,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn apply_startup_state(&mut self, _: &RuntimeState, _: &AtomicBool) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn apply_temperature_gamma(
+            &mut self,
+            _: Kelvin,
+            _: GammaPercent,
+            _: &AtomicBool,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn backend_name(&self) -> &'static str {
+            "Frames"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            crate::backend::wayland::WaylandBackend::CAPABILITIES
+        }
+
+        fn wait_for_frame(&mut self, timeout: Duration) -> bool {
+            self.timeouts.push(timeout);
+            let shown = self.frames > 0;
+            self.frames = self.frames.saturating_sub(1);
+            shown
+        }
+    }
+
+    #[test]
+    fn steps_wait_on_each_frame_of_the_interval() {
+        let frame_60hz = Duration::from_secs_f64(1.0 / 60.0);
+        let mut backend = FrameBackend {
+            frames: 5,
+            timeouts: Vec::new(),
+        };
+
+        assert!(wait_for_frames(&mut backend, frame_60hz * 2, frame_60hz));
+        assert_eq!(backend.timeouts, [frame_60hz * 2; 2]);
+
+        // A frame that goes unreported hands the rest of the interval back to the clock
+        backend.timeouts.clear();
+        assert!(!wait_for_frames(&mut backend, frame_60hz * 5, frame_60hz));
+        assert_eq!(backend.timeouts.len(), 4);
+    }
+
+    #[test]
+    fn steps_land_on_whole_frames() {
+        let frame_60hz = Duration::from_secs_f64(1.0 / 60.0);
+
+        // The default 1ms base is capped at one step per frame
+        assert_eq!(
+            whole_frames(Duration::from_millis(1), frame_60hz),
+            frame_60hz
+        );
+        // A slow machine's interval is rounded to the nearest frame rather than beating
+        assert_eq!(
+            whole_frames(Duration::from_millis(30), frame_60hz),
+            frame_60hz * 2
+        );
+        assert_eq!(
+            whole_frames(Duration::from_millis(24), frame_60hz),
+            frame_60hz
+        );
+    }
+}
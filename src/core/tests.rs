@@ -1,9 +1,11 @@
 use super::*;
-use crate::backend::ColorTemperatureBackend;
+use crate::backend::{Capabilities, ColorTemperatureBackend};
+use crate::common::units::values;
 use crate::config::{Backend, Config, TransitionMode, UpdateInterval};
 use crate::core::context::Context;
-use crate::core::period::Period;
+use crate::core::period::{ChangeReason, Period};
 use crate::core::runtime_state::RuntimeState;
+use crate::core::schedule::Scheduler;
 use crate::io::signals::SignalState;
 use serial_test::serial;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -21,7 +23,7 @@ impl ColorTemperatureBackend for CaptureBackend {
         _running: &AtomicBool,
     ) -> Result<()> {
         let (t, g) = runtime_state.values();
-        *self.last.lock().unwrap() = (t, g);
+        *self.last.lock().unwrap() = (t.get(), g.get());
         Ok(())
     }
 
@@ -35,63 +37,51 @@ impl ColorTemperatureBackend for CaptureBackend {
 
     fn apply_temperature_gamma(
         &mut self,
-        temperature: u32,
-        gamma: f64,
+        temperature: Kelvin,
+        gamma: GammaPercent,
         _running: &AtomicBool,
     ) -> Result<()> {
-        *self.last.lock().unwrap() = (temperature, gamma);
+        *self.last.lock().unwrap() = (temperature.get(), gamma.get());
         Ok(())
     }
 
     fn backend_name(&self) -> &'static str {
         "Wayland"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        crate::backend::wayland::WaylandBackend::CAPABILITIES
+    }
 }
 
 fn static_mode_config() -> Config {
-    Config {
-        backend: Backend::Wayland,
-        transition_mode: TransitionMode::Static,
-        smoothing: true,
-        startup_duration: 0.2,
-        shutdown_duration: 0.2,
-        adaptive_interval: 50,
-        night_temp: 3300,
-        day_temp: 6500,
-        night_gamma: 90.0,
-        day_gamma: 100.0,
-        update_interval: UpdateInterval::Fixed(60),
-        static_temp: Some(6500),
-        static_gamma: Some(100.0),
-        sunset: None,
-        sunrise: None,
-        transition_duration: crate::common::constants::DEFAULT_TRANSITION_DURATION_MIN,
-        latitude: None,
-        longitude: None,
-    }
+    Config::builder()
+        .backend(Backend::Wayland)
+        .static_values(6500, 100.0)
+        .smoothing(true)
+        .startup_duration(0.2)
+        .shutdown_duration(0.2)
+        .adaptive_interval(50)
+        .update_interval(UpdateInterval::Fixed(60))
+        .build()
 }
 
 fn geo_adaptive_config() -> Config {
-    Config {
-        backend: Backend::Wayland,
-        transition_mode: TransitionMode::Geo,
-        smoothing: true,
-        startup_duration: 0.2,
-        shutdown_duration: 0.2,
-        adaptive_interval: 50,
-        night_temp: 3300,
-        day_temp: 6500,
-        night_gamma: 90.0,
-        day_gamma: 100.0,
-        update_interval: UpdateInterval::Adaptive,
-        static_temp: Some(6500),
-        static_gamma: Some(100.0),
-        sunset: None,
-        sunrise: None,
-        transition_duration: crate::common::constants::DEFAULT_TRANSITION_DURATION_MIN,
-        latitude: Some(51.5074),
-        longitude: Some(-0.1278),
-    }
+    let mut config = Config::builder()
+        .backend(Backend::Wayland)
+        .transition_mode(TransitionMode::Geo)
+        .smoothing(true)
+        .startup_duration(0.2)
+        .shutdown_duration(0.2)
+        .adaptive_interval(50)
+        .static_temp(6500)
+        .static_gamma(100.0)
+        .latitude(51.5074)
+        .longitude(-0.1278)
+        .build();
+    config.sunset = None;
+    config.sunrise = None;
+    config
 }
 
 fn empty_signal_state() -> SignalState {
@@ -103,6 +93,7 @@ fn empty_signal_state() -> SignalState {
         interrupt: Arc::new(AtomicBool::new(false)),
         in_test_mode: Arc::new(AtomicBool::new(false)),
         instant_shutdown: Arc::new(AtomicBool::new(false)),
+        restarting: Arc::new(AtomicBool::new(false)),
         current_preset: Arc::new(Mutex::new(None)),
     }
 }
@@ -138,10 +129,11 @@ fn recover_state_does_not_self_abort_on_pre_set_interrupt() {
         lock_info: None,
         bypass_smoothing: false,
         ipc_notifier: None,
+        history: AppliedHistory::default(),
     });
 
     let mut tracker = Context::new();
-    core.recover_state(&mut tracker, "wake")
+    core.recover_state(&mut tracker, ChangeReason::Wake)
         .expect("recover_state returned an error");
 
     let (temp, gamma) = *last.lock().unwrap();
@@ -162,6 +154,54 @@ fn recover_state_does_not_self_abort_on_pre_set_interrupt() {
     );
 }
 
+/// A config change made during the smooth startup retargets it from the values reached,
+/// rather than leaving them on screen until the main loop gets to the reload.
+#[test]
+#[serial]
+fn startup_retargets_to_a_config_changed_midway() {
+    let mut config = static_mode_config();
+    config.static_temp = Some(4000);
+    config.static_gamma = Some(90.0);
+    let runtime_state = RuntimeState::new(
+        Period::Static,
+        &config,
+        crate::core::schedule::Schedule::from_config(&config, None),
+        chrono::Local::now(),
+    );
+
+    let signal_state = empty_signal_state();
+    *signal_state.current_preset.lock().unwrap() =
+        crate::state::preset::get_active_preset().ok().flatten();
+    let mut changed = config.clone();
+    changed.static_temp = Some(3000);
+    changed.static_gamma = Some(80.0);
+    // What the config watcher does for `sunsetr set` and SIGUSR2 for a preset switch
+    signal_state.interrupt.store(true, Ordering::SeqCst);
+    signal_state
+        .signal_sender
+        .send(crate::io::signals::SignalMessage::Reload(Box::new(changed)))
+        .unwrap();
+
+    let last = Arc::new(Mutex::new((0u32, 0.0f64)));
+    let mut core = Core::new(CoreParams {
+        backend: Box::new(CaptureBackend { last: last.clone() }),
+        runtime_state,
+        signal_state,
+        debug_enabled: false,
+        lock_info: None,
+        bypass_smoothing: false,
+        ipc_notifier: None,
+        history: AppliedHistory::default(),
+    });
+
+    core.apply_initial_state()
+        .expect("startup returned an error");
+
+    assert_eq!(*last.lock().unwrap(), (3000, 80.0));
+    assert_eq!(core.runtime_state.values(), values(3000, 80.0));
+    assert!(core.signal_state.signal_receiver.try_recv().is_err());
+}
+
 /// A reload that moves only future timing, with the current values, period,
 /// and preset all unchanged, must still be adopted. A discarded reload leaves
 /// the instance sleeping toward the old schedule's boundaries and reporting
@@ -207,6 +247,7 @@ fn reload_adopts_config_that_changes_only_future_timing() {
         lock_info: None,
         bypass_smoothing: false,
         ipc_notifier: None,
+        history: AppliedHistory::default(),
     });
 
     let new_config = clock_config(5);
@@ -220,6 +261,268 @@ fn reload_adopts_config_that_changes_only_future_timing() {
     );
 }
 
+/// Pushed targets are announced with the external reason and kept when the config is
+/// reloaded.
+#[test]
+#[serial]
+fn external_targets_are_announced_and_survive_reloads() {
+    use crate::state::ipc::{IpcNotifier, events::IpcEvent};
+
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::External;
+    config.smoothing = false;
+    let now = chrono::Local::now();
+    let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+    let runtime_state = RuntimeState::at(&config, schedule, now);
+    // Static values until the first push
+    assert_eq!(runtime_state.values(), values(6500, 100.0));
+
+    let (ipc_notifier, ipc_events) = IpcNotifier::new();
+    let last = Arc::new(Mutex::new((0u32, 0.0f64)));
+    let mut core = Core::new(CoreParams {
+        backend: Box::new(CaptureBackend { last: last.clone() }),
+        runtime_state,
+        signal_state: empty_signal_state(),
+        debug_enabled: false,
+        lock_info: None,
+        bypass_smoothing: false,
+        ipc_notifier: Some(ipc_notifier),
+        history: AppliedHistory::default(),
+    });
+    let mut tracker = Context::new();
+
+    let (temp, gamma) = values(1000, 92.0);
+    core.handle_external_targets(&mut tracker, temp, gamma)
+        .unwrap();
+    assert_eq!(*last.lock().unwrap(), (1000, 92.0));
+    assert!(ipc_events.try_iter().any(|event| matches!(
+        event,
+        IpcEvent::StateApplied {
+            reason: ChangeReason::External,
+            ..
+        }
+    )));
+
+    let mut reloaded = config.clone();
+    reloaded.startup_duration = 1.0;
+    core.handle_config_reload(reloaded)
+        .expect("reload returned an error");
+    assert_eq!(core.runtime_state.values(), values(1000, 92.0));
+}
+
+/// Pushes arriving faster than the guard allows are held back, and only the latest one is
+/// applied once the wait is over.
+#[test]
+#[serial]
+fn rapid_external_targets_are_held_back_to_the_latest() {
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::External;
+    config.smoothing = false;
+    let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+    let last = Arc::new(Mutex::new((0u32, 0.0f64)));
+    let mut core = Core::new(CoreParams {
+        backend: Box::new(CaptureBackend { last: last.clone() }),
+        runtime_state: RuntimeState::at(&config, schedule, chrono::Local::now()),
+        signal_state: empty_signal_state(),
+        debug_enabled: false,
+        lock_info: None,
+        bypass_smoothing: false,
+        ipc_notifier: None,
+        history: AppliedHistory::default(),
+    });
+    let mut tracker = Context::new();
+    let push = |temp| HeldBack {
+        external: Some(values(temp, 90.0)),
+        ..HeldBack::default()
+    };
+
+    core.handle_request(&mut tracker, push(3000)).unwrap();
+    core.handle_request(&mut tracker, push(6000)).unwrap();
+    core.handle_request(&mut tracker, push(4000)).unwrap();
+    assert_eq!(*last.lock().unwrap(), (3000, 90.0));
+
+    let wait = core
+        .release_held_back(&mut tracker)
+        .unwrap()
+        .expect("nothing was held back");
+    assert!(wait <= crate::core::guard::MIN_REQUEST_INTERVAL);
+    std::thread::sleep(wait);
+    assert_eq!(core.release_held_back(&mut tracker).unwrap(), None);
+    assert_eq!(*last.lock().unwrap(), (4000, 90.0));
+}
+
+/// Test values asked for right after another change wait their turn like any other
+/// request, rather than taking over the screen at once.
+#[test]
+#[serial]
+fn test_mode_waits_out_the_guard() {
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::External;
+    config.smoothing = false;
+    let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+    let last = Arc::new(Mutex::new((0u32, 0.0f64)));
+    let mut core = Core::new(CoreParams {
+        backend: Box::new(CaptureBackend { last: last.clone() }),
+        runtime_state: RuntimeState::at(&config, schedule, chrono::Local::now()),
+        signal_state: empty_signal_state(),
+        debug_enabled: false,
+        lock_info: None,
+        bypass_smoothing: false,
+        ipc_notifier: None,
+        history: AppliedHistory::default(),
+    });
+    let mut tracker = Context::new();
+
+    let pushed = HeldBack {
+        external: Some(values(3000, 90.0)),
+        ..HeldBack::default()
+    };
+    core.handle_request(&mut tracker, pushed).unwrap();
+    let test = HeldBack {
+        test: Some(values(2000, 70.0)),
+        ..HeldBack::default()
+    };
+    core.handle_request(&mut tracker, test).unwrap();
+
+    assert_eq!(*last.lock().unwrap(), (3000, 90.0));
+    assert_eq!(core.guard.held_back.test, Some(values(2000, 70.0)));
+    assert!(!core.signal_state.in_test_mode.load(Ordering::Relaxed));
+}
+
+#[test]
+fn external_targets_are_ignored_in_other_modes() {
+    let config = static_mode_config();
+    let runtime_state = RuntimeState::new(Period::Static, &config, None, chrono::Local::now());
+    let (temp, gamma) = values(4000, 90.0);
+    assert!(runtime_state.with_external_targets(temp, gamma).is_none());
+}
+
+#[test]
+fn values_changed_waits_for_a_step_during_transitions() {
+    let mut config = static_mode_config();
+    config.values_changed_temp_step = 100;
+    config.values_changed_gamma_step = 1.0;
+    let last = Some(values(5000, 95.0));
+
+    assert!(values_worth_reporting(
+        None,
+        values(5000, 95.0),
+        &config,
+        true
+    ));
+    assert!(!values_worth_reporting(
+        last,
+        values(5000, 95.0),
+        &config,
+        false
+    ));
+
+    // Small steps inside a transition are held back until they add up
+    assert!(!values_worth_reporting(
+        last,
+        values(4950, 94.5),
+        &config,
+        true
+    ));
+    assert!(values_worth_reporting(
+        last,
+        values(4900, 94.5),
+        &config,
+        true
+    ));
+    assert!(values_worth_reporting(
+        last,
+        values(4950, 94.0),
+        &config,
+        true
+    ));
+
+    // Reaching a stable period always reports the final values
+    assert!(values_worth_reporting(
+        last,
+        values(4990, 95.0),
+        &config,
+        false
+    ));
+
+    config.values_changed_temp_step = 0;
+    config.values_changed_gamma_step = 0.0;
+    assert!(values_worth_reporting(
+        last,
+        values(4999, 95.0),
+        &config,
+        true
+    ));
+}
+
+/// Smooth and instant reloads announce the target first and then exactly one applied
+/// state, so clients see the same events whichever way the values were applied.
+#[test]
+#[serial]
+fn reloads_emit_the_same_events_with_or_without_smoothing() {
+    use crate::state::ipc::{IpcNotifier, events::IpcEvent};
+
+    for smoothing in [true, false] {
+        let mut config = static_mode_config();
+        config.smoothing = smoothing;
+        let runtime_state = RuntimeState::new(Period::Static, &config, None, chrono::Local::now());
+
+        let signal_state = empty_signal_state();
+        *signal_state.current_preset.lock().unwrap() =
+            crate::state::preset::get_active_preset().ok().flatten();
+
+        let (ipc_notifier, ipc_events) = IpcNotifier::new();
+        let last = Arc::new(Mutex::new((0u32, 0.0f64)));
+        let mut core = Core::new(CoreParams {
+            backend: Box::new(CaptureBackend { last: last.clone() }),
+            runtime_state,
+            signal_state,
+            debug_enabled: false,
+            lock_info: None,
+            bypass_smoothing: false,
+            ipc_notifier: Some(ipc_notifier),
+            history: AppliedHistory::default(),
+        });
+
+        let mut new_config = config.clone();
+        new_config.static_temp = Some(4000);
+        core.handle_config_reload(new_config)
+            .expect("reload returned an error");
+
+        assert_eq!(
+            *last.lock().unwrap(),
+            (4000, 100.0),
+            "smoothing={smoothing}"
+        );
+        let events: Vec<&'static str> = ipc_events
+            .try_iter()
+            .map(|event| match event {
+                IpcEvent::ConfigChanged { target_temp, .. } => {
+                    assert_eq!(target_temp.get(), 4000);
+                    "config_changed"
+                }
+                IpcEvent::StateApplied { state, reason } => {
+                    assert_eq!(
+                        (state.current_temp.get(), reason),
+                        (4000, ChangeReason::Reload)
+                    );
+                    "state_applied"
+                }
+                IpcEvent::ValuesChanged { current_temp, .. } => {
+                    assert_eq!(current_temp.get(), 4000);
+                    "values_changed"
+                }
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            events,
+            vec!["config_changed", "state_applied", "values_changed"],
+            "smoothing={smoothing}"
+        );
+    }
+}
+
 /// The adaptive update interval must position the current time within the same
 /// timezone frame as the transition window. When the configured coordinates sit
 /// in a different timezone than the system clock, mixing a coordinate-frame
@@ -280,3 +583,778 @@ fn adaptive_interval_uses_coordinate_frame_for_geo() {
     let interval = state.effective_update_interval_secs();
     assert_eq!(interval, 36, "interval at the window midpoint");
 }
+
+#[test]
+fn only_wakes_well_past_the_deadline_count_as_oversleep() {
+    let deadline = chrono::Local::now();
+    let after = |secs| deadline + chrono::Duration::seconds(secs);
+
+    assert_eq!(oversleep(Some(deadline), after(-5)), None);
+    assert_eq!(oversleep(Some(deadline), after(1)), None);
+    assert_eq!(oversleep(Some(deadline), after(30)), None);
+    assert_eq!(
+        oversleep(Some(deadline), after(600)),
+        Some(std::time::Duration::from_secs(600))
+    );
+    // Static mode sleeps without a deadline
+    assert_eq!(oversleep(None, after(600)), None);
+}
+
+#[test]
+fn heartbeats_are_sent_once_per_interval() {
+    use crate::state::ipc::events::IpcEvent;
+
+    let mut config = static_mode_config();
+    config.heartbeat_interval = 30;
+    let runtime_state = RuntimeState::new(Period::Static, &config, None, chrono::Local::now());
+
+    let (ipc_notifier, events) = IpcNotifier::new();
+    let last = Arc::new(Mutex::new((0u32, 0.0f64)));
+    let mut core = Core::new(CoreParams {
+        backend: Box::new(CaptureBackend { last }),
+        runtime_state,
+        signal_state: empty_signal_state(),
+        debug_enabled: false,
+        lock_info: None,
+        bypass_smoothing: false,
+        ipc_notifier: Some(ipc_notifier),
+        history: AppliedHistory::default(),
+    });
+
+    core.send_heartbeat_if_due();
+    assert!(
+        events.try_recv().is_err(),
+        "heartbeat sent before the interval"
+    );
+
+    core.last_heartbeat -= std::time::Duration::from_secs(30);
+    core.send_heartbeat_if_due();
+    core.send_heartbeat_if_due();
+    assert!(matches!(
+        events.try_recv(),
+        Ok(IpcEvent::Heartbeat {
+            interval_secs: 30,
+            ..
+        })
+    ));
+    assert!(events.try_recv().is_err(), "heartbeat sent twice");
+
+    config.heartbeat_interval = 0;
+    core.runtime_state = RuntimeState::new(Period::Static, &config, None, chrono::Local::now());
+    core.last_heartbeat -= std::time::Duration::from_secs(3600);
+    core.send_heartbeat_if_due();
+    assert!(events.try_recv().is_err(), "heartbeat sent while disabled");
+}
+
+/// The next update reported to clients is one update interval out during a transition,
+/// the transition end when that comes first, and absent in static mode.
+#[test]
+fn next_update_follows_the_main_loop_sleep() {
+    use chrono::{Local, TimeZone};
+
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::Center;
+    config.sunset = Some("19:00:00".to_string());
+    config.sunrise = Some("06:00:00".to_string());
+    config.transition_duration = 30;
+    let schedule = || crate::core::schedule::Schedule::from_config(&config, None);
+
+    let mid = Local.with_ymd_and_hms(2024, 6, 21, 19, 0, 0).unwrap();
+    let state = RuntimeState::new(Period::Sunset, &config, schedule(), mid);
+    assert_eq!(
+        state.next_update(),
+        Some(mid + chrono::Duration::seconds(60))
+    );
+
+    let near_end = Local.with_ymd_and_hms(2024, 6, 21, 19, 14, 30).unwrap();
+    let state = RuntimeState::new(Period::Sunset, &config, schedule(), near_end);
+    let end =
+        near_end + chrono::Duration::from_std(state.time_until_transition_end().unwrap()).unwrap();
+    assert_eq!(state.next_update(), Some(end));
+    assert!(end < near_end + chrono::Duration::seconds(60));
+
+    let config = static_mode_config();
+    let state = RuntimeState::new(
+        Period::Static,
+        &config,
+        crate::core::schedule::Schedule::from_config(&config, None),
+        mid,
+    );
+    assert_eq!(state.next_update(), None);
+}
+
+/// A value offset moves the scheduled values, transitions included, until it expires.
+#[test]
+fn value_offset_moves_values_until_it_expires() {
+    use chrono::{Local, TimeZone};
+
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::Center;
+    config.sunset = Some("19:00:00".to_string());
+    config.sunrise = Some("06:00:00".to_string());
+    config.transition_duration = 30;
+    config.value_offset = Some(crate::state::value_offset::ValueOffset {
+        temp: 500,
+        gamma: -10.0,
+        expires_at: Local.with_ymd_and_hms(2024, 6, 22, 5, 45, 0).unwrap(),
+    });
+    let state = |period, time| {
+        let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+        RuntimeState::new(period, &config, schedule, time)
+    };
+
+    let night = Local.with_ymd_and_hms(2024, 6, 22, 2, 0, 0).unwrap();
+    assert_eq!(state(Period::Night, night).values(), values(3800, 80.0));
+
+    let mid_sunset = Local.with_ymd_and_hms(2024, 6, 21, 19, 0, 0).unwrap();
+    let mid_sunset = state(Period::Sunset, mid_sunset);
+    let (temp, gamma) = mid_sunset.values();
+    let (scheduled_temp, scheduled_gamma) =
+        crate::core::values::values(mid_sunset.phase(), &config);
+    assert_eq!(
+        (temp.get(), gamma.get()),
+        (scheduled_temp + 500, scheduled_gamma - 10.0)
+    );
+
+    let morning = Local.with_ymd_and_hms(2024, 6, 22, 8, 0, 0).unwrap();
+    let state = state(Period::Day, morning);
+    assert_eq!(state.value_offset(), None);
+    assert_eq!(state.values(), values(6500, 100.0));
+}
+
+/// A pause until sunrise ends as the next sunrise starts, past one underway, and has
+/// nothing to end on in static mode.
+#[test]
+fn next_sunrise_start_looks_past_one_underway() {
+    use chrono::{Local, TimeZone};
+
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::Center;
+    config.sunset = Some("19:00:00".to_string());
+    config.sunrise = Some("06:00:00".to_string());
+    config.transition_duration = 30;
+    let schedule = || crate::core::schedule::Schedule::from_config(&config, None);
+    let sunrise = Local.with_ymd_and_hms(2024, 6, 22, 5, 45, 0).unwrap();
+
+    let noon = Local.with_ymd_and_hms(2024, 6, 21, 12, 0, 0).unwrap();
+    let state = RuntimeState::new(Period::Day, &config, schedule(), noon);
+    assert_eq!(state.next_sunrise_start(), Some(sunrise));
+
+    let night = Local.with_ymd_and_hms(2024, 6, 22, 2, 0, 0).unwrap();
+    let state = RuntimeState::new(Period::Night, &config, schedule(), night);
+    assert_eq!(state.next_sunrise_start(), Some(sunrise));
+
+    let mid_sunrise = Local.with_ymd_and_hms(2024, 6, 22, 6, 0, 0).unwrap();
+    let state = RuntimeState::new(Period::Sunrise, &config, schedule(), mid_sunrise);
+    assert_eq!(
+        state.next_sunrise_start(),
+        Some(sunrise + chrono::Duration::days(1))
+    );
+
+    let config = static_mode_config();
+    let state = RuntimeState::new(Period::Static, &config, None, noon);
+    assert_eq!(state.next_sunrise_start(), None);
+}
+
+/// Overrides expire as a sunset or sunrise begins, which lies past the end of a transition
+/// already underway.
+#[test]
+fn overrides_expire_at_the_next_transition_start() {
+    use chrono::{Local, TimeZone};
+
+    assert!(begins_transition(Some(Period::Day), Period::Sunset));
+    assert!(begins_transition(Some(Period::Night), Period::Sunrise));
+    assert!(!begins_transition(Some(Period::Sunset), Period::Night));
+    assert!(!begins_transition(Some(Period::Day), Period::Day));
+    assert!(!begins_transition(None, Period::Sunset));
+
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::Center;
+    config.sunset = Some("19:00:00".to_string());
+    config.sunrise = Some("06:00:00".to_string());
+    config.transition_duration = 30;
+    let schedule = || crate::core::schedule::Schedule::from_config(&config, None);
+
+    let afternoon = Local.with_ymd_and_hms(2024, 6, 21, 15, 0, 0).unwrap();
+    let state = RuntimeState::new(Period::Day, &config, schedule(), afternoon);
+    assert_eq!(
+        state.next_transition_start(),
+        Some(Local.with_ymd_and_hms(2024, 6, 21, 18, 45, 0).unwrap())
+    );
+
+    let mid_sunset = Local.with_ymd_and_hms(2024, 6, 21, 19, 0, 0).unwrap();
+    let state = RuntimeState::new(Period::Sunset, &config, schedule(), mid_sunset);
+    assert_eq!(
+        state.next_transition_start(),
+        Some(Local.with_ymd_and_hms(2024, 6, 22, 5, 45, 0).unwrap())
+    );
+}
+
+#[test]
+fn power_saving_slows_updates_and_skips_smoothing() {
+    use crate::config::PowerSaving;
+    use chrono::{Local, TimeZone};
+
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::Center;
+    config.sunset = Some("19:00:00".to_string());
+    config.sunrise = Some("06:00:00".to_string());
+    config.transition_duration = 30;
+    config.smoothing = true;
+    let mid = Local.with_ymd_and_hms(2024, 6, 21, 19, 0, 0).unwrap();
+    let state = |config: &Config| {
+        let schedule = crate::core::schedule::Schedule::from_config(config, None);
+        RuntimeState::new(Period::Sunset, config, schedule, mid)
+    };
+
+    config.power_saving = PowerSaving::Off;
+    let regular = state(&config);
+    assert_eq!(regular.effective_update_interval_secs(), 60);
+    assert!(regular.smoothing());
+
+    config.power_saving = PowerSaving::On;
+    let saving = state(&config);
+    assert_eq!(
+        saving.effective_update_interval_secs(),
+        crate::common::constants::POWER_SAVING_UPDATE_INTERVAL_SEC
+    );
+    assert!(!saving.smoothing());
+    // The end of the transition is still reached on time
+    let near_end = Local.with_ymd_and_hms(2024, 6, 21, 19, 14, 0).unwrap();
+    let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+    let saving = RuntimeState::new(Period::Sunset, &config, schedule, near_end);
+    assert_eq!(
+        saving.time_until_next_update(),
+        saving.time_until_transition_end().unwrap()
+    );
+}
+
+#[test]
+fn adjust_update_interval_gives_short_transitions_enough_steps() {
+    use crate::config::{PowerSaving, UpdateInterval};
+    use chrono::{Local, TimeZone};
+
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::Center;
+    config.sunset = Some("19:00:00".to_string());
+    config.sunrise = Some("06:00:00".to_string());
+    config.transition_duration = 5;
+    config.sunrise_duration = Some(120);
+    config.update_interval = UpdateInterval::Fixed(300);
+    config.power_saving = PowerSaving::Off;
+    let state = |config: &Config, period, time| {
+        let schedule = crate::core::schedule::Schedule::from_config(config, None);
+        RuntimeState::new(period, config, schedule, time)
+    };
+    let sunset = Local.with_ymd_and_hms(2024, 6, 21, 19, 0, 0).unwrap();
+    let sunrise = Local.with_ymd_and_hms(2024, 6, 22, 6, 0, 0).unwrap();
+
+    assert_eq!(
+        state(&config, Period::Sunset, sunset).effective_update_interval_secs(),
+        300
+    );
+
+    config.adjust_update_interval = true;
+    // 5 minutes over ten steps, while sunrise's two hours already take enough at 300
+    assert_eq!(
+        state(&config, Period::Sunset, sunset).effective_update_interval_secs(),
+        30
+    );
+    assert_eq!(
+        state(&config, Period::Sunrise, sunrise).effective_update_interval_secs(),
+        300
+    );
+}
+
+#[test]
+fn reduce_motion_skips_smoothing() {
+    use crate::config::PowerSaving;
+
+    let mut config = static_mode_config();
+    config.smoothing = true;
+    config.power_saving = PowerSaving::Off;
+    config.reduce_motion = true;
+    let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+    let state = RuntimeState::new(Period::Static, &config, schedule, chrono::Local::now());
+    assert!(!state.smoothing());
+}
+
+#[test]
+fn pause_holds_outputs_neutral_until_toggled_back() {
+    use crate::state::ipc::{IpcNotifier, events::IpcEvent};
+
+    let config = static_mode_config();
+    let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+    let (ipc_notifier, ipc_events) = IpcNotifier::new();
+    let mut core = Core::new(CoreParams {
+        backend: Box::new(CaptureBackend {
+            last: Arc::new(Mutex::new((0, 0.0))),
+        }),
+        runtime_state: RuntimeState::at(&config, schedule, chrono::Local::now()),
+        signal_state: empty_signal_state(),
+        debug_enabled: false,
+        lock_info: None,
+        bypass_smoothing: false,
+        ipc_notifier: Some(ipc_notifier),
+        history: AppliedHistory::default(),
+    });
+    let paused_states = |events: &std::sync::mpsc::Receiver<IpcEvent>| -> Vec<bool> {
+        events
+            .try_iter()
+            .filter_map(|event| match event {
+                IpcEvent::StateApplied {
+                    state,
+                    reason: ChangeReason::Pause,
+                } => Some(state.paused),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let mut tracker = Context::new();
+
+    core.request_pause(&mut tracker, None, None).unwrap();
+    assert!(core.backend_config(&config).presentation.is_some());
+    assert_eq!(paused_states(&ipc_events), [true]);
+
+    // Asking for the state it is already in still gets an answer
+    core.request_pause(&mut tracker, Some(true), None).unwrap();
+    assert_eq!(paused_states(&ipc_events), [true]);
+
+    // Toggling straight back waits out the guard
+    core.request_pause(&mut tracker, None, None).unwrap();
+    assert!(paused_states(&ipc_events).is_empty());
+    std::thread::sleep(crate::core::guard::MIN_REQUEST_INTERVAL);
+    core.release_held_back(&mut tracker).unwrap();
+    assert!(core.backend_config(&config).presentation.is_none());
+    assert_eq!(paused_states(&ipc_events), [false]);
+}
+
+/// A pause with an end resumes by itself once the main loop reaches it, and tells clients
+/// both times. A sunrise the schedule doesn't have leaves the pause without an end.
+#[test]
+fn timed_pause_resumes_by_itself() {
+    use crate::core::pause::{PauseEvent, PauseUntil};
+    use crate::state::ipc::{IpcNotifier, events::IpcEvent};
+
+    let config = static_mode_config();
+    let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+    let (ipc_notifier, ipc_events) = IpcNotifier::new();
+    let mut core = Core::new(CoreParams {
+        backend: Box::new(CaptureBackend {
+            last: Arc::new(Mutex::new((0, 0.0))),
+        }),
+        runtime_state: RuntimeState::at(&config, schedule, chrono::Local::now()),
+        signal_state: empty_signal_state(),
+        debug_enabled: false,
+        lock_info: None,
+        bypass_smoothing: false,
+        ipc_notifier: Some(ipc_notifier),
+        history: AppliedHistory::default(),
+    });
+    let pause_changes = |events: &std::sync::mpsc::Receiver<IpcEvent>| {
+        events
+            .try_iter()
+            .filter_map(|event| match event {
+                IpcEvent::PauseChanged { paused, until } => Some((paused, until)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let in_an_hour = chrono::Local::now() + chrono::Duration::hours(1);
+    core.handle_pause(true, Some(PauseUntil::Time(in_an_hour)));
+    assert_eq!(pause_changes(&ipc_events), [(true, Some(in_an_hour))]);
+    let remaining = core.end_pause().expect("the pause has an end");
+    assert!(remaining > std::time::Duration::from_secs(3500));
+
+    let just_now = chrono::Local::now() - chrono::Duration::seconds(1);
+    core.handle_pause(true, Some(PauseUntil::Time(just_now)));
+    assert_eq!(core.end_pause(), None);
+    assert_eq!(core.pause, None);
+    assert_eq!(
+        pause_changes(&ipc_events),
+        [(true, Some(just_now)), (false, None)]
+    );
+
+    core.handle_pause(true, Some(PauseUntil::Event(PauseEvent::Sunrise)));
+    assert_eq!(core.pause, Some(Pause { until: None }));
+    assert_eq!(core.end_pause(), None);
+}
+
+#[test]
+fn winddown_lowers_night_values_toward_bedtime() {
+    use crate::config::WinddownConfig;
+    use chrono::{Local, TimeZone};
+
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::Center;
+    config.sunset = Some("19:00:00".to_string());
+    config.sunrise = Some("06:00:00".to_string());
+    config.transition_duration = 30;
+    config.night_temp = 3300;
+    config.night_gamma = 90.0;
+    config.winddown = Some(WinddownConfig {
+        start: "22:00:00".to_string(),
+        bedtime: "23:00:00".to_string(),
+        temp_drop: 600,
+        gamma_drop: 10.0,
+    });
+    let state = |hour, minute| {
+        let now = Local
+            .with_ymd_and_hms(2024, 6, 21, hour, minute, 0)
+            .unwrap();
+        let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+        RuntimeState::new(Period::Night, &config, schedule, now)
+    };
+
+    let before = state(21, 0);
+    assert_eq!(before.values(), values(3300, 90.0));
+    assert_eq!(
+        before.time_until_next_update(),
+        std::time::Duration::from_secs(3600)
+    );
+
+    let halfway = state(22, 30);
+    assert_eq!(halfway.winddown_progress(), Some(0.5));
+    assert_eq!(halfway.values().1.get(), 85.0);
+    assert_eq!(
+        halfway.time_until_next_update(),
+        std::time::Duration::from_secs(halfway.effective_update_interval_secs())
+    );
+
+    let bedtime = state(23, 30);
+    assert_eq!(bedtime.values(), values(2700, 80.0));
+    assert_eq!(
+        bedtime.time_until_next_update(),
+        bedtime.time_until_next_event()
+    );
+}
+
+/// One `apply_*_state` call as seen by `RecordingBackend`.
+#[derive(Debug, Clone)]
+struct AppliedState {
+    at: chrono::DateTime<chrono::Local>,
+    period: Period,
+    temp: u32,
+    gamma: f64,
+}
+
+/// Backend stub that records every state it is asked to apply, stamped with
+/// the (possibly simulated) time of the call.
+struct RecordingBackend {
+    applied: Arc<Mutex<Vec<AppliedState>>>,
+    resets: Arc<Mutex<Vec<(u32, f64)>>>,
+    capabilities: Capabilities,
+}
+
+/// A compositor that leaves the last values on screen, so the exit write is recorded.
+const KEEPS_GAMMA: Capabilities = Capabilities {
+    keeps_gamma_on_exit: true,
+    ..crate::backend::wayland::WaylandBackend::CAPABILITIES
+};
+
+impl ColorTemperatureBackend for RecordingBackend {
+    fn apply_transition_state(
+        &mut self,
+        runtime_state: &RuntimeState,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        let (temp, gamma) = runtime_state.values();
+        self.applied.lock().unwrap().push(AppliedState {
+            at: crate::time::source::now(),
+            period: runtime_state.period(),
+            temp: temp.get(),
+            gamma: gamma.get(),
+        });
+        Ok(())
+    }
+
+    fn apply_startup_state(
+        &mut self,
+        runtime_state: &RuntimeState,
+        running: &AtomicBool,
+    ) -> Result<()> {
+        self.apply_transition_state(runtime_state, running)
+    }
+
+    fn apply_temperature_gamma(
+        &mut self,
+        temperature: Kelvin,
+        gamma: GammaPercent,
+        _running: &AtomicBool,
+    ) -> Result<()> {
+        self.resets
+            .lock()
+            .unwrap()
+            .push((temperature.get(), gamma.get()));
+        Ok(())
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "Wayland"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+}
+
+/// Without a smooth shutdown, the values written on exit still follow `shutdown_target`.
+#[test]
+fn instant_shutdown_writes_the_shutdown_target() {
+    let config = Config::builder()
+        .backend(Backend::Wayland)
+        .static_values(4000, 90.0)
+        .smoothing(false)
+        .shutdown_target(crate::config::ShutdownTarget::Day)
+        .day_temp(5500)
+        .day_gamma(95.0)
+        .update_interval(UpdateInterval::Fixed(60))
+        .build();
+    let resets = Arc::new(Mutex::new(Vec::new()));
+    let runtime_state = RuntimeState::new(
+        Period::Static,
+        &config,
+        crate::core::schedule::Schedule::from_config(&config, None),
+        chrono::Local::now(),
+    );
+    let signal_state = empty_signal_state();
+    signal_state.running.store(false, Ordering::SeqCst);
+
+    Core::new(CoreParams {
+        backend: Box::new(RecordingBackend {
+            applied: Arc::new(Mutex::new(Vec::new())),
+            resets: resets.clone(),
+            capabilities: KEEPS_GAMMA,
+        }),
+        runtime_state,
+        signal_state,
+        debug_enabled: false,
+        lock_info: None,
+        bypass_smoothing: false,
+        ipc_notifier: None,
+        history: AppliedHistory::default(),
+    })
+    .execute()
+    .unwrap();
+
+    assert_eq!(*resets.lock().unwrap(), vec![(5500, 95.0)]);
+}
+
+/// The X server, the kernel and Mutter all keep the ramps sunsetr leaves behind, so the
+/// backends driving them end on the shutdown target even though none of them smooths.
+#[test]
+fn ramp_backends_end_on_the_shutdown_target() {
+    use crate::backend::BackendType;
+
+    for backend_type in [BackendType::X11, BackendType::Drm, BackendType::Gnome] {
+        let config = Config::builder()
+            .backend(Backend::Wayland)
+            .static_values(4000, 90.0)
+            .smoothing(false)
+            .shutdown_target(crate::config::ShutdownTarget::Day)
+            .day_temp(5500)
+            .day_gamma(95.0)
+            .update_interval(UpdateInterval::Fixed(60))
+            .build();
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let resets = Arc::new(Mutex::new(Vec::new()));
+        let runtime_state = RuntimeState::new(
+            Period::Static,
+            &config,
+            crate::core::schedule::Schedule::from_config(&config, None),
+            chrono::Local::now(),
+        );
+        let signal_state = empty_signal_state();
+        signal_state.running.store(false, Ordering::SeqCst);
+
+        Core::new(CoreParams {
+            backend: Box::new(RecordingBackend {
+                applied: applied.clone(),
+                resets: resets.clone(),
+                capabilities: backend_type.capabilities(),
+            }),
+            runtime_state,
+            signal_state,
+            debug_enabled: false,
+            lock_info: None,
+            bypass_smoothing: false,
+            ipc_notifier: None,
+            history: AppliedHistory::default(),
+        })
+        .execute()
+        .unwrap();
+
+        assert_eq!(
+            resets.lock().unwrap().last(),
+            Some(&(5500, 95.0)),
+            "{}",
+            backend_type.name()
+        );
+    }
+}
+
+/// Run `Core::execute` through a full simulated day and check everything it
+/// emitted: the applied values per period, the spacing of transition updates,
+/// and the IPC events that mirror them.
+#[test]
+fn simulated_day_applies_every_period_and_keeps_updating() {
+    use crate::state::ipc::{IpcNotifier, events::IpcEvent};
+    use crate::time::source::{SimulatedTimeSource, SimulationPace, with_scoped_source};
+    use chrono::TimeZone;
+
+    let start = chrono::Local
+        .with_ymd_and_hms(2025, 6, 10, 12, 0, 0)
+        .unwrap();
+    let end = start + chrono::Duration::hours(24);
+    let source = Arc::new(SimulatedTimeSource::new(
+        start,
+        end,
+        SimulationPace::FastForward,
+    ));
+
+    let mut config = static_mode_config();
+    config.transition_mode = TransitionMode::FinishBy;
+    config.smoothing = false;
+    config.sunset = Some("19:00:00".to_string());
+    config.sunrise = Some("06:00:00".to_string());
+    config.transition_duration = 30;
+    config.update_interval = UpdateInterval::Fixed(60);
+
+    let applied = Arc::new(Mutex::new(Vec::new()));
+    let resets = Arc::new(Mutex::new(Vec::new()));
+    let (ipc_notifier, ipc_events) = IpcNotifier::new();
+
+    with_scoped_source(source, || {
+        let schedule = crate::core::schedule::Schedule::from_config(&config, None);
+        let period = schedule
+            .as_ref()
+            .expect("clock config yields a schedule")
+            .current_period(start);
+        let runtime_state = RuntimeState::new(period, &config, schedule, start);
+
+        Core::new(CoreParams {
+            backend: Box::new(RecordingBackend {
+                applied: applied.clone(),
+                resets: resets.clone(),
+                capabilities: KEEPS_GAMMA,
+            }),
+            runtime_state,
+            signal_state: empty_signal_state(),
+            debug_enabled: false,
+            lock_info: None,
+            bypass_smoothing: false,
+            ipc_notifier: Some(ipc_notifier),
+            history: AppliedHistory::default(),
+        })
+        .execute()
+        .expect("simulated run returned an error");
+    });
+
+    let applied = applied.lock().unwrap().clone();
+    let first = applied.first().expect("nothing was applied");
+    assert_eq!(first.at, start);
+    assert_eq!(
+        (first.period, first.temp, first.gamma),
+        (Period::Day, 6500, 100.0)
+    );
+
+    let mut periods: Vec<Period> = applied.iter().map(|a| a.period).collect();
+    periods.dedup();
+    assert_eq!(
+        periods,
+        vec![
+            Period::Day,
+            Period::Sunset,
+            Period::Night,
+            Period::Sunrise,
+            Period::Day
+        ]
+    );
+
+    let night = applied.iter().find(|a| a.period == Period::Night).unwrap();
+    assert_eq!((night.temp, night.gamma), (3300, 90.0));
+    assert_eq!(night.at.format("%H:%M:%S").to_string(), "19:00:00");
+
+    for (transition, warming) in [(Period::Sunset, false), (Period::Sunrise, true)] {
+        let updates: Vec<&AppliedState> =
+            applied.iter().filter(|a| a.period == transition).collect();
+        assert!(
+            updates.len() >= 25,
+            "{transition:?} stopped updating after {} steps",
+            updates.len()
+        );
+        for pair in updates.windows(2) {
+            let gap = pair[1].at - pair[0].at;
+            assert!(
+                gap <= chrono::Duration::seconds(61),
+                "{transition:?} went {gap} without an update at {}",
+                pair[0].at
+            );
+            if warming {
+                assert!(pair[1].temp >= pair[0].temp);
+            } else {
+                assert!(pair[1].temp <= pair[0].temp);
+            }
+        }
+    }
+
+    let last = applied.last().unwrap();
+    assert_eq!(
+        (last.period, last.temp, last.gamma),
+        (Period::Day, 6500, 100.0)
+    );
+    assert_eq!(*resets.lock().unwrap(), vec![(6500, 100.0)]);
+
+    let events: Vec<IpcEvent> = ipc_events.try_iter().collect();
+    let period_changes: Vec<(Period, Period, ChangeReason)> = events
+        .iter()
+        .filter_map(|event| match event {
+            IpcEvent::PeriodChanged {
+                from_period,
+                to_period,
+                reason,
+            } => Some((*from_period, *to_period, *reason)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        period_changes,
+        vec![
+            (Period::Day, Period::Sunset, ChangeReason::Schedule),
+            (Period::Sunset, Period::Night, ChangeReason::Schedule),
+            (Period::Night, Period::Sunrise, ChangeReason::Schedule),
+            (Period::Sunrise, Period::Day, ChangeReason::Schedule),
+        ]
+    );
+
+    let reasons: Vec<ChangeReason> = events
+        .iter()
+        .filter_map(|event| match event {
+            IpcEvent::StateApplied { reason, .. } => Some(*reason),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(reasons.first(), Some(&ChangeReason::Startup));
+    assert!(
+        reasons[1..]
+            .iter()
+            .all(|reason| matches!(reason, ChangeReason::Schedule | ChangeReason::Boundary))
+    );
+
+    let broadcast: Vec<(u32, f64)> = events
+        .iter()
+        .filter_map(|event| match event {
+            IpcEvent::StateApplied { state, .. } => {
+                Some((state.current_temp.get(), state.current_gamma.get()))
+            }
+            _ => None,
+        })
+        .collect();
+    let backend_values: Vec<(u32, f64)> = applied.iter().map(|a| (a.temp, a.gamma)).collect();
+    assert_eq!(
+        broadcast, backend_values,
+        "every applied state should be broadcast exactly once"
+    );
+}
@@ -0,0 +1,218 @@
+//! Temperature and gamma as a pure function of the phase and config.
+//!
+//! Stable periods take their values straight from the config. Sunset and sunrise blend
+//! between the day and night values by progress: temperature along a harmonic mean (linear in
+//! mired, which tracks perception more closely than Kelvin) and gamma linearly. Static mode uses the
+//! static values, falling back to day defaults.
+//!
+//! The schedule decides the phase, this module only turns it into values.
+//! [`RuntimeState::values`](crate::core::runtime_state::RuntimeState::values) and the JSON
+//! timeline of `--simulate` both go through [`values`], so what the simulation prints is
+//! exactly what the daemon applies.
+
+use crate::common::constants::{DEFAULT_DAY_GAMMA, DEFAULT_DAY_TEMP, DEFAULT_SATURATION};
+use crate::common::utils::{interpolate_f64, interpolate_inverse_u32};
+use crate::config::Config;
+use crate::core::period::Phase;
+
+/// Color temperature in Kelvin.
+pub fn temperature(phase: Phase, config: &Config) -> u32 {
+    match phase {
+        Phase::Day => config.day_temp,
+        Phase::Night => config.night_temp,
+        Phase::Static => config.static_temp.unwrap_or(DEFAULT_DAY_TEMP),
+        Phase::Sunset { progress } => {
+            interpolate_inverse_u32(config.day_temp, config.night_temp, progress)
+        }
+        Phase::Sunrise { progress } => {
+            interpolate_inverse_u32(config.night_temp, config.day_temp, progress)
+        }
+    }
+}
+
+/// Gamma in percent.
+pub fn gamma(phase: Phase, config: &Config) -> f64 {
+    match phase {
+        Phase::Day => config.day_gamma,
+        Phase::Night => config.night_gamma,
+        Phase::Static => config.static_gamma.unwrap_or(DEFAULT_DAY_GAMMA),
+        Phase::Sunset { progress } => {
+            interpolate_f64(config.day_gamma, config.night_gamma, progress)
+        }
+        Phase::Sunrise { progress } => {
+            interpolate_f64(config.night_gamma, config.day_gamma, progress)
+        }
+    }
+}
+
+/// Saturation in percent. Only the night lowers it, so day and static mode leave colors
+/// alone and transitions blend linearly like gamma.
+pub fn saturation(phase: Phase, config: &Config) -> f64 {
+    match phase {
+        Phase::Day | Phase::Static => DEFAULT_SATURATION,
+        Phase::Night => config.night_saturation,
+        Phase::Sunset { progress } => {
+            interpolate_f64(DEFAULT_SATURATION, config.night_saturation, progress)
+        }
+        Phase::Sunrise { progress } => {
+            interpolate_f64(config.night_saturation, DEFAULT_SATURATION, progress)
+        }
+    }
+}
+
+/// Brightness in percent. Static mode keeps the day's, and transitions blend linearly like
+/// gamma.
+pub fn brightness(phase: Phase, config: &Config) -> f64 {
+    match phase {
+        Phase::Day | Phase::Static => config.day_brightness,
+        Phase::Night => config.night_brightness,
+        Phase::Sunset { progress } => {
+            interpolate_f64(config.day_brightness, config.night_brightness, progress)
+        }
+        Phase::Sunrise { progress } => {
+            interpolate_f64(config.night_brightness, config.day_brightness, progress)
+        }
+    }
+}
+
+/// Temperature and gamma together.
+pub fn values(phase: Phase, config: &Config) -> (u32, f64) {
+    (temperature(phase, config), gamma(phase, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Backend, UpdateInterval};
+    use crate::core::period::Period;
+
+    fn config() -> Config {
+        Config::builder()
+            .backend(Backend::Wayland)
+            .smoothing(false)
+            .startup_duration(0.0)
+            .shutdown_duration(0.0)
+            .update_interval(UpdateInterval::Fixed(60))
+            .static_temp(4000)
+            .static_gamma(80.0)
+            .transition_duration(45)
+            .build()
+    }
+
+    fn sunset(progress: f32) -> Phase {
+        Phase::Sunset { progress }
+    }
+
+    fn sunrise(progress: f32) -> Phase {
+        Phase::Sunrise { progress }
+    }
+
+    #[test]
+    fn stable_periods_ignore_progress() {
+        let config = config();
+        for progress in [None, Some(0.0), Some(0.5), Some(1.0)] {
+            let phase = |period| Phase::new(period, progress);
+            assert_eq!(values(phase(Period::Day), &config), (6500, 100.0));
+            assert_eq!(values(phase(Period::Night), &config), (3300, 90.0));
+            assert_eq!(values(phase(Period::Static), &config), (4000, 80.0));
+        }
+    }
+
+    #[test]
+    fn static_values_fall_back_to_day_defaults() {
+        let mut config = config();
+        config.static_temp = None;
+        config.static_gamma = None;
+        assert_eq!(
+            values(Phase::Static, &config),
+            (DEFAULT_DAY_TEMP, DEFAULT_DAY_GAMMA)
+        );
+    }
+
+    #[test]
+    fn transitions_start_and_end_at_the_stable_values() {
+        let config = config();
+        assert_eq!(values(sunset(0.0), &config), (6500, 100.0));
+        assert_eq!(values(sunset(1.0), &config), (3300, 90.0));
+        assert_eq!(values(sunrise(0.0), &config), (3300, 90.0));
+        assert_eq!(values(sunrise(1.0), &config), (6500, 100.0));
+
+        // No progress yet is the start of the transition
+        assert_eq!(
+            values(Phase::new(Period::Sunset, None), &config),
+            (6500, 100.0)
+        );
+        assert_eq!(
+            values(Phase::new(Period::Sunrise, None), &config),
+            (3300, 90.0)
+        );
+    }
+
+    #[test]
+    fn progress_outside_the_transition_is_clamped() {
+        let config = config();
+        assert_eq!(values(sunset(-0.5), &config), (6500, 100.0));
+        assert_eq!(values(sunset(1.5), &config), (3300, 90.0));
+    }
+
+    #[test]
+    fn temperature_follows_a_harmonic_mean_and_gamma_a_line() {
+        let config = config();
+        // 6500 * 3300 / (3300 + 3200 * 0.5)
+        assert_eq!(temperature(sunset(0.5), &config), 4378);
+        assert_eq!(gamma(sunset(0.5), &config), 95.0);
+        assert_eq!(temperature(sunrise(0.5), &config), 4378);
+        assert_eq!(gamma(sunrise(0.25), &config), 92.5);
+    }
+
+    #[test]
+    fn transitions_are_monotonic() {
+        let config = config();
+        let sweep = |phase: fn(f32) -> Phase| -> Vec<(u32, f64)> {
+            (0..=100)
+                .map(|step| values(phase(step as f32 / 100.0), &config))
+                .collect()
+        };
+
+        for pair in sweep(sunset).windows(2) {
+            assert!(pair[1].0 <= pair[0].0 && pair[1].1 <= pair[0].1, "{pair:?}");
+        }
+        for pair in sweep(sunrise).windows(2) {
+            assert!(pair[1].0 >= pair[0].0 && pair[1].1 >= pair[0].1, "{pair:?}");
+        }
+    }
+
+    #[test]
+    fn inverted_configs_transition_the_other_way() {
+        let mut config = config();
+        config.day_temp = 3000;
+        config.night_temp = 5000;
+        assert_eq!(temperature(sunset(0.0), &config), 3000);
+        assert_eq!(temperature(sunset(1.0), &config), 5000);
+        let mid = temperature(sunset(0.5), &config);
+        assert!((3000..5000).contains(&mid));
+    }
+
+    #[test]
+    fn brightness_follows_the_phase() {
+        let mut config = config();
+        config.night_brightness = 60.0;
+        config.day_brightness = 90.0;
+        assert_eq!(brightness(Phase::Day, &config), 90.0);
+        assert_eq!(brightness(Phase::Static, &config), 90.0);
+        assert_eq!(brightness(Phase::Night, &config), 60.0);
+        assert_eq!(brightness(sunset(0.5), &config), 75.0);
+        assert_eq!(brightness(sunrise(0.25), &config), 67.5);
+    }
+
+    #[test]
+    fn only_the_night_lowers_saturation() {
+        let mut config = config();
+        config.night_saturation = 60.0;
+        assert_eq!(saturation(Phase::Day, &config), 100.0);
+        assert_eq!(saturation(Phase::Static, &config), 100.0);
+        assert_eq!(saturation(Phase::Night, &config), 60.0);
+        assert_eq!(saturation(sunset(0.5), &config), 80.0);
+        assert_eq!(saturation(sunrise(0.25), &config), 70.0);
+    }
+}
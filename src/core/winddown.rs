@@ -0,0 +1,206 @@
+//! Wind-down: nights that keep getting warmer toward bedtime.
+//!
+//! Layered on the night values by
+//! [`RuntimeState::values`](crate::core::runtime_state::RuntimeState::values). From the
+//! configured start, temperature and gamma drop gradually, reach the full drops at bedtime
+//! and hold them until the night ends. A start that falls before the night begins takes
+//! effect with the night, already partway down the ramp, and a wind-down whose bedtime
+//! passes before the night begins is skipped.
+//!
+//! Times of day are compared by seconds since midnight, like the manual transition windows.
+
+use chrono::{DateTime, Local, NaiveTime, Timelike};
+use std::time::Duration;
+
+use crate::common::constants::{MINIMUM_GAMMA, MINIMUM_TEMP};
+use crate::common::utils::{interpolate_f64, interpolate_inverse_u32};
+use crate::config::WinddownConfig;
+
+const DAY_SECS: i64 = 24 * 3600;
+
+/// The current night as its start and end.
+type Night = (DateTime<Local>, DateTime<Local>);
+
+/// Where `now` falls relative to the wind-down of the current night.
+struct Position {
+    /// Seconds since the most recent start time.
+    elapsed: i64,
+    /// Seconds from start to bedtime.
+    ramp: i64,
+    /// Seconds since the night began.
+    since_night_start: i64,
+    /// Seconds until the night ends.
+    until_night_end: i64,
+}
+
+impl Position {
+    fn new(winddown: &WinddownConfig, now: DateTime<Local>, night: Night) -> Option<Self> {
+        let (night_start, night_end) = night;
+        let start = seconds_of_day(&winddown.start)?;
+        let bedtime = seconds_of_day(&winddown.bedtime)?;
+        Some(Self {
+            elapsed: (i64::from(now.time().num_seconds_from_midnight()) - start)
+                .rem_euclid(DAY_SECS),
+            ramp: (bedtime - start).rem_euclid(DAY_SECS),
+            since_night_start: (now - night_start).num_seconds(),
+            until_night_end: (night_end - now).num_seconds(),
+        })
+    }
+
+    /// The start time has passed, its bedtime came after the night began and the night
+    /// ends before the start comes around again.
+    fn is_active(&self) -> bool {
+        self.until_night_end > 0
+            && self.elapsed - self.ramp < self.since_night_start
+            && self.elapsed + self.until_night_end < DAY_SECS
+    }
+
+    fn progress(&self) -> f32 {
+        if self.ramp == 0 {
+            return 1.0;
+        }
+        (self.elapsed as f32 / self.ramp as f32).min(1.0)
+    }
+}
+
+fn seconds_of_day(time: &str) -> Option<i64> {
+    NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .ok()
+        .map(|time| i64::from(time.num_seconds_from_midnight()))
+}
+
+/// How far down the ramp the night is at `now`, from 0.0 at start to 1.0 from bedtime on.
+/// None outside the wind-down.
+pub fn progress(winddown: &WinddownConfig, now: DateTime<Local>, night: Night) -> Option<f32> {
+    Position::new(winddown, now, night)
+        .filter(Position::is_active)
+        .map(|position| position.progress())
+}
+
+/// The night values lowered by `progress` of the configured drops, temperature along the
+/// same mired-linear curve transitions use.
+pub fn apply(values: (u32, f64), winddown: &WinddownConfig, progress: f32) -> (u32, f64) {
+    let (temp, gamma) = values;
+    let bedtime_temp = temp.saturating_sub(winddown.temp_drop).max(MINIMUM_TEMP);
+    let bedtime_gamma = (gamma - winddown.gamma_drop).max(MINIMUM_GAMMA);
+    (
+        interpolate_inverse_u32(temp, bedtime_temp.min(temp), progress),
+        interpolate_f64(gamma, bedtime_gamma.min(gamma), progress),
+    )
+}
+
+/// How long until the wind-down next moves the values: at most `update_interval` while
+/// ramping, or until it starts when that happens before the night ends. None once the
+/// full drop is held or when no wind-down happens this night.
+pub fn time_until_next_step(
+    winddown: &WinddownConfig,
+    now: DateTime<Local>,
+    night: Night,
+    update_interval: Duration,
+) -> Option<Duration> {
+    let position = Position::new(winddown, now, night)?;
+    if position.is_active() {
+        let until_bedtime = position.ramp - position.elapsed;
+        return (until_bedtime > 0)
+            .then(|| update_interval.min(Duration::from_secs(until_bedtime as u64)));
+    }
+    let until_start = DAY_SECS - position.elapsed;
+    (until_start < position.until_night_end).then(|| Duration::from_secs(until_start as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn winddown() -> WinddownConfig {
+        WinddownConfig {
+            start: "22:00:00".to_string(),
+            bedtime: "23:00:00".to_string(),
+            temp_drop: 600,
+            gamma_drop: 10.0,
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2025, 1, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn ramps_from_start_to_bedtime_and_holds_until_morning() {
+        let winddown = winddown();
+        let night = (at(10, 19, 15), at(11, 6, 0));
+
+        assert_eq!(progress(&winddown, at(10, 21, 30), night), None);
+        assert_eq!(progress(&winddown, at(10, 22, 0), night), Some(0.0));
+        assert_eq!(progress(&winddown, at(10, 22, 30), night), Some(0.5));
+        assert_eq!(progress(&winddown, at(10, 23, 0), night), Some(1.0));
+        assert_eq!(progress(&winddown, at(11, 3, 0), night), Some(1.0));
+    }
+
+    #[test]
+    fn a_start_after_the_night_never_applies() {
+        let mut winddown = winddown();
+        winddown.start = "07:00:00".to_string();
+        winddown.bedtime = "08:00:00".to_string();
+        let night = (at(10, 19, 15), at(11, 6, 0));
+
+        assert_eq!(progress(&winddown, at(11, 3, 0), night), None);
+        assert_eq!(
+            time_until_next_step(&winddown, at(11, 3, 0), night, Duration::from_secs(60)),
+            None
+        );
+    }
+
+    #[test]
+    fn a_start_before_the_night_joins_partway_down() {
+        let mut winddown = winddown();
+        winddown.start = "18:15:00".to_string();
+        winddown.bedtime = "20:15:00".to_string();
+        let night = (at(10, 19, 15), at(11, 6, 0));
+        assert_eq!(progress(&winddown, at(10, 19, 15), night), Some(0.5));
+
+        // Bedtime before the night began
+        winddown.bedtime = "19:00:00".to_string();
+        assert_eq!(progress(&winddown, at(10, 19, 15), night), None);
+    }
+
+    #[test]
+    fn drops_are_spread_over_the_ramp() {
+        let winddown = winddown();
+        assert_eq!(apply((3300, 90.0), &winddown, 0.0), (3300, 90.0));
+        assert_eq!(apply((3300, 90.0), &winddown, 1.0), (2700, 80.0));
+        let (temp, gamma) = apply((3300, 90.0), &winddown, 0.5);
+        assert!((2700..3300).contains(&temp));
+        assert_eq!(gamma, 85.0);
+
+        // Never below the valid ranges
+        assert_eq!(apply((1200, 15.0), &winddown, 1.0), (1000, 10.0));
+    }
+
+    #[test]
+    fn wakes_for_the_start_and_each_step_until_bedtime() {
+        let winddown = winddown();
+        let night = (at(10, 19, 15), at(11, 6, 0));
+        let interval = Duration::from_secs(60);
+
+        assert_eq!(
+            time_until_next_step(&winddown, at(10, 21, 0), night, interval),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            time_until_next_step(&winddown, at(10, 22, 10), night, interval),
+            Some(interval)
+        );
+        assert_eq!(
+            time_until_next_step(&winddown, at(10, 22, 59), night, interval),
+            Some(interval)
+        );
+        assert_eq!(
+            time_until_next_step(&winddown, at(10, 23, 30), night, interval),
+            None
+        );
+    }
+}
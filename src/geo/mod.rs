@@ -4,6 +4,7 @@
 
 pub mod city_selector;
 pub mod display;
+pub mod provider;
 pub mod solar;
 pub mod times;
 pub mod timezone;
@@ -23,13 +24,20 @@ pub enum GeoSelectionResult {
     Cancelled,
 }
 
-/// Run the interactive geo location workflow.
+/// Run the geo location workflow.
 ///
 /// `target` names the config to write (the default config or a preset) and
-/// skips the which-config picker. None runs the picker.
+/// skips the which-config picker. None runs the picker. `provider` names the
+/// [`provider::LocationProvider`] to ask for coordinates, the interactive city
+/// search when None.
 pub fn run_geo_workflow(
     debug_enabled: bool,
     target: Option<String>,
+    provider: Option<&str>,
 ) -> anyhow::Result<GeoSelectionResult> {
-    GeoWorkflow::new(debug_enabled, target).run()
+    let provider = match provider {
+        Some(name) => provider::find(name)?,
+        None => provider::default_provider(),
+    };
+    GeoWorkflow::new(debug_enabled, target, provider).run()
 }
@@ -0,0 +1,103 @@
+//! Location providers: where `sunsetr geo` gets coordinates from.
+//!
+//! Each source of coordinates implements [`LocationProvider`] and is listed in
+//! [`PROVIDERS`], the only place that knows which providers exist. The geo workflow asks a
+//! provider for a [`Location`] and takes it from there (latitude cap, sun time preview,
+//! writing the config), so a new source such as geoclue, an IP lookup or gpsd is a module
+//! implementing the trait plus one line in the registry. The same goes for a custom
+//! provider compiled into a downstream build.
+
+use anyhow::Result;
+
+use crate::geo::{detect_coordinates_from_timezone, select_city_interactive};
+
+/// Coordinates found by a provider, with a name to show for them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// A city or place name, e.g. "Chicago, United States".
+    pub name: String,
+}
+
+/// A source of coordinates for geo mode.
+pub trait LocationProvider: Sync {
+    /// Name used to pick the provider with `sunsetr geo --provider <name>`.
+    fn name(&self) -> &'static str;
+
+    /// One line for help output.
+    fn description(&self) -> &'static str;
+
+    /// Find the location. `None` means the user backed out, which isn't an error.
+    fn locate(&self) -> Result<Option<Location>>;
+}
+
+/// Every provider, in the order help lists them. The first is the default.
+pub static PROVIDERS: &[&dyn LocationProvider] = &[&CityProvider, &TimezoneProvider];
+
+/// The provider named `name`.
+pub fn find(name: &str) -> Result<&'static dyn LocationProvider> {
+    PROVIDERS
+        .iter()
+        .copied()
+        .find(|provider| provider.name() == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = PROVIDERS.iter().map(|provider| provider.name()).collect();
+            anyhow::anyhow!(
+                "Unknown location provider '{name}' (available: {})",
+                names.join(", ")
+            )
+        })
+}
+
+/// The provider used when none is named.
+pub fn default_provider() -> &'static dyn LocationProvider {
+    PROVIDERS[0]
+}
+
+/// Interactive search of the bundled city database.
+pub struct CityProvider;
+
+impl LocationProvider for CityProvider {
+    fn name(&self) -> &'static str {
+        "city"
+    }
+
+    fn description(&self) -> &'static str {
+        "Search for the nearest city interactively"
+    }
+
+    fn locate(&self) -> Result<Option<Location>> {
+        match select_city_interactive() {
+            Ok((latitude, longitude, name)) => Ok(Some(Location {
+                latitude,
+                longitude,
+                name,
+            })),
+            Err(e) if e.to_string().contains("cancelled") => Ok(None),
+            Err(e) => Err(e.context("Failed to run interactive city selection")),
+        }
+    }
+}
+
+/// A representative city for the system timezone, without any interaction.
+pub struct TimezoneProvider;
+
+impl LocationProvider for TimezoneProvider {
+    fn name(&self) -> &'static str {
+        "timezone"
+    }
+
+    fn description(&self) -> &'static str {
+        "Use a city in the system timezone, without prompting"
+    }
+
+    fn locate(&self) -> Result<Option<Location>> {
+        let (latitude, longitude, name) = detect_coordinates_from_timezone()?;
+        Ok(Some(Location {
+            latitude,
+            longitude,
+            name,
+        }))
+    }
+}
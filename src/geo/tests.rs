@@ -734,3 +734,29 @@ mod transition_times_tests {
         });
     }
 }
+
+#[cfg(test)]
+mod provider_tests {
+    use crate::geo::provider::*;
+
+    #[test]
+    fn providers_are_found_by_name() {
+        assert_eq!(find("timezone").unwrap().name(), "timezone");
+        assert_eq!(default_provider().name(), "city");
+
+        let Err(error) = find("gpsd") else {
+            panic!("gpsd is not a provider yet");
+        };
+        let error = error.to_string();
+        assert!(error.contains("Unknown location provider 'gpsd'"));
+        assert!(error.contains("city, timezone"));
+    }
+
+    #[test]
+    fn provider_names_are_unique() {
+        let mut names: Vec<_> = PROVIDERS.iter().map(|provider| provider.name()).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), PROVIDERS.len());
+    }
+}
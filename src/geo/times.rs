@@ -9,6 +9,7 @@ use chrono_tz::Tz;
 use std::time::Duration as StdDuration;
 
 use crate::core::period::Period;
+use crate::core::period::calculations::{night_start_overrun, pull_sunset_back, sunrise_advance};
 use crate::geo::solar::{SolarTimes, calculate_solar_times};
 
 #[derive(Debug, Clone)]
@@ -63,13 +64,16 @@ fn sunrise_start_date(end_date: NaiveDate, start: NaiveTime, end: NaiveTime) ->
 
 impl GeoTimes {
     pub fn new(latitude: f64, longitude: f64) -> Result<Self> {
-        let now = crate::time::source::now();
+        Self::at(latitude, longitude, crate::time::source::now())
+    }
+
+    /// The windows as they would be computed with the clock at `time`.
+    pub fn at(latitude: f64, longitude: f64, time: DateTime<Local>) -> Result<Self> {
         let coordinate_tz = crate::geo::solar::determine_timezone(latitude, longitude);
-        let now_in_tz = now.with_timezone(&coordinate_tz);
-        let today = now_in_tz.date_naive();
+        let today = time.with_timezone(&coordinate_tz).date_naive();
 
         let solar_result = calculate_solar_times(latitude, longitude, today)?;
-        Self::from_solar_result(&solar_result, today, now, latitude, longitude)
+        Self::from_solar_result(&solar_result, today, time, latitude, longitude)
     }
 
     /// Build from config when in geo mode with coordinates set.
@@ -282,6 +286,38 @@ impl GeoTimes {
         None
     }
 
+    /// Apply `night_latest_start` and `min_day_duration` to the computed windows.
+    pub fn apply_day_limits(&mut self, config: &crate::config::Config) {
+        if let Some(latest) = config.night_latest_start_time() {
+            self.limit_night_start(latest);
+        }
+        if let Some(minutes) = config.min_day_duration {
+            self.keep_min_day(Duration::minutes(minutes as i64));
+        }
+    }
+
+    /// End the sunset transition by `latest` local time, see [`night_start_overrun`].
+    fn limit_night_start(&mut self, latest: NaiveTime) {
+        let end = self.sunset_end.with_timezone(&Local).time();
+        if let Some(overrun) = night_start_overrun(end, latest) {
+            let length = self.sunset_end - self.sunset_start;
+            (self.sunset_start, self.sunset_end) =
+                pull_sunset_back(self.sunset_start, self.sunset_end, length, overrun);
+        }
+    }
+
+    /// Move the sunrise transition earlier until `min_day` separates it from sunset.
+    fn keep_min_day(&mut self, min_day: Duration) {
+        if let Some(advance) = sunrise_advance(
+            (self.sunset_start.time(), self.sunset_end.time()),
+            (self.sunrise_start.time(), self.sunrise_end.time()),
+            min_day,
+        ) {
+            self.sunrise_start -= advance;
+            self.sunrise_end -= advance;
+        }
+    }
+
     pub fn handle_location_change(&mut self, latitude: f64, longitude: f64) -> Result<()> {
         *self = Self::new(latitude, longitude)?;
         Ok(())
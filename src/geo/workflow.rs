@@ -3,7 +3,8 @@
 use anyhow::{Context, Result};
 
 use crate::config::Config;
-use crate::geo::{GeoSelectionResult, log_solar_debug_info, select_city_interactive};
+use crate::geo::provider::LocationProvider;
+use crate::geo::{GeoSelectionResult, log_solar_debug_info};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ConfigTarget {
@@ -14,13 +15,19 @@ pub enum ConfigTarget {
 pub struct GeoWorkflow {
     debug_enabled: bool,
     target: Option<String>,
+    provider: &'static dyn LocationProvider,
 }
 
 impl GeoWorkflow {
-    pub fn new(debug_enabled: bool, target: Option<String>) -> Self {
+    pub fn new(
+        debug_enabled: bool,
+        target: Option<String>,
+        provider: &'static dyn LocationProvider,
+    ) -> Self {
         Self {
             debug_enabled,
             target,
+            provider,
         }
     }
 
@@ -38,7 +45,7 @@ impl GeoWorkflow {
         }
         let target = target.unwrap();
 
-        let coords = self.select_city()?;
+        let coords = self.locate()?;
         if coords.is_none() {
             return Ok(GeoSelectionResult::Cancelled);
         }
@@ -104,20 +111,19 @@ impl GeoWorkflow {
         }
     }
 
-    /// Run interactive city selection, returning `(latitude, longitude, city_name)`
+    /// Ask the provider for a location, returning `(latitude, longitude, city_name)`
     /// or `None` if the user cancels.
-    fn select_city(&self) -> Result<Option<(f64, f64, String)>> {
-        use anyhow::Context;
+    fn locate(&self) -> Result<Option<(f64, f64, String)>> {
+        if self.debug_enabled {
+            log_pipe!();
+            log_debug!("Location provider: {}", self.provider.name());
+        }
 
-        let (mut latitude, longitude, city_name) = match select_city_interactive() {
-            Ok(coords) => coords,
-            Err(e) => {
-                if e.to_string().contains("cancelled") {
-                    return Ok(None);
-                }
-                return Err(e).context("Failed to run interactive city selection");
-            }
+        let Some(location) = self.provider.locate()? else {
+            return Ok(None);
         };
+        let (mut latitude, longitude, city_name) =
+            (location.latitude, location.longitude, location.name);
 
         let was_capped = latitude.abs() > 65.0;
         if was_capped {
@@ -0,0 +1,203 @@
+//! Crash reports written to the state directory.
+//!
+//! When sunsetr panics or stops on a fatal error, it writes one text file with what a bug
+//! report needs: the version, the backend, the config file, the last log lines and a
+//! backtrace. Nothing is sent anywhere. Coordinates are removed from the config, and log
+//! lines that mention a location are dropped, so the file can be attached as is.
+
+use anyhow::{Context, Result};
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::fmt::Write as _;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::common::build_info::BuildInfo;
+use crate::common::logger;
+
+/// The backend in use, once one has been picked.
+static BACKEND: Mutex<Option<&'static str>> = Mutex::new(None);
+
+/// Config keys whose values are left out of the report.
+pub const REDACTED_KEYS: &[&str] = &["latitude", "longitude"];
+
+/// Words marking a log line that can give away where the user is.
+const LOCATION_WORDS: &[&str] = &[
+    "latitude",
+    "longitude",
+    "coordinates",
+    "location",
+    "°n",
+    "°s",
+    "°e",
+    "°w",
+    "sun times for",
+];
+
+/// Record the backend for any report written later.
+pub fn note_backend(name: &'static str) {
+    if let Ok(mut backend) = BACKEND.lock() {
+        *backend = Some(name);
+    }
+}
+
+/// Write a report for every panic, after the default hook has printed it.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let backtrace = Backtrace::force_capture();
+        match write_report(&panic_message(info), &backtrace.to_string()) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(e) => eprintln!("Failed to write a crash report: {e:#}"),
+        }
+    }));
+}
+
+/// Write a report for an error that stopped sunsetr, logging where it went.
+pub fn report_fatal_error(error: &anyhow::Error) {
+    let backtrace = match error.backtrace().status() {
+        BacktraceStatus::Captured => error.backtrace().to_string(),
+        _ => "Not captured, run with RUST_BACKTRACE=1 to include one".to_string(),
+    };
+    let what = format!("Fatal error: {}", crate::common::error::format_chain(error));
+    match write_report(&what, &backtrace) {
+        Ok(path) => {
+            log_pipe!();
+            log_info!("Crash report written to {}", path.display());
+            log_indented!("Coordinates are removed, so it can be attached to a bug report");
+        }
+        Err(e) => {
+            log_pipe!();
+            log_warning!("Failed to write a crash report: {e:#}");
+        }
+    }
+}
+
+fn panic_message(info: &PanicHookInfo<'_>) -> String {
+    let payload = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("(no message)");
+    let thread = std::thread::current();
+    let thread = thread.name().unwrap_or("unnamed");
+    match info.location() {
+        Some(location) => format!("Panic in thread '{thread}' at {location}: {payload}"),
+        None => format!("Panic in thread '{thread}': {payload}"),
+    }
+}
+
+fn write_report(what: &str, backtrace: &str) -> Result<PathBuf> {
+    let config_dir = crate::config::get_custom_config_dir();
+    let state_dir = crate::state::preset::get_state_dir(config_dir.as_deref())?;
+    std::fs::create_dir_all(&state_dir)
+        .with_context(|| format!("Failed to create {}", state_dir.display()))?;
+
+    let now = chrono::Local::now();
+    let path = state_dir.join(format!(
+        "crash-{}-{}.txt",
+        now.format("%Y%m%d-%H%M%S"),
+        std::process::id()
+    ));
+    std::fs::write(&path, report(what, backtrace, &now.to_rfc3339()))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn report(what: &str, backtrace: &str, time: &str) -> String {
+    let build = BuildInfo::current();
+    let backend = BACKEND
+        .try_lock()
+        .ok()
+        .and_then(|backend| *backend)
+        .unwrap_or("not selected yet");
+
+    let mut report = String::new();
+    let _ = writeln!(report, "sunsetr crash report");
+    let _ = writeln!(report, "Time: {time}");
+    let _ = writeln!(report, "Version: {} ({})", build.version, build.profile);
+    let _ = writeln!(report, "Target: {}", build.target);
+    let _ = writeln!(report, "Backend: {backend}");
+    let _ = writeln!(
+        report,
+        "Compositor: {:?}",
+        crate::backend::detect_compositor()
+    );
+    let _ = writeln!(report, "\n== What happened ==\n{what}");
+
+    let _ = writeln!(report, "\n== Config (coordinates removed) ==");
+    match crate::config::Config::get_config_path()
+        .and_then(|path| Ok(std::fs::read_to_string(path)?))
+    {
+        Ok(content) => report.push_str(&redact_config(&content)),
+        Err(e) => {
+            let _ = writeln!(report, "Unavailable: {e:#}");
+        }
+    }
+
+    let lines = logger::recent_lines();
+    let _ = writeln!(
+        report,
+        "\n== Last {} log lines (location lines removed) ==",
+        lines.len()
+    );
+    for line in &lines {
+        let _ = writeln!(report, "{}", redact_log_line(line));
+    }
+
+    let _ = writeln!(report, "\n== Backtrace ==\n{backtrace}");
+    report
+}
+
+/// The config with the values of [`REDACTED_KEYS`] replaced, comments and layout kept.
+pub fn redact_config(content: &str) -> String {
+    let mut redacted = String::with_capacity(content.len());
+    for line in content.lines() {
+        let key = line.split('=').next().unwrap_or_default().trim();
+        if line.contains('=') && REDACTED_KEYS.contains(&key) {
+            let _ = writeln!(redacted, "{key} = \"<removed>\"");
+        } else {
+            let _ = writeln!(redacted, "{line}");
+        }
+    }
+    redacted
+}
+
+/// The line, or a placeholder when it mentions where the user is.
+pub fn redact_log_line(line: &str) -> &str {
+    let lower = line.to_lowercase();
+    if LOCATION_WORDS.iter().any(|word| lower.contains(word)) {
+        "<line removed>"
+    } else {
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations_are_removed() {
+        let config = "#[Private geo coordinates]\nlatitude = 40.7128\n  longitude=-74.006\nnight_temp = 3300\n";
+        assert_eq!(
+            redact_config(config),
+            "#[Private geo coordinates]\nlatitude = \"<removed>\"\nlongitude = \"<removed>\"\nnight_temp = 3300\n"
+        );
+
+        assert_eq!(
+            redact_log_line("┃   Location: 40.713°N, 74.006°W"),
+            "<line removed>"
+        );
+        assert_eq!(
+            redact_log_line("┣ Sun times for New York (40.7128°N, 74.0060°W)"),
+            "<line removed>"
+        );
+        assert_eq!(
+            redact_log_line("┣ Night temp: 3300K"),
+            "┣ Night temp: 3300K"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! darkman integration, so the dark-mode daemon and sunsetr agree on when it is night.
+//!
+//! Works in either direction over the session D-Bus:
+//!
+//! - `transition_mode = "darkman"` follows darkman's `Mode`. Dark applies the night values
+//!   and light the day values, with a smooth transition when smoothing is on. The monitor
+//!   records the mode here for the darkman schedule to read and wakes Core with
+//!   `SignalMessage::DarkModeChanged`.
+//! - `darkman_export = true` sets darkman's mode from sunsetr's schedule instead, switching
+//!   to dark halfway through sunset and back to light halfway through sunrise.
+//!
+//! Exporting while following would only echo darkman's own mode back, so config
+//! validation allows one or the other.
+
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use zbus::blocking::Connection;
+
+use crate::core::period::Phase;
+use crate::io::signals::SignalMessage;
+
+const MODE_UNKNOWN: u8 = 0;
+const MODE_LIGHT: u8 = 1;
+const MODE_DARK: u8 = 2;
+
+static MODE: AtomicU8 = AtomicU8::new(MODE_UNKNOWN);
+static MONITOR_STARTED: AtomicBool = AtomicBool::new(false);
+
+#[zbus::proxy(
+    interface = "nl.whynothugo.darkman",
+    default_service = "nl.whynothugo.darkman",
+    default_path = "/nl/whynothugo/darkman"
+)]
+trait Darkman {
+    /// `dark` or `light`. Changes come as `ModeChanged` rather than `PropertiesChanged`.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn mode(&self) -> zbus::Result<String>;
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn set_mode(&self, mode: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn mode_changed(&self, new_mode: &str) -> zbus::Result<()>;
+}
+
+/// Whether darkman is in dark mode. `None` until the monitor has read the mode, and for
+/// good when darkman isn't running.
+pub fn is_dark() -> Option<bool> {
+    match MODE.load(Ordering::Relaxed) {
+        MODE_LIGHT => Some(false),
+        MODE_DARK => Some(true),
+        _ => None,
+    }
+}
+
+/// Read darkman's current mode and follow its changes from then on.
+///
+/// Reads the mode before returning, so the first state applied is already right. Until
+/// darkman answers or announces a mode, sunsetr stays on the day values. Calling this
+/// again once the monitor runs does nothing.
+pub fn start_mode_monitor(signal_sender: Sender<SignalMessage>, debug_enabled: bool) {
+    if MONITOR_STARTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let proxy = match connect() {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            log_pipe!();
+            log_warning!("Could not read darkman's mode: {e:#}");
+            log_indented!("Staying on the day values until darkman is running");
+            MONITOR_STARTED.store(false, Ordering::Relaxed);
+            return;
+        }
+    };
+    match proxy.mode() {
+        Ok(mode) => {
+            if debug_enabled {
+                log_pipe!();
+                log_debug!("darkman mode: {mode}");
+            }
+            record_mode(&mode);
+        }
+        Err(e) => {
+            log_pipe!();
+            log_warning!("Could not read darkman's mode: {e}");
+            log_indented!("Staying on the day values until darkman switches modes");
+        }
+    }
+
+    thread::spawn(move || {
+        if let Err(e) = follow_mode(&proxy, &signal_sender, debug_enabled) {
+            log_pipe!();
+            log_warning!("Stopped following darkman: {e:#}");
+        }
+    });
+}
+
+fn follow_mode(
+    proxy: &DarkmanProxyBlocking<'static>,
+    signal_sender: &Sender<SignalMessage>,
+    debug_enabled: bool,
+) -> Result<()> {
+    let changes = proxy
+        .receive_mode_changed()
+        .context("Failed to subscribe to darkman's mode changes")?;
+    for change in changes {
+        let args = change
+            .args()
+            .context("Failed to read darkman's changed mode")?;
+        if debug_enabled {
+            log_pipe!();
+            log_debug!("darkman mode changed to {}", args.new_mode);
+        }
+        if !record_mode(args.new_mode) {
+            continue;
+        }
+        if signal_sender.send(SignalMessage::DarkModeChanged).is_err() {
+            // The main loop is gone
+            return Ok(());
+        }
+    }
+    anyhow::bail!("darkman mode change stream ended")
+}
+
+/// Record `mode`, returning whether it differs from the one recorded before.
+fn record_mode(mode: &str) -> bool {
+    let mode = match mode {
+        "dark" => MODE_DARK,
+        "light" => MODE_LIGHT,
+        _ => MODE_UNKNOWN,
+    };
+    MODE.swap(mode, Ordering::Relaxed) != mode
+}
+
+/// Set darkman's mode in the background, warning when darkman can't be reached.
+pub fn export(dark: bool, debug_enabled: bool) {
+    let mode = if dark { "dark" } else { "light" };
+    thread::spawn(move || {
+        let result = connect().and_then(|proxy| {
+            proxy
+                .set_mode(mode)
+                .context("darkman rejected the mode change")
+        });
+        match result {
+            Ok(()) if debug_enabled => {
+                log_pipe!();
+                log_debug!("Set darkman mode to {mode}");
+            }
+            Ok(()) => {}
+            Err(e) => {
+                log_pipe!();
+                log_warning!("Could not set darkman's mode to {mode}: {e:#}");
+            }
+        }
+    });
+}
+
+/// The darkman mode for `phase`: dark from halfway through sunset to halfway through
+/// sunrise. `None` for static mode, which has no night.
+pub fn dark_at(phase: Phase) -> Option<bool> {
+    match phase {
+        Phase::Day => Some(false),
+        Phase::Night => Some(true),
+        Phase::Sunset { progress } => Some(progress >= 0.5),
+        Phase::Sunrise { progress } => Some(progress < 0.5),
+        Phase::Static => None,
+    }
+}
+
+/// Without caching, so every read asks darkman rather than a copy from before a change.
+fn connect() -> Result<DarkmanProxyBlocking<'static>> {
+    let connection = Connection::session().context("Failed to connect to session D-Bus")?;
+    DarkmanProxyBlocking::builder(&connection)
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()
+        .context("Failed to create darkman proxy")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dark_mode_switches_halfway_through_transitions() {
+        assert_eq!(dark_at(Phase::Day), Some(false));
+        assert_eq!(dark_at(Phase::Sunset { progress: 0.49 }), Some(false));
+        assert_eq!(dark_at(Phase::Sunset { progress: 0.5 }), Some(true));
+        assert_eq!(dark_at(Phase::Night), Some(true));
+        assert_eq!(dark_at(Phase::Sunrise { progress: 0.49 }), Some(true));
+        assert_eq!(dark_at(Phase::Sunrise { progress: 0.5 }), Some(false));
+        assert_eq!(dark_at(Phase::Static), None);
+    }
+}
@@ -0,0 +1,397 @@
+//! Media playback detection through the compositor's idle inhibitors, for `[media]`.
+//!
+//! Video players hold an idle inhibitor while they play, and Wayland doesn't let other
+//! clients list inhibitors. What ext-idle-notify-v1 version 2 offers is two kinds of idle
+//! notification: one that respects inhibitors and one that only watches input. When the
+//! input notification fires after `detect_after` seconds but the regular one doesn't,
+//! something is keeping the session awake while nobody touches it, which is taken as media
+//! playing. The monitor records that here for [`RuntimeState`] to read and wakes Core with
+//! `SignalMessage::MediaPlaybackChanged`.
+//!
+//! Touching the input can't tell whether playback went on, so playback is assumed to
+//! continue until input stays active for longer than `detect_after`. A quick nudge of the
+//! mouse mid-film doesn't bring the night gamma back.
+//!
+//! [`RuntimeState`]: crate::core::runtime_state::RuntimeState
+
+use anyhow::{Context, Result};
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
+use wayland_client::{
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle, delegate_noop,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::{wl_registry::WlRegistry, wl_seat::WlSeat},
+};
+use wayland_protocols::ext::idle_notify::v1::client::{
+    ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+    ext_idle_notifier_v1::ExtIdleNotifierV1,
+};
+
+use crate::io::signals::SignalMessage;
+
+/// How long the two notifications may disagree before it counts. Both fire after the same
+/// timeout, but the compositor may not send them together.
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// Longest the monitor sleeps without events, so a reloaded `detect_after` is picked up.
+const SETTINGS_RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+static MEDIA_PLAYING: AtomicBool = AtomicBool::new(false);
+static MONITOR_STARTED: AtomicBool = AtomicBool::new(false);
+static DETECT_AFTER_SECS: AtomicU64 = AtomicU64::new(0);
+
+/// Whether media looks like it's playing. Always false when the monitor isn't running.
+pub fn media_playing() -> bool {
+    MEDIA_PLAYING.load(Ordering::Relaxed)
+}
+
+/// Watch the compositor's idle notifications for media playback.
+///
+/// Returns immediately. Calling this again once the monitor runs only updates
+/// `detect_after_secs`, which the monitor picks up within half a minute. Without a
+/// compositor offering ext-idle-notify-v1 version 2 it warns and `[media]` does nothing.
+pub fn start_media_monitor(
+    signal_sender: Sender<SignalMessage>,
+    detect_after_secs: u64,
+    debug_enabled: bool,
+) {
+    DETECT_AFTER_SECS.store(detect_after_secs, Ordering::Relaxed);
+    if MONITOR_STARTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let (connection, queue, globals) = match connect() {
+        Ok(connected) => connected,
+        Err(e) => {
+            log_pipe!();
+            log_warning!("Media playback detection unavailable: {e:#}");
+            log_indented!("It needs a compositor with ext-idle-notify-v1 version 2");
+            MONITOR_STARTED.store(false, Ordering::Relaxed);
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        if let Err(e) = watch(&connection, queue, &globals, &signal_sender, debug_enabled) {
+            log_pipe!();
+            log_warning!("Stopped detecting media playback: {e:#}");
+        }
+        MEDIA_PLAYING.store(false, Ordering::Relaxed);
+        MONITOR_STARTED.store(false, Ordering::Relaxed);
+    });
+}
+
+/// The seat and idle notifier the monitor needs.
+struct Globals {
+    seat: WlSeat,
+    notifier: ExtIdleNotifierV1,
+}
+
+fn connect() -> Result<(Connection, EventQueue<Monitor>, Globals)> {
+    let connection = Connection::connect_to_env().context("No Wayland display")?;
+    let (globals, queue) = registry_queue_init::<Monitor>(&connection)
+        .context("Failed to read the Wayland registry")?;
+    let qh = queue.handle();
+    let seat = globals
+        .bind::<WlSeat, _, _>(&qh, 1..=1, ())
+        .context("The compositor has no seat")?;
+    // Version 2 added the input-only notification that detection relies on
+    let notifier = globals
+        .bind::<ExtIdleNotifierV1, _, _>(&qh, 2..=2, ())
+        .context("The compositor has no ext-idle-notify-v1 version 2")?;
+    Ok((connection, queue, Globals { seat, notifier }))
+}
+
+fn watch(
+    connection: &Connection,
+    mut queue: EventQueue<Monitor>,
+    globals: &Globals,
+    signal_sender: &Sender<SignalMessage>,
+    debug_enabled: bool,
+) -> Result<()> {
+    let qh = queue.handle();
+    let mut detect_after_secs = DETECT_AFTER_SECS.load(Ordering::Relaxed);
+    let mut monitor = Monitor {
+        detector: Detector::new(Duration::from_secs(detect_after_secs)),
+    };
+    let mut notifications = Notifications::create(globals, detect_after_secs, &qh);
+
+    loop {
+        let configured = DETECT_AFTER_SECS.load(Ordering::Relaxed);
+        if configured != detect_after_secs {
+            detect_after_secs = configured;
+            notifications.destroy();
+            notifications = Notifications::create(globals, detect_after_secs, &qh);
+            monitor.detector = Detector::new(Duration::from_secs(detect_after_secs));
+        }
+
+        connection
+            .flush()
+            .context("Failed to flush the Wayland connection")?;
+        if let Some(guard) = queue.prepare_read() {
+            let timeout = monitor
+                .detector
+                .next_deadline()
+                .map_or(SETTINGS_RECHECK_INTERVAL, |deadline| {
+                    deadline.saturating_duration_since(Instant::now())
+                })
+                .min(SETTINGS_RECHECK_INTERVAL);
+            let mut fds = [PollFd::new(guard.connection_fd(), PollFlags::POLLIN)];
+            match poll(
+                &mut fds,
+                PollTimeout::try_from(timeout).unwrap_or(PollTimeout::MAX),
+            ) {
+                Ok(0) | Err(nix::errno::Errno::EINTR) => drop(guard),
+                Ok(_) => {
+                    guard.read().context("Failed to read Wayland events")?;
+                }
+                Err(e) => return Err(e).context("Failed to wait for Wayland events"),
+            }
+        }
+        queue
+            .dispatch_pending(&mut monitor)
+            .context("Failed to dispatch Wayland events")?;
+
+        // Compared with what was reported rather than the detector's own state, which
+        // starts over when `detect_after` changes
+        monitor.detector.poll(Instant::now());
+        let playing = monitor.detector.playing;
+        if MEDIA_PLAYING.swap(playing, Ordering::Relaxed) != playing {
+            if debug_enabled {
+                log_pipe!();
+                log_debug!(
+                    "Media playback {}",
+                    if playing { "detected" } else { "ended" }
+                );
+            }
+            if signal_sender
+                .send(SignalMessage::MediaPlaybackChanged)
+                .is_err()
+            {
+                // The main loop is gone
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Which of the two idle notifications an event comes from.
+#[derive(Debug, Clone, Copy)]
+enum Notification {
+    /// Respects idle inhibitors.
+    Session,
+    /// Only watches input.
+    Input,
+}
+
+struct Notifications {
+    session: ExtIdleNotificationV1,
+    input: ExtIdleNotificationV1,
+}
+
+impl Notifications {
+    fn create(globals: &Globals, detect_after_secs: u64, qh: &QueueHandle<Monitor>) -> Self {
+        let timeout_ms = u32::try_from(detect_after_secs * 1000).unwrap_or(u32::MAX);
+        Self {
+            session: globals.notifier.get_idle_notification(
+                timeout_ms,
+                &globals.seat,
+                qh,
+                Notification::Session,
+            ),
+            input: globals.notifier.get_input_idle_notification(
+                timeout_ms,
+                &globals.seat,
+                qh,
+                Notification::Input,
+            ),
+        }
+    }
+
+    fn destroy(&self) {
+        self.session.destroy();
+        self.input.destroy();
+    }
+}
+
+struct Monitor {
+    detector: Detector,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for Monitor {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtIdleNotificationV1, Notification> for Monitor {
+    fn event(
+        state: &mut Self,
+        _: &ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        notification: &Notification,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let now = Instant::now();
+        let detector = &mut state.detector;
+        match (notification, event) {
+            (Notification::Session, ext_idle_notification_v1::Event::Idled) => detector.idled(now),
+            (Notification::Session, ext_idle_notification_v1::Event::Resumed) => {
+                detector.resumed(now)
+            }
+            (Notification::Input, ext_idle_notification_v1::Event::Idled) => {
+                detector.input_idled(now)
+            }
+            (Notification::Input, ext_idle_notification_v1::Event::Resumed) => {
+                detector.input_resumed(now)
+            }
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(Monitor: ignore WlSeat);
+delegate_noop!(Monitor: ExtIdleNotifierV1);
+
+/// Decides on playback from the two notifications, kept apart from Wayland for testing.
+#[derive(Debug)]
+struct Detector {
+    detect_after: Duration,
+    /// The input-only notification has fired.
+    input_idle: bool,
+    /// The notification respecting inhibitors has fired.
+    idle: bool,
+    playing: bool,
+    /// When to compare the two notifications, once both had time to arrive.
+    decide_at: Option<Instant>,
+    /// When to give up on playback after input came back and stayed.
+    expire_at: Option<Instant>,
+}
+
+impl Detector {
+    fn new(detect_after: Duration) -> Self {
+        Self {
+            detect_after,
+            input_idle: false,
+            idle: false,
+            playing: false,
+            decide_at: None,
+            expire_at: None,
+        }
+    }
+
+    fn input_idled(&mut self, now: Instant) {
+        self.input_idle = true;
+        self.decide_at = Some(now + SETTLE_TIME);
+        self.expire_at = None;
+    }
+
+    fn input_resumed(&mut self, now: Instant) {
+        self.input_idle = false;
+        self.decide_at = None;
+        if self.playing {
+            // Input going idle again before this keeps playback going
+            self.expire_at = Some(now + self.detect_after + SETTLE_TIME);
+        }
+    }
+
+    /// Nothing inhibits idling any more, so whatever played has stopped.
+    fn idled(&mut self, now: Instant) {
+        self.idle = true;
+        self.decide_at = Some(now);
+        self.expire_at = None;
+    }
+
+    /// The session woke while input may still be idle, as when a player starts while
+    /// nobody is at the keyboard.
+    fn resumed(&mut self, now: Instant) {
+        self.idle = false;
+        if self.input_idle {
+            self.decide_at = Some(now + SETTLE_TIME);
+        }
+    }
+
+    /// Settle any decision due by `now`, returning the new playback state if it changed.
+    fn poll(&mut self, now: Instant) -> Option<bool> {
+        let was_playing = self.playing;
+        if self.decide_at.is_some_and(|at| at <= now) {
+            self.decide_at = None;
+            self.playing = self.input_idle && !self.idle;
+        }
+        if self.expire_at.is_some_and(|at| at <= now) {
+            self.expire_at = None;
+            self.playing = false;
+        }
+        (self.playing != was_playing).then_some(self.playing)
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        self.decide_at.into_iter().chain(self.expire_at).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_inhibited_session_with_idle_input_is_playing_media() {
+        let start = Instant::now();
+        let detect_after = Duration::from_secs(60);
+        let mut detector = Detector::new(detect_after);
+
+        // Only the input notification fires: something inhibits idling
+        detector.input_idled(start);
+        assert_eq!(detector.poll(start), None);
+        assert_eq!(detector.next_deadline(), Some(start + SETTLE_TIME));
+        assert_eq!(detector.poll(start + SETTLE_TIME), Some(true));
+
+        // A nudge of the mouse mid-film keeps playback going
+        let nudge = start + Duration::from_secs(600);
+        detector.input_resumed(nudge);
+        detector.input_idled(nudge + detect_after);
+        assert_eq!(detector.poll(nudge + detect_after + SETTLE_TIME), None);
+        assert!(detector.playing);
+
+        // Staying at the keyboard ends it
+        let back = nudge + Duration::from_secs(600);
+        detector.input_resumed(back);
+        assert_eq!(detector.poll(back + detect_after), None);
+        assert_eq!(
+            detector.poll(back + detect_after + SETTLE_TIME),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn an_idle_session_is_not_playing_media() {
+        let start = Instant::now();
+        let mut detector = Detector::new(Duration::from_secs(60));
+
+        // Both notifications fire, a moment apart
+        detector.input_idled(start);
+        detector.idled(start);
+        assert_eq!(detector.poll(start + SETTLE_TIME), None);
+        assert_eq!(detector.next_deadline(), None);
+
+        // A player starting while nobody is around wakes only the session
+        let later = start + Duration::from_secs(300);
+        detector.resumed(later);
+        assert_eq!(detector.poll(later + SETTLE_TIME), Some(true));
+
+        // Playback stopping lets the session go idle again
+        let stopped = later + Duration::from_secs(900);
+        detector.idled(stopped);
+        assert_eq!(detector.poll(stopped), Some(false));
+    }
+}
@@ -200,6 +200,51 @@ pub fn send_instant_shutdown_signal(pid: u32) -> Result<()> {
         .map_err(|e| anyhow::anyhow!("Failed to send instant shutdown signal: {}", e))
 }
 
+/// Stop the instance for `sunsetr restart`, which lets its IPC clients know to wait for
+/// the replacement instead of giving up.
+pub fn send_restart_signal(pid: u32, instant: bool) -> Result<()> {
+    use nix::sys::signal::{Signal, kill};
+    use nix::unistd::Pid;
+
+    let shutdown_file_path = format!("/tmp/sunsetr-shutdown-{}.tmp", pid);
+    let flags = if instant {
+        "instant\nrestart\n"
+    } else {
+        "restart\n"
+    };
+    std::fs::write(&shutdown_file_path, flags).context("Failed to write restart flag")?;
+
+    kill(Pid::from_raw(pid as i32), Signal::SIGTERM)
+        .map_err(|e| anyhow::anyhow!("Failed to send restart signal: {}", e))
+}
+
+/// Start sunsetr as our own child, for compositors that offer no way to spawn it.
+///
+/// The child gets a session of its own, so closing the terminal it was started from
+/// doesn't hang it up once we have exited.
+fn spawn_directly(sunsetr_path: &str) -> Result<()> {
+    use std::os::unix::process::CommandExt;
+
+    let mut cmd = std::process::Command::new(sunsetr_path);
+    if let Some(config_dir) = crate::config::get_custom_config_dir() {
+        cmd.args(["--config", &config_dir.display().to_string()]);
+    }
+    cmd.stdin(std::process::Stdio::null());
+    // SAFETY: setsid is async-signal-safe and touches no memory of the parent
+    unsafe {
+        cmd.pre_exec(|| {
+            nix::unistd::setsid()?;
+            Ok(())
+        });
+    }
+    let _child = cmd
+        .spawn()
+        .context("Failed to spawn sunsetr process directly")?;
+
+    log_decorated!("Background process started (direct spawn).");
+    Ok(())
+}
+
 /// Spawn a background instance via the compositor's own spawn command, so it is
 /// parented to the compositor and survives the launching process exiting.
 pub fn spawn_background_instance(debug_enabled: bool) -> Result<()> {
@@ -311,21 +356,43 @@ pub fn spawn_background_instance(debug_enabled: bool) -> Result<()> {
 
             log_decorated!("Background process started.");
         }
-        Compositor::Other(name) => {
-            log_pipe!();
-            log_warning!("Unknown compositor '{}' detected", name);
-            log_indented!("Starting sunsetr directly (may not have proper parent relationship)");
+        Compositor::River => {
+            log_block_start!("Starting sunsetr via river compositor...");
 
-            let _child = if let Some(config_dir) = crate::config::get_custom_config_dir() {
-                std::process::Command::new(&*sunsetr_path)
-                    .args(["--config", &config_dir.display().to_string()])
-                    .spawn()
+            // riverctl hands the command to `sh -c`
+            let spawn_cmd = if let Some(config_dir) = crate::config::get_custom_config_dir() {
+                format!("'{}' --config '{}'", sunsetr_path, config_dir.display())
             } else {
-                std::process::Command::new(&*sunsetr_path).spawn()
+                format!("'{}'", sunsetr_path)
+            };
+
+            #[cfg(debug_assertions)]
+            eprintln!(
+                "DEBUG: About to spawn via river: riverctl spawn {}",
+                spawn_cmd
+            );
+
+            let output = std::process::Command::new("riverctl")
+                .args(["spawn", &spawn_cmd])
+                .output()
+                .context("Failed to execute riverctl command")?;
+
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!("riverctl spawn command failed: {}", stderr);
             }
-            .context("Failed to spawn sunsetr process directly")?;
 
-            log_decorated!("Background process started (direct spawn).");
+            log_decorated!("Background process started.");
+        }
+        Compositor::Labwc => {
+            log_block_start!("Starting sunsetr directly (labwc has no spawn command)...");
+            spawn_directly(&sunsetr_path)?;
+        }
+        Compositor::Other(name) => {
+            log_pipe!();
+            log_warning!("Unknown compositor '{}' detected", name);
+            log_indented!("Starting sunsetr directly (may not have proper parent relationship)");
+            spawn_directly(&sunsetr_path)?;
         }
     }
 
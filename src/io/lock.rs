@@ -74,13 +74,13 @@ const TEST_LOCK_FILENAME: &str = "sunsetr-test.lock";
 pub fn get_main_lock_path() -> PathBuf {
     let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
         .unwrap_or_else(|_| format!("/run/user/{}", nix::unistd::getuid()));
-    PathBuf::from(runtime_dir).join(MAIN_LOCK_FILENAME)
+    PathBuf::from(runtime_dir).join(super::nested::scoped_file_name(MAIN_LOCK_FILENAME))
 }
 
 pub fn get_test_lock_path() -> PathBuf {
     let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
         .unwrap_or_else(|_| format!("/run/user/{}", nix::unistd::getuid()));
-    PathBuf::from(runtime_dir).join(TEST_LOCK_FILENAME)
+    PathBuf::from(runtime_dir).join(super::nested::scoped_file_name(TEST_LOCK_FILENAME))
 }
 
 /// Lock file path for a specific config file.
@@ -1,4 +1,10 @@
+pub mod crash;
+pub mod darkman;
 pub mod dbus;
+pub mod idle;
 pub mod instance;
 pub mod lock;
+pub mod nested;
+pub mod power;
+pub mod readiness;
 pub mod signals;
@@ -0,0 +1,103 @@
+//! Detection of a compositor nested inside another, such as a Hyprland window on a
+//! Hyprland desktop while developing it.
+//!
+//! Both sessions share `$XDG_RUNTIME_DIR`, so their instances would contend for one lock
+//! file and one IPC socket, and commands typed in the nested session would reach the outer
+//! instance. When nested, runtime file names carry the nested `WAYLAND_DISPLAY`, giving
+//! each session its own instance that drives only its own compositor's outputs.
+
+use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The display of the outer compositor this session's compositor runs inside, or `None`
+/// for a top-level session. Detected once per process.
+pub fn parent_display() -> Option<&'static str> {
+    static PARENT: OnceLock<Option<String>> = OnceLock::new();
+    PARENT.get_or_init(detect_parent_display).as_deref()
+}
+
+/// `file_name` for a file in `$XDG_RUNTIME_DIR`, with the nested display inserted before
+/// the extension when nested, e.g. `sunsetr-wayland-1.lock`.
+pub fn scoped_file_name(file_name: &str) -> String {
+    match parent_display() {
+        Some(_) => with_display(file_name, std::env::var("WAYLAND_DISPLAY").ok().as_deref()),
+        None => file_name.to_string(),
+    }
+}
+
+fn with_display(file_name: &str, display: Option<&str>) -> String {
+    // An absolute WAYLAND_DISPLAY names the socket itself
+    let Some(display) = display
+        .and_then(|display| Path::new(display).file_name())
+        .map(|name| name.to_string_lossy())
+    else {
+        return file_name.to_string();
+    };
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) => format!("{stem}-{display}.{extension}"),
+        None => format!("{file_name}-{display}"),
+    }
+}
+
+/// The compositor is the peer of a connection to `$WAYLAND_DISPLAY`. A nested one was
+/// itself started as a client of another display, which its initial environment names.
+fn detect_parent_display() -> Option<String> {
+    let display = std::env::var("WAYLAND_DISPLAY").ok()?;
+    let socket = if Path::new(&display).is_absolute() {
+        PathBuf::from(&display)
+    } else {
+        let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+            .unwrap_or_else(|_| format!("/run/user/{}", nix::unistd::getuid()));
+        Path::new(&runtime_dir).join(&display)
+    };
+    let stream = UnixStream::connect(socket).ok()?;
+    let compositor = getsockopt(&stream, PeerCredentials).ok()?.pid();
+    let environ = std::fs::read(format!("/proc/{compositor}/environ")).ok()?;
+    parent_in_environ(&environ, &display)
+}
+
+/// The `WAYLAND_DISPLAY` in a NUL-separated environment block, when it names a display
+/// other than `display`.
+fn parent_in_environ(environ: &[u8], display: &str) -> Option<String> {
+    environ
+        .split(|byte| *byte == 0)
+        .find_map(|entry| entry.strip_prefix(b"WAYLAND_DISPLAY="))
+        .map(|parent| String::from_utf8_lossy(parent).into_owned())
+        .filter(|parent| !parent.is_empty() && parent != display)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compositor_started_inside_another_display_is_nested() {
+        let nested = b"HOME=/home/user\0WAYLAND_DISPLAY=wayland-0\0XDG_SESSION_TYPE=wayland\0";
+        assert_eq!(
+            parent_in_environ(nested, "wayland-1"),
+            Some("wayland-0".to_string())
+        );
+
+        let top_level = b"HOME=/home/user\0XDG_SESSION_TYPE=tty\0";
+        assert_eq!(parent_in_environ(top_level, "wayland-0"), None);
+
+        // A session manager that exported the compositor's own display beforehand
+        let exported = b"WAYLAND_DISPLAY=wayland-1\0";
+        assert_eq!(parent_in_environ(exported, "wayland-1"), None);
+    }
+
+    #[test]
+    fn nested_file_names_carry_the_display() {
+        assert_eq!(
+            with_display("sunsetr.lock", Some("wayland-1")),
+            "sunsetr-wayland-1.lock"
+        );
+        assert_eq!(
+            with_display("sunsetr-events.sock", Some("/run/user/1000/wayland-nested")),
+            "sunsetr-events-wayland-nested.sock"
+        );
+        assert_eq!(with_display("sunsetr.lock", None), "sunsetr.lock");
+    }
+}
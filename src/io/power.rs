@@ -0,0 +1,115 @@
+//! Power profile monitoring for `power_saving = "auto"`.
+//!
+//! Watches power-profiles-daemon's `ActiveProfile` over the system D-Bus and records
+//! whether the `power-saver` profile is selected. [`RuntimeState`] reads the flag when it
+//! picks the update interval and decides on smoothing, so a change takes effect at the
+//! next update. Core is woken with `SignalMessage::PowerProfileChanged` to apply it now.
+//!
+//! [`RuntimeState`]: crate::core::runtime_state::RuntimeState
+
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use zbus::blocking::Connection;
+
+use crate::io::signals::SignalMessage;
+
+/// The profile power-profiles-daemon selects for battery saving.
+const POWER_SAVER_PROFILE: &str = "power-saver";
+
+/// The bus name used by power-profiles-daemon before 0.20, which newer releases still
+/// provide for compatibility.
+const LEGACY_SERVICE: &str = "net.hadess.PowerProfiles";
+const LEGACY_PATH: &str = "/net/hadess/PowerProfiles";
+
+static POWER_SAVER_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UPower.PowerProfiles",
+    default_service = "org.freedesktop.UPower.PowerProfiles",
+    default_path = "/org/freedesktop/UPower/PowerProfiles"
+)]
+trait PowerProfiles {
+    /// `power-saver`, `balanced` or `performance`.
+    #[zbus(property)]
+    fn active_profile(&self) -> zbus::Result<String>;
+}
+
+/// Whether the system is on the power-saver profile. Always false when
+/// power-profiles-daemon is unavailable or the monitor isn't running.
+pub fn power_saver_active() -> bool {
+    POWER_SAVER_ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Spawn the power profile monitor thread.
+///
+/// Returns immediately. Without power-profiles-daemon the thread exits quietly and power
+/// saving only follows `power_saving = "on"`.
+pub fn start_power_profile_monitor(signal_sender: Sender<SignalMessage>, debug_enabled: bool) {
+    thread::spawn(move || {
+        if let Err(e) = monitor_power_profile(signal_sender, debug_enabled)
+            && debug_enabled
+        {
+            log_pipe!();
+            log_debug!("Power profile monitoring unavailable: {e:#}");
+        }
+    });
+}
+
+fn monitor_power_profile(signal_sender: Sender<SignalMessage>, debug_enabled: bool) -> Result<()> {
+    let connection = Connection::system().context("Failed to connect to system D-Bus")?;
+    let proxy = connect_proxy(&connection)?;
+
+    let profile = proxy
+        .active_profile()
+        .context("Failed to read the active power profile")?;
+    if debug_enabled {
+        log_pipe!();
+        log_debug!("Power profile: {profile}");
+    }
+    record_profile(&profile, &signal_sender);
+
+    for change in proxy.receive_active_profile_changed() {
+        let profile = change
+            .get()
+            .context("Failed to read the changed power profile")?;
+        if debug_enabled {
+            log_pipe!();
+            log_debug!("Power profile changed to {profile}");
+        }
+        if !record_profile(&profile, &signal_sender) {
+            // The main loop is gone
+            return Ok(());
+        }
+    }
+    anyhow::bail!("power profile change stream ended")
+}
+
+/// Connect to power-profiles-daemon under its current bus name, or the legacy one.
+fn connect_proxy(connection: &Connection) -> Result<PowerProfilesProxyBlocking<'static>> {
+    let proxy = PowerProfilesProxyBlocking::new(connection)
+        .context("Failed to create power profiles proxy")?;
+    if proxy.active_profile().is_ok() {
+        return Ok(proxy);
+    }
+
+    PowerProfilesProxyBlocking::builder(connection)
+        .destination(LEGACY_SERVICE)?
+        .path(LEGACY_PATH)?
+        .interface(LEGACY_SERVICE)?
+        .build()
+        .context("Failed to create legacy power profiles proxy")
+}
+
+/// Record `profile` and wake the main loop when power saving flipped. Returns false once
+/// the main loop stopped listening.
+fn record_profile(profile: &str, signal_sender: &Sender<SignalMessage>) -> bool {
+    let saving = profile == POWER_SAVER_PROFILE;
+    if POWER_SAVER_ACTIVE.swap(saving, Ordering::Relaxed) == saving {
+        return true;
+    }
+    signal_sender
+        .send(SignalMessage::PowerProfileChanged)
+        .is_ok()
+}
@@ -0,0 +1,211 @@
+//! Startup handshake between `sunsetr --background` and the instance it starts.
+//!
+//! The background instance is started through the compositor, so it is not a child of
+//! the command that asked for it and can't inherit a pipe from it. That command listens on
+//! a socket in `$XDG_RUNTIME_DIR` instead, before starting the instance. The instance
+//! connects as soon as it runs and later reports either that its first values are applied
+//! or the error it stopped with. Hanging up without a report means it exited some other
+//! way, such as a panic.
+
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::common::error::Silent;
+
+const SOCKET_FILENAME: &str = "sunsetr-ready.sock";
+
+/// How long the compositor gets to start the instance.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the instance gets to apply its first values, which can include the longest
+/// smooth startup.
+pub const READY_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Connection to a waiting `sunsetr --background`, until the instance has reported.
+static REPORTER: Mutex<Option<UnixStream>> = Mutex::new(None);
+
+/// How starting a background instance went.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// Running with its first values applied, under this PID.
+    Ready(u32),
+    /// Stopped during startup with this error.
+    Failed(String),
+    /// Exited during startup without reporting why.
+    Exited,
+    /// Never connected, so it most likely didn't start.
+    NotStarted,
+    /// Connected, but hadn't finished starting when the wait ended.
+    StillStarting,
+}
+
+/// The listening side, held by `sunsetr --background` while the instance starts.
+pub struct Waiter {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl Waiter {
+    /// Listen for the instance about to be started.
+    pub fn listen() -> Result<Self> {
+        Self::listen_at(socket_path())
+    }
+
+    fn listen_at(path: PathBuf) -> Result<Self> {
+        // Left behind by a waiter that was killed
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to listen on {}", path.display()))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener, path })
+    }
+
+    /// Wait for the instance to connect and report.
+    pub fn wait(&self) -> Outcome {
+        self.wait_for(CONNECT_TIMEOUT, READY_TIMEOUT)
+    }
+
+    fn wait_for(&self, connect_timeout: Duration, ready_timeout: Duration) -> Outcome {
+        let deadline = Instant::now() + connect_timeout;
+        let stream = loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock && Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Err(_) => return Outcome::NotStarted,
+            }
+        };
+
+        if stream.set_nonblocking(false).is_err()
+            || stream.set_read_timeout(Some(ready_timeout)).is_err()
+        {
+            return Outcome::StillStarting;
+        }
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        match reader.read_line(&mut status) {
+            Ok(0) => Outcome::Exited,
+            Ok(_) => match status.trim_end().split_once(' ') {
+                Some(("ready", pid)) => pid.parse().map_or(Outcome::Exited, Outcome::Ready),
+                _ if status.trim_end() == "error" => {
+                    let mut message = String::new();
+                    let _ = reader.read_to_string(&mut message);
+                    Outcome::Failed(message.trim_end().to_string())
+                }
+                _ => Outcome::Exited,
+            },
+            Err(_) => Outcome::StillStarting,
+        }
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Connect to a `sunsetr --background` waiting for this instance, if there is one.
+pub fn connect() {
+    if let Ok(stream) = UnixStream::connect(socket_path()) {
+        *REPORTER.lock().unwrap() = Some(stream);
+    }
+}
+
+/// Tell the waiting command that the first values are applied.
+pub fn report_ready() {
+    report(&ready_message());
+}
+
+/// Tell the waiting command why startup failed.
+pub fn report_failure(error: &anyhow::Error) {
+    report(&failure_message(error));
+}
+
+fn ready_message() -> String {
+    format!("ready {}\n", std::process::id())
+}
+
+fn failure_message(error: &anyhow::Error) -> String {
+    let message = if error.is::<Silent>() {
+        // Already logged by the instance, whose output the compositor keeps
+        "startup failed, see sunsetr's log".to_string()
+    } else {
+        crate::common::error::format_chain(error)
+    };
+    format!("error\n{message}\n")
+}
+
+fn report(message: &str) {
+    if let Some(mut stream) = REPORTER.lock().unwrap().take() {
+        let _ = stream.write_all(message.as_bytes());
+    }
+}
+
+/// Whether systemd runs this process as a service, which leaves nothing to background.
+pub fn under_systemd() -> bool {
+    std::env::var_os("INVOCATION_ID").is_some()
+}
+
+fn socket_path() -> PathBuf {
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+        .unwrap_or_else(|_| format!("/run/user/{}", nix::unistd::getuid()));
+    PathBuf::from(runtime_dir).join(super::nested::scoped_file_name(SOCKET_FILENAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORT: Duration = Duration::from_millis(200);
+
+    fn waiter() -> (tempfile::TempDir, Waiter) {
+        let dir = tempfile::tempdir().unwrap();
+        let waiter = Waiter::listen_at(dir.path().join(SOCKET_FILENAME)).unwrap();
+        (dir, waiter)
+    }
+
+    fn send(waiter: &Waiter, message: &str) {
+        let mut stream = UnixStream::connect(&waiter.path).unwrap();
+        stream.write_all(message.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn reports_reach_the_waiting_command() {
+        let (_dir, waiter) = waiter();
+        send(&waiter, &ready_message());
+        assert_eq!(
+            waiter.wait_for(SHORT, SHORT),
+            Outcome::Ready(std::process::id())
+        );
+
+        let error = anyhow::anyhow!("no gamma protocol").context("Failed to start");
+        send(&waiter, &failure_message(&error));
+        assert_eq!(
+            waiter.wait_for(SHORT, SHORT),
+            Outcome::Failed("Failed to start:\n  no gamma protocol".to_string())
+        );
+    }
+
+    #[test]
+    fn silence_is_told_apart() {
+        let (_dir, waiter) = waiter();
+        assert_eq!(waiter.wait_for(SHORT, SHORT), Outcome::NotStarted);
+
+        // Connected, then exited without a report
+        drop(UnixStream::connect(&waiter.path).unwrap());
+        assert_eq!(waiter.wait_for(SHORT, SHORT), Outcome::Exited);
+
+        let _starting = UnixStream::connect(&waiter.path).unwrap();
+        assert_eq!(waiter.wait_for(SHORT, SHORT), Outcome::StillStarting);
+
+        let path = waiter.path.clone();
+        drop(waiter);
+        assert!(!path.exists());
+    }
+}
@@ -14,19 +14,47 @@ use std::{
     thread,
 };
 
+use crate::common::units::{GammaPercent, Kelvin};
+
 #[derive(Debug, Clone)]
 pub struct TestModeParams {
-    pub temperature: u32,
-    pub gamma: f64,
+    pub temperature: Kelvin,
+    pub gamma: GammaPercent,
 }
 
 #[derive(Debug)]
 pub enum SignalMessage {
     Reload(Box<crate::config::Config>),
-    TestMode(TestModeParams),
+    /// Values for `sunsetr test` to show, or `None` to end the test.
+    TestMode(Option<TestModeParams>),
+    /// A live preview request from an IPC client.
+    Preview(crate::core::preview::PreviewRequest),
+    /// Targets pushed by an external scheduler over IPC.
+    ExternalTargets {
+        temp: Kelvin,
+        gamma: GammaPercent,
+    },
     Shutdown,
     TimeChange,
     ResumeFromSleep,
+    /// The system entered or left the power-saver profile.
+    PowerProfileChanged,
+    /// darkman switched between light and dark mode.
+    DarkModeChanged,
+    /// Media playback started or stopped, as seen through idle inhibitors.
+    MediaPlaybackChanged,
+    /// Rebuild the backend's per-output state and apply the current values again.
+    Refresh,
+    /// Override `reduce_motion` over IPC, or go back to the config's value with `None`.
+    ReduceMotion(Option<bool>),
+    /// Hold every output neutral for IPC clients taking a screenshot, or stop.
+    NeutralHold(bool),
+    /// Pause or resume color shifting, flipping it when `paused` is `None`, and resume by
+    /// itself at `until`.
+    Pause {
+        paused: Option<bool>,
+        until: Option<crate::core::pause::PauseUntil>,
+    },
 }
 
 /// Signal handling state shared between threads.
@@ -43,6 +71,9 @@ pub struct SignalState {
     pub interrupt: Arc<AtomicBool>,
     pub in_test_mode: Arc<AtomicBool>,
     pub instant_shutdown: Arc<AtomicBool>,
+    /// Set when the shutdown was requested by `sunsetr restart`, so IPC clients are told
+    /// a replacement is on its way.
+    pub restarting: Arc<AtomicBool>,
     pub current_preset: Arc<std::sync::Mutex<Option<String>>>,
 }
 
@@ -59,9 +90,18 @@ impl SignalState {
                     latest_config = Some(cfg);
                 }
                 msg @ (SignalMessage::TestMode(_)
+                | SignalMessage::Preview(_)
+                | SignalMessage::ExternalTargets { .. }
                 | SignalMessage::Shutdown
                 | SignalMessage::TimeChange
-                | SignalMessage::ResumeFromSleep) => {
+                | SignalMessage::ResumeFromSleep
+                | SignalMessage::PowerProfileChanged
+                | SignalMessage::DarkModeChanged
+                | SignalMessage::MediaPlaybackChanged
+                | SignalMessage::Refresh
+                | SignalMessage::ReduceMotion(_)
+                | SignalMessage::NeutralHold(_)
+                | SignalMessage::Pause { .. }) => {
                     deferred.push(msg);
                 }
             }
@@ -79,6 +119,7 @@ pub fn setup_signal_handler(debug_enabled: bool) -> Result<SignalState> {
     let running = Arc::new(AtomicBool::new(true));
     let in_test_mode = Arc::new(AtomicBool::new(false));
     let instant_shutdown = Arc::new(AtomicBool::new(false));
+    let restarting = Arc::new(AtomicBool::new(false));
     let interrupt = Arc::new(AtomicBool::new(false));
     let (signal_sender, signal_receiver) = std::sync::mpsc::channel::<SignalMessage>();
 
@@ -87,6 +128,7 @@ pub fn setup_signal_handler(debug_enabled: bool) -> Result<SignalState> {
 
     let running_clone = running.clone();
     let instant_shutdown_clone = instant_shutdown.clone();
+    let restarting_clone = restarting.clone();
     let interrupt_clone = interrupt.clone();
     let signal_sender_clone = signal_sender.clone();
 
@@ -126,15 +168,22 @@ pub fn setup_signal_handler(debug_enabled: bool) -> Result<SignalState> {
                                 (lines[0].parse::<u32>(), lines[1].parse::<f64>())
                         {
                             log_pipe!();
-                            if temp == 0 {
+                            // The file can be written by anything, not only `sunsetr test`
+                            let test_params = if temp == 0 {
                                 log_info!("Received test mode exit signal");
+                                None
                             } else {
-                                log_info!("Received test mode signal");
-                            }
-
-                            let test_params = TestModeParams {
-                                temperature: temp,
-                                gamma,
+                                match (Kelvin::new(temp), GammaPercent::new(gamma)) {
+                                    (Ok(temperature), Ok(gamma)) => {
+                                        log_info!("Received test mode signal");
+                                        Some(TestModeParams { temperature, gamma })
+                                    }
+                                    (Err(e), _) | (_, Err(e)) => {
+                                        log_warning!("Ignoring test mode signal: {e}");
+                                        let _ = std::fs::remove_file(&test_file_path);
+                                        continue;
+                                    }
+                                }
                             };
 
                             match signal_sender_clone.send(SignalMessage::TestMode(test_params)) {
@@ -202,12 +251,18 @@ pub fn setup_signal_handler(debug_enabled: bool) -> Result<SignalState> {
                 SIGTERM => {
                     let shutdown_file_path =
                         format!("/tmp/sunsetr-shutdown-{}.tmp", std::process::id());
-                    let is_instant_shutdown = std::fs::read_to_string(&shutdown_file_path)
-                        .map(|content| content.trim() == "instant")
-                        .unwrap_or(false);
+                    // One flag per line: "instant" and/or "restart"
+                    let flags = std::fs::read_to_string(&shutdown_file_path).unwrap_or_default();
+                    let has_flag = |name: &str| flags.lines().any(|flag| flag.trim() == name);
+                    let is_instant_shutdown = has_flag("instant");
 
                     let _ = std::fs::remove_file(&shutdown_file_path);
 
+                    // Set before running is cleared, which is when the IPC server reads it
+                    if has_flag("restart") {
+                        restarting_clone.store(true, Ordering::SeqCst);
+                    }
+
                     if is_instant_shutdown {
                         #[cfg(debug_assertions)]
                         {
@@ -333,6 +388,7 @@ pub fn setup_signal_handler(debug_enabled: bool) -> Result<SignalState> {
         interrupt,
         in_test_mode,
         instant_shutdown,
+        restarting,
         current_preset: Arc::new(std::sync::Mutex::new(initial_preset)),
     })
 }
@@ -351,32 +407,13 @@ mod tests {
             interrupt: Arc::new(AtomicBool::new(false)),
             in_test_mode: Arc::new(AtomicBool::new(false)),
             instant_shutdown: Arc::new(AtomicBool::new(false)),
+            restarting: Arc::new(AtomicBool::new(false)),
             current_preset: Arc::new(std::sync::Mutex::new(None)),
         }
     }
 
     fn config_with_marker(night_temp: u32) -> Config {
-        use crate::common::constants::*;
-        Config {
-            backend: DEFAULT_BACKEND,
-            transition_mode: crate::config::TransitionMode::Geo,
-            smoothing: DEFAULT_SMOOTHING,
-            startup_duration: DEFAULT_STARTUP_DURATION_SEC,
-            shutdown_duration: DEFAULT_SHUTDOWN_DURATION_SEC,
-            adaptive_interval: DEFAULT_ADAPTIVE_INTERVAL_MS,
-            night_temp,
-            day_temp: DEFAULT_DAY_TEMP,
-            night_gamma: DEFAULT_NIGHT_GAMMA,
-            day_gamma: DEFAULT_DAY_GAMMA,
-            update_interval: crate::config::UpdateInterval::Adaptive,
-            static_temp: None,
-            static_gamma: None,
-            sunset: None,
-            sunrise: None,
-            transition_duration: DEFAULT_TRANSITION_DURATION_MIN,
-            latitude: None,
-            longitude: None,
-        }
+        Config::builder().night_temp(night_temp).build()
     }
 
     #[test]
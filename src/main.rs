@@ -25,6 +25,11 @@ mod state;
 mod io;
 mod time;
 
+// Counts allocations for the `--profile` report
+#[cfg(debug_assertions)]
+#[global_allocator]
+static ALLOCATOR: common::profile::CountingAllocator = common::profile::CountingAllocator;
+
 use std::process::ExitCode;
 
 use anyhow::Result;
@@ -35,6 +40,7 @@ use crate::io::instance::restore_config_dir;
 use crate::sunsetr::Sunsetr;
 
 fn main() -> ExitCode {
+    io::crash::install_panic_hook();
     let action = CliAction::from_env();
 
     if let Some(dir) = action.config_dir()
@@ -62,10 +68,7 @@ fn main() -> ExitCode {
 /// Route a parsed CLI action to its handler.
 fn dispatch(action: CliAction) -> Result<()> {
     match action {
-        CliAction::ShowVersion => {
-            args::display_version_info();
-            Ok(())
-        }
+        CliAction::ShowVersion { json } => args::display_version_info(json),
         CliAction::ShowHelp => {
             args::display_help();
             Ok(())
@@ -83,16 +86,37 @@ fn dispatch(action: CliAction) -> Result<()> {
         CliAction::Run {
             debug_enabled,
             background,
+            profile,
+            safe_mode,
+            dry_run,
             ..
-        } => Sunsetr::new(debug_enabled).background(background).run(),
+        } => Sunsetr::new(debug_enabled)
+            .background(background)
+            .profile(profile)
+            .safe_mode(safe_mode)
+            .dry_run(dry_run)
+            .run(),
         CliAction::Simulate {
             debug_enabled,
             start_time,
             end_time,
             pace,
             log_to_file,
+            json,
             ..
-        } => time::simulate::run_simulation(start_time, end_time, pace, debug_enabled, log_to_file),
+        } => {
+            if json {
+                time::simulate::run_json_timeline(start_time, end_time)
+            } else {
+                time::simulate::run_simulation(
+                    start_time,
+                    end_time,
+                    pace,
+                    debug_enabled,
+                    log_to_file,
+                )
+            }
+        }
         CliAction::PresetCommand {
             debug_enabled,
             subcommand,
@@ -111,11 +135,23 @@ fn dispatch(action: CliAction) -> Result<()> {
             ..
         } => commands::restart::handle_restart_command(instant, debug_enabled, background),
         CliAction::StopCommand => commands::stop::handle_stop_command(),
+        CliAction::RefreshCommand => commands::refresh::handle_refresh_command(),
+        CliAction::ToggleCommand => commands::toggle::handle_toggle_command(),
+        CliAction::PauseCommand { expires_in, until } => {
+            commands::pause::handle_pause_command(expires_in, until)
+        }
+        CliAction::PresentationCommand { subcommand, .. } => {
+            commands::presentation::handle_presentation_command(&subcommand)
+        }
+        CliAction::AdjustCommand { subcommand, .. } => {
+            commands::adjust::handle_adjust_command(&subcommand)
+        }
         CliAction::GeoCommand {
             debug_enabled,
             target,
+            provider,
             ..
-        } => commands::geo::handle_geo_command(debug_enabled, target),
+        } => commands::geo::handle_geo_command(debug_enabled, target, provider),
         CliAction::TestCommand {
             debug_enabled,
             temperature,
@@ -124,9 +160,47 @@ fn dispatch(action: CliAction) -> Result<()> {
         CliAction::StatusCommand { json, follow } => {
             commands::status::handle_status_command(json, follow)
         }
-        CliAction::SetCommand { fields, target, .. } => {
-            commands::set::handle_set_command(fields, target.as_deref())
+        CliAction::HistoryCommand { json } => commands::history::handle_history_command(json),
+        CliAction::GrabCommand { command } => commands::grab::handle_grab_command(&command),
+        CliAction::DoctorCommand { json, .. } => commands::doctor::handle_doctor_command(json),
+        CliAction::BugreportCommand { output, .. } => {
+            commands::bugreport::handle_bugreport_command(output)
+        }
+        CliAction::ComputeCommand { at, json, .. } => {
+            commands::compute::handle_compute_command(at, json)
+        }
+        CliAction::CurveCommand { date, svg, .. } => {
+            commands::curve::handle_curve_command(date, svg)
+        }
+        CliAction::WeekCommand { start, ascii, .. } => {
+            commands::week::handle_week_command(start, ascii)
         }
+        CliAction::RenderPreviewCommand {
+            temp,
+            gamma,
+            output,
+            ..
+        } => commands::render_preview::handle_render_preview_command(temp, gamma, &output),
+        CliAction::ConfigCommand { subcommand, .. } => {
+            commands::config::handle_config_command(&subcommand)
+        }
+        CliAction::SetCommand { clear: true, .. } => commands::set::handle_clear_overrides(),
+        CliAction::SetCommand {
+            fields,
+            runtime: true,
+            ..
+        } => commands::set::handle_runtime_set(fields),
+        CliAction::SetCommand {
+            fields,
+            target,
+            create,
+            ..
+        } => commands::set::handle_set_command(fields, target.as_deref(), create),
+        CliAction::GetCommand {
+            json,
+            overrides: true,
+            ..
+        } => commands::get::handle_get_overrides(json),
         CliAction::GetCommand {
             fields,
             target,
@@ -2,66 +2,114 @@
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 
-use crate::core::period::{Period, PeriodType};
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::core::period::{Period, PeriodType, Phase};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DisplayState {
     pub active_preset: String,
+    /// When a `preset --for` switch ends and the earlier presets come back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset_expires_at: Option<DateTime<Local>>,
     pub period: Period,
     #[serde(rename = "state")]
     pub period_type: PeriodType,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub progress: Option<f32>,
-    pub current_temp: u32,
-    pub current_gamma: f64,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub target_temp: Option<u32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub target_gamma: Option<f64>,
+    pub current_temp: Kelvin,
+    pub current_gamma: GammaPercent,
+    /// Present exactly while in a sunset or sunrise.
+    #[serde(flatten)]
+    pub transition: Option<TransitionProgress>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub next_period: Option<DateTime<Local>>,
+    /// When the running process next wakes to update the display. A client that sees
+    /// this pass without a new state_applied event knows the process has stalled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_update: Option<DateTime<Local>>,
+    /// Outputs held neutral by presentation mode, absent while it is off. The temperature
+    /// and gamma above stay the scheduled ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presentation: Option<crate::state::presentation::Presentation>,
+    /// Set while color shifting is paused and every output shows 6500K at 100%. The
+    /// temperature and gamma above stay the scheduled ones.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+    /// When a pause resumes by itself, absent for one lasting until resumed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paused_until: Option<DateTime<Local>>,
+    /// Offsets set with `sunsetr adjust`, absent without one. The temperature and gamma
+    /// above, and a transition's targets, already include them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adjustment: Option<crate::state::value_offset::ValueOffset>,
+}
+
+/// How far a transition has come and the values it is heading for.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TransitionProgress {
+    pub progress: f32,
+    pub target_temp: Kelvin,
+    pub target_gamma: GammaPercent,
+}
+
+impl TransitionProgress {
+    /// The transition `phase` is in, or `None` for day, night and static.
+    pub fn new(phase: Phase, config: &crate::config::Config) -> Option<Self> {
+        let (progress, (target_temp, target_gamma)) = match phase {
+            Phase::Sunset { progress } => (progress, config.night_values()),
+            Phase::Sunrise { progress } => (progress, config.day_values()),
+            Phase::Day | Phase::Night | Phase::Static => return None,
+        };
+        Some(Self {
+            progress,
+            target_temp,
+            target_gamma,
+        })
+    }
 }
 
 impl DisplayState {
     pub fn new(runtime_state: &crate::core::runtime_state::RuntimeState) -> Self {
         let current_state = runtime_state.period();
-        let config = runtime_state.config();
-
-        let (target_temp, target_gamma) = if current_state.is_transitioning() {
-            match current_state {
-                Period::Sunset => (Some(config.night_temp), Some(config.night_gamma)),
-                Period::Sunrise => (Some(config.day_temp), Some(config.day_gamma)),
-                _ => (None, None),
-            }
-        } else {
-            (None, None)
-        };
-
+        let adjustment = runtime_state.value_offset();
+        let transition = TransitionProgress::new(runtime_state.phase(), runtime_state.config())
+            .map(|transition| match adjustment {
+                Some(offset) => {
+                    let (target_temp, target_gamma) =
+                        offset.apply((transition.target_temp, transition.target_gamma));
+                    TransitionProgress {
+                        target_temp,
+                        target_gamma,
+                        ..transition
+                    }
+                }
+                None => transition,
+            });
         let next_period = runtime_state.next_period_start();
 
-        let progress = if current_state.is_transitioning() {
-            runtime_state.progress()
-        } else {
-            None
-        };
-
         let active_preset = crate::state::preset::get_active_preset()
             .ok()
             .flatten()
             .unwrap_or_else(|| "default".to_string());
+        let preset_expires_at = crate::state::preset::get_preset_expiry()
+            .ok()
+            .flatten()
+            .map(|expiry| expiry.expires_at);
 
         let (current_temp, current_gamma) = runtime_state.values();
 
         DisplayState {
             active_preset,
+            preset_expires_at,
             period: current_state,
             period_type: current_state.period_type(),
-            progress,
             current_temp,
             current_gamma,
-            target_temp,
-            target_gamma,
+            transition,
             next_period,
+            next_update: runtime_state.next_update(),
+            presentation: runtime_state.config().presentation.clone(),
+            paused: false,
+            paused_until: None,
+            adjustment,
         }
     }
 }
@@ -74,26 +122,13 @@ mod tests {
     use chrono::TimeZone;
 
     fn create_test_config() -> Config {
-        Config {
-            backend: crate::config::Backend::Auto,
-            smoothing: false,
-            startup_duration: 10.0,
-            shutdown_duration: 10.0,
-            adaptive_interval: crate::common::constants::DEFAULT_ADAPTIVE_INTERVAL_MS,
-            latitude: None,
-            longitude: None,
-            sunset: Some("19:00:00".to_string()),
-            sunrise: Some("06:00:00".to_string()),
-            night_temp: 3300,
-            day_temp: 6500,
-            night_gamma: 90.0,
-            day_gamma: 100.0,
-            static_temp: None,
-            static_gamma: None,
-            transition_duration: 30,
-            update_interval: crate::config::UpdateInterval::Fixed(60),
-            transition_mode: TransitionMode::FinishBy,
-        }
+        Config::builder()
+            .smoothing(false)
+            .startup_duration(10.0)
+            .shutdown_duration(10.0)
+            .transition_duration(30)
+            .update_interval(crate::config::UpdateInterval::Fixed(60))
+            .build()
     }
 
     #[test]
@@ -111,12 +146,10 @@ mod tests {
         let display_state = DisplayState::new(&runtime_state);
 
         assert!(!display_state.period.is_transitioning());
-        assert_eq!(display_state.current_temp, 6500);
-        assert_eq!(display_state.current_gamma, 100.0);
+        assert_eq!(display_state.current_temp.get(), 6500);
+        assert_eq!(display_state.current_gamma.get(), 100.0);
         assert!(display_state.next_period.is_some());
-        assert!(display_state.target_temp.is_none());
-        assert!(display_state.target_gamma.is_none());
-        assert!(display_state.progress.is_none());
+        assert!(display_state.transition.is_none());
     }
 
     #[test]
@@ -149,9 +182,10 @@ mod tests {
         assert_eq!(display_state.period, Period::Sunset);
         assert_eq!(display_state.current_temp, expected_temp);
         assert_eq!(display_state.current_gamma, expected_gamma);
-        assert_eq!(display_state.target_temp, Some(3300));
-        assert_eq!(display_state.target_gamma, Some(90.0));
-        assert!(display_state.progress.is_some());
+        let transition = display_state.transition.unwrap();
+        assert_eq!(transition.target_temp.get(), 3300);
+        assert_eq!(transition.target_gamma.get(), 90.0);
+        assert_eq!(runtime_state.phase().progress(), Some(transition.progress));
     }
 
     #[test]
@@ -173,11 +207,9 @@ mod tests {
         let display_state = DisplayState::new(&runtime_state);
 
         assert!(!display_state.period.is_transitioning());
-        assert_eq!(display_state.current_temp, 5000);
-        assert_eq!(display_state.current_gamma, 85.0);
-        assert!(display_state.target_temp.is_none());
-        assert!(display_state.target_gamma.is_none());
-        assert!(display_state.progress.is_none());
+        assert_eq!(display_state.current_temp.get(), 5000);
+        assert_eq!(display_state.current_gamma.get(), 85.0);
+        assert!(display_state.transition.is_none());
         assert!(display_state.next_period.is_none());
     }
 
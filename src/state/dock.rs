@@ -0,0 +1,304 @@
+//! Switching presets as outputs are docked and undocked.
+//!
+//! A preset whose config sets `activate_when_outputs` becomes the active preset once all of
+//! those outputs are connected, and whatever was active before comes back once they are
+//! not. The running instance only writes the preset state; the config watcher reloads as
+//! it would after `sunsetr preset`. Docking connects and reconfigures outputs in bursts, so
+//! a change has to hold for [`DOCK_SETTLE`] before it counts. A preset switched by hand
+//! while docked is left alone on undocking.
+
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::state::preset::{PresetState, PresetSwitch};
+
+/// How long the connected outputs must stay the same before presets follow them.
+pub const DOCK_SETTLE: Duration = Duration::from_secs(2);
+
+/// A preset and the outputs that activate it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DockRule {
+    pub preset: String,
+    pub outputs: BTreeSet<String>,
+}
+
+/// The rules set by the presets under `config_dir`, by preset name. Presets that don't
+/// load are left out, since switching to them would fail anyway.
+pub fn rules(config_dir: &Path) -> Vec<DockRule> {
+    let Ok(entries) = std::fs::read_dir(config_dir.join("presets")) else {
+        return Vec::new();
+    };
+    let mut rules: Vec<DockRule> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let preset = entry.file_name().to_str()?.to_string();
+            let path = entry.path().join("sunsetr.toml");
+            if !path.exists() {
+                return None;
+            }
+            match Config::load_from_path(&path) {
+                Ok(config) if !config.activate_when_outputs.is_empty() => Some(DockRule {
+                    preset,
+                    outputs: config.activate_when_outputs.into_iter().collect(),
+                }),
+                Ok(_) => None,
+                Err(e) => {
+                    log_pipe!();
+                    log_warning!("Preset '{preset}' can't be docked to: {e}");
+                    None
+                }
+            }
+        })
+        .collect();
+    rules.sort_by(|a, b| a.preset.cmp(&b.preset));
+    rules
+}
+
+/// The preset for the `connected` outputs: of the rules whose outputs are all connected,
+/// the one naming the most, the first by name on a tie.
+pub fn matching<'a>(rules: &'a [DockRule], connected: &BTreeSet<String>) -> Option<&'a str> {
+    rules
+        .iter()
+        .filter(|rule| rule.outputs.is_subset(connected))
+        // max_by_key keeps the last of equals, so walk the rules backwards
+        .rev()
+        .max_by_key(|rule| rule.outputs.len())
+        .map(|rule| rule.preset.as_str())
+}
+
+/// A change to the preset state.
+#[derive(Debug, Clone, PartialEq)]
+enum Switch {
+    Activate(String),
+    Restore(PresetState),
+}
+
+/// A preset activated by docking and what was active before it.
+#[derive(Debug)]
+struct Docked {
+    preset: String,
+    previous: PresetState,
+}
+
+/// Follows the connected outputs and switches presets once they settle.
+#[derive(Debug)]
+pub struct Dock {
+    seen: Option<BTreeSet<String>>,
+    seen_since: Instant,
+    // Whether `seen` has been acted on
+    settled: bool,
+    docked: Option<Docked>,
+}
+
+impl Default for Dock {
+    fn default() -> Self {
+        Self {
+            seen: None,
+            seen_since: Instant::now(),
+            settled: false,
+            docked: None,
+        }
+    }
+}
+
+impl Dock {
+    /// Record the connected outputs, `None` when the backend doesn't know them, and switch
+    /// presets once they have settled.
+    pub fn poll(&mut self, outputs: Option<BTreeSet<String>>) {
+        let Some(connected) = self.settle(outputs, Instant::now()) else {
+            return;
+        };
+        if let Err(e) = self.follow(&connected) {
+            log_pipe!();
+            log_warning!("Failed to switch presets for the connected outputs: {e}");
+        }
+    }
+
+    fn follow(&mut self, connected: &BTreeSet<String>) -> Result<()> {
+        let config_dir = crate::config::loading::get_config_base_dir()?;
+        let rules = rules(&config_dir);
+        // Nothing to follow, and nothing docked that would need undoing
+        if rules.is_empty() && self.docked.is_none() {
+            return Ok(());
+        }
+
+        let current = PresetState::load()?;
+        match self.plan(&rules, connected, &current) {
+            Some(Switch::Activate(preset)) => {
+                log_block_start!("Docked, activating preset '{preset}'");
+                PresetSwitch::begin(Some(&preset), None)?.commit();
+            }
+            Some(Switch::Restore(previous)) => {
+                log_block_start!("Undocked, returning to {}", previous.label());
+                previous.restore()?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// The outputs once they have stayed the same for [`DOCK_SETTLE`], once per change.
+    fn settle(
+        &mut self,
+        outputs: Option<BTreeSet<String>>,
+        now: Instant,
+    ) -> Option<BTreeSet<String>> {
+        let outputs = outputs?;
+        if self.seen.as_ref() != Some(&outputs) {
+            self.seen = Some(outputs);
+            self.seen_since = now;
+            self.settled = false;
+            return None;
+        }
+        if self.settled || now.duration_since(self.seen_since) < DOCK_SETTLE {
+            return None;
+        }
+        self.settled = true;
+        self.seen.clone()
+    }
+
+    /// The switch the `connected` outputs call for, given the `current` presets.
+    fn plan(
+        &mut self,
+        rules: &[DockRule],
+        connected: &BTreeSet<String>,
+        current: &PresetState,
+    ) -> Option<Switch> {
+        // Switched by hand since docking, so undocking has nothing to undo
+        if self
+            .docked
+            .as_ref()
+            .is_some_and(|docked| current.base.as_deref() != Some(docked.preset.as_str()))
+        {
+            self.docked = None;
+        }
+
+        match matching(rules, connected) {
+            Some(preset) if current.base.as_deref() == Some(preset) => None,
+            Some(preset) => {
+                let previous = match self.docked.take() {
+                    Some(docked) => docked.previous,
+                    None => current.clone(),
+                };
+                self.docked = Some(Docked {
+                    preset: preset.to_string(),
+                    previous,
+                });
+                Some(Switch::Activate(preset.to_string()))
+            }
+            None => self
+                .docked
+                .take()
+                .map(|docked| Switch::Restore(docked.previous)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn rule(preset: &str, names: &[&str]) -> DockRule {
+        DockRule {
+            preset: preset.to_string(),
+            outputs: outputs(names),
+        }
+    }
+
+    fn state(base: Option<&str>) -> PresetState {
+        PresetState {
+            base: base.map(str::to_string),
+            stack: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn most_specific_fully_connected_rule_wins() {
+        let rules = [
+            rule("desk", &["DP-3"]),
+            rule("office", &["DP-3", "DP-4"]),
+            rule("projector", &["HDMI-A-1"]),
+        ];
+        assert_eq!(matching(&rules, &outputs(&["eDP-1"])), None);
+        assert_eq!(matching(&rules, &outputs(&["eDP-1", "DP-3"])), Some("desk"));
+        assert_eq!(
+            matching(&rules, &outputs(&["eDP-1", "DP-3", "DP-4"])),
+            Some("office")
+        );
+        assert_eq!(
+            matching(&rules, &outputs(&["DP-3", "HDMI-A-1"])),
+            Some("desk")
+        );
+    }
+
+    #[test]
+    fn outputs_must_settle_before_they_count() {
+        let mut dock = Dock::default();
+        let start = Instant::now();
+        assert_eq!(dock.settle(None, start), None);
+        assert_eq!(dock.settle(Some(outputs(&["eDP-1"])), start), None);
+
+        // A second output arriving restarts the wait
+        let later = start + Duration::from_secs(1);
+        assert_eq!(dock.settle(Some(outputs(&["eDP-1", "DP-3"])), later), None);
+        assert_eq!(
+            dock.settle(Some(outputs(&["eDP-1", "DP-3"])), start + DOCK_SETTLE),
+            None
+        );
+        assert_eq!(
+            dock.settle(Some(outputs(&["eDP-1", "DP-3"])), later + DOCK_SETTLE),
+            Some(outputs(&["eDP-1", "DP-3"]))
+        );
+        // Acted on once, not again on every poll
+        assert_eq!(
+            dock.settle(Some(outputs(&["eDP-1", "DP-3"])), later + DOCK_SETTLE * 2),
+            None
+        );
+    }
+
+    #[test]
+    fn undocking_restores_what_was_active_before() {
+        let rules = [rule("desk", &["DP-3"])];
+        let mut dock = Dock::default();
+        let laptop = outputs(&["eDP-1"]);
+        let docked = outputs(&["eDP-1", "DP-3"]);
+
+        assert_eq!(dock.plan(&rules, &laptop, &state(Some("night"))), None);
+        assert_eq!(
+            dock.plan(&rules, &docked, &state(Some("night"))),
+            Some(Switch::Activate("desk".to_string()))
+        );
+        assert_eq!(dock.plan(&rules, &docked, &state(Some("desk"))), None);
+        assert_eq!(
+            dock.plan(&rules, &laptop, &state(Some("desk"))),
+            Some(Switch::Restore(state(Some("night"))))
+        );
+        assert_eq!(dock.plan(&rules, &laptop, &state(Some("night"))), None);
+    }
+
+    #[test]
+    fn presets_switched_by_hand_are_left_alone() {
+        let rules = [rule("desk", &["DP-3"])];
+        let mut dock = Dock::default();
+        let laptop = outputs(&["eDP-1"]);
+        let docked = outputs(&["eDP-1", "DP-3"]);
+
+        // Already active by hand when docking, so undocking keeps it
+        assert_eq!(dock.plan(&rules, &docked, &state(Some("desk"))), None);
+        assert_eq!(dock.plan(&rules, &laptop, &state(Some("desk"))), None);
+
+        // Replaced by hand while docked
+        assert_eq!(
+            dock.plan(&rules, &docked, &state(None)),
+            Some(Switch::Activate("desk".to_string()))
+        );
+        assert_eq!(dock.plan(&rules, &laptop, &state(Some("movie"))), None);
+    }
+}
@@ -1,14 +1,38 @@
 //! IPC client utilities for connecting to the sunsetr process.
 
 use anyhow::{Context, Result};
-use std::io::{BufRead, BufReader};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
 use std::os::unix::net::UnixStream;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-use super::events::IpcEvent;
+use super::events::{IpcEvent, IpcRequest, PROTOCOL_VERSION};
 use super::server::socket_path;
+use crate::core::history::AppliedEntry;
+use crate::core::pause::PauseUntil;
+use crate::core::period::ChangeReason;
 use crate::state::display::DisplayState;
 
+/// How long to wait for the server to answer a hello.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Protocol spoken by servers that predate the handshake and never answer it.
+const PRE_HANDSHAKE_PROTOCOL_VERSION: u32 = 1;
+
+/// How long to keep trying the socket after a restarting event, covering the old
+/// instance's shutdown transition and the new one's startup.
+pub const RECONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How long to wait for the state applied after a refresh or pause request.
+const STATE_REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for the outputs to be held neutral after a hold_neutral request.
+const HOLD_NEUTRAL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pause between connection attempts while waiting for a restarted instance.
+const RECONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
 /// The IPC connection to the sunsetr process has closed.
 ///
 /// Returned by [`IpcClient::try_receive_event`] so callers can distinguish a
@@ -25,16 +49,60 @@ impl std::fmt::Display for ConnectionClosed {
 impl std::error::Error for ConnectionClosed {}
 
 pub struct IpcClient {
-    #[allow(dead_code)]
     stream: UnixStream,
     reader: BufReader<UnixStream>,
+    // Events read while waiting for the handshake reply, delivered before new reads
+    pending: VecDeque<IpcEvent>,
 }
 
 impl IpcClient {
     pub fn connect() -> Result<Self> {
         let socket_path = socket_path().context("Failed to get IPC socket path")?;
+        Self::connect_to(&socket_path)
+    }
+
+    /// Connect to the running instance to send it a request, reading past the state the
+    /// server replays on connection so it isn't taken for the answer. `None` when sunsetr
+    /// isn't running.
+    pub fn connect_for_request() -> Result<Option<Self>> {
+        let Ok(mut client) = Self::connect() else {
+            return Ok(None);
+        };
+        client
+            .current()
+            .context("Failed to receive current state from sunsetr process")?;
+        Ok(Some(client))
+    }
+
+    /// Connect to the instance replacing one that sent [`IpcEvent::Restarting`], retrying
+    /// until its socket is back or `timeout` passes. Returns `None` if `stop` is raised
+    /// while waiting.
+    pub fn reconnect(timeout: Duration, stop: &AtomicBool) -> Result<Option<Self>> {
+        let socket_path = socket_path().context("Failed to get IPC socket path")?;
+        Self::reconnect_to(&socket_path, timeout, stop)
+    }
 
-        let stream = UnixStream::connect(&socket_path).with_context(|| {
+    pub(super) fn reconnect_to(
+        socket_path: &std::path::Path,
+        timeout: Duration,
+        stop: &AtomicBool,
+    ) -> Result<Option<Self>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if stop.load(Ordering::SeqCst) {
+                return Ok(None);
+            }
+            // The old socket refuses connections until it's removed and bound again
+            match Self::connect_to(socket_path) {
+                Ok(client) => return Ok(Some(client)),
+                Err(e) if Instant::now() >= deadline => return Err(e),
+                Err(_) => std::thread::sleep(RECONNECT_RETRY_INTERVAL),
+            }
+        }
+    }
+
+    pub(super) fn connect_to(socket_path: &std::path::Path) -> Result<Self> {
+        let stream = UnixStream::connect(socket_path).with_context(|| {
             format!(
                 "Failed to connect to sunsetr IPC socket at {:?}. Is sunsetr running?",
                 socket_path
@@ -50,7 +118,67 @@ impl IpcClient {
             .context("Failed to clone stream for reader")?;
         let reader = BufReader::new(reader_stream);
 
-        Ok(Self { stream, reader })
+        Ok(Self {
+            stream,
+            reader,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Negotiate the protocol version with the server and return the agreed version.
+    ///
+    /// Events that arrive ahead of the reply are kept and returned by later reads. A
+    /// server that stays silent predates the handshake and is taken to speak version 1.
+    pub fn handshake(&mut self) -> Result<u32> {
+        let request = serde_json::to_string(&IpcRequest::Hello {
+            protocol_version: PROTOCOL_VERSION,
+        })?;
+        (&self.stream)
+            .write_all(format!("{request}\n").as_bytes())
+            .context("Failed to send hello to IPC socket")?;
+
+        let previous_timeout = self.stream.read_timeout()?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        let mut line = String::new();
+
+        let result = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Ok(PRE_HANDSHAKE_PROTOCOL_VERSION);
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+
+            match self.reader.read_line(&mut line) {
+                Ok(0) => break Err(ConnectionClosed.into()),
+                Ok(_) => {
+                    let trimmed = line.trim();
+                    if !trimmed.is_empty() {
+                        let event: IpcEvent = serde_json::from_str(trimmed).with_context(|| {
+                            format!("Failed to parse IPC event JSON: {trimmed}")
+                        })?;
+                        match event {
+                            IpcEvent::Hello {
+                                protocol_version, ..
+                            } => break Ok(protocol_version),
+                            other => self.pending.push_back(other),
+                        }
+                    }
+                    line.clear();
+                }
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break Ok(PRE_HANDSHAKE_PROTOCOL_VERSION);
+                }
+                Err(e) => break Err(anyhow::Error::from(e).context("Failed to read hello reply")),
+            }
+        };
+
+        self.stream.set_read_timeout(previous_timeout)?;
+        result
     }
 
     /// Read the current DisplayState from the server.
@@ -58,6 +186,16 @@ impl IpcClient {
     /// The server emits a StateApplied event immediately on connection, so this
     /// reads that initial event.
     pub fn current(&mut self) -> Result<DisplayState> {
+        if let Some(event) = self.pending.pop_front() {
+            return match event {
+                IpcEvent::StateApplied { state, .. } => Ok(state),
+                _ => Err(anyhow::anyhow!(
+                    "Expected StateApplied event on connection, got: {:?}",
+                    event
+                )),
+            };
+        }
+
         let mut line = String::new();
         self.reader
             .read_line(&mut line)
@@ -73,7 +211,7 @@ impl IpcClient {
             .with_context(|| format!("Failed to parse IPC event JSON: {}", line.trim()))?;
 
         match event {
-            IpcEvent::StateApplied { state } => Ok(state),
+            IpcEvent::StateApplied { state, .. } => Ok(state),
             _ => Err(anyhow::anyhow!(
                 "Expected StateApplied event on connection, got: {:?}",
                 event
@@ -81,11 +219,217 @@ impl IpcClient {
         }
     }
 
+    /// Ask the server for the events it broadcast most recently, oldest first.
+    ///
+    /// Events that arrive ahead of the answer are kept for later reads.
+    pub fn recent_events(&mut self) -> Result<Vec<IpcEvent>> {
+        let request = serde_json::to_string(&IpcRequest::RecentEvents)?;
+        (&self.stream)
+            .write_all(format!("{request}\n").as_bytes())
+            .context("Failed to send recent_events request to IPC socket")?;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = self
+                .reader
+                .read_line(&mut line)
+                .context("Failed to read recent events from IPC socket")?;
+            if read == 0 {
+                return Err(ConnectionClosed.into());
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: IpcEvent = serde_json::from_str(line.trim())
+                .with_context(|| format!("Failed to parse IPC event JSON: {}", line.trim()))?;
+            match event {
+                IpcEvent::RecentEvents { events } => return Ok(events),
+                other => self.pending.push_back(other),
+            }
+        }
+    }
+
+    /// Ask the server for the states applied most recently, oldest first.
+    ///
+    /// Events that arrive ahead of the answer are kept for later reads.
+    pub fn history(&mut self) -> Result<Vec<AppliedEntry>> {
+        let request = serde_json::to_string(&IpcRequest::History)?;
+        (&self.stream)
+            .write_all(format!("{request}\n").as_bytes())
+            .context("Failed to send history request to IPC socket")?;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = self
+                .reader
+                .read_line(&mut line)
+                .context("Failed to read history from IPC socket")?;
+            if read == 0 {
+                return Err(ConnectionClosed.into());
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: IpcEvent = serde_json::from_str(line.trim())
+                .with_context(|| format!("Failed to parse IPC event JSON: {}", line.trim()))?;
+            match event {
+                IpcEvent::History { entries } => return Ok(entries),
+                other => self.pending.push_back(other),
+            }
+        }
+    }
+
+    /// Ask the server to rebuild every output and wait for the state it applies afterwards.
+    ///
+    /// Connect with [`IpcClient::connect_for_request`], since the server replays its last
+    /// state_applied, which may itself come from a refresh. Returns `None` when no
+    /// state is applied in time, as happens while a preview is up or with servers that
+    /// don't know the request.
+    pub fn refresh(&mut self) -> Result<Option<DisplayState>> {
+        self.request_state(&IpcRequest::Refresh, "refresh", ChangeReason::Refresh)
+    }
+
+    /// Pause or resume color shifting, flipping it with `None`, and wait for the state
+    /// applied afterwards, whose `paused` says which it is now. A pause resumes by itself at
+    /// `until`. Returns `None` when no state is applied in time, as with servers that don't
+    /// know the request.
+    pub fn pause(
+        &mut self,
+        paused: Option<bool>,
+        until: Option<PauseUntil>,
+    ) -> Result<Option<DisplayState>> {
+        let request = IpcRequest::Pause { paused, until };
+        self.request_state(&request, "pause", ChangeReason::Pause)
+    }
+
+    /// Send `request` and wait for the state_applied event with `reason` that answers it.
+    fn request_state(
+        &mut self,
+        request: &IpcRequest,
+        request_type: &str,
+        reason: ChangeReason,
+    ) -> Result<Option<DisplayState>> {
+        let line = serde_json::to_string(request)?;
+        (&self.stream)
+            .write_all(format!("{line}\n").as_bytes())
+            .with_context(|| format!("Failed to send {request_type} request to IPC socket"))?;
+
+        let previous_timeout = self.stream.read_timeout()?;
+        let deadline = Instant::now() + STATE_REPLY_TIMEOUT;
+        let mut line = String::new();
+
+        let result = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Ok(None);
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+
+            line.clear();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => break Err(ConnectionClosed.into()),
+                Ok(_) if line.trim().is_empty() => {}
+                Ok(_) => {
+                    let event: IpcEvent = serde_json::from_str(line.trim()).with_context(|| {
+                        format!("Failed to parse IPC event JSON: {}", line.trim())
+                    })?;
+                    match event {
+                        IpcEvent::StateApplied {
+                            state,
+                            reason: applied,
+                        } if applied == reason => break Ok(Some(state)),
+                        IpcEvent::RequestRejected {
+                            request_type: rejected,
+                            message,
+                        } if rejected == request_type => break Err(anyhow::anyhow!(message)),
+                        other => self.pending.push_back(other),
+                    }
+                }
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break Ok(None);
+                }
+                Err(e) => {
+                    break Err(anyhow::Error::from(e)
+                        .context(format!("Failed to read {request_type} reply")));
+                }
+            }
+        };
+
+        self.stream.set_read_timeout(previous_timeout)?;
+        result
+    }
+
+    /// Ask the server to hold every output neutral until this client disconnects, and wait
+    /// until it does. Returns `false` when that isn't confirmed in time, as with servers
+    /// that don't know the request.
+    pub fn hold_neutral(&mut self) -> Result<bool> {
+        let request = serde_json::to_string(&IpcRequest::HoldNeutral)?;
+        (&self.stream)
+            .write_all(format!("{request}\n").as_bytes())
+            .context("Failed to send hold_neutral request to IPC socket")?;
+
+        let previous_timeout = self.stream.read_timeout()?;
+        let deadline = Instant::now() + HOLD_NEUTRAL_TIMEOUT;
+        let mut line = String::new();
+
+        let result = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Ok(false);
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+
+            line.clear();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => break Err(ConnectionClosed.into()),
+                Ok(_) if line.trim().is_empty() => {}
+                Ok(_) => {
+                    let event: IpcEvent = serde_json::from_str(line.trim()).with_context(|| {
+                        format!("Failed to parse IPC event JSON: {}", line.trim())
+                    })?;
+                    match event {
+                        IpcEvent::NeutralHeld { held: true } => break Ok(true),
+                        IpcEvent::RequestRejected {
+                            request_type,
+                            message,
+                        } if request_type == "hold_neutral" => break Err(anyhow::anyhow!(message)),
+                        other => self.pending.push_back(other),
+                    }
+                }
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break Ok(false);
+                }
+                Err(e) => {
+                    break Err(anyhow::Error::from(e).context("Failed to read hold_neutral reply"));
+                }
+            }
+        };
+
+        self.stream.set_read_timeout(previous_timeout)?;
+        result
+    }
+
     /// Try to receive the next IpcEvent from the server without blocking.
     ///
     /// Returns `Ok(None)` when no data is available yet, and a downcastable
     /// [`ConnectionClosed`] error once the server has closed the connection.
     pub fn try_receive_event(&mut self) -> Result<Option<IpcEvent>> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(Some(event));
+        }
+
         let mut line = String::new();
         match self.reader.read_line(&mut line) {
             Ok(0) => Err(ConnectionClosed.into()),
@@ -135,6 +479,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_handshake_treats_silent_server_as_v1() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("silent.sock");
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+
+        let mut client = IpcClient::connect_to(&path).unwrap();
+        let _server_side = listener.accept().unwrap();
+
+        assert_eq!(client.handshake().unwrap(), PRE_HANDSHAKE_PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn test_reconnect_waits_for_the_socket_to_return() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("restarted.sock");
+
+        let replacement = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(300));
+                let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+                listener.accept().unwrap();
+            }
+        });
+
+        let stop = AtomicBool::new(false);
+        let client = IpcClient::reconnect_to(&path, Duration::from_secs(5), &stop).unwrap();
+        assert!(client.is_some());
+        replacement.join().unwrap();
+
+        // Nothing comes back within the timeout
+        let gone = temp_dir.path().join("gone.sock");
+        assert!(IpcClient::reconnect_to(&gone, Duration::from_millis(200), &stop).is_err());
+
+        stop.store(true, Ordering::SeqCst);
+        assert!(
+            IpcClient::reconnect_to(&gone, Duration::from_secs(5), &stop)
+                .unwrap()
+                .is_none()
+        );
+    }
+
     #[test]
     fn test_socket_path() {
         let path = socket_path().unwrap();
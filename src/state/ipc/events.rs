@@ -1,9 +1,20 @@
 //! Event data structures for the IPC system.
 
-use crate::core::period::Period;
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::{PlaceholderCoordinates, TransitionMode};
+use crate::core::history::AppliedEntry;
+use crate::core::pause::Pause;
+use crate::core::period::{ChangeReason, Period};
 use crate::state::display::DisplayState;
+use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 
+/// Version of the event wire format.
+///
+/// Bumped only for breaking changes to existing events. New event types and new fields
+/// are additive and keep the version, so clients must ignore what they don't recognize.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "event_type", rename_all = "snake_case")]
 pub enum IpcEvent {
@@ -11,12 +22,16 @@ pub enum IpcEvent {
     StateApplied {
         #[serde(flatten)]
         state: DisplayState,
+        #[serde(default)]
+        reason: ChangeReason,
     },
 
-    /// Emitted only for automatic time-based period transitions.
+    /// Emitted whenever the period changes.
     PeriodChanged {
         from_period: Period,
         to_period: Period,
+        #[serde(default)]
+        reason: ChangeReason,
     },
 
     /// Emitted when the active preset changes, carrying the target values
@@ -25,28 +40,225 @@ pub enum IpcEvent {
         from_preset: Option<String>,
         to_preset: Option<String>,
         target_period: Period,
-        target_temp: u32,
-        target_gamma: f64,
+        target_temp: Kelvin,
+        target_gamma: GammaPercent,
     },
 
     /// Emitted when config values change, carrying the target values before any
     /// smooth transition completes.
     ConfigChanged {
         target_period: Period,
-        target_temp: u32,
-        target_gamma: f64,
+        target_temp: Kelvin,
+        target_gamma: GammaPercent,
+    },
+
+    /// Emitted alongside state_applied only when temperature or gamma moved by at least
+    /// `values_changed_temp_step` or `values_changed_gamma_step` since the last one, and
+    /// whenever a period outside a transition lands on new values. Lets clients that only
+    /// show the values sleep through the small steps of a long transition.
+    ValuesChanged {
+        period: Period,
+        current_temp: Kelvin,
+        current_gamma: GammaPercent,
+    },
+
+    /// Emitted when an output is connected while sunsetr is running.
+    OutputAdded { name: String },
+
+    /// Emitted when an output is disconnected while sunsetr is running.
+    OutputRemoved { name: String },
+
+    /// Emitted every `heartbeat_interval` seconds by the main loop, whether or not anything
+    /// changed, so clients can tell a quiet daemon from a hung one.
+    Heartbeat {
+        timestamp: DateTime<Local>,
+        interval_secs: u64,
+    },
+
+    /// Sent only to a client that opened with [`IpcRequest::Hello`], carrying the
+    /// protocol version both sides will speak.
+    Hello {
+        protocol_version: u32,
+        sunsetr_version: String,
+    },
+
+    /// Emitted when a live preview is shown, committed or cancelled, carrying the
+    /// previewed values. While a preview is up, scheduled updates are held back and no
+    /// state_applied events are sent.
+    PreviewChanged {
+        action: PreviewAction,
+        temp: Kelvin,
+        gamma: GammaPercent,
+    },
+
+    /// Sent only to the client whose request was refused, e.g. for values out of range.
+    RequestRejected {
+        request_type: String,
+        message: String,
+    },
+
+    /// Emitted when `override_expiry` drops a manual override as a sunset or sunrise
+    /// begins, or when a `sunsetr adjust` offset runs out. `fields` names the runtime
+    /// overrides dropped and is empty for the other kinds.
+    OverrideExpired {
+        kind: OverrideKind,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        fields: Vec<String>,
+    },
+
+    /// Emitted on startup or reload when geo mode's coordinates look like a placeholder
+    /// (0,0 or latitude equal to longitude) and the schedule fell back to `fallback_mode`.
+    CoordinatesRejected {
+        latitude: f64,
+        longitude: f64,
+        reason: String,
+        fallback_mode: String,
+    },
+
+    /// Sent only to a client that asked with [`IpcRequest::RecentEvents`]: the last events
+    /// broadcast, oldest first, leaving out heartbeats.
+    RecentEvents { events: Vec<IpcEvent> },
+
+    /// Sent only to a client that asked with [`IpcRequest::History`]: the states applied
+    /// most recently, oldest first.
+    History { entries: Vec<AppliedEntry> },
+
+    /// Emitted when every output starts or stops being held neutral for a client that sent
+    /// [`IpcRequest::HoldNeutral`], once the backend has the change.
+    NeutralHeld { held: bool },
+
+    /// Emitted when color shifting is paused, resumed, or the end of a pause changes,
+    /// including when a pause runs out. `until` is when a pause resumes by itself.
+    PauseChanged {
+        paused: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        until: Option<DateTime<Local>>,
+    },
+
+    /// Emitted as `sunsetr restart` stops this instance, the last event on the connection.
+    /// The replacement binds the same socket path, so clients should reconnect rather
+    /// than exit.
+    Restarting,
+}
+
+/// What happened to a live preview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewAction {
+    Set,
+    Commit,
+    Cancel,
+}
+
+/// Which manual override expired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverrideKind {
+    /// Fields set with `set --runtime`.
+    Runtime,
+    /// Values held by `sunsetr test`.
+    Test,
+    /// Offsets set with `sunsetr adjust`, dropped as the next day begins.
+    Adjustment,
+}
+
+/// Messages a client may send to the server, one JSON object per line.
+///
+/// Sending nothing is valid: clients that never say hello receive the same event stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "request_type", rename_all = "snake_case")]
+pub enum IpcRequest {
+    /// Offer the highest protocol version the client understands.
+    Hello { protocol_version: u32 },
+
+    /// Show these values at once, without a transition and without saving them. Send
+    /// again to move the preview.
+    PreviewSet { temp: Kelvin, gamma: GammaPercent },
+
+    /// Save the previewed values to the active config, for the current period.
+    Commit,
+
+    /// Drop the preview and return to the scheduled values.
+    Cancel,
+
+    /// Apply this complete `sunsetr.toml` document now, validated and applied like a reload
+    /// but without reading the config directory. The next reload from disk replaces it.
+    ApplyConfig { config: String },
+
+    /// Show these values until the next push, in `transition_mode = "external"`. Values
+    /// out of range fail to parse and are rejected.
+    SetTargets { temp: Kelvin, gamma: GammaPercent },
+
+    /// Ask for the events broadcast most recently, answered with a recent_events event.
+    RecentEvents,
+
+    /// Ask for the states applied most recently, answered with a history event.
+    History,
+
+    /// Re-read the outputs, rebuild the backend's gamma controls and tables, and apply the
+    /// current values again, answered by a state_applied event with the refresh reason.
+    Refresh,
+
+    /// Turn `reduce_motion` on or off until sunsetr exits, or without `enabled` go back
+    /// to the config's setting.
+    ReduceMotion {
+        #[serde(default)]
+        enabled: Option<bool>,
     },
+
+    /// Hold every output neutral until this connection closes, for a screenshot taken in
+    /// between. Answered with neutral_held once the backend applied it.
+    HoldNeutral,
+
+    /// Pause color shifting, holding every output at 6500K and 100% until resumed or until
+    /// `until`, or resume it. Without `paused` the current state is flipped. Answered by a
+    /// state_applied event with the pause reason.
+    Pause {
+        #[serde(default)]
+        paused: Option<bool>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        until: Option<crate::core::pause::PauseUntil>,
+    },
+}
+
+impl IpcRequest {
+    /// The `request_type` the request is sent with.
+    pub fn request_type(&self) -> &'static str {
+        match self {
+            Self::Hello { .. } => "hello",
+            Self::PreviewSet { .. } => "preview_set",
+            Self::Commit => "commit",
+            Self::Cancel => "cancel",
+            Self::ApplyConfig { .. } => "apply_config",
+            Self::SetTargets { .. } => "set_targets",
+            Self::RecentEvents => "recent_events",
+            Self::History => "history",
+            Self::Refresh => "refresh",
+            Self::ReduceMotion { .. } => "reduce_motion",
+            Self::HoldNeutral => "hold_neutral",
+            Self::Pause { .. } => "pause",
+        }
+    }
+
+    /// Whether the request only reads from the server, rather than changing what it shows.
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Self::Hello { .. } | Self::RecentEvents | Self::History
+        )
+    }
 }
 
 impl IpcEvent {
-    pub fn state_applied(state: DisplayState) -> Self {
-        IpcEvent::StateApplied { state }
+    pub fn state_applied(state: DisplayState, reason: ChangeReason) -> Self {
+        IpcEvent::StateApplied { state, reason }
     }
 
-    pub fn period_changed(from: Period, to: Period) -> Self {
+    pub fn period_changed(from: Period, to: Period, reason: ChangeReason) -> Self {
         IpcEvent::PeriodChanged {
             from_period: from,
             to_period: to,
+            reason,
         }
     }
 
@@ -54,8 +266,8 @@ impl IpcEvent {
         from: Option<String>,
         to: Option<String>,
         target_period: Period,
-        target_temp: u32,
-        target_gamma: f64,
+        target_temp: Kelvin,
+        target_gamma: GammaPercent,
     ) -> Self {
         IpcEvent::PresetChanged {
             from_preset: from,
@@ -66,45 +278,137 @@ impl IpcEvent {
         }
     }
 
-    pub fn config_changed(target_period: Period, target_temp: u32, target_gamma: f64) -> Self {
+    pub fn config_changed(
+        target_period: Period,
+        target_temp: Kelvin,
+        target_gamma: GammaPercent,
+    ) -> Self {
         IpcEvent::ConfigChanged {
             target_period,
             target_temp,
             target_gamma,
         }
     }
+
+    pub fn values_changed(
+        period: Period,
+        current_temp: Kelvin,
+        current_gamma: GammaPercent,
+    ) -> Self {
+        IpcEvent::ValuesChanged {
+            period,
+            current_temp,
+            current_gamma,
+        }
+    }
+
+    pub fn output_added(name: String) -> Self {
+        IpcEvent::OutputAdded { name }
+    }
+
+    pub fn output_removed(name: String) -> Self {
+        IpcEvent::OutputRemoved { name }
+    }
+
+    pub fn heartbeat(interval_secs: u64) -> Self {
+        IpcEvent::Heartbeat {
+            timestamp: Local::now(),
+            interval_secs,
+        }
+    }
+
+    pub fn preview_changed(action: PreviewAction, temp: Kelvin, gamma: GammaPercent) -> Self {
+        IpcEvent::PreviewChanged {
+            action,
+            temp,
+            gamma,
+        }
+    }
+
+    pub fn request_rejected(request_type: &str, message: impl Into<String>) -> Self {
+        IpcEvent::RequestRejected {
+            request_type: request_type.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn coordinates_rejected(
+        placeholder: &PlaceholderCoordinates,
+        fallback_mode: TransitionMode,
+    ) -> Self {
+        IpcEvent::CoordinatesRejected {
+            latitude: placeholder.latitude,
+            longitude: placeholder.longitude,
+            reason: placeholder.reason.to_string(),
+            fallback_mode: fallback_mode.to_string(),
+        }
+    }
+
+    pub fn override_expired(kind: OverrideKind, fields: Vec<String>) -> Self {
+        IpcEvent::OverrideExpired { kind, fields }
+    }
+
+    pub fn neutral_held(held: bool) -> Self {
+        IpcEvent::NeutralHeld { held }
+    }
+
+    pub fn pause_changed(pause: Option<Pause>) -> Self {
+        IpcEvent::PauseChanged {
+            paused: pause.is_some(),
+            until: pause.and_then(|pause| pause.until),
+        }
+    }
+
+    /// Answer a client's hello with the highest version both sides support.
+    pub fn hello(client_version: u32) -> Self {
+        IpcEvent::Hello {
+            protocol_version: client_version.min(PROTOCOL_VERSION),
+            sunsetr_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::display::TransitionProgress;
 
     #[test]
     fn test_event_serialization() {
         let state = DisplayState {
             active_preset: "evening".to_string(),
+            preset_expires_at: None,
             period: Period::Sunset,
             period_type: Period::Sunset.period_type(),
-            progress: Some(0.5),
-            current_temp: 4500,
-            current_gamma: 95.0,
-            target_temp: Some(3300),
-            target_gamma: Some(90.0),
+            current_temp: Kelvin::new(4500).unwrap(),
+            current_gamma: GammaPercent::new(95.0).unwrap(),
+            transition: Some(TransitionProgress {
+                progress: 0.5,
+                target_temp: Kelvin::new(3300).unwrap(),
+                target_gamma: GammaPercent::new(90.0).unwrap(),
+            }),
             next_period: None,
+            next_update: None,
+            presentation: None,
+            paused: false,
+            paused_until: None,
+            adjustment: None,
         };
 
-        let event = IpcEvent::state_applied(state);
+        let event = IpcEvent::state_applied(state, ChangeReason::Schedule);
         let json = serde_json::to_string(&event).unwrap();
 
         assert!(json.contains("\"event_type\":\"state_applied\""));
+        assert!(json.contains("\"reason\":\"schedule\""));
         assert!(json.contains("\"period\":\"sunset\""));
         assert!(json.contains("\"current_temp\":4500"));
 
         let deserialized: IpcEvent = serde_json::from_str(&json).unwrap();
         match deserialized {
-            IpcEvent::StateApplied { state } => {
+            IpcEvent::StateApplied { state, reason } => {
                 assert_eq!(state.active_preset, "evening");
-                assert_eq!(state.current_temp, 4500);
+                assert_eq!(state.current_temp.get(), 4500);
+                assert_eq!(reason, ChangeReason::Schedule);
             }
             _ => panic!("Wrong event type deserialized"),
         }
@@ -112,12 +416,28 @@ mod tests {
 
     #[test]
     fn test_period_changed_serialization() {
-        let event = IpcEvent::period_changed(Period::Day, Period::Sunset);
+        let event = IpcEvent::period_changed(Period::Day, Period::Sunset, ChangeReason::Wake);
         let json = serde_json::to_string(&event).unwrap();
 
         assert!(json.contains("\"event_type\":\"period_changed\""));
         assert!(json.contains("\"from_period\":\"day\""));
         assert!(json.contains("\"to_period\":\"sunset\""));
+        assert!(json.contains("\"reason\":\"wake\""));
+    }
+
+    #[test]
+    fn test_missing_or_unknown_reason_deserializes_as_unknown() {
+        for json in [
+            r#"{"event_type":"period_changed","from_period":"day","to_period":"sunset"}"#,
+            r#"{"event_type":"period_changed","from_period":"day","to_period":"sunset","reason":"solar_flare"}"#,
+        ] {
+            match serde_json::from_str(json).unwrap() {
+                IpcEvent::PeriodChanged { reason, .. } => {
+                    assert_eq!(reason, ChangeReason::Unknown)
+                }
+                event => panic!("Wrong event type deserialized: {event:?}"),
+            }
+        }
     }
 
     #[test]
@@ -126,8 +446,8 @@ mod tests {
             Some("daytime".to_string()),
             Some("evening".to_string()),
             Period::Static,
-            3300,
-            90.0,
+            Kelvin::new(3300).unwrap(),
+            GammaPercent::new(90.0).unwrap(),
         );
         let json = serde_json::to_string(&event).unwrap();
 
@@ -141,7 +461,11 @@ mod tests {
 
     #[test]
     fn test_config_changed_serialization() {
-        let event = IpcEvent::config_changed(Period::Night, 3500, 92.5);
+        let event = IpcEvent::config_changed(
+            Period::Night,
+            Kelvin::new(3500).unwrap(),
+            GammaPercent::new(92.5).unwrap(),
+        );
         let json = serde_json::to_string(&event).unwrap();
 
         assert!(json.contains("\"event_type\":\"config_changed\""));
@@ -149,4 +473,323 @@ mod tests {
         assert!(json.contains("\"target_temp\":3500"));
         assert!(json.contains("\"target_gamma\":92.5"));
     }
+
+    #[test]
+    fn test_output_events_serialization() {
+        let json = serde_json::to_string(&IpcEvent::output_added("HDMI-A-1".to_string())).unwrap();
+        assert_eq!(json, r#"{"event_type":"output_added","name":"HDMI-A-1"}"#);
+
+        let json = serde_json::to_string(&IpcEvent::output_removed("DP-1".to_string())).unwrap();
+        assert_eq!(json, r#"{"event_type":"output_removed","name":"DP-1"}"#);
+    }
+
+    /// Snapshot file stem for each variant. The exhaustive match makes a new variant
+    /// fail to compile until it is given a snapshot.
+    fn snapshot_name(event: &IpcEvent) -> &'static str {
+        match event {
+            IpcEvent::StateApplied { state, .. } if state.period.is_transitioning() => {
+                "state_applied_transitioning"
+            }
+            IpcEvent::StateApplied { .. } => "state_applied_stable",
+            IpcEvent::PeriodChanged { .. } => "period_changed",
+            IpcEvent::PresetChanged { .. } => "preset_changed",
+            IpcEvent::ConfigChanged { .. } => "config_changed",
+            IpcEvent::OutputAdded { .. } => "output_added",
+            IpcEvent::OutputRemoved { .. } => "output_removed",
+            IpcEvent::ValuesChanged { .. } => "values_changed",
+            IpcEvent::Heartbeat { .. } => "heartbeat",
+            IpcEvent::Hello { .. } => "hello",
+            IpcEvent::PreviewChanged { .. } => "preview_changed",
+            IpcEvent::RequestRejected { .. } => "request_rejected",
+            IpcEvent::CoordinatesRejected { .. } => "coordinates_rejected",
+            IpcEvent::OverrideExpired { .. } => "override_expired",
+            IpcEvent::RecentEvents { .. } => "recent_events",
+            IpcEvent::History { .. } => "history",
+            IpcEvent::NeutralHeld { .. } => "neutral_held",
+            IpcEvent::PauseChanged { .. } => "pause_changed",
+            IpcEvent::Restarting => "restarting",
+        }
+    }
+
+    fn snapshot_samples() -> Vec<IpcEvent> {
+        let next_period = chrono::DateTime::parse_from_rfc3339("2025-11-11T17:49:25-06:00")
+            .unwrap()
+            .with_timezone(&chrono::Local);
+        let next_update = chrono::DateTime::parse_from_rfc3339("2025-11-11T17:21:55-06:00")
+            .unwrap()
+            .with_timezone(&chrono::Local);
+
+        vec![
+            IpcEvent::state_applied(
+                DisplayState {
+                    active_preset: "default".to_string(),
+                    preset_expires_at: None,
+                    period: Period::Sunset,
+                    period_type: Period::Sunset.period_type(),
+                    current_temp: Kelvin::new(4900).unwrap(),
+                    current_gamma: GammaPercent::new(95.0).unwrap(),
+                    transition: Some(TransitionProgress {
+                        progress: 0.5,
+                        target_temp: Kelvin::new(3300).unwrap(),
+                        target_gamma: GammaPercent::new(90.0).unwrap(),
+                    }),
+                    next_period: Some(next_period),
+                    next_update: Some(next_update),
+                    presentation: None,
+                    paused: false,
+                    paused_until: None,
+                    adjustment: None,
+                },
+                ChangeReason::Schedule,
+            ),
+            IpcEvent::state_applied(
+                DisplayState {
+                    active_preset: "gaming".to_string(),
+                    preset_expires_at: None,
+                    period: Period::Static,
+                    period_type: Period::Static.period_type(),
+                    current_temp: Kelvin::new(4700).unwrap(),
+                    current_gamma: GammaPercent::new(100.0).unwrap(),
+                    transition: None,
+                    next_period: None,
+                    next_update: None,
+                    presentation: None,
+                    paused: false,
+                    paused_until: None,
+                    adjustment: None,
+                },
+                ChangeReason::Preset,
+            ),
+            IpcEvent::period_changed(Period::Day, Period::Sunset, ChangeReason::Boundary),
+            IpcEvent::preset_changed(
+                None,
+                Some("gaming".to_string()),
+                Period::Static,
+                Kelvin::new(4700).unwrap(),
+                GammaPercent::new(100.0).unwrap(),
+            ),
+            IpcEvent::config_changed(
+                Period::Night,
+                Kelvin::new(3500).unwrap(),
+                GammaPercent::new(92.5).unwrap(),
+            ),
+            IpcEvent::values_changed(
+                Period::Sunset,
+                Kelvin::new(4900).unwrap(),
+                GammaPercent::new(95.0).unwrap(),
+            ),
+            IpcEvent::output_added("DP-2".to_string()),
+            IpcEvent::output_removed("DP-2".to_string()),
+            IpcEvent::Heartbeat {
+                timestamp: next_update,
+                interval_secs: 60,
+            },
+            IpcEvent::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                sunsetr_version: "0.0.0".to_string(),
+            },
+            IpcEvent::preview_changed(
+                PreviewAction::Set,
+                Kelvin::new(2800).unwrap(),
+                GammaPercent::new(85.0).unwrap(),
+            ),
+            IpcEvent::request_rejected("preview_set", "temp (500) must be between 1000 and 20000"),
+            IpcEvent::coordinates_rejected(
+                &PlaceholderCoordinates {
+                    latitude: 0.0,
+                    longitude: 0.0,
+                    reason: "0,0 is open ocean in the Gulf of Guinea",
+                },
+                TransitionMode::FinishBy,
+            ),
+            IpcEvent::override_expired(
+                OverrideKind::Runtime,
+                vec!["night_temp".to_string(), "night_gamma".to_string()],
+            ),
+            IpcEvent::RecentEvents {
+                events: vec![
+                    IpcEvent::period_changed(Period::Day, Period::Sunset, ChangeReason::Boundary),
+                    IpcEvent::output_added("DP-2".to_string()),
+                ],
+            },
+            IpcEvent::History {
+                entries: vec![
+                    AppliedEntry {
+                        timestamp: next_update,
+                        temp: Kelvin::new(4500).unwrap(),
+                        gamma: GammaPercent::new(95.0).unwrap(),
+                        reason: ChangeReason::Schedule,
+                        outputs: Some(vec!["DP-2".to_string(), "eDP-1".to_string()]),
+                    },
+                    AppliedEntry {
+                        timestamp: next_period,
+                        temp: Kelvin::new(3300).unwrap(),
+                        gamma: GammaPercent::new(90.0).unwrap(),
+                        reason: ChangeReason::Boundary,
+                        outputs: None,
+                    },
+                ],
+            },
+            IpcEvent::neutral_held(true),
+            IpcEvent::pause_changed(Some(Pause {
+                until: Some(next_period),
+            })),
+            IpcEvent::Restarting,
+        ]
+    }
+
+    fn snapshot_dir() -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/state/ipc/snapshots")
+            .join(format!("v{PROTOCOL_VERSION}"))
+    }
+
+    /// Rewrite timestamps in UTC so snapshots compare equal in any local timezone.
+    fn normalize(mut value: serde_json::Value) -> serde_json::Value {
+        if let Some(serde_json::Value::Array(entries)) = value.get_mut("entries") {
+            for entry in entries {
+                *entry = normalize(entry.take());
+            }
+        }
+        for key in ["next_period", "next_update", "timestamp", "until"] {
+            if let Some(next) = value.get_mut(key)
+                && let Some(text) = next.as_str()
+            {
+                let instant = chrono::DateTime::parse_from_rfc3339(text).unwrap();
+                *next = serde_json::Value::String(instant.with_timezone(&chrono::Utc).to_rfc3339());
+            }
+        }
+        value
+    }
+
+    /// Every event must serialize exactly as its committed snapshot and read back
+    /// unchanged. Run with `SUNSETR_UPDATE_SNAPSHOTS=1` to rewrite the snapshots after
+    /// an intentional, additive change.
+    #[test]
+    fn test_events_match_snapshots() {
+        let dir = snapshot_dir();
+        let update = std::env::var_os("SUNSETR_UPDATE_SNAPSHOTS").is_some();
+
+        for event in snapshot_samples() {
+            let path = dir.join(format!("{}.json", snapshot_name(&event)));
+            let actual = serde_json::to_value(&event).unwrap();
+
+            if update {
+                std::fs::create_dir_all(&dir).unwrap();
+                let pretty = serde_json::to_string_pretty(&actual).unwrap();
+                std::fs::write(&path, format!("{pretty}\n")).unwrap();
+                continue;
+            }
+
+            let snapshot = std::fs::read_to_string(&path)
+                .unwrap_or_else(|e| panic!("missing snapshot {}: {e}", path.display()));
+            let expected: serde_json::Value = serde_json::from_str(&snapshot).unwrap();
+            assert_eq!(
+                normalize(actual),
+                normalize(expected),
+                "wire format of {} changed; bump PROTOCOL_VERSION if this is a breaking change",
+                path.display()
+            );
+
+            let reparsed: IpcEvent = serde_json::from_str(&snapshot)
+                .unwrap_or_else(|e| panic!("{} no longer parses: {e}", path.display()));
+            assert_eq!(
+                normalize(serde_json::to_value(&reparsed).unwrap()),
+                normalize(serde_json::to_value(&event).unwrap())
+            );
+        }
+    }
+
+    /// Snapshots without a sample would silently stop being checked.
+    #[test]
+    fn test_every_snapshot_has_a_sample() {
+        let names: std::collections::HashSet<String> = snapshot_samples()
+            .iter()
+            .map(|event| format!("{}.json", snapshot_name(event)))
+            .collect();
+
+        for entry in std::fs::read_dir(snapshot_dir()).unwrap() {
+            let file_name = entry.unwrap().file_name().to_string_lossy().into_owned();
+            assert!(
+                names.contains(&file_name),
+                "snapshot {file_name} has no sample event"
+            );
+        }
+    }
+
+    #[test]
+    fn test_hello_negotiates_down_to_server_version() {
+        match IpcEvent::hello(PROTOCOL_VERSION + 1) {
+            IpcEvent::Hello {
+                protocol_version, ..
+            } => assert_eq!(protocol_version, PROTOCOL_VERSION),
+            other => panic!("expected hello, got {other:?}"),
+        }
+
+        let request: IpcRequest =
+            serde_json::from_str(r#"{"request_type":"hello","protocol_version":1}"#).unwrap();
+        assert!(matches!(
+            request,
+            IpcRequest::Hello {
+                protocol_version: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn test_reduce_motion_without_enabled_follows_the_config() {
+        let request: IpcRequest =
+            serde_json::from_str(r#"{"request_type":"reduce_motion","enabled":true}"#).unwrap();
+        assert!(matches!(
+            request,
+            IpcRequest::ReduceMotion {
+                enabled: Some(true)
+            }
+        ));
+
+        let request: IpcRequest =
+            serde_json::from_str(r#"{"request_type":"reduce_motion"}"#).unwrap();
+        assert!(matches!(
+            request,
+            IpcRequest::ReduceMotion { enabled: None }
+        ));
+    }
+
+    #[test]
+    fn test_pause_without_paused_toggles() {
+        let request: IpcRequest = serde_json::from_str(r#"{"request_type":"pause"}"#).unwrap();
+        assert!(matches!(
+            request,
+            IpcRequest::Pause {
+                paused: None,
+                until: None
+            }
+        ));
+
+        let request: IpcRequest =
+            serde_json::from_str(r#"{"request_type":"pause","paused":false}"#).unwrap();
+        assert!(matches!(
+            request,
+            IpcRequest::Pause {
+                paused: Some(false),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_pause_until_sunrise() {
+        use crate::core::pause::{PauseEvent, PauseUntil};
+
+        let request: IpcRequest =
+            serde_json::from_str(r#"{"request_type":"pause","paused":true,"until":"sunrise"}"#)
+                .unwrap();
+        assert!(matches!(
+            request,
+            IpcRequest::Pause {
+                paused: Some(true),
+                until: Some(PauseUntil::Event(PauseEvent::Sunrise))
+            }
+        ));
+    }
 }
@@ -4,19 +4,27 @@ use anyhow::{Context, Result};
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, mpsc};
 
-use crate::core::period::Period;
+use crate::backend::OutputEvent;
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::{PlaceholderCoordinates, TransitionMode};
+use crate::core::history::AppliedHistory;
+use crate::core::pause::Pause;
+use crate::core::period::{ChangeReason, Period};
 use crate::core::runtime_state::RuntimeState;
+use crate::io::signals::SignalMessage;
 use crate::state::display::DisplayState;
 
 pub mod client;
 pub mod events;
 mod server;
 
-use events::IpcEvent;
+use events::{IpcEvent, OverrideKind, PreviewAction};
 
 /// Sends typed events from Core to the IPC server thread.
 ///
-/// Delivery is fire-and-forget so Core's main loop never blocks on IPC.
+/// Delivery is fire-and-forget so Core's main loop never blocks on IPC. Clones share the
+/// same channel, which lets the backend worker report hotplug events directly.
+#[derive(Clone)]
 pub struct IpcNotifier {
     event_sender: mpsc::Sender<IpcEvent>,
 }
@@ -28,8 +36,8 @@ impl IpcNotifier {
         (notifier, event_receiver)
     }
 
-    pub fn send_period_changed(&self, from: Period, to: Period) {
-        let event = IpcEvent::period_changed(from, to);
+    pub fn send_period_changed(&self, from: Period, to: Period, reason: ChangeReason) {
+        let event = IpcEvent::period_changed(from, to, reason);
         let _ = self.event_sender.send(event);
     }
 
@@ -38,21 +46,86 @@ impl IpcNotifier {
         from: Option<String>,
         to: Option<String>,
         target_period: Period,
-        target_temp: u32,
-        target_gamma: f64,
+        target_temp: Kelvin,
+        target_gamma: GammaPercent,
     ) {
         let event = IpcEvent::preset_changed(from, to, target_period, target_temp, target_gamma);
         let _ = self.event_sender.send(event);
     }
 
-    pub fn send_config_changed(&self, target_period: Period, target_temp: u32, target_gamma: f64) {
+    pub fn send_config_changed(
+        &self,
+        target_period: Period,
+        target_temp: Kelvin,
+        target_gamma: GammaPercent,
+    ) {
         let event = IpcEvent::config_changed(target_period, target_temp, target_gamma);
         let _ = self.event_sender.send(event);
     }
 
-    pub fn send_state_applied(&self, runtime_state: &RuntimeState) {
-        let display_state = DisplayState::new(runtime_state);
-        let event = IpcEvent::state_applied(display_state);
+    pub fn send_output_event(&self, output_event: OutputEvent) {
+        let event = match output_event {
+            OutputEvent::Added(name) => IpcEvent::output_added(name),
+            OutputEvent::Removed(name) => IpcEvent::output_removed(name),
+        };
+        let _ = self.event_sender.send(event);
+    }
+
+    pub fn send_values_changed(
+        &self,
+        period: Period,
+        current_temp: Kelvin,
+        current_gamma: GammaPercent,
+    ) {
+        let event = IpcEvent::values_changed(period, current_temp, current_gamma);
+        let _ = self.event_sender.send(event);
+    }
+
+    pub fn send_heartbeat(&self, interval_secs: u64) {
+        let _ = self.event_sender.send(IpcEvent::heartbeat(interval_secs));
+    }
+
+    pub fn send_preview_changed(&self, action: PreviewAction, temp: Kelvin, gamma: GammaPercent) {
+        let _ = self
+            .event_sender
+            .send(IpcEvent::preview_changed(action, temp, gamma));
+    }
+
+    pub fn send_coordinates_rejected(
+        &self,
+        placeholder: &PlaceholderCoordinates,
+        fallback_mode: TransitionMode,
+    ) {
+        let event = IpcEvent::coordinates_rejected(placeholder, fallback_mode);
+        let _ = self.event_sender.send(event);
+    }
+
+    pub fn send_override_expired(&self, kind: OverrideKind, fields: Vec<String>) {
+        let _ = self
+            .event_sender
+            .send(IpcEvent::override_expired(kind, fields));
+    }
+
+    pub fn send_neutral_held(&self, held: bool) {
+        let _ = self.event_sender.send(IpcEvent::neutral_held(held));
+    }
+
+    pub fn send_pause_changed(&self, pause: Option<Pause>) {
+        let _ = self.event_sender.send(IpcEvent::pause_changed(pause));
+    }
+
+    pub fn send_state_applied(
+        &self,
+        runtime_state: &RuntimeState,
+        pause: Option<Pause>,
+        reason: ChangeReason,
+    ) {
+        let display_state = DisplayState {
+            paused: pause.is_some(),
+            paused_until: pause.and_then(|pause| pause.until),
+            ..DisplayState::new(runtime_state)
+        };
+        let event = IpcEvent::state_applied(display_state, reason);
         let _ = self.event_sender.send(event);
     }
 }
@@ -64,9 +137,18 @@ pub struct IpcServer {
 }
 
 impl IpcServer {
+    /// Start serving `event_receiver`'s events. Preview requests from clients are forwarded
+    /// to the main loop through `command_sender`, and `history` answers history requests.
+    /// `restarting` is checked as the server stops, so clients of an instance stopped by
+    /// `sunsetr restart` hear about it. Only processes of the same user, or of a primary group
+    /// in `allowed_gids`, may connect.
     pub fn start(
         event_receiver: mpsc::Receiver<IpcEvent>,
+        command_sender: mpsc::Sender<SignalMessage>,
+        history: AppliedHistory,
+        allowed_gids: Vec<u32>,
         running_flag: Arc<AtomicBool>,
+        restarting: Arc<AtomicBool>,
         debug_enabled: bool,
     ) -> Result<Self> {
         let running = Arc::clone(&running_flag);
@@ -80,7 +162,15 @@ impl IpcServer {
                 #[cfg(debug_assertions)]
                 eprintln!("DEBUG: IPC server thread closure started");
 
-                match Self::run(event_receiver, running, debug_enabled) {
+                match Self::run(
+                    event_receiver,
+                    command_sender,
+                    history,
+                    allowed_gids,
+                    running,
+                    restarting,
+                    debug_enabled,
+                ) {
                     Ok(()) => {
                         #[cfg(debug_assertions)]
                         eprintln!("DEBUG: IPC server completed successfully");
@@ -121,7 +211,11 @@ impl IpcServer {
 
     fn run(
         event_receiver: mpsc::Receiver<IpcEvent>,
+        command_sender: mpsc::Sender<SignalMessage>,
+        history: AppliedHistory,
+        allowed_gids: Vec<u32>,
         running: Arc<AtomicBool>,
+        restarting: Arc<AtomicBool>,
         debug_enabled: bool,
     ) -> Result<()> {
         #[cfg(debug_assertions)]
@@ -144,13 +238,15 @@ impl IpcServer {
 
         #[cfg(debug_assertions)]
         eprintln!("DEBUG: Creating IPC socket server");
-        let socket_server = server::IpcSocketServer::new(socket_path)
-            .context("Failed to create IPC socket server")?;
+        let socket_server = server::IpcSocketServer::new(socket_path, Some(command_sender))
+            .context("Failed to create IPC socket server")?
+            .with_history(history)
+            .with_allowed_gids(allowed_gids);
 
         #[cfg(debug_assertions)]
         eprintln!("DEBUG: Starting IPC socket server main loop");
         socket_server
-            .run(event_receiver, running, debug_enabled)
+            .run(event_receiver, running, restarting, debug_enabled)
             .context("IPC socket server failed")?;
 
         #[cfg(debug_assertions)]
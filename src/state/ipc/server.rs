@@ -1,8 +1,9 @@
 //! Unix socket server implementation for sunsetr IPC.
 
 use anyhow::{Context, Result};
+use nix::sys::socket::{UnixCredentials, getsockopt, sockopt::PeerCredentials};
 use nix::unistd::getuid;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::{BufWriter, Write};
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::PathBuf;
@@ -10,25 +11,180 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, mpsc};
 use std::time::{Duration, Instant};
 
-use crate::state::display::DisplayState;
-use crate::state::ipc::events::IpcEvent;
+use crate::config::Config;
+use crate::core::history::AppliedHistory;
+use crate::core::period::ChangeReason;
+use crate::core::preview::PreviewRequest;
+use crate::io::signals::SignalMessage;
+use crate::state::ipc::events::{IpcEvent, IpcRequest};
+
+/// Longest request line accepted from a client, room for an `apply_config` document.
+/// Anything longer is discarded.
+const MAX_REQUEST_LINE_BYTES: usize = 64 * 1024;
+
+/// How many broadcast events are kept for `recent_events` requests.
+const RECENT_EVENT_COUNT: usize = 20;
 
 pub struct IpcSocketServer {
     socket_path: PathBuf,
     listener: UnixListener,
     clients: HashMap<u32, ClientConnection>,
     next_client_id: u32,
-    current_state: Option<DisplayState>,
+    /// The latest state_applied event, replayed to clients as they connect.
+    current_state: Option<IpcEvent>,
+    /// The coordinates_rejected event standing since the last startup or reload, replayed
+    /// after the state so late clients still learn geo mode fell back.
+    coordinates_rejected: Option<IpcEvent>,
+    /// The last events broadcast, heartbeats aside, for `recent_events` requests.
+    recent_events: VecDeque<IpcEvent>,
+    /// The states Core applied recently, for `history` requests.
+    history: AppliedHistory,
+    /// Forwards requests that act on the display to the main loop.
+    command_sender: Option<mpsc::Sender<SignalMessage>>,
+    /// Whether Core was last told to hold every output neutral.
+    neutral_held: bool,
+    /// Primary groups whose processes may connect besides the user running sunsetr.
+    allowed_gids: Vec<u32>,
+    /// Users whose refused connections were logged already, `None` for peers without
+    /// credentials.
+    refused_users: HashSet<Option<u32>>,
 }
 
 struct ClientConnection {
     raw_stream: UnixStream,
     writer: BufWriter<UnixStream>,
     connected_at: Instant,
+    // Bytes of a request line still waiting for its newline
+    pending_request: Vec<u8>,
+    /// Whether the connecting process runs as the user running sunsetr. Only those may send
+    /// commands; processes let in by `ipc_allowed_gids` may subscribe and read.
+    may_command: bool,
+    /// Whether this client asked for every output to be held neutral.
+    holds_neutral: bool,
+}
+
+impl ClientConnection {
+    fn send(&mut self, event: &IpcEvent) -> Result<()> {
+        let json_line = serde_json::to_string(event).context("Failed to serialize IpcEvent")?;
+        self.writer.write_all(json_line.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Answer each complete request line received so far. A request that names its
+    /// `request_type` but doesn't parse, such as one with values out of range, is rejected.
+    /// Other lines are ignored, as clients were never required to stay silent.
+    fn handle_requests(
+        &mut self,
+        data: &[u8],
+        command_sender: Option<&mpsc::Sender<SignalMessage>>,
+        recent_events: &VecDeque<IpcEvent>,
+        history: &AppliedHistory,
+        neutral_held: bool,
+    ) -> Result<()> {
+        self.pending_request.extend_from_slice(data);
+
+        while let Some(newline) = self.pending_request.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending_request.drain(..=newline).collect();
+            let request = match serde_json::from_slice::<IpcRequest>(&line) {
+                Ok(request) => request,
+                Err(e) => {
+                    if let Some(request_type) = request_type_of(&line) {
+                        self.send(&IpcEvent::request_rejected(&request_type, e.to_string()))?;
+                    }
+                    continue;
+                }
+            };
+            if !self.may_command && !request.is_read_only() {
+                self.send(&IpcEvent::request_rejected(
+                    request.request_type(),
+                    "only processes of the user running sunsetr may send commands",
+                ))?;
+                continue;
+            }
+            let (request_type, command) = match request {
+                IpcRequest::Hello { protocol_version } => {
+                    self.send(&IpcEvent::hello(protocol_version))?;
+                    continue;
+                }
+                IpcRequest::RecentEvents => {
+                    self.send(&IpcEvent::RecentEvents {
+                        events: recent_events.iter().cloned().collect(),
+                    })?;
+                    continue;
+                }
+                IpcRequest::History => {
+                    self.send(&IpcEvent::History {
+                        entries: history.entries(),
+                    })?;
+                    continue;
+                }
+                IpcRequest::PreviewSet { temp, gamma } => (
+                    "preview_set",
+                    SignalMessage::Preview(PreviewRequest::Set { temp, gamma }),
+                ),
+                IpcRequest::Commit => ("commit", SignalMessage::Preview(PreviewRequest::Commit)),
+                IpcRequest::Cancel => ("cancel", SignalMessage::Preview(PreviewRequest::Cancel)),
+                IpcRequest::SetTargets { temp, gamma } => (
+                    "set_targets",
+                    SignalMessage::ExternalTargets { temp, gamma },
+                ),
+                IpcRequest::Refresh => ("refresh", SignalMessage::Refresh),
+                IpcRequest::Pause { paused, until } => {
+                    ("pause", SignalMessage::Pause { paused, until })
+                }
+                IpcRequest::ReduceMotion { enabled } => {
+                    ("reduce_motion", SignalMessage::ReduceMotion(enabled))
+                }
+                IpcRequest::HoldNeutral => {
+                    if command_sender.is_none() {
+                        self.send(&IpcEvent::request_rejected(
+                            "hold_neutral",
+                            "this instance does not accept hold_neutral requests",
+                        ))?;
+                    } else if !self.holds_neutral {
+                        self.holds_neutral = true;
+                        // Held for another client already, so Core won't announce it again
+                        if neutral_held {
+                            self.send(&IpcEvent::neutral_held(true))?;
+                        }
+                    }
+                    continue;
+                }
+                IpcRequest::ApplyConfig { config } => match Config::from_toml(&config) {
+                    Ok(config) => ("apply_config", SignalMessage::Reload(Box::new(config))),
+                    Err(e) => {
+                        self.send(&IpcEvent::request_rejected(
+                            "apply_config",
+                            format!("{e:#}"),
+                        ))?;
+                        continue;
+                    }
+                },
+            };
+
+            let forwarded = command_sender.is_some_and(|sender| sender.send(command).is_ok());
+            if !forwarded {
+                self.send(&IpcEvent::request_rejected(
+                    request_type,
+                    format!("this instance does not accept {request_type} requests"),
+                ))?;
+            }
+        }
+
+        if self.pending_request.len() > MAX_REQUEST_LINE_BYTES {
+            self.pending_request.clear();
+        }
+        Ok(())
+    }
 }
 
 impl IpcSocketServer {
-    pub fn new(socket_path: PathBuf) -> Result<Self> {
+    pub fn new(
+        socket_path: PathBuf,
+        command_sender: Option<mpsc::Sender<SignalMessage>>,
+    ) -> Result<Self> {
         if socket_path.exists() {
             std::fs::remove_file(&socket_path)
                 .with_context(|| format!("Failed to remove existing socket: {:?}", socket_path))?;
@@ -52,14 +208,35 @@ impl IpcSocketServer {
             clients: HashMap::new(),
             next_client_id: 1,
             current_state: None,
+            coordinates_rejected: None,
+            recent_events: VecDeque::new(),
+            history: AppliedHistory::default(),
+            command_sender,
+            neutral_held: false,
+            allowed_gids: Vec::new(),
+            refused_users: HashSet::new(),
         })
     }
 
-    /// Blocks until `running` is cleared, then removes the socket file.
+    /// Answer `history` requests from `history` instead of an empty record.
+    pub fn with_history(mut self, history: AppliedHistory) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Also accept connections from processes whose primary group is in `gids`.
+    pub fn with_allowed_gids(mut self, gids: Vec<u32>) -> Self {
+        self.allowed_gids = gids;
+        self
+    }
+
+    /// Blocks until `running` is cleared, then removes the socket file. When `restarting`
+    /// is set by then, clients are told to reconnect before their connections close.
     pub fn run(
         mut self,
         event_receiver: mpsc::Receiver<IpcEvent>,
         running: Arc<AtomicBool>,
+        restarting: Arc<AtomicBool>,
         debug_enabled: bool,
     ) -> Result<()> {
         if debug_enabled {
@@ -84,20 +261,46 @@ impl IpcSocketServer {
             }
 
             self.accept(debug_enabled)?;
-            self.prune_clients(debug_enabled);
+            self.poll_clients(debug_enabled);
         }
 
         if debug_enabled {
             log_debug!("IPC server shutting down");
         }
 
+        if restarting.load(Ordering::SeqCst) {
+            while let Ok(event) = event_receiver.try_recv() {
+                self.update_state(event, debug_enabled)?;
+            }
+            self.broadcast_event(&IpcEvent::Restarting, debug_enabled)?;
+        }
+
         self.cleanup()?;
         Ok(())
     }
 
     fn update_state(&mut self, event: IpcEvent, debug_enabled: bool) -> Result<()> {
-        if let IpcEvent::StateApplied { ref state } = event {
-            self.current_state = Some(state.clone());
+        match event {
+            IpcEvent::StateApplied { reason, .. } => {
+                // Core repeats the warning after these while the coordinates are still bad
+                if matches!(
+                    reason,
+                    ChangeReason::Startup | ChangeReason::Reload | ChangeReason::Preset
+                ) {
+                    self.coordinates_rejected = None;
+                }
+                self.current_state = Some(event.clone());
+            }
+            IpcEvent::CoordinatesRejected { .. } => {
+                self.coordinates_rejected = Some(event.clone());
+            }
+            _ => {}
+        }
+        if !matches!(event, IpcEvent::Heartbeat { .. }) {
+            if self.recent_events.len() == RECENT_EVENT_COUNT {
+                self.recent_events.pop_front();
+            }
+            self.recent_events.push_back(event.clone());
         }
         self.broadcast_event(&event, debug_enabled)
     }
@@ -145,6 +348,13 @@ impl IpcSocketServer {
         loop {
             match self.listener.accept() {
                 Ok((stream, _addr)) => {
+                    let peer = getsockopt(&stream, PeerCredentials).ok();
+                    let identity = peer.map(|credentials| (credentials.uid(), credentials.gid()));
+                    if !peer_allowed(identity, getuid().as_raw(), &self.allowed_gids) {
+                        self.log_refused(peer.as_ref());
+                        continue;
+                    }
+
                     let client_id = self.next_client_id;
                     self.next_client_id += 1;
 
@@ -160,34 +370,21 @@ impl IpcSocketServer {
                         raw_stream: stream,
                         writer: BufWriter::new(writer_stream),
                         connected_at: Instant::now(),
+                        pending_request: Vec::new(),
+                        may_command: identity.is_some_and(|(uid, _)| uid == getuid().as_raw()),
+                        holds_neutral: false,
                     };
 
-                    if let Some(ref current_state) = self.current_state {
-                        let event = IpcEvent::state_applied(current_state.clone());
-                        let json_line = serde_json::to_string(&event)
-                            .context("Failed to serialize current state event for new client")?;
-                        let message = format!("{}\n", json_line);
-
-                        if let Err(e) = client.writer.write_all(message.as_bytes()) {
-                            if debug_enabled {
-                                log_debug!(
-                                    "Failed to send current state to client {}: {}",
-                                    client_id,
-                                    e
-                                );
-                            }
-                            continue;
-                        }
-                        if let Err(e) = client.writer.flush() {
-                            if debug_enabled {
-                                log_debug!(
-                                    "Failed to flush current state to client {}: {}",
-                                    client_id,
-                                    e
-                                );
-                            }
-                            continue;
+                    let mut replayed = self.current_state.iter().chain(&self.coordinates_rejected);
+                    if let Err(e) = replayed.try_for_each(|event| client.send(event)) {
+                        if debug_enabled {
+                            log_debug!(
+                                "Failed to send current state to client {}: {}",
+                                client_id,
+                                e
+                            );
                         }
+                        continue;
                     }
 
                     self.clients.insert(client_id, client);
@@ -209,31 +406,61 @@ impl IpcSocketServer {
         Ok(())
     }
 
-    fn prune_clients(&mut self, debug_enabled: bool) {
+    /// Log a refused connection, once per user so a client retrying in a loop can't flood
+    /// the log.
+    fn log_refused(&mut self, peer: Option<&UnixCredentials>) {
+        if !self.refused_users.insert(peer.map(UnixCredentials::uid)) {
+            return;
+        }
+        log_pipe!();
+        match peer {
+            Some(peer) => log_warning!(
+                "Refused IPC connection from pid {} (uid {}, gid {})",
+                peer.pid(),
+                peer.uid(),
+                peer.gid()
+            ),
+            None => log_warning!("Refused IPC connection from a process of unknown identity"),
+        }
+        log_indented!("Only the user running sunsetr and groups in ipc_allowed_gids may connect");
+    }
+
+    /// Read whatever clients have sent, answering requests and dropping closed connections.
+    fn poll_clients(&mut self, debug_enabled: bool) {
         use std::io::Read;
         let mut disconnected = Vec::new();
 
         for (client_id, client) in &mut self.clients {
-            let mut buffer = [0u8; 1];
-            match client.raw_stream.read(&mut buffer) {
-                Ok(0) => {
-                    disconnected.push(*client_id);
-                }
-                Ok(_) => {
-                    // The protocol is server-to-client only, so unexpected client
-                    // data is ignored and the connection kept alive.
-                }
-                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    // WouldBlock means no data is waiting, so the connection is still alive.
-                }
-                Err(ref e)
-                    if e.kind() == std::io::ErrorKind::ConnectionReset
-                        || e.kind() == std::io::ErrorKind::BrokenPipe =>
-                {
-                    disconnected.push(*client_id);
-                }
-                Err(_) => {
-                    disconnected.push(*client_id);
+            let mut buffer = [0u8; 512];
+            loop {
+                match client.raw_stream.read(&mut buffer) {
+                    Ok(0) => {
+                        disconnected.push(*client_id);
+                        break;
+                    }
+                    Ok(n) => {
+                        if client
+                            .handle_requests(
+                                &buffer[..n],
+                                self.command_sender.as_ref(),
+                                &self.recent_events,
+                                &self.history,
+                                self.neutral_held,
+                            )
+                            .is_err()
+                        {
+                            disconnected.push(*client_id);
+                            break;
+                        }
+                    }
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        // WouldBlock means no data is waiting, so the connection is still alive.
+                        break;
+                    }
+                    Err(_) => {
+                        disconnected.push(*client_id);
+                        break;
+                    }
                 }
             }
         }
@@ -258,6 +485,22 @@ impl IpcSocketServer {
                 }
             }
         }
+
+        self.update_neutral_hold();
+    }
+
+    /// Tell Core to hold every output neutral while any client asks for it, and to let go
+    /// once the last of them released it or disconnected.
+    fn update_neutral_hold(&mut self) {
+        let wanted = self.clients.values().any(|client| client.holds_neutral);
+        if wanted == self.neutral_held {
+            return;
+        }
+        if let Some(sender) = &self.command_sender
+            && sender.send(SignalMessage::NeutralHold(wanted)).is_ok()
+        {
+            self.neutral_held = wanted;
+        }
     }
 
     fn cleanup(&self) -> Result<()> {
@@ -269,6 +512,19 @@ impl IpcSocketServer {
     }
 }
 
+/// Whether a peer running as `(uid, gid)` may connect: a process of the user running
+/// sunsetr, `own_uid`, or one whose primary group is in `allowed_gids`. Peers the kernel
+/// reported no credentials for are refused.
+fn peer_allowed(peer: Option<(u32, u32)>, own_uid: u32, allowed_gids: &[u32]) -> bool {
+    peer.is_some_and(|(uid, gid)| uid == own_uid || allowed_gids.contains(&gid))
+}
+
+/// The `request_type` a JSON object names, whether or not the rest of it parses.
+fn request_type_of(line: &[u8]) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(line).ok()?;
+    Some(value.get("request_type")?.as_str()?.to_string())
+}
+
 pub fn socket_path() -> Result<PathBuf> {
     let runtime_dir = if let Ok(xdg_runtime_dir) = std::env::var("XDG_RUNTIME_DIR") {
         PathBuf::from(xdg_runtime_dir)
@@ -277,12 +533,13 @@ pub fn socket_path() -> Result<PathBuf> {
         PathBuf::from(format!("/run/user/{}", uid))
     };
 
-    Ok(runtime_dir.join("sunsetr-events.sock"))
+    Ok(runtime_dir.join(crate::io::nested::scoped_file_name("sunsetr-events.sock")))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::units::{GammaPercent, Kelvin, values};
 
     #[test]
     fn test_socket_path() {
@@ -290,12 +547,561 @@ mod tests {
         assert!(path.to_string_lossy().contains("sunsetr-events.sock"));
     }
 
+    #[test]
+    fn test_peers_need_the_same_user_or_an_allowed_group() {
+        assert!(peer_allowed(Some((1000, 1000)), 1000, &[]));
+        assert!(peer_allowed(Some((1000, 50)), 1000, &[]));
+        assert!(!peer_allowed(Some((1001, 1001)), 1000, &[]));
+        assert!(peer_allowed(Some((1001, 985)), 1000, &[985]));
+        assert!(!peer_allowed(Some((0, 0)), 1000, &[985]));
+        assert!(!peer_allowed(None, 1000, &[985]));
+    }
+
+    /// Run a server on a temporary socket, returning its event sender and a stop guard.
+    fn spawn_server(
+        socket_path: &std::path::Path,
+    ) -> (
+        mpsc::Sender<IpcEvent>,
+        Arc<AtomicBool>,
+        std::thread::JoinHandle<()>,
+    ) {
+        let server = IpcSocketServer::new(socket_path.to_path_buf(), None).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = std::thread::spawn({
+            let running = Arc::clone(&running);
+            move || {
+                server
+                    .run(receiver, running, Arc::new(AtomicBool::new(false)), false)
+                    .unwrap()
+            }
+        });
+        (sender, running, thread)
+    }
+
+    fn read_event(reader: &mut impl std::io::BufRead) -> IpcEvent {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str(line.trim()).unwrap()
+    }
+
+    #[test]
+    fn test_hello_is_answered_with_negotiated_version() {
+        use std::io::BufReader;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("hello.sock");
+        let (_sender, running, thread) = spawn_server(&socket_path);
+
+        let stream = UnixStream::connect(&socket_path).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        // Junk ahead of the hello must not stop the server from answering it
+        (&stream)
+            .write_all(b"not json\n{\"request_type\":\"hello\",\"protocol_version\":99}\n")
+            .unwrap();
+
+        match read_event(&mut reader) {
+            IpcEvent::Hello {
+                protocol_version,
+                sunsetr_version,
+            } => {
+                assert_eq!(
+                    protocol_version,
+                    crate::state::ipc::events::PROTOCOL_VERSION
+                );
+                assert_eq!(sunsetr_version, env!("CARGO_PKG_VERSION"));
+            }
+            other => panic!("expected hello, got {other:?}"),
+        }
+
+        running.store(false, Ordering::SeqCst);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_restart_is_announced_before_the_socket_closes() {
+        use std::io::BufReader;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("restart.sock");
+        let server = IpcSocketServer::new(socket_path.clone(), None).unwrap();
+        let (_sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let restarting = Arc::new(AtomicBool::new(false));
+        let thread = std::thread::spawn({
+            let running = Arc::clone(&running);
+            let restarting = Arc::clone(&restarting);
+            move || server.run(receiver, running, restarting, false).unwrap()
+        });
+
+        let stream = UnixStream::connect(&socket_path).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+        std::thread::sleep(Duration::from_millis(50));
+
+        restarting.store(true, Ordering::SeqCst);
+        running.store(false, Ordering::SeqCst);
+        thread.join().unwrap();
+
+        assert!(matches!(read_event(&mut reader), IpcEvent::Restarting));
+        assert!(!socket_path.exists());
+    }
+
+    #[test]
+    fn test_client_handshake_keeps_initial_state() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("client.sock");
+        let (sender, running, thread) = spawn_server(&socket_path);
+
+        let state = crate::state::display::DisplayState {
+            active_preset: "default".to_string(),
+            preset_expires_at: None,
+            period: crate::core::period::Period::Night,
+            period_type: crate::core::period::Period::Night.period_type(),
+            current_temp: Kelvin::new(3300).unwrap(),
+            current_gamma: GammaPercent::new(90.0).unwrap(),
+            transition: None,
+            next_period: None,
+            next_update: None,
+            presentation: None,
+            paused: false,
+            paused_until: None,
+            adjustment: None,
+        };
+        sender
+            .send(IpcEvent::state_applied(state, ChangeReason::Startup))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        let mut client = crate::state::ipc::client::IpcClient::connect_to(&socket_path).unwrap();
+        assert_eq!(
+            client.handshake().unwrap(),
+            crate::state::ipc::events::PROTOCOL_VERSION
+        );
+        assert_eq!(client.current().unwrap().current_temp.get(), 3300);
+
+        running.store(false, Ordering::SeqCst);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_recent_events_leave_out_heartbeats() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("recent.sock");
+        let (sender, running, thread) = spawn_server(&socket_path);
+
+        sender
+            .send(IpcEvent::output_added("DP-2".to_string()))
+            .unwrap();
+        sender.send(IpcEvent::heartbeat(60)).unwrap();
+        sender
+            .send(IpcEvent::output_removed("DP-2".to_string()))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        let mut client = crate::state::ipc::client::IpcClient::connect_to(&socket_path).unwrap();
+        let events = client.recent_events().unwrap();
+        assert!(matches!(
+            events.as_slice(),
+            [IpcEvent::OutputAdded { .. }, IpcEvent::OutputRemoved { .. }]
+        ));
+
+        running.store(false, Ordering::SeqCst);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_history_requests_get_the_applied_history() {
+        use crate::core::history::AppliedEntry;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("history.sock");
+        let history = AppliedHistory::default();
+        let server = IpcSocketServer::new(socket_path.clone(), None)
+            .unwrap()
+            .with_history(history.clone());
+        let (_sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = std::thread::spawn({
+            let running = Arc::clone(&running);
+            move || {
+                server
+                    .run(receiver, running, Arc::new(AtomicBool::new(false)), false)
+                    .unwrap()
+            }
+        });
+
+        let mut client = crate::state::ipc::client::IpcClient::connect_to(&socket_path).unwrap();
+        assert!(client.history().unwrap().is_empty());
+
+        let entry = AppliedEntry {
+            timestamp: chrono::Local::now(),
+            temp: Kelvin::new(3300).unwrap(),
+            gamma: GammaPercent::new(90.0).unwrap(),
+            reason: ChangeReason::Boundary,
+            outputs: Some(vec!["eDP-1".to_string()]),
+        };
+        history.record(entry.clone());
+        let mut client = crate::state::ipc::client::IpcClient::connect_to(&socket_path).unwrap();
+        assert_eq!(client.history().unwrap(), vec![entry]);
+
+        running.store(false, Ordering::SeqCst);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_preview_requests_are_checked_and_forwarded() {
+        use crate::core::preview::PreviewRequest;
+        use std::io::BufReader;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("preview.sock");
+        let (command_sender, commands) = mpsc::channel();
+        let server = IpcSocketServer::new(socket_path.clone(), Some(command_sender)).unwrap();
+        let (_sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = std::thread::spawn({
+            let running = Arc::clone(&running);
+            move || {
+                server
+                    .run(receiver, running, Arc::new(AtomicBool::new(false)), false)
+                    .unwrap()
+            }
+        });
+
+        let stream = UnixStream::connect(&socket_path).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        (&stream)
+            .write_all(b"{\"request_type\":\"preview_set\",\"temp\":500,\"gamma\":90.0}\n")
+            .unwrap();
+        match read_event(&mut reader) {
+            IpcEvent::RequestRejected {
+                request_type,
+                message,
+            } => {
+                assert_eq!(request_type, "preview_set");
+                assert!(message.contains("temperature 500K"), "{message}");
+            }
+            other => panic!("expected request_rejected, got {other:?}"),
+        }
+
+        (&stream)
+            .write_all(
+                b"{\"request_type\":\"preview_set\",\"temp\":4000,\"gamma\":85.0}\n\
+                  {\"request_type\":\"commit\"}\n",
+            )
+            .unwrap();
+        let forwarded = |commands: &mpsc::Receiver<SignalMessage>| match commands
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap()
+        {
+            SignalMessage::Preview(request) => request,
+            other => panic!("expected a preview request, got {other:?}"),
+        };
+        assert_eq!(
+            forwarded(&commands),
+            PreviewRequest::Set {
+                temp: Kelvin::new(4000).unwrap(),
+                gamma: GammaPercent::new(85.0).unwrap()
+            }
+        );
+        assert_eq!(forwarded(&commands), PreviewRequest::Commit);
+
+        running.store(false, Ordering::SeqCst);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_apply_config_is_validated_before_reloading() {
+        use std::io::BufReader;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("apply.sock");
+        let (command_sender, commands) = mpsc::channel();
+        let server = IpcSocketServer::new(socket_path.clone(), Some(command_sender)).unwrap();
+        let (_sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = std::thread::spawn({
+            let running = Arc::clone(&running);
+            move || {
+                server
+                    .run(receiver, running, Arc::new(AtomicBool::new(false)), false)
+                    .unwrap()
+            }
+        });
+
+        let stream = UnixStream::connect(&socket_path).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let apply = |config: &str| {
+            let request = IpcRequest::ApplyConfig {
+                config: config.to_string(),
+            };
+            let line = format!("{}\n", serde_json::to_string(&request).unwrap());
+            (&stream).write_all(line.as_bytes()).unwrap();
+        };
+
+        apply("transition_mode = \"static\"\nstatic_temp = 500\nstatic_gamma = 90.0\n");
+        match read_event(&mut reader) {
+            IpcEvent::RequestRejected {
+                request_type,
+                message,
+            } => {
+                assert_eq!(request_type, "apply_config");
+                assert!(message.contains("static_temp"), "{message}");
+            }
+            other => panic!("expected request_rejected, got {other:?}"),
+        }
+
+        apply("transition_mode = \"static\"\nstatic_temp = 4000\nstatic_gamma = 90.0\n");
+        match commands.recv_timeout(Duration::from_secs(5)).unwrap() {
+            SignalMessage::Reload(config) => {
+                assert_eq!(
+                    config.transition_mode,
+                    crate::config::TransitionMode::Static
+                );
+                assert_eq!(config.static_temp, Some(4000));
+            }
+            other => panic!("expected a reload, got {other:?}"),
+        }
+        assert!(commands.try_recv().is_err());
+
+        running.store(false, Ordering::SeqCst);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_targets_from_the_same_user_are_forwarded() {
+        use std::io::BufReader;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("targets.sock");
+        let (command_sender, commands) = mpsc::channel();
+        let server = IpcSocketServer::new(socket_path.clone(), Some(command_sender)).unwrap();
+        let (_sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = std::thread::spawn({
+            let running = Arc::clone(&running);
+            move || {
+                server
+                    .run(receiver, running, Arc::new(AtomicBool::new(false)), false)
+                    .unwrap()
+            }
+        });
+
+        let stream = UnixStream::connect(&socket_path).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        (&stream)
+            .write_all(b"{\"request_type\":\"set_targets\",\"temp\":25000,\"gamma\":92.5}\n")
+            .unwrap();
+        match read_event(&mut reader) {
+            IpcEvent::RequestRejected {
+                request_type,
+                message,
+            } => {
+                assert_eq!(request_type, "set_targets");
+                assert!(message.contains("temperature 25000K"), "{message}");
+            }
+            other => panic!("expected request_rejected, got {other:?}"),
+        }
+
+        (&stream)
+            .write_all(b"{\"request_type\":\"set_targets\",\"temp\":2500,\"gamma\":92.5}\n")
+            .unwrap();
+        match commands.recv_timeout(Duration::from_secs(5)).unwrap() {
+            SignalMessage::ExternalTargets { temp, gamma } => {
+                assert_eq!((temp, gamma), values(2500, 92.5));
+            }
+            other => panic!("expected external targets, got {other:?}"),
+        }
+
+        running.store(false, Ordering::SeqCst);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_commands_from_an_allowed_group_are_refused() {
+        use std::io::{BufRead, BufReader};
+
+        let (server_end, client_end) = UnixStream::pair().unwrap();
+        let mut client = ClientConnection {
+            writer: BufWriter::new(server_end.try_clone().unwrap()),
+            raw_stream: server_end,
+            connected_at: Instant::now(),
+            pending_request: Vec::new(),
+            may_command: false,
+            holds_neutral: false,
+        };
+        let (command_sender, commands) = mpsc::channel();
+        client
+            .handle_requests(
+                b"{\"request_type\":\"set_targets\",\"temp\":3000,\"gamma\":90.0}\n\
+                  {\"request_type\":\"pause\"}\n\
+                  {\"request_type\":\"history\"}\n",
+                Some(&command_sender),
+                &VecDeque::new(),
+                &AppliedHistory::default(),
+                false,
+            )
+            .unwrap();
+
+        let mut reader = BufReader::new(client_end);
+        let mut next = || {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            serde_json::from_str::<IpcEvent>(&line).unwrap()
+        };
+        for expected in ["set_targets", "pause"] {
+            match next() {
+                IpcEvent::RequestRejected { request_type, .. } => {
+                    assert_eq!(request_type, expected)
+                }
+                other => panic!("expected {expected} to be rejected, got {other:?}"),
+            }
+        }
+        assert!(matches!(next(), IpcEvent::History { .. }));
+        assert!(commands.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_neutral_hold_lasts_while_a_client_asks_for_it() {
+        use std::io::BufReader;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("hold.sock");
+        let (command_sender, commands) = mpsc::channel();
+        let server = IpcSocketServer::new(socket_path.clone(), Some(command_sender)).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = std::thread::spawn({
+            let running = Arc::clone(&running);
+            move || {
+                server
+                    .run(receiver, running, Arc::new(AtomicBool::new(false)), false)
+                    .unwrap()
+            }
+        });
+        let hold = |stream: &UnixStream| {
+            let mut stream = stream;
+            stream
+                .write_all(b"{\"request_type\":\"hold_neutral\"}\n")
+                .unwrap();
+        };
+        let expect_hold = |wanted| match commands.recv_timeout(Duration::from_secs(5)).unwrap() {
+            SignalMessage::NeutralHold(held) => assert_eq!(held, wanted),
+            other => panic!("expected a neutral hold, got {other:?}"),
+        };
+
+        let first = UnixStream::connect(&socket_path).unwrap();
+        hold(&first);
+        expect_hold(true);
+        sender.send(IpcEvent::neutral_held(true)).unwrap();
+
+        // Already held, so the second client is answered without asking Core again
+        let second = UnixStream::connect(&socket_path).unwrap();
+        second
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut reader = BufReader::new(second.try_clone().unwrap());
+        std::thread::sleep(Duration::from_millis(50));
+        hold(&second);
+        loop {
+            if let IpcEvent::NeutralHeld { held } = read_event(&mut reader) {
+                assert!(held);
+                break;
+            }
+        }
+
+        drop(first);
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(commands.try_recv().is_err());
+        drop(reader);
+        drop(second);
+        expect_hold(false);
+
+        running.store(false, Ordering::SeqCst);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_refresh_is_answered_by_the_state_it_applies() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("refresh.sock");
+        let (command_sender, commands) = mpsc::channel();
+        let server = IpcSocketServer::new(socket_path.clone(), Some(command_sender)).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = std::thread::spawn({
+            let running = Arc::clone(&running);
+            move || {
+                server
+                    .run(receiver, running, Arc::new(AtomicBool::new(false)), false)
+                    .unwrap()
+            }
+        });
+
+        let state = |current_temp| crate::state::display::DisplayState {
+            active_preset: "default".to_string(),
+            preset_expires_at: None,
+            period: crate::core::period::Period::Night,
+            period_type: crate::core::period::Period::Night.period_type(),
+            current_temp: Kelvin::new(current_temp).unwrap(),
+            current_gamma: GammaPercent::new(90.0).unwrap(),
+            transition: None,
+            next_period: None,
+            next_update: None,
+            presentation: None,
+            paused: false,
+            paused_until: None,
+            adjustment: None,
+        };
+        // A replayed refresh must not be mistaken for the answer
+        sender
+            .send(IpcEvent::state_applied(state(3300), ChangeReason::Refresh))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        let core = std::thread::spawn(move || {
+            match commands.recv_timeout(Duration::from_secs(5)).unwrap() {
+                SignalMessage::Refresh => {}
+                other => panic!("expected a refresh, got {other:?}"),
+            }
+            sender
+                .send(IpcEvent::state_applied(state(3400), ChangeReason::Refresh))
+                .unwrap();
+        });
+
+        let mut client = crate::state::ipc::client::IpcClient::connect_to(&socket_path).unwrap();
+        assert_eq!(client.current().unwrap().current_temp.get(), 3300);
+        let refreshed = client.refresh().unwrap().unwrap();
+        assert_eq!(refreshed.current_temp.get(), 3400);
+
+        core.join().unwrap();
+        running.store(false, Ordering::SeqCst);
+        thread.join().unwrap();
+    }
+
     #[test]
     fn test_server_creation_and_cleanup() {
         let temp_dir = tempfile::tempdir().unwrap();
         let socket_path = temp_dir.path().join("test-sunsetr.sock");
 
-        let server = IpcSocketServer::new(socket_path.clone()).unwrap();
+        let server = IpcSocketServer::new(socket_path.clone(), None).unwrap();
 
         assert!(socket_path.exists());
 
@@ -1,3 +1,7 @@
 pub mod display;
+pub mod dock;
 pub mod ipc;
+pub mod overrides;
+pub mod presentation;
 pub mod preset;
+pub mod value_offset;
@@ -0,0 +1,55 @@
+//! Runtime overrides: settings changed with `set --runtime`.
+//!
+//! They live in `overrides.toml` in the state directory and are merged over the config
+//! files, presets included, every time the config loads. Temporary tweaks thereby stay
+//! out of the dotfiles and survive restarts until `set --clear` drops them.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::get_custom_config_dir;
+use crate::state::preset::{get_state_dir, write_atomic};
+
+const OVERRIDES_FILE: &str = "overrides.toml";
+
+/// Path of the overrides file for the current config directory, whether or not it exists.
+pub fn overrides_path() -> Result<PathBuf> {
+    let config_dir = get_custom_config_dir();
+    Ok(get_state_dir(config_dir.as_deref())?.join(OVERRIDES_FILE))
+}
+
+/// The current overrides, empty when none are set.
+pub fn load() -> Result<toml::Table> {
+    let path = overrides_path()?;
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read overrides from {}", path.display()))?;
+    content
+        .parse()
+        .with_context(|| format!("Failed to parse overrides from {}", path.display()))
+}
+
+/// Add `values` to the overrides, replacing earlier overrides of the same fields.
+pub fn set(values: toml::Table) -> Result<()> {
+    let mut overrides = load()?;
+    overrides.extend(values);
+
+    let path = overrides_path()?;
+    let state_dir = path.parent().context("Failed to get state directory")?;
+    fs::create_dir_all(state_dir)?;
+    write_atomic(state_dir, OVERRIDES_FILE, &toml::to_string(&overrides)?)
+        .context("Failed to write runtime overrides")
+}
+
+/// Drop all overrides. Returns whether there were any.
+pub fn clear() -> Result<bool> {
+    let path = overrides_path()?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+}
@@ -0,0 +1,85 @@
+//! Presentation mode: outputs held at neutral values until it is turned off.
+//!
+//! Turned on and off with `sunsetr presentation`, it lives in `presentation.toml` in the
+//! state directory. Every config load attaches it, so it outlasts period changes, reloads,
+//! preset switches and restarts, unlike a preview or test mode. The file's presence is the
+//! switch, and its `outputs` list names the outputs kept neutral, all of them when empty.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::get_custom_config_dir;
+use crate::state::preset::{get_state_dir, write_atomic};
+
+const PRESENTATION_FILE: &str = "presentation.toml";
+
+/// Outputs kept neutral by presentation mode.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Presentation {
+    /// Connector names, every output when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<String>,
+}
+
+impl Presentation {
+    /// Whether `output` is kept neutral.
+    pub fn covers(&self, output: &str) -> bool {
+        self.outputs.is_empty() || self.outputs.iter().any(|name| name == output)
+    }
+
+    /// The outputs for display, e.g. "DP-1, HDMI-A-1" or "all outputs".
+    pub fn describe(&self) -> String {
+        if self.outputs.is_empty() {
+            "all outputs".to_string()
+        } else {
+            self.outputs.join(", ")
+        }
+    }
+}
+
+/// Path of the presentation file for the current config directory, whether or not it exists.
+pub fn presentation_path() -> Result<PathBuf> {
+    let config_dir = get_custom_config_dir();
+    Ok(get_state_dir(config_dir.as_deref())?.join(PRESENTATION_FILE))
+}
+
+/// Presentation mode as currently set, `None` when it is off.
+pub fn load() -> Result<Option<Presentation>> {
+    let path = presentation_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read presentation mode from {}", path.display()))?;
+    toml::from_str(&content)
+        .map(Some)
+        .with_context(|| format!("Failed to parse presentation mode from {}", path.display()))
+}
+
+/// Turn presentation mode on for `outputs`, or every output when empty, replacing the
+/// outputs of an earlier call.
+pub fn enable(outputs: Vec<String>) -> Result<Presentation> {
+    let presentation = Presentation { outputs };
+    let path = presentation_path()?;
+    let state_dir = path.parent().context("Failed to get state directory")?;
+    fs::create_dir_all(state_dir)?;
+    write_atomic(
+        state_dir,
+        PRESENTATION_FILE,
+        &toml::to_string(&presentation)?,
+    )
+    .context("Failed to write presentation mode")?;
+    Ok(presentation)
+}
+
+/// Turn presentation mode off. Returns whether it was on.
+pub fn disable() -> Result<bool> {
+    let path = presentation_path()?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+}
@@ -1,25 +1,40 @@
 //! State management for sunsetr, following XDG Base Directory standards.
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::config::get_custom_config_dir;
 
-/// State lives in `XDG_STATE_HOME/sunsetr/<namespace>`, where the namespace is
-/// `default` for the default config directory or `custom_<hash>` for a custom
-/// one set via `--config`.
-pub fn get_state_dir(config_dir: Option<&Path>) -> Result<PathBuf> {
-    let state_home = std::env::var("XDG_STATE_HOME")
+/// Marker older releases kept in the config directory itself, moved into the
+/// state directory by [`migrate_legacy_state`].
+const LEGACY_ACTIVE_PRESET: &str = ".active_preset";
+
+/// A pending `preset --for` expiry, see [`PresetExpiry`].
+const PRESET_EXPIRY: &str = "preset_expiry.toml";
+
+/// `$XDG_STATE_HOME`, or `~/.local/state` when it is unset, empty or relative, as
+/// the XDG Base Directory specification asks.
+fn state_home() -> PathBuf {
+    std::env::var_os("XDG_STATE_HOME")
         .map(PathBuf::from)
-        .unwrap_or_else(|_| {
+        .filter(|path| path.is_absolute())
+        .unwrap_or_else(|| {
             dirs::home_dir()
                 .unwrap_or_else(|| PathBuf::from("/tmp"))
                 .join(".local/state")
-        });
+        })
+}
 
-    let state_base = state_home.join("sunsetr");
+/// State lives in `XDG_STATE_HOME/sunsetr/<namespace>`, where the namespace is
+/// `default` for the default config directory or `custom_<hash>` for a custom
+/// one set via `--config`. Nothing sunsetr changes at runtime is written to the
+/// config directory, so it can be mounted read-only.
+pub fn get_state_dir(config_dir: Option<&Path>) -> Result<PathBuf> {
+    let state_base = state_home().join("sunsetr");
 
     let namespace = match config_dir {
         None => "default".to_string(),
@@ -51,6 +66,7 @@ fn get_state_namespace(config_path: &Path) -> String {
 /// Returns the active preset name, or `None` when none is set or the recorded
 /// preset no longer exists on disk, clearing the stale marker in that case.
 pub fn get_active_preset() -> Result<Option<String>> {
+    migrate_legacy_state()?;
     let identity_valid = check_directory_identity()?;
 
     if !identity_valid {
@@ -91,12 +107,14 @@ pub fn get_active_preset() -> Result<Option<String>> {
     }
 }
 
+/// Return to the default config, dropping the active preset and any stacked on it.
 pub fn clear_active_preset() -> Result<()> {
     let config_dir = get_custom_config_dir();
     let state_dir = get_state_dir(config_dir.as_deref())?;
     let marker_path = state_dir.join("active_preset");
 
     let _ = fs::remove_file(&marker_path);
+    let _ = fs::remove_file(state_dir.join("preset_stack"));
     let _ = fs::remove_file(state_dir.join("dir_id"));
     Ok(())
 }
@@ -117,12 +135,330 @@ pub fn set_active_preset(preset_name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Presets pushed on top of the active preset (or the default config), bottom
+/// first. Each one overrides the settings it sets in the layers below it.
+/// Stacked presets that no longer exist on disk are dropped.
+pub fn get_preset_stack() -> Result<Vec<String>> {
+    if !check_directory_identity()? {
+        return Ok(Vec::new());
+    }
+
+    let config_dir = get_custom_config_dir();
+    let state_dir = get_state_dir(config_dir.as_deref())?;
+    let Ok(content) = fs::read_to_string(state_dir.join("preset_stack")) else {
+        return Ok(Vec::new());
+    };
+
+    let names: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+    let mut stack = Vec::with_capacity(names.len());
+    for name in &names {
+        if validate_preset_exists(name)? {
+            stack.push(name.to_string());
+        } else {
+            log_warning!("Stacked preset '{}' not found, dropping it", name);
+        }
+    }
+
+    if stack.len() != names.len() {
+        let _ = write_preset_stack(&stack);
+    }
+    Ok(stack)
+}
+
+fn write_preset_stack(stack: &[String]) -> Result<()> {
+    let config_dir = get_custom_config_dir();
+    let state_dir = get_state_dir(config_dir.as_deref())?;
+
+    if stack.is_empty() {
+        let _ = fs::remove_file(state_dir.join("preset_stack"));
+        return Ok(());
+    }
+
+    fs::create_dir_all(&state_dir)?;
+    write_directory_identity(&state_dir, config_dir.as_deref())?;
+    write_atomic(&state_dir, "preset_stack", &stack.join("\n"))
+        .context("Failed to write preset stack")
+}
+
+/// The active preset together with the presets stacked on top of it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PresetState {
+    pub base: Option<String>,
+    #[serde(default)]
+    pub stack: Vec<String>,
+}
+
+impl PresetState {
+    pub fn load() -> Result<Self> {
+        Ok(Self {
+            base: get_active_preset()?,
+            stack: get_preset_stack()?,
+        })
+    }
+
+    /// Make this the active state, replacing whatever is recorded.
+    pub fn restore(&self) -> Result<()> {
+        match &self.base {
+            Some(name) => set_active_preset(name)?,
+            None => clear_active_preset()?,
+        }
+        write_preset_stack(&self.stack)
+    }
+
+    /// Human-readable form, e.g. `day + movie`, or `default` when nothing is active.
+    pub fn label(&self) -> String {
+        let mut layers = vec![self.base.as_deref().unwrap_or("default")];
+        layers.extend(self.stack.iter().map(String::as_str));
+        layers.join(" + ")
+    }
+
+    /// This state with preset `name` called `renamed` instead, or left out when `renamed`
+    /// is `None`. Leaving out the base preset puts the stack on the default config.
+    pub fn renaming(&self, name: &str, renamed: Option<&str>) -> Self {
+        let rename = |preset: &String| {
+            if preset == name {
+                renamed.map(str::to_string)
+            } else {
+                Some(preset.clone())
+            }
+        };
+        Self {
+            base: self.base.as_ref().and_then(rename),
+            stack: self.stack.iter().filter_map(rename).collect(),
+        }
+    }
+}
+
+/// A preset switch made with `--for`: once `expires_at` passes, the running instance makes
+/// `restore`, the presets active before the switch, active again. Any later switch
+/// replaces it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresetExpiry {
+    pub expires_at: DateTime<Local>,
+    pub restore: PresetState,
+}
+
+impl PresetExpiry {
+    /// Time left at `now`, zero once it has passed.
+    pub fn remaining(&self, now: DateTime<Local>) -> std::time::Duration {
+        (self.expires_at - now).to_std().unwrap_or_default()
+    }
+
+    /// Drop the expiry and bring back the presets it restores.
+    pub fn expire(&self) -> Result<()> {
+        write_preset_expiry(None)?;
+        self.restore.restore()
+    }
+}
+
+/// The pending `preset --for` expiry, if any.
+pub fn get_preset_expiry() -> Result<Option<PresetExpiry>> {
+    if !check_directory_identity()? {
+        return Ok(None);
+    }
+
+    let config_dir = get_custom_config_dir();
+    let path = get_state_dir(config_dir.as_deref())?.join(PRESET_EXPIRY);
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    toml::from_str(&content)
+        .map(Some)
+        .with_context(|| format!("Failed to parse preset expiry from {}", path.display()))
+}
+
+fn write_preset_expiry(expiry: Option<&PresetExpiry>) -> Result<()> {
+    let config_dir = get_custom_config_dir();
+    let state_dir = get_state_dir(config_dir.as_deref())?;
+
+    let Some(expiry) = expiry else {
+        let _ = fs::remove_file(state_dir.join(PRESET_EXPIRY));
+        return Ok(());
+    };
+
+    fs::create_dir_all(&state_dir)?;
+    write_atomic(&state_dir, PRESET_EXPIRY, &toml::to_string(expiry)?)
+        .context("Failed to write preset expiry")
+}
+
+/// The recorded presets and the pending expiry, read before a preset is renamed or removed
+/// so they can follow it afterwards. Read once the preset is gone, they would already have
+/// dropped it.
+pub struct PresetReferences {
+    state: PresetState,
+    expiry: Option<PresetExpiry>,
+}
+
+impl PresetReferences {
+    pub fn load() -> Result<Self> {
+        Ok(Self {
+            state: PresetState::load()?,
+            expiry: get_preset_expiry()?,
+        })
+    }
+
+    /// Whether preset `name` is active or comes back when the pending expiry ends.
+    pub fn mention(&self, name: &str) -> bool {
+        let mentions = |state: &PresetState| state.renaming(name, None) != *state;
+        mentions(&self.state)
+            || self
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| mentions(&expiry.restore))
+    }
+
+    /// Point every reference to preset `name` at `renamed`, or drop them when that is
+    /// `None`. Returns the new active presets when they changed.
+    pub fn update(&self, name: &str, renamed: Option<&str>) -> Result<Option<PresetState>> {
+        if let Some(expiry) = &self.expiry {
+            let restore = expiry.restore.renaming(name, renamed);
+            if restore != expiry.restore {
+                write_preset_expiry(Some(&PresetExpiry {
+                    expires_at: expiry.expires_at,
+                    restore,
+                }))?;
+            }
+        }
+
+        let state = self.state.renaming(name, renamed);
+        if state == self.state {
+            return Ok(None);
+        }
+        state.restore()?;
+        Ok(Some(state))
+    }
+}
+
+/// A change of the active presets that is undone unless it is committed.
+///
+/// The state has to be written before the new presets are applied, since the
+/// running instance reads it while reloading. Dropping the switch without
+/// committing, e.g. on an early return after a failed apply, restores whatever
+/// was active before.
+#[must_use = "the switch is rolled back when dropped unless committed"]
+pub struct PresetSwitch {
+    previous: PresetState,
+    previous_expiry: Option<PresetExpiry>,
+    committed: bool,
+}
+
+impl PresetSwitch {
+    /// Make `target` the only active preset (`None` for the default config),
+    /// dropping any stacked presets. With `expires_in` the previous presets come back
+    /// after that long.
+    pub fn begin(target: Option<&str>, expires_in: Option<std::time::Duration>) -> Result<Self> {
+        Self::replace(
+            |_| PresetState {
+                base: target.map(str::to_string),
+                stack: Vec::new(),
+            },
+            expires_in,
+        )
+    }
+
+    /// Stack `name` on top of the active presets, popped again after `expires_in` when
+    /// given.
+    pub fn push(name: &str, expires_in: Option<std::time::Duration>) -> Result<Self> {
+        Self::replace(
+            |current| {
+                let mut next = current.clone();
+                next.stack.push(name.to_string());
+                next
+            },
+            expires_in,
+        )
+    }
+
+    /// Remove the topmost stacked preset, returning its name, or `None` when
+    /// nothing is stacked.
+    pub fn pop() -> Result<Option<(Self, String)>> {
+        let mut popped = None;
+        let switch = Self::replace(
+            |current| {
+                let mut next = current.clone();
+                popped = next.stack.pop();
+                next
+            },
+            None,
+        )?;
+        match popped {
+            Some(name) => Ok(Some((switch, name))),
+            None => {
+                switch.commit();
+                Ok(None)
+            }
+        }
+    }
+
+    fn replace(
+        next: impl FnOnce(&PresetState) -> PresetState,
+        expires_in: Option<std::time::Duration>,
+    ) -> Result<Self> {
+        let previous = PresetState::load()?;
+        let previous_expiry = get_preset_expiry()?;
+        let next = next(&previous);
+        if next != previous {
+            let expiry = expires_in
+                .map(|duration| -> Result<PresetExpiry> {
+                    Ok(PresetExpiry {
+                        expires_at: crate::time::source::now()
+                            + chrono::Duration::from_std(duration)?,
+                        restore: previous.clone(),
+                    })
+                })
+                .transpose()?;
+            // Before the presets, so the reload they trigger already sees it
+            write_preset_expiry(expiry.as_ref())?;
+            next.restore()?;
+        }
+        Ok(Self {
+            previous,
+            previous_expiry,
+            committed: false,
+        })
+    }
+
+    /// Put the previous presets and their expiry back.
+    fn undo(&self) -> Result<()> {
+        write_preset_expiry(self.previous_expiry.as_ref())?;
+        self.previous.restore()
+    }
+
+    /// The presets that were active before the switch.
+    pub fn previous(&self) -> &PresetState {
+        &self.previous
+    }
+
+    /// Keep the new presets active.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+
+    /// Restore the previous presets, reporting any failure to do so.
+    pub fn rollback(mut self) -> Result<()> {
+        self.committed = true;
+        self.undo()
+    }
+}
+
+impl Drop for PresetSwitch {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = self.undo();
+        }
+    }
+}
+
 /// Write `contents` to `dir/file_name` through a same-directory temp file and
 /// rename, so a concurrent reader in another process sees either the old or the
 /// new contents and never a truncated file. The running instance's config watcher
 /// reads these state files the moment they change and clears state it
 /// considers invalid, so an observable truncation loses the active preset.
-fn write_atomic(dir: &Path, file_name: &str, contents: &str) -> Result<()> {
+pub(super) fn write_atomic(dir: &Path, file_name: &str, contents: &str) -> Result<()> {
     let tmp_path = dir.join(format!(".{}.{}.tmp", file_name, std::process::id()));
 
     fs::write(&tmp_path, contents)
@@ -208,6 +544,8 @@ fn check_directory_identity() -> Result<bool> {
         Ok(meta) => meta,
         Err(_) => {
             let _ = fs::remove_file(state_dir.join("active_preset"));
+            let _ = fs::remove_file(state_dir.join("preset_stack"));
+            let _ = fs::remove_file(state_dir.join(PRESET_EXPIRY));
             let _ = fs::remove_file(&dir_id_file);
             return Ok(false);
         }
@@ -217,6 +555,8 @@ fn check_directory_identity() -> Result<bool> {
 
     if stored_id.trim() != current_id {
         let _ = fs::remove_file(state_dir.join("active_preset"));
+        let _ = fs::remove_file(state_dir.join("preset_stack"));
+        let _ = fs::remove_file(state_dir.join(PRESET_EXPIRY));
         let _ = fs::remove_file(&dir_id_file);
         return Ok(false);
     }
@@ -233,17 +573,51 @@ pub fn get_state_watch_path() -> Result<PathBuf> {
     Ok(state_dir)
 }
 
+/// Move an active preset marker left in the config directory by an older release
+/// into the state directory.
+///
+/// A marker already in the state directory wins. The legacy file is removed when
+/// the config directory is writable. Otherwise `legacy_migrated` records that it
+/// was taken over, so clearing the preset later doesn't bring it back.
+fn migrate_legacy_state() -> Result<()> {
+    let config_dir = get_custom_config_dir();
+    let legacy_dir = match &config_dir {
+        Some(path) => path.clone(),
+        None => match dirs::config_dir() {
+            Some(dir) => dir.join("sunsetr"),
+            None => return Ok(()),
+        },
+    };
+    let legacy_path = legacy_dir.join(LEGACY_ACTIVE_PRESET);
+    if !legacy_path.is_file() {
+        return Ok(());
+    }
+
+    let state_dir = get_state_dir(config_dir.as_deref())?;
+    if state_dir.join("legacy_migrated").exists() {
+        return Ok(());
+    }
+
+    let preset_name = fs::read_to_string(&legacy_path)
+        .with_context(|| format!("Failed to read {}", legacy_path.display()))?;
+    let preset_name = preset_name.trim();
+    fs::create_dir_all(&state_dir)?;
+    if !preset_name.is_empty() && !state_dir.join("active_preset").exists() {
+        write_directory_identity(&state_dir, config_dir.as_deref())?;
+        write_atomic(&state_dir, "active_preset", preset_name)
+            .context("Failed to migrate preset marker")?;
+    }
+
+    if fs::remove_file(&legacy_path).is_err() {
+        write_atomic(&state_dir, "legacy_migrated", "")
+            .context("Failed to record preset marker migration")?;
+    }
+    Ok(())
+}
+
 /// Removes state directories untouched for 90 days.
 pub fn cleanup_orphaned_state_dirs() -> Result<()> {
-    let state_home = std::env::var("XDG_STATE_HOME")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| {
-            dirs::home_dir()
-                .unwrap_or_else(|| PathBuf::from("/tmp"))
-                .join(".local/state")
-        });
-
-    let sunsetr_state = state_home.join("sunsetr");
+    let sunsetr_state = state_home().join("sunsetr");
     if !sunsetr_state.exists() {
         return Ok(());
     }
@@ -301,18 +675,14 @@ mod tests {
         });
     }
 
-    /// Replicates issue #58: one thread switches presets the way the CLI
-    /// does while another polls the way the running instance's config
-    /// watcher does. The reader must never observe a missing or invalid
-    /// active preset, and the marker must survive the storm.
-    #[test]
-    #[serial_test::serial]
-    fn preset_switching_never_reverts_under_concurrent_reads() {
+    /// Run `f` against presets `reading`, `gaming` and `movie` in throwaway config and
+    /// state directories, restoring the environment afterwards.
+    fn with_presets(f: impl FnOnce() + std::panic::UnwindSafe) {
         let temp_dir = tempfile::tempdir().unwrap();
         let config_home = temp_dir.path().join("config");
         let state_home = temp_dir.path().join("state");
         let config_dir = config_home.join("sunsetr");
-        for preset in ["reading", "gaming"] {
+        for preset in ["reading", "gaming", "movie"] {
             let preset_dir = config_dir.join("presets").join(preset);
             fs::create_dir_all(&preset_dir).unwrap();
             fs::write(preset_dir.join("sunsetr.toml"), "").unwrap();
@@ -325,7 +695,32 @@ mod tests {
             std::env::set_var("XDG_STATE_HOME", &state_home);
         }
 
-        let result = std::panic::catch_unwind(|| {
+        let result = std::panic::catch_unwind(f);
+
+        unsafe {
+            match original_config {
+                Some(val) => std::env::set_var("XDG_CONFIG_HOME", val),
+                None => std::env::remove_var("XDG_CONFIG_HOME"),
+            }
+            match original_state {
+                Some(val) => std::env::set_var("XDG_STATE_HOME", val),
+                None => std::env::remove_var("XDG_STATE_HOME"),
+            }
+        }
+
+        if let Err(panic) = result {
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    /// Replicates issue #58: one thread switches presets the way the CLI
+    /// does while another polls the way the running instance's config
+    /// watcher does. The reader must never observe a missing or invalid
+    /// active preset, and the marker must survive the storm.
+    #[test]
+    #[serial_test::serial]
+    fn preset_switching_never_reverts_under_concurrent_reads() {
+        with_presets(|| {
             set_active_preset("reading").unwrap();
 
             let failures = std::thread::scope(|s| {
@@ -354,20 +749,183 @@ mod tests {
             );
             assert_eq!(get_active_preset().unwrap().as_deref(), Some("reading"));
         });
+    }
 
-        unsafe {
-            match original_config {
-                Some(val) => std::env::set_var("XDG_CONFIG_HOME", val),
-                None => std::env::remove_var("XDG_CONFIG_HOME"),
-            }
-            match original_state {
-                Some(val) => std::env::set_var("XDG_STATE_HOME", val),
-                None => std::env::remove_var("XDG_STATE_HOME"),
-            }
-        }
+    #[test]
+    #[serial_test::serial]
+    fn uncommitted_switch_restores_the_previous_preset() {
+        with_presets(|| {
+            set_active_preset("reading").unwrap();
 
-        if let Err(panic) = result {
-            std::panic::resume_unwind(panic);
-        }
+            let switch = PresetSwitch::begin(Some("gaming"), None).unwrap();
+            assert_eq!(switch.previous().base.as_deref(), Some("reading"));
+            assert_eq!(get_active_preset().unwrap().as_deref(), Some("gaming"));
+            drop(switch);
+            assert_eq!(get_active_preset().unwrap().as_deref(), Some("reading"));
+
+            // Deactivating rolls back the same way
+            let switch = PresetSwitch::begin(None, None).unwrap();
+            assert_eq!(get_active_preset().unwrap(), None);
+            switch.rollback().unwrap();
+            assert_eq!(get_active_preset().unwrap().as_deref(), Some("reading"));
+        });
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn committed_switch_persists_and_rollback_clears_a_new_marker() {
+        with_presets(|| {
+            let switch = PresetSwitch::begin(Some("gaming"), None).unwrap();
+            assert_eq!(switch.previous().base, None);
+            switch.rollback().unwrap();
+            assert_eq!(get_active_preset().unwrap(), None);
+
+            PresetSwitch::begin(Some("gaming"), None).unwrap().commit();
+            assert_eq!(get_active_preset().unwrap().as_deref(), Some("gaming"));
+        });
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn pushed_presets_stack_and_pop_in_order() {
+        with_presets(|| {
+            set_active_preset("reading").unwrap();
+
+            PresetSwitch::push("movie", None).unwrap().commit();
+            PresetSwitch::push("gaming", None).unwrap().commit();
+            let state = PresetState::load().unwrap();
+            assert_eq!(state.base.as_deref(), Some("reading"));
+            assert_eq!(state.stack, ["movie", "gaming"]);
+            assert_eq!(state.label(), "reading + movie + gaming");
+
+            let (switch, popped) = PresetSwitch::pop().unwrap().unwrap();
+            assert_eq!(popped, "gaming");
+            switch.commit();
+            assert_eq!(get_preset_stack().unwrap(), ["movie"]);
+
+            // A flat switch replaces the whole stack
+            PresetSwitch::begin(Some("gaming"), None).unwrap().commit();
+            assert!(get_preset_stack().unwrap().is_empty());
+            assert!(PresetSwitch::pop().unwrap().is_none());
+        });
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn failed_push_and_flat_switch_restore_the_stack() {
+        with_presets(|| {
+            PresetSwitch::push("movie", None).unwrap().commit();
+            assert_eq!(PresetState::load().unwrap().label(), "default + movie");
+
+            drop(PresetSwitch::push("gaming", None).unwrap());
+            assert_eq!(get_preset_stack().unwrap(), ["movie"]);
+
+            drop(PresetSwitch::begin(Some("reading"), None).unwrap());
+            let state = PresetState::load().unwrap();
+            assert_eq!(state.base, None);
+            assert_eq!(state.stack, ["movie"]);
+        });
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn timed_switch_records_what_to_restore_until_replaced() {
+        with_presets(|| {
+            set_active_preset("reading").unwrap();
+            let three_hours = std::time::Duration::from_secs(3 * 3600);
+
+            PresetSwitch::begin(Some("gaming"), Some(three_hours))
+                .unwrap()
+                .commit();
+            let expiry = get_preset_expiry().unwrap().unwrap();
+            assert_eq!(expiry.restore.label(), "reading");
+            let remaining = expiry.remaining(crate::time::source::now());
+            assert!(remaining > three_hours - std::time::Duration::from_secs(60));
+
+            // A failed switch puts the timer back along with the presets
+            drop(PresetSwitch::push("movie", None).unwrap());
+            assert_eq!(get_preset_expiry().unwrap(), Some(expiry.clone()));
+
+            expiry.expire().unwrap();
+            assert_eq!(get_active_preset().unwrap().as_deref(), Some("reading"));
+            assert_eq!(get_preset_expiry().unwrap(), None);
+
+            PresetSwitch::push("movie", Some(three_hours))
+                .unwrap()
+                .commit();
+            PresetSwitch::begin(Some("gaming"), None).unwrap().commit();
+            assert_eq!(get_preset_expiry().unwrap(), None);
+        });
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn references_follow_a_renamed_or_removed_preset() {
+        with_presets(|| {
+            set_active_preset("reading").unwrap();
+            PresetSwitch::push("movie", Some(std::time::Duration::from_secs(3600)))
+                .unwrap()
+                .commit();
+            let config_home = std::env::var("XDG_CONFIG_HOME").unwrap();
+            let presets = Path::new(&config_home).join("sunsetr/presets");
+
+            let references = PresetReferences::load().unwrap();
+            assert!(references.mention("reading"));
+            assert!(!references.mention("gaming"));
+            fs::rename(presets.join("reading"), presets.join("books")).unwrap();
+            let state = references.update("reading", Some("books")).unwrap();
+            assert_eq!(state.unwrap().label(), "books + movie");
+            assert_eq!(PresetState::load().unwrap().label(), "books + movie");
+            assert_eq!(
+                get_preset_expiry().unwrap().unwrap().restore.label(),
+                "books"
+            );
+
+            let references = PresetReferences::load().unwrap();
+            fs::remove_dir_all(presets.join("books")).unwrap();
+            references.update("books", None).unwrap();
+            assert_eq!(PresetState::load().unwrap().label(), "default + movie");
+            assert_eq!(
+                get_preset_expiry().unwrap().unwrap().restore.label(),
+                "default"
+            );
+
+            let references = PresetReferences::load().unwrap();
+            assert_eq!(references.update("gaming", None).unwrap(), None);
+        });
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn stacked_presets_missing_on_disk_are_dropped() {
+        with_presets(|| {
+            PresetSwitch::push("movie", None).unwrap().commit();
+            PresetSwitch::push("gaming", None).unwrap().commit();
+
+            let config_home = std::env::var("XDG_CONFIG_HOME").unwrap();
+            fs::remove_dir_all(Path::new(&config_home).join("sunsetr/presets/movie")).unwrap();
+
+            assert_eq!(get_preset_stack().unwrap(), ["gaming"]);
+        });
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn legacy_marker_in_the_config_directory_is_migrated() {
+        with_presets(|| {
+            let config_home = std::env::var("XDG_CONFIG_HOME").unwrap();
+            let legacy_path = Path::new(&config_home)
+                .join("sunsetr")
+                .join(LEGACY_ACTIVE_PRESET);
+            fs::write(&legacy_path, "gaming\n").unwrap();
+
+            assert_eq!(get_active_preset().unwrap().as_deref(), Some("gaming"));
+            assert!(!legacy_path.exists());
+
+            // A marker in the state directory is newer than any legacy one
+            fs::write(&legacy_path, "movie").unwrap();
+            set_active_preset("reading").unwrap();
+            assert_eq!(get_active_preset().unwrap().as_deref(), Some("reading"));
+        });
     }
 }
@@ -0,0 +1,150 @@
+//! Value offsets: relative adjustments to the scheduled values set with `sunsetr adjust`.
+//!
+//! They live in `adjustment.toml` in the state directory and are attached to every config
+//! load like presentation mode, so they carry through reloads and restarts while the config
+//! files stay as they are. An offset lasts until `adjust reset` or the next day, which
+//! begins with the next sunrise, or at midnight for a schedule without one.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::common::units::{GammaPercent, Kelvin};
+use crate::config::get_custom_config_dir;
+use crate::state::preset::{get_state_dir, write_atomic};
+
+const ADJUSTMENT_FILE: &str = "adjustment.toml";
+
+/// Offsets added to the scheduled temperature and gamma until `expires_at`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ValueOffset {
+    /// Kelvin added to the scheduled temperature.
+    #[serde(default)]
+    pub temp: i32,
+    /// Percentage points added to the scheduled gamma.
+    #[serde(default)]
+    pub gamma: f64,
+    pub expires_at: DateTime<Local>,
+}
+
+impl ValueOffset {
+    /// Whether the offset still applies at `now`.
+    pub fn is_active(&self, now: DateTime<Local>) -> bool {
+        now < self.expires_at
+    }
+
+    /// Whether it leaves both values as they are.
+    pub fn is_zero(&self) -> bool {
+        self.temp == 0 && self.gamma == 0.0
+    }
+
+    /// `values` moved by the offsets, stopping at the ends of the ranges.
+    pub fn apply(&self, (temp, gamma): (Kelvin, GammaPercent)) -> (Kelvin, GammaPercent) {
+        (temp.offset(self.temp), gamma.offset(self.gamma))
+    }
+
+    /// The offsets for display, e.g. "+500K, -10%", leaving out one that is zero.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.temp != 0 || self.gamma == 0.0 {
+            parts.push(format!("{:+}K", self.temp));
+        }
+        if self.gamma != 0.0 {
+            parts.push(format!("{:+}%", self.gamma));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Path of the offset file for the current config directory, whether or not it exists.
+pub fn offset_path() -> Result<PathBuf> {
+    let config_dir = get_custom_config_dir();
+    Ok(get_state_dir(config_dir.as_deref())?.join(ADJUSTMENT_FILE))
+}
+
+/// The offset as last set, `None` when there is none. It may have expired already.
+pub fn load() -> Result<Option<ValueOffset>> {
+    let path = offset_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read adjustment from {}", path.display()))?;
+    toml::from_str(&content)
+        .map(Some)
+        .with_context(|| format!("Failed to parse adjustment from {}", path.display()))
+}
+
+/// Replace the offset with `offset`.
+pub fn save(offset: &ValueOffset) -> Result<()> {
+    let path = offset_path()?;
+    let state_dir = path.parent().context("Failed to get state directory")?;
+    fs::create_dir_all(state_dir)?;
+    write_atomic(state_dir, ADJUSTMENT_FILE, &toml::to_string(offset)?)
+        .context("Failed to write adjustment")
+}
+
+/// Drop the offset. Returns whether there was one.
+pub fn clear() -> Result<bool> {
+    let path = offset_path()?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::units::values;
+    use chrono::TimeZone;
+
+    fn offset(temp: i32, gamma: f64) -> ValueOffset {
+        ValueOffset {
+            temp,
+            gamma,
+            expires_at: Local.with_ymd_and_hms(2024, 6, 22, 4, 45, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn offsets_move_values_within_range() {
+        assert_eq!(
+            offset(500, -10.0).apply(values(3300, 90.0)),
+            values(3800, 80.0)
+        );
+        assert_eq!(
+            offset(-3000, -95.0).apply(values(3300, 90.0)),
+            values(1000, 10.0)
+        );
+        assert_eq!(
+            offset(20_000, 0.0).apply(values(6500, 100.0)),
+            values(20000, 100.0)
+        );
+    }
+
+    #[test]
+    fn expires_at_the_stored_time() {
+        let offset = offset(500, 0.0);
+        assert!(offset.is_active(offset.expires_at - chrono::TimeDelta::seconds(1)));
+        assert!(!offset.is_active(offset.expires_at));
+    }
+
+    #[test]
+    fn describes_the_offsets_set() {
+        assert_eq!(offset(500, -10.0).describe(), "+500K, -10%");
+        assert_eq!(offset(-300, 0.0).describe(), "-300K");
+        assert_eq!(offset(0, 2.5).describe(), "+2.5%");
+        assert_eq!(offset(0, 0.0).describe(), "+0K");
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let offset = offset(500, -10.0);
+        let text = toml::to_string(&offset).unwrap();
+        assert_eq!(toml::from_str::<ValueOffset>(&text).unwrap(), offset);
+    }
+}
@@ -12,14 +12,23 @@
 //! The `Sunsetr` struct uses a builder pattern to support different startup contexts:
 //! - Normal startup: `Sunsetr::new(debug_enabled).run()`
 //! - Simulation: `Sunsetr::new(debug_enabled).without_lock().without_headers().run()`
+//! - Profiling: `Sunsetr::new(debug_enabled).profile(true).run()`
+//! - Safe mode: `Sunsetr::new(debug_enabled).safe_mode(true).run()`
+//! - Dry run: `Sunsetr::new(debug_enabled).dry_run(true).run()`
 
 use anyhow::{Context, Result};
 
 use crate::{
-    backend::{create_backend, detect_backend},
-    common::utils::TerminalGuard,
-    config::{self, Config},
-    core::{Core, CoreParams},
+    backend::{
+        BackendType, ColorTemperatureBackend, Session, create_backend, detect_backend, failover,
+        probe,
+        profiled::ProfiledBackend,
+        rediscover,
+        worker::{Attempt, BackendWorker},
+    },
+    common::{error::Silent, profile, utils::TerminalGuard},
+    config::{self, Backend, Config, TransitionMode, loading::MissingCoordinates},
+    core::{Core, CoreParams, history::AppliedHistory},
     geo::times::GeoTimes,
     io::dbus,
     io::signals::setup_signal_handler,
@@ -36,6 +45,9 @@ pub struct Sunsetr {
     show_headers: bool,
     bypass_smoothing: bool,
     background: bool,
+    profile: bool,
+    safe_mode: bool,
+    dry_run: bool,
 }
 
 impl Sunsetr {
@@ -47,6 +59,9 @@ impl Sunsetr {
             show_headers: true,
             bypass_smoothing: false,
             background: false,
+            profile: false,
+            safe_mode: false,
+            dry_run: false,
         }
     }
 
@@ -73,6 +88,25 @@ impl Sunsetr {
         self
     }
 
+    /// Record loop and backend timings and print a profile report on exit
+    pub fn profile(mut self, profile: bool) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Start with neutral values instead of exiting when the config fails to load. The
+    /// config watcher applies the real config once it loads again.
+    pub fn safe_mode(mut self, safe_mode: bool) -> Self {
+        self.safe_mode = safe_mode;
+        self
+    }
+
+    /// Use the mock backend whatever the config says, so nothing reaches the display.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
     /// Execute the application with the configured settings.
     ///
     /// This method handles the complete application lifecycle including:
@@ -84,25 +118,87 @@ impl Sunsetr {
     /// - Main application loop
     /// - Graceful shutdown and cleanup
     ///
-    /// If background mode is enabled, spawns a background instance instead.
-    pub fn run(self) -> Result<()> {
-        if self.background {
-            if self.show_headers {
-                log_version!();
-            }
+    /// If background mode is enabled, spawns a background instance instead and waits
+    /// until it reports that it started, or why it didn't.
+    pub fn run(mut self) -> Result<()> {
+        if self.background && crate::io::readiness::under_systemd() {
+            // systemd already keeps the service apart from whatever started it
+            self.background = false;
+        } else if self.background {
+            return self.run_in_background();
+        }
 
-            if let Ok(Some(_)) = crate::io::instance::get_running_instance() {
-                crate::io::instance::handle_instance_conflict(
-                    &crate::io::lock::get_main_lock_path(),
-                    self.debug_enabled,
-                )?;
-            }
+        // A background start waits to hear how this one went
+        if self.create_lock {
+            crate::io::readiness::connect();
+        }
+        let result = self.run_instance();
+        if let Err(e) = &result {
+            crate::io::readiness::report_failure(e);
+        }
+        result
+    }
 
-            let result = crate::io::instance::spawn_background_instance(self.debug_enabled);
+    fn run_in_background(self) -> Result<()> {
+        if self.show_headers {
+            log_version!();
+        }
+
+        if let Ok(Some(_)) = crate::io::instance::get_running_instance() {
+            crate::io::instance::handle_instance_conflict(
+                &crate::io::lock::get_main_lock_path(),
+                self.debug_enabled,
+            )?;
+        }
+
+        let waiter = crate::io::readiness::Waiter::listen()?;
+        if let Err(e) = crate::io::instance::spawn_background_instance(self.debug_enabled) {
             log_end!();
-            return result;
+            return Err(e);
         }
+        log_decorated!("Waiting for it to apply its first values...");
 
+        use crate::io::readiness::Outcome;
+        match waiter.wait() {
+            Outcome::Ready(pid) => {
+                log_block_start!("sunsetr is running in the background (PID {pid})");
+                log_end!();
+                Ok(())
+            }
+            Outcome::Failed(error) => {
+                log_pipe!();
+                log_error!("The background instance failed to start:");
+                for line in error.lines() {
+                    log_indented!("{}", line.trim_start());
+                }
+                log_end!();
+                Err(Silent.into())
+            }
+            Outcome::Exited => {
+                log_error_end!("The background instance exited during startup");
+                Err(Silent.into())
+            }
+            Outcome::NotStarted => {
+                log_error_end!(
+                    "The background instance did not start within {} seconds",
+                    crate::io::readiness::CONNECT_TIMEOUT.as_secs()
+                );
+                Err(Silent.into())
+            }
+            Outcome::StillStarting => {
+                log_pipe!();
+                log_warning!(
+                    "The background instance is still starting after {} seconds",
+                    crate::io::readiness::READY_TIMEOUT.as_secs()
+                );
+                log_indented!("Check on it with: sunsetr status");
+                log_end!();
+                Ok(())
+            }
+        }
+    }
+
+    fn run_instance(self) -> Result<()> {
         let _ = crate::state::preset::cleanup_orphaned_state_dirs();
 
         if self.show_headers {
@@ -125,8 +221,32 @@ impl Sunsetr {
         }
 
         let _term = TerminalGuard::new().context("failed to initialize terminal features")?;
-        let config = Config::load()?;
+        let (mut config, in_safe_mode) = match Config::load() {
+            Ok(config) => (config, false),
+            Err(e) if e.downcast_ref::<MissingCoordinates>().is_some() => {
+                // Secrets managers like sops-nix and agenix may only write geo.toml after
+                // sunsetr starts. The config watcher reloads into geo mode once it appears.
+                log_pipe!();
+                log_warning!("Geo mode has no coordinates yet, starting with neutral values");
+                log_indented!("Waiting for geo.toml to provide them");
+                log_indented!("Or select a location with: sunsetr geo");
+                (Config::safe_mode(), true)
+            }
+            Err(e) if self.safe_mode => {
+                log_pipe!();
+                crate::common::error::log_error_chain("Failed to load config", &e);
+                log_pipe!();
+                log_warning!("Starting in safe mode with neutral values");
+                log_indented!("The config will be applied as soon as it is fixed");
+                (Config::safe_mode(), true)
+            }
+            Err(e) => return Err(e),
+        };
+        if self.dry_run {
+            config.backend = Backend::None;
+        }
         let backend_type = detect_backend(&config)?;
+        crate::io::crash::note_backend(backend_type.name());
 
         let (lock_file, lock_path) = if self.create_lock {
             match crate::io::instance::ensure_single_instance()? {
@@ -137,6 +257,14 @@ impl Sunsetr {
             (None, None)
         };
 
+        if let Some(outer) = crate::io::nested::parent_display() {
+            log_pipe!();
+            log_info!("Nested inside the compositor on {outer}");
+            log_indented!(
+                "Using a lock file and IPC socket of its own, leaving the outer instance be"
+            );
+        }
+
         let signal_state = setup_signal_handler(self.debug_enabled)?;
 
         if let Err(e) = dbus::start_sleep_resume_monitor(
@@ -152,6 +280,14 @@ impl Sunsetr {
             log_indented!("This is normal in environments without systemd or D-Bus");
         }
 
+        // A simulation's pace shouldn't depend on the battery
+        if !crate::time::source::is_simulated() {
+            crate::io::power::start_power_profile_monitor(
+                signal_state.signal_sender.clone(),
+                self.debug_enabled,
+            );
+        }
+
         if let Err(e) = config::start_config_watcher(
             signal_state.signal_sender.clone(),
             signal_state.interrupt.clone(),
@@ -163,49 +299,58 @@ impl Sunsetr {
             log_indented!("Hot config reload disabled, use SIGUSR2 for manual reload");
         }
 
-        config.log_config(Some(backend_type));
+        if !in_safe_mode {
+            config.log_config(Some(backend_type));
+        }
 
         let geo_times =
             GeoTimes::from_config(&config).context("Failed to initialize geo transition times")?;
         log_block_start!("Detected backend: {}", backend_type.name());
 
+        // The first state needs darkman's mode before the schedule is asked for a period
+        if config.transition_mode == TransitionMode::Darkman && !crate::time::source::is_simulated()
+        {
+            crate::io::darkman::start_mode_monitor(
+                signal_state.signal_sender.clone(),
+                self.debug_enabled,
+            );
+        }
+        if let Some(media) = &config.media
+            && !crate::time::source::is_simulated()
+        {
+            crate::io::idle::start_media_monitor(
+                signal_state.signal_sender.clone(),
+                media.detect_after,
+                self.debug_enabled,
+            );
+        }
         let schedule = crate::core::schedule::Schedule::from_config(&config, geo_times.clone());
-        let now = crate::time::source::now();
-        let initial_period = schedule
-            .as_ref()
-            .map_or(crate::core::period::Period::Static, |schedule| {
-                schedule.current_period(now)
-            });
-        let runtime_state =
-            crate::core::runtime_state::RuntimeState::new(initial_period, &config, schedule, now);
+        let runtime_state = crate::core::runtime_state::RuntimeState::at(
+            &config,
+            schedule,
+            crate::time::source::now(),
+        );
 
         let (initial_temp, initial_gamma) = runtime_state.values();
 
-        let backend = create_backend(
-            backend_type,
-            &config,
-            self.debug_enabled,
-            geo_times.as_ref(),
-            Some((initial_temp, initial_gamma)),
-        )?;
-
-        let lock_info = if let (Some(lock_file), Some(lock_path)) = (lock_file, lock_path) {
-            log_block_start!("Lock acquired, starting sunsetr...");
-            Some((lock_file, lock_path))
-        } else {
-            if !crate::time::source::is_simulated() {
-                log_block_start!("Restarting sunsetr...");
-            }
-            None
-        };
+        if self.profile {
+            profile::enable();
+        }
 
-        let (ipc_notifier, ipc_server) = if crate::time::source::is_simulated() {
-            (None, None)
-        } else {
-            let (notifier, state_receiver) = crate::state::ipc::IpcNotifier::new();
+        // Started before the backend, whose worker reports hotplug events through it. Clients
+        // following a restarted instance reconnect as soon as the socket is back, so it
+        // shouldn't wait on a slow compositor connection.
+        let history = AppliedHistory::default();
+        let ipc_channel =
+            (!crate::time::source::is_simulated()).then(crate::state::ipc::IpcNotifier::new);
+        let (ipc_notifier, ipc_server) = if let Some((notifier, state_receiver)) = ipc_channel {
             let server = crate::state::ipc::IpcServer::start(
                 state_receiver,
+                signal_state.signal_sender.clone(),
+                history.clone(),
+                config.ipc_allowed_gids.clone(),
                 signal_state.running.clone(),
+                signal_state.restarting.clone(),
                 self.debug_enabled,
             )
             .context("Failed to start IPC server")?;
@@ -213,6 +358,94 @@ impl Sunsetr {
                 log_debug!("IPC server started successfully");
             }
             (Some(notifier), Some(server))
+        } else {
+            (None, None)
+        };
+
+        // The backend lives on its own thread so slow applies never stall the main loop
+        let backend = BackendWorker::spawn(
+            {
+                let config = config.clone();
+                let geo_times = geo_times.clone();
+                let debug_enabled = self.debug_enabled;
+                let profile = self.profile;
+                let mut backend_type = backend_type;
+                let choice = config.backend;
+                let create = move |backend_type| -> Result<Box<dyn ColorTemperatureBackend>> {
+                    let backend = create_backend(
+                        backend_type,
+                        &config,
+                        debug_enabled,
+                        geo_times.as_ref(),
+                        Some((initial_temp, initial_gamma)),
+                    )?;
+                    Ok(if profile {
+                        Box::new(ProfiledBackend::new(backend)) as Box<dyn ColorTemperatureBackend>
+                    } else {
+                        backend
+                    })
+                };
+                move |attempt| {
+                    match attempt {
+                        Attempt::Start => {}
+                        // A compositor restart may have changed the protocols on offer. X
+                        // servers, DRM cards, the D-Bus interfaces of KWin and Mutter and
+                        // the mock backend have none to probe
+                        Attempt::Reconnect
+                            if !matches!(
+                                backend_type,
+                                BackendType::X11
+                                    | BackendType::Drm
+                                    | BackendType::Kde
+                                    | BackendType::Gnome
+                                    | BackendType::Mock
+                            ) =>
+                        {
+                            rediscover::rediscover()?;
+                            let protocols = probe::probe_protocols()?;
+                            backend_type =
+                                probe::select_backend(choice, &Session::from_env(), &protocols)
+                                    .context("Compositor advertises no usable gamma protocol")?;
+                        }
+                        Attempt::Reconnect => {}
+                        // An explicit choice is kept, failing as it always did
+                        Attempt::Failover if choice != Backend::Auto => {
+                            anyhow::bail!("backend = \"{choice}\" is set explicitly");
+                        }
+                        Attempt::Failover => {
+                            return failover::fail_over(&mut backend_type, &create);
+                        }
+                    }
+                    create(backend_type)
+                }
+            },
+            ipc_notifier.clone(),
+        );
+        let backend = match backend {
+            Ok(backend) => backend,
+            Err(e) => {
+                signal_state
+                    .running
+                    .store(false, std::sync::atomic::Ordering::SeqCst);
+                if let Some(server) = ipc_server {
+                    let _ = server.shutdown();
+                }
+                if !e.is::<Silent>() {
+                    crate::io::crash::report_fatal_error(&e);
+                }
+                return Err(e);
+            }
+        };
+        let backend: Box<dyn ColorTemperatureBackend> = Box::new(backend);
+
+        let lock_info = if let (Some(lock_file), Some(lock_path)) = (lock_file, lock_path) {
+            log_block_start!("Lock acquired, starting sunsetr...");
+            Some((lock_file, lock_path))
+        } else {
+            if !crate::time::source::is_simulated() {
+                log_block_start!("Restarting sunsetr...");
+            }
+            None
         };
 
         let core = Core::new(CoreParams {
@@ -223,9 +456,15 @@ impl Sunsetr {
             lock_info,
             bypass_smoothing: self.bypass_smoothing,
             ipc_notifier,
+            history,
         });
 
         let result = core.execute();
+        if let Err(e) = &result
+            && !e.is::<Silent>()
+        {
+            crate::io::crash::report_fatal_error(e);
+        }
 
         if let Some(server) = ipc_server
             && let Err(e) = server.shutdown()
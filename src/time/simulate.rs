@@ -2,13 +2,28 @@
 //!
 //! Runs the application itself under an accelerated time source, so transitions
 //! and geo calculations play out without waiting for wall-clock time.
+//!
+//! With `--log`, every main loop iteration also writes a [`IterationRecord`] to the file,
+//! one JSON object per line after [`RECORD_PREFIX`], so tools reading the log don't have
+//! to parse the human-readable lines around it.
+//!
+//! `--format json` skips the application and the backend. It fast-forwards through the
+//! same schedule and value code and prints each update the daemon would apply.
 
 use crate::common::logger::LoggerGuard;
+use crate::common::units::{GammaPercent, Kelvin};
 use crate::common::utils::ProgressBar;
+use crate::config::{Config, TransitionMode};
+use crate::core::period::Period;
+use crate::core::runtime_state::RuntimeState;
+use crate::core::schedule::Schedule;
+use crate::geo::times::GeoTimes;
 use crate::io::instance::get_running_instance_pid;
 use crate::time::source::{SimulatedTimeSource, SimulationPace, TimeSource};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::io::Write;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
@@ -78,6 +93,49 @@ impl Drop for SimulationGuards {
     }
 }
 
+/// Simulated start and end, kept both as parsed for display and converted to Local for the
+/// time source. In geo mode these differ in timezone.
+struct SimulationRange {
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    geo_tz: Option<chrono_tz::Tz>,
+    display_start: String,
+    display_end: String,
+}
+
+impl SimulationRange {
+    /// Parse the times in the configured location's timezone in geo mode, else locally.
+    fn parse(start_time: &str, end_time: &str, config: Option<&Config>) -> Result<Self> {
+        let geo_tz = config
+            .filter(|config| config.transition_mode == TransitionMode::Geo)
+            .and_then(|config| Some((config.latitude?, config.longitude?)))
+            .map(|(lat, lon)| crate::geo::solar::determine_timezone(lat, lon));
+
+        let parse = |text: &str, label: &str| -> Result<(DateTime<Local>, String)> {
+            let parsed = match geo_tz {
+                Some(tz) => crate::time::source::parse_datetime_in_tz(text, tz).map(|time| {
+                    let display = time.format("%Y-%m-%d %H:%M:%S").to_string();
+                    // Keep the instant, the time source runs in Local
+                    (time.with_timezone(&Local), display)
+                }),
+                None => crate::time::source::parse_datetime(text)
+                    .map(|time| (time, time.format("%Y-%m-%d %H:%M:%S").to_string())),
+            };
+            parsed.map_err(|e| anyhow::anyhow!("Invalid {} time: {}", label, e))
+        };
+
+        let (start, display_start) = parse(start_time, "start")?;
+        let (end, display_end) = parse(end_time, "end")?;
+        Ok(Self {
+            start,
+            end,
+            geo_tz,
+            display_start,
+            display_end,
+        })
+    }
+}
+
 /// Prepares the simulation environment and returns guards that must be kept
 /// alive while the caller runs the application under the installed time source.
 pub fn setup_simulation(
@@ -116,68 +174,27 @@ pub fn setup_simulation(
         );
         std::process::exit(1);
     }
-    // Keep both the parsed times for display and the Local-converted times for
-    // the simulation. In geo mode these differ in timezone.
-    let (start, end, geo_tz_opt, display_start, display_end) = if let Ok(config) = &loaded_config {
-        if config.transition_mode == crate::config::TransitionMode::Geo {
-            if let (Some(lat), Some(lon)) = (config.latitude, config.longitude) {
-                let geo_tz = crate::geo::solar::determine_timezone(lat, lon);
-
-                let start_tz = crate::time::source::parse_datetime_in_tz(&start_time, geo_tz)
-                    .map_err(|e| anyhow::anyhow!("Invalid start time: {}", e))?;
-                let end_tz = crate::time::source::parse_datetime_in_tz(&end_time, geo_tz)
-                    .map_err(|e| anyhow::anyhow!("Invalid end time: {}", e))?;
-
-                // Convert to Local for SimulatedTimeSource while preserving the instant.
-                let start_local = start_tz.with_timezone(&Local);
-                let end_local = end_tz.with_timezone(&Local);
-
-                (
-                    start_local,
-                    end_local,
-                    Some(geo_tz),
-                    start_tz.format("%Y-%m-%d %H:%M:%S").to_string(),
-                    end_tz.format("%Y-%m-%d %H:%M:%S").to_string(),
-                )
-            } else {
-                let start = crate::time::source::parse_datetime(&start_time)
-                    .map_err(|e| anyhow::anyhow!("Invalid start time: {}", e))?;
-                let end = crate::time::source::parse_datetime(&end_time)
-                    .map_err(|e| anyhow::anyhow!("Invalid end time: {}", e))?;
-                (
-                    start,
-                    end,
-                    None,
-                    start.format("%Y-%m-%d %H:%M:%S").to_string(),
-                    end.format("%Y-%m-%d %H:%M:%S").to_string(),
-                )
-            }
-        } else {
-            let start = crate::time::source::parse_datetime(&start_time)
-                .map_err(|e| anyhow::anyhow!("Invalid start time: {}", e))?;
-            let end = crate::time::source::parse_datetime(&end_time)
-                .map_err(|e| anyhow::anyhow!("Invalid end time: {}", e))?;
-            (
-                start,
-                end,
-                None,
-                start.format("%Y-%m-%d %H:%M:%S").to_string(),
-                end.format("%Y-%m-%d %H:%M:%S").to_string(),
-            )
-        }
-    } else {
-        let start = crate::time::source::parse_datetime(&start_time)
-            .map_err(|e| anyhow::anyhow!("Invalid start time: {}", e))?;
-        let end = crate::time::source::parse_datetime(&end_time)
-            .map_err(|e| anyhow::anyhow!("Invalid end time: {}", e))?;
-        (
-            start,
-            end,
-            None,
-            start.format("%Y-%m-%d %H:%M:%S").to_string(),
-            end.format("%Y-%m-%d %H:%M:%S").to_string(),
+
+    if let Ok(config) = &loaded_config
+        && matches!(
+            config.transition_mode,
+            crate::config::TransitionMode::External | crate::config::TransitionMode::Darkman
         )
-    };
+    {
+        log_version!();
+        log_error_end!(
+            "Cannot run simulation in {} transition mode\n   Another program decides the values as things happen\n   There is no schedule to simulate",
+            config.transition_mode
+        );
+        std::process::exit(1);
+    }
+    let SimulationRange {
+        start,
+        end,
+        geo_tz: geo_tz_opt,
+        display_start,
+        display_end,
+    } = SimulationRange::parse(&start_time, &end_time, loaded_config.as_ref().ok())?;
 
     if end <= start {
         log_error_end!("End time must be after start time");
@@ -294,6 +311,125 @@ pub fn run_simulation(
     Ok(())
 }
 
+/// One update in the `--format json` timeline.
+#[derive(Debug, Serialize)]
+struct SimulatedUpdate {
+    time: DateTime<Local>,
+    period: Period,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<f32>,
+    temp: Kelvin,
+    gamma: GammaPercent,
+}
+
+/// Print every update between `start_time` and `end_time` as one JSON object per line.
+///
+/// Time advances by the daemon's own sleep after each update, and values come from
+/// [`RuntimeState::values`], so the timeline matches what a running instance applies.
+pub fn run_json_timeline(start_time: String, end_time: String) -> Result<()> {
+    let config = Config::load()?;
+    let range = SimulationRange::parse(&start_time, &end_time, Some(&config))?;
+    if range.end <= range.start {
+        anyhow::bail!("End time must be after start time");
+    }
+
+    let source = Arc::new(SimulatedTimeSource::new(
+        range.start,
+        range.end,
+        SimulationPace::FastForward,
+    ));
+    crate::time::source::init_time_source(source.clone());
+
+    let geo_times =
+        GeoTimes::from_config(&config).context("Failed to initialize geo transition times")?;
+    let schedule = Schedule::from_config(&config, geo_times);
+    let mut state = RuntimeState::at(&config, schedule, source.now());
+
+    // Period changes log as they would in the daemon, which would interleave with the JSON
+    crate::common::logger::Log::set_enabled(false);
+    let mut stdout = std::io::stdout().lock();
+    loop {
+        let (temp, gamma) = state.values();
+        let update = SimulatedUpdate {
+            time: source.now(),
+            period: state.period(),
+            progress: state.phase().progress(),
+            temp,
+            gamma,
+        };
+        writeln!(stdout, "{}", serde_json::to_string(&update)?)?;
+
+        let wait = state.time_until_next_update();
+        if source.is_ended() || wait == Duration::MAX {
+            break;
+        }
+        // A zero wait at a period boundary would never advance the clock
+        source.sleep(wait.max(Duration::from_secs(1)));
+        state = state.with_current_period().0;
+    }
+    Ok(())
+}
+
+/// Marks the lines of a `--log` file that hold an [`IterationRecord`].
+pub const RECORD_PREFIX: &str = "@sim ";
+
+/// What a main loop iteration did before going to sleep.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Decision {
+    /// The first iteration, right after the initial state was applied.
+    Starting,
+    /// A config reload was just applied.
+    ReloadApplied,
+    /// Mid-transition, but the update interval has not passed yet.
+    Waiting,
+    /// The schedule had nothing new to apply.
+    NoChange,
+    /// The schedule moved and the new state was applied.
+    Update,
+    /// The schedule moved but the backend failed to apply it.
+    ApplyFailed,
+    /// A transition ended and the loop moved on to the next period without sleeping.
+    Boundary,
+}
+
+/// One main loop iteration, as written to the `--log` file.
+#[derive(Debug, Serialize)]
+pub(crate) struct IterationRecord {
+    pub iteration: u64,
+    pub time: DateTime<Local>,
+    pub decision: Decision,
+    pub period: Period,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<f32>,
+    /// Values sent to the backend this iteration, `None` when nothing was applied.
+    pub applied: Option<AppliedValues>,
+    /// How long the loop sleeps next, `None` when it goes straight to the next iteration.
+    pub sleep_secs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub(crate) struct AppliedValues {
+    pub temp: Kelvin,
+    pub gamma: GammaPercent,
+}
+
+impl IterationRecord {
+    fn to_line(&self) -> Result<String> {
+        Ok(format!("{RECORD_PREFIX}{}\n", serde_json::to_string(self)?))
+    }
+}
+
+/// Write `record` to the simulation log, doing nothing unless running with `--log`.
+pub(crate) fn record_iteration(record: &IterationRecord) {
+    if !crate::common::logger::Log::is_file_logging() {
+        return;
+    }
+    if let Ok(line) = record.to_line() {
+        crate::common::logger::write_file_only(&line);
+    }
+}
+
 /// Spawns a thread that renders the simulation progress bar.
 ///
 /// It writes straight to stdout, bypassing the logger channel, so the bar stays
@@ -396,3 +532,49 @@ fn log_simulation_details(
         log_indented!("Note: Actual time may vary due to system and processing overhead");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn records_are_prefixed_json_lines() {
+        let record = IterationRecord {
+            iteration: 3,
+            time: Local.with_ymd_and_hms(2025, 1, 15, 18, 45, 0).unwrap(),
+            decision: Decision::Update,
+            period: Period::Sunset,
+            progress: Some(0.5),
+            applied: Some(AppliedValues {
+                temp: Kelvin::new(4378).unwrap(),
+                gamma: GammaPercent::new(95.0).unwrap(),
+            }),
+            sleep_secs: Some(60.0),
+        };
+        let line = record.to_line().unwrap();
+        assert!(line.ends_with('\n') && !line.trim_end().contains('\n'));
+
+        let json: serde_json::Value =
+            serde_json::from_str(line.strip_prefix(RECORD_PREFIX).unwrap()).unwrap();
+        assert_eq!(json["iteration"], 3);
+        assert_eq!(json["decision"], "update");
+        assert_eq!(json["period"], "sunset");
+        assert_eq!(json["applied"]["temp"], 4378);
+        assert_eq!(json["sleep_secs"], 60.0);
+
+        let idle = IterationRecord {
+            decision: Decision::Boundary,
+            progress: None,
+            applied: None,
+            sleep_secs: None,
+            ..record
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(idle.to_line().unwrap().strip_prefix(RECORD_PREFIX).unwrap())
+                .unwrap();
+        assert_eq!(json["decision"], "boundary");
+        assert!(json["applied"].is_null() && json["sleep_secs"].is_null());
+        assert!(json.get("progress").is_none());
+    }
+}
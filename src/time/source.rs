@@ -193,6 +193,12 @@ impl TimeSource for SimulatedTimeSource {
     }
 }
 
+#[cfg(test)]
+thread_local! {
+    static SCOPED_SOURCE: std::cell::RefCell<Option<Arc<dyn TimeSource>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
 pub fn init_time_source(source: Arc<dyn TimeSource>) {
     TIME_SOURCE.set(source).ok();
 }
@@ -201,26 +207,43 @@ pub fn is_initialized() -> bool {
     TIME_SOURCE.get().is_some()
 }
 
-pub fn now() -> DateTime<Local> {
-    TIME_SOURCE.get_or_init(|| Arc::new(RealTimeSource)).now()
+/// The active time source, for handing to threads that must share the caller's clock.
+pub fn current() -> Arc<dyn TimeSource> {
+    #[cfg(test)]
+    if let Some(source) = SCOPED_SOURCE.with(|scoped| scoped.borrow().clone()) {
+        return source;
+    }
+
+    Arc::clone(TIME_SOURCE.get_or_init(|| Arc::new(RealTimeSource)))
+}
+
+/// Run `f` with `source` as the current thread's time source.
+///
+/// The process-wide source is left alone, so tests running in parallel keep real time.
+#[cfg(test)]
+pub fn with_scoped_source<R>(source: Arc<dyn TimeSource>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Arc<dyn TimeSource>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED_SOURCE.with(|scoped| *scoped.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(SCOPED_SOURCE.with(|scoped| scoped.borrow_mut().replace(source)));
+    f()
 }
 
-pub fn sleep(duration: StdDuration) {
-    TIME_SOURCE
-        .get_or_init(|| Arc::new(RealTimeSource))
-        .sleep(duration)
+pub fn now() -> DateTime<Local> {
+    current().now()
 }
 
 pub fn is_simulated() -> bool {
-    TIME_SOURCE
-        .get_or_init(|| Arc::new(RealTimeSource))
-        .is_simulated()
+    current().is_simulated()
 }
 
 pub fn simulation_ended() -> bool {
-    TIME_SOURCE
-        .get_or_init(|| Arc::new(RealTimeSource))
-        .is_ended()
+    current().is_ended()
 }
 
 pub fn parse_datetime(s: &str) -> Result<DateTime<Local>, String> {